@@ -0,0 +1,136 @@
+//! Times converting the synthetic workloads of `json2rust::testing::Workload`,
+//! run with `cargo bench`.
+//!
+//! To check for regressions, save the timings of a known good commit with
+//! `cargo bench --bench conversion -- --save-baseline main`, and compare to
+//! them later with `cargo bench --bench conversion -- --baseline main`. That
+//! fails, exiting with 1, if the median time of any workload is more than
+//! `JSON2RUST_BENCH_TOLERANCE` percent, 20 by default, above the baseline's.
+//!
+//! CI runs both on the same machine, one after the other:
+//!
+//! ```text
+//! git checkout origin/main
+//! cargo bench --bench conversion -- --save-baseline main
+//! git checkout -
+//! JSON2RUST_BENCH_TOLERANCE=10 cargo bench --bench conversion -- --baseline main
+//! ```
+
+use criterion::{criterion_group, Criterion, Throughput};
+use json2rust::testing::Workload;
+use json2rust::{convert_samples_json_with_warnings, ConvertOptions};
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+/// The size of each workload, chosen so a run takes a few milliseconds.
+fn size(workload: Workload) -> usize {
+    match workload {
+        Workload::LongStrings => 2_000,
+        Workload::DeepNesting => 500,
+        Workload::WideObject => 5_000,
+        Workload::ManySamples => 5_000,
+    }
+}
+
+fn conversion(c: &mut Criterion) {
+    let mut options = ConvertOptions::default();
+    // Deeper than samples may nest by default
+    options.set_max_depth(Some(1_000));
+    let mut group = c.benchmark_group("conversion");
+    for workload in Workload::ALL {
+        let samples = workload.samples(size(workload));
+        let samples: Vec<&str> = samples.iter().map(String::as_str).collect();
+        let bytes = samples.iter().map(|sample| sample.len() as u64).sum();
+        group.throughput(Throughput::Bytes(bytes));
+        group.bench_function(workload.name(), |b| {
+            b.iter(|| {
+                convert_samples_json_with_warnings(&samples, &options)
+                    .expect("workloads are valid json")
+            })
+        });
+    }
+    group.finish();
+}
+
+/// The name of the baseline given with `--baseline`, if any.
+fn baseline_name() -> Option<String> {
+    let mut args = env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--baseline" || arg == "-b" {
+            return args.next();
+        }
+        if let Some(name) = arg.strip_prefix("--baseline=") {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// Where criterion keeps its results, as it finds it.
+fn criterion_dir() -> PathBuf {
+    if let Some(home) = env::var_os("CRITERION_HOME") {
+        return home.into();
+    }
+    let target = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("target"));
+    target.join("criterion")
+}
+
+/// The median time in nanoseconds criterion estimated for a run, from the
+/// `estimates.json` in `dir`.
+fn median(dir: &Path) -> f64 {
+    let path = dir.join("estimates.json");
+    let estimates: serde_json::Value = fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_else(|| {
+            eprintln!("failed to read the estimates in {}", path.display());
+            process::exit(2);
+        });
+    estimates["median"]["point_estimate"]
+        .as_f64()
+        .unwrap_or_else(|| {
+            eprintln!("no median in {}", path.display());
+            process::exit(2);
+        })
+}
+
+/// Compares the timings of this run to those of `baseline`, returning whether
+/// no workload got slower than the tolerance allows.
+fn within_tolerance(baseline: &str) -> bool {
+    let tolerance: f64 = env::var("JSON2RUST_BENCH_TOLERANCE")
+        .ok()
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(20.0);
+    let group = criterion_dir().join("conversion");
+    let mut ok = true;
+    for workload in Workload::ALL {
+        let dir = group.join(workload.name());
+        let (old, new) = (median(&dir.join(baseline)), median(&dir.join("new")));
+        if new > old * (1.0 + tolerance / 100.0) {
+            eprintln!(
+                "{} regressed: {:.0} ns, up {:.1}% from {:.0} ns in baseline {}",
+                workload.name(),
+                new,
+                (new / old - 1.0) * 100.0,
+                old,
+                baseline
+            );
+            ok = false;
+        }
+    }
+    ok
+}
+
+criterion_group!(benches, conversion);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+    if let Some(baseline) = baseline_name() {
+        if !within_tolerance(&baseline) {
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Generates code for whatever arbitrary input converts to, which must never
+//! panic. Run with `cargo fuzz run codegen`.
+
+#![no_main]
+
+use json2rust::{convert_sample_json_with_options, generate_rust_with_options, ConvertOptions};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let json = match std::str::from_utf8(data) {
+        Ok(json) => json,
+        Err(_) => return,
+    };
+    let options = ConvertOptions::default();
+    if let Ok(structure) = convert_sample_json_with_options(json, &options) {
+        generate_rust_with_options(&structure, "Root", &options);
+    }
+});
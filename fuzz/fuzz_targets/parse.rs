@@ -0,0 +1,23 @@
+//! Parses arbitrary input, strictly and with every leniency, which may fail
+//! but must never panic. Run with `cargo fuzz run parse`.
+
+#![no_main]
+
+use json2rust::{convert_sample_json_with_warnings, ConvertOptions};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let json = match std::str::from_utf8(data) {
+        Ok(json) => json,
+        Err(_) => return,
+    };
+    let mut lenient = ConvertOptions::default();
+    lenient
+        .set_lenient_numbers(true)
+        .set_relaxed_json(true)
+        .set_non_finite_numbers(true)
+        .set_extended_json(true);
+    for options in [&ConvertOptions::default(), &lenient] {
+        let _ = convert_sample_json_with_warnings(json, options);
+    }
+});
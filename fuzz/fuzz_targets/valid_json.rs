@@ -0,0 +1,15 @@
+//! Converts the valid json of `json2rust::testing::arbitrary_json`, seeded
+//! from the input, which must always succeed. Run with
+//! `cargo fuzz run valid_json`.
+
+#![no_main]
+
+use json2rust::testing::arbitrary_json;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|seed: u64| {
+    let json = arbitrary_json(seed);
+    if let Err(e) = json2rust::convert_sample_json(&json) {
+        panic!("failed to convert {}: {:?}", json, e);
+    }
+});
@@ -0,0 +1,31 @@
+/* C interface to json2rust, built with
+ * `cargo rustc --release --features ffi --crate-type cdylib`. */
+#ifndef JSON2RUST_H
+#define JSON2RUST_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define JSON2RUST_OK 0
+#define JSON2RUST_NULL_ARGUMENT 1
+#define JSON2RUST_INVALID_UTF8 2
+#define JSON2RUST_INVALID_OPTIONS 3
+#define JSON2RUST_INVALID_INPUT 4
+#define JSON2RUST_INTERNAL_ERROR 5
+
+/* Generates rust types for the json sample `input`. `options_json` may be
+ * NULL, or a json object with the tables of json2rust.toml and an optional
+ * "root_name". On JSON2RUST_OK `*out` is the generated code, and on any other
+ * code except JSON2RUST_NULL_ARGUMENT it is an error message. Free it with
+ * json2rust_free_string. */
+int json2rust_generate(const char *input, const char *options_json, char **out);
+
+/* Frees a string returned by json2rust_generate. Does nothing for NULL. */
+void json2rust_free_string(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
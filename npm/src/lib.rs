@@ -0,0 +1,159 @@
+//! Javascript bindings for json2rust, packaged for npm with
+//! `wasm-pack build --release --target web`.
+//!
+//! ```js
+//! import init, { convert } from "json2rust-wasm";
+//!
+//! await init();
+//! console.log(convert('{"id": 1}', { root_name: "User" }));
+//! ```
+
+use js_sys::{Reflect, JSON};
+use json2rust::{conversion_to_rust, convert_sample_json_with_warnings, generated_file};
+use json2rust::{Config, ConvertOptions};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &'static str = r#"
+/** Thrown by `convert`, with what was invalid as `code`. */
+export interface Json2RustError extends Error {
+  name: "Json2RustError";
+  code: ErrorCode;
+}
+
+/** The `[inference]` table of `json2rust.toml`. */
+export interface InferenceOptions {
+  chaos_threshold?: number;
+  force_value?: string[];
+  extended_json?: boolean;
+  lenient_numbers?: boolean;
+  relaxed_json?: boolean;
+  non_finite_numbers?: boolean;
+  exponent_integers?: boolean;
+  locale_numbers?: boolean;
+  mixed_numbers?: "union" | "string" | "number" | "error";
+  whole_floats?: "float" | "warn" | "int";
+  floats?: "float" | "decimal" | "error";
+  empty_strings_as_none?: boolean;
+  all_optional?: boolean;
+  numeric_bools?: boolean;
+  int_bool_threshold?: number;
+}
+
+/** The `[codegen]` table of `json2rust.toml`. */
+export interface CodegenOptions {
+  server_types?: boolean;
+  openapi_schemas?: boolean;
+  validation?: boolean;
+  json_schemas?: boolean;
+  output_version?: number;
+  sample_locations?: boolean;
+  set_fields?: string[];
+  hash_sets?: boolean;
+  fixed_size_arrays?: number;
+  distinguish_null?: boolean;
+  patch_types?: boolean;
+  server_generated?: string[];
+  conversions?: boolean;
+  display_impls?: boolean;
+  ordering_derives?: boolean;
+  forward_compatible?: boolean;
+  type_aliases?: string[];
+  root_alias?: boolean;
+  comment_unknown_fields?: boolean;
+  helpers_module?: string;
+  type_attributes?: string[];
+  header?: string;
+  footer?: string;
+  user_code_region?: boolean;
+  banner?: boolean;
+  banner_timestamp?: boolean;
+}
+
+/** The `[naming]` table of `json2rust.toml`. */
+export interface NamingOptions {
+  strategy?: "field_name" | "full_path" | "parent_prefixed" | "singularized";
+  /** How acronyms such as `URL` are written in type names, `capitalized` by default */
+  acronyms?: "capitalized" | "uppercase";
+}
+
+/** The tables of `json2rust.toml`, and the name of the root type. */
+export interface Options {
+  /** `Root` by default */
+  root_name?: string;
+  /** Rust types for paths, such as `{"*_at": "chrono::DateTime<Utc>"}` */
+  type_mappings?: Record<string, string>;
+  /** Attributes for fields, by json pointer */
+  field_attributes?: Record<string, string>;
+  /** Plurals to their singular form, for the `singularized` naming strategy */
+  inflections?: Record<string, string>;
+  inference?: InferenceOptions;
+  codegen?: CodegenOptions;
+  naming?: NamingOptions;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Options")]
+    pub type Options;
+}
+
+/// What `convert` found invalid, as the `code` of the `Json2RustError` it
+/// throws. The values are those of `include/json2rust.h`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The options had an unknown or invalid setting
+    InvalidOptions = 3,
+    /// The sample wasn't valid json, or couldn't be converted with the options
+    InvalidInput = 4,
+}
+
+fn error(code: ErrorCode, message: &str) -> JsValue {
+    let error = js_sys::Error::new(message);
+    error.set_name("Json2RustError");
+    // Setting a property of a fresh object can't fail
+    let _ = Reflect::set(&error, &"code".into(), &(code as u32).into());
+    error.into()
+}
+
+/// Generates rust types for the json `sample`. Throws a `Json2RustError` if
+/// the sample or the options are invalid.
+#[wasm_bindgen]
+pub fn convert(sample: &str, options: Option<Options>) -> Result<String, JsValue> {
+    let mut convert_options = ConvertOptions::default();
+    let mut root_name = "Root".to_string();
+    if let Some(options) = options {
+        let root = Reflect::get(&options, &"root_name".into())?;
+        if !root.is_undefined() {
+            root_name = root.as_string().ok_or_else(|| {
+                error(
+                    ErrorCode::InvalidOptions,
+                    "Invalid options: 'root_name' must be a string",
+                )
+            })?;
+        }
+        let options_json = String::from(JSON::stringify(&options)?);
+        let config = Config::parse_json(&options_json).map_err(|e| {
+            error(
+                ErrorCode::InvalidOptions,
+                &format!("Invalid options: {}", e),
+            )
+        })?;
+        config.apply(&mut convert_options);
+    }
+    match convert_sample_json_with_warnings(sample, &convert_options) {
+        Ok(conversion) => {
+            let code = conversion_to_rust(&conversion, &root_name, &convert_options);
+            Ok(generated_file(&code, &convert_options))
+        }
+        Err(e) => Err(error(
+            ErrorCode::InvalidInput,
+            &match e.suggestion(sample) {
+                Some(suggestion) => format!("Invalid json: {}\nhint: {}", e, suggestion),
+                None => format!("Invalid json: {}", e),
+            },
+        )),
+    }
+}
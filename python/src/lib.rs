@@ -0,0 +1,67 @@
+//! Python bindings for json2rust, built into the `json2rust` module with
+//! `maturin build --release`.
+//!
+//! ```python
+//! import json2rust
+//! print(json2rust.convert('{"id": 1}', root_name="User"))
+//! ```
+
+use json2rust::{conversion_to_rust, convert_sample_json_with_warnings, generated_file};
+use json2rust::{Config, ConvertOptions};
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+create_exception!(
+    json2rust,
+    Json2RustError,
+    PyValueError,
+    "Raised when the sample or the options can't be converted."
+);
+
+/// Generates rust types for the json `sample`.
+///
+/// The options are the tables of `json2rust.toml` as dicts, such as
+/// `inference={"chaos_threshold": 4}`, and `root_name` for the name of the
+/// root type, `Root` by default.
+#[pyfunction]
+#[pyo3(signature = (sample, **options))]
+fn convert(py: Python<'_>, sample: &str, options: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    let mut convert_options = ConvertOptions::default();
+    let mut root_name = "Root".to_string();
+    if let Some(options) = options {
+        let options = options.copy()?;
+        if let Some(name) = options.get_item("root_name")? {
+            root_name = name.extract()?;
+            options.del_item("root_name")?;
+        }
+        let options_json: String = py
+            .import("json")?
+            .call_method1("dumps", (options,))?
+            .extract()?;
+        let config = Config::parse_json(&options_json)
+            .map_err(|e| Json2RustError::new_err(format!("Invalid options: {}", e)))?;
+        config.apply(&mut convert_options);
+    }
+    py.detach(
+        || match convert_sample_json_with_warnings(sample, &convert_options) {
+            Ok(conversion) => {
+                let code = conversion_to_rust(&conversion, &root_name, &convert_options);
+                Ok(generated_file(&code, &convert_options))
+            }
+            Err(e) => Err(match e.suggestion(sample) {
+                Some(suggestion) => format!("Invalid json: {}\nhint: {}", e, suggestion),
+                None => format!("Invalid json: {}", e),
+            }),
+        },
+    )
+    .map_err(Json2RustError::new_err)
+}
+
+#[pymodule(name = "json2rust")]
+fn json2rust_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(convert, m)?)?;
+    m.add("Json2RustError", m.py().get_type::<Json2RustError>())?;
+    Ok(())
+}
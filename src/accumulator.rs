@@ -0,0 +1,384 @@
+use crate::hash::Fnv;
+use crate::options::ConvertOptions;
+use crate::parser::KeyInterner;
+use crate::progress::Progress;
+use crate::shared::Error;
+use crate::{inference, CompactStructure, JsonStructure, MemoryUsage};
+use alloc::collections::BTreeSet;
+// The tree merges are only used by history and snapshots
+#[cfg(any(feature = "std", test))]
+use crate::{JsonPair, JsonValue, RustType};
+#[cfg(any(feature = "std", test))]
+use alloc::{boxed::Box, vec, vec::Vec};
+
+/// Infers a single structure from samples given one at a time, such as the
+/// records of a huge log, keeping only the structure merged so far in memory,
+/// as a `CompactStructure`, rather than every sample.
+///
+/// Each sample is inferred on its own and merged into the rest the way
+/// `convert_samples_json_with_warnings` would have merged it, except that
+/// strings detected as different types in different samples become `String`,
+/// and the chaos threshold and mixed number policy apply to every sample
+/// separately.
+pub struct SchemaAccumulator<'a> {
+    options: &'a ConvertOptions,
+    structure: CompactStructure,
+    samples: usize,
+    // Hashes of the shapes seen so far, with
+    // `ConvertOptions::set_dedupe_samples`, up to `max_shapes` of them
+    shapes: BTreeSet<u64>,
+    max_shapes: usize,
+    // The samples whose shape wasn't among `shapes`
+    unique: usize,
+}
+
+/// The most shapes remembered with `ConvertOptions::set_dedupe_samples`.
+const MAX_SHAPES: usize = 65_536;
+
+impl<'a> SchemaAccumulator<'a> {
+    pub fn new(options: &'a ConvertOptions) -> SchemaAccumulator<'a> {
+        SchemaAccumulator {
+            options,
+            structure: CompactStructure::default(),
+            samples: 0,
+            shapes: BTreeSet::new(),
+            max_shapes: MAX_SHAPES,
+            unique: 0,
+        }
+    }
+
+    /// Merges the structure of `sample` into the structure so far.
+    pub fn accumulate(&mut self, sample: &str) -> Result<(), Error> {
+        // Keys aren't shared between samples, as nodes aren't kept, and maps
+        // keyed by ids would grow the interner forever
+        let node = crate::parse_sample(sample, self.options, &mut KeyInterner::default())?.node;
+        self.samples += 1;
+        if self.options.dedupe_samples {
+            let mut hasher = Fnv::new();
+            hasher.write_str(&inference::shape_of(&node));
+            let shape = hasher.finish();
+            let is_new = !self.shapes.contains(&shape);
+            if is_new {
+                self.unique += 1;
+                if self.shapes.len() < self.max_shapes {
+                    self.shapes.insert(shape);
+                }
+            }
+            self.options.report_progress(Progress::Deduped {
+                samples: self.samples,
+                unique: self.unique,
+            });
+            if !is_new {
+                return Ok(());
+            }
+        }
+        let structure = crate::convert_node(&node, self.options)?.structure;
+        self.structure.merge(&structure)?;
+        self.options.report_progress(Progress::Merged {
+            samples: self.samples,
+        });
+        Ok(())
+    }
+
+    /// The structure of every sample accumulated, which is unknown if there
+    /// were none.
+    pub fn finish(self) -> JsonStructure {
+        self.structure.to_structure()
+    }
+
+    /// How much heap memory the structure merged so far holds.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.structure.memory_usage()
+    }
+}
+
+/// Which values can be merged into a single variant of a union.
+#[derive(Eq, PartialEq)]
+pub(crate) enum VariantKind {
+    Object,
+    Array,
+    String,
+    Number,
+    Bool,
+}
+
+#[cfg(any(feature = "std", test))]
+fn variant_kind(structure: &JsonStructure) -> Option<VariantKind> {
+    match structure {
+        JsonStructure::Object(_) | JsonStructure::Dictionary(_) => Some(VariantKind::Object),
+        JsonStructure::Array(_) => Some(VariantKind::Array),
+        JsonStructure::Value(JsonValue::Int) | JsonStructure::Value(JsonValue::Float) => {
+            Some(VariantKind::Number)
+        }
+        _ if is_wide_int(structure) => Some(VariantKind::Number),
+        JsonStructure::Value(JsonValue::String) | JsonStructure::Value(JsonValue::Custom(_)) => {
+            Some(VariantKind::String)
+        }
+        JsonStructure::Value(JsonValue::Bool) => Some(VariantKind::Bool),
+        JsonStructure::Optional(_) | JsonStructure::Union(_) | JsonStructure::Unknown => None,
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+/// Whether this is an integer too large for an `i64`.
+fn is_wide_int(structure: &JsonStructure) -> bool {
+    matches!(
+        structure,
+        JsonStructure::Value(JsonValue::Custom(rust_type))
+            if [inference::U64, inference::I128].contains(&rust_type.path())
+    )
+}
+
+#[cfg(any(feature = "std", test))]
+fn make_optional(structure: JsonStructure) -> JsonStructure {
+    match structure {
+        JsonStructure::Optional(_) => structure,
+        other => JsonStructure::Optional(Box::new(other)),
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+/// Merges the structures of two samples of the same value into one describing
+/// both, where fields missing from either object become optional.
+pub(crate) fn merge_structures(a: JsonStructure, b: JsonStructure) -> JsonStructure {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (JsonStructure::Unknown, other) | (other, JsonStructure::Unknown) => other,
+        (JsonStructure::Optional(a), JsonStructure::Optional(b)) => {
+            make_optional(merge_structures(*a, *b))
+        }
+        (JsonStructure::Optional(a), b) | (b, JsonStructure::Optional(a)) => {
+            make_optional(merge_structures(*a, b))
+        }
+        (JsonStructure::Union(a), b) => merge_variants(a, b),
+        (a, JsonStructure::Union(b)) => merge_variants(vec![a], JsonStructure::Union(b)),
+        (a, b) if variant_kind(&a) == variant_kind(&b) => merge_same_kind(a, b),
+        (a, b) => JsonStructure::Union(vec![a, b]),
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+/// Merges `other` into the variants of a union, merging each of its variants
+/// into the variant of the same kind, if there is one.
+fn merge_variants(mut variants: Vec<JsonStructure>, other: JsonStructure) -> JsonStructure {
+    let others = match other {
+        JsonStructure::Union(others) => others,
+        other => vec![other],
+    };
+    // Unions of several objects are tagged unions, with
+    // `ConvertOptions::tag_union`, whose variants are objects with a single
+    // field named after the variant, only merged with the variant of that name
+    let objects = |variants: &[JsonStructure]| {
+        variants
+            .iter()
+            .filter(|v| matches!(v, JsonStructure::Object(_)))
+            .count()
+    };
+    let is_tagged = objects(&variants) > 1 || objects(&others) > 1;
+    for other in others {
+        let kind = variant_kind(&other);
+        let same_variant = |v: &JsonStructure| match (v, &other) {
+            (JsonStructure::Object(a), JsonStructure::Object(b)) if is_tagged => {
+                a.first().map(JsonPair::key) == b.first().map(JsonPair::key)
+            }
+            _ => variant_kind(v) == kind,
+        };
+        match variants.iter().position(same_variant) {
+            Some(index) => {
+                let variant = variants.remove(index);
+                variants.insert(index, merge_structures(variant, other));
+            }
+            None => variants.push(other),
+        }
+    }
+    match variants.len() {
+        1 => variants.remove(0),
+        _ => JsonStructure::Union(variants),
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+fn merge_same_kind(a: JsonStructure, b: JsonStructure) -> JsonStructure {
+    match (a, b) {
+        (JsonStructure::Object(a), JsonStructure::Object(b)) => merge_objects(a, b),
+        (JsonStructure::Dictionary(a), JsonStructure::Dictionary(b)) => {
+            let (key, a) = a.into_parts();
+            let b = b.into_parts().1;
+            JsonStructure::Dictionary(JsonPair::new(key, merge_structures(a, b)))
+        }
+        (JsonStructure::Dictionary(pair), JsonStructure::Object(pairs))
+        | (JsonStructure::Object(pairs), JsonStructure::Dictionary(pair)) => {
+            let (key, value) = pair.into_parts();
+            let value = pairs.into_iter().fold(value, |value, pair| {
+                merge_structures(value, pair.into_parts().1)
+            });
+            JsonStructure::Dictionary(JsonPair::new(key, value))
+        }
+        (JsonStructure::Array(a), JsonStructure::Array(b)) => {
+            JsonStructure::array(merge_structures(*a, *b))
+        }
+        (JsonStructure::Value(JsonValue::Int), JsonStructure::Value(JsonValue::Int)) => {
+            JsonStructure::int()
+        }
+        (JsonStructure::Value(JsonValue::Bool), _) => JsonStructure::bool(),
+        // Different kinds of integers, at least one of them too large for an
+        // `i64`, and possibly negative
+        (a, b)
+            if !matches!(a, JsonStructure::Value(JsonValue::Float))
+                && !matches!(b, JsonStructure::Value(JsonValue::Float))
+                && (is_wide_int(&a) || is_wide_int(&b)) =>
+        {
+            JsonStructure::custom(RustType::new(inference::I128))
+        }
+        (JsonStructure::Value(JsonValue::Int), _) | (JsonStructure::Value(JsonValue::Float), _) => {
+            JsonStructure::float()
+        }
+        // Strings detected as different types, or as a type in only some samples
+        _ => JsonStructure::string(),
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+fn merge_objects(a: Vec<JsonPair>, b: Vec<JsonPair>) -> JsonStructure {
+    let mut b: Vec<Option<JsonPair>> = b.into_iter().map(Some).collect();
+    let mut pairs: Vec<JsonPair> = a
+        .into_iter()
+        .map(|pair| {
+            let (key, value) = pair.into_parts();
+            let other = b
+                .iter_mut()
+                .find(|p| p.as_ref().is_some_and(|p| p.key() == key))
+                .and_then(Option::take);
+            let value = match other {
+                Some(other) => merge_structures(value, other.into_parts().1),
+                None => make_optional(value),
+            };
+            JsonPair::new(key, value)
+        })
+        .collect();
+    pairs.extend(b.into_iter().flatten().map(|pair| {
+        let (key, value) = pair.into_parts();
+        JsonPair::new(key, make_optional(value))
+    }));
+    JsonStructure::Object(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_samples_json_with_warnings;
+    use alloc::string::String;
+
+    fn accumulate(samples: &[&str]) -> JsonStructure {
+        let options = ConvertOptions::default();
+        let mut accumulator = SchemaAccumulator::new(&options);
+        for sample in samples {
+            accumulator.accumulate(sample).unwrap();
+        }
+        accumulator.finish()
+    }
+
+    #[test]
+    fn merges_like_converting_every_sample_at_once() {
+        let cases: &[&[&str]] = &[
+            &[
+                r#"{"id": 1, "tags": []}"#,
+                r#"{"id": 2.5, "tags": ["a"], "note": null}"#,
+            ],
+            &[
+                r#"{"a": {"b": 1}}"#,
+                r#"{"a": null}"#,
+                r#"{"a": {"c": true}}"#,
+            ],
+            &[r#"[1, "x"]"#, r#"[{"k": 1}]"#, r#"[2.5, {"k": null}]"#],
+            &[r#"{"ip": "127.0.0.1"}"#, r#"{"ip": "::1"}"#],
+        ];
+        for samples in cases {
+            let expected = convert_samples_json_with_warnings(samples, &ConvertOptions::default())
+                .unwrap()
+                .structure;
+            assert_eq!(accumulate(samples), expected, "{:?}", samples);
+        }
+    }
+
+    #[test]
+    fn strings_detected_in_only_some_samples_are_strings() {
+        assert_eq!(
+            accumulate(&[r#"{"ip": "127.0.0.1"}"#, r#"{"ip": "localhost"}"#]),
+            JsonStructure::object()
+                .field("ip", JsonStructure::string())
+                .build()
+        );
+        assert_eq!(accumulate(&[]), JsonStructure::Unknown);
+    }
+
+    #[test]
+    fn widens_integers_too_large_in_some_samples() {
+        let u64 = JsonStructure::custom(RustType::new("u64"));
+        let i128 = JsonStructure::custom(RustType::new("i128"));
+        let id = |structure| JsonStructure::object().field("id", structure).build();
+
+        assert_eq!(
+            accumulate(&[r#"{"id": 18446744073709551615}"#, r#"{"id": 1}"#]),
+            id(i128)
+        );
+        assert_eq!(
+            accumulate(&[
+                r#"{"id": 18446744073709551615}"#,
+                r#"{"id": 9223372036854775808}"#
+            ]),
+            id(u64)
+        );
+    }
+
+    #[test]
+    fn remembers_a_bounded_number_of_shapes() {
+        let samples: Vec<String> = (0..20)
+            .map(|i| alloc::format!(r#"{{"field_{}": {}}}"#, i % 10, i))
+            .collect();
+        let mut options = ConvertOptions::default();
+        options.set_dedupe_samples(true);
+        let mut accumulator = SchemaAccumulator::new(&options);
+        accumulator.max_shapes = 4;
+        for sample in &samples {
+            accumulator.accumulate(sample).unwrap();
+        }
+
+        assert_eq!(accumulator.shapes.len(), 4);
+        // The shapes beyond the first four are merged every time they are seen
+        assert_eq!(accumulator.unique, 16);
+        assert_eq!(
+            accumulator.finish(),
+            accumulate(&samples.iter().map(String::as_str).collect::<Vec<_>>())
+        );
+    }
+
+    #[test]
+    fn keeps_the_variants_of_tagged_unions_apart() {
+        let variant = |name: &str, key: &str| {
+            JsonStructure::object()
+                .field(
+                    name,
+                    JsonStructure::object()
+                        .field(key, JsonStructure::int())
+                        .build(),
+                )
+                .build()
+        };
+        let circle_or_square =
+            JsonStructure::Union(vec![variant("circle", "radius"), variant("square", "side")]);
+
+        assert_eq!(
+            merge_structures(circle_or_square.clone(), variant("point", "x")),
+            JsonStructure::Union(vec![
+                variant("circle", "radius"),
+                variant("square", "side"),
+                variant("point", "x"),
+            ])
+        );
+        assert_eq!(
+            merge_structures(circle_or_square.clone(), variant("circle", "radius")),
+            circle_or_square
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use crate::backend::{Artifact, CodegenBackend};
+use crate::inference::{I128, U64};
+use crate::naming::to_snake_case;
+use crate::options::ConvertOptions;
+use crate::pointer::{child_path, element_path};
+use crate::stats::PathStats;
+use crate::{Conversion, JsonPair, JsonStructure, JsonValue};
+
+/// Arrow types for the custom types of date and time fields, by the name of
+/// the type without its path.
+const TEMPORAL_TYPES: &[(&str, &str)] = &[
+    (
+        "DateTime",
+        "DataType::Timestamp(TimeUnit::Microsecond, Some(\"UTC\".into()))",
+    ),
+    (
+        "NaiveDateTime",
+        "DataType::Timestamp(TimeUnit::Microsecond, None)",
+    ),
+    ("NaiveDate", "DataType::Date32"),
+    ("NaiveTime", "DataType::Time64(TimeUnit::Microsecond)"),
+];
+
+/// Strings are dictionary encoded when each of their distinct values was seen
+/// at least this many times on average.
+const MIN_DICTIONARY_REPEATS: usize = 2;
+
+struct SchemaWriter<'a> {
+    // Stats of the sample, for finding the strings to dictionary encode. Empty
+    // when the schema isn't generated from a conversion
+    stats: &'a [PathStats],
+    // Json pointer to the value currently being described
+    pointer: String,
+    // The items of `arrow::datatypes` used so far, and whether `Arc` is
+    imports: Vec<&'static str>,
+    uses_arc: bool,
+}
+
+impl SchemaWriter<'_> {
+    fn import(&mut self, name: &'static str) {
+        if !self.imports.contains(&name) {
+            self.imports.push(name);
+        }
+    }
+
+    /// The `DataType` of `structure`, for a field at the `indent` level.
+    fn data_type(&mut self, structure: &JsonStructure, indent: usize) -> String {
+        match structure {
+            JsonStructure::Object(pairs) => {
+                self.import("Fields");
+                format!(
+                    "DataType::Struct(Fields::from(vec![\n{}{}]))",
+                    self.fields(pairs, indent + 1),
+                    "    ".repeat(indent)
+                )
+            }
+            JsonStructure::Array(inner) => {
+                self.uses_arc = true;
+                let item = self.field("item", inner, indent, element_path(&self.pointer));
+                format!(
+                    "DataType::List(Arc::new({}))",
+                    item.trim().trim_end_matches(',')
+                )
+            }
+            JsonStructure::Dictionary(pair) => {
+                self.uses_arc = true;
+                self.import("Fields");
+                let entries = [
+                    self.field("keys", &JsonStructure::string(), indent + 3, String::new()),
+                    self.field(
+                        "values",
+                        pair.value(),
+                        indent + 3,
+                        element_path(&self.pointer),
+                    ),
+                ];
+                format!(
+                    "DataType::Map(\n{indent}    Arc::new(Field::new(\n{indent}        \"entries\",\n\
+                     {indent}        DataType::Struct(Fields::from(vec![\n{fields}{indent}        ])),\n\
+                     {indent}        false,\n{indent}    )),\n{indent}    false,\n{indent})",
+                    indent = "    ".repeat(indent),
+                    fields = entries.concat()
+                )
+            }
+            JsonStructure::Optional(inner) => self.data_type(inner, indent),
+            JsonStructure::Value(JsonValue::Int) => "DataType::Int64".to_string(),
+            JsonStructure::Value(JsonValue::Float) => "DataType::Float64".to_string(),
+            JsonStructure::Value(JsonValue::Bool) => "DataType::Boolean".to_string(),
+            JsonStructure::Value(JsonValue::Custom(rust_type)) if rust_type.path() == U64 => {
+                "DataType::UInt64".to_string()
+            }
+            JsonStructure::Value(JsonValue::Custom(rust_type)) if rust_type.path() == I128 => {
+                "DataType::Decimal128(38, 0)".to_string()
+            }
+            JsonStructure::Value(JsonValue::Custom(rust_type)) => {
+                let name = rust_type.path().split('<').next().unwrap_or_default();
+                let name = name.rsplit("::").next().unwrap_or_default();
+                match TEMPORAL_TYPES
+                    .iter()
+                    .find(|(temporal, _)| *temporal == name)
+                {
+                    Some((_, data_type)) => {
+                        self.import("TimeUnit");
+                        data_type.to_string()
+                    }
+                    // Other custom types are usually sent as strings
+                    None => "DataType::Utf8".to_string(),
+                }
+            }
+            JsonStructure::Value(JsonValue::String) if self.is_dictionary_candidate() => {
+                "DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))"
+                    .to_string()
+            }
+            // Values of mixed or unknown types are kept as their json text
+            JsonStructure::Value(JsonValue::String)
+            | JsonStructure::Union(_)
+            | JsonStructure::Unknown => "DataType::Utf8".to_string(),
+        }
+    }
+
+    /// Whether the strings at the current pointer are a few values repeated
+    /// over and over, such as statuses or country codes.
+    fn is_dictionary_candidate(&self) -> bool {
+        self.stats
+            .iter()
+            .rev()
+            .find(|stats| stats.path == self.pointer)
+            .and_then(|stats| {
+                let values = stats.string_values.as_ref()?;
+                Some(stats.count - stats.null_count >= values.len() * MIN_DICTIONARY_REPEATS)
+            })
+            .unwrap_or(false)
+    }
+
+    /// A `Field::new(..),` line for a field named `name` holding the value at
+    /// `pointer`, at the `indent` level.
+    fn field(
+        &mut self,
+        name: &str,
+        structure: &JsonStructure,
+        indent: usize,
+        pointer: String,
+    ) -> String {
+        let nullable = matches!(
+            structure,
+            JsonStructure::Optional(_) | JsonStructure::Unknown
+        );
+        let parent = core::mem::replace(&mut self.pointer, pointer);
+        let data_type = self.data_type(structure, indent + 1);
+        self.pointer = parent;
+        let prefix = "    ".repeat(indent);
+        if data_type.contains('\n') {
+            format!(
+                "{prefix}Field::new(\n{prefix}    {:?},\n{prefix}    {},\n{prefix}    {},\n{prefix}),\n",
+                name,
+                data_type,
+                nullable,
+                prefix = prefix
+            )
+        } else {
+            format!(
+                "{}Field::new({:?}, {}, {}),\n",
+                prefix, name, data_type, nullable
+            )
+        }
+    }
+
+    fn fields(&mut self, pairs: &[JsonPair], indent: usize) -> String {
+        pairs
+            .iter()
+            .map(|pair| {
+                let pointer = child_path(&self.pointer, pair.key());
+                self.field(pair.key(), pair.value(), indent, pointer)
+            })
+            .collect()
+    }
+}
+
+/// The `arrow::datatypes::Schema` of the records of `structure`, as the
+/// source of a function named after `root_name` building it.
+fn arrow_schema(structure: &JsonStructure, stats: &[PathStats], root_name: &str) -> String {
+    let mut root = structure.non_optional();
+    let mut pointer = String::new();
+    while let JsonStructure::Array(inner) = root {
+        root = inner.non_optional();
+        pointer = element_path(&pointer);
+    }
+    let mut writer = SchemaWriter {
+        stats,
+        pointer,
+        imports: vec!["DataType", "Field", "Schema"],
+        uses_arc: false,
+    };
+    let fields = match root {
+        JsonStructure::Object(pairs) => writer.fields(pairs, 2),
+        // Records that aren't objects are a single field
+        other => {
+            let pointer = writer.pointer.clone();
+            writer.field("value", other, 2, pointer)
+        }
+    };
+    writer.imports.sort_unstable();
+
+    let mut output = format!("use arrow::datatypes::{{{}}};\n", writer.imports.join(", "));
+    if writer.uses_arc {
+        output.push_str("use std::sync::Arc;\n");
+    }
+    output.push_str(&format!(
+        "\n/// The arrow schema of `{}` records.\npub fn {}_schema() -> Schema {{\n    \
+         Schema::new(vec![\n{}    ])\n}}\n",
+        root_name,
+        to_snake_case(root_name),
+        fields
+    ));
+    output
+}
+
+/// Generates the function building the arrow schema of the records of a
+/// conversion, like `ArrowBackend`, using the stats of the sample to dictionary
+/// encode strings that are a few values repeated many times.
+pub fn conversion_to_arrow_schema(conversion: &Conversion, root_name: &str) -> String {
+    arrow_schema(&conversion.structure, &conversion.stats, root_name)
+}
+
+/// Emits a function building the `arrow::datatypes::Schema` of the records of
+/// the sample, as a single `<root_name>_schema.rs` file, so the same inference
+/// can drive ingesting the samples into data frames. The records are the
+/// elements of samples that are arrays, and fields keep the keys of the
+/// sample as their names. Fields of mixed or unknown types are `Utf8`, for
+/// their json text.
+pub struct ArrowBackend;
+
+impl CodegenBackend for ArrowBackend {
+    fn generate(
+        &self,
+        structure: &JsonStructure,
+        root_name: &str,
+        _options: &ConvertOptions,
+    ) -> Vec<Artifact> {
+        vec![Artifact::new(
+            format!("{}_schema.rs", to_snake_case(root_name)),
+            arrow_schema(structure, &[], root_name),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RustType;
+
+    #[test]
+    fn generates_arrow_schemas() {
+        let structure = JsonStructure::array(
+            JsonStructure::object()
+                .field("id", JsonStructure::int())
+                .optional_field("name", JsonStructure::string())
+                .field("tags", JsonStructure::array(JsonStructure::string()))
+                .field(
+                    "at",
+                    JsonStructure::custom(RustType::new("chrono::DateTime<chrono::Utc>")),
+                )
+                .field(
+                    "owner",
+                    JsonStructure::object()
+                        .field("active", JsonStructure::bool())
+                        .build(),
+                )
+                .field(
+                    "scores",
+                    JsonStructure::dictionary("scores", JsonStructure::float()),
+                )
+                .build(),
+        );
+        let artifacts = ArrowBackend.generate(&structure, "Order", &ConvertOptions::default());
+
+        assert_eq!(artifacts[0].file_name, "order_schema.rs");
+        assert_eq!(
+            artifacts[0].contents,
+            r#"use arrow::datatypes::{DataType, Field, Fields, Schema, TimeUnit};
+use std::sync::Arc;
+
+/// The arrow schema of `Order` records.
+pub fn order_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("tags", DataType::List(Arc::new(Field::new("item", DataType::Utf8, false))), false),
+        Field::new("at", DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())), false),
+        Field::new(
+            "owner",
+            DataType::Struct(Fields::from(vec![
+                Field::new("active", DataType::Boolean, false),
+            ])),
+            false,
+        ),
+        Field::new(
+            "scores",
+            DataType::Map(
+                Arc::new(Field::new(
+                    "entries",
+                    DataType::Struct(Fields::from(vec![
+                        Field::new("keys", DataType::Utf8, false),
+                        Field::new("values", DataType::Float64, false),
+                    ])),
+                    false,
+                )),
+                false,
+            ),
+            false,
+        ),
+    ])
+}
+"#
+        );
+    }
+
+    #[test]
+    fn describes_nested_samples() {
+        let sample = r#"[
+            {"id": 1, "status": "shipped", "lines": [{"sku": "a", "discounts": [0.5]}],
+             "shipping": {"city": "Oslo", "note": null}},
+            {"id": 2, "status": "pending", "lines": [{"sku": "b", "discounts": []}],
+             "shipping": null},
+            {"id": 3, "status": "shipped", "lines": [{"sku": "c", "discounts": null}],
+             "shipping": {"city": "Rome", "note": "Call first"}},
+            {"id": 4, "status": "pending", "lines": [], "shipping": null}
+        ]"#;
+        let conversion =
+            crate::convert_sample_json_with_warnings(sample, &ConvertOptions::default()).unwrap();
+
+        // Statuses repeat, so they are dictionary encoded, unlike the skus and
+        // cities, which are all different
+        assert_eq!(
+            conversion_to_arrow_schema(&conversion, "Order"),
+            r#"use arrow::datatypes::{DataType, Field, Fields, Schema};
+use std::sync::Arc;
+
+/// The arrow schema of `Order` records.
+pub fn order_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("status", DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)), false),
+        Field::new(
+            "lines",
+            DataType::List(Arc::new(Field::new(
+                "item",
+                DataType::Struct(Fields::from(vec![
+                    Field::new("sku", DataType::Utf8, false),
+                    Field::new("discounts", DataType::List(Arc::new(Field::new("item", DataType::Float64, false))), true),
+                ])),
+                false,
+            ))),
+            false,
+        ),
+        Field::new(
+            "shipping",
+            DataType::Struct(Fields::from(vec![
+                Field::new("city", DataType::Utf8, false),
+                Field::new("note", DataType::Utf8, true),
+            ])),
+            true,
+        ),
+    ])
+}
+"#
+        );
+    }
+}
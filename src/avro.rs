@@ -0,0 +1,508 @@
+use crate::backend::{Artifact, CodegenBackend};
+use crate::codegen::union_variant_names;
+use crate::json_writer::JsonOutput;
+use crate::naming::{to_snake_case, transliterate};
+use crate::options::ConvertOptions;
+use crate::parser::{parse_json, JsonNode};
+use crate::shared::Error;
+use crate::symbols::SymbolTable;
+use crate::{JsonPair, JsonStructure, JsonValue, RustType};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Avro logical types, with the primitive type they annotate and the rust type
+/// used for them.
+const LOGICAL_TYPES: &[(&str, &str, &str)] = &[
+    ("timestamp-millis", "long", "chrono::DateTime<chrono::Utc>"),
+    ("timestamp-micros", "long", "chrono::DateTime<chrono::Utc>"),
+    ("local-timestamp-millis", "long", "chrono::NaiveDateTime"),
+    ("local-timestamp-micros", "long", "chrono::NaiveDateTime"),
+    ("date", "int", "chrono::NaiveDate"),
+    ("time-millis", "int", "chrono::NaiveTime"),
+    ("time-micros", "long", "chrono::NaiveTime"),
+    ("uuid", "string", "uuid::Uuid"),
+    ("decimal", "bytes", "rust_decimal::Decimal"),
+];
+
+fn schema_error<S: Into<String>>(message: S) -> Error {
+    Error::InvalidSchema(message.into())
+}
+
+fn attribute<'a>(attributes: &'a [(Rc<str>, JsonNode)], name: &str) -> Option<&'a JsonNode> {
+    attributes
+        .iter()
+        .find(|(key, _)| **key == *name)
+        .map(|(_, v)| v)
+}
+
+fn string_attribute<'a>(
+    attributes: &'a [(Rc<str>, JsonNode)],
+    name: &str,
+) -> Result<&'a str, Error> {
+    match attribute(attributes, name) {
+        Some(JsonNode::String(s)) => Ok(s),
+        _ => Err(schema_error(format!(
+            "Expected a string '{}' attribute",
+            name
+        ))),
+    }
+}
+
+struct AvroReader {
+    // Named types defined so far, by both their name and full name, as later
+    // parts of the schema can refer to them by name
+    named: HashMap<String, JsonStructure>,
+    // Namespace of the enclosing named type, which nested types inherit
+    namespace: Option<String>,
+}
+
+impl AvroReader {
+    fn read(&mut self, schema: &JsonNode, field_name: &str) -> Result<JsonStructure, Error> {
+        match schema {
+            JsonNode::String(name) => self.read_named(name),
+            JsonNode::Array(variants) => self.read_union(variants, field_name),
+            JsonNode::Object(attributes) => self.read_complex(attributes, field_name),
+            _ => Err(schema_error(
+                "Expected a type name, union or type definition",
+            )),
+        }
+    }
+
+    fn read_named(&self, name: &str) -> Result<JsonStructure, Error> {
+        match name {
+            "boolean" => Ok(JsonStructure::bool()),
+            "int" | "long" => Ok(JsonStructure::int()),
+            "float" | "double" => Ok(JsonStructure::float()),
+            "bytes" | "string" => Ok(JsonStructure::string()),
+            "null" => Ok(JsonStructure::optional(JsonStructure::Unknown)),
+            _ => self
+                .named
+                .get(name)
+                .cloned()
+                .ok_or_else(|| schema_error(format!("Unknown type '{}'", name))),
+        }
+    }
+
+    fn read_union(
+        &mut self,
+        variants: &[JsonNode],
+        field_name: &str,
+    ) -> Result<JsonStructure, Error> {
+        let mut nullable = false;
+        let mut structures = Vec::new();
+        for variant in variants {
+            if *variant == JsonNode::String("null".to_string()) {
+                nullable = true;
+            } else {
+                structures.push(self.read(variant, field_name)?);
+            }
+        }
+
+        let structure = match structures.len() {
+            0 => JsonStructure::Unknown,
+            1 => structures.remove(0),
+            _ => JsonStructure::Union(structures),
+        };
+        if nullable {
+            Ok(JsonStructure::optional(structure))
+        } else {
+            Ok(structure)
+        }
+    }
+
+    fn read_complex(
+        &mut self,
+        attributes: &[(Rc<str>, JsonNode)],
+        field_name: &str,
+    ) -> Result<JsonStructure, Error> {
+        if let Some(JsonNode::String(logical_type)) = attribute(attributes, "logicalType") {
+            if let Some((_, _, rust_type)) =
+                LOGICAL_TYPES.iter().find(|(l, _, _)| l == logical_type)
+            {
+                return Ok(JsonStructure::custom(RustType::new(*rust_type)));
+            }
+        }
+
+        let type_node = attribute(attributes, "type")
+            .ok_or_else(|| schema_error("Expected a 'type' attribute"))?;
+        let type_name = match type_node {
+            JsonNode::String(name) => name.as_str(),
+            // A nested definition, such as `{"type": {"type": "array", ...}}`
+            other => return self.read(other, field_name),
+        };
+
+        let enclosing_namespace = self.namespace.clone();
+        if let Some(JsonNode::String(namespace)) = attribute(attributes, "namespace") {
+            self.namespace = Some(namespace.clone());
+        }
+        let structure = self.read_type(type_name, attributes, field_name);
+        let namespace = std::mem::replace(&mut self.namespace, enclosing_namespace);
+        let structure = structure?;
+
+        if let Some(JsonNode::String(name)) = attribute(attributes, "name") {
+            if let Some(namespace) = namespace.filter(|_| !name.contains('.')) {
+                self.named
+                    .insert(format!("{}.{}", namespace, name), structure.clone());
+            }
+            self.named.insert(name.clone(), structure.clone());
+        }
+        Ok(structure)
+    }
+
+    fn read_type(
+        &mut self,
+        type_name: &str,
+        attributes: &[(Rc<str>, JsonNode)],
+        field_name: &str,
+    ) -> Result<JsonStructure, Error> {
+        Ok(match type_name {
+            "record" | "error" => {
+                let fields = match attribute(attributes, "fields") {
+                    Some(JsonNode::Array(fields)) => fields,
+                    _ => return Err(schema_error("Expected a 'fields' array on the record")),
+                };
+                let mut pairs = Vec::new();
+                for field in fields {
+                    let field = match field {
+                        JsonNode::Object(field) => field,
+                        _ => return Err(schema_error("Expected record fields to be objects")),
+                    };
+                    let name = string_attribute(field, "name")?;
+                    let field_type = attribute(field, "type").ok_or_else(|| {
+                        schema_error(format!("Expected a 'type' attribute on field '{}'", name))
+                    })?;
+                    pairs.push(JsonPair::new(name, self.read(field_type, name)?));
+                }
+                JsonStructure::Object(pairs)
+            }
+            "array" => {
+                let items = attribute(attributes, "items")
+                    .ok_or_else(|| schema_error("Expected an 'items' attribute on the array"))?;
+                JsonStructure::array(self.read(items, field_name)?)
+            }
+            "map" => {
+                let values = attribute(attributes, "values")
+                    .ok_or_else(|| schema_error("Expected a 'values' attribute on the map"))?;
+                JsonStructure::dictionary(field_name, self.read(values, field_name)?)
+            }
+            "enum" | "fixed" => JsonStructure::string(),
+            name => self.read_named(name)?,
+        })
+    }
+}
+
+/// Reads an Avro schema (`.avsc`) into a structure, so types can be generated
+/// for e.g. the values of a Kafka topic.
+///
+/// Enums and fixed values become strings, maps become dictionaries, `null`
+/// in a union makes the value optional, and known logical types such as
+/// `timestamp-millis` become custom rust types.
+pub fn avro_schema_to_structure(schema: &str) -> Result<JsonStructure, Error> {
+    let node = parse_json(schema)?;
+    let mut reader = AvroReader {
+        named: HashMap::new(),
+        namespace: None,
+    };
+    reader.read(&node, "root")
+}
+
+struct AvroWriter<'a> {
+    symbols: &'a SymbolTable,
+    // Records are only defined once, and referred to by name afterwards
+    defined: Vec<String>,
+    path: Vec<String>,
+}
+
+impl<'a> AvroWriter<'a> {
+    fn write(&mut self, structure: &JsonStructure) -> JsonOutput {
+        match structure {
+            JsonStructure::Object(pairs) => self.write_record(pairs),
+            JsonStructure::Array(inner) => JsonOutput::object(vec![
+                ("type", JsonOutput::string("array")),
+                ("items", self.write(inner)),
+            ]),
+            JsonStructure::Dictionary(pair) => JsonOutput::object(vec![
+                ("type", JsonOutput::string("map")),
+                ("values", self.write(pair.value())),
+            ]),
+            JsonStructure::Value(JsonValue::String) => JsonOutput::string("string"),
+            JsonStructure::Value(JsonValue::Int) => JsonOutput::string("long"),
+            JsonStructure::Value(JsonValue::Float) => JsonOutput::string("double"),
+            JsonStructure::Value(JsonValue::Bool) => JsonOutput::string("boolean"),
+            JsonStructure::Value(JsonValue::Custom(rust_type)) => {
+                // Decimals need a precision, which isn't known, so they are
+                // written as strings along with other unknown custom types
+                match LOGICAL_TYPES
+                    .iter()
+                    .find(|(l, _, r)| *r == rust_type.path() && *l != "decimal")
+                {
+                    Some((logical_type, base, _)) => JsonOutput::object(vec![
+                        ("type", JsonOutput::string(*base)),
+                        ("logicalType", JsonOutput::string(*logical_type)),
+                    ]),
+                    None => JsonOutput::string("string"),
+                }
+            }
+            JsonStructure::Optional(inner) => {
+                let mut variants = vec![JsonOutput::string("null")];
+                match inner.as_ref() {
+                    JsonStructure::Unknown => return JsonOutput::string("null"),
+                    JsonStructure::Union(union) => variants.extend(self.write_variants(union)),
+                    other => variants.push(self.write(other)),
+                }
+                JsonOutput::Array(variants)
+            }
+            JsonStructure::Union(variants) => JsonOutput::Array(self.write_variants(variants)),
+            // Avro has no type for arbitrary values, so they are kept as json text
+            JsonStructure::Unknown => JsonOutput::string("string"),
+        }
+    }
+
+    fn write_variants(&mut self, variants: &[JsonStructure]) -> Vec<JsonOutput> {
+        variants
+            .iter()
+            .zip(union_variant_names(variants))
+            .map(|(variant, variant_name)| {
+                self.path.push(variant_name);
+                let output = self.write(variant);
+                self.path.pop();
+                output
+            })
+            .collect()
+    }
+
+    fn write_record(&mut self, pairs: &[JsonPair]) -> JsonOutput {
+        let name = self.symbols.name_at(&self.path).to_string();
+        if self.defined.contains(&name) {
+            return JsonOutput::String(name);
+        }
+        self.defined.push(name.clone());
+
+        let fields = pairs
+            .iter()
+            .enumerate()
+            .map(|(index, pair)| {
+                self.path.push(pair.key().to_string());
+                let field_type = self.write(pair.value());
+                self.path.pop();
+                let mut field = vec![
+                    ("name", JsonOutput::string(avro_name(pair.key(), index))),
+                    ("type", field_type),
+                ];
+                if pair.value().is_optional() {
+                    field.push(("default", JsonOutput::Null));
+                }
+                JsonOutput::object(field)
+            })
+            .collect();
+
+        JsonOutput::object(vec![
+            ("type", JsonOutput::string("record")),
+            ("name", JsonOutput::String(name)),
+            ("fields", JsonOutput::Array(fields)),
+        ])
+    }
+}
+
+/// Avro names must match `[A-Za-z_][A-Za-z0-9_]*`, so other keys are turned
+/// into snake case names.
+fn avro_name(key: &str, index: usize) -> String {
+    let is_valid = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_valid {
+        return key.to_string();
+    }
+    match to_snake_case(&transliterate(key)) {
+        name if name.is_empty() => format!("field_{}", index + 1),
+        name if name.starts_with(|c: char| c.is_ascii_digit()) => format!("_{}", name),
+        name => name,
+    }
+}
+
+/// Emits an Avro schema for the structure, as a single `<root_name>.avsc` file.
+/// Records are named the same way as the rust types would be.
+pub struct AvroBackend;
+
+impl CodegenBackend for AvroBackend {
+    fn generate(
+        &self,
+        structure: &JsonStructure,
+        root_name: &str,
+        options: &ConvertOptions,
+    ) -> Vec<Artifact> {
+        let symbols = SymbolTable::build(
+            structure,
+            root_name,
+            options.naming_strategy.as_ref(),
+            &options.abbreviations,
+            options.acronyms,
+        );
+        let mut writer = AvroWriter {
+            symbols: &symbols,
+            defined: Vec::new(),
+            path: Vec::new(),
+        };
+        let mut schema = writer.write(structure).to_pretty_string();
+        schema.push('\n');
+        vec![Artifact::new(
+            format!("{}.avsc", to_snake_case(root_name)),
+            schema,
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    #[test]
+    fn reads_avro_schemas() {
+        let schema = r#"{
+            "type": "record",
+            "name": "Order",
+            "namespace": "shop",
+            "fields": [
+                {"name": "id", "type": "long"},
+                {"name": "note", "type": ["null", "string"], "default": null},
+                {"name": "created", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+                {"name": "status", "type": {"type": "enum", "name": "Status", "symbols": ["A"]}},
+                {"name": "lines", "type": {"type": "array", "items": {
+                    "type": "record",
+                    "name": "Line",
+                    "fields": [{"name": "price", "type": "double"}]
+                }}},
+                {"name": "extra", "type": {"type": "array", "items": "shop.Line"}},
+                {"name": "labels", "type": {"type": "map", "values": "string"}},
+                {"name": "value", "type": ["int", "boolean"]}
+            ]
+        }"#;
+
+        let line = JsonStructure::object()
+            .field("price", JsonStructure::float())
+            .build();
+        assert_eq!(
+            avro_schema_to_structure(schema).unwrap(),
+            JsonStructure::object()
+                .field("id", JsonStructure::int())
+                .optional_field("note", JsonStructure::string())
+                .field(
+                    "created",
+                    JsonStructure::custom(RustType::new("chrono::DateTime<chrono::Utc>"))
+                )
+                .field("status", JsonStructure::string())
+                .field("lines", JsonStructure::array(line.clone()))
+                .field("extra", JsonStructure::array(line))
+                .field(
+                    "labels",
+                    JsonStructure::dictionary("labels", JsonStructure::string())
+                )
+                .field(
+                    "value",
+                    JsonStructure::Union(vec![JsonStructure::int(), JsonStructure::bool()])
+                )
+                .build()
+        )
+    }
+
+    #[test]
+    fn reports_invalid_schemas() {
+        assert_eq!(
+            avro_schema_to_structure(r#"{"type": "array", "items": "Missing"}"#),
+            Err(Error::InvalidSchema("Unknown type 'Missing'".to_string()))
+        );
+        assert_eq!(
+            avro_schema_to_structure(r#"{"type": "record", "name": "A"}"#),
+            Err(Error::InvalidSchema(
+                "Expected a 'fields' array on the record".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn writes_avro_schemas() {
+        let structure = convert_sample_json(
+            r#"{"user-id": 1, "tags": {"a": "x"}, "items": [{"sku": "a", "qty": null}], "v": [1, "a"]}"#,
+        )
+        .unwrap();
+
+        let artifacts = AvroBackend.generate(&structure, "Order", &ConvertOptions::default());
+
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].file_name, "order.avsc");
+        assert_eq!(
+            artifacts[0].contents,
+            r#"{
+  "type": "record",
+  "name": "Order",
+  "fields": [
+    {
+      "name": "user_id",
+      "type": "long"
+    },
+    {
+      "name": "tags",
+      "type": {
+        "type": "record",
+        "name": "Tags",
+        "fields": [
+          {
+            "name": "a",
+            "type": "string"
+          }
+        ]
+      }
+    },
+    {
+      "name": "items",
+      "type": {
+        "type": "array",
+        "items": {
+          "type": "record",
+          "name": "Item",
+          "fields": [
+            {
+              "name": "sku",
+              "type": "string"
+            },
+            {
+              "name": "qty",
+              "type": "null",
+              "default": null
+            }
+          ]
+        }
+      }
+    },
+    {
+      "name": "v",
+      "type": {
+        "type": "array",
+        "items": [
+          "long",
+          "string"
+        ]
+      }
+    }
+  ]
+}
+"#
+        )
+    }
+
+    #[test]
+    fn schemas_roundtrip() {
+        let schema = r#"{"type": "record", "name": "Event", "fields": [
+            {"name": "at", "type": {"type": "int", "logicalType": "date"}},
+            {"name": "scores", "type": {"type": "map", "values": ["null", "double"]}}
+        ]}"#;
+        let structure = avro_schema_to_structure(schema).unwrap();
+
+        let artifacts = AvroBackend.generate(&structure, "Event", &ConvertOptions::default());
+
+        assert_eq!(
+            avro_schema_to_structure(&artifacts[0].contents).unwrap(),
+            structure
+        );
+    }
+}
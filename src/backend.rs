@@ -0,0 +1,136 @@
+use crate::codegen::generate_rust_with_options;
+use crate::de_helpers::de_helpers_module;
+use crate::generated_file::generated_file;
+use crate::naming::to_snake_case;
+use crate::options::ConvertOptions;
+use crate::JsonStructure;
+
+/// A file produced by a codegen backend.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Artifact {
+    /// Suggested file name, relative to wherever the output is written
+    pub file_name: String,
+    pub contents: String,
+}
+
+impl Artifact {
+    pub fn new<N: Into<String>, C: Into<String>>(file_name: N, contents: C) -> Artifact {
+        Artifact {
+            file_name: file_name.into(),
+            contents: contents.into(),
+        }
+    }
+}
+
+/// Turns an inferred structure into output files. Implement this to emit
+/// something other than rust types, such as validation code or a different
+/// style of types.
+pub trait CodegenBackend {
+    fn generate(
+        &self,
+        structure: &JsonStructure,
+        root_name: &str,
+        options: &ConvertOptions,
+    ) -> Vec<Artifact>;
+}
+
+/// Emits rust structs and enums deriving serde's `Serialize` and `Deserialize`,
+/// as a single `<root_name>.rs` file, with the header and footer from the
+/// options. With `ConvertOptions::set_helpers_module`,
+/// the `deserialize_with` helpers the types use are emitted to `de_helpers.rs`.
+pub struct RustSerdeBackend;
+
+impl CodegenBackend for RustSerdeBackend {
+    fn generate(
+        &self,
+        structure: &JsonStructure,
+        root_name: &str,
+        options: &ConvertOptions,
+    ) -> Vec<Artifact> {
+        let code = generate_rust_with_options(structure, root_name, options);
+        let helpers = match &options.helpers_module {
+            Some(_) => de_helpers_module(&code),
+            None => None,
+        };
+        let mut artifacts = vec![Artifact::new(
+            format!("{}.rs", to_snake_case(root_name)),
+            generated_file(&code, options),
+        )];
+        if let Some(helpers) = helpers {
+            artifacts.push(Artifact::new("de_helpers.rs", helpers));
+        }
+        artifacts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    struct FieldListBackend;
+
+    impl CodegenBackend for FieldListBackend {
+        fn generate(
+            &self,
+            structure: &JsonStructure,
+            root_name: &str,
+            _: &ConvertOptions,
+        ) -> Vec<Artifact> {
+            let fields: Vec<&str> = structure
+                .fields()
+                .unwrap_or(&[])
+                .iter()
+                .map(|p| p.key())
+                .collect();
+            vec![Artifact::new(
+                format!("{}.txt", root_name),
+                fields.join("\n"),
+            )]
+        }
+    }
+
+    fn run(backend: &dyn CodegenBackend, json: &str) -> Vec<Artifact> {
+        let structure = convert_sample_json(json).unwrap();
+        backend.generate(&structure, "ApiResponse", &ConvertOptions::default())
+    }
+
+    #[test]
+    fn rust_backend_emits_a_single_file() {
+        let artifacts = run(&RustSerdeBackend, r#"{"id": 1}"#);
+
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].file_name, "api_response.rs");
+        assert!(artifacts[0].contents.contains("pub struct ApiResponse {"));
+    }
+
+    #[test]
+    fn rust_backend_emits_the_shared_helpers() {
+        let structure = convert_sample_json(r#"[{"active": true}, {"active": 0}]"#).unwrap();
+        let mut options = ConvertOptions::default();
+        options
+            .set_numeric_bools(true)
+            .set_helpers_module(Some("crate::de_helpers".to_string()));
+        let artifacts = RustSerdeBackend.generate(&structure, "ApiResponse", &options);
+
+        assert_eq!(artifacts.len(), 2);
+        assert!(artifacts[0]
+            .contents
+            .contains("#[serde(deserialize_with = \"crate::de_helpers::bool_from_int\")]"));
+        assert!(!artifacts[0].contents.contains("fn bool_from_int"));
+        assert_eq!(artifacts[1].file_name, "de_helpers.rs");
+        assert!(artifacts[1]
+            .contents
+            .contains("pub fn bool_from_int<'de, D>"));
+    }
+
+    #[test]
+    fn custom_backends_can_be_used_as_trait_objects() {
+        let artifacts = run(&FieldListBackend, r#"{"id": 1, "name": "x"}"#);
+
+        assert_eq!(
+            artifacts,
+            vec![Artifact::new("ApiResponse.txt", "id\nname")]
+        );
+    }
+}
@@ -0,0 +1,11 @@
+use std::io;
+use std::process;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(e) = json2rust::run_language_server(stdin.lock(), stdout.lock()) {
+        eprintln!("json2rust-lsp: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::shared::Error;
+
+/// Reads big endian values from a binary sample, as used by both MessagePack
+/// and CBOR.
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pub position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> ByteReader<'a> {
+        ByteReader { bytes, position: 0 }
+    }
+
+    pub fn error<S: Into<String>>(&self, message: S) -> Error {
+        Error::InvalidBinary {
+            offset: self.position,
+            message: message.into(),
+        }
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.peek().is_none()
+    }
+
+    pub fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).cloned()
+    }
+
+    pub fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| self.error("Unexpected end of input"))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    /// Reads an unsigned integer of `len` bytes.
+    pub fn uint(&mut self, len: usize) -> Result<u64, Error> {
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |value, byte| (value << 8) | u64::from(*byte)))
+    }
+
+    /// Reads a two's complement signed integer of `len` bytes.
+    pub fn int(&mut self, len: usize) -> Result<i64, Error> {
+        let shift = 64 - 8 * len as u32;
+        Ok(((self.uint(len)? << shift) as i64) >> shift)
+    }
+
+    /// Reads an IEEE 754 float of 2, 4 or 8 bytes.
+    pub fn float(&mut self, len: usize) -> Result<f64, Error> {
+        let bits = self.uint(len)?;
+        Ok(match len {
+            2 => half_to_f64(bits as u16),
+            4 => f64::from(f32::from_bits(bits as u32)),
+            _ => f64::from_bits(bits),
+        })
+    }
+
+    /// Reads `len` bytes of utf-8 text.
+    pub fn text(&mut self, len: usize) -> Result<String, Error> {
+        let start = self.position;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::InvalidBinary {
+            offset: start,
+            message: "Invalid utf-8 in string".to_string(),
+        })
+    }
+
+    /// Converts a length read from the input, failing if it can't possibly fit
+    /// in the rest of the input, so huge lengths don't cause huge allocations.
+    pub fn length(&self, len: u64) -> Result<usize, Error> {
+        if len > (self.bytes.len() - self.position) as u64 {
+            return Err(self.error("Length is larger than the rest of the input"));
+        }
+        Ok(len as usize)
+    }
+}
+
+/// Converts a half precision float, which only CBOR uses.
+fn half_to_f64(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f64::from(bits & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_big_endian_values() {
+        let mut reader = ByteReader::new(&[0x01, 0x02, 0x03, b'h', b'i']);
+
+        assert_eq!(reader.byte(), Ok(1));
+        assert_eq!(reader.uint(2), Ok(0x0203));
+        assert_eq!(reader.text(2), Ok("hi".to_string()));
+        assert!(reader.is_at_end());
+        assert_eq!(
+            reader.byte(),
+            Err(Error::InvalidBinary {
+                offset: 5,
+                message: "Unexpected end of input".to_string()
+            })
+        );
+    }
+}
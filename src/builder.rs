@@ -0,0 +1,89 @@
+use crate::{JsonPair, JsonStructure};
+use alloc::{string::String, vec::Vec};
+
+/// Assembles an object structure by hand, e.g. to feed code generation
+/// without a sample.
+///
+/// ```
+/// # #[cfg(feature = "std")] {
+/// use json2rust::{generate_rust, JsonStructure};
+///
+/// let user = JsonStructure::object()
+///     .field("id", JsonStructure::int())
+///     .optional_field("name", JsonStructure::string())
+///     .build();
+///
+/// assert!(generate_rust(&user, "User").contains("pub name: Option<String>,"));
+/// # }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ObjectBuilder {
+    pairs: Vec<JsonPair>,
+}
+
+impl ObjectBuilder {
+    pub fn new() -> ObjectBuilder {
+        ObjectBuilder::default()
+    }
+
+    /// Adds a field, replacing any earlier field with the same key.
+    pub fn field<S: Into<String>>(mut self, key: S, value: JsonStructure) -> ObjectBuilder {
+        let pair = JsonPair::new(key, value);
+        match self.pairs.iter_mut().find(|p| p.key() == pair.key()) {
+            Some(existing) => *existing = pair,
+            None => self.pairs.push(pair),
+        }
+        self
+    }
+
+    /// Adds a field that may be missing or null.
+    pub fn optional_field<S: Into<String>>(self, key: S, value: JsonStructure) -> ObjectBuilder {
+        self.field(key, JsonStructure::optional(value))
+    }
+
+    pub fn build(self) -> JsonStructure {
+        JsonStructure::Object(self.pairs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    #[test]
+    fn builds_the_same_structure_as_inference() {
+        let built = JsonStructure::object()
+            .field("id", JsonStructure::int())
+            .field(
+                "tags",
+                JsonStructure::array(
+                    JsonStructure::object()
+                        .field("name", JsonStructure::string())
+                        .build(),
+                ),
+            )
+            .optional_field("score", JsonStructure::float())
+            .build();
+
+        let inferred = convert_sample_json(
+            r#"[{"id": 1, "tags": [{"name": "a"}], "score": null}, {"id": 2, "tags": [], "score": 1.5}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(Some(&built), inferred.element());
+    }
+
+    #[test]
+    fn replaces_fields_with_the_same_key() {
+        let built = JsonStructure::object()
+            .field("id", JsonStructure::int())
+            .field("id", JsonStructure::string())
+            .build();
+
+        assert_eq!(
+            built,
+            JsonStructure::Object(vec![JsonPair::new("id", JsonStructure::string())])
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::hash::Fnv;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Entries written so far by this process, to name the next temporary file.
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// An on-disk cache of generated code, keyed by a hash of the sample and the
+/// options, so build scripts and watchers can skip inference and code
+/// generation for samples that haven't changed. Entries are kept per version of
+/// json2rust, and those of other versions are removed as new ones are written.
+///
+/// Reading and writing the cache is best effort: if it fails, the code is
+/// generated as if it wasn't cached.
+///
+/// ```no_run
+/// use json2rust::{json_to_rust, GenerationCache};
+///
+/// let sample = std::fs::read_to_string("user.json").unwrap();
+/// let code = GenerationCache::in_target_dir()
+///     .get_or_insert_with(&sample, "", || json_to_rust(&sample, "User"))
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct GenerationCache {
+    dir: PathBuf,
+}
+
+impl GenerationCache {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> GenerationCache {
+        GenerationCache { dir: dir.into() }
+    }
+
+    /// A cache in `json2rust-cache` in the cargo target directory, which is
+    /// `CARGO_TARGET_DIR` or else `target`.
+    pub fn in_target_dir() -> GenerationCache {
+        let target = env::var_os("CARGO_TARGET_DIR").map_or_else(|| "target".into(), PathBuf::from);
+        GenerationCache::new(target.join("json2rust-cache"))
+    }
+
+    /// The cached code for `sample`, or else the code `generate` returns, which
+    /// is cached. `options_key` must describe everything else the code depends
+    /// on, such as the root name and the contents of the config file, as the
+    /// options themselves can hold closures that can't be hashed.
+    pub fn get_or_insert_with<E, F>(
+        &self,
+        sample: &str,
+        options_key: &str,
+        generate: F,
+    ) -> Result<String, E>
+    where
+        F: FnOnce() -> Result<String, E>,
+    {
+        let mut hasher = Fnv::new();
+        hasher.write_str(options_key);
+        hasher.write_str(sample);
+        let dir = self.dir.join(env!("CARGO_PKG_VERSION"));
+        let path = dir.join(format!("{:016x}.rs", hasher.finish()));
+        if let Ok(code) = fs::read_to_string(&path) {
+            return Ok(code);
+        }
+        let code = generate()?;
+        if fs::create_dir_all(&dir).is_ok() {
+            self.remove_other_versions();
+            let _ = write_entry(&path, &code);
+        }
+        Ok(code)
+    }
+
+    /// Removes every cached entry.
+    pub fn clear(&self) -> std::io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn remove_other_versions(&self) {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        for entry in entries.flatten() {
+            if entry.file_name() != env!("CARGO_PKG_VERSION") {
+                let _ = fs::remove_dir_all(entry.path());
+            }
+        }
+    }
+}
+
+/// Writes `code` to a temporary file next to `path` and renames it into place,
+/// so other processes reading the cache at the same time never see a partly
+/// written entry.
+fn write_entry(path: &Path, code: &str) -> io::Result<()> {
+    let temporary = path.with_extension(format!(
+        "rs.{}-{}.tmp",
+        process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = fs::write(&temporary, code).and_then(|()| fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_to_rust;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn reuses_code_for_unchanged_samples() {
+        let temp_dir = TempDir::new("json2rust-cache-test").unwrap();
+        let dir = temp_dir.path().join("cache");
+        let cache = GenerationCache::new(&dir);
+        fs::create_dir_all(dir.join("0.0.1")).unwrap();
+
+        let sample = r#"{"id": 1}"#;
+        let code = cache
+            .get_or_insert_with(sample, "User", || json_to_rust(sample, "User"))
+            .unwrap();
+        assert!(code.contains("pub struct User {"));
+        assert!(!dir.join("0.0.1").exists());
+        // Only the entry is left, without the temporary file it was written to
+        let entries: Vec<PathBuf> = fs::read_dir(dir.join(env!("CARGO_PKG_VERSION")))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].extension(), Some("rs".as_ref()));
+
+        let cached: Result<String, ()> =
+            cache.get_or_insert_with(sample, "User", || panic!("generated again"));
+        assert_eq!(cached, Ok(code));
+        let other: Result<String, &str> =
+            cache.get_or_insert_with(sample, "Account", || Err("not cached"));
+        assert_eq!(other, Err("not cached"));
+
+        cache.clear().unwrap();
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,254 @@
+use crate::binary::ByteReader;
+use crate::parser::{JsonNode, KeyInterner};
+use crate::shared::Error;
+
+/// Marks the end of an indefinite length item.
+const BREAK: u8 = 0xff;
+
+struct CborDecoder<'a> {
+    reader: ByteReader<'a>,
+    keys: KeyInterner,
+}
+
+impl<'a> CborDecoder<'a> {
+    /// Reads the argument of an item, which is `None` for indefinite lengths.
+    fn argument(&mut self, info: u8) -> Result<Option<u64>, Error> {
+        match info {
+            0..=23 => Ok(Some(u64::from(info))),
+            24..=27 => Ok(Some(self.reader.uint(1 << (info - 24))?)),
+            31 => Ok(None),
+            _ => Err(self
+                .reader
+                .error(format!("Invalid additional info {}", info))),
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonNode, Error> {
+        let start = self.reader.position;
+        let initial = self.reader.byte()?;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        if major == 7 {
+            return match info {
+                20 | 21 => Ok(JsonNode::Bool),
+                22 | 23 => Ok(JsonNode::Null),
+                25..=27 => Ok(JsonNode::Float(self.reader.float(1 << (info - 24))?)),
+                _ => {
+                    self.reader.position = start;
+                    Err(self
+                        .reader
+                        .error(format!("Unsupported simple value {}", info)))
+                }
+            };
+        }
+
+        let argument = self.argument(info)?;
+        match (major, argument) {
+            (0, Some(value)) => Ok(JsonNode::Int(i128::from(value))),
+            (1, Some(value)) => Ok(JsonNode::Int(-1 - i128::from(value))),
+            (2, _) => {
+                self.string_chunks(2, argument)?;
+                Ok(JsonNode::Bytes)
+            }
+            (3, _) => Ok(JsonNode::String(self.string_chunks(3, argument)?)),
+            (4, _) => {
+                let length = self.length(argument)?;
+                let mut values = Vec::new();
+                while self.has_next(values.len(), length)? {
+                    values.push(self.value()?);
+                }
+                Ok(JsonNode::Array(values))
+            }
+            (5, _) => {
+                let length = self.length(argument)?;
+                let mut pairs = Vec::new();
+                while self.has_next(pairs.len(), length)? {
+                    let key = self.key()?;
+                    pairs.push((self.keys.intern(&key), self.value()?));
+                }
+                Ok(JsonNode::Object(pairs))
+            }
+            // Bignums are still integers, other tags don't change the shape of
+            // the value they wrap
+            (6, Some(2)) => Ok(JsonNode::Int(self.bignum()?)),
+            (6, Some(3)) => Ok(JsonNode::Int(-1 - self.bignum()?)),
+            (6, Some(_)) => self.value(),
+            _ => {
+                self.reader.position = start;
+                Err(self.reader.error("Invalid indefinite length item"))
+            }
+        }
+    }
+
+    /// Reads the byte string of a bignum as an unsigned integer, clamped to
+    /// what fits in an i128.
+    fn bignum(&mut self) -> Result<i128, Error> {
+        let start = self.reader.position;
+        let initial = self.reader.byte()?;
+        if initial >> 5 != 2 {
+            self.reader.position = start;
+            return Err(self.reader.error("Expected a byte string in a bignum"));
+        }
+        match self.argument(initial & 0x1f)? {
+            Some(length) => {
+                let length = self.reader.length(length)?;
+                Ok(self
+                    .reader
+                    .take(length)?
+                    .iter()
+                    .try_fold(0i128, |value, byte| {
+                        value.checked_mul(256)?.checked_add(i128::from(*byte))
+                    })
+                    .unwrap_or(i128::MAX))
+            }
+            None => {
+                self.string_chunks(2, None)?;
+                Ok(i128::MAX)
+            }
+        }
+    }
+
+    /// Checks the length of an array or map, as every item takes at least one byte.
+    fn length(&self, length: Option<u64>) -> Result<Option<usize>, Error> {
+        length.map(|length| self.reader.length(length)).transpose()
+    }
+
+    /// Whether an array or map has more items, after `count` items have been
+    /// read. Items of indefinite length end with a break.
+    fn has_next(&mut self, count: usize, length: Option<usize>) -> Result<bool, Error> {
+        match length {
+            Some(length) => Ok(count < length),
+            None if self.reader.peek() == Some(BREAK) => {
+                self.reader.byte()?;
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Reads a byte or text string of the given major type, which may be split
+    /// into chunks if it has an indefinite length.
+    fn string_chunks(&mut self, major: u8, length: Option<u64>) -> Result<String, Error> {
+        let length = match length {
+            Some(length) => self.reader.length(length)?,
+            None => {
+                let mut text = String::new();
+                while self.reader.peek() != Some(BREAK) {
+                    let initial = self.reader.byte()?;
+                    if initial >> 5 != major {
+                        self.reader.position -= 1;
+                        return Err(self
+                            .reader
+                            .error("Invalid chunk in indefinite length string"));
+                    }
+                    match self.argument(initial & 0x1f)? {
+                        Some(length) => text.push_str(&self.string_chunks(major, Some(length))?),
+                        None => return Err(self.reader.error("Nested indefinite length string")),
+                    }
+                }
+                self.reader.byte()?;
+                return Ok(text);
+            }
+        };
+        if major == 3 {
+            self.reader.text(length)
+        } else {
+            self.reader.take(length)?;
+            Ok(String::new())
+        }
+    }
+
+    /// Reads a map key. Integer keys are used as their decimal text, like they
+    /// would be in json.
+    fn key(&mut self) -> Result<String, Error> {
+        let start = self.reader.position;
+        let initial = self.reader.byte()?;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        match (major, self.argument(info)?) {
+            (0, Some(value)) => Ok(value.to_string()),
+            (1, Some(value)) => Ok((-1 - i128::from(value)).to_string()),
+            (3, length) => self.string_chunks(3, length),
+            _ => {
+                self.reader.position = start;
+                Err(self.reader.error("Map keys must be strings or integers"))
+            }
+        }
+    }
+}
+
+/// Decodes a CBOR document into a node tree.
+pub fn parse_cbor(bytes: &[u8]) -> Result<JsonNode, Error> {
+    let mut decoder = CborDecoder {
+        reader: ByteReader::new(bytes),
+        keys: KeyInterner::default(),
+    };
+    let node = decoder.value()?;
+    if !decoder.reader.is_at_end() {
+        return Err(decoder.reader.error("Unexpected data after the value"));
+    }
+    Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_cbor() {
+        // {"id": 7, "name": (_ "a" "b"), -3: [true, null, 1.5], "raw": h'ff00', "n": 2(h'01')}
+        let bytes = [
+            0xa5, 0x62, b'i', b'd', 0x07, 0x64, b'n', b'a', b'm', b'e', 0x7f, 0x61, b'a', 0x61,
+            b'b', 0xff, 0x22, 0x83, 0xf5, 0xf6, 0xf9, 0x3e, 0x00, 0x63, b'r', b'a', b'w', 0x42,
+            0xff, 0x00, 0x61, b'n', 0xc2, 0x41, 0x01,
+        ];
+
+        assert_eq!(
+            parse_cbor(&bytes),
+            Ok(JsonNode::Object(vec![
+                ("id".into(), JsonNode::Int(7)),
+                ("name".into(), JsonNode::String("ab".to_string())),
+                (
+                    "-3".into(),
+                    JsonNode::Array(vec![JsonNode::Bool, JsonNode::Null, JsonNode::Float(1.5)])
+                ),
+                ("raw".into(), JsonNode::Bytes),
+                ("n".into(), JsonNode::Int(1)),
+            ]))
+        )
+    }
+
+    #[test]
+    fn decodes_indefinite_arrays() {
+        assert_eq!(
+            parse_cbor(&[0x9f, 0x01, 0x9f, 0xff, 0xff]),
+            Ok(JsonNode::Array(vec![
+                JsonNode::Int(1),
+                JsonNode::Array(vec![])
+            ]))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_cbor() {
+        assert_eq!(
+            parse_cbor(&[0x1c]),
+            Err(Error::InvalidBinary {
+                offset: 1,
+                message: "Invalid additional info 28".to_string()
+            })
+        );
+        assert_eq!(
+            parse_cbor(&[0xa1, 0xf5, 0x01]),
+            Err(Error::InvalidBinary {
+                offset: 1,
+                message: "Map keys must be strings or integers".to_string()
+            })
+        );
+        assert_eq!(
+            parse_cbor(&[0x01, 0x02]),
+            Err(Error::InvalidBinary {
+                offset: 1,
+                message: "Unexpected data after the value".to_string()
+            })
+        );
+    }
+}
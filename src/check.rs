@@ -0,0 +1,267 @@
+use crate::dependencies::{dependencies_toml, plan_dependencies};
+use crate::options::ConvertOptions;
+use crate::origins::{conversion_to_rust_with_origins, origin_at, Origin};
+use crate::sample_test::generate_sample_tests;
+use crate::temp_dir::TempDir;
+use crate::Conversion;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// An error the compiler reported for generated code.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CompileError {
+    pub message: String,
+    /// The line of the generated code, if the error points at one
+    pub line: Option<usize>,
+    /// Json pointer of the value the erroring code was generated for
+    pub path: Option<String>,
+    /// The line of the sample the value at `path` was first found on
+    pub sample_line: Option<i64>,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.path, self.line) {
+            (Some(path), Some(line)) => match self.sample_line {
+                Some(sample_line) => write!(
+                    f,
+                    "{} (line {}, line {} of the sample): {}",
+                    path, line, sample_line, self.message
+                ),
+                None => write!(f, "{} (line {}): {}", path, line, self.message),
+            },
+            (None, Some(line)) => write!(f, "line {}: {}", line, self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Reads the errors from the output of `cargo check --message-format short`,
+/// which puts each on a line like `src/generated.rs:3:5: error[E0412]: message`.
+fn parse_errors(output: &str, origins: &[Origin], mapped_lines: usize) -> Vec<CompileError> {
+    output
+        .lines()
+        .filter_map(|line| {
+            if let Some(rest) = line.strip_prefix("src/generated.rs:") {
+                let mut parts = rest.splitn(3, ':');
+                let line = parts.next()?.parse::<usize>().ok()?;
+                let _column = parts.next()?;
+                let message = parts.next()?.trim();
+                if !message.starts_with("error") {
+                    return None;
+                }
+                let origin = if line <= mapped_lines {
+                    origin_at(origins, line)
+                } else {
+                    None
+                };
+                Some(CompileError {
+                    message: message.to_string(),
+                    line: Some(line),
+                    path: origin.map(|origin| origin.pointer.clone()),
+                    sample_line: origin
+                        .and_then(|origin| origin.span)
+                        .map(|span| span.start.line()),
+                })
+            } else if line.starts_with("error") && !line.starts_with("error: could not compile") {
+                Some(CompileError {
+                    message: line.to_string(),
+                    line: None,
+                    path: None,
+                    sample_line: None,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Writes `code` into a cargo project in `dir`, with the dependencies it needs,
+/// and runs `cargo check` on it, including its tests, or `cargo clippy` with
+/// the pedantic lints denied when `lint` is set. Returns the errors, which are
+/// mapped to json pointers and the sample with `origins` for the first
+/// `mapped_lines` lines.
+fn check_in(
+    dir: &Path,
+    code: &str,
+    origins: &[Origin],
+    mapped_lines: usize,
+    lint: bool,
+) -> io::Result<Vec<CompileError>> {
+    fs::create_dir_all(dir.join("src"))?;
+    let manifest = format!(
+        "[package]\nname = \"json2rust-check\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+         # Keeps the project out of any workspace it is created in\n[workspace]\n\n{}",
+        dependencies_toml(&plan_dependencies(code))
+    );
+    fs::write(dir.join("Cargo.toml"), manifest)?;
+    // Clippy skips some lints on types a library exports, which users' own
+    // modules don't
+    let lib = if lint {
+        "#![allow(dead_code)]\nmod generated;\n"
+    } else {
+        "pub mod generated;\n"
+    };
+    fs::write(dir.join("src").join("lib.rs"), lib)?;
+    fs::write(dir.join("src").join("generated.rs"), code)?;
+
+    let mut command = Command::new("cargo");
+    // Cargo locks its target directory, so checks running at the same time
+    // can share the dependencies compiled for them
+    command.env(
+        "CARGO_TARGET_DIR",
+        env::temp_dir().join("json2rust-check-target"),
+    );
+    command.arg(if lint { "clippy" } else { "check" }).args([
+        "--all-targets",
+        "--quiet",
+        "--message-format",
+        "short",
+    ]);
+    if lint {
+        command.args(["--", "-D", "warnings", "-D", "clippy::pedantic"]);
+    }
+    let output = command.current_dir(dir).output()?;
+    if output.status.success() {
+        return Ok(Vec::new());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let errors = parse_errors(&stderr, origins, mapped_lines);
+    if errors.is_empty() {
+        return Ok(vec![CompileError {
+            message: stderr.trim().to_string(),
+            line: None,
+            path: None,
+            sample_line: None,
+        }]);
+    }
+    Ok(errors)
+}
+
+/// Checks that `code` compiles, by running `cargo check` on it in a temporary
+/// cargo project with the dependencies from `plan_dependencies`. This needs
+/// `cargo`, and usually network access to fetch the dependencies.
+///
+/// Each check gets its own project in the system temp directory, removed
+/// afterwards, while their target directory is kept between checks, so the
+/// dependencies are only built once.
+pub fn check_code(code: &str) -> io::Result<Vec<CompileError>> {
+    check_in(TempDir::new("json2rust-check")?.path(), code, &[], 0, false)
+}
+
+/// Runs clippy on `code` like `check_code`, with every warning and the
+/// pedantic lints denied, so the lints it reports are errors. Generated code
+/// is meant to pass it.
+pub fn lint_code(code: &str) -> io::Result<Vec<CompileError>> {
+    check_in(TempDir::new("json2rust-check")?.path(), code, &[], 0, true)
+}
+
+/// Generates the types for a converted json sample along with the tests from
+/// `generate_sample_tests`, and checks that they compile, like `check_code`.
+/// Errors in the types are reported with the json pointer of the value the
+/// failing code was generated for, and its line in the sample.
+pub fn check_conversion(
+    conversion: &Conversion,
+    sample: &str,
+    root_name: &str,
+    options: &ConvertOptions,
+) -> io::Result<Vec<CompileError>> {
+    let (mut code, origins) = conversion_to_rust_with_origins(conversion, root_name, options);
+    let mapped_lines = code.lines().count();
+    code.push_str(&generate_sample_tests(
+        sample,
+        &conversion.structure,
+        root_name,
+        options,
+    ));
+    check_in(
+        TempDir::new("json2rust-check")?.path(),
+        &code,
+        &origins,
+        mapped_lines,
+        false,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json_with_warnings;
+    use crate::shared::{JsonTokenInfo, Span};
+
+    /// Needs `cargo clippy` and the dependencies of the generated code, run
+    /// with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn representative_outputs_are_clippy_clean() {
+        let sample = r#"[
+            {"user_id": 1, "_id": "x", "a": true, "b": false, "c": true, "d": 1, "e": true, "type": "t",
+             "tags": [1, "a"], "count": "3", "name": "", "nested": {"x-y": 1.5}},
+            {"user_id": 2, "_id": "y", "a": 0, "b": false, "c": true, "d": 0, "e": true, "type": "t",
+             "tags": [], "count": 4, "name": "x", "nested": {"x-y": 2}}
+        ]"#;
+        let mut options = ConvertOptions::default();
+        options
+            .set_mixed_number_policy(crate::MixedNumberPolicy::Number)
+            .set_numeric_bools(true)
+            .set_empty_strings_as_none(true);
+        let conversion = convert_sample_json_with_warnings(sample, &options).unwrap();
+        let mut code = crate::conversion_to_rust(&conversion, "User", &options);
+        code.push_str(&generate_sample_tests(
+            sample,
+            &conversion.structure,
+            "User",
+            &options,
+        ));
+
+        assert_eq!(lint_code(&code).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn parses_compiler_errors() {
+        let output = "warning: unused import\n\
+                      src/generated.rs:5:13: error[E0412]: cannot find type `Foo` in this scope\n\
+                      src/generated.rs:20:1: error: expected item\n\
+                      src/generated.rs:6:1: warning: unused\n\
+                      error: could not compile `json2rust-check` (lib) due to 2 previous errors\n\
+                      error: failed to select a version for `chrono`";
+        let origin = |line, pointer: &str, sample_line| Origin {
+            line,
+            pointer: pointer.to_string(),
+            span: Some(Span {
+                start: JsonTokenInfo::new(sample_line, 3, 0),
+                end: JsonTokenInfo::new(sample_line, 4, 1),
+            }),
+        };
+        let origins = vec![origin(3, "", 1), origin(5, "/id", 2)];
+
+        assert_eq!(
+            parse_errors(output, &origins, 10),
+            vec![
+                CompileError {
+                    message: "error[E0412]: cannot find type `Foo` in this scope".to_string(),
+                    line: Some(5),
+                    path: Some("/id".to_string()),
+                    sample_line: Some(2),
+                },
+                CompileError {
+                    message: "error: expected item".to_string(),
+                    line: Some(20),
+                    path: None,
+                    sample_line: None,
+                },
+                CompileError {
+                    message: "error: failed to select a version for `chrono`".to_string(),
+                    line: None,
+                    path: None,
+                    sample_line: None,
+                },
+            ]
+        );
+    }
+}
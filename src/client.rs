@@ -0,0 +1,314 @@
+use crate::codegen::{generate_rust_roots, root_type, Direction};
+use crate::naming::{field_name, to_snake_case, type_name, unique_name};
+use crate::options::ConvertOptions;
+use crate::shared::Error;
+use crate::{convert_sample_json_with_options, JsonPair};
+
+/// A typed `reqwest` function calling one endpoint of an API.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ClientStub {
+    /// Name of the generated function, e.g. `create_user`
+    pub function_name: String,
+    /// Http method, e.g. `POST`
+    pub method: String,
+    /// Path relative to the base url, with parameters as `{name}`, e.g.
+    /// `/users/{id}`
+    pub path: String,
+    /// Rust type of the request body, if the endpoint takes one
+    pub request_type: Option<String>,
+    /// Rust type of the response body, or `None` to ignore the response body
+    pub response_type: Option<String>,
+}
+
+impl ClientStub {
+    pub fn new<N: Into<String>, M: Into<String>, P: Into<String>>(
+        function_name: N,
+        method: M,
+        path: P,
+    ) -> ClientStub {
+        ClientStub {
+            function_name: function_name.into(),
+            method: method.into(),
+            path: path.into(),
+            request_type: None,
+            response_type: None,
+        }
+    }
+
+    /// The `{name}` parameters of the path, in order.
+    fn parameters(&self) -> Vec<(String, String)> {
+        self.path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+            .map(|name| match field_name(name) {
+                field if field.is_empty() => (name.to_string(), "param".to_string()),
+                field => (name.to_string(), field),
+            })
+            .collect()
+    }
+
+    /// Generates the async function for the endpoint.
+    pub fn to_rust(&self) -> String {
+        let parameters = self.parameters();
+        let mut arguments = vec![
+            "client: &reqwest::Client".to_string(),
+            "base: &reqwest::Url".to_string(),
+        ];
+        arguments.extend(parameters.iter().map(|(_, arg)| format!("{}: &str", arg)));
+        if let Some(request_type) = &self.request_type {
+            arguments.push(format!("body: &{}", request_type));
+        }
+
+        let mut path = self.path.clone();
+        for (name, _) in &parameters {
+            path = path.replace(&format!("{{{}}}", name), "{}");
+        }
+        let path = path.escape_debug().to_string();
+        let url = if parameters.is_empty() {
+            format!(
+                "format!(\"{{}}{}\", base.as_str().trim_end_matches('/'))",
+                path
+            )
+        } else {
+            let args: Vec<&str> = parameters.iter().map(|(_, arg)| arg.as_str()).collect();
+            format!(
+                "format!(\n        \"{{}}{}\",\n        base.as_str().trim_end_matches('/'),\n        {}\n    )",
+                path,
+                args.join(",\n        ")
+            )
+        };
+
+        let method = to_snake_case(&self.method);
+        let mut call = match method.as_str() {
+            "get" | "post" | "put" | "patch" | "delete" | "head" => {
+                format!("    client\n        .{}(url)\n", method)
+            }
+            _ => format!(
+                "    client\n        .request(reqwest::Method::{}, url)\n",
+                self.method.to_uppercase()
+            ),
+        };
+        if self.request_type.is_some() {
+            call.push_str("        .json(body)\n");
+        }
+        call.push_str("        .send()\n        .await?\n        .error_for_status()?\n");
+        let mut errors =
+            "Fails if the request can't be sent or the response has an error status".to_string();
+        let return_type = match &self.response_type {
+            Some(response_type) => {
+                call.push_str("        .json()\n        .await\n");
+                errors = format!(
+                    "Fails if the request can't be sent, the response has an error status,\n\
+                     /// or its body isn't a `{}`",
+                    response_type
+                );
+                response_type.as_str()
+            }
+            None => {
+                call = call.replacen("    client", "    let _ = client", 1);
+                call = call.trim_end().to_string() + ";\n    Ok(())\n";
+                "()"
+            }
+        };
+
+        format!(
+            "/// `{} {}`\n///\n/// # Errors\n///\n/// {}.\npub async fn {}(\n    {},\n) -> Result<{}, reqwest::Error> {{\n    let url = {};\n{}}}\n",
+            self.method.to_uppercase(),
+            self.path,
+            errors,
+            self.function_name,
+            arguments.join(",\n    "),
+            return_type,
+            url,
+            call
+        )
+    }
+}
+
+/// Generates response types and a `reqwest` function for every endpoint, given
+/// as a method, a path and a sample response body. Functions are named after
+/// the method and path, e.g. `get_users_id` for `GET /users/{id}`, and return
+/// a `GetUsersIdResponse`.
+pub fn client_to_rust(
+    endpoints: &[(&str, &str, &str)],
+    options: &ConvertOptions,
+) -> Result<String, Error> {
+    let mut roots = Vec::new();
+    let mut stubs = Vec::new();
+    let mut function_names = Vec::new();
+    for (method, path, sample) in endpoints {
+        let function_name = match to_snake_case(&format!("{} {}", method, path)) {
+            name if name.is_empty() => "call".to_string(),
+            name => name,
+        };
+        let function_name = unique_name(function_name, &mut function_names);
+        let response_name = format!("{}Response", type_name(&function_name));
+        let response = convert_sample_json_with_options(sample, options)?;
+
+        let mut stub = ClientStub::new(function_name, *method, *path);
+        stub.response_type = Some(root_type(&response, &response_name));
+        stubs.push(stub);
+        roots.push((JsonPair::new(response_name, response), Direction::Response));
+    }
+
+    // Generated together, so nested types shared between endpoints are only
+    // defined once, and different ones don't collide
+    let mut output = generate_rust_roots(&roots, options);
+    for stub in stubs {
+        output.push('\n');
+        output.push_str(&stub.to_rust());
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_stubs_with_bodies() {
+        let mut stub = ClientStub::new("create_user", "POST", "/users");
+        stub.request_type = Some("CreateUserRequest".to_string());
+        stub.response_type = Some("CreateUserResponse".to_string());
+
+        assert_eq!(
+            stub.to_rust(),
+            r#"/// `POST /users`
+///
+/// # Errors
+///
+/// Fails if the request can't be sent, the response has an error status,
+/// or its body isn't a `CreateUserResponse`.
+pub async fn create_user(
+    client: &reqwest::Client,
+    base: &reqwest::Url,
+    body: &CreateUserRequest,
+) -> Result<CreateUserResponse, reqwest::Error> {
+    let url = format!("{}/users", base.as_str().trim_end_matches('/'));
+    client
+        .post(url)
+        .json(body)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+"#
+        )
+    }
+
+    #[test]
+    fn generates_a_client_for_several_endpoints() {
+        let result = client_to_rust(
+            &[
+                ("GET", "/users", r#"[{"id": 1, "role": {"name": "a"}}]"#),
+                ("GET", "/users/{id}", r#"{"id": 1, "role": {"name": "a"}}"#),
+            ],
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetUsersResponse {
+    pub id: i64,
+    pub role: Role,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Role {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetUsersIdResponse {
+    pub id: i64,
+    pub role: Role,
+}
+
+/// `GET /users`
+///
+/// # Errors
+///
+/// Fails if the request can't be sent, the response has an error status,
+/// or its body isn't a `Vec<GetUsersResponse>`.
+pub async fn get_users(
+    client: &reqwest::Client,
+    base: &reqwest::Url,
+) -> Result<Vec<GetUsersResponse>, reqwest::Error> {
+    let url = format!("{}/users", base.as_str().trim_end_matches('/'));
+    client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+/// `GET /users/{id}`
+///
+/// # Errors
+///
+/// Fails if the request can't be sent, the response has an error status,
+/// or its body isn't a `GetUsersIdResponse`.
+pub async fn get_users_id(
+    client: &reqwest::Client,
+    base: &reqwest::Url,
+    id: &str,
+) -> Result<GetUsersIdResponse, reqwest::Error> {
+    let url = format!(
+        "{}/users/{}",
+        base.as_str().trim_end_matches('/'),
+        id
+    );
+    client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+"#
+        )
+    }
+
+    #[test]
+    fn generates_stubs_with_path_parameters() {
+        let stub = ClientStub::new("delete_user", "DELETE", "/users/{userId}/keys/{key}");
+
+        assert_eq!(
+            stub.to_rust(),
+            r#"/// `DELETE /users/{userId}/keys/{key}`
+///
+/// # Errors
+///
+/// Fails if the request can't be sent or the response has an error status.
+pub async fn delete_user(
+    client: &reqwest::Client,
+    base: &reqwest::Url,
+    user_id: &str,
+    key: &str,
+) -> Result<(), reqwest::Error> {
+    let url = format!(
+        "{}/users/{}/keys/{}",
+        base.as_str().trim_end_matches('/'),
+        user_id,
+        key
+    );
+    let _ = client
+        .delete(url)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+"#
+        )
+    }
+}
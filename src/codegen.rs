@@ -0,0 +1,3988 @@
+use crate::de_helpers::{helper_attribute, helpers_code};
+use crate::inference;
+use crate::layout::{field_order, Layout};
+use crate::naming::{
+    expand_abbreviations, field_name, field_names_without_common_prefix, to_snake_case,
+    transliterate_with_mapping, type_name, type_name_with_acronyms, unique_field_names,
+    variant_names, AcronymStyle, NameContext,
+};
+use crate::options::{
+    ArrayRoot, ConvertOptions, IntEnumStyle, MixedNumberPolicy, UnionTagging, WholeFloatPolicy,
+};
+use crate::parser::{parse_json_with, JsonNode};
+use crate::pointer::{child_path, element_path};
+use crate::shape_check::shape_check_code;
+use crate::stats::{PathStats, StringFormat};
+use crate::style;
+use crate::symbols::SymbolTable;
+use crate::tokenizer::Leniency;
+use crate::type_mapping::glob_matches;
+use crate::warnings::Warning;
+use crate::{Conversion, JsonPair, JsonStructure, JsonValue};
+
+/// The types used in generated code that implement `Eq` and `Ord`, along with
+/// the containers that do when what they hold does.
+const ORDERABLE_TYPES: &[&str] = &[
+    "String", "i64", "u64", "i128", "bool", "Option", "Vec", "BTreeSet",
+];
+
+/// Keys of the field an object is most likely known by, in order of
+/// preference, for the accessor of a union of a value and an object.
+const IDENTIFYING_KEYS: &[&str] = &["name", "id", "key", "value", "title", "slug"];
+
+/// The longest array serde implements `Serialize` and `Deserialize` for.
+const MAX_SERDE_ARRAY: usize = 32;
+
+/// How deep generated types are looked into for their layouts, which is only
+/// reached by types that hold themselves and can't be laid out anyway.
+const MAX_LAYOUT_DEPTH: usize = 32;
+
+/// Optional values at least this many bytes are warned about by `type_sizes`,
+/// as they take the space even when they are `None`.
+const LARGE_OPTION_SIZE: usize = 128;
+
+/// Enums whose largest variant is at least this many bytes larger than their
+/// smallest are warned about by `type_sizes`, as every value takes the space
+/// of the largest.
+const LARGE_VARIANT_DIFFERENCE: usize = 128;
+
+/// The json pointer of the value each line of generated code was generated
+/// for, as `(line, pointer)` sorted by line. Entries apply from their line up to
+/// the next entry. Lines start at 1.
+pub(crate) type SourceMap = Vec<(usize, String)>;
+
+/// Which way values of a generated type are sent, when generating types for
+/// server handlers.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub(crate) enum Direction {
+    /// Only received in requests, so only deserialized
+    Request,
+    /// Only sent in responses, so only serialized
+    Response,
+    Both,
+}
+
+impl Direction {
+    fn merge(self, other: Direction) -> Direction {
+        if self == other {
+            self
+        } else {
+            Direction::Both
+        }
+    }
+}
+
+struct Definition {
+    // The attributes and body of the type, without its derives
+    body: String,
+    // Json pointer of the value the type was generated for, and of each of its
+    // fields, along with the line of the body the field starts on
+    pointer: String,
+    field_pointers: Vec<(usize, String)>,
+    is_struct: bool,
+    direction: Direction,
+    // Indices of the definitions of the types this one uses
+    children: Vec<usize>,
+    // Clippy lints the type can't avoid, as they come from the json
+    allowed_lints: Vec<&'static str>,
+    // Whether the type also derives `Default`, which patch types do
+    derives_default: bool,
+    // Whether the type derives the serde traits of `serde_repr` instead, which
+    // integer enums do
+    serde_repr: bool,
+    // The name of the type, and the types of its fields or variants
+    name: String,
+    field_types: Vec<String>,
+    // Doc comment lines on how the type was generated, after its docs
+    notes: Vec<String>,
+}
+
+struct CodeGenerator<'a> {
+    symbols: &'a SymbolTable,
+    options: &'a ConvertOptions,
+    // Stats of the sample, used for validation constraints. Empty when the
+    // types aren't generated from a single conversion
+    stats: &'a [PathStats],
+    // Json pointer to the value currently being generated
+    pointer: String,
+    definitions: Vec<Definition>,
+    // Names of the types defined so far, so types shared between several
+    // values are only defined once
+    defined: Vec<String>,
+    // Keys leading to the value currently being generated
+    path: Vec<String>,
+    // The definition currently being generated, and the direction of its root
+    parent: Option<usize>,
+    direction: Direction,
+    uses_hash_map: bool,
+    // The set type used by fields generated as sets, if any
+    set_type: Option<&'static str>,
+    // Names of the `deserialize_with` helpers used by coerced fields
+    helpers: Vec<&'static str>,
+    // Keys of the wrapper objects that fields were unwrapped from
+    wrappers: Vec<String>,
+    // Names and fields of the structs generated so far, other than patch
+    // types, and the `From` impls between them already generated
+    struct_fields: Vec<(String, Vec<(String, String)>)>,
+    from_impls: Vec<(String, String)>,
+    // Type aliases written after the types, as the name they are based on,
+    // their name and the type they stand for
+    aliases: Vec<(String, String, String)>,
+    // The struct of the rows of an array root, and the name of the struct of
+    // their columns, for `ConvertOptions::set_columnar`
+    columns: Option<(String, String)>,
+    // The struct of the records of the sample, which database mappings are
+    // derived for
+    record: Option<String>,
+    // Structs whose fields were reordered, and the bytes that saves, for
+    // `ConvertOptions::set_reorder_fields`
+    field_order_savings: Vec<(String, usize)>,
+    // Json pointers, keys and types of the optional fields, for `type_sizes`
+    optional_fields: Vec<(String, String, String)>,
+}
+
+impl<'a> CodeGenerator<'a> {
+    fn new(
+        symbols: &'a SymbolTable,
+        options: &'a ConvertOptions,
+        direction: Direction,
+    ) -> CodeGenerator<'a> {
+        CodeGenerator {
+            symbols,
+            options,
+            stats: &[],
+            pointer: String::new(),
+            definitions: Vec::new(),
+            defined: Vec::new(),
+            path: Vec::new(),
+            parent: None,
+            direction,
+            uses_hash_map: false,
+            set_type: None,
+            helpers: Vec::new(),
+            wrappers: Vec::new(),
+            struct_fields: Vec::new(),
+            from_impls: Vec::new(),
+            aliases: Vec::new(),
+            columns: None,
+            record: None,
+            field_order_savings: Vec::new(),
+            optional_fields: Vec::new(),
+        }
+    }
+
+    fn type_for(&mut self, structure: &JsonStructure) -> String {
+        if let Some(tagging) = self.options.union_tagging(&self.pointer) {
+            if matches!(
+                structure,
+                JsonStructure::Object(_) | JsonStructure::Union(_)
+            ) {
+                return self.emit_tagged_enum(structure, tagging);
+            }
+        }
+        match structure {
+            JsonStructure::Array(inner) if self.is_set(inner) => {
+                let set_type = if self.options.hash_sets {
+                    "HashSet"
+                } else {
+                    "BTreeSet"
+                };
+                self.set_type = Some(set_type);
+                format!("{}<{}>", set_type, self.element_type(inner))
+            }
+            JsonStructure::Array(inner) => match self.fixed_size() {
+                Some(size) => format!("[{}; {}]", self.element_type(inner), size),
+                None => format!("Vec<{}>", self.element_type(inner)),
+            },
+            JsonStructure::Dictionary(pair) => {
+                self.uses_hash_map = true;
+                format!("HashMap<String, {}>", self.element_type(&pair.value))
+            }
+            JsonStructure::Object(pairs) => self.emit_struct(pairs),
+            JsonStructure::Value(value) => primitive_type(value),
+            JsonStructure::Optional(inner) => format!("Option<{}>", self.type_for(inner)),
+            JsonStructure::Union(variants) => self.emit_enum(variants),
+            JsonStructure::Unknown => "serde_json::Value".to_string(),
+        }
+    }
+
+    fn element_type(&mut self, structure: &JsonStructure) -> String {
+        let element = element_path(&self.pointer);
+        let parent = std::mem::replace(&mut self.pointer, element);
+        let element_type = self.type_for(structure);
+        self.pointer = parent;
+        element_type
+    }
+
+    /// Whether the array field being generated is generated as a set, with
+    /// `ConvertOptions::add_set_field`: its key must match one of the patterns,
+    /// its elements must be strings or integers, and no array in the sample may
+    /// have had the same element twice. Only known when generating with
+    /// `conversion_to_rust`.
+    fn is_set(&self, element: &JsonStructure) -> bool {
+        let key = match self.path.last() {
+            Some(key) if !self.pointer.is_empty() && !self.pointer.ends_with("/*") => key,
+            _ => return false,
+        };
+        let matches = self
+            .options
+            .set_fields
+            .iter()
+            .any(|pattern| glob_matches(pattern, key));
+        let scalar = match element {
+            JsonStructure::Value(JsonValue::String) | JsonStructure::Value(JsonValue::Int) => true,
+            JsonStructure::Value(JsonValue::Custom(rust_type)) => {
+                [inference::U64, inference::I128].contains(&rust_type.path())
+            }
+            _ => false,
+        };
+        matches
+            && scalar
+            && self
+                .stats
+                .iter()
+                .rev()
+                .find(|s| s.path == self.pointer)
+                .and_then(|s| s.unique_elements)
+                == Some(true)
+    }
+
+    /// The number of elements of the array being generated, if it is generated
+    /// as `[T; N]` with `ConvertOptions::set_fixed_size_arrays`.
+    fn fixed_size(&self) -> Option<usize> {
+        let min_observations = self.options.fixed_size_arrays?;
+        let stats = self.stats.iter().rev().find(|s| s.path == self.pointer)?;
+        match stats.element_counts {
+            Some((min, max))
+                if min == max
+                    && (1..=MAX_SERDE_ARRAY).contains(&min)
+                    && stats.count - stats.null_count >= min_observations =>
+            {
+                Some(min)
+            }
+            _ => None,
+        }
+    }
+
+    /// The `#[validate(...)]` rules for a field, from the values observed for
+    /// it in the sample. Nested structs are validated too.
+    fn validations(&self, structure: &JsonStructure, pointer: &str) -> Vec<String> {
+        let stats = self.stats.iter().rev().find(|s| s.path == pointer);
+        let mut rules = Vec::new();
+        match unwrap_optional(structure) {
+            JsonStructure::Value(JsonValue::String) => {
+                match stats.and_then(|s| s.string_format) {
+                    Some(StringFormat::Email) => rules.push("email".to_string()),
+                    Some(StringFormat::Url) => rules.push("url".to_string()),
+                    Some(StringFormat::Json) | None => {}
+                }
+                if let Some((min, max)) = stats.and_then(|s| s.length_range) {
+                    rules.push(format!("length(min = {}, max = {})", min, max));
+                }
+            }
+            JsonStructure::Value(value @ JsonValue::Int)
+            | JsonStructure::Value(value @ JsonValue::Float) => {
+                let range = stats
+                    .and_then(|s| s.value_range)
+                    .filter(|(min, max)| min.is_finite() && max.is_finite());
+                if let Some((min, max)) = range {
+                    let literal = |n: f64| match value {
+                        JsonValue::Int => format!("{}", n as i128),
+                        _ => format!("{:?}", n),
+                    };
+                    rules.push(format!(
+                        "range(min = {}, max = {})",
+                        literal(min),
+                        literal(max)
+                    ));
+                }
+            }
+            JsonStructure::Object(_) => rules.push("nested".to_string()),
+            // Structs in arrays nested any number of levels deep
+            JsonStructure::Array(inner)
+                if matches!(innermost_element(inner), JsonStructure::Object(_)) =>
+            {
+                rules.push("nested".to_string())
+            }
+            _ => {}
+        }
+        rules
+    }
+
+    /// The type and serde attribute of a field holding json sent as a string,
+    /// with `ConvertOptions::set_json_strings`, decoded by the `json_string`
+    /// helper. Only known when generating with `conversion_to_rust`.
+    fn json_string_field(&mut self, structure: &JsonStructure) -> Option<(String, String)> {
+        let stats = self.stats.iter().rev().find(|s| s.path == self.pointer)?;
+        if !self.options.json_strings || stats.string_format != Some(StringFormat::Json) {
+            return None;
+        }
+        let (optional, inner) = match structure {
+            JsonStructure::Optional(inner) => (true, &**inner),
+            other => (false, other),
+        };
+        let rust_type = self.type_for(inner);
+        Some(if optional {
+            self.helper_field(rust_type, "optional_json_string", true)
+        } else {
+            self.helper_field(rust_type, "json_string", false)
+        })
+    }
+
+    /// The type and serde attribute of a field read with a `deserialize_with`
+    /// helper: one that is sometimes a string and sometimes a number, when the
+    /// `MixedNumberPolicy` coerces it to one of them, a bool sometimes sent as
+    /// 0 or 1, an integer sometimes sent as a whole float, or an optional string
+    /// that may be empty.
+    fn coerced_field(&mut self, structure: &JsonStructure) -> Option<(String, String)> {
+        let (optional, inner) = match structure {
+            JsonStructure::Optional(inner) => (true, &**inner),
+            other => (false, other),
+        };
+        let (rust_type, helper) = match (inner, self.options.mixed_numbers, optional) {
+            (union, _, optional) if self.options.numeric_bools() && union.bool_or_int() => (
+                "bool".to_string(),
+                if optional {
+                    "optional_bool_from_int"
+                } else {
+                    "bool_from_int"
+                },
+            ),
+            (union, _, optional)
+                if self.options.whole_floats == WholeFloatPolicy::Int && union.int_or_float() =>
+            {
+                (
+                    "i64".to_string(),
+                    if optional {
+                        "optional_int_from_float"
+                    } else {
+                        "int_from_float"
+                    },
+                )
+            }
+            (
+                JsonStructure::Value(
+                    value @ (JsonValue::String | JsonValue::Int | JsonValue::Float),
+                ),
+                _,
+                true,
+            ) if self.options.empty_strings_as_none => {
+                (primitive_type(value), "empty_string_as_none")
+            }
+            (union, policy, optional) => {
+                let number = union.string_or_number()?;
+                match (policy, optional) {
+                    (MixedNumberPolicy::String, false) => {
+                        ("String".to_string(), "string_from_number")
+                    }
+                    (MixedNumberPolicy::String, true) => {
+                        ("String".to_string(), "optional_string_from_number")
+                    }
+                    (MixedNumberPolicy::Number, false) => {
+                        (primitive_type(number), "number_from_string")
+                    }
+                    (MixedNumberPolicy::Number, true) => {
+                        (primitive_type(number), "optional_number_from_string")
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        Some(self.helper_field(rust_type, helper, optional))
+    }
+
+    /// The type and serde attribute of a field of type `rust_type`, optional or
+    /// not, read with the `deserialize_with` helper named `helper`.
+    fn helper_field(
+        &mut self,
+        rust_type: String,
+        helper: &'static str,
+        optional: bool,
+    ) -> (String, String) {
+        if !self.helpers.contains(&helper) {
+            self.helpers.push(helper);
+        }
+        let attribute = helper_attribute(helper);
+        let helper = match &self.options.helpers_module {
+            Some(module) => format!("{}::{}", module, helper),
+            None => helper.to_string(),
+        };
+        if optional {
+            (
+                format!("Option<{}>", rust_type),
+                format!("#[serde(default, {} = \"{}\")]", attribute, helper),
+            )
+        } else {
+            (
+                rust_type,
+                format!("#[serde({} = \"{}\")]", attribute, helper),
+            )
+        }
+    }
+
+    /// The type and serde attribute of a field that was missing from some
+    /// objects of the sample and null in others, with
+    /// `ConvertOptions::set_distinguish_null`. It becomes `Option<Option<T>>`,
+    /// which is `None` when the field is missing and `Some(None)` when it is
+    /// null, and is left out when serializing `None`.
+    fn missing_or_null_field(&mut self, structure: &JsonStructure) -> Option<(String, String)> {
+        if !self.options.distinguish_null {
+            return None;
+        }
+        let stats = self.stats;
+        let stats = stats.iter().rev().find(|s| s.path == self.pointer)?;
+        if stats.present_count == stats.parent_count || stats.null_count == 0 {
+            return None;
+        }
+        let rust_type = self.type_for(structure);
+        Some((
+            format!("Option<{}>", rust_type),
+            self.double_option_attribute(),
+        ))
+    }
+
+    /// The serde attribute of `Option<Option<T>>` fields, which tell a missing
+    /// field apart from a null one.
+    fn double_option_attribute(&mut self) -> String {
+        if !self.helpers.contains(&"double_option") {
+            self.helpers.push("double_option");
+        }
+        let helper = match &self.options.helpers_module {
+            Some(module) => format!("{}::double_option", module),
+            None => "double_option".to_string(),
+        };
+        format!(
+            "#[serde(default, deserialize_with = \"{}\", skip_serializing_if = \"Option::is_none\")]",
+            helper
+        )
+    }
+
+    /// The type and serde attribute of a field of a patch type, generated from
+    /// the type of the field in the full type. Fields that can be null become
+    /// `Option<Option<T>>`, so the patch can set them to null.
+    fn patch_field(&mut self, field_type: &str) -> (String, String) {
+        if field_type.starts_with("Option<Option<") {
+            (field_type.to_string(), self.double_option_attribute())
+        } else if field_type.starts_with("Option<") {
+            (
+                format!("Option<{}>", field_type),
+                self.double_option_attribute(),
+            )
+        } else {
+            (
+                format!("Option<{}>", field_type),
+                "#[serde(default, skip_serializing_if = \"Option::is_none\")]".to_string(),
+            )
+        }
+    }
+
+    /// The type a field with the json key `key` is written with when it gets a
+    /// type alias with `ConvertOptions::add_type_alias`, such as
+    /// `Option<UserId>`, defining the alias the first time. Only strings,
+    /// numbers, bools and detected types get one, and fields with the same
+    /// `name_key` and type share it.
+    fn aliased_type(
+        &mut self,
+        key: &str,
+        name_key: &str,
+        structure: &JsonStructure,
+        field_type: &str,
+    ) -> Option<String> {
+        let matches = self
+            .options
+            .type_aliases
+            .iter()
+            .any(|pattern| glob_matches(pattern, key));
+        let target = match unwrap_optional(structure) {
+            JsonStructure::Value(value) if matches => primitive_type(value),
+            _ => return None,
+        };
+        let mut options = 0;
+        let mut inner = field_type;
+        while let Some(unwrapped) = inner
+            .strip_prefix("Option<")
+            .and_then(|t| t.strip_suffix('>'))
+        {
+            inner = unwrapped;
+            options += 1;
+        }
+        let base = type_name_with_acronyms(name_key, self.options.acronyms);
+        if inner != target || !base.starts_with(char::is_alphabetic) {
+            return None;
+        }
+        let existing = self
+            .aliases
+            .iter()
+            .find(|(b, _, t)| *b == base && *t == target)
+            .map(|(_, name, _)| name.clone());
+        let name = match existing {
+            Some(name) => name,
+            None => {
+                let name = self.unique_name(base.clone());
+                self.aliases.push((base, name.clone(), target));
+                name
+            }
+        };
+        Some(format!(
+            "{}{}{}",
+            "Option<".repeat(options),
+            name,
+            ">".repeat(options)
+        ))
+    }
+
+    /// Why the field being generated is commented out with
+    /// `ConvertOptions::set_comment_unknown_fields`, when its type couldn't be
+    /// determined as it was only null or an empty array, along with the line
+    /// of the sample it was first on. Values forced to be `serde_json::Value`
+    /// aren't.
+    fn undetermined_note(&self, structure: &JsonStructure) -> Option<String> {
+        if !self.options.comment_unknown_fields
+            || self.options.forced_value_paths.contains(&self.pointer)
+        {
+            return None;
+        }
+        let reason = match structure {
+            JsonStructure::Optional(inner) if **inner == JsonStructure::Unknown => "was only null",
+            JsonStructure::Array(_) | JsonStructure::Optional(_)
+                if innermost_element(structure) == &JsonStructure::Unknown =>
+            {
+                "was only an empty array"
+            }
+            _ => return None,
+        };
+        let key = self.path.last().map(String::as_str).unwrap_or_default();
+        let line = self
+            .stats
+            .iter()
+            .rev()
+            .find(|s| s.path == self.pointer)
+            .and_then(|s| s.span)
+            .map(|span| format!(" on line {}", span.start.line()))
+            .unwrap_or_default();
+        Some(format!(
+            "`{}`{} {} in the sample, so its type is unknown",
+            escape(key),
+            line,
+            reason
+        ))
+    }
+
+    /// Defines a name starting with `base` that isn't used by any other type.
+    fn unique_name(&mut self, base: String) -> String {
+        let mut name = base.clone();
+        let mut counter = 2;
+        while self.defined.contains(&name) || self.symbols.has_name(&name) {
+            name = format!("{}{}", base, counter);
+            counter += 1;
+        }
+        self.defined.push(name.clone());
+        name
+    }
+
+    /// Adds a `<Name>Patch` struct after the types, with the fields of the
+    /// struct `name`, for `ConvertOptions::set_patch_types`.
+    fn emit_patch(&mut self, name: &str, index: usize, fields: &str, field_types: Vec<String>) {
+        let patch_name = self.unique_name(format!("{}Patch", name));
+        let definition = &self.definitions[index];
+        let definition = Definition {
+            body: format!("pub struct {} {{\n{}}}\n", patch_name, fields),
+            pointer: definition.pointer.clone(),
+            field_pointers: Vec::new(),
+            is_struct: true,
+            direction: definition.direction,
+            children: definition.children.clone(),
+            allowed_lints: definition.allowed_lints.clone(),
+            derives_default: true,
+            serde_repr: false,
+            name: patch_name,
+            field_types,
+            notes: Vec::new(),
+        };
+        self.definitions.push(definition);
+    }
+
+    /// Adds a `<Name>Create` struct after the types, with the fields of the
+    /// struct `name` that aren't generated by the server, and a `From` impl
+    /// for it, for `ConvertOptions::mark_server_generated`. It is used as a
+    /// request body, so server types derive `Deserialize` for it.
+    fn emit_create(&mut self, name: &str, index: usize, body: &str, fields: Vec<(String, String)>) {
+        let create_name = self.unique_name(format!("{}Create", name));
+        let copied: Vec<&str> = fields.iter().map(|(field, _)| field.as_str()).collect();
+        let definition = &self.definitions[index];
+        let definition = Definition {
+            body: format!(
+                "pub struct {} {{\n{}}}\n\n{}",
+                create_name,
+                body,
+                from_impl(name, &create_name, &copied, &[])
+            ),
+            pointer: definition.pointer.clone(),
+            field_pointers: Vec::new(),
+            is_struct: true,
+            direction: Direction::Request,
+            children: definition.children.clone(),
+            allowed_lints: definition.allowed_lints.clone(),
+            derives_default: false,
+            serde_repr: false,
+            name: create_name.clone(),
+            field_types: fields.iter().map(|(_, t)| t.clone()).collect(),
+            notes: Vec::new(),
+        };
+        self.definitions.push(definition);
+        self.from_impls
+            .push((name.to_string(), create_name.clone()));
+        self.struct_fields.push((create_name, fields));
+    }
+
+    /// The `From` impls between structs sharing most of their fields, for
+    /// `ConvertOptions::set_conversions`. A struct converts into another when
+    /// every field of the other has the same name and type in it, or is an
+    /// `Option` it doesn't have, which is set to `None`, and the shared fields
+    /// are more than half of the fields of each.
+    fn conversions(&self) -> String {
+        let mut output = String::new();
+        for (source, source_fields) in &self.struct_fields {
+            for (target, target_fields) in &self.struct_fields {
+                let already_converted = self
+                    .from_impls
+                    .iter()
+                    .any(|(s, t)| s == source && t == target);
+                if source == target || already_converted {
+                    continue;
+                }
+                let mut copied = Vec::new();
+                let mut defaulted = Vec::new();
+                for (field, field_type) in target_fields {
+                    if source_fields.contains(&(field.clone(), field_type.clone())) {
+                        copied.push(field.as_str());
+                    } else if field_type.starts_with("Option<") {
+                        defaulted.push(field.as_str());
+                    } else {
+                        break;
+                    }
+                }
+                let converts = copied.len() + defaulted.len() == target_fields.len()
+                    && copied.len() * 2 > source_fields.len()
+                    && copied.len() * 2 > target_fields.len();
+                if converts {
+                    output.push('\n');
+                    output.push_str(&from_impl(source, target, &copied, &defaulted));
+                }
+            }
+        }
+        output
+    }
+
+    /// The single field of a wrapper object, such as `{"value": 1}`, that the
+    /// field holding it is unwrapped to with `ConvertOptions::unwrap_wrapper`.
+    /// Wrappers of optional and union values are kept, as are all wrappers when
+    /// deriving schemas, which would describe the unwrapped type.
+    fn wrapped_field<'s>(&self, structure: &'s JsonStructure) -> Option<&'s JsonPair> {
+        if self.options.json_schemas || self.options.openapi_schemas {
+            return None;
+        }
+        let inner = match structure {
+            JsonStructure::Object(pairs) if pairs.len() == 1 => &pairs[0],
+            _ => return None,
+        };
+        let matches = self
+            .options
+            .wrapper_keys
+            .iter()
+            .any(|pattern| glob_matches(pattern, &inner.key));
+        let unwrappable = !matches!(
+            *inner.value,
+            JsonStructure::Optional(_) | JsonStructure::Union(_)
+        ) && wrapper_module(&inner.key).is_some();
+        if matches && unwrappable {
+            Some(inner)
+        } else {
+            None
+        }
+    }
+
+    /// The type and serde attribute of a field that had the same string or
+    /// integer everywhere in the sample, with `ConvertOptions::set_constant_fields`.
+    /// Strings become an enum with the value as its only variant, and integers
+    /// are checked with a `deserialize_with` helper.
+    fn constant_field(
+        &mut self,
+        structure: &JsonStructure,
+        struct_name: &str,
+        key: &str,
+    ) -> Option<(String, Option<String>)> {
+        if !self.options.constant_fields || self.options.redact_samples {
+            return None;
+        }
+        let stats = self.stats.iter().rev().find(|s| s.path == self.pointer)?;
+        if stats.count < 2 || stats.null_count > 0 || stats.examples.len() != 1 {
+            return None;
+        }
+        let example = stats.examples[0].clone();
+        match structure {
+            JsonStructure::Value(JsonValue::Int) => {
+                let value: i64 = example.parse().ok()?;
+                if !self.helpers.contains(&"constant_int") {
+                    self.helpers.push("constant_int");
+                }
+                let helper = match &self.options.helpers_module {
+                    Some(module) => format!("{}::constant_int", module),
+                    None => "constant_int".to_string(),
+                };
+                // Negative const generic arguments need braces
+                let value = if value < 0 {
+                    format!("{{ {} }}", value)
+                } else {
+                    value.to_string()
+                };
+                Some((
+                    primitive_type(&JsonValue::Int),
+                    Some(format!(
+                        "#[serde(deserialize_with = \"{}::<_, {}>\")]",
+                        helper, value
+                    )),
+                ))
+            }
+            JsonStructure::Value(JsonValue::String) => {
+                let value = match parse_json_with(&example, Leniency::default()).ok()? {
+                    JsonNode::String(value) => value,
+                    _ => return None,
+                };
+                let variant = variant_names(&[&value], self.options.acronyms).remove(0);
+                let name = self.reserve_field_type(struct_name, key);
+                // Named so it doesn't clash with the variant of the value
+                let other = if self.options.forward_compatible {
+                    let other = if variant == "Other" {
+                        "Unrecognized"
+                    } else {
+                        "Other"
+                    };
+                    format!("    #[serde(other)]\n    {},\n", other)
+                } else {
+                    String::new()
+                };
+                self.definitions.push(Definition {
+                    body: format!(
+                        "pub enum {} {{\n    #[serde(rename = \"{}\")]\n    {},\n{}}}\n",
+                        name,
+                        escape(&value),
+                        variant,
+                        other
+                    ),
+                    pointer: self.pointer.clone(),
+                    field_pointers: Vec::new(),
+                    is_struct: false,
+                    direction: self.direction,
+                    children: Vec::new(),
+                    allowed_lints: Vec::new(),
+                    derives_default: false,
+                    serde_repr: false,
+                    name: name.clone(),
+                    field_types: Vec::new(),
+                    notes: Vec::new(),
+                });
+                Some((name, None))
+            }
+            _ => None,
+        }
+    }
+
+    /// Names the type of the field `key` of the struct `struct_name` after
+    /// both, for types generated from the values of a single field, and makes
+    /// it a child of the struct.
+    fn reserve_field_type(&mut self, struct_name: &str, key: &str) -> String {
+        let base = format!(
+            "{}{}",
+            struct_name,
+            type_name_with_acronyms(key, self.options.acronyms)
+        );
+        let mut name = base.clone();
+        let mut counter = 2;
+        while self.defined.contains(&name) || self.symbols.has_name(&name) {
+            name = format!("{}{}", base, counter);
+            counter += 1;
+        }
+        self.defined.push(name.clone());
+        if let Some(parent) = self.parent {
+            let index = self.definitions.len();
+            self.definitions[parent].children.push(index);
+        }
+        name
+    }
+
+    /// The type of an integer field that took a small fixed set of values, with
+    /// `ConvertOptions::set_int_enums`: an enum or a newtype with a variant or
+    /// constant for each value, named like enums of strings are.
+    fn int_enum_field(
+        &mut self,
+        structure: &JsonStructure,
+        struct_name: &str,
+        key: &str,
+    ) -> Option<(String, Option<String>)> {
+        let style = self.options.int_enums?;
+        if self.options.redact_samples {
+            return None;
+        }
+        // Options that tell missing fields from null ones need the field to
+        // be generated as they ask
+        let (optional, inner) = match structure {
+            JsonStructure::Optional(inner) if !self.options.distinguish_null => (true, &**inner),
+            structure => (false, structure),
+        };
+        if *inner != JsonStructure::Value(JsonValue::Int) {
+            return None;
+        }
+        let stats = self.stats.iter().rev().find(|s| s.path == self.pointer)?;
+        let values = stats.int_values.clone()?;
+        if values.len() * 2 > stats.count - stats.null_count {
+            return None;
+        }
+        let labels: Vec<String> = values
+            .iter()
+            .map(|value| match value {
+                value if *value < 0 => format!("minus_{}", value.unsigned_abs()),
+                value => value.to_string(),
+            })
+            .collect();
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        let names = variant_names(&labels, self.options.acronyms);
+        let name = self.reserve_field_type(struct_name, key);
+        let (body, field_types) = match style {
+            IntEnumStyle::Repr => {
+                let mut body = format!("#[repr(i64)]\npub enum {} {{\n", name);
+                for (variant, value) in names.iter().zip(&values) {
+                    body.push_str(&format!("    {} = {},\n", variant, value));
+                }
+                body.push_str("}\n");
+                (body, Vec::new())
+            }
+            IntEnumStyle::Newtype => {
+                let mut body = format!(
+                    "#[serde(transparent)]\npub struct {}(pub i64);\n\nimpl {} {{\n",
+                    name, name
+                );
+                for value in &values {
+                    let constant = match value {
+                        value if *value < 0 => format!("MINUS_{}", value.unsigned_abs()),
+                        value => format!("VALUE_{}", value),
+                    };
+                    body.push_str(&format!(
+                        "    pub const {}: {} = {}({});\n",
+                        constant, name, name, value
+                    ));
+                }
+                body.push_str("}\n");
+                (body, vec![primitive_type(&JsonValue::Int)])
+            }
+        };
+        self.definitions.push(Definition {
+            body,
+            pointer: self.pointer.clone(),
+            field_pointers: Vec::new(),
+            is_struct: false,
+            direction: self.direction,
+            children: Vec::new(),
+            allowed_lints: Vec::new(),
+            derives_default: false,
+            serde_repr: style == IntEnumStyle::Repr,
+            name: name.clone(),
+            field_types,
+            notes: Vec::new(),
+        });
+        match optional {
+            true => Some((format!("Option<{}>", name), None)),
+            false => Some((name, None)),
+        }
+    }
+
+    /// Reserves a slot for the definition of the type at the current path, so
+    /// parents are emitted before their children. Returns `None` if a type with
+    /// the same name has already been defined.
+    fn reserve_definition(&mut self, is_struct: bool) -> (String, Option<usize>) {
+        let name = self.symbols.name_at(&self.path).to_string();
+        let existing = self.defined.iter().position(|defined| *defined == name);
+        let index = existing.unwrap_or(self.definitions.len());
+        if let Some(parent) = self.parent {
+            self.definitions[parent].children.push(index);
+        }
+        if let Some(index) = existing {
+            let definition = &mut self.definitions[index];
+            definition.direction = definition.direction.merge(self.direction);
+            return (name, None);
+        }
+        self.defined.push(name.clone());
+        self.definitions.push(Definition {
+            body: String::new(),
+            pointer: self.pointer.clone(),
+            field_pointers: Vec::new(),
+            is_struct,
+            direction: self.direction,
+            children: Vec::new(),
+            allowed_lints: Vec::new(),
+            derives_default: false,
+            serde_repr: false,
+            name: name.clone(),
+            field_types: Vec::new(),
+            notes: Vec::new(),
+        });
+        (name, Some(index))
+    }
+
+    fn emit_struct(&mut self, pairs: &[JsonPair]) -> String {
+        let (struct_name, index) = match self.reserve_definition(true) {
+            (name, Some(index)) => (name, index),
+            (name, None) => return name,
+        };
+        let parent = self.parent.replace(index);
+        let is_root = self.path.is_empty();
+        // Reserved like the definition, so structs are listed in the same order
+        let struct_index = self.struct_fields.len();
+        self.struct_fields.push((struct_name.clone(), Vec::new()));
+
+        let mut body = format!("pub struct {} {{\n", struct_name);
+        let mut patch_body = String::new();
+        let mut patch_types = Vec::new();
+        // Fields of the `<Name>Create` type, which leaves out the fields
+        // generated by the server, and whether there are any of those
+        let mut create_body = String::new();
+        let mut create_fields = Vec::new();
+        let mut server_generated = false;
+        let mut fields = Vec::new();
+        // The type of each field, and none for fields commented out
+        let mut layout_types = Vec::new();
+        let keys: Vec<String> = pairs
+            .iter()
+            .map(|pair| expand_abbreviations(&pair.key, &self.options.abbreviations))
+            .collect();
+        let names: Vec<String> = if self.options.strip_common_prefix {
+            let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+            field_names_without_common_prefix(&keys)
+        } else {
+            keys.iter().map(|key| field_name(key)).collect()
+        };
+        let original_keys: Vec<&str> = pairs.iter().map(|pair| pair.key.as_str()).collect();
+        let names = unique_field_names(&original_keys, &names);
+        for (position, (pair, name)) in pairs.iter().zip(names).enumerate() {
+            let field = match name {
+                name if name.is_empty() => self
+                    .options
+                    .output_version
+                    .strategy()
+                    .unnamed_field(position),
+                name => name,
+            };
+            self.path.push(pair.key.clone());
+            let pointer = child_path(&self.pointer, &pair.key);
+            let parent_pointer = std::mem::replace(&mut self.pointer, pointer);
+            if let Some(note) = self.undetermined_note(&pair.value) {
+                let field_type = self.type_for(&pair.value);
+                let pointer = std::mem::replace(&mut self.pointer, parent_pointer);
+                self.path.pop();
+                self.definitions[index]
+                    .field_pointers
+                    .push((body.matches('\n').count(), pointer));
+                body.push_str(&format!("    // TODO: {}\n", note));
+                if field.trim_start_matches("r#") != pair.key {
+                    body.push_str(&format!(
+                        "    // #[serde(rename = \"{}\")]\n",
+                        escape(&pair.key)
+                    ));
+                }
+                body.push_str(&format!("    // pub {}: {},\n", field, field_type));
+                layout_types.push(None);
+                continue;
+            }
+            let wrapped = self.wrapped_field(&pair.value);
+            let coerced = match wrapped {
+                Some(_) => None,
+                None => self
+                    .json_string_field(&pair.value)
+                    .or_else(|| self.coerced_field(&pair.value)),
+            };
+            let constant = match (wrapped, &coerced) {
+                (None, None) => self
+                    .constant_field(&pair.value, &struct_name, &pair.key)
+                    .or_else(|| self.int_enum_field(&pair.value, &struct_name, &pair.key)),
+                _ => None,
+            };
+            let coerced = match (wrapped, coerced, &constant) {
+                (None, None, None) => self.missing_or_null_field(&pair.value),
+                (_, coerced, _) => coerced,
+            };
+            let field_type = match (&coerced, wrapped) {
+                (Some((rust_type, _)), _) => rust_type.clone(),
+                (None, None) if constant.is_some() => constant
+                    .as_ref()
+                    .map(|(t, _)| t.clone())
+                    .unwrap_or_default(),
+                (None, Some(inner)) => {
+                    self.path.push(inner.key.clone());
+                    let pointer = child_path(&self.pointer, &inner.key);
+                    let parent_pointer = std::mem::replace(&mut self.pointer, pointer);
+                    let inner_type = self.type_for(&inner.value);
+                    self.pointer = parent_pointer;
+                    self.path.pop();
+                    inner_type
+                }
+                (None, None) => {
+                    let field_type = self.type_for(&pair.value);
+                    self.boxed_if_large(field_type)
+                }
+            };
+            // Written with the alias, while the analyses of the fields use the
+            // type it stands for
+            let written_type = match wrapped {
+                Some(_) => None,
+                None => self.aliased_type(&pair.key, &keys[position], &pair.value, &field_type),
+            }
+            .unwrap_or_else(|| field_type.clone());
+            let pointer = std::mem::replace(&mut self.pointer, parent_pointer);
+            self.path.pop();
+            self.definitions[index]
+                .field_pointers
+                .push((body.matches('\n').count(), pointer.clone()));
+            let field_start = body.len();
+            for line in self.docs(&pointer, Some(&pair.key)) {
+                body.push_str(&format!("    {}\n", line));
+            }
+            // Constant strings and integer enums have nothing to validate
+            let is_constant_enum = matches!(&constant, Some((_, None)));
+            if self.options.validation && !is_constant_enum {
+                let rules = match wrapped {
+                    Some(inner) => {
+                        self.validations(&inner.value, &child_path(&pointer, &inner.key))
+                    }
+                    None => self.validations(&pair.value, &pointer),
+                };
+                if !rules.is_empty() {
+                    body.push_str(&format!("    #[validate({})]\n", rules.join(", ")));
+                }
+            }
+            if field.trim_start_matches("r#") != pair.key {
+                body.push_str(&format!(
+                    "    #[serde(rename = \"{}\")]\n",
+                    escape(&pair.key)
+                ));
+            }
+            // Unwrapped fields need their wrapper module, which can't read an
+            // `Option`, so patches leave them out
+            if is_root && self.options.patch_types && wrapped.is_none() {
+                if field.trim_start_matches("r#") != pair.key {
+                    patch_body.push_str(&format!(
+                        "    #[serde(rename = \"{}\")]\n",
+                        escape(&pair.key)
+                    ));
+                }
+                let (patch_type, attribute) = self.patch_field(&written_type);
+                patch_body.push_str(&format!(
+                    "    {}\n    pub {}: {},\n",
+                    attribute, field, patch_type
+                ));
+                patch_types.push(patch_type);
+            }
+            if let Some((_, attribute)) = &coerced {
+                body.push_str(&format!("    {}\n", attribute));
+            }
+            if let Some((_, Some(attribute))) = &constant {
+                body.push_str(&format!("    {}\n", attribute));
+            }
+            if let Some(inner) = wrapped {
+                body.push_str(&format!(
+                    "    #[serde(with = \"{}\")]\n",
+                    wrapper_module(&inner.key).unwrap_or_default()
+                ));
+                if !self.wrappers.contains(&inner.key) {
+                    self.wrappers.push(inner.key.clone());
+                }
+            }
+            for (path, attribute) in &self.options.field_attributes {
+                if *path == pointer {
+                    body.push_str(&format!("    {}\n", attribute));
+                }
+            }
+            body.push_str(&format!("    pub {}: {},\n", field, written_type));
+            if self.options.server_generated.contains(&pointer) {
+                server_generated = true;
+            } else {
+                create_body.push_str(&body[field_start..]);
+                create_fields.push((field.clone(), field_type.clone()));
+            }
+            if field_type.starts_with("Option<") {
+                self.optional_fields
+                    .push((pointer.clone(), pair.key.clone(), field_type.clone()));
+            }
+            layout_types.push(Some(field_type.clone()));
+            fields.push((field, field_type));
+        }
+        if self.options.reorder_fields {
+            self.reorder_fields(index, &mut body, &layout_types, &original_keys);
+        }
+        body.push_str("}\n");
+
+        self.parent = parent;
+        self.definitions[index].body = body;
+        self.definitions[index].allowed_lints = struct_lints(&struct_name, &fields);
+        self.definitions[index].field_types = fields.iter().map(|(_, t)| t.clone()).collect();
+        if is_root && self.options.patch_types {
+            self.emit_patch(&struct_name, index, &patch_body, patch_types);
+        }
+        self.struct_fields[struct_index].1 = fields;
+        if server_generated {
+            self.emit_create(&struct_name, index, &create_body, create_fields);
+        }
+        struct_name
+    }
+
+    /// Boxes the value of the optional `field_type`, for
+    /// `ConvertOptions::set_box_threshold`, when it is estimated to be at
+    /// least the threshold.
+    fn boxed_if_large(&self, field_type: String) -> String {
+        let threshold = match self.options.box_threshold {
+            Some(threshold) => threshold,
+            None => return field_type,
+        };
+        let inner = match field_type
+            .strip_prefix("Option<")
+            .and_then(|t| t.strip_suffix('>'))
+        {
+            Some(inner) if !inner.starts_with("Option<") => inner,
+            _ => return field_type,
+        };
+        match self.layout_of(inner, 0).size >= threshold {
+            true => format!("Option<Box<{}>>", inner),
+            false => field_type,
+        }
+    }
+
+    /// Declares the fields of the struct of the definition `index`, whose
+    /// `body` has fields of `field_types`, by descending alignment and size,
+    /// for `ConvertOptions::set_reorder_fields`, when that saves padding. The
+    /// fields keep their attributes and doc comments, and fields commented out
+    /// go last.
+    fn reorder_fields(
+        &mut self,
+        index: usize,
+        body: &mut String,
+        field_types: &[Option<String>],
+        keys: &[&str],
+    ) {
+        let layouts: Vec<Layout> = field_types
+            .iter()
+            .map(|field_type| match field_type {
+                Some(field_type) => self.layout_of(field_type, 0),
+                None => Layout::new(0, 1, false),
+            })
+            .collect();
+        let order = field_order(&layouts);
+        let ordered: Vec<Layout> = order.iter().map(|&i| layouts[i]).collect();
+        let saving = Layout::declared(&layouts).size - Layout::declared(&ordered).size;
+        if saving == 0 {
+            return;
+        }
+        // Each field is the lines from the one it starts on to the one the
+        // next field starts on
+        let lines: Vec<&str> = body.split_inclusive('\n').collect();
+        let field_pointers = std::mem::take(&mut self.definitions[index].field_pointers);
+        let starts: Vec<usize> = field_pointers.iter().map(|(line, _)| *line).collect();
+        let mut reordered = String::new();
+        if !self
+            .options
+            .type_attributes
+            .iter()
+            .any(|a| a.contains("repr("))
+        {
+            reordered.push_str("#[repr(C)]\n");
+        }
+        reordered.push_str(&lines[..starts[0]].concat());
+        for &i in &order {
+            let end = starts.get(i + 1).copied().unwrap_or(lines.len());
+            self.definitions[index]
+                .field_pointers
+                .push((reordered.matches('\n').count(), field_pointers[i].1.clone()));
+            reordered.push_str(&lines[starts[i]..end].concat());
+        }
+        *body = reordered;
+
+        let keys: Vec<String> = keys
+            .iter()
+            .map(|key| format!("`{}`", key.escape_debug()))
+            .collect();
+        let definition = &mut self.definitions[index];
+        definition.notes.push(format!(
+            "/// Fields are declared by alignment with `#[repr(C)]`, saving an estimated {}",
+            saving
+        ));
+        definition.notes.push(format!(
+            "/// bytes. The sample has them in the order {}.",
+            keys.join(", ")
+        ));
+        self.field_order_savings
+            .push((definition.name.clone(), saving));
+    }
+
+    /// The estimated layout of `rust_type`, with the layouts of the generated
+    /// types it holds, which are looked up at most `MAX_LAYOUT_DEPTH` deep.
+    fn layout_of(&self, rust_type: &str, depth: usize) -> Layout {
+        Layout::of(rust_type, &|name| match depth {
+            MAX_LAYOUT_DEPTH => None,
+            depth => self.generated_layout(name, depth + 1),
+        })
+    }
+
+    /// The estimated layout of the generated type or alias `name`, if there is
+    /// one.
+    fn generated_layout(&self, name: &str, depth: usize) -> Option<Layout> {
+        if let Some((_, _, rust_type)) = self.aliases.iter().find(|(_, alias, _)| alias == name) {
+            return Some(self.layout_of(rust_type, depth));
+        }
+        let definition = self.definitions.iter().find(|d| d.name == name)?;
+        if definition.serde_repr {
+            return Some(Layout::new(8, 8, false));
+        }
+        let fields: Vec<Layout> = definition
+            .field_types
+            .iter()
+            .map(|field_type| self.layout_of(field_type, depth))
+            .collect();
+        Some(match definition.is_struct {
+            true => Layout::reordered(&fields),
+            false => Layout::enumeration(&fields),
+        })
+    }
+
+    /// The estimated sizes of the generated types, largest first, and warnings
+    /// for the types much larger than they look, for `type_sizes`.
+    fn type_sizes(&self) -> (Vec<TypeSize>, Vec<Warning>) {
+        let mut sizes: Vec<TypeSize> = self
+            .definitions
+            .iter()
+            .map(|definition| TypeSize {
+                name: definition.name.clone(),
+                path: definition.pointer.clone(),
+                size: self
+                    .generated_layout(&definition.name, 0)
+                    .map_or(0, |layout| layout.size),
+            })
+            .collect();
+        sizes.sort_by_key(|size| std::cmp::Reverse(size.size));
+
+        let mut warnings = Vec::new();
+        for definition in self.definitions.iter().filter(|d| !d.is_struct) {
+            let variants: Vec<(Layout, &String)> = definition
+                .field_types
+                .iter()
+                .map(|variant| (self.layout_of(variant, 0), variant))
+                .collect();
+            let largest = variants.iter().max_by_key(|(layout, _)| layout.size);
+            let smallest = variants.iter().map(|(layout, _)| layout.size).min();
+            if let (Some((largest, variant)), Some(smallest)) = (largest, smallest) {
+                if largest.size - smallest >= LARGE_VARIANT_DIFFERENCE {
+                    warnings.push(Warning::new(
+                        definition.pointer.clone(),
+                        format!(
+                            "every `{}` takes an estimated {} bytes, the size of its \
+                             variant holding `{}`; box that variant's value, or generate it \
+                             as `serde_json::Value` with `inference.force_value = [\"{}\"]`",
+                            definition.name,
+                            self.layout_of(&definition.name, 0).size,
+                            variant,
+                            definition.pointer.escape_debug()
+                        ),
+                    ));
+                }
+            }
+        }
+        for (pointer, key, field_type) in &self.optional_fields {
+            let size = self.layout_of(field_type, 0).size;
+            if size >= LARGE_OPTION_SIZE {
+                warnings.push(Warning::new(
+                    pointer.clone(),
+                    format!(
+                        "`{}` is a `{}` of an estimated {} bytes, which it takes even when \
+                         it is `None`; box it with `codegen.box_threshold = {}`, or generate \
+                         it as `serde_json::Value` with `inference.force_value = [\"{}\"]`",
+                        key.escape_debug(),
+                        field_type,
+                        size,
+                        LARGE_OPTION_SIZE,
+                        pointer.escape_debug()
+                    ),
+                ));
+            }
+        }
+        (sizes, warnings)
+    }
+
+    fn emit_enum(&mut self, variants: &[JsonStructure]) -> String {
+        let (enum_name, index) = match self.reserve_definition(false) {
+            (name, Some(index)) => (name, index),
+            (name, None) => return name,
+        };
+        let parent = self.parent.replace(index);
+
+        let mut body = format!("#[serde(untagged)]\npub enum {} {{\n", enum_name);
+        let variant_names = union_variant_names(variants);
+        let mut variant_types = Vec::new();
+        for (variant, variant_name) in variants.iter().zip(&variant_names) {
+            self.path.push(variant_name.clone());
+            let variant_type = self.type_for(variant);
+            self.path.pop();
+            body.push_str(&format!("    {}({}),\n", variant_name, variant_type));
+            variant_types.push(variant_type);
+        }
+        // Untagged variants are tried in order, so the catch-all comes last
+        if self.options.forward_compatible && !variants.contains(&JsonStructure::Unknown) {
+            body.push_str("    Other(serde_json::Value),\n");
+            variant_types.push("serde_json::Value".to_string());
+        }
+        body.push_str("}\n");
+        // Every variant needs the value, so not with the catch-all
+        if !self.options.forward_compatible {
+            if let Some(accessor) =
+                self.value_or_object_accessor(&enum_name, variants, &variant_names, &variant_types)
+            {
+                body.push('\n');
+                body.push_str(&accessor);
+            }
+        }
+
+        self.parent = parent;
+        self.definitions[index].body = body;
+        self.definitions[index].field_types = variant_types;
+        enum_name
+    }
+
+    /// The accessor of a union of a value and an object holding the same value
+    /// in one of its fields, such as `"author": "ada"` and
+    /// `"author": {"name": "ada"}`, returning the value either way. The field
+    /// is the only one of the same type, or else the first of
+    /// `IDENTIFYING_KEYS`.
+    fn value_or_object_accessor(
+        &self,
+        enum_name: &str,
+        variants: &[JsonStructure],
+        variant_names: &[String],
+        variant_types: &[String],
+    ) -> Option<String> {
+        let (value, object) = match variants {
+            [JsonStructure::Value(_), JsonStructure::Object(_)] => (0, 1),
+            [JsonStructure::Object(_), JsonStructure::Value(_)] => (1, 0),
+            _ => return None,
+        };
+        let pairs = match &variants[object] {
+            JsonStructure::Object(pairs) => pairs,
+            _ => return None,
+        };
+        let value_type = &variant_types[value];
+        let fields = &self
+            .struct_fields
+            .iter()
+            .find(|(name, _)| *name == variant_types[object])?
+            .1;
+        // Fields are found by their name, so fields renamed to tell them apart
+        // from others are left out
+        let candidates: Vec<(&str, &str)> = pairs
+            .iter()
+            .filter(|pair| *pair.value() == variants[value])
+            .filter_map(|pair| {
+                let name = field_name(pair.key());
+                fields
+                    .iter()
+                    .find(|(field, field_type)| *field == name && field_type == value_type)
+                    .map(|(field, _)| (pair.key(), field.as_str()))
+            })
+            .collect();
+        let field = match candidates.as_slice() {
+            [(_, field)] => *field,
+            _ => IDENTIFYING_KEYS.iter().find_map(|key| {
+                candidates
+                    .iter()
+                    .find(|(candidate, _)| candidate.eq_ignore_ascii_case(key))
+                    .map(|(_, field)| *field)
+            })?,
+        };
+
+        let (return_type, from_value, from_object) = match value_type.as_str() {
+            "String" => ("&str".to_string(), "value", format!("&value.{}", field)),
+            "i64" | "u64" | "i128" | "f64" | "bool" => {
+                (value_type.clone(), "*value", format!("value.{}", field))
+            }
+            _ => (
+                format!("&{}", value_type),
+                "value",
+                format!("&value.{}", field),
+            ),
+        };
+        Some(format!(
+            "impl {enum_name} {{\n    \
+             pub fn {field}(&self) -> {return_type} {{\n        \
+             match self {{\n            \
+             {enum_name}::{value_variant}(value) => {from_value},\n            \
+             {enum_name}::{object_variant}(value) => {from_object},\n        \
+             }}\n    \
+             }}\n\
+             }}\n",
+            enum_name = enum_name,
+            field = field,
+            return_type = return_type,
+            value_variant = variant_names[value],
+            from_value = from_value,
+            object_variant = variant_names[object],
+            from_object = from_object,
+        ))
+    }
+
+    /// Generates a union hinted with `ConvertOptions::tag_union`, inferred as
+    /// objects with a single field named after the variant, or only one such
+    /// object if only one variant was seen.
+    fn emit_tagged_enum(&mut self, structure: &JsonStructure, tagging: &UnionTagging) -> String {
+        // A single variant is in the symbol table like any object, where the
+        // variants of a union are first keyed by their name
+        let (variants, segments): (Vec<&JsonStructure>, Vec<Option<String>>) = match structure {
+            JsonStructure::Union(variants) => (
+                variants.iter().collect(),
+                union_variant_names(variants)
+                    .into_iter()
+                    .map(Some)
+                    .collect(),
+            ),
+            _ => (vec![structure], vec![None]),
+        };
+        let pairs: Vec<&JsonPair> = variants
+            .iter()
+            .filter_map(|variant| match variant {
+                JsonStructure::Object(pairs) if pairs.len() == 1 => Some(&pairs[0]),
+                _ => None,
+            })
+            .collect();
+        if pairs.len() != variants.len() {
+            // Not inferred as a tagged union, as a value didn't match the hint
+            return match structure {
+                JsonStructure::Object(pairs) => self.emit_struct(pairs),
+                JsonStructure::Union(variants) => self.emit_enum(variants),
+                _ => unreachable!("only objects and unions are tagged"),
+            };
+        }
+
+        let (enum_name, index) = match self.reserve_definition(false) {
+            (name, Some(index)) => (name, index),
+            (name, None) => return name,
+        };
+        let parent = self.parent.replace(index);
+
+        let mut body = match tagging {
+            UnionTagging::Internal { tag } => format!("#[serde(tag = \"{}\")]\n", escape(tag)),
+            UnionTagging::Adjacent { tag, content } => format!(
+                "#[serde(tag = \"{}\", content = \"{}\")]\n",
+                escape(tag),
+                escape(content)
+            ),
+            UnionTagging::External => String::new(),
+        };
+        body.push_str(&format!("pub enum {} {{\n", enum_name));
+        let tags: Vec<&str> = pairs.iter().map(|pair| pair.key()).collect();
+        let names = variant_names(&tags, self.options.acronyms);
+        let mut variant_types = Vec::new();
+        for ((pair, segment), name) in pairs.iter().zip(segments).zip(names) {
+            let pointer = match tagging {
+                UnionTagging::Internal { .. } => self.pointer.clone(),
+                UnionTagging::Adjacent { content, .. } => child_path(&self.pointer, content),
+                UnionTagging::External => child_path(&self.pointer, pair.key()),
+            };
+            let union_pointer = std::mem::replace(&mut self.pointer, pointer);
+            let depth = self.path.len();
+            self.path.extend(segment);
+            self.path.push(pair.key.clone());
+            // The fields of internally tagged variants are at the path of the
+            // union, so they aren't looked at for the hint again
+            let variant_type = match (tagging, pair.value()) {
+                (UnionTagging::Internal { .. }, JsonStructure::Object(fields)) => {
+                    self.emit_struct(fields)
+                }
+                (_, value) => self.type_for(value),
+            };
+            self.path.truncate(depth);
+            self.pointer = union_pointer;
+            if name != pair.key() {
+                body.push_str(&format!(
+                    "    #[serde(rename = \"{}\")]\n",
+                    escape(pair.key())
+                ));
+            }
+            body.push_str(&format!("    {}({}),\n", name, variant_type));
+            variant_types.push(variant_type);
+        }
+        // Only internally and adjacently tagged enums can catch unknown
+        // variants, with a unit variant
+        if self.options.forward_compatible && *tagging != UnionTagging::External {
+            body.push_str("    #[serde(other)]\n    Other,\n");
+        }
+        body.push_str("}\n");
+
+        self.parent = parent;
+        self.definitions[index].body = body;
+        self.definitions[index].field_types = variant_types;
+        enum_name
+    }
+
+    /// Types used by both a request and a response type are sent both ways, so
+    /// directions are passed on from every type to the types it uses.
+    fn propagate_directions(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in 0..self.definitions.len() {
+                let direction = self.definitions[index].direction;
+                for child in self.definitions[index].children.clone() {
+                    let merged = self.definitions[child].direction.merge(direction);
+                    if merged != self.definitions[child].direction {
+                        self.definitions[child].direction = merged;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The serde traits derived for a definition. Outside of server types every
+    /// type derives both.
+    fn serde_derives(&self, direction: Direction) -> &'static [&'static str] {
+        match direction {
+            Direction::Request if self.options.server_types => &["Deserialize"],
+            Direction::Response if self.options.server_types => &["Serialize"],
+            _ => &["Serialize", "Deserialize"],
+        }
+    }
+
+    /// Which definitions can derive `Eq` and `Ord`, as none of their fields
+    /// or variants are or hold floats, maps, json values or custom types.
+    /// Types using each other are only orderable if all of them are.
+    fn orderable(&self) -> Vec<bool> {
+        let mut orderable = vec![true; self.definitions.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in 0..self.definitions.len() {
+                let supported = self.definitions[index]
+                    .field_types
+                    .iter()
+                    .flat_map(|t| t.split(|c: char| !c.is_alphanumeric() && c != '_' && c != ':'))
+                    .filter(|word| {
+                        !word.is_empty() && !word.starts_with(|c: char| c.is_ascii_digit())
+                    })
+                    .all(|word| {
+                        ORDERABLE_TYPES.contains(&word)
+                            || self
+                                .definitions
+                                .iter()
+                                .position(|d| d.name == word)
+                                .is_some_and(|i| orderable[i])
+                    });
+                if orderable[index] && !supported {
+                    orderable[index] = false;
+                    changed = true;
+                }
+            }
+        }
+        orderable
+    }
+
+    fn finish(self) -> String {
+        self.finish_with_source_map().0
+    }
+
+    /// The lines of the doc comment of the type or field generated for the
+    /// value at `pointer`: its description, the key of fields whose name had
+    /// to be transliterated from it, and its sample location.
+    fn docs(&self, pointer: &str, key: Option<&str>) -> Vec<String> {
+        let description = self
+            .stats
+            .iter()
+            .rev()
+            .find(|s| s.path == pointer)
+            .and_then(|s| s.description.as_deref());
+        let mut lines: Vec<String> = description
+            .into_iter()
+            .flat_map(str::lines)
+            .map(|line| format!("/// {}", line).trim_end().to_string())
+            .collect();
+        let original_key = key
+            .filter(|key| !transliterate_with_mapping(key).1.is_empty())
+            .map(|key| format!("/// `{}` in the sample", key.escape_debug()));
+        let notes: Vec<String> = original_key
+            .into_iter()
+            .chain(self.sample_location(pointer))
+            .collect();
+        if !lines.is_empty() && !notes.is_empty() {
+            lines.push("///".to_string());
+        }
+        lines.extend(notes);
+        lines
+    }
+
+    /// Doc comment with the json pointer and the line of the sample the first
+    /// value at `pointer` was on, when documenting sample locations.
+    fn sample_location(&self, pointer: &str) -> Option<String> {
+        if !self.options.sample_locations {
+            return None;
+        }
+        let span = self
+            .stats
+            .iter()
+            .rev()
+            .find(|s| s.path == pointer)
+            .and_then(|s| s.span)?;
+        Some(match pointer {
+            "" => format!("/// Line {} of the sample", span.start.line()),
+            pointer => format!(
+                "/// `{}`, line {} of the sample",
+                pointer,
+                span.start.line()
+            ),
+        })
+    }
+
+    /// Assembles the generated code, along with a source map of the json
+    /// pointers each line was generated for.
+    fn finish_with_source_map(mut self) -> (String, SourceMap) {
+        self.propagate_directions();
+        // Lines of the definitions, which are offset by the imports at the end
+        let mut source_map = Vec::new();
+        let mut uses_serialize = !self.options.server_types;
+        let mut uses_deserialize = !self.options.server_types;
+        let mut definitions = String::new();
+        let orderable = self.orderable();
+        let mut serde_repr_derives: Vec<&str> = Vec::new();
+        for (definition, orderable) in self.definitions.iter().zip(orderable) {
+            let serde_derives = self.serde_derives(definition.direction);
+            if !definition.serde_repr {
+                uses_serialize |= serde_derives.contains(&"Serialize");
+                uses_deserialize |= serde_derives.contains(&"Deserialize");
+            }
+            let mut derives = self
+                .options
+                .output_version
+                .strategy()
+                .base_derives()
+                .to_vec();
+            if definition.serde_repr {
+                derives.push("Copy");
+            }
+            if self.options.ordering_derives && orderable {
+                derives.extend(["Eq", "PartialOrd", "Ord"]);
+            }
+            if definition.derives_default {
+                derives.push("Default");
+            }
+            if definition.serde_repr {
+                for derive in serde_derives {
+                    let derive = match *derive {
+                        "Serialize" => "Serialize_repr",
+                        _ => "Deserialize_repr",
+                    };
+                    derives.push(derive);
+                    if !serde_repr_derives.contains(&derive) {
+                        serde_repr_derives.push(derive);
+                    }
+                }
+            } else {
+                derives.extend(serde_derives);
+            }
+            if self.options.validation && definition.is_struct {
+                derives.push("Validate");
+            }
+            if self.options.json_schemas {
+                derives.push("JsonSchema");
+            }
+            if self.options.openapi_schemas {
+                derives.push("ToSchema");
+            }
+            let is_record = definition.is_struct
+                && self.record.as_ref() == Some(&definition.name)
+                && is_flat(definition);
+            if is_record && self.options.sqlx_derives {
+                derives.push("sqlx::FromRow");
+            }
+            let diesel_table = self.options.diesel_table.as_ref().filter(|_| is_record);
+            if diesel_table.is_some() {
+                derives.extend(["diesel::Queryable", "diesel::Selectable"]);
+            }
+            definitions.push('\n');
+            source_map.push((
+                definitions.matches('\n').count(),
+                definition.pointer.clone(),
+            ));
+            let docs = self.docs(&definition.pointer, None);
+            for line in &docs {
+                definitions.push_str(&format!("{}\n", line));
+            }
+            if !docs.is_empty() && !definition.notes.is_empty() {
+                definitions.push_str("///\n");
+            }
+            for line in &definition.notes {
+                definitions.push_str(&format!("{}\n", line));
+            }
+            definitions.push_str(&format!("#[derive({})]\n", derives.join(", ")));
+            if let Some(table) = diesel_table {
+                definitions.push_str(&format!("#[diesel(table_name = {})]\n", table));
+            }
+            if self.options.forward_compatible && definition.is_struct {
+                definitions.push_str("#[non_exhaustive]\n");
+            }
+            // Request bodies with fields the handler doesn't know about are
+            // rejected, rather than silently ignored
+            if self.options.server_types
+                && definition.is_struct
+                && definition.direction == Direction::Request
+            {
+                definitions.push_str("#[serde(deny_unknown_fields)]\n");
+            }
+            for attribute in &self.options.type_attributes {
+                definitions.push_str(&format!("{}\n", attribute));
+            }
+            if !definition.allowed_lints.is_empty() {
+                definitions.push_str(&format!(
+                    "#[allow({})]\n",
+                    definition.allowed_lints.join(", ")
+                ));
+            }
+            let body_line = definitions.matches('\n').count();
+            source_map.extend(
+                definition
+                    .field_pointers
+                    .iter()
+                    .map(|(line, pointer)| (body_line + line, pointer.clone())),
+            );
+            definitions.push_str(&definition.body);
+        }
+
+        let mut output = String::new();
+        // Roots that only get a type alias, or nothing, derive nothing
+        let derives = !self.definitions.is_empty();
+        match (uses_serialize, uses_deserialize) {
+            _ if !derives => {}
+            (true, false) => output.push_str("use serde::Serialize;\n"),
+            (false, true) => output.push_str("use serde::Deserialize;\n"),
+            _ => output.push_str("use serde::{Deserialize, Serialize};\n"),
+        }
+        match (self.uses_hash_map, self.set_type) {
+            (true, Some(set_type)) => {
+                output.push_str(&format!(
+                    "use std::collections::{{{}, HashMap}};\n",
+                    set_type
+                ));
+            }
+            (true, None) => output.push_str("use std::collections::HashMap;\n"),
+            (false, Some(set_type)) => {
+                output.push_str(&format!("use std::collections::{};\n", set_type));
+            }
+            (false, None) => {}
+        }
+        if !serde_repr_derives.is_empty() {
+            serde_repr_derives.sort_unstable();
+            output.push_str(&match serde_repr_derives.as_slice() {
+                [derive] => format!("use serde_repr::{};\n", derive),
+                derives => format!("use serde_repr::{{{}}};\n", derives.join(", ")),
+            });
+        }
+        if self.options.json_schemas && derives {
+            output.push_str("use schemars::JsonSchema;\n");
+        }
+        if self.options.openapi_schemas && derives {
+            output.push_str("use utoipa::ToSchema;\n");
+        }
+        if self.options.validation && self.definitions.iter().any(|d| d.is_struct) {
+            output.push_str("use validator::Validate;\n");
+        }
+        let imports = output.matches('\n').count();
+        let source_map = source_map
+            .into_iter()
+            .map(|(line, pointer)| (imports + line + 1, pointer))
+            .collect();
+        output.push_str(&definitions);
+        if !self.aliases.is_empty() {
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            for (_, name, target) in &self.aliases {
+                output.push_str(&format!("pub type {} = {};\n", name, target));
+            }
+        }
+        if self.options.conversions {
+            output.push_str(&self.conversions());
+        }
+        if self.options.display_impls {
+            for (name, fields) in &self.struct_fields {
+                output.push_str(&display_impl(name, fields));
+            }
+        }
+        if let Some((rows, columns)) = &self.columns {
+            if let Some((_, fields)) = self.struct_fields.iter().find(|(name, _)| name == rows) {
+                let derives = self.options.output_version.strategy().base_derives();
+                output.push_str(&columns_code(rows, columns, fields, derives));
+            }
+        }
+        if self.options.helpers_module.is_none() {
+            output.push_str(&helpers_code(&self.helpers, false));
+        }
+        for key in &self.wrappers {
+            output.push_str(&wrapper_code(key, self.options.server_types));
+        }
+        if !style::is_restyled(self.options) {
+            return (output, source_map);
+        }
+        let (output, line_map) = style::restyle(&output, self.options);
+        let source_map = source_map
+            .into_iter()
+            .map(|(line, pointer)| (line_map[line - 1] + 1, pointer))
+            .collect();
+        (output, source_map)
+    }
+}
+
+/// The name of the module reading and writing values wrapped in an object
+/// with the single field `key`, if the key makes a valid name.
+fn wrapper_module(key: &str) -> Option<String> {
+    let name = to_snake_case(key);
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Some(format!("{}_wrapper", name))
+    } else {
+        None
+    }
+}
+
+/// The `#[serde(with = ...)]` module for fields unwrapped from objects with
+/// the single field `key`. Server types only use one of its functions.
+fn wrapper_code(key: &str, server_types: bool) -> String {
+    format!(
+        "
+/// Reads and writes values wrapped in an object with a single `{key}` field.
+{allow}mod {module} {{
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Wrapper<T> {{
+        #[serde(rename = \"{escaped}\")]
+        value: T,
+    }}
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: serde::Serialize,
+        S: serde::Serializer,
+    {{
+        serde::Serialize::serialize(&Wrapper {{ value }}, serializer)
+    }}
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: serde::Deserialize<'de>,
+        D: serde::Deserializer<'de>,
+    {{
+        let wrapper: Wrapper<T> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(wrapper.value)
+    }}
+}}
+",
+        key = key,
+        allow = if server_types {
+            "#[allow(dead_code)]\n"
+        } else {
+            ""
+        },
+        module = wrapper_module(key).unwrap_or_default(),
+        escaped = escape(key)
+    )
+}
+
+/// The clippy lints a struct with the given fields and their types triggers,
+/// which can't be fixed without renaming the json.
+/// Names of fields that identify a value in a log line, in order of preference
+/// after `id`.
+const NAME_LIKE_FIELDS: &[&str] = &[
+    "name", "title", "username", "email", "slug", "key", "code", "label",
+];
+
+/// The most fields shown by the `Display` impl of a struct.
+const MAX_DISPLAY_FIELDS: usize = 3;
+
+/// A compact `impl Display` for the struct `name`, for
+/// `ConvertOptions::set_display_impls`, such as `User(id=1, name="Al")`. It
+/// shows the id and name-like fields of the struct, or its first fields if it
+/// has none, leaving out fields that aren't a string, number or bool. Empty if
+/// the struct has no such fields.
+fn display_impl(name: &str, fields: &[(String, String)]) -> String {
+    let scalar = |field_type: &str| {
+        let inner = field_type
+            .strip_prefix("Option<")
+            .and_then(|t| t.strip_suffix('>'))
+            .unwrap_or(field_type);
+        ["String", "i64", "u64", "i128", "f64", "bool"].contains(&inner)
+    };
+    let shown: Vec<&(String, String)> = fields.iter().filter(|(_, t)| scalar(t)).collect();
+    let key_like = |field: &str| {
+        let field = field.trim_start_matches("r#");
+        field == "id" || field.ends_with("_id") || NAME_LIKE_FIELDS.contains(&field)
+    };
+    let mut chosen: Vec<&(String, String)> = shown
+        .iter()
+        .filter(|(field, _)| key_like(field))
+        .cloned()
+        .collect();
+    if chosen.is_empty() {
+        chosen = shown;
+    }
+    chosen.sort_by_key(|(field, _)| field != "id");
+    chosen.truncate(MAX_DISPLAY_FIELDS);
+    if chosen.is_empty() {
+        return String::new();
+    }
+    let placeholders: Vec<String> = chosen
+        .iter()
+        .map(|(field, field_type)| {
+            let format = if field_type == "String" || field_type.starts_with("Option<") {
+                "{:?}"
+            } else {
+                "{}"
+            };
+            format!("{}={}", field.trim_start_matches("r#"), format)
+        })
+        .collect();
+    let arguments: String = chosen
+        .iter()
+        .map(|(field, _)| format!(", self.{}", field))
+        .collect();
+    format!(
+        "\nimpl std::fmt::Display for {name} {{\n    \
+         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{\n        \
+         write!(f, \"{name}({placeholders})\"{arguments})\n    }}\n}}\n",
+        name = name,
+        placeholders = placeholders.join(", "),
+        arguments = arguments
+    )
+}
+
+/// Whether the fields of a struct are all scalars, or `Option`s and `Vec`s of
+/// them, which database columns can hold.
+fn is_flat(definition: &Definition) -> bool {
+    definition.children.is_empty()
+        && !definition.field_types.iter().any(|field_type| {
+            ["serde_json::Value", "HashMap", "Set<", "Vec<Vec<", "["]
+                .iter()
+                .any(|nested| field_type.contains(nested))
+        })
+}
+
+/// A struct `columns` with a `Vec` for each of the `fields` of the struct
+/// `rows`, along with the impls collecting rows into it.
+fn columns_code(
+    rows: &str,
+    columns: &str,
+    fields: &[(String, String)],
+    derives: &[&str],
+) -> String {
+    let mut definition = String::new();
+    let mut pushes = String::new();
+    for (field, field_type) in fields {
+        definition.push_str(&format!("    pub {}: Vec<{}>,\n", field, field_type));
+        pushes.push_str(&format!(
+            "            columns.{field}.push(row.{field});\n",
+            field = field
+        ));
+    }
+    format!(
+        "\n/// The fields of `{rows}`s, as a column each.\n\
+         #[derive({derives}, Default)]\n\
+         pub struct {columns} {{\n{definition}}}\n\n\
+         impl std::iter::FromIterator<{rows}> for {columns} {{\n    \
+         fn from_iter<I: IntoIterator<Item = {rows}>>(rows: I) -> {columns} {{\n        \
+         let mut columns = {columns}::default();\n        \
+         for row in rows {{\n{pushes}        }}\n        \
+         columns\n    }}\n}}\n\n\
+         impl From<Vec<{rows}>> for {columns} {{\n    \
+         fn from(rows: Vec<{rows}>) -> {columns} {{\n        \
+         rows.into_iter().collect()\n    }}\n}}\n",
+        rows = rows,
+        columns = columns,
+        derives = derives.join(", "),
+        definition = definition,
+        pushes = pushes
+    )
+}
+
+/// `impl From<source> for target`, copying the `copied` fields and setting the
+/// `defaulted` ones to `None`.
+fn from_impl(source: &str, target: &str, copied: &[&str], defaulted: &[&str]) -> String {
+    let mut fields = String::new();
+    for field in copied {
+        fields.push_str(&format!("            {}: value.{},\n", field, field));
+    }
+    for field in defaulted {
+        fields.push_str(&format!("            {}: None,\n", field));
+    }
+    format!(
+        "impl From<{source}> for {target} {{\n    \
+         fn from(value: {source}) -> {target} {{\n        \
+         {target} {{\n{fields}        }}\n    }}\n}}\n",
+        source = source,
+        target = target,
+        fields = fields
+    )
+}
+
+fn struct_lints(struct_name: &str, fields: &[(String, String)]) -> Vec<&'static str> {
+    let mut lints = Vec::new();
+    if fields.iter().filter(|(_, t)| t == "bool").count() > 3 {
+        lints.push("clippy::struct_excessive_bools");
+    }
+    let names: Vec<&str> = fields
+        .iter()
+        .map(|(name, _)| name.trim_start_matches("r#"))
+        .collect();
+    let struct_name = to_snake_case(struct_name);
+    let repeats_struct_name = names.iter().any(|name| {
+        *name == struct_name
+            || name.starts_with(&format!("{}_", struct_name))
+            || name.ends_with(&format!("_{}", struct_name))
+    });
+    // Clippy also flags three or more fields that all start or end with the
+    // same word
+    let shares_word = |word: fn(&str) -> Option<&str>| {
+        names.len() >= 3
+            && names.iter().all(|name| name.contains('_'))
+            && names.iter().all(|name| word(name) == word(names[0]))
+    };
+    if repeats_struct_name
+        || shares_word(|name| name.split('_').next())
+        || shares_word(|name| name.rsplit('_').next())
+    {
+        lints.push("clippy::struct_field_names");
+    }
+    lints
+}
+
+pub(crate) fn primitive_type(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String => "String".to_string(),
+        JsonValue::Float => "f64".to_string(),
+        JsonValue::Int => "i64".to_string(),
+        JsonValue::Bool => "bool".to_string(),
+        JsonValue::Custom(rust_type) => rust_type.path().to_string(),
+    }
+}
+
+pub fn variant_name(structure: &JsonStructure) -> String {
+    match structure {
+        JsonStructure::Array(_) => "Array".to_string(),
+        JsonStructure::Dictionary(_) | JsonStructure::Object(_) => "Object".to_string(),
+        JsonStructure::Value(JsonValue::String) => "String".to_string(),
+        JsonStructure::Value(JsonValue::Float) => "Float".to_string(),
+        JsonStructure::Value(JsonValue::Int) => "Integer".to_string(),
+        JsonStructure::Value(JsonValue::Bool) => "Bool".to_string(),
+        JsonStructure::Value(JsonValue::Custom(rust_type)) => {
+            type_name(rust_type.path().rsplit("::").next().unwrap_or("Custom"))
+        }
+        JsonStructure::Optional(inner) => variant_name(inner),
+        JsonStructure::Union(_) => "Union".to_string(),
+        JsonStructure::Unknown => "Unknown".to_string(),
+    }
+}
+
+/// The names of the variants of a union, which are also the keys of their
+/// types in the symbol table. Unions of several objects with a single field
+/// are tagged unions, with `ConvertOptions::tag_union`, whose variants are
+/// named after that field.
+pub fn union_variant_names(variants: &[JsonStructure]) -> Vec<String> {
+    let tags: Option<Vec<&str>> = variants
+        .iter()
+        .map(|variant| match variant {
+            JsonStructure::Object(pairs) if pairs.len() == 1 => Some(pairs[0].key()),
+            _ => None,
+        })
+        .collect();
+    match tags {
+        Some(tags) if tags.len() > 1 => variant_names(&tags, AcronymStyle::Capitalized),
+        _ => variants.iter().map(variant_name).collect(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.escape_debug().to_string()
+}
+
+/// Generates rust type definitions for `structure`, naming the root type
+/// `root_name`.
+pub fn generate_rust(structure: &JsonStructure, root_name: &str) -> String {
+    generate_rust_with_options(structure, root_name, &ConvertOptions::default())
+}
+
+fn unwrap_optional(structure: &JsonStructure) -> &JsonStructure {
+    match structure {
+        JsonStructure::Optional(inner) => unwrap_optional(inner),
+        other => other,
+    }
+}
+
+/// The elements of the innermost of nested arrays, such as `T` of
+/// `Vec<Vec<Option<T>>>`, given the element of the outermost.
+fn innermost_element(structure: &JsonStructure) -> &JsonStructure {
+    match unwrap_optional(structure) {
+        JsonStructure::Array(inner) => innermost_element(inner),
+        other => other,
+    }
+}
+
+/// Arrays at the root produce a type for their elements, rather than a type for
+/// the array itself.
+fn unwrap_root(structure: &JsonStructure) -> &JsonStructure {
+    let mut root = structure;
+    while let JsonStructure::Array(inner) = root {
+        root = inner;
+    }
+    root
+}
+
+pub fn generate_rust_with_options(
+    structure: &JsonStructure,
+    root_name: &str,
+    options: &ConvertOptions,
+) -> String {
+    generate_with_stats(structure, &[], root_name, options).code
+}
+
+/// Generates rust type definitions for a converted sample. Unlike
+/// `generate_rust_with_options`, this can use the stats gathered from the
+/// sample, for the validation rules added by `ConvertOptions::set_validation`.
+pub fn conversion_to_rust(
+    conversion: &Conversion,
+    root_name: &str,
+    options: &ConvertOptions,
+) -> String {
+    conversion_to_rust_with_source_map(conversion, root_name, options).0
+}
+
+pub(crate) fn conversion_to_rust_with_source_map(
+    conversion: &Conversion,
+    root_name: &str,
+    options: &ConvertOptions,
+) -> (String, SourceMap) {
+    let generated =
+        generate_with_stats(&conversion.structure, &conversion.stats, root_name, options);
+    (generated.code, generated.source_map)
+}
+
+/// The structs of the types generated for `conversion` whose fields
+/// `ConvertOptions::set_reorder_fields` declares in another order than the
+/// sample's, along with the bytes each is estimated to save, on 64-bit
+/// targets.
+pub fn field_order_savings(
+    conversion: &Conversion,
+    root_name: &str,
+    options: &ConvertOptions,
+) -> Vec<(String, usize)> {
+    if !options.reorder_fields {
+        return Vec::new();
+    }
+    generate_with_stats(&conversion.structure, &conversion.stats, root_name, options)
+        .field_order_savings
+}
+
+/// The estimated size of a generated type, on 64-bit targets.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TypeSize {
+    pub name: String,
+    /// Json pointer to the value the type was generated for
+    pub path: String,
+    /// The estimated `size_of` the type, in bytes
+    pub size: usize,
+}
+
+/// Estimates `size_of` each type generated for `conversion`, on 64-bit
+/// targets, largest first, along with warnings for the types that are much
+/// larger than they look: enums with a variant much larger than the others,
+/// such as the unions of heterogeneous arrays, and optional fields of large
+/// types, which take their space even when they are `None`. The warnings say
+/// how to box them or generate them as `serde_json::Value` instead.
+pub fn type_sizes(
+    conversion: &Conversion,
+    root_name: &str,
+    options: &ConvertOptions,
+) -> (Vec<TypeSize>, Vec<Warning>) {
+    generate_with_stats(&conversion.structure, &conversion.stats, root_name, options).type_sizes
+}
+
+/// The code generated for a structure, and what was found generating it.
+struct Generated {
+    code: String,
+    source_map: SourceMap,
+    field_order_savings: Vec<(String, usize)>,
+    type_sizes: (Vec<TypeSize>, Vec<Warning>),
+}
+
+fn generate_with_stats(
+    structure: &JsonStructure,
+    stats: &[PathStats],
+    root_name: &str,
+    options: &ConvertOptions,
+) -> Generated {
+    let root = unwrap_root(structure);
+    let element_name = array_element_name(structure, root_name, options);
+    let symbols = SymbolTable::build(
+        root,
+        element_name.as_deref().unwrap_or(root_name),
+        options.naming_strategy.as_ref(),
+        &options.abbreviations,
+        options.acronyms,
+    );
+    let mut generator = CodeGenerator::new(&symbols, options, Direction::Both);
+    generator.stats = stats;
+    let mut value = structure;
+    while let JsonStructure::Array(inner) = value {
+        generator.pointer = element_path(&generator.pointer);
+        value = inner;
+    }
+    let root_type = generator.type_for(root);
+    if let JsonStructure::Object(_) = root {
+        generator.record = Some(root_type.clone());
+    }
+    if let (true, JsonStructure::Array(inner)) = (options.columnar, structure) {
+        if let JsonStructure::Object(_) = inner.as_ref() {
+            let columns = generator.unique_name(format!("{}Columns", root_type));
+            generator.columns = Some((root_type.clone(), columns));
+        }
+    }
+    let alias_root = match element_name {
+        Some(_) => options.array_root == ArrayRoot::Alias,
+        None => {
+            let array_alias = options.array_root == ArrayRoot::Alias
+                && matches!(structure, JsonStructure::Array(_));
+            (options.root_alias || array_alias)
+                && !generator.defined.iter().any(|name| name == root_name)
+        }
+    };
+    if alias_root {
+        let mut root_type = root_type;
+        let mut value = structure;
+        while let JsonStructure::Array(inner) = value {
+            root_type = format!("Vec<{}>", root_type);
+            value = inner;
+        }
+        generator
+            .aliases
+            .insert(0, (String::new(), root_name.to_string(), root_type));
+    }
+    let field_order_savings = std::mem::take(&mut generator.field_order_savings);
+    let type_sizes = generator.type_sizes();
+    let (mut code, source_map) = generator.finish_with_source_map();
+    if options.schema_fingerprint {
+        let fingerprint = structure.fingerprint();
+        // Grouped by four digits, as clippy::unreadable_literal asks
+        code.push_str(&format!(
+            "\n/// The `JsonStructure::fingerprint` of the structure these types were\n\
+             /// generated from.\n\
+             pub const {}_FINGERPRINT: u64 = 0x{:04x}_{:04x}_{:04x}_{:04x};\n",
+            to_snake_case(root_name).to_uppercase(),
+            fingerprint >> 48,
+            (fingerprint >> 32) & 0xffff,
+            (fingerprint >> 16) & 0xffff,
+            fingerprint & 0xffff
+        ));
+    }
+    if options.shape_check {
+        let json_strings: Vec<&str> = stats
+            .iter()
+            .filter(|s| options.json_strings && s.string_format == Some(StringFormat::Json))
+            .map(|s| s.path.as_str())
+            .collect();
+        code.push_str(&shape_check_code(structure, &json_strings));
+    }
+    Generated {
+        code,
+        source_map,
+        field_order_savings,
+        type_sizes,
+    }
+}
+
+/// The name of the elements of a root that is an array of objects, when
+/// `ConvertOptions::set_array_root` names them apart from the root: the root
+/// name as an array element, such as `User` for `Users`, or with `Element`
+/// appended when that is the root name itself.
+pub(crate) fn array_element_name(
+    structure: &JsonStructure,
+    root_name: &str,
+    options: &ConvertOptions,
+) -> Option<String> {
+    if options.array_root == ArrayRoot::Struct || !matches!(structure, JsonStructure::Array(_)) {
+        return None;
+    }
+    if !matches!(
+        unwrap_root(structure),
+        JsonStructure::Object(_) | JsonStructure::Union(_)
+    ) {
+        return None;
+    }
+    let name = type_name(&options.naming_strategy.type_name(&NameContext {
+        path: &[],
+        field_name: root_name,
+        parent: None,
+        is_array_element: true,
+    }));
+    if name == type_name(root_name) {
+        Some(format!("{}Element", type_name(root_name)))
+    } else {
+        Some(name)
+    }
+}
+
+/// Generates the types for several structures at once, named after the keys
+/// of `roots`, along with the direction each is sent in. Names are resolved
+/// across all of them, so nested types with the same name but different shapes
+/// don't collide.
+pub(crate) fn generate_rust_roots(
+    roots: &[(JsonPair, Direction)],
+    options: &ConvertOptions,
+) -> String {
+    let directions: Vec<Direction> = roots.iter().map(|(_, direction)| *direction).collect();
+    let roots: Vec<JsonPair> = roots
+        .iter()
+        .map(|(pair, _)| JsonPair::new(pair.key(), unwrap_root(pair.value()).clone()))
+        .collect();
+    // The roots are named as the fields of an object that is never generated
+    let container = JsonStructure::Object(roots);
+    let symbols = SymbolTable::build(
+        &container,
+        "",
+        options.naming_strategy.as_ref(),
+        &options.abbreviations,
+        options.acronyms,
+    );
+    let mut generator = CodeGenerator::new(&symbols, options, Direction::Both);
+    for (pair, direction) in container.fields().unwrap_or(&[]).iter().zip(directions) {
+        generator.direction = direction;
+        generator.path.push(pair.key().to_string());
+        generator.type_for(pair.value());
+        generator.path.pop();
+    }
+    generator.finish()
+}
+
+/// The rust type of a whole document with the given structure, when its types
+/// were generated with the root named `root_name`, e.g. `Vec<User>` for an
+/// array of objects.
+pub(crate) fn root_type(structure: &JsonStructure, root_name: &str) -> String {
+    match structure {
+        JsonStructure::Array(inner) => format!("Vec<{}>", root_type(inner, root_name)),
+        JsonStructure::Optional(inner) => format!("Option<{}>", root_type(inner, root_name)),
+        JsonStructure::Object(_) | JsonStructure::Union(_) => root_name.to_string(),
+        JsonStructure::Value(value) => primitive_type(value),
+        JsonStructure::Dictionary(_) | JsonStructure::Unknown => "serde_json::Value".to_string(),
+    }
+}
+
+/// Generates the types for `roots` in a module named `module`, documented
+/// with `doc`.
+pub(crate) fn generate_rust_module(
+    module: &str,
+    doc: &str,
+    roots: &[(JsonPair, Direction)],
+    options: &ConvertOptions,
+) -> String {
+    let mut output = format!("/// {}\npub mod {} {{\n", doc, module);
+    for line in generate_rust_roots(roots, options).lines() {
+        if !line.is_empty() {
+            output.push_str("    ");
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+    output.push_str("}\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+    use crate::naming::{AcronymStyle, FullPathStrategy};
+
+    fn generate(json: &str) -> String {
+        generate_rust(&convert_sample_json(json).unwrap(), "Root")
+    }
+
+    #[test]
+    fn generates_a_simple_struct() {
+        let result = generate(r#"{"name": "foo", "age": 42, "score": 4.5, "active": true}"#);
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    pub name: String,
+    pub age: i64,
+    pub score: f64,
+    pub active: bool,
+}
+"#
+        )
+    }
+
+    #[test]
+    fn generates_nested_structs_and_renames() {
+        let result = generate(
+            r#"{"userInfo": {"type": "admin", "tags": []}, "items": [{"id": 1}, {"id": null}]}"#,
+        );
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    #[serde(rename = "userInfo")]
+    pub user_info: UserInfo,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserInfo {
+    pub r#type: String,
+    pub tags: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    pub id: Option<i64>,
+}
+"#
+        )
+    }
+
+    #[test]
+    fn generates_untagged_enums_for_unions() {
+        let result = generate(r#"{"value": ["a", 1, {"b": true}]}"#);
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    pub value: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Object(ValueObject),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValueObject {
+    pub b: bool,
+}
+"#
+        )
+    }
+
+    #[test]
+    fn adds_accessors_to_unions_of_a_value_or_an_object() {
+        let result = generate(
+            r#"[{"author": "ada", "year": 1843},
+                {"author": {"name": "bob", "email": "bob@example.com"}, "year": {"value": 2}}]"#,
+        );
+
+        assert!(result.contains(
+            "impl Author {\n    pub fn name(&self) -> &str {\n        match self {\n            \
+             Author::String(value) => value,\n            \
+             Author::Object(value) => &value.name,\n        }\n    }\n}\n"
+        ));
+        assert!(result.contains(
+            "    pub fn value(&self) -> i64 {\n        match self {\n            \
+             Year::Integer(value) => *value,\n            \
+             Year::Object(value) => value.value,\n"
+        ));
+
+        // Nothing tells which of several fields of the type holds the value
+        let result = generate(r#"[{"a": "x"}, {"a": {"first": "y", "last": "z"}}]"#);
+        assert!(!result.contains("impl A"));
+    }
+
+    #[test]
+    fn generates_tagged_enums_for_hinted_unions() {
+        let generate_tagged = |json: &str, tagging: UnionTagging| {
+            let mut options = ConvertOptions::default();
+            options.tag_union("/shapes/*", tagging);
+            let structure = crate::convert_sample_json_with_options(json, &options).unwrap();
+            generate_rust_with_options(&structure, "Root", &options)
+        };
+
+        let result = generate_tagged(
+            r#"{"shapes": [{"type": "circle", "radius": 1.5}, {"type": "square", "side": 2},
+                           {"type": "circle", "radius": 2.5}]}"#,
+            UnionTagging::Internal {
+                tag: "type".to_string(),
+            },
+        );
+        assert!(result.contains(
+            "#[serde(tag = \"type\")]\npub enum Shape {\n    \
+             #[serde(rename = \"circle\")]\n    Circle(Circle),\n    \
+             #[serde(rename = \"square\")]\n    Square(Square),\n}\n"
+        ));
+        assert!(result.contains("pub struct Circle {\n    pub radius: f64,\n}\n"));
+        assert!(result.contains("pub struct Square {\n    pub side: i64,\n}\n"));
+
+        let result = generate_tagged(
+            r#"{"shapes": [{"kind": "Circle", "data": {"radius": 1.5}},
+                           {"kind": "Point"}]}"#,
+            UnionTagging::Adjacent {
+                tag: "kind".to_string(),
+                content: "data".to_string(),
+            },
+        );
+        // A value without the content isn't adjacently tagged
+        assert!(result.contains("pub struct Shape {\n    pub kind: String,\n"));
+
+        let result = generate_tagged(
+            r#"{"shapes": [{"kind": "Circle", "data": {"radius": 1.5}},
+                           {"kind": "Square", "data": {"side": 2}}]}"#,
+            UnionTagging::Adjacent {
+                tag: "kind".to_string(),
+                content: "data".to_string(),
+            },
+        );
+        assert!(result.contains(
+            "#[serde(tag = \"kind\", content = \"data\")]\npub enum Shape {\n    \
+             Circle(Circle),\n    Square(Square),\n}\n"
+        ));
+
+        let result = generate_tagged(
+            r#"{"shapes": [{"Circle": {"radius": 1.5}}, {"Square": {"side": 2}}]}"#,
+            UnionTagging::External,
+        );
+        assert!(result.contains("pub enum Shape {\n    Circle(Circle),\n    Square(Square),\n}\n"));
+        assert!(!result.contains("untagged"));
+    }
+
+    #[test]
+    fn uses_detected_types() {
+        let result = generate(r#"[{"ip": "192.168.1.1", "version": "0.1.0"}]"#);
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    pub ip: std::net::IpAddr,
+    pub version: semver::Version,
+}
+"#
+        )
+    }
+
+    #[test]
+    fn uses_the_naming_strategy() {
+        let mut options = ConvertOptions::default();
+        options.set_naming_strategy(FullPathStrategy);
+        let structure =
+            convert_sample_json(r#"{"order": {"items": [{"id": 1}]}, "tags": ["a", 1]}"#).unwrap();
+
+        let result = generate_rust_with_options(&structure, "Root", &options);
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    pub order: Order,
+    pub tags: Vec<Tags>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    pub items: Vec<OrderItems>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderItems {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Tags {
+    String(String),
+    Integer(i64),
+}
+"#
+        )
+    }
+
+    #[test]
+    fn disambiguates_colliding_names() {
+        let options = ConvertOptions::default();
+        let structure =
+            convert_sample_json(r#"{"items": [{"id": 1}], "cart": {"items": [{"sku": "a"}]}}"#)
+                .unwrap();
+
+        let result = generate_rust_with_options(&structure, "Root", &options);
+
+        assert!(result.contains("pub struct RootItem {\n    pub id: i64,"));
+        assert!(result.contains("pub struct CartItem {\n    pub sku: String,"));
+        assert!(result.contains("pub items: Vec<CartItem>,"));
+    }
+
+    #[test]
+    fn disambiguates_keys_differing_only_by_case() {
+        let structure = convert_sample_json(r#"{"userId": 1, "user_id": 2}"#).unwrap();
+
+        let result = generate_rust_with_options(&structure, "Root", &ConvertOptions::default());
+
+        assert!(result.contains(
+            "    #[serde(rename = \"userId\")]\n    pub user_id_2: i64,\n    pub user_id: i64,"
+        ));
+    }
+
+    #[test]
+    fn writes_acronyms_in_upper_case() {
+        let mut options = ConvertOptions::default();
+        options
+            .set_acronym_style(AcronymStyle::Uppercase)
+            .set_constant_fields(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"apiUrl": {"host": "a"}, "userIds": [{"id": 1}], "kind": "json"},
+               {"apiUrl": {"host": "b"}, "userIds": [], "kind": "json"}]"#,
+            &options,
+        )
+        .unwrap();
+
+        let result = conversion_to_rust(&conversion, "Root", &options);
+
+        assert!(result.contains("    #[serde(rename = \"apiUrl\")]\n    pub api_url: APIURL,"));
+        assert!(result.contains("pub struct APIURL {"));
+        assert!(
+            result.contains("    #[serde(rename = \"userIds\")]\n    pub user_ids: Vec<UserID>,")
+        );
+        assert!(result.contains("pub enum RootKind {\n    #[serde(rename = \"json\")]\n    JSON,"));
+    }
+
+    #[test]
+    fn generates_several_roots_with_distinct_names() {
+        let request = convert_sample_json(r#"{"items": [{"sku": "a"}]}"#).unwrap();
+        let response = convert_sample_json(r#"[{"items": [{"id": 1}]}]"#).unwrap();
+
+        let result = generate_rust_roots(
+            &[
+                (JsonPair::new("Request", request), Direction::Request),
+                (JsonPair::new("Response", response), Direction::Response),
+            ],
+            &ConvertOptions::default(),
+        );
+
+        assert!(result.contains("pub struct Request {\n    pub items: Vec<RequestItem>,"));
+        assert!(result.contains("pub struct RequestItem {\n    pub sku: String,"));
+        assert!(result.contains("pub struct Response {\n    pub items: Vec<ResponseItem>,"));
+        assert!(result.contains("pub struct ResponseItem {\n    pub id: i64,"));
+    }
+
+    #[test]
+    fn renames_non_ascii_keys() {
+        let result = generate(
+            r#"{"größe": 1, "名前": "x", "cafe\u0301": true, "🎉": null, "😀smile": 2.5}"#,
+        );
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    /// `größe` in the sample
+    #[serde(rename = "größe")]
+    pub grosse: i64,
+    /// `名前` in the sample
+    #[serde(rename = "名前")]
+    pub field_2: String,
+    /// `DECOMPOSED` in the sample
+    #[serde(rename = "DECOMPOSED")]
+    pub cafe: bool,
+    /// `🎉` in the sample
+    #[serde(rename = "🎉")]
+    pub field_4: Option<serde_json::Value>,
+    /// `😀smile` in the sample
+    #[serde(rename = "😀smile")]
+    pub smile: f64,
+}
+"#
+            .replace("DECOMPOSED", "cafe\u{301}")
+        )
+    }
+
+    #[test]
+    fn generates_fields_for_blank_keys() {
+        let result = generate(r#"{"": 1, "  ": "x", "scores": {"": 1.5, " ": 2}}"#);
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    #[serde(rename = "")]
+    pub field_1: i64,
+    #[serde(rename = "  ")]
+    pub field_2: String,
+    pub scores: HashMap<String, f64>,
+}
+"#
+        )
+    }
+
+    #[test]
+    fn generates_nested_arrays() {
+        let sample = r#"{"matrix": [[1, null], [2]], "cube": [[[0.5]], []],
+            "groups": [[{"id": 1, "name": "a"}], [{"id": 2, "name": null}]],
+            "rows": [[1, "a"], null], "grid": [[{"a": 1}, [true]]]}"#;
+        let structure = convert_sample_json(sample).unwrap();
+
+        assert_eq!(
+            generate_rust(&structure, "Root"),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    pub matrix: Vec<Vec<Option<i64>>>,
+    pub cube: Vec<Vec<Vec<f64>>>,
+    pub groups: Vec<Vec<Group>>,
+    pub rows: Vec<Option<Vec<Row>>>,
+    pub grid: Vec<Vec<Grid>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Group {
+    pub id: i64,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Row {
+    Integer(i64),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Grid {
+    Object(GridObject),
+    Array(Vec<bool>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GridObject {
+    pub a: i64,
+}
+"#
+        );
+
+        let mut options = ConvertOptions::default();
+        options.set_validation(true);
+        let result = generate_rust_with_options(&structure, "Root", &options);
+        assert!(result.contains("    #[validate(nested)]\n    pub groups: Vec<Vec<Group>>,"));
+        assert!(result.contains("    pub matrix: Vec<Vec<Option<i64>>>,"));
+    }
+
+    #[test]
+    fn adds_validation_rules_from_the_sample() {
+        let mut options = ConvertOptions::default();
+        options.set_validation(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"email": "a@b.io", "name": "Al", "age": 31, "score": 0.5, "tags": ["x"],
+                "address": {"city": "Oslo"}},
+               {"email": "bob@example.com", "name": "Robert", "age": 7, "score": 2,
+                "address": {"city": "Rome"}}]"#,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            conversion_to_rust(&conversion, "User", &options),
+            r#"use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct User {
+    #[validate(email, length(min = 6, max = 15))]
+    pub email: String,
+    #[validate(length(min = 2, max = 6))]
+    pub name: String,
+    #[validate(range(min = 7, max = 31))]
+    pub age: i64,
+    #[validate(range(min = 0.5, max = 2.0))]
+    pub score: f64,
+    pub tags: Option<Vec<String>>,
+    #[validate(nested)]
+    pub address: Address,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct Address {
+    #[validate(length(min = 4, max = 4))]
+    pub city: String,
+}
+"#
+        )
+    }
+
+    #[test]
+    fn coerces_mixed_numbers_with_deserialize_helpers() {
+        let structure = convert_sample_json(
+            r#"[{"id": "1", "count": 2, "tags": [1, "a"]}, {"id": 2, "count": null}]"#,
+        )
+        .unwrap();
+        let mut options = ConvertOptions::default();
+        options.set_mixed_number_policy(MixedNumberPolicy::Number);
+
+        assert_eq!(
+            generate_rust_with_options(&structure, "Root", &options),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    #[serde(deserialize_with = "number_from_string")]
+    pub id: i64,
+    pub count: Option<i64>,
+    pub tags: Option<Vec<Tag>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Tag {
+    Integer(i64),
+    String(String),
+}
+
+/// A value sent either as a json string or as a json number.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(serde_json::Number),
+}
+
+impl StringOrNumber {
+    fn into_string(self) -> String {
+        match self {
+            StringOrNumber::String(s) => s,
+            StringOrNumber::Number(n) => n.to_string(),
+        }
+    }
+
+    fn parse<T, E>(self) -> Result<T, E>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+        E: serde::de::Error,
+    {
+        self.into_string().trim().parse().map_err(E::custom)
+    }
+}
+
+/// Deserializes a number sent either as a json number or as a string.
+fn number_from_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    <StringOrNumber as serde::Deserialize>::deserialize(deserializer)?.parse()
+}
+"#
+        );
+
+        let structure =
+            convert_sample_json(r#"[{"code": "a"}, {"code": 1}, {"code": null}]"#).unwrap();
+        options.set_mixed_number_policy(MixedNumberPolicy::String);
+        let result = generate_rust_with_options(&structure, "Root", &options);
+
+        assert!(result.contains(
+            "    #[serde(default, deserialize_with = \"optional_string_from_number\")]\n    \
+             pub code: Option<String>,"
+        ));
+        assert!(result.contains("fn optional_string_from_number<'de, D>"));
+        assert!(!result.contains("fn parse<T, E>"));
+    }
+
+    #[test]
+    fn decodes_json_strings() {
+        let mut options = ConvertOptions::default();
+        options.set_json_strings(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"payload": "{\"a\": 1}", "meta": "[1]"}, {"payload": "{\"a\": 2}", "meta": null}]"#,
+            &options,
+        )
+        .unwrap();
+
+        let result = conversion_to_rust(&conversion, "Root", &options);
+
+        assert!(result.contains(
+            "    #[serde(with = \"json_string\")]\n    \
+             pub payload: Payload,\n    \
+             #[serde(default, with = \"optional_json_string\")]\n    \
+             pub meta: Option<Vec<i64>>,\n"
+        ));
+        assert!(result.contains("pub struct Payload {\n    pub a: i64,\n}\n"));
+        assert!(result.contains("\nmod json_string {\n    pub fn serialize<S, T>"));
+        assert!(result.contains("\nmod optional_json_string {\n    pub fn serialize<S, T>"));
+
+        options.set_shape_check(true);
+        let result = conversion_to_rust(&conversion, "Root", &options);
+        assert!(result.contains(
+            "SchemaShape::Array(&SchemaShape::Object(&[(\"payload\", SchemaShape::String), \
+             (\"meta\", SchemaShape::Optional(&SchemaShape::String))]))"
+        ));
+    }
+
+    #[test]
+    fn coerces_empty_strings_and_numeric_bools() {
+        let mut options = ConvertOptions::default();
+        options
+            .set_empty_strings_as_none(true)
+            .set_numeric_bools(true);
+        let structure = crate::convert_sample_json_with_options(
+            r#"[{"name": "a", "active": 1, "admin": true, "count": 3},
+                {"name": "", "active": false, "count": ""}]"#,
+            &options,
+        )
+        .unwrap();
+        let result = generate_rust_with_options(&structure, "Root", &options);
+
+        assert!(result.contains(
+            "    #[serde(default, deserialize_with = \"empty_string_as_none\")]\n    \
+             pub name: Option<String>,\n    \
+             #[serde(deserialize_with = \"bool_from_int\")]\n    \
+             pub active: bool,\n    \
+             pub admin: Option<bool>,\n    \
+             #[serde(default, deserialize_with = \"empty_string_as_none\")]\n    \
+             pub count: Option<i64>,"
+        ));
+        assert!(result.contains("\nfn empty_string_as_none<'de, D, T>"));
+        assert!(result.contains("\nfn bool_from_int<'de, D>"));
+        assert!(!result.contains("fn parse<T, E>"));
+
+        options.set_helpers_module(Some("crate::de_helpers".to_string()));
+        let result = generate_rust_with_options(&structure, "Root", &options);
+
+        assert!(result.contains("deserialize_with = \"crate::de_helpers::bool_from_int\""));
+        assert!(!result.contains("fn bool_from_int"));
+    }
+
+    #[test]
+    fn coerces_whole_floats() {
+        let mut options = ConvertOptions::default();
+        options.set_whole_float_policy(WholeFloatPolicy::Int);
+        let structure = crate::convert_sample_json_with_options(
+            r#"[{"count": 3.0, "total": 1}, {"count": 7, "total": 2.0}, {"total": null}]"#,
+            &options,
+        )
+        .unwrap();
+        let result = generate_rust_with_options(&structure, "Root", &options);
+
+        assert!(result.contains(
+            "    #[serde(default, deserialize_with = \"optional_int_from_float\")]\n    \
+             pub count: Option<i64>,\n    \
+             #[serde(default, deserialize_with = \"optional_int_from_float\")]\n    \
+             pub total: Option<i64>,"
+        ));
+        assert!(result.contains("\nenum IntOrFloat {"));
+        assert!(result.contains("\nfn optional_int_from_float<'de, D>"));
+        assert!(!result.contains("\nfn int_from_float<'de, D>"));
+    }
+
+    #[test]
+    fn adds_type_and_field_attributes() {
+        let structure =
+            convert_sample_json(r#"{"id": 1, "items": [{"legacy_id": 2}], "tag": [1, "a"]}"#)
+                .unwrap();
+        let mut options = ConvertOptions::default();
+        options
+            .add_type_attribute("#[cfg_attr(feature = \"openapi\", derive(ToSchema))]")
+            .add_field_attribute("/items/*/legacy_id", "#[deprecated]")
+            .add_field_attribute("/items/*/legacy_id", "#[doc(hidden)]");
+
+        assert_eq!(
+            generate_rust_with_options(&structure, "Root", &options),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct Root {
+    pub id: i64,
+    pub items: Vec<Item>,
+    pub tag: Vec<Tag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct Item {
+    #[deprecated]
+    #[doc(hidden)]
+    pub legacy_id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[serde(untagged)]
+pub enum Tag {
+    Integer(i64),
+    String(String),
+}
+"#
+        );
+    }
+
+    #[test]
+    fn adds_the_schema_fingerprint() {
+        let structure = convert_sample_json(r#"{"id": 1}"#).unwrap();
+        let mut options = ConvertOptions::default();
+        options.set_schema_fingerprint(true);
+        let code = generate_rust_with_options(&structure, "ApiResponse", &options);
+
+        assert!(code.ends_with(&format!(
+            "}}\n\n/// The `JsonStructure::fingerprint` of the structure these types were\n\
+             /// generated from.\n\
+             pub const API_RESPONSE_FINGERPRINT: u64 = 0x{:04x}_{:04x}_{:04x}_{:04x};\n",
+            structure.fingerprint() >> 48,
+            (structure.fingerprint() >> 32) & 0xffff,
+            (structure.fingerprint() >> 16) & 0xffff,
+            structure.fingerprint() & 0xffff
+        )));
+    }
+
+    #[test]
+    fn strips_common_prefixes_from_field_names() {
+        let structure =
+            convert_sample_json(r#"{"user_name": "a", "user_age": 1, "user_type": "b"}"#).unwrap();
+        let mut options = ConvertOptions::default();
+        options.set_strip_common_prefix(true);
+
+        assert_eq!(
+            generate_rust_with_options(&structure, "Root", &options),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    #[serde(rename = "user_name")]
+    pub name: String,
+    #[serde(rename = "user_age")]
+    pub age: i64,
+    #[serde(rename = "user_type")]
+    pub r#type: String,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn expands_abbreviations_in_names() {
+        let structure = convert_sample_json(r#"{"total_amt": 1, "cfg": {"max_qty": 2}}"#).unwrap();
+        let mut options = ConvertOptions::default();
+        options
+            .add_abbreviation("amt", "amount")
+            .add_abbreviation("CFG", "config")
+            .add_abbreviation("qty", "quantity");
+
+        assert_eq!(
+            generate_rust_with_options(&structure, "CfgRoot", &options),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CfgRoot {
+    #[serde(rename = "total_amt")]
+    pub total_amount: i64,
+    #[serde(rename = "cfg")]
+    pub config: Config,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(rename = "max_qty")]
+    pub max_quantity: i64,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn unwraps_single_field_wrappers() {
+        let structure = convert_sample_json(
+            r#"{"price": {"value": 5}, "user": {"id": 1}, "note": {"value": null}}"#,
+        )
+        .unwrap();
+        let mut options = ConvertOptions::default();
+        options.unwrap_wrapper("val*");
+        let code = generate_rust_with_options(&structure, "Root", &options);
+
+        assert!(code.contains(
+            "pub struct Root {\n    #[serde(with = \"value_wrapper\")]\n    pub price: i64,\n    \
+             pub user: User,\n    pub note: Note,\n}"
+        ));
+        assert!(code.contains("\nmod value_wrapper {\n"));
+        assert!(code.contains("#[serde(rename = \"value\")]\n        value: T,"));
+
+        options.set_json_schemas(true);
+        let code = generate_rust_with_options(&structure, "Root", &options);
+        assert!(!code.contains("value_wrapper"));
+    }
+
+    #[test]
+    fn adds_the_shape_check() {
+        let structure = convert_sample_json(r#"{"id": 1, "tags": {"a": "b"}}"#).unwrap();
+        let mut options = ConvertOptions::default();
+        options.set_shape_check(true);
+        let code = generate_rust_with_options(&structure, "Root", &options);
+
+        assert!(code.contains(
+            "\npub fn matches_schema(value: &serde_json::Value) -> Vec<SchemaViolation> {\n"
+        ));
+        assert!(code.ends_with(
+            "static SCHEMA_SHAPE: SchemaShape = SchemaShape::Object(&[(\"id\", SchemaShape::Int), \
+             (\"tags\", SchemaShape::Object(&[(\"a\", SchemaShape::String)]))]);\n"
+        ));
+        assert!(crate::plan_dependencies(&code)
+            .iter()
+            .any(|d| d.name == "serde_json"));
+    }
+
+    #[test]
+    fn checks_constant_fields() {
+        let mut options = ConvertOptions::default();
+        options.set_constant_fields(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"object": "charge", "version": 2, "offset": -3, "id": 1},
+               {"object": "charge", "version": 2, "offset": -3, "id": 2}]"#,
+            &options,
+        )
+        .unwrap();
+
+        let result = conversion_to_rust(&conversion, "Root", &options);
+
+        assert!(result.contains(
+            "pub struct Root {\n    \
+             pub object: RootObject,\n    \
+             #[serde(deserialize_with = \"constant_int::<_, 2>\")]\n    \
+             pub version: i64,\n    \
+             #[serde(deserialize_with = \"constant_int::<_, { -3 }>\")]\n    \
+             pub offset: i64,\n    \
+             pub id: i64,\n}\n"
+        ));
+        assert!(result.contains(
+            "pub enum RootObject {\n    #[serde(rename = \"charge\")]\n    Charge,\n}\n"
+        ));
+        assert!(result.contains("\nfn constant_int<'de, D, const VALUE: i64>"));
+
+        options.set_helpers_module(Some("crate::de_helpers".to_string()));
+        let result = conversion_to_rust(&conversion, "Root", &options);
+        assert!(result.contains("deserialize_with = \"crate::de_helpers::constant_int::<_, 2>\""));
+        assert!(crate::de_helpers::de_helpers_module(&result)
+            .unwrap()
+            .contains("\npub fn constant_int<'de, D, const VALUE: i64>"));
+
+        // A single sample says nothing about whether the value can change
+        let conversion =
+            crate::convert_sample_json_with_warnings(r#"{"object": "charge"}"#, &options).unwrap();
+        assert!(conversion_to_rust(&conversion, "Root", &options).contains("pub object: String,"));
+    }
+
+    #[test]
+    fn names_the_variants_of_constant_strings() {
+        let mut options = ConvertOptions::default();
+        options.set_constant_fields(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"status": "IN_PROGRESS", "method": "2fa"},
+               {"status": "IN_PROGRESS", "method": "2fa"}]"#,
+            &options,
+        )
+        .unwrap();
+
+        let result = conversion_to_rust(&conversion, "Root", &options);
+
+        assert!(result.contains(
+            "pub enum RootStatus {\n    #[serde(rename = \"IN_PROGRESS\")]\n    InProgress,\n}\n"
+        ));
+        assert!(result
+            .contains("pub enum RootMethod {\n    #[serde(rename = \"2fa\")]\n    Value2fa,\n}\n"));
+    }
+
+    #[test]
+    fn generates_enums_of_few_integers() {
+        let mut options = ConvertOptions::default();
+        options.set_int_enums(Some(IntEnumStyle::Repr));
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"status": 1, "level": -1, "id": 1}, {"status": 2, "level": null, "id": 2},
+               {"status": 1, "level": 3, "id": 3}, {"status": 2, "level": -1, "id": 4},
+               {"status": 1, "level": 3, "id": 5}]"#,
+            &options,
+        )
+        .unwrap();
+
+        let result = conversion_to_rust(&conversion, "Root", &options);
+
+        assert!(result.contains("use serde_repr::{Deserialize_repr, Serialize_repr};\n"));
+        assert!(result.contains(
+            "pub struct Root {\n    \
+             pub status: RootStatus,\n    \
+             pub level: Option<RootLevel>,\n    \
+             pub id: i64,\n}\n"
+        ));
+        assert!(result.contains(
+            "#[derive(Debug, Clone, PartialEq, Copy, Serialize_repr, Deserialize_repr)]\n\
+             #[repr(i64)]\npub enum RootStatus {\n    Value1 = 1,\n    Value2 = 2,\n}\n"
+        ));
+        assert!(result.contains("pub enum RootLevel {\n    Minus1 = -1,\n    Value3 = 3,\n}\n"));
+        assert_eq!(
+            crate::plan_dependencies(&result)
+                .iter()
+                .map(|d| d.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["serde", "serde_repr"]
+        );
+
+        options.set_int_enums(Some(IntEnumStyle::Newtype));
+        let result = conversion_to_rust(&conversion, "Root", &options);
+        assert!(result.contains(
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+             #[serde(transparent)]\npub struct RootStatus(pub i64);\n\n\
+             impl RootStatus {\n    \
+             pub const VALUE_1: RootStatus = RootStatus(1);\n    \
+             pub const VALUE_2: RootStatus = RootStatus(2);\n}\n"
+        ));
+    }
+
+    #[test]
+    fn distinguishes_missing_fields_from_null_ones() {
+        let mut options = ConvertOptions::default();
+        options.set_distinguish_null(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"name": "a", "email": null, "age": 3, "note": null},
+               {"name": null, "email": "b@c.io"},
+               {"email": "d@e.io", "note": null}]"#,
+            &options,
+        )
+        .unwrap();
+
+        let result = conversion_to_rust(&conversion, "Update", &options);
+        assert!(result.contains(
+            "pub struct Update {\n    \
+             #[serde(default, deserialize_with = \"double_option\", skip_serializing_if = \"Option::is_none\")]\n    \
+             pub name: Option<Option<String>>,\n    \
+             pub email: Option<String>,\n    \
+             pub age: Option<i64>,\n    \
+             #[serde(default, deserialize_with = \"double_option\", skip_serializing_if = \"Option::is_none\")]\n    \
+             pub note: Option<Option<serde_json::Value>>,\n}\n"
+        ));
+        assert!(result.contains("\nfn double_option<'de, D, T>"));
+
+        options.set_distinguish_null(false);
+        let result = conversion_to_rust(&conversion, "Update", &options);
+        assert!(result.contains("    pub name: Option<String>,\n"));
+        assert!(!result.contains("double_option"));
+    }
+
+    #[test]
+    fn generates_patch_types() {
+        let mut options = ConvertOptions::default();
+        options.set_patch_types(true);
+        let structure =
+            convert_sample_json(r#"{"name": "a", "bio": null, "Address": {"city": "Oslo"}}"#)
+                .unwrap();
+
+        assert_eq!(
+            generate_rust_with_options(&structure, "User", &options),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    pub name: String,
+    pub bio: Option<serde_json::Value>,
+    #[serde(rename = "Address")]
+    pub address: Address,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Address {
+    pub city: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct UserPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub bio: Option<Option<serde_json::Value>>,
+    #[serde(rename = "Address")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+}
+
+/// Deserializes a field that may be null as `Some(None)`, so that with
+/// `#[serde(default)]` it can be told apart from a missing field, which is
+/// `None`.
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    <Option<T> as serde::Deserialize>::deserialize(deserializer).map(Some)
+}
+"#
+        );
+    }
+
+    #[test]
+    fn generates_create_types_without_server_generated_fields() {
+        let mut options = ConvertOptions::default();
+        options
+            .mark_server_generated("/id")
+            .mark_server_generated("/createdAt")
+            .mark_server_generated("/tags/*/id");
+        let structure = convert_sample_json(
+            r#"{"id": 1, "name": "a", "createdAt": "2024-01-01", "tags": [{"id": 2, "label": "x"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            generate_rust_with_options(&structure, "Post", &options),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Post {
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagCreate {
+    pub label: String,
+}
+
+impl From<Tag> for TagCreate {
+    fn from(value: Tag) -> TagCreate {
+        TagCreate {
+            label: value.label,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostCreate {
+    pub name: String,
+    pub tags: Vec<Tag>,
+}
+
+impl From<Post> for PostCreate {
+    fn from(value: Post) -> PostCreate {
+        PostCreate {
+            name: value.name,
+            tags: value.tags,
+        }
+    }
+}
+"#
+        );
+
+        // Server types only read the create type
+        options.set_server_types(true);
+        let result = generate_rust_with_options(&structure, "Post", &options);
+        assert!(result.contains(
+            "#[derive(Debug, Clone, PartialEq, Deserialize)]\n\
+             #[serde(deny_unknown_fields)]\n\
+             pub struct PostCreate {"
+        ));
+    }
+
+    #[test]
+    fn converts_between_similar_structs() {
+        let mut options = ConvertOptions::default();
+        options.set_conversions(true);
+        let structure = convert_sample_json(
+            r#"{"summary": {"id": 1, "name": "a", "price": 2.5},
+                "detail": {"id": 1, "name": "a", "price": 2.5, "stock": 3},
+                "owner": {"id": 1, "email": "a@b.io"}}"#,
+        )
+        .unwrap();
+        let result = generate_rust_with_options(&structure, "Product", &options);
+
+        assert!(result.ends_with(
+            "\nimpl From<Detail> for Summary {
+    fn from(value: Detail) -> Summary {
+        Summary {
+            id: value.id,
+            name: value.name,
+            price: value.price,
+        }
+    }
+}
+"
+        ));
+        assert!(!result.contains("for Detail"));
+        assert!(!result.contains("for Owner"));
+
+        // Optional fields the other struct doesn't have are left empty
+        let structure = convert_sample_json(
+            r#"[{"a": {"id": 1, "name": "x", "note": "n"}, "b": {"id": 1, "name": "x"}},
+                {"a": {"id": 1, "name": "x"}, "b": {"id": 1, "name": "x"}}]"#,
+        )
+        .unwrap();
+        let result = generate_rust_with_options(&structure, "Root", &options);
+        assert!(result.contains("            name: value.name,\n            note: None,\n"));
+        assert!(result.contains("impl From<A> for B {"));
+    }
+
+    #[test]
+    fn generates_forward_compatible_types() {
+        let mut options = ConvertOptions::default();
+        options
+            .set_constant_fields(true)
+            .set_forward_compatible(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"object": "charge", "amount": 1}, {"object": "charge", "amount": "2"}]"#,
+            &options,
+        )
+        .unwrap();
+
+        let result = conversion_to_rust(&conversion, "Root", &options);
+
+        assert!(result.contains(
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+             #[non_exhaustive]\n\
+             pub struct Root {"
+        ));
+        assert!(result.contains(
+            "pub enum RootObject {\n    \
+             #[serde(rename = \"charge\")]\n    \
+             Charge,\n    \
+             #[serde(other)]\n    \
+             Other,\n}"
+        ));
+        assert!(result.contains("    String(String),\n    Other(serde_json::Value),\n}"));
+    }
+
+    #[test]
+    fn generates_type_aliases() {
+        let mut options = ConvertOptions::default();
+        options.add_type_alias("*_id").set_root_alias(true);
+        let json = r#"{"user_id": "a", "post": {"user_id": "b", "parent_id": null}}"#;
+        let structure = crate::convert_sample_json_with_options(json, &options).unwrap();
+
+        let result = generate_rust_with_options(&structure, "Root", &options);
+
+        assert!(result.contains("    pub user_id: UserId,\n"));
+        assert!(result.contains("pub struct Post {\n    pub user_id: UserId,\n"));
+        assert!(result.contains("    pub parent_id: Option<serde_json::Value>,\n"));
+        assert!(result.ends_with("}\n\npub type UserId = String;\n"));
+
+        let structure = crate::convert_sample_json(r#"[[1, 2]]"#).unwrap();
+        let result = generate_rust_with_options(&structure, "Grid", &options);
+        assert_eq!(result, "pub type Grid = Vec<Vec<i64>>;\n");
+
+        options.set_root_alias(false);
+        let result = generate_rust_with_options(&structure, "Grid", &options);
+        assert_eq!(result, "");
+    }
+
+    #[test]
+    fn generates_columns_of_array_roots() {
+        let mut options = ConvertOptions::default();
+        options.set_columnar(true);
+        let structure =
+            crate::convert_sample_json(r#"[{"id": 1, "type": "a", "tags": ["x"]}]"#).unwrap();
+
+        let result = generate_rust_with_options(&structure, "Order", &options);
+
+        assert!(result.ends_with(
+            r#"
+/// The fields of `Order`s, as a column each.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OrderColumns {
+    pub id: Vec<i64>,
+    pub r#type: Vec<String>,
+    pub tags: Vec<Vec<String>>,
+}
+
+impl std::iter::FromIterator<Order> for OrderColumns {
+    fn from_iter<I: IntoIterator<Item = Order>>(rows: I) -> OrderColumns {
+        let mut columns = OrderColumns::default();
+        for row in rows {
+            columns.id.push(row.id);
+            columns.r#type.push(row.r#type);
+            columns.tags.push(row.tags);
+        }
+        columns
+    }
+}
+
+impl From<Vec<Order>> for OrderColumns {
+    fn from(rows: Vec<Order>) -> OrderColumns {
+        rows.into_iter().collect()
+    }
+}
+"#
+        ));
+
+        // Only arrays of objects have rows
+        let structure = crate::convert_sample_json(r#"{"id": 1}"#).unwrap();
+        let result = generate_rust_with_options(&structure, "Order", &options);
+        assert!(!result.contains("Columns"));
+    }
+
+    #[test]
+    fn maps_flat_records_to_database_rows() {
+        let mut options = ConvertOptions::default();
+        options
+            .set_sqlx_derives(true)
+            .set_diesel_table(Some("crate::schema::accounts".to_string()));
+        let structure =
+            crate::convert_sample_json(r#"[{"id": 1, "userName": "a", "tags": ["x"]}]"#).unwrap();
+
+        let result = generate_rust_with_options(&structure, "Account", &options);
+
+        assert!(result.contains(
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow, \
+             diesel::Queryable, diesel::Selectable)]\n\
+             #[diesel(table_name = crate::schema::accounts)]\n\
+             pub struct Account {"
+        ));
+
+        // Nested values don't fit in columns
+        let structure =
+            crate::convert_sample_json(r#"{"id": 1, "address": {"city": "a"}}"#).unwrap();
+        let result = generate_rust_with_options(&structure, "Account", &options);
+        assert!(!result.contains("sqlx"));
+        assert!(!result.contains("diesel"));
+    }
+
+    #[test]
+    fn reorders_fields_to_save_padding() {
+        let mut options = ConvertOptions::default();
+        options.set_reorder_fields(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"{"isActive": true, "id": 1, "verified": false, "tags": {"a": 1}}"#,
+            &options,
+        )
+        .unwrap();
+
+        let result = conversion_to_rust(&conversion, "Account", &options);
+        assert!(result.contains(
+            "/// Fields are declared by alignment with `#[repr(C)]`, saving an estimated 8\n\
+             /// bytes. The sample has them in the order `isActive`, `id`, `verified`, `tags`.\n\
+             #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+             #[repr(C)]\n\
+             pub struct Account {\n    \
+             pub id: i64,\n    \
+             pub tags: Tags,\n    \
+             #[serde(rename = \"isActive\")]\n    \
+             pub is_active: bool,\n    \
+             pub verified: bool,\n\
+             }"
+        ));
+        // Nothing is saved reordering the fields of `Tags`
+        assert!(result.contains("pub struct Tags {\n    pub a: i64,\n}"));
+        assert_eq!(
+            field_order_savings(&conversion, "Account", &options),
+            vec![("Account".to_string(), 8)]
+        );
+
+        let (_, source_map) = conversion_to_rust_with_source_map(&conversion, "Account", &options);
+        let lines: Vec<&str> = result.lines().collect();
+        let (line, _) = source_map
+            .iter()
+            .find(|(_, pointer)| pointer == "/isActive")
+            .unwrap();
+        assert_eq!(lines[*line - 1], "    #[serde(rename = \"isActive\")]");
+    }
+
+    #[test]
+    fn estimates_type_sizes() {
+        let options = ConvertOptions::default();
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[
+                {"id": 1, "events": [1, {"a": "x", "b": "x", "c": "x", "d": "x", "e": "x", "f": "x"}],
+                 "billing": null},
+                {"id": 2, "events": [], "billing": {"a": "x", "b": "x", "c": "x", "d": "x", "e": "x"}}
+            ]"#,
+            &options,
+        )
+        .unwrap();
+
+        let (sizes, warnings) = type_sizes(&conversion, "Account", &options);
+        let sizes: Vec<(&str, usize)> = sizes.iter().map(|s| (s.name.as_str(), s.size)).collect();
+        assert_eq!(
+            sizes,
+            vec![
+                ("Account", 152),
+                ("Event", 152),
+                ("EventsObject", 144),
+                ("Billing", 120)
+            ]
+        );
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "/*/events/*");
+        assert!(warnings[0]
+            .message
+            .starts_with("every `Event` takes an estimated 152 bytes"));
+
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"billing": null}, {"billing": {"a": "x", "b": "x", "c": "x", "d": "x", "e": "x", "f": "x"}}]"#,
+            &options,
+        )
+        .unwrap();
+        let (_, warnings) = type_sizes(&conversion, "Account", &options);
+        assert_eq!(
+            warnings,
+            vec![Warning::new(
+                "/*/billing",
+                "`billing` is a `Option<Billing>` of an estimated 144 bytes, which it takes \
+                 even when it is `None`; box it with `codegen.box_threshold = 128`, or \
+                 generate it as `serde_json::Value` with \
+                 `inference.force_value = [\"/*/billing\"]`"
+            )]
+        );
+    }
+
+    #[test]
+    fn boxes_large_optional_fields() {
+        let mut options = ConvertOptions::default();
+        options.set_box_threshold(Some(128));
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[
+                {"note": null, "billing": null},
+                {"note": "x", "billing": {"a": "x", "b": "x", "c": "x", "d": "x", "e": "x", "f": "x"}}
+            ]"#,
+            &options,
+        )
+        .unwrap();
+
+        let result = conversion_to_rust(&conversion, "Account", &options);
+        assert!(result.contains(
+            "pub struct Account {\n    \
+             pub note: Option<String>,\n    \
+             pub billing: Option<Box<Billing>>,\n\
+             }"
+        ));
+        let (sizes, warnings) = type_sizes(&conversion, "Account", &options);
+        assert_eq!(sizes[0].size, 144);
+        assert_eq!(sizes[1].size, 32);
+        assert_eq!(warnings, vec![]);
+    }
+
+    #[test]
+    fn names_the_elements_of_array_roots() {
+        let mut options = ConvertOptions::default();
+        options.set_array_root(ArrayRoot::Alias);
+        let structure = crate::convert_sample_json(r#"[{"id": 1}, {"id": 2}]"#).unwrap();
+
+        let result = generate_rust_with_options(&structure, "Users", &options);
+        assert!(result.contains("pub struct User {\n    pub id: i64,\n}"));
+        assert!(result.ends_with("\npub type Users = Vec<User>;\n"));
+
+        let result = generate_rust_with_options(&structure, "Root", &options);
+        assert!(result.contains("pub struct RootElement {"));
+        assert!(result.ends_with("\npub type Root = Vec<RootElement>;\n"));
+
+        options
+            .set_array_root(ArrayRoot::Element)
+            .set_root_alias(true);
+        let result = generate_rust_with_options(&structure, "Users", &options);
+        assert!(result.contains("pub struct User {"));
+        assert!(!result.contains("pub type"));
+
+        // Arrays of other values are aliased too
+        let structure = crate::convert_sample_json(r#"["a", "b"]"#).unwrap();
+        options
+            .set_array_root(ArrayRoot::Alias)
+            .set_root_alias(false);
+        let result = generate_rust_with_options(&structure, "Names", &options);
+        assert_eq!(result, "pub type Names = Vec<String>;\n");
+
+        // Roots that aren't arrays of objects are left alone
+        let structure = crate::convert_sample_json(r#"{"id": 1}"#).unwrap();
+        let result = generate_rust_with_options(&structure, "Users", &options);
+        assert!(result.contains("pub struct Users {"));
+    }
+
+    #[test]
+    fn comments_out_fields_of_unknown_type() {
+        let mut options = ConvertOptions::default();
+        options.set_comment_unknown_fields(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            "{\n  \"id\": 1,\n  \"deletedAt\": null,\n  \"tags\": []\n}",
+            &options,
+        )
+        .unwrap();
+
+        let result = conversion_to_rust(&conversion, "Root", &options);
+
+        assert!(result.contains(
+            "pub struct Root {\n    \
+             pub id: i64,\n    \
+             // TODO: `deletedAt` on line 3 was only null in the sample, so its type is unknown\n    \
+             // #[serde(rename = \"deletedAt\")]\n    \
+             // pub deleted_at: Option<serde_json::Value>,\n    \
+             // TODO: `tags` on line 4 was only an empty array in the sample, so its type is unknown\n    \
+             // pub tags: Vec<serde_json::Value>,\n}"
+        ));
+    }
+
+    #[test]
+    fn generates_display_impls() {
+        let mut options = ConvertOptions::default();
+        options.set_display_impls(true);
+        let structure = convert_sample_json(
+            r#"{"title": "Hi", "body": "...", "id": 7, "author_id": 5, "views": 3,
+                "stats": {"likes": 1.5, "shares": 2, "tags": ["a"]}, "meta": {"tags": ["b"]}}"#,
+        )
+        .unwrap();
+        let result = generate_rust_with_options(&structure, "Post", &options);
+
+        assert_eq!(
+            &result[result.find("\nimpl").unwrap()..],
+            r#"
+impl std::fmt::Display for Post {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Post(id={}, title={:?}, author_id={})", self.id, self.title, self.author_id)
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Stats(likes={}, shares={})", self.likes, self.shares)
+    }
+}
+"#
+        );
+        assert!(!result.contains("for Meta"));
+    }
+
+    #[test]
+    fn derives_ordering_where_every_field_supports_it() {
+        let mut options = ConvertOptions::default();
+        options.set_ordering_derives(true);
+        let structure = convert_sample_json(
+            r#"{"name": "a", "tags": [{"id": 1, "label": null}], "price": {"amount": 1.5},
+                "owner": {"contact": {"email": "a@b.io"}, "extra": {"score": 0.5}},
+                "value": [1, "a"]}"#,
+        )
+        .unwrap();
+        let result = generate_rust_with_options(&structure, "Item", &options);
+        let derives = |name: &str| {
+            let end = result.find(&format!("pub struct {} ", name)).unwrap();
+            let start = result[..end].rfind("#[derive(").unwrap();
+            result[start..end].to_string()
+        };
+
+        assert_eq!(
+            derives("Tag"),
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n"
+        );
+        assert_eq!(
+            derives("Contact"),
+            "#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]\n"
+        );
+        // Floats anywhere below a type rule it out
+        assert!(!derives("Price").contains("Ord"));
+        assert!(!derives("Owner").contains("Ord"));
+        assert!(!derives("Item").contains("Ord"));
+        assert!(result.contains(
+            "#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]\n\
+             #[serde(untagged)]\npub enum Value {"
+        ));
+    }
+
+    #[test]
+    fn generates_fixed_size_arrays() {
+        let mut options = ConvertOptions::default();
+        options.set_fixed_size_arrays(Some(2));
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"color": [255, 0, 0], "path": [[0.5, 1], [2, 3]], "sizes": [1, 2], "once": [1]},
+               {"color": [0, 0, 255], "path": [], "sizes": [1]},
+               {"color": null, "path": [[4, 5]], "sizes": [1, 2]}]"#,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            conversion_to_rust(&conversion, "Shape", &options),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shape {
+    pub color: Option<[i64; 3]>,
+    pub path: Vec<[f64; 2]>,
+    pub sizes: Vec<i64>,
+    pub once: Option<Vec<i64>>,
+}
+"#
+        );
+
+        options.set_fixed_size_arrays(Some(3));
+        let result = conversion_to_rust(&conversion, "Shape", &options);
+        assert!(result.contains("    pub color: Option<Vec<i64>>,\n"));
+        assert!(result.contains("    pub path: Vec<[f64; 2]>,\n"));
+        options.set_fixed_size_arrays(None);
+        let result = conversion_to_rust(&conversion, "Shape", &options);
+        assert!(result.contains("    pub path: Vec<Vec<f64>>,\n"));
+    }
+
+    #[test]
+    fn generates_sets_for_unique_elements() {
+        let mut options = ConvertOptions::default();
+        options.add_set_field("*tags").add_set_field("scores");
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"[{"tags": ["a", "b"], "scores": [1, 1], "ids": [1, 2], "user_tags": [1, 2]},
+               {"tags": ["b"], "scores": [2], "ids": [3], "user_tags": null}]"#,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            conversion_to_rust(&conversion, "Post", &options),
+            r#"use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Post {
+    pub tags: BTreeSet<String>,
+    pub scores: Vec<i64>,
+    pub ids: Vec<i64>,
+    pub user_tags: Option<BTreeSet<i64>>,
+}
+"#
+        );
+
+        options.set_hash_sets(true);
+        let result = conversion_to_rust(&conversion, "Post", &options);
+        assert!(result.contains("use std::collections::HashSet;\n"));
+        assert!(result.contains("    pub tags: HashSet<String>,\n"));
+        // Without stats nothing is known to be unique
+        let result = generate_rust_with_options(&conversion.structure, "Post", &options);
+        assert!(result.contains("    pub tags: Vec<String>,\n"));
+    }
+
+    #[test]
+    fn allows_the_clippy_lints_the_json_causes() {
+        let fields = |names: &[&str], field_type: &str| -> Vec<(String, String)> {
+            names
+                .iter()
+                .map(|name| (name.to_string(), field_type.to_string()))
+                .collect()
+        };
+
+        assert_eq!(
+            struct_lints("Flags", &fields(&["a", "b", "c", "d"], "bool")),
+            vec!["clippy::struct_excessive_bools"]
+        );
+        assert_eq!(
+            struct_lints("Flags", &fields(&["a", "b", "c"], "bool")),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            struct_lints("UserRole", &fields(&["user_role_id", "name"], "i64")),
+            vec!["clippy::struct_field_names"]
+        );
+        assert_eq!(
+            struct_lints("Point", &fields(&["x_pos", "y_pos", "z_pos"], "f64")),
+            vec!["clippy::struct_field_names"]
+        );
+        assert_eq!(
+            struct_lints("Point", &fields(&["x_pos", "y_pos"], "f64")),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn documents_types_and_fields_with_their_sample_location() {
+        let mut options = ConvertOptions::default();
+        options.set_sample_locations(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            "{\n  \"id\": 1,\n  \"owner\": {\n    \"name\": \"a\"\n  }\n}",
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            conversion_to_rust(&conversion, "Root", &options),
+            r#"use serde::{Deserialize, Serialize};
+
+/// Line 1 of the sample
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    /// `/id`, line 2 of the sample
+    pub id: i64,
+    /// `/owner`, line 3 of the sample
+    pub owner: Owner,
+}
+
+/// `/owner`, line 3 of the sample
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Owner {
+    /// `/owner/name`, line 4 of the sample
+    pub name: String,
+}
+"#
+        )
+    }
+
+    #[test]
+    fn documents_fields_with_the_descriptions_of_the_sample() {
+        let mut options = ConvertOptions::default();
+        options.set_field_descriptions(true);
+        let conversion = crate::convert_sample_json_with_warnings(
+            r#"{"price": 10, "price_description": "In cents\nof the currency",
+                "items": [{"description": "A line of the order", "sku": "a"}]}"#,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            conversion_to_rust(&conversion, "Order", &options),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    /// In cents
+    /// of the currency
+    pub price: i64,
+    pub items: Vec<Item>,
+}
+
+/// A line of the order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    pub sku: String,
+}
+"#
+        );
+        assert!(conversion
+            .stats
+            .iter()
+            .all(|s| !s.path.contains("description")));
+    }
+}
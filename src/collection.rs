@@ -0,0 +1,340 @@
+use crate::codegen::{generate_rust_module, Direction};
+use crate::naming::{field_name, unique_name};
+use crate::options::ConvertOptions;
+use crate::parser::{parse_json, JsonNode};
+use crate::shared::Error;
+use crate::{convert_nodes, JsonPair, JsonStructure};
+
+/// The types inferred for one saved request of an API client collection.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CollectionRequest {
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    /// Merged from the json bodies of the request and its saved examples
+    pub request: Option<JsonStructure>,
+    /// Merged from the json bodies of the saved example responses
+    pub response: Option<JsonStructure>,
+}
+
+/// The samples found for a request, before they are inferred.
+struct Samples<'a> {
+    name: String,
+    method: String,
+    url: String,
+    requests: Vec<JsonNode>,
+    responses: Vec<JsonNode>,
+    options: &'a ConvertOptions,
+}
+
+impl<'a> Samples<'a> {
+    fn new(request: &JsonNode, name: &str, options: &'a ConvertOptions) -> Samples<'a> {
+        let url = match request.get("url") {
+            Some(JsonNode::String(url)) => url.clone(),
+            // Postman urls can also be objects, with the full url as `raw`
+            Some(url) => url
+                .get("raw")
+                .and_then(JsonNode::as_str)
+                .unwrap_or("")
+                .to_string(),
+            None => String::new(),
+        };
+        Samples {
+            name: name.to_string(),
+            method: request
+                .get("method")
+                .and_then(JsonNode::as_str)
+                .unwrap_or("GET")
+                .to_string(),
+            url,
+            requests: Vec::new(),
+            responses: Vec::new(),
+            options,
+        }
+    }
+
+    fn infer(&self, bodies: &[JsonNode]) -> Result<Option<JsonStructure>, Error> {
+        if bodies.is_empty() {
+            return Ok(None);
+        }
+        let nodes: Vec<&JsonNode> = bodies.iter().collect();
+        Ok(Some(convert_nodes(&nodes, self.options)?.structure))
+    }
+
+    fn finish(self) -> Result<CollectionRequest, Error> {
+        Ok(CollectionRequest {
+            request: self.infer(&self.requests)?,
+            response: self.infer(&self.responses)?,
+            name: self.name,
+            method: self.method,
+            url: self.url,
+        })
+    }
+}
+
+/// Parses text that may or may not be json, such as a saved response body.
+fn parse_body(text: Option<&str>) -> Option<JsonNode> {
+    parse_json(text?).ok()
+}
+
+/// The json body of a Postman request, which is kept as raw text.
+fn postman_body(request: &JsonNode) -> Option<JsonNode> {
+    let body = request.get("body")?;
+    if body.get("mode").and_then(JsonNode::as_str) != Some("raw") {
+        return None;
+    }
+    parse_body(body.get("raw").and_then(JsonNode::as_str))
+}
+
+/// Collects the requests of a Postman collection, including those in folders.
+fn read_postman<'a>(
+    items: &[JsonNode],
+    options: &'a ConvertOptions,
+    requests: &mut Vec<Samples<'a>>,
+) {
+    for item in items {
+        if let Some(JsonNode::Array(children)) = item.get("item") {
+            read_postman(children, options, requests);
+            continue;
+        }
+        let request = match item.get("request") {
+            Some(request) => request,
+            None => continue,
+        };
+        let name = item.get("name").and_then(JsonNode::as_str).unwrap_or("");
+        let mut samples = Samples::new(request, name, options);
+        samples.requests.extend(postman_body(request));
+        if let Some(JsonNode::Array(examples)) = item.get("response") {
+            for example in examples {
+                if let Some(original) = example.get("originalRequest") {
+                    samples.requests.extend(postman_body(original));
+                }
+                samples
+                    .responses
+                    .extend(parse_body(example.get("body").and_then(JsonNode::as_str)));
+            }
+        }
+        requests.push(samples);
+    }
+}
+
+/// Collects the requests of an Insomnia export. Exports don't include
+/// responses, so only request bodies are found.
+fn read_insomnia<'a>(
+    resources: &[JsonNode],
+    options: &'a ConvertOptions,
+    requests: &mut Vec<Samples<'a>>,
+) {
+    for resource in resources {
+        if resource.get("_type").and_then(JsonNode::as_str) != Some("request") {
+            continue;
+        }
+        let name = resource
+            .get("name")
+            .and_then(JsonNode::as_str)
+            .unwrap_or("");
+        let mut samples = Samples::new(resource, name, options);
+        if let Some(body) = resource.get("body") {
+            let is_json = body
+                .get("mimeType")
+                .and_then(JsonNode::as_str)
+                .is_some_and(|m| m.contains("json"));
+            if is_json {
+                samples
+                    .requests
+                    .extend(parse_body(body.get("text").and_then(JsonNode::as_str)));
+            }
+        }
+        requests.push(samples);
+    }
+}
+
+/// Reads a Postman collection (v2.0 or v2.1) or an Insomnia export, and infers
+/// request and response types for every request in it, from the request bodies
+/// and saved example responses.
+pub fn convert_collection(
+    collection: &str,
+    options: &ConvertOptions,
+) -> Result<Vec<CollectionRequest>, Error> {
+    let root = parse_json(collection)?;
+    let mut requests = Vec::new();
+    match (root.get("item"), root.get("resources")) {
+        (Some(JsonNode::Array(items)), _) => read_postman(items, options, &mut requests),
+        (_, Some(JsonNode::Array(resources))) => read_insomnia(resources, options, &mut requests),
+        _ => {
+            return Err(Error::InvalidSchema(
+                "Expected a Postman collection or an Insomnia export".to_string(),
+            ))
+        }
+    }
+    requests.into_iter().map(Samples::finish).collect()
+}
+
+/// Generates a module for every request of a collection with json bodies, named
+/// after the request, holding a `Request` type for the request body and a
+/// `Response` type for the example responses.
+pub fn collection_to_rust(collection: &str, options: &ConvertOptions) -> Result<String, Error> {
+    let mut output = String::new();
+    let mut modules: Vec<String> = Vec::new();
+    for request in convert_collection(collection, options)? {
+        let roots: Vec<(JsonPair, Direction)> = [
+            ("Request", Direction::Request, &request.request),
+            ("Response", Direction::Response, &request.response),
+        ]
+        .iter()
+        .filter_map(|(name, direction, s)| {
+            s.as_ref()
+                .map(|s| (JsonPair::new(*name, s.clone()), *direction))
+        })
+        .collect();
+        if roots.is_empty() {
+            continue;
+        }
+
+        let module = match field_name(&request.name) {
+            name if name.is_empty() => "request".to_string(),
+            name => name,
+        };
+        let module = unique_name(module, &mut modules);
+        let doc = format!("{} (`{} {}`)", request.name, request.method, request.url);
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&generate_rust_module(&module, &doc, &roots, options));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POSTMAN: &str = r#"{
+        "info": {"name": "Shop", "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"},
+        "item": [
+            {"name": "Users", "item": [
+                {"name": "List users",
+                 "request": {"method": "GET", "url": {"raw": "{{base}}/users"}},
+                 "response": [
+                    {"name": "ok", "code": 200, "body": "[{\"id\": 1, \"name\": \"a\"}]"},
+                    {"name": "empty", "code": 200, "body": "[{\"id\": 2}]"},
+                    {"name": "html", "code": 500, "body": "<html></html>"}
+                 ]}
+            ]},
+            {"name": "Create user",
+             "request": {"method": "POST", "url": "{{base}}/users",
+                "body": {"mode": "raw", "raw": "{\"name\": \"a\"}"}},
+             "response": [
+                {"name": "created", "originalRequest": {"method": "POST", "url": "{{base}}/users",
+                    "body": {"mode": "raw", "raw": "{\"name\": \"b\", \"admin\": true}"}},
+                 "body": "{\"id\": 3}"}
+             ]},
+            {"name": "Health", "request": {"method": "GET", "url": "{{base}}/health"}}
+        ]
+    }"#;
+
+    #[test]
+    fn reads_postman_collections() {
+        let requests = convert_collection(POSTMAN, &ConvertOptions::default()).unwrap();
+
+        assert_eq!(
+            requests,
+            vec![
+                CollectionRequest {
+                    name: "List users".into(),
+                    method: "GET".into(),
+                    url: "{{base}}/users".into(),
+                    request: None,
+                    response: Some(JsonStructure::array(
+                        JsonStructure::object()
+                            .field("id", JsonStructure::int())
+                            .optional_field("name", JsonStructure::string())
+                            .build()
+                    )),
+                },
+                CollectionRequest {
+                    name: "Create user".into(),
+                    method: "POST".into(),
+                    url: "{{base}}/users".into(),
+                    request: Some(
+                        JsonStructure::object()
+                            .field("name", JsonStructure::string())
+                            .optional_field("admin", JsonStructure::bool())
+                            .build()
+                    ),
+                    response: Some(
+                        JsonStructure::object()
+                            .field("id", JsonStructure::int())
+                            .build()
+                    ),
+                },
+                CollectionRequest {
+                    name: "Health".into(),
+                    method: "GET".into(),
+                    url: "{{base}}/health".into(),
+                    request: None,
+                    response: None,
+                },
+            ]
+        )
+    }
+
+    #[test]
+    fn reads_insomnia_exports() {
+        let requests = convert_collection(
+            r#"{"_type": "export", "resources": [
+                {"_type": "workspace", "name": "Shop"},
+                {"_type": "request", "name": "Login", "method": "POST", "url": "/login",
+                 "body": {"mimeType": "application/json", "text": "{\"user\": \"a\"}"}}
+            ]}"#,
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].request,
+            Some(
+                JsonStructure::object()
+                    .field("user", JsonStructure::string())
+                    .build()
+            )
+        );
+    }
+
+    #[test]
+    fn generates_a_module_per_request() {
+        let result = collection_to_rust(POSTMAN, &ConvertOptions::default()).unwrap();
+
+        assert_eq!(
+            result,
+            r#"/// List users (`GET {{base}}/users`)
+pub mod list_users {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Response {
+        pub id: i64,
+        pub name: Option<String>,
+    }
+}
+
+/// Create user (`POST {{base}}/users`)
+pub mod create_user {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Request {
+        pub name: String,
+        pub admin: Option<bool>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Response {
+        pub id: i64,
+    }
+}
+"#
+        )
+    }
+}
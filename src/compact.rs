@@ -0,0 +1,828 @@
+use crate::accumulator::VariantKind;
+use crate::shared::Error;
+use crate::{inference, JsonPair, JsonStructure, JsonValue, RustType};
+use alloc::{string::String, vec, vec::Vec};
+use core::convert::TryFrom;
+use core::mem::size_of;
+
+/// Heap memory held by a structure.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub struct MemoryUsage {
+    /// The number of values described, such as objects, arrays and strings
+    pub nodes: usize,
+    /// The number of heap allocations
+    pub allocations: usize,
+    /// The bytes allocated on the heap, not counting the allocator's own
+    /// overhead
+    pub bytes: usize,
+}
+
+impl MemoryUsage {
+    fn add<T>(&mut self, capacity: usize) {
+        if capacity > 0 {
+            self.allocations += 1;
+            self.bytes += capacity * size_of::<T>();
+        }
+    }
+}
+
+impl JsonStructure {
+    /// How much heap memory the structure holds.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        count_structure(self, &mut usage);
+        usage
+    }
+
+    /// The structure in the compact form of `CompactStructure`, which fails
+    /// if it has more nodes, fields, variants or keys than fit in a `u32`.
+    pub fn compact(&self) -> Result<CompactStructure, Error> {
+        let mut compact = CompactStructure::default();
+        compact.root = Some(compact.add(self)?);
+        compact.live_nodes = compact.nodes.len();
+        compact.nodes.shrink_to_fit();
+        compact.fields.shrink_to_fit();
+        compact.variants.shrink_to_fit();
+        compact.strings.shrink_to_fit();
+        compact.sorted_strings.shrink_to_fit();
+        Ok(compact)
+    }
+}
+
+fn count_structure(structure: &JsonStructure, usage: &mut MemoryUsage) {
+    usage.nodes += 1;
+    match structure {
+        JsonStructure::Array(inner) | JsonStructure::Optional(inner) => {
+            usage.add::<JsonStructure>(1);
+            count_structure(inner, usage);
+        }
+        JsonStructure::Dictionary(pair) => count_pair(pair, usage),
+        JsonStructure::Object(pairs) => {
+            usage.add::<JsonPair>(pairs.capacity());
+            for pair in pairs {
+                count_pair(pair, usage);
+            }
+        }
+        JsonStructure::Union(variants) => {
+            usage.add::<JsonStructure>(variants.capacity());
+            for variant in variants {
+                count_structure(variant, usage);
+            }
+        }
+        JsonStructure::Value(JsonValue::Custom(rust_type)) => {
+            usage.add::<u8>(rust_type.path.capacity())
+        }
+        JsonStructure::Value(_) | JsonStructure::Unknown => {}
+    }
+}
+
+fn count_pair(pair: &JsonPair, usage: &mut MemoryUsage) {
+    usage.add::<u8>(pair.key.capacity());
+    usage.add::<JsonStructure>(1);
+    count_structure(&pair.value, usage);
+}
+
+/// A structure kept in a few flat arrays rather than a tree of boxes, with
+/// every key and type path stored once, for holding large merged structures
+/// with far fewer allocations. Made with `JsonStructure::compact`, or by
+/// merging structures into the default, unknown, one with `merge`.
+#[derive(Debug, Clone, Default)]
+pub struct CompactStructure {
+    // Children come before their parents, and nodes left behind by merges
+    // aren't reachable from the root until garbage is collected
+    nodes: Vec<Node>,
+    // The key and node of every field, with those of each object together
+    fields: Vec<(u32, u32)>,
+    // The nodes of every union variant, with those of each union together
+    variants: Vec<u32>,
+    // Keys and type paths, each once
+    strings: Vec<String>,
+    // The indices of `strings`, sorted by the string, to find them again
+    sorted_strings: Vec<u32>,
+    root: Option<u32>,
+    // The nodes after garbage was last collected
+    live_nodes: usize,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum Node {
+    Array(u32),
+    Dictionary { key: u32, value: u32 },
+    Object { start: u32, len: u32 },
+    String,
+    Float,
+    Int,
+    Bool,
+    Custom(u32),
+    Optional(u32),
+    Union { start: u32, len: u32 },
+    Unknown,
+}
+
+/// The lengths of the arrays of a structure before a merge, to go back to if
+/// it fails or changes nothing.
+struct Checkpoint {
+    nodes: usize,
+    fields: usize,
+    variants: usize,
+    strings: usize,
+}
+
+/// Garbage is only collected once there are at least this many nodes.
+const MIN_COLLECTED_NODES: usize = 64;
+
+/// The index of the element after the first `len` of an array.
+fn index(len: usize) -> Result<u32, Error> {
+    u32::try_from(len).map_err(|_| Error::StructureTooLarge {
+        limit: u32::MAX as usize,
+    })
+}
+
+impl PartialEq for CompactStructure {
+    fn eq(&self, other: &CompactStructure) -> bool {
+        self.to_structure() == other.to_structure()
+    }
+}
+
+impl Eq for CompactStructure {}
+
+impl CompactStructure {
+    /// The structure as a tree again.
+    pub fn to_structure(&self) -> JsonStructure {
+        match self.root {
+            Some(root) => self.structure(root),
+            None => JsonStructure::Unknown,
+        }
+    }
+
+    /// Merges `structure` into this one, the way `SchemaAccumulator` merges
+    /// the structures of samples, without turning this one into a tree.
+    ///
+    /// Fails, leaving this structure as it was, if the merged structure has
+    /// more nodes, fields, variants or keys than fit in a `u32`.
+    pub fn merge(&mut self, structure: &JsonStructure) -> Result<(), Error> {
+        let checkpoint = self.checkpoint();
+        let merged = self.add(structure).and_then(|added| match self.root {
+            Some(root) => self.merge_nodes(root, added),
+            None => Ok(added),
+        });
+        match merged {
+            Ok(merged) if self.root.is_some_and(|root| self.same(root, merged)) => {
+                self.rollback(checkpoint);
+            }
+            Ok(merged) => {
+                self.root = Some(merged);
+                if self.nodes.len() > 2 * self.live_nodes.max(MIN_COLLECTED_NODES) {
+                    self.collect_garbage()?;
+                }
+            }
+            Err(error) => {
+                self.rollback(checkpoint);
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// How much heap memory the structure holds, including nodes left behind
+    /// by merges that haven't been collected yet.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage {
+            nodes: self.root.map_or(0, |root| self.count(root)),
+            ..MemoryUsage::default()
+        };
+        usage.add::<Node>(self.nodes.capacity());
+        usage.add::<(u32, u32)>(self.fields.capacity());
+        usage.add::<u32>(self.variants.capacity());
+        usage.add::<String>(self.strings.capacity());
+        usage.add::<u32>(self.sorted_strings.capacity());
+        for s in &self.strings {
+            usage.add::<u8>(s.capacity());
+        }
+        usage
+    }
+
+    fn count(&self, index: u32) -> usize {
+        1 + match self.nodes[index as usize] {
+            Node::Array(inner) | Node::Optional(inner) => self.count(inner),
+            Node::Dictionary { value, .. } => self.count(value),
+            Node::Object { start, len } => self
+                .fields_of(start, len)
+                .iter()
+                .map(|(_, value)| self.count(*value))
+                .sum(),
+            Node::Union { start, len } => self
+                .variants_of(start, len)
+                .iter()
+                .map(|variant| self.count(*variant))
+                .sum(),
+            _ => 0,
+        }
+    }
+
+    fn structure(&self, index: u32) -> JsonStructure {
+        let string = |index: u32| self.strings[index as usize].as_str();
+        match self.nodes[index as usize] {
+            Node::Array(inner) => JsonStructure::array(self.structure(inner)),
+            Node::Dictionary { key, value } => {
+                JsonStructure::Dictionary(JsonPair::new(string(key), self.structure(value)))
+            }
+            Node::Object { start, len } => JsonStructure::Object(
+                self.fields_of(start, len)
+                    .iter()
+                    .map(|(key, value)| JsonPair::new(string(*key), self.structure(*value)))
+                    .collect(),
+            ),
+            Node::String => JsonStructure::string(),
+            Node::Float => JsonStructure::float(),
+            Node::Int => JsonStructure::int(),
+            Node::Bool => JsonStructure::bool(),
+            Node::Custom(path) => JsonStructure::custom(RustType::new(string(path))),
+            Node::Optional(inner) => JsonStructure::optional(self.structure(inner)),
+            Node::Union { start, len } => JsonStructure::Union(
+                self.variants_of(start, len)
+                    .iter()
+                    .map(|variant| self.structure(*variant))
+                    .collect(),
+            ),
+            Node::Unknown => JsonStructure::Unknown,
+        }
+    }
+
+    fn fields_of(&self, start: u32, len: u32) -> &[(u32, u32)] {
+        &self.fields[start as usize..start as usize + len as usize]
+    }
+
+    fn variants_of(&self, start: u32, len: u32) -> &[u32] {
+        &self.variants[start as usize..start as usize + len as usize]
+    }
+
+    fn intern(&mut self, s: &str) -> Result<u32, Error> {
+        let strings = &self.strings;
+        match self
+            .sorted_strings
+            .binary_search_by(|index| strings[*index as usize].as_str().cmp(s))
+        {
+            Ok(position) => Ok(self.sorted_strings[position]),
+            Err(position) => {
+                let index = index(self.strings.len())?;
+                self.strings.push(s.into());
+                self.sorted_strings.insert(position, index);
+                Ok(index)
+            }
+        }
+    }
+
+    fn push(&mut self, node: Node) -> Result<u32, Error> {
+        let index = index(self.nodes.len())?;
+        self.nodes.push(node);
+        Ok(index)
+    }
+
+    fn push_object(&mut self, fields: Vec<(u32, u32)>) -> Result<u32, Error> {
+        let start = index(self.fields.len())?;
+        let len = index(fields.len())?;
+        index(self.fields.len() + fields.len())?;
+        self.fields.extend(fields);
+        self.push(Node::Object { start, len })
+    }
+
+    fn push_union(&mut self, variants: Vec<u32>) -> Result<u32, Error> {
+        let start = index(self.variants.len())?;
+        let len = index(variants.len())?;
+        index(self.variants.len() + variants.len())?;
+        self.variants.extend(variants);
+        self.push(Node::Union { start, len })
+    }
+
+    fn add(&mut self, structure: &JsonStructure) -> Result<u32, Error> {
+        let node = match structure {
+            JsonStructure::Array(inner) => Node::Array(self.add(inner)?),
+            JsonStructure::Dictionary(pair) => Node::Dictionary {
+                key: self.intern(&pair.key)?,
+                value: self.add(&pair.value)?,
+            },
+            JsonStructure::Object(pairs) => {
+                let fields = pairs
+                    .iter()
+                    .map(|pair| Ok((self.intern(&pair.key)?, self.add(&pair.value)?)))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                return self.push_object(fields);
+            }
+            JsonStructure::Value(JsonValue::String) => Node::String,
+            JsonStructure::Value(JsonValue::Float) => Node::Float,
+            JsonStructure::Value(JsonValue::Int) => Node::Int,
+            JsonStructure::Value(JsonValue::Bool) => Node::Bool,
+            JsonStructure::Value(JsonValue::Custom(rust_type)) => {
+                Node::Custom(self.intern(&rust_type.path)?)
+            }
+            JsonStructure::Optional(inner) => Node::Optional(self.add(inner)?),
+            JsonStructure::Union(variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| self.add(variant))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                return self.push_union(variants);
+            }
+            JsonStructure::Unknown => Node::Unknown,
+        };
+        self.push(node)
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            nodes: self.nodes.len(),
+            fields: self.fields.len(),
+            variants: self.variants.len(),
+            strings: self.strings.len(),
+        }
+    }
+
+    fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.nodes.truncate(checkpoint.nodes);
+        self.fields.truncate(checkpoint.fields);
+        self.variants.truncate(checkpoint.variants);
+        self.strings.truncate(checkpoint.strings);
+        self.sorted_strings
+            .retain(|index| (*index as usize) < checkpoint.strings);
+    }
+
+    /// Copies the nodes reachable from the root into new arrays, dropping the
+    /// rest.
+    fn collect_garbage(&mut self) -> Result<(), Error> {
+        let mut collected = CompactStructure::default();
+        if let Some(root) = self.root {
+            let mut copies = vec![None; self.nodes.len()];
+            collected.root = Some(collected.copy(self, root, &mut copies)?);
+        }
+        collected.live_nodes = collected.nodes.len();
+        *self = collected;
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        from: &CompactStructure,
+        index: u32,
+        copies: &mut Vec<Option<u32>>,
+    ) -> Result<u32, Error> {
+        if let Some(copy) = copies[index as usize] {
+            return Ok(copy);
+        }
+        let node = match from.nodes[index as usize] {
+            Node::Array(inner) => Node::Array(self.copy(from, inner, copies)?),
+            Node::Dictionary { key, value } => Node::Dictionary {
+                key: self.intern(&from.strings[key as usize])?,
+                value: self.copy(from, value, copies)?,
+            },
+            Node::Object { start, len } => {
+                let fields = from
+                    .fields_of(start, len)
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok((
+                            self.intern(&from.strings[*key as usize])?,
+                            self.copy(from, *value, copies)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let copy = self.push_object(fields)?;
+                copies[index as usize] = Some(copy);
+                return Ok(copy);
+            }
+            Node::Custom(path) => Node::Custom(self.intern(&from.strings[path as usize])?),
+            Node::Optional(inner) => Node::Optional(self.copy(from, inner, copies)?),
+            Node::Union { start, len } => {
+                let variants = from
+                    .variants_of(start, len)
+                    .iter()
+                    .map(|variant| self.copy(from, *variant, copies))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let copy = self.push_union(variants)?;
+                copies[index as usize] = Some(copy);
+                return Ok(copy);
+            }
+            leaf => leaf,
+        };
+        let copy = self.push(node)?;
+        copies[index as usize] = Some(copy);
+        Ok(copy)
+    }
+}
+
+/// Merging nodes, the way `accumulator::merge_structures` merges structures,
+/// where a node merged with a node of the same structure is kept as it was.
+impl CompactStructure {
+    fn same(&self, a: u32, b: u32) -> bool {
+        if a == b {
+            return true;
+        }
+        match (self.nodes[a as usize], self.nodes[b as usize]) {
+            (Node::Array(a), Node::Array(b)) | (Node::Optional(a), Node::Optional(b)) => {
+                self.same(a, b)
+            }
+            (
+                Node::Dictionary { key, value },
+                Node::Dictionary {
+                    key: other_key,
+                    value: other_value,
+                },
+            ) => key == other_key && self.same(value, other_value),
+            (
+                Node::Object { start, len },
+                Node::Object {
+                    start: other,
+                    len: other_len,
+                },
+            ) => {
+                len == other_len
+                    && self
+                        .fields_of(start, len)
+                        .iter()
+                        .zip(self.fields_of(other, other_len))
+                        .all(|((key, a), (other_key, b))| key == other_key && self.same(*a, *b))
+            }
+            (
+                Node::Union { start, len },
+                Node::Union {
+                    start: other,
+                    len: other_len,
+                },
+            ) => {
+                len == other_len
+                    && self
+                        .variants_of(start, len)
+                        .iter()
+                        .zip(self.variants_of(other, other_len))
+                        .all(|(a, b)| self.same(*a, *b))
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    fn variant_kind(&self, index: u32) -> Option<VariantKind> {
+        match self.nodes[index as usize] {
+            Node::Object { .. } | Node::Dictionary { .. } => Some(VariantKind::Object),
+            Node::Array(_) => Some(VariantKind::Array),
+            Node::Int | Node::Float => Some(VariantKind::Number),
+            _ if self.is_wide_int(index) => Some(VariantKind::Number),
+            Node::String | Node::Custom(_) => Some(VariantKind::String),
+            Node::Bool => Some(VariantKind::Bool),
+            Node::Optional(_) | Node::Union { .. } | Node::Unknown => None,
+        }
+    }
+
+    fn is_wide_int(&self, index: u32) -> bool {
+        matches!(
+            self.nodes[index as usize],
+            Node::Custom(path)
+                if [inference::U64, inference::I128].contains(&self.strings[path as usize].as_str())
+        )
+    }
+
+    fn make_optional(&mut self, index: u32) -> Result<u32, Error> {
+        match self.nodes[index as usize] {
+            Node::Optional(_) => Ok(index),
+            _ => self.push(Node::Optional(index)),
+        }
+    }
+
+    fn merge_nodes(&mut self, a: u32, b: u32) -> Result<u32, Error> {
+        if self.same(a, b) {
+            return Ok(a);
+        }
+        match (self.nodes[a as usize], self.nodes[b as usize]) {
+            (Node::Unknown, _) => Ok(b),
+            (_, Node::Unknown) => Ok(a),
+            (Node::Optional(inner), Node::Optional(other)) => self.merge_optional(a, inner, other),
+            (Node::Optional(inner), _) => self.merge_optional(a, inner, b),
+            (_, Node::Optional(inner)) => self.merge_optional(b, inner, a),
+            (Node::Union { start, len }, _) => {
+                let variants = self.variants_of(start, len).to_vec();
+                self.merge_variants(a, variants, b)
+            }
+            (_, Node::Union { .. }) => self.merge_variants(a, vec![a], b),
+            _ if self.variant_kind(a) == self.variant_kind(b) => self.merge_same_kind(a, b),
+            _ => self.push_union(vec![a, b]),
+        }
+    }
+
+    fn merge_optional(&mut self, optional: u32, inner: u32, other: u32) -> Result<u32, Error> {
+        match self.merge_nodes(inner, other)? {
+            merged if merged == inner => Ok(optional),
+            merged => self.make_optional(merged),
+        }
+    }
+
+    fn first_key(&self, index: u32) -> Option<u32> {
+        match self.nodes[index as usize] {
+            Node::Object { start, len } => self.fields_of(start, len).first().map(|(key, _)| *key),
+            _ => None,
+        }
+    }
+
+    fn merge_variants(
+        &mut self,
+        original: u32,
+        mut variants: Vec<u32>,
+        other: u32,
+    ) -> Result<u32, Error> {
+        let others = match self.nodes[other as usize] {
+            Node::Union { start, len } => self.variants_of(start, len).to_vec(),
+            _ => vec![other],
+        };
+        // Tagged unions, as in `accumulator::merge_variants`
+        let objects = |variants: &[u32]| {
+            variants
+                .iter()
+                .filter(|v| matches!(self.nodes[**v as usize], Node::Object { .. }))
+                .count()
+        };
+        let is_tagged = objects(&variants) > 1 || objects(&others) > 1;
+        let mut changed = false;
+        for other in others {
+            let kind = self.variant_kind(other);
+            let is_object = |index: u32| matches!(self.nodes[index as usize], Node::Object { .. });
+            let position = variants.iter().position(|v| {
+                if is_tagged && is_object(*v) && is_object(other) {
+                    self.first_key(*v) == self.first_key(other)
+                } else {
+                    self.variant_kind(*v) == kind
+                }
+            });
+            match position {
+                Some(index) => {
+                    let merged = self.merge_nodes(variants[index], other)?;
+                    changed |= merged != variants[index];
+                    variants[index] = merged;
+                }
+                None => {
+                    changed = true;
+                    variants.push(other);
+                }
+            }
+        }
+        match variants.len() {
+            1 => Ok(variants[0]),
+            _ if !changed && matches!(self.nodes[original as usize], Node::Union { .. }) => {
+                Ok(original)
+            }
+            _ => self.push_union(variants),
+        }
+    }
+
+    fn merge_same_kind(&mut self, a: u32, b: u32) -> Result<u32, Error> {
+        match (self.nodes[a as usize], self.nodes[b as usize]) {
+            (
+                Node::Object { start, len },
+                Node::Object {
+                    start: other,
+                    len: other_len,
+                },
+            ) => self.merge_objects(a, start, len, other, other_len),
+            (Node::Dictionary { key, value }, Node::Dictionary { value: other, .. }) => {
+                match self.merge_nodes(value, other)? {
+                    merged if merged == value => Ok(a),
+                    merged => self.push(Node::Dictionary { key, value: merged }),
+                }
+            }
+            (Node::Dictionary { key, value }, Node::Object { start, len })
+            | (Node::Object { start, len }, Node::Dictionary { key, value }) => {
+                let mut merged = value;
+                for (_, field) in self.fields_of(start, len).to_vec() {
+                    merged = self.merge_nodes(merged, field)?;
+                }
+                self.push(Node::Dictionary { key, value: merged })
+            }
+            (Node::Array(inner), Node::Array(other)) => match self.merge_nodes(inner, other)? {
+                merged if merged == inner => Ok(a),
+                merged => self.push(Node::Array(merged)),
+            },
+            (Node::Bool, _) => Ok(a),
+            // Different kinds of integers, as in `accumulator::merge_same_kind`
+            (x, y)
+                if x != Node::Float
+                    && y != Node::Float
+                    && (self.is_wide_int(a) || self.is_wide_int(b)) =>
+            {
+                let i128 = self.intern(inference::I128)?;
+                self.push(Node::Custom(i128))
+            }
+            (Node::Int, _) | (Node::Float, _) => self.push(Node::Float),
+            _ => self.push(Node::String),
+        }
+    }
+
+    fn merge_objects(
+        &mut self,
+        original: u32,
+        start: u32,
+        len: u32,
+        other: u32,
+        other_len: u32,
+    ) -> Result<u32, Error> {
+        let mut others: Vec<Option<(u32, u32)>> = self
+            .fields_of(other, other_len)
+            .iter()
+            .copied()
+            .map(Some)
+            .collect();
+        let mut fields = Vec::with_capacity(len as usize);
+        for (key, value) in self.fields_of(start, len).to_vec() {
+            let other = others
+                .iter_mut()
+                .find(|field| field.is_some_and(|(other_key, _)| other_key == key))
+                .and_then(Option::take);
+            let value = match other {
+                Some((_, other)) => self.merge_nodes(value, other)?,
+                None => self.make_optional(value)?,
+            };
+            fields.push((key, value));
+        }
+        for (key, value) in others.into_iter().flatten() {
+            fields.push((key, self.make_optional(value)?));
+        }
+        if fields.as_slice() == self.fields_of(start, len) {
+            return Ok(original);
+        }
+        self.push_object(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accumulator::merge_structures;
+    use crate::convert_sample_json;
+
+    fn merge_all(structures: &[JsonStructure]) -> CompactStructure {
+        let mut compact = CompactStructure::default();
+        for structure in structures {
+            compact.merge(structure).unwrap();
+        }
+        compact
+    }
+
+    #[test]
+    fn compacts_without_losing_anything() {
+        let structure = convert_sample_json(
+            r#"{"ip": "::1", "items": [{"a": null, "b": {"x": 1}}, {"a": 1.5, "c": [true, "x"]}],
+                "": {"": []}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(structure.compact().unwrap().to_structure(), structure);
+        assert_eq!(
+            JsonStructure::Unknown.compact().unwrap().to_structure(),
+            JsonStructure::Unknown
+        );
+    }
+
+    #[test]
+    fn counts_memory() {
+        let structure = JsonStructure::object()
+            .field("id", JsonStructure::int())
+            .field("tags", JsonStructure::array(JsonStructure::string()))
+            .build();
+        let usage = structure.memory_usage();
+
+        assert_eq!(usage.nodes, 4);
+        // The fields, their keys and boxed values, and the boxed array element
+        assert_eq!(usage.allocations, 6);
+
+        let rows: Vec<JsonStructure> = (0..100)
+            .map(|i| {
+                let fields = (0..10).fold(JsonStructure::object(), |object, field| {
+                    object.field(
+                        alloc::format!("field_{}", field),
+                        match i % 2 {
+                            0 => JsonStructure::int(),
+                            _ => JsonStructure::string(),
+                        },
+                    )
+                });
+                JsonStructure::array(fields.build())
+            })
+            .collect();
+        let structure = JsonStructure::Union(rows);
+        let compact = structure.compact().unwrap();
+        assert_eq!(compact.memory_usage().nodes, structure.memory_usage().nodes);
+        assert!(compact.memory_usage().allocations < 20);
+        assert!(compact.memory_usage().bytes * 2 < structure.memory_usage().bytes);
+    }
+
+    #[test]
+    fn merges_like_merging_trees() {
+        let variant = |name: &str, key: &str| {
+            JsonStructure::object()
+                .field(
+                    name,
+                    JsonStructure::object()
+                        .field(key, JsonStructure::int())
+                        .build(),
+                )
+                .build()
+        };
+        let cases: &[&[&str]] = &[
+            &[
+                r#"{"id": 1, "tags": []}"#,
+                r#"{"id": 2.5, "tags": ["a"], "note": null}"#,
+            ],
+            &[
+                r#"{"a": {"b": 1}}"#,
+                r#"{"a": null}"#,
+                r#"{"a": {"c": true}}"#,
+            ],
+            &[r#"[1, "x"]"#, r#"[{"k": 1}]"#, r#"[2.5, {"k": null}]"#],
+            &[r#"{"id": 18446744073709551615}"#, r#"{"id": 1}"#],
+            &[
+                r#"{"a": 1}"#,
+                r#"[true]"#,
+                r#"{"b": "x"}"#,
+                r#"null"#,
+                r#"[1]"#,
+            ],
+        ];
+        let mut structures: Vec<Vec<JsonStructure>> = cases
+            .iter()
+            .map(|samples| {
+                samples
+                    .iter()
+                    .map(|sample| convert_sample_json(sample).unwrap())
+                    .collect()
+            })
+            .collect();
+        structures.push(vec![
+            JsonStructure::Union(vec![variant("circle", "radius"), variant("square", "side")]),
+            variant("point", "x"),
+            variant("circle", "radius"),
+        ]);
+        structures.push(vec![
+            JsonStructure::object()
+                .field(
+                    "ids",
+                    JsonStructure::object()
+                        .field("1", JsonStructure::int())
+                        .build(),
+                )
+                .build(),
+            JsonStructure::object()
+                .field(
+                    "ids",
+                    JsonStructure::Dictionary(JsonPair::new("id", JsonStructure::float())),
+                )
+                .build(),
+        ]);
+
+        for structures in structures {
+            let expected = structures.iter().cloned().reduce(merge_structures).unwrap();
+            assert_eq!(
+                merge_all(&structures).to_structure(),
+                expected,
+                "{:?}",
+                structures
+            );
+        }
+        assert_eq!(merge_all(&[]).to_structure(), JsonStructure::Unknown);
+    }
+
+    #[test]
+    fn keeps_merges_of_the_same_shape_from_growing() {
+        let sample = |i: usize| {
+            convert_sample_json(&alloc::format!(
+                r#"{{"id": {}, "name": "x", "tags": ["a"], "parent": {{"id": 1}}}}"#,
+                i
+            ))
+            .unwrap()
+        };
+        let mut compact = merge_all(&[sample(0), convert_sample_json("{}").unwrap()]);
+        let nodes = compact.nodes.len();
+        for i in 0..1_000 {
+            compact.merge(&sample(i)).unwrap();
+        }
+        assert_eq!(compact.nodes.len(), nodes);
+
+        // Each new field leaves the object it was merged into behind, which is
+        // collected rather than kept
+        for i in 0..300 {
+            let structure = JsonStructure::object()
+                .field(alloc::format!("field_{}", i), JsonStructure::int())
+                .build();
+            compact.merge(&structure).unwrap();
+        }
+        let live = compact.memory_usage().nodes;
+        assert!(compact.nodes.len() <= 2 * live.max(MIN_COLLECTED_NODES) + live);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn errors_on_indices_too_large_for_u32() {
+        assert_eq!(index(u32::MAX as usize), Ok(u32::MAX));
+        assert_eq!(
+            index(u32::MAX as usize + 1),
+            Err(Error::StructureTooLarge {
+                limit: u32::MAX as usize
+            })
+        );
+    }
+}
@@ -0,0 +1,1393 @@
+use crate::naming::{
+    AcronymStyle, FieldNameStrategy, FullPathStrategy, ParentPrefixedStrategy, SingularizedStrategy,
+};
+use crate::tokenizer::{tokenize_json, JsonToken, JsonTokenType, Leniency};
+use crate::{
+    ArrayRoot, ConvertOptions, FloatPolicy, Inflector, IntEnumStyle, MixedNumberPolicy,
+    OutputVersion, RustType, UnionTagging, WholeFloatPolicy,
+};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Settings read from a `json2rust.toml` config file.
+///
+/// Only the subset of toml needed for the config is supported: tables,
+/// comments, and string, boolean, integer and string array values.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct Config {
+    /// `[type_mappings]`, field name globs to rust types
+    pub type_mappings: Vec<(String, String)>,
+    /// `inference.chaos_threshold`, where 0 disables the check
+    pub chaos_threshold: Option<usize>,
+    /// `inference.force_value`, json pointers always generated as `serde_json::Value`
+    pub force_value: Vec<String>,
+    /// `[tagged_unions]`, json pointers of unions to how they are tagged:
+    /// `"external"`, `"internal:<tag>"` or `"adjacent:<tag>:<content>"`
+    pub tagged_unions: Vec<(String, UnionTagging)>,
+    /// `inference.ignore_paths`, json pointers or JSONPaths of fields to leave
+    /// out
+    pub ignore_paths: Vec<String>,
+    /// `inference.ignore_keys`, globs of field keys to leave out
+    pub ignore_keys: Vec<String>,
+    /// `inference.extended_json`, reads samples as MongoDB extended json
+    pub extended_json: Option<bool>,
+    /// `inference.lenient_numbers`, accepts numbers such as `.5` and `5.`
+    pub lenient_numbers: Option<bool>,
+    /// `inference.relaxed_json`, accepts javascript object literals and python
+    /// reprs
+    pub relaxed_json: Option<bool>,
+    /// `inference.non_finite_numbers`, accepts `NaN` and `Infinity` as floats
+    pub non_finite_numbers: Option<bool>,
+    /// `inference.exponent_integers`, reads numbers such as `1e3` as integers
+    pub exponent_integers: Option<bool>,
+    /// `inference.locale_numbers`, types strings such as `"1.234,56"` as `f64`
+    pub locale_numbers: Option<bool>,
+    /// `inference.mixed_numbers`, one of `union`, `string`, `number` or `error`
+    pub mixed_numbers: Option<MixedNumberPolicy>,
+    /// `inference.whole_floats`, one of `float`, `warn` or `int`
+    pub whole_floats: Option<WholeFloatPolicy>,
+    /// `inference.floats`, one of `float`, `decimal` or `error`
+    pub floats: Option<FloatPolicy>,
+    /// `inference.empty_strings_as_none`, treats empty strings like null
+    pub empty_strings_as_none: Option<bool>,
+    /// `inference.json_strings`, infers the structure of json sent as strings
+    pub json_strings: Option<bool>,
+    /// `inference.field_descriptions`, lifts descriptions in the sample into
+    /// doc comments
+    pub field_descriptions: Option<bool>,
+    /// `inference.redact_samples`, hides the example values in reports
+    pub redact_samples: Option<bool>,
+    /// `inference.detect_secrets`, warns about values that look like
+    /// credentials and keeps no examples of them
+    pub detect_secrets: Option<bool>,
+    /// `inference.dedupe_samples`, infers only from the first sample of each
+    /// shape
+    pub dedupe_samples: Option<bool>,
+    /// `inference.all_optional`, makes every field optional
+    pub all_optional: Option<bool>,
+    /// `inference.numeric_bools`, types fields sent as bools or 0 and 1 as `bool`
+    pub numeric_bools: Option<bool>,
+    /// `inference.int_bool_threshold`, the number of values that must all be 0
+    /// or 1 to infer a `bool`, where 0 disables the inference
+    pub int_bool_threshold: Option<usize>,
+    /// `naming.strategy`, one of `field_name`, `full_path`, `parent_prefixed`
+    /// or `singularized`
+    pub naming_strategy: Option<String>,
+    /// `naming.acronyms`, `capitalized` or `uppercase`, how acronyms are
+    /// written in type names
+    pub acronyms: Option<String>,
+    /// `naming.strip_common_prefix`, leaves the words every key of an object
+    /// starts with out of its field names
+    pub strip_common_prefix: Option<bool>,
+    /// `[inflections]`, plurals to their singular form, used by the
+    /// `singularized` naming strategy
+    pub inflections: Vec<(String, String)>,
+    /// `[abbreviations]`, words to expand in field and type names
+    pub abbreviations: Vec<(String, String)>,
+    /// `codegen.server_types`, tailors request and response types for server
+    /// handlers
+    pub server_types: Option<bool>,
+    /// `codegen.openapi_schemas`, derives `utoipa::ToSchema`
+    pub openapi_schemas: Option<bool>,
+    /// `codegen.validation`, derives `validator::Validate` with rules from the
+    /// sample
+    pub validation: Option<bool>,
+    /// `codegen.json_schemas`, derives `schemars::JsonSchema`
+    pub json_schemas: Option<bool>,
+    /// `codegen.output_version`, the version of the generated code to pin
+    pub output_version: Option<OutputVersion>,
+    /// `codegen.sample_locations`, documents types and fields with their json
+    /// pointer and line in the sample
+    pub sample_locations: Option<bool>,
+    /// `codegen.schema_fingerprint`, adds a constant with the fingerprint of
+    /// the inferred structure
+    pub schema_fingerprint: Option<bool>,
+    /// `codegen.shape_check`, adds a `matches_schema` function checking
+    /// payloads against the inferred structure
+    pub shape_check: Option<bool>,
+    /// `codegen.constant_fields`, rejects other values for fields that had the
+    /// same value everywhere in the sample
+    pub constant_fields: Option<bool>,
+    /// `codegen.int_enums`, `repr` or `newtype`, generates integer fields that
+    /// took a few values as enums or newtypes with constants
+    pub int_enums: Option<IntEnumStyle>,
+    /// `codegen.unwrap_wrappers`, globs of the keys of single-field wrapper
+    /// objects to unwrap
+    pub unwrap_wrappers: Vec<String>,
+    /// `codegen.set_fields`, globs of the keys of array fields to generate as
+    /// sets when their elements were unique
+    pub set_fields: Vec<String>,
+    /// `codegen.hash_sets`, generates sets as `HashSet` instead of `BTreeSet`
+    pub hash_sets: Option<bool>,
+    /// `codegen.fixed_size_arrays`, the number of times an array must be seen
+    /// with the same length to be generated as `[T; N]`
+    pub fixed_size_arrays: Option<usize>,
+    /// `codegen.distinguish_null`, tells fields that are missing apart from
+    /// fields that are null
+    pub distinguish_null: Option<bool>,
+    /// `codegen.patch_types`, adds a `<Root>Patch` struct for partial updates
+    pub patch_types: Option<bool>,
+    /// `codegen.server_generated`, json pointers of fields generated by the
+    /// server, left out of `<Name>Create` types
+    pub server_generated: Vec<String>,
+    /// `codegen.conversions`, adds `From` impls between structs sharing most
+    /// of their fields
+    pub conversions: Option<bool>,
+    /// `codegen.display_impls`, adds a compact `impl Display` for every struct
+    pub display_impls: Option<bool>,
+    /// `codegen.ordering_derives`, derives `Eq`, `PartialOrd` and `Ord` where
+    /// every field supports them
+    pub ordering_derives: Option<bool>,
+    /// `codegen.forward_compatible`, adds catch-all enum variants and marks
+    /// structs `#[non_exhaustive]`
+    pub forward_compatible: Option<bool>,
+    /// `codegen.type_aliases`, globs of the keys of scalar fields to generate
+    /// with a type alias named after the key
+    pub type_aliases: Vec<String>,
+    /// `codegen.root_alias`, generates a type alias for roots that aren't
+    /// objects
+    pub root_alias: Option<bool>,
+    /// `codegen.array_root`, how the types of an array of objects are named:
+    /// `"struct"`, `"alias"` or `"element"`
+    pub array_root: Option<ArrayRoot>,
+    /// `codegen.columnar`, also generates the rows of an array of objects as
+    /// a struct of columns
+    pub columnar: Option<bool>,
+    /// `codegen.sqlx_derives`, derives `sqlx::FromRow` on flat record structs
+    pub sqlx_derives: Option<bool>,
+    /// `codegen.diesel_table`, the Diesel table flat record structs map to,
+    /// such as `crate::schema::users`
+    pub diesel_table: Option<String>,
+    /// `codegen.reorder_fields`, declares fields by descending alignment when
+    /// that saves padding
+    pub reorder_fields: Option<bool>,
+    /// `codegen.box_threshold`, the estimated size in bytes from which the
+    /// values of optional fields are boxed
+    pub box_threshold: Option<usize>,
+    /// `codegen.comment_unknown_fields`, comments out fields that were only
+    /// null or empty arrays
+    pub comment_unknown_fields: Option<bool>,
+    /// `codegen.helpers_module`, the module with the shared `deserialize_with`
+    /// helpers, such as `crate::de_helpers`
+    pub helpers_module: Option<String>,
+    /// `codegen.type_attributes`, attributes added to every generated type
+    pub type_attributes: Vec<String>,
+    /// `[field_attributes]`, json pointers of fields to an attribute added to
+    /// them
+    pub field_attributes: Vec<(String, String)>,
+    /// `codegen.header`, code written at the top of generated files
+    pub header: Option<String>,
+    /// `codegen.footer`, code written at the end of generated files
+    pub footer: Option<String>,
+    /// `codegen.user_code_region`, ends generated files with a region of user
+    /// code kept when regenerating
+    pub user_code_region: Option<bool>,
+    /// `codegen.banner`, starts generated files with comments recording where
+    /// they came from
+    pub banner: Option<bool>,
+    /// `codegen.banner_timestamp`, also records when they were generated
+    pub banner_timestamp: Option<bool>,
+    /// `style.indent_width`, spaces a level of the generated code is indented
+    /// with
+    pub indent_width: Option<usize>,
+    /// `style.hard_tabs`, indents the generated code with tabs
+    pub hard_tabs: Option<bool>,
+    /// `style.trailing_commas`, whether the last item of a list has a comma
+    pub trailing_commas: Option<bool>,
+    /// `style.inline_field_attributes`, puts field attributes on the line of
+    /// the field
+    pub inline_field_attributes: Option<bool>,
+    /// `style.split_derives`, derives the traits of the standard library and
+    /// of other crates separately
+    pub split_derives: Option<bool>,
+    /// `style.formatter`, a command the command line tool pipes generated
+    /// files through, such as `"rustfmt --edition 2021"`
+    pub formatter: Option<String>,
+    /// `limits.max_input_bytes`, the longest json sample read
+    pub max_input_bytes: Option<usize>,
+    /// `limits.max_depth`, the deepest nesting of objects and arrays read
+    pub max_depth: Option<usize>,
+    /// `limits.time_limit_ms`, the milliseconds a conversion by `json2rust
+    /// serve` may take
+    pub time_limit_ms: Option<usize>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum ConfigValue {
+    String(String),
+    Bool(bool),
+    Int(i64),
+    Array(Vec<String>),
+}
+
+const NAMING_STRATEGIES: &[&str] = &["field_name", "full_path", "parent_prefixed", "singularized"];
+
+const ACRONYM_STYLES: &[&str] = &["capitalized", "uppercase"];
+
+const MIXED_NUMBER_POLICIES: &[&str] = &["union", "string", "number", "error"];
+const WHOLE_FLOAT_POLICIES: &[&str] = &["float", "warn", "int"];
+const FLOAT_POLICIES: &[&str] = &["float", "decimal", "error"];
+const INT_ENUM_STYLES: &[&str] = &["repr", "newtype"];
+const ARRAY_ROOTS: &[&str] = &["struct", "alias", "element"];
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        Config::from_entries(parse_toml(text)?)
+    }
+
+    /// Reads the same settings from json, with an object for each table, such
+    /// as `{"inference": {"chaos_threshold": 4}}`. Top-level values that aren't
+    /// objects are skipped, so callers can keep their own settings beside the
+    /// tables.
+    pub fn parse_json(text: &str) -> Result<Config, ConfigError> {
+        Config::from_entries(parse_json_entries(text)?)
+    }
+
+    /// Reads the settings of another config file over these, such as a project
+    /// config over the user's global one. Settings it sets replace these, and
+    /// the keys of its tables, such as `[type_mappings]`, replace the same keys
+    /// of these tables and add to the others.
+    pub fn merge(&mut self, text: &str) -> Result<(), ConfigError> {
+        self.read_entries(parse_toml(text)?)
+    }
+
+    fn from_entries(entries: Vec<TomlEntry>) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        config.read_entries(entries)?;
+        Ok(config)
+    }
+
+    fn read_entries(&mut self, entries: Vec<TomlEntry>) -> Result<(), ConfigError> {
+        let config = self;
+        for entry in entries {
+            let table = match entry.section.as_str() {
+                "type_mappings" => Some(&mut config.type_mappings),
+                "field_attributes" => Some(&mut config.field_attributes),
+                "inflections" => Some(&mut config.inflections),
+                "abbreviations" => Some(&mut config.abbreviations),
+                _ => None,
+            };
+            if entry.section == "tagged_unions" {
+                let tagging = union_tagging(&entry.string()?).ok_or_else(|| {
+                    entry.error(format!(
+                        "'{}' must be \"external\", \"internal:<tag>\" or \
+                         \"adjacent:<tag>:<content>\"",
+                        entry.key
+                    ))
+                })?;
+                config.tagged_unions.retain(|(key, _)| *key != entry.key);
+                config.tagged_unions.push((entry.key.clone(), tagging));
+                continue;
+            }
+            if let Some(table) = table {
+                table.retain(|(key, _)| *key != entry.key);
+                table.push((entry.key.clone(), entry.string()?));
+                continue;
+            }
+            match (entry.section.as_str(), entry.key.as_str()) {
+                ("inference", "chaos_threshold") => {
+                    config.chaos_threshold = Some(entry.positive_int()?)
+                }
+                ("inference", "force_value") => config.force_value = entry.strings()?,
+                ("inference", "ignore_paths") => config.ignore_paths = entry.strings()?,
+                ("inference", "ignore_keys") => config.ignore_keys = entry.strings()?,
+                ("inference", "extended_json") => config.extended_json = Some(entry.bool()?),
+                ("inference", "lenient_numbers") => config.lenient_numbers = Some(entry.bool()?),
+                ("inference", "relaxed_json") => config.relaxed_json = Some(entry.bool()?),
+                ("inference", "non_finite_numbers") => {
+                    config.non_finite_numbers = Some(entry.bool()?)
+                }
+                ("inference", "exponent_integers") => {
+                    config.exponent_integers = Some(entry.bool()?)
+                }
+                ("inference", "locale_numbers") => config.locale_numbers = Some(entry.bool()?),
+                ("inference", "mixed_numbers") => {
+                    config.mixed_numbers =
+                        Some(match entry.one_of(MIXED_NUMBER_POLICIES)?.as_str() {
+                            "string" => MixedNumberPolicy::String,
+                            "number" => MixedNumberPolicy::Number,
+                            "error" => MixedNumberPolicy::Error,
+                            _ => MixedNumberPolicy::Union,
+                        })
+                }
+                ("inference", "whole_floats") => {
+                    config.whole_floats = Some(match entry.one_of(WHOLE_FLOAT_POLICIES)?.as_str() {
+                        "warn" => WholeFloatPolicy::Warn,
+                        "int" => WholeFloatPolicy::Int,
+                        _ => WholeFloatPolicy::Float,
+                    })
+                }
+                ("inference", "floats") => {
+                    config.floats = Some(match entry.one_of(FLOAT_POLICIES)?.as_str() {
+                        "decimal" => FloatPolicy::Decimal,
+                        "error" => FloatPolicy::Error,
+                        _ => FloatPolicy::Float,
+                    })
+                }
+                ("inference", "empty_strings_as_none") => {
+                    config.empty_strings_as_none = Some(entry.bool()?)
+                }
+                ("inference", "json_strings") => config.json_strings = Some(entry.bool()?),
+                ("inference", "field_descriptions") => {
+                    config.field_descriptions = Some(entry.bool()?)
+                }
+                ("inference", "redact_samples") => config.redact_samples = Some(entry.bool()?),
+                ("inference", "detect_secrets") => config.detect_secrets = Some(entry.bool()?),
+                ("inference", "dedupe_samples") => config.dedupe_samples = Some(entry.bool()?),
+                ("inference", "all_optional") => config.all_optional = Some(entry.bool()?),
+                ("inference", "numeric_bools") => config.numeric_bools = Some(entry.bool()?),
+                ("inference", "int_bool_threshold") => {
+                    config.int_bool_threshold = Some(entry.positive_int()?)
+                }
+                ("codegen", "server_types") => config.server_types = Some(entry.bool()?),
+                ("codegen", "openapi_schemas") => config.openapi_schemas = Some(entry.bool()?),
+                ("codegen", "validation") => config.validation = Some(entry.bool()?),
+                ("codegen", "json_schemas") => config.json_schemas = Some(entry.bool()?),
+                ("codegen", "sample_locations") => config.sample_locations = Some(entry.bool()?),
+                ("codegen", "schema_fingerprint") => {
+                    config.schema_fingerprint = Some(entry.bool()?)
+                }
+                ("codegen", "shape_check") => config.shape_check = Some(entry.bool()?),
+                ("codegen", "constant_fields") => config.constant_fields = Some(entry.bool()?),
+                ("codegen", "int_enums") => {
+                    config.int_enums = Some(match entry.one_of(INT_ENUM_STYLES)?.as_str() {
+                        "repr" => IntEnumStyle::Repr,
+                        _ => IntEnumStyle::Newtype,
+                    })
+                }
+                ("codegen", "unwrap_wrappers") => config.unwrap_wrappers = entry.strings()?,
+                ("codegen", "set_fields") => config.set_fields = entry.strings()?,
+                ("codegen", "hash_sets") => config.hash_sets = Some(entry.bool()?),
+                ("codegen", "distinguish_null") => config.distinguish_null = Some(entry.bool()?),
+                ("codegen", "patch_types") => config.patch_types = Some(entry.bool()?),
+                ("codegen", "server_generated") => config.server_generated = entry.strings()?,
+                ("codegen", "conversions") => config.conversions = Some(entry.bool()?),
+                ("codegen", "display_impls") => config.display_impls = Some(entry.bool()?),
+                ("codegen", "ordering_derives") => config.ordering_derives = Some(entry.bool()?),
+                ("codegen", "forward_compatible") => {
+                    config.forward_compatible = Some(entry.bool()?)
+                }
+                ("codegen", "type_aliases") => config.type_aliases = entry.strings()?,
+                ("codegen", "root_alias") => config.root_alias = Some(entry.bool()?),
+                ("codegen", "array_root") => {
+                    config.array_root = Some(match entry.one_of(ARRAY_ROOTS)?.as_str() {
+                        "alias" => ArrayRoot::Alias,
+                        "element" => ArrayRoot::Element,
+                        _ => ArrayRoot::Struct,
+                    })
+                }
+                ("codegen", "columnar") => config.columnar = Some(entry.bool()?),
+                ("codegen", "sqlx_derives") => config.sqlx_derives = Some(entry.bool()?),
+                ("codegen", "diesel_table") => config.diesel_table = Some(entry.string()?),
+                ("codegen", "reorder_fields") => config.reorder_fields = Some(entry.bool()?),
+                ("codegen", "box_threshold") => config.box_threshold = Some(entry.positive_int()?),
+                ("codegen", "comment_unknown_fields") => {
+                    config.comment_unknown_fields = Some(entry.bool()?)
+                }
+                ("codegen", "fixed_size_arrays") => {
+                    config.fixed_size_arrays = Some(entry.positive_int()?)
+                }
+                ("codegen", "header") => config.header = Some(entry.string()?),
+                ("codegen", "footer") => config.footer = Some(entry.string()?),
+                ("codegen", "user_code_region") => config.user_code_region = Some(entry.bool()?),
+                ("codegen", "banner") => config.banner = Some(entry.bool()?),
+                ("codegen", "banner_timestamp") => config.banner_timestamp = Some(entry.bool()?),
+                ("codegen", "type_attributes") => config.type_attributes = entry.strings()?,
+                ("codegen", "helpers_module") => config.helpers_module = Some(entry.string()?),
+                ("codegen", "output_version") => {
+                    let number = entry.positive_int()?;
+                    let version = OutputVersion::from_number(number as u32)
+                        .ok_or_else(|| entry.error(format!("Unknown output version {}", number)))?;
+                    config.output_version = Some(version)
+                }
+                ("style", "indent_width") => config.indent_width = Some(entry.positive_int()?),
+                ("style", "hard_tabs") => config.hard_tabs = Some(entry.bool()?),
+                ("style", "trailing_commas") => config.trailing_commas = Some(entry.bool()?),
+                ("style", "inline_field_attributes") => {
+                    config.inline_field_attributes = Some(entry.bool()?)
+                }
+                ("style", "split_derives") => config.split_derives = Some(entry.bool()?),
+                ("style", "formatter") => config.formatter = Some(entry.string()?),
+                ("limits", "max_input_bytes") => {
+                    config.max_input_bytes = Some(entry.positive_int()?)
+                }
+                ("limits", "max_depth") => config.max_depth = Some(entry.positive_int()?),
+                ("limits", "time_limit_ms") => config.time_limit_ms = Some(entry.positive_int()?),
+                ("naming", "strategy") => {
+                    config.naming_strategy = Some(entry.one_of(NAMING_STRATEGIES)?)
+                }
+                ("naming", "acronyms") => config.acronyms = Some(entry.one_of(ACRONYM_STYLES)?),
+                ("naming", "strip_common_prefix") => {
+                    config.strip_common_prefix = Some(entry.bool()?)
+                }
+                (section, key) => {
+                    return Err(entry.error(format!("Unknown setting '{}.{}'", section, key)))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, options: &mut ConvertOptions) {
+        for (pattern, rust_type) in &self.type_mappings {
+            options.add_type_mapping(pattern.as_str(), RustType::new(rust_type.as_str()));
+        }
+        match self.chaos_threshold {
+            Some(0) => {
+                options.set_chaos_threshold(None);
+            }
+            Some(threshold) => {
+                options.set_chaos_threshold(Some(threshold));
+            }
+            None => {}
+        }
+        for path in &self.force_value {
+            options.force_json_value(path.as_str());
+        }
+        for (path, tagging) in &self.tagged_unions {
+            options.tag_union(path.as_str(), tagging.clone());
+        }
+        for path in &self.ignore_paths {
+            options.ignore_path(path);
+        }
+        for key in &self.ignore_keys {
+            options.ignore_key(key.as_str());
+        }
+        if let Some(extended_json) = self.extended_json {
+            options.set_extended_json(extended_json);
+        }
+        if let Some(lenient_numbers) = self.lenient_numbers {
+            options.set_lenient_numbers(lenient_numbers);
+        }
+        if let Some(relaxed_json) = self.relaxed_json {
+            options.set_relaxed_json(relaxed_json);
+        }
+        if let Some(non_finite_numbers) = self.non_finite_numbers {
+            options.set_non_finite_numbers(non_finite_numbers);
+        }
+        if let Some(exponent_integers) = self.exponent_integers {
+            options.set_exponent_integers(exponent_integers);
+        }
+        if let Some(locale_numbers) = self.locale_numbers {
+            options.set_locale_numbers(locale_numbers);
+        }
+        if let Some(policy) = self.mixed_numbers {
+            options.set_mixed_number_policy(policy);
+        }
+        if let Some(policy) = self.whole_floats {
+            options.set_whole_float_policy(policy);
+        }
+        if let Some(policy) = self.floats {
+            options.set_float_policy(policy);
+        }
+        if let Some(empty_strings_as_none) = self.empty_strings_as_none {
+            options.set_empty_strings_as_none(empty_strings_as_none);
+        }
+        if let Some(json_strings) = self.json_strings {
+            options.set_json_strings(json_strings);
+        }
+        if let Some(field_descriptions) = self.field_descriptions {
+            options.set_field_descriptions(field_descriptions);
+        }
+        if let Some(redact_samples) = self.redact_samples {
+            options.set_redact_samples(redact_samples);
+        }
+        if let Some(detect_secrets) = self.detect_secrets {
+            options.set_detect_secrets(detect_secrets);
+        }
+        if let Some(dedupe_samples) = self.dedupe_samples {
+            options.set_dedupe_samples(dedupe_samples);
+        }
+        if let Some(all_optional) = self.all_optional {
+            options.set_all_optional(all_optional);
+        }
+        if let Some(numeric_bools) = self.numeric_bools {
+            options.set_numeric_bools(numeric_bools);
+        }
+        match self.int_bool_threshold {
+            Some(0) => {
+                options.set_int_bool_threshold(None);
+            }
+            Some(threshold) => {
+                options.set_int_bool_threshold(Some(threshold));
+            }
+            None => {}
+        }
+        if let Some(server_types) = self.server_types {
+            options.set_server_types(server_types);
+        }
+        if let Some(openapi_schemas) = self.openapi_schemas {
+            options.set_openapi_schemas(openapi_schemas);
+        }
+        if let Some(validation) = self.validation {
+            options.set_validation(validation);
+        }
+        if let Some(json_schemas) = self.json_schemas {
+            options.set_json_schemas(json_schemas);
+        }
+        if let Some(version) = self.output_version {
+            options.set_output_version(version);
+        }
+        if let Some(sample_locations) = self.sample_locations {
+            options.set_sample_locations(sample_locations);
+        }
+        if let Some(schema_fingerprint) = self.schema_fingerprint {
+            options.set_schema_fingerprint(schema_fingerprint);
+        }
+        if let Some(shape_check) = self.shape_check {
+            options.set_shape_check(shape_check);
+        }
+        if let Some(constant_fields) = self.constant_fields {
+            options.set_constant_fields(constant_fields);
+        }
+        if let Some(style) = self.int_enums {
+            options.set_int_enums(Some(style));
+        }
+        for key in &self.unwrap_wrappers {
+            options.unwrap_wrapper(key.as_str());
+        }
+        for pattern in &self.set_fields {
+            options.add_set_field(pattern.as_str());
+        }
+        if let Some(hash_sets) = self.hash_sets {
+            options.set_hash_sets(hash_sets);
+        }
+        if let Some(min_observations) = self.fixed_size_arrays {
+            options.set_fixed_size_arrays(Some(min_observations));
+        }
+        if let Some(distinguish_null) = self.distinguish_null {
+            options.set_distinguish_null(distinguish_null);
+        }
+        if let Some(patch_types) = self.patch_types {
+            options.set_patch_types(patch_types);
+        }
+        for path in &self.server_generated {
+            options.mark_server_generated(path.as_str());
+        }
+        if let Some(conversions) = self.conversions {
+            options.set_conversions(conversions);
+        }
+        if let Some(display_impls) = self.display_impls {
+            options.set_display_impls(display_impls);
+        }
+        if let Some(ordering_derives) = self.ordering_derives {
+            options.set_ordering_derives(ordering_derives);
+        }
+        if let Some(forward_compatible) = self.forward_compatible {
+            options.set_forward_compatible(forward_compatible);
+        }
+        for pattern in &self.type_aliases {
+            options.add_type_alias(pattern.as_str());
+        }
+        if let Some(root_alias) = self.root_alias {
+            options.set_root_alias(root_alias);
+        }
+        if let Some(array_root) = self.array_root {
+            options.set_array_root(array_root);
+        }
+        if let Some(columnar) = self.columnar {
+            options.set_columnar(columnar);
+        }
+        if let Some(sqlx_derives) = self.sqlx_derives {
+            options.set_sqlx_derives(sqlx_derives);
+        }
+        if let Some(table) = &self.diesel_table {
+            options.set_diesel_table(Some(table.clone()));
+        }
+        if let Some(reorder_fields) = self.reorder_fields {
+            options.set_reorder_fields(reorder_fields);
+        }
+        if let Some(threshold) = self.box_threshold {
+            options.set_box_threshold(Some(threshold));
+        }
+        if let Some(comment_unknown_fields) = self.comment_unknown_fields {
+            options.set_comment_unknown_fields(comment_unknown_fields);
+        }
+        if let Some(header) = &self.header {
+            options.set_header(Some(header.clone()));
+        }
+        if let Some(footer) = &self.footer {
+            options.set_footer(Some(footer.clone()));
+        }
+        if let Some(user_code_region) = self.user_code_region {
+            options.set_user_code_region(user_code_region);
+        }
+        if let Some(banner) = self.banner {
+            options.set_banner(banner);
+        }
+        if let Some(banner_timestamp) = self.banner_timestamp {
+            options.set_banner_timestamp(banner_timestamp);
+        }
+        for attribute in &self.type_attributes {
+            options.add_type_attribute(attribute.as_str());
+        }
+        for (path, attribute) in &self.field_attributes {
+            options.add_field_attribute(path.as_str(), attribute.as_str());
+        }
+        if let Some(module) = &self.helpers_module {
+            options.set_helpers_module(Some(module.clone()));
+        }
+        if let Some(indent_width) = self.indent_width {
+            options.set_indent_width(indent_width);
+        }
+        if let Some(hard_tabs) = self.hard_tabs {
+            options.set_hard_tabs(hard_tabs);
+        }
+        if let Some(trailing_commas) = self.trailing_commas {
+            options.set_trailing_commas(trailing_commas);
+        }
+        if let Some(inline_field_attributes) = self.inline_field_attributes {
+            options.set_inline_field_attributes(inline_field_attributes);
+        }
+        if let Some(split_derives) = self.split_derives {
+            options.set_split_derives(split_derives);
+        }
+        if let Some(max_input_bytes) = self.max_input_bytes {
+            options.set_max_input_bytes(Some(max_input_bytes));
+        }
+        if let Some(max_depth) = self.max_depth {
+            options.set_max_depth(Some(max_depth));
+        }
+        for (abbreviation, expansion) in &self.abbreviations {
+            options.add_abbreviation(abbreviation.as_str(), expansion.as_str());
+        }
+        match self.acronyms.as_deref() {
+            Some("uppercase") => {
+                options.set_acronym_style(AcronymStyle::Uppercase);
+            }
+            Some(_) => {
+                options.set_acronym_style(AcronymStyle::Capitalized);
+            }
+            None => {}
+        }
+        if let Some(strip_common_prefix) = self.strip_common_prefix {
+            options.set_strip_common_prefix(strip_common_prefix);
+        }
+        match self.naming_strategy.as_deref() {
+            Some("full_path") => {
+                options.set_naming_strategy(FullPathStrategy);
+            }
+            Some("parent_prefixed") => {
+                options.set_naming_strategy(ParentPrefixedStrategy);
+            }
+            Some("field_name") => {
+                options.set_naming_strategy(FieldNameStrategy);
+            }
+            _ => {
+                let mut inflector = Inflector::new();
+                for (plural, singular) in &self.inflections {
+                    inflector.add_word(plural.as_str(), singular.as_str());
+                }
+                options.set_naming_strategy(SingularizedStrategy::with_inflector(inflector));
+            }
+        }
+    }
+}
+
+struct TomlEntry {
+    section: String,
+    key: String,
+    value: ConfigValue,
+    line: usize,
+}
+
+impl TomlEntry {
+    fn error(&self, message: String) -> ConfigError {
+        ConfigError {
+            line: self.line,
+            message,
+        }
+    }
+
+    fn string(&self) -> Result<String, ConfigError> {
+        match self.value {
+            ConfigValue::String(ref s) => Ok(s.clone()),
+            _ => Err(self.error(format!("'{}' must be a string", self.key))),
+        }
+    }
+
+    fn strings(&self) -> Result<Vec<String>, ConfigError> {
+        match self.value {
+            ConfigValue::Array(ref values) => Ok(values.clone()),
+            _ => Err(self.error(format!("'{}' must be an array of strings", self.key))),
+        }
+    }
+
+    fn bool(&self) -> Result<bool, ConfigError> {
+        match self.value {
+            ConfigValue::Bool(b) => Ok(b),
+            _ => Err(self.error(format!("'{}' must be true or false", self.key))),
+        }
+    }
+
+    fn positive_int(&self) -> Result<usize, ConfigError> {
+        match self.value {
+            ConfigValue::Int(i) if i >= 0 => Ok(i as usize),
+            _ => Err(self.error(format!("'{}' must be a positive integer", self.key))),
+        }
+    }
+
+    fn one_of(&self, allowed: &[&str]) -> Result<String, ConfigError> {
+        let value = self.string()?;
+        if allowed.contains(&value.as_str()) {
+            Ok(value)
+        } else {
+            Err(self.error(format!(
+                "'{}' must be one of {}, got '{}'",
+                self.key,
+                allowed.join(", "),
+                value
+            )))
+        }
+    }
+}
+
+/// How a union is tagged, written as `external`, `internal:<tag>` or
+/// `adjacent:<tag>:<content>`.
+fn union_tagging(value: &str) -> Option<UnionTagging> {
+    let mut parts = value.split(':');
+    let tagging = match (parts.next()?, parts.next(), parts.next()) {
+        ("external", None, None) => UnionTagging::External,
+        ("internal", Some(tag), None) if !tag.is_empty() => UnionTagging::Internal {
+            tag: tag.to_string(),
+        },
+        ("adjacent", Some(tag), Some(content)) if !tag.is_empty() && !content.is_empty() => {
+            UnionTagging::Adjacent {
+                tag: tag.to_string(),
+                content: content.to_string(),
+            }
+        }
+        _ => return None,
+    };
+    match parts.next() {
+        Some(_) => None,
+        None => Some(tagging),
+    }
+}
+
+fn parse_toml(text: &str) -> Result<Vec<TomlEntry>, ConfigError> {
+    let mut entries = Vec::new();
+    let mut section = String::new();
+
+    for (index, raw_line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: &str| ConfigError {
+            line: line_number,
+            message: message.to_string(),
+        };
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            let end = line
+                .find(']')
+                .ok_or_else(|| error("Unclosed table header"))?;
+            section = line[1..end].trim().to_string();
+            continue;
+        }
+
+        let (key, rest) = if line.starts_with('"') {
+            let (key, rest) = parse_string(line).map_err(|m| error(&m))?;
+            (key, rest)
+        } else {
+            let end = line.find('=').ok_or_else(|| error("Expected '='"))?;
+            (line[..end].trim().to_string(), &line[end..])
+        };
+        let rest = rest.trim_start();
+        if !rest.starts_with('=') {
+            return Err(error("Expected '='"));
+        }
+        let (value, rest) = parse_value(rest[1..].trim_start()).map_err(|m| error(&m))?;
+        let rest = rest.trim();
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(error("Unexpected characters after value"));
+        }
+        entries.push(TomlEntry {
+            section: section.clone(),
+            key,
+            value,
+            line: line_number,
+        });
+    }
+    Ok(entries)
+}
+
+fn parse_json_entries(text: &str) -> Result<Vec<TomlEntry>, ConfigError> {
+    let tokens = tokenize_json(text, Leniency::default()).map_err(|e| ConfigError {
+        line: e.location().map_or(1, |location| location.line() as usize),
+        message: format!("Invalid json: {}", e),
+    })?;
+    // Only the structure matters to the entries, not the separators
+    let mut tokens = tokens
+        .iter()
+        .filter(|token| token.token_type != JsonTokenType::Comma);
+    let mut entries = Vec::new();
+    let mut next = |expected: &str| {
+        tokens.next().ok_or_else(|| ConfigError {
+            line: text.lines().count().max(1),
+            message: format!("Expected {}", expected),
+        })
+    };
+    let error = |token: &JsonToken, message: &str| ConfigError {
+        line: token.span.start.line() as usize,
+        message: message.to_string(),
+    };
+    let expect_colon = |token: &JsonToken| match token.token_type {
+        JsonTokenType::Colon => Ok(()),
+        _ => Err(error(token, "Expected ':'")),
+    };
+
+    let token = next("an object")?;
+    if token.token_type != JsonTokenType::ObjectStart {
+        return Err(error(token, "Expected an object"));
+    }
+    // Nesting of the top-level value being skipped
+    let mut skipping = 0;
+    let mut section = None;
+    loop {
+        let token = next("a key")?;
+        match (&token.token_type, &section) {
+            (JsonTokenType::ObjectStart, _) | (JsonTokenType::ArrayStart, _) if skipping > 0 => {
+                skipping += 1
+            }
+            (JsonTokenType::ObjectEnd, _) | (JsonTokenType::ArrayEnd, _) if skipping > 0 => {
+                skipping -= 1
+            }
+            (_, _) if skipping > 0 => {}
+            (JsonTokenType::ObjectEnd, None) => return Ok(entries),
+            (JsonTokenType::ObjectEnd, Some(_)) => section = None,
+            (JsonTokenType::Key(key), None) => {
+                expect_colon(next("':'")?)?;
+                let value = next("a value")?;
+                match value.token_type {
+                    JsonTokenType::ObjectStart => section = Some(key.clone()),
+                    JsonTokenType::ArrayStart => skipping = 1,
+                    _ => {}
+                }
+            }
+            (JsonTokenType::Key(key), Some(name)) => {
+                expect_colon(next("':'")?)?;
+                let value = next("a value")?;
+                let value = match &value.token_type {
+                    JsonTokenType::String(s) => ConfigValue::String(s.clone()),
+                    JsonTokenType::Int(i) => ConfigValue::Int(
+                        i64::try_from(*i).map_err(|_| error(value, "Number too large"))?,
+                    ),
+                    JsonTokenType::Bool => {
+                        // Tokens don't keep the value of a bool
+                        let start = value.span.start.char() as usize;
+                        ConfigValue::Bool(text.chars().nth(start) == Some('t'))
+                    }
+                    JsonTokenType::ArrayStart => {
+                        let mut values = Vec::new();
+                        loop {
+                            let element = next("']'")?;
+                            match &element.token_type {
+                                JsonTokenType::ArrayEnd => break,
+                                JsonTokenType::String(s) => values.push(s.clone()),
+                                _ => return Err(error(element, "Expected a string")),
+                            }
+                        }
+                        ConfigValue::Array(values)
+                    }
+                    _ => return Err(error(value, &format!("Invalid value for '{}'", key))),
+                };
+                entries.push(TomlEntry {
+                    section: name.to_string(),
+                    key: key.to_string(),
+                    value,
+                    line: token.span.start.line() as usize,
+                });
+            }
+            _ => return Err(error(token, "Expected a key")),
+        }
+    }
+}
+
+/// The settings of config files read over each other, later files taking
+/// precedence like with [`Config::merge`], as a config file. Each setting ends
+/// with a comment naming the file it came from. `layers` are pairs of names
+/// and texts of the files.
+pub fn resolved_config(layers: &[(&str, &str)]) -> Result<String, ConfigError> {
+    // (section, key, value, name of the file), in the order first set
+    let mut settings: Vec<(String, String, ConfigValue, &str)> = Vec::new();
+    for (name, text) in layers {
+        for entry in parse_toml(text)? {
+            let value = (entry.section, entry.key, entry.value, *name);
+            match settings
+                .iter_mut()
+                .find(|(section, key, _, _)| *section == value.0 && *key == value.1)
+            {
+                Some(setting) => *setting = value,
+                None => settings.push(value),
+            }
+        }
+    }
+    let mut sections: Vec<&str> = Vec::new();
+    for (section, _, _, _) in &settings {
+        if !sections.contains(&section.as_str()) {
+            sections.push(section);
+        }
+    }
+    let mut toml = String::new();
+    for section in sections {
+        if !toml.is_empty() {
+            toml.push('\n');
+        }
+        if !section.is_empty() {
+            toml.push_str(&format!("[{}]\n", section));
+        }
+        for (_, key, value, name) in settings.iter().filter(|s| s.0 == section) {
+            let key = if key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                key.clone()
+            } else {
+                toml_string(key)
+            };
+            let value = match value {
+                ConfigValue::String(s) => toml_string(s),
+                ConfigValue::Bool(b) => b.to_string(),
+                ConfigValue::Int(i) => i.to_string(),
+                ConfigValue::Array(values) => {
+                    let values: Vec<String> = values.iter().map(|v| toml_string(v)).collect();
+                    format!("[{}]", values.join(", "))
+                }
+            };
+            toml.push_str(&format!("{} = {} # {}\n", key, value, name));
+        }
+    }
+    Ok(toml)
+}
+
+fn toml_string(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+fn parse_value(text: &str) -> Result<(ConfigValue, &str), String> {
+    if text.starts_with('"') {
+        let (s, rest) = parse_string(text)?;
+        return Ok((ConfigValue::String(s), rest));
+    }
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut values = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((ConfigValue::Array(values), after));
+            }
+            let (s, after) = parse_string(rest)?;
+            values.push(s);
+            rest = after.trim_start();
+            rest = rest.strip_prefix(',').unwrap_or(rest);
+        }
+    }
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == '#')
+        .unwrap_or(text.len());
+    let (word, rest) = text.split_at(end);
+    match word {
+        "true" => Ok((ConfigValue::Bool(true), rest)),
+        "false" => Ok((ConfigValue::Bool(false), rest)),
+        _ => word
+            .parse()
+            .map(|i| (ConfigValue::Int(i), rest))
+            .map_err(|_| format!("Invalid value '{}'", word)),
+    }
+}
+
+fn parse_string(text: &str) -> Result<(String, &str), String> {
+    let mut value = String::new();
+    let mut chars = text.char_indices().skip(1);
+    if !text.starts_with('"') {
+        return Err("Expected a string".to_string());
+    }
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &text[i + 1..])),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, c @ '"')) | Some((_, c @ '\\')) => value.push(c),
+                _ => return Err("Invalid escape sequence".to_string()),
+            },
+            c => value.push(c),
+        }
+    }
+    Err("Unclosed string".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_type_mappings() {
+        let config = Config::parse(
+            r#"
+# Timestamps
+[type_mappings]
+"*_at" = "chrono::DateTime<Utc>" # trailing comment
+metadata = "serde_json::Value"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.type_mappings,
+            vec![
+                ("*_at".to_string(), "chrono::DateTime<Utc>".to_string()),
+                ("metadata".to_string(), "serde_json::Value".to_string()),
+            ]
+        )
+    }
+
+    #[test]
+    fn parses_inference_settings() {
+        let config = Config::parse(
+            "[inference]\nchaos_threshold = 0\nforce_value = [\"/a/*\"]\nignore_paths = [\"$.debug\"]\nignore_keys = [\"_*\"]\nextended_json = true\n\
+             lenient_numbers = true\nrelaxed_json = true\nnon_finite_numbers = true\nexponent_integers = true\n\
+             locale_numbers = true\nmixed_numbers = \"number\"\nwhole_floats = \"int\"\nfloats = \"decimal\"\nempty_strings_as_none = true\njson_strings = true\nfield_descriptions = true\nredact_samples = true\ndetect_secrets = false\ndedupe_samples = true\n\
+             all_optional = true\nnumeric_bools = true\nint_bool_threshold = 5",
+        )
+        .unwrap();
+
+        assert_eq!(config.chaos_threshold, Some(0));
+        assert_eq!(config.extended_json, Some(true));
+        assert_eq!(config.lenient_numbers, Some(true));
+        assert_eq!(config.relaxed_json, Some(true));
+        assert_eq!(config.non_finite_numbers, Some(true));
+        assert_eq!(config.exponent_integers, Some(true));
+        assert_eq!(config.locale_numbers, Some(true));
+        assert_eq!(config.mixed_numbers, Some(MixedNumberPolicy::Number));
+        assert_eq!(config.whole_floats, Some(WholeFloatPolicy::Int));
+        assert_eq!(config.floats, Some(FloatPolicy::Decimal));
+        assert_eq!(config.empty_strings_as_none, Some(true));
+        assert_eq!(config.json_strings, Some(true));
+        assert_eq!(config.field_descriptions, Some(true));
+        assert_eq!(config.redact_samples, Some(true));
+        assert_eq!(config.detect_secrets, Some(false));
+        assert_eq!(config.dedupe_samples, Some(true));
+        assert_eq!(config.all_optional, Some(true));
+        assert_eq!(config.numeric_bools, Some(true));
+        assert_eq!(config.int_bool_threshold, Some(5));
+        assert_eq!(config.force_value, vec!["/a/*".to_string()]);
+        assert_eq!(config.ignore_paths, vec!["$.debug".to_string()]);
+        assert_eq!(config.ignore_keys, vec!["_*".to_string()]);
+    }
+
+    #[test]
+    fn parses_codegen_settings() {
+        let config = Config::parse(
+            "[codegen]\nserver_types = true\nopenapi_schemas = false\nvalidation = true\njson_schemas = true\n\
+             output_version = 1\nsample_locations = true\nschema_fingerprint = true\nshape_check = true\nconstant_fields = true\nint_enums = \"repr\"\nunwrap_wrappers = [\"value\"]\n\
+             set_fields = [\"tags\"]\nhash_sets = true\nfixed_size_arrays = 3\ndistinguish_null = true\npatch_types = true\n\
+             server_generated = [\"/id\"]\nconversions = true\ndisplay_impls = true\nordering_derives = true\nforward_compatible = true\ntype_aliases = [\"*_id\"]\nroot_alias = true\narray_root = \"alias\"\ncolumnar = true\nsqlx_derives = true\ndiesel_table = \"crate::schema::users\"\nreorder_fields = true\nbox_threshold = 128\ncomment_unknown_fields = true\nhelpers_module = \"crate::de_helpers\"\n\
+             header = \"//! @generated\\n\"\nfooter = \"// end\"\nuser_code_region = true\nbanner = true\nbanner_timestamp = false",
+        )
+        .unwrap();
+
+        assert_eq!(config.server_types, Some(true));
+        assert_eq!(config.openapi_schemas, Some(false));
+        assert_eq!(config.validation, Some(true));
+        assert_eq!(config.json_schemas, Some(true));
+        assert_eq!(config.output_version, Some(OutputVersion::V1));
+        assert_eq!(config.sample_locations, Some(true));
+        assert_eq!(config.schema_fingerprint, Some(true));
+        assert_eq!(config.shape_check, Some(true));
+        assert_eq!(config.constant_fields, Some(true));
+        assert_eq!(config.int_enums, Some(IntEnumStyle::Repr));
+        assert_eq!(config.unwrap_wrappers, vec!["value".to_string()]);
+        assert_eq!(config.set_fields, vec!["tags".to_string()]);
+        assert_eq!(config.hash_sets, Some(true));
+        assert_eq!(config.fixed_size_arrays, Some(3));
+        assert_eq!(config.distinguish_null, Some(true));
+        assert_eq!(config.patch_types, Some(true));
+        assert_eq!(config.server_generated, vec!["/id".to_string()]);
+        assert_eq!(config.conversions, Some(true));
+        assert_eq!(config.display_impls, Some(true));
+        assert_eq!(config.ordering_derives, Some(true));
+        assert_eq!(config.forward_compatible, Some(true));
+        assert_eq!(config.type_aliases, vec!["*_id".to_string()]);
+        assert_eq!(config.root_alias, Some(true));
+        assert_eq!(config.array_root, Some(ArrayRoot::Alias));
+        assert_eq!(config.columnar, Some(true));
+        assert_eq!(config.sqlx_derives, Some(true));
+        assert_eq!(
+            config.diesel_table,
+            Some("crate::schema::users".to_string())
+        );
+        assert_eq!(config.reorder_fields, Some(true));
+        assert_eq!(config.box_threshold, Some(128));
+        assert_eq!(config.comment_unknown_fields, Some(true));
+        assert_eq!(config.helpers_module, Some("crate::de_helpers".to_string()));
+        assert_eq!(config.header, Some("//! @generated\n".to_string()));
+        assert_eq!(config.footer, Some("// end".to_string()));
+        assert_eq!(config.user_code_region, Some(true));
+        assert_eq!(config.banner, Some(true));
+        assert_eq!(config.banner_timestamp, Some(false));
+        assert_eq!(
+            Config::parse("[codegen]\noutput_version = 7").unwrap_err(),
+            ConfigError {
+                line: 2,
+                message: "Unknown output version 7".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn parses_naming_settings() {
+        let config = Config::parse(
+            "[naming]\nstrategy = \"full_path\"\nstrip_common_prefix = true\n\
+             acronyms = \"uppercase\"\n\n\
+             [abbreviations]\namt = \"amount\"",
+        )
+        .unwrap();
+
+        assert_eq!(config.naming_strategy, Some("full_path".to_string()));
+        assert_eq!(config.strip_common_prefix, Some(true));
+        assert_eq!(config.acronyms, Some("uppercase".to_string()));
+        assert_eq!(
+            config.abbreviations,
+            vec![("amt".to_string(), "amount".to_string())]
+        );
+    }
+
+    #[test]
+    fn parses_style_settings() {
+        let config = Config::parse(
+            "[style]\nindent_width = 2\nhard_tabs = false\ntrailing_commas = false\n\
+             inline_field_attributes = true\nsplit_derives = true\nformatter = \"rustfmt\"",
+        )
+        .unwrap();
+
+        assert_eq!(config.indent_width, Some(2));
+        assert_eq!(config.hard_tabs, Some(false));
+        assert_eq!(config.trailing_commas, Some(false));
+        assert_eq!(config.inline_field_attributes, Some(true));
+        assert_eq!(config.split_derives, Some(true));
+        assert_eq!(config.formatter, Some("rustfmt".to_string()));
+    }
+
+    #[test]
+    fn parses_limits() {
+        let config =
+            Config::parse("[limits]\nmax_input_bytes = 1000\nmax_depth = 3\ntime_limit_ms = 50")
+                .unwrap();
+        let mut options = ConvertOptions::default();
+        config.apply(&mut options);
+
+        assert_eq!(config.time_limit_ms, Some(50));
+        assert_eq!(
+            crate::convert_sample_json_with_options("[[[[1]]]]", &options)
+                .unwrap_err()
+                .code(),
+            crate::ErrorCode::TooDeep
+        );
+        assert_eq!(
+            crate::convert_sample_json_with_options(&"1".repeat(1001), &options)
+                .unwrap_err()
+                .code(),
+            crate::ErrorCode::InputTooLarge
+        );
+    }
+
+    #[test]
+    fn parses_attributes() {
+        let config = Config::parse(
+            r##"
+[codegen]
+type_attributes = ["#[cfg_attr(feature = \"openapi\", derive(ToSchema))]"]
+
+[field_attributes]
+"/items/*/legacy_id" = "#[deprecated]"
+"##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.type_attributes,
+            vec!["#[cfg_attr(feature = \"openapi\", derive(ToSchema))]".to_string()]
+        );
+        assert_eq!(
+            config.field_attributes,
+            vec![(
+                "/items/*/legacy_id".to_string(),
+                "#[deprecated]".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn parses_tagged_unions() {
+        let config = Config::parse(
+            r#"
+[tagged_unions]
+"/events/*" = "adjacent:type:payload"
+"/shapes/*" = "internal:kind"
+"/result" = "external"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.tagged_unions,
+            vec![
+                (
+                    "/events/*".to_string(),
+                    UnionTagging::Adjacent {
+                        tag: "type".to_string(),
+                        content: "payload".to_string()
+                    }
+                ),
+                (
+                    "/shapes/*".to_string(),
+                    UnionTagging::Internal {
+                        tag: "kind".to_string()
+                    }
+                ),
+                ("/result".to_string(), UnionTagging::External),
+            ]
+        );
+        assert_eq!(
+            Config::parse("[tagged_unions]\n\"/a\" = \"internal\"")
+                .unwrap_err()
+                .line,
+            2
+        );
+    }
+
+    #[test]
+    fn parses_values() {
+        let entries: Vec<(String, String, ConfigValue, usize)> =
+            parse_toml("[a]\nb = true\nc = -4\nd = [\"x\", \"y\"]")
+                .unwrap()
+                .into_iter()
+                .map(|e| (e.section, e.key, e.value, e.line))
+                .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                ("a".into(), "b".into(), ConfigValue::Bool(true), 2),
+                ("a".into(), "c".into(), ConfigValue::Int(-4), 3),
+                (
+                    "a".into(),
+                    "d".into(),
+                    ConfigValue::Array(vec!["x".into(), "y".into()]),
+                    4
+                ),
+            ]
+        )
+    }
+
+    #[test]
+    fn parses_json_settings() {
+        let config = Config::parse_json(
+            r#"{
+                "root_name": "ApiResponse",
+                "ignored": [{"a": 1}],
+                "type_mappings": {"*_at": "chrono::DateTime<Utc>"},
+                "inference": {"chaos_threshold": 4, "extended_json": false, "force_value": ["tags"]},
+                "codegen": {"server_types": true}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                type_mappings: vec![("*_at".to_string(), "chrono::DateTime<Utc>".to_string())],
+                chaos_threshold: Some(4),
+                extended_json: Some(false),
+                force_value: vec!["tags".to_string()],
+                server_types: Some(true),
+                ..Config::default()
+            }
+        );
+        assert_eq!(
+            Config::parse_json("{\n\"inference\": {\n\"chaos_threshold\": true}}"),
+            Err(ConfigError {
+                line: 3,
+                message: "'chaos_threshold' must be a positive integer".to_string()
+            })
+        );
+        assert_eq!(
+            Config::parse_json("[]"),
+            Err(ConfigError {
+                line: 1,
+                message: "Expected an object".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        assert_eq!(
+            Config::parse("[type_mappings]\nfoo = 5"),
+            Err(ConfigError {
+                line: 2,
+                message: "'foo' must be a string".to_string()
+            })
+        );
+        assert_eq!(
+            Config::parse("\n\nfoo = \"bar"),
+            Err(ConfigError {
+                line: 3,
+                message: "Unclosed string".to_string()
+            })
+        );
+        assert_eq!(
+            Config::parse("[naming]\nstrategy = \"shouting\""),
+            Err(ConfigError {
+                line: 2,
+                message: "'strategy' must be one of field_name, full_path, parent_prefixed, \
+                          singularized, got 'shouting'"
+                    .to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn merges_config_files_over_each_other() {
+        let global = "[codegen]\nvalidation = true\nordering_derives = true\n\
+                      [type_mappings]\nid = \"u64\"\n\"*_at\" = \"String\"";
+        let project = "[codegen]\nordering_derives = false\n[type_mappings]\nid = \"Uuid\"";
+        let mut config = Config::parse(global).unwrap();
+        config.merge(project).unwrap();
+
+        assert_eq!(config.validation, Some(true));
+        assert_eq!(config.ordering_derives, Some(false));
+        assert_eq!(
+            config.type_mappings,
+            vec![
+                ("*_at".to_string(), "String".to_string()),
+                ("id".to_string(), "Uuid".to_string())
+            ]
+        );
+        assert_eq!(
+            resolved_config(&[("global.toml", global), ("json2rust.toml", project)]).unwrap(),
+            "[codegen]\nvalidation = true # global.toml\nordering_derives = false # json2rust.toml\n\
+             \n[type_mappings]\nid = \"Uuid\" # json2rust.toml\n\"*_at\" = \"String\" # global.toml\n"
+        );
+    }
+}
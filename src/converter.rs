@@ -0,0 +1,110 @@
+#[cfg(feature = "std")]
+use crate::codegen::conversion_to_rust;
+use crate::options::ConvertOptions;
+use crate::shared::Error;
+use crate::{convert_sample_json_with_warnings, convert_samples_json_with_warnings, Conversion};
+#[cfg(feature = "std")]
+use alloc::string::String;
+
+/// Converts samples with options that are set up once, with their detectors,
+/// passes and naming strategy built, and reused for every conversion.
+///
+/// A converter is `Send + Sync`, so a server or a batch job can share one
+/// between threads, such as behind an `Arc` or a `static`.
+///
+/// ```
+/// use json2rust::{ConvertOptions, Converter, JsonStructure};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let mut options = ConvertOptions::default();
+/// options.set_hash_sets(true);
+/// let converter = Arc::new(Converter::new(options));
+///
+/// let handles: Vec<_> = (0..2)
+///     .map(|id| {
+///         let converter = Arc::clone(&converter);
+///         thread::spawn(move || converter.convert(&format!("{{\"id\": {}}}", id)))
+///     })
+///     .collect();
+/// for handle in handles {
+///     let conversion = handle.join().unwrap().unwrap();
+///     assert!(matches!(conversion.structure, JsonStructure::Object(_)));
+/// }
+/// ```
+pub struct Converter {
+    options: ConvertOptions,
+}
+
+impl Converter {
+    pub fn new(options: ConvertOptions) -> Converter {
+        Converter { options }
+    }
+
+    pub fn options(&self) -> &ConvertOptions {
+        &self.options
+    }
+
+    /// Infers the structure of a json sample, like
+    /// `convert_sample_json_with_warnings`.
+    pub fn convert(&self, json: &str) -> Result<Conversion, Error> {
+        convert_sample_json_with_warnings(json, &self.options)
+    }
+
+    /// Infers a single structure describing several json samples, like
+    /// `convert_samples_json_with_warnings`.
+    pub fn convert_samples(&self, samples: &[&str]) -> Result<Conversion, Error> {
+        convert_samples_json_with_warnings(samples, &self.options)
+    }
+
+    /// Generates the types of a conversion, like `conversion_to_rust`.
+    #[cfg(feature = "std")]
+    pub fn generate(&self, conversion: &Conversion, root_name: &str) -> String {
+        conversion_to_rust(conversion, root_name, &self.options)
+    }
+
+    /// Converts a json sample directly into rust source code, with the root
+    /// type named `root_name`.
+    #[cfg(feature = "std")]
+    pub fn json_to_rust(&self, json: &str, root_name: &str) -> Result<String, Error> {
+        Ok(self.generate(&self.convert(json)?, root_name))
+    }
+}
+
+impl Default for Converter {
+    fn default() -> Converter {
+        Converter::new(ConvertOptions::default())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::RustType;
+    use std::thread;
+
+    #[test]
+    fn converts_concurrently_with_shared_options() {
+        let mut options = ConvertOptions::default();
+        options.add_detector(|field_name, _| {
+            if field_name == "id" {
+                Some(RustType::new("UserId"))
+            } else {
+                None
+            }
+        });
+        let converter = &Converter::new(options);
+
+        let codes: Vec<String> = thread::scope(|scope| {
+            let handles: Vec<_> = ["{\"id\": \"a\"}", "{\"id\": \"b\", \"name\": \"c\"}"]
+                .iter()
+                .map(|json| scope.spawn(move || converter.json_to_rust(json, "User").unwrap()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert!(codes[0].contains("pub id: UserId,"));
+        assert!(codes[1].contains("pub id: UserId,"));
+        assert!(codes[1].contains("pub name: String,"));
+    }
+}
@@ -0,0 +1,303 @@
+use crate::options::ConvertOptions;
+use crate::parser::JsonNode;
+use crate::shared::Error;
+use crate::{generate_rust_with_options, Conversion, JsonStructure};
+use std::rc::Rc;
+
+/// Delimiters recognized when looking at the header row.
+const DELIMITERS: &[char] = &[',', ';', '\t', '|'];
+
+/// Splits delimited text into records of cells, following RFC 4180: cells can
+/// be quoted, quotes are escaped by doubling them, and quoted cells can span
+/// several lines. Returns each record along with the line it starts on.
+fn read_records(text: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, Error> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut cell = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if cell.is_empty() => in_quotes = true,
+            '\n' if in_quotes => {
+                line += 1;
+                cell.push(c);
+            }
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut cell));
+                // Blank lines don't hold a record
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push((record_line, std::mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                record_line = line;
+            }
+            c if c == delimiter && !in_quotes => record.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(Error::InvalidCsv {
+            line: record_line,
+            message: "Unclosed quoted cell".to_string(),
+        });
+    }
+    record.push(cell);
+    if record.len() > 1 || !record[0].is_empty() {
+        records.push((record_line, record));
+    }
+    Ok(records)
+}
+
+/// Guesses the delimiter from the header row, as the most common of the known
+/// delimiters outside of quotes.
+fn detect_delimiter(text: &str) -> char {
+    let header = text.lines().next().unwrap_or("");
+    let mut counts = vec![0; DELIMITERS.len()];
+    let mut in_quotes = false;
+    for c in header.chars() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if let Some(index) = DELIMITERS.iter().position(|d| *d == c) {
+            if !in_quotes {
+                counts[index] += 1;
+            }
+        }
+    }
+    let (index, _) =
+        counts.iter().enumerate().fold(
+            (0, 0),
+            |best, (i, &count)| if count > best.1 { (i, count) } else { best },
+        );
+    DELIMITERS[index]
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, PartialOrd, Ord)]
+enum ColumnKind {
+    Bool,
+    Int,
+    Float,
+    String,
+}
+
+fn cell_kind(cell: &str) -> ColumnKind {
+    if cell.eq_ignore_ascii_case("true") || cell.eq_ignore_ascii_case("false") {
+        ColumnKind::Bool
+    } else if cell.parse::<i64>().is_ok() {
+        ColumnKind::Int
+    } else if cell.parse::<f64>().is_ok() && cell.contains(|c: char| c.is_ascii_digit()) {
+        ColumnKind::Float
+    } else {
+        ColumnKind::String
+    }
+}
+
+/// The narrowest kind all the cells of a column can be read as. Booleans don't
+/// mix with numbers, and anything else makes the column a string column.
+fn column_kind(cells: &[&str]) -> ColumnKind {
+    cells
+        .iter()
+        .map(|cell| cell_kind(cell))
+        .fold(None, |kind, cell| match (kind, cell) {
+            (None, cell) => Some(cell),
+            (Some(ColumnKind::Bool), ColumnKind::Bool) => Some(ColumnKind::Bool),
+            (Some(ColumnKind::Bool), _) | (Some(_), ColumnKind::Bool) => Some(ColumnKind::String),
+            (Some(kind), cell) => Some(kind.max(cell)),
+        })
+        .unwrap_or(ColumnKind::String)
+}
+
+/// Turns the rows into json objects, typing each column as a whole, as every
+/// cell in a csv file is text.
+fn rows_to_nodes(text: &str) -> Result<Vec<JsonNode>, Error> {
+    let mut records = read_records(text, detect_delimiter(text))?.into_iter();
+    let header = match records.next() {
+        Some((_, header)) => header,
+        None => return Ok(Vec::new()),
+    };
+    let header: Vec<String> = header.iter().map(|h| h.trim().to_string()).collect();
+    let rows: Vec<(usize, Vec<String>)> = records.collect();
+
+    if let Some((line, row)) = rows.iter().find(|(_, row)| row.len() > header.len()) {
+        return Err(Error::InvalidCsv {
+            line: *line,
+            message: format!(
+                "Row has {} cells, but the header only has {}",
+                row.len(),
+                header.len()
+            ),
+        });
+    }
+
+    let kinds: Vec<ColumnKind> = (0..header.len())
+        .map(|column| {
+            let cells: Vec<&str> = rows
+                .iter()
+                .filter_map(|(_, row)| row.get(column))
+                .map(|cell| cell.trim())
+                .filter(|cell| !cell.is_empty())
+                .collect();
+            column_kind(&cells)
+        })
+        .collect();
+
+    let names: Vec<Rc<str>> = header.iter().map(|name| name.as_str().into()).collect();
+    Ok(rows
+        .iter()
+        .map(|(_, row)| {
+            // Rows shorter than the header leave the last columns out
+            let pairs = row
+                .iter()
+                .zip(names.iter().zip(&kinds))
+                .map(|(cell, (name, kind))| {
+                    let node = match (kind, cell.trim()) {
+                        (_, "") => JsonNode::Null,
+                        (ColumnKind::Bool, _) => JsonNode::Bool,
+                        (ColumnKind::Int, cell) => JsonNode::Int(cell.parse().unwrap_or(0)),
+                        (ColumnKind::Float, cell) => JsonNode::Float(cell.parse().unwrap_or(0.0)),
+                        (ColumnKind::String, _) => JsonNode::String(cell.clone()),
+                    };
+                    (Rc::clone(name), node)
+                })
+                .collect();
+            JsonNode::Object(pairs)
+        })
+        .collect())
+}
+
+/// Infers the structure of the rows of a delimited file, using the header row
+/// for field names. The delimiter is detected from the header, and can be a
+/// comma, semicolon, tab or pipe.
+///
+/// Columns are typed as a whole, so a column is only an int if every cell is,
+/// and empty cells make the column optional. The result is an array of row
+/// objects.
+pub fn convert_sample_csv(csv: &str) -> Result<JsonStructure, Error> {
+    convert_sample_csv_with_options(csv, &ConvertOptions::default())
+}
+
+pub fn convert_sample_csv_with_options(
+    csv: &str,
+    options: &ConvertOptions,
+) -> Result<JsonStructure, Error> {
+    Ok(convert_sample_csv_with_warnings(csv, options)?.structure)
+}
+
+pub fn convert_sample_csv_with_warnings(
+    csv: &str,
+    options: &ConvertOptions,
+) -> Result<Conversion, Error> {
+    let rows = JsonNode::Array(rows_to_nodes(csv)?);
+    crate::convert_node(&rows, options)
+}
+
+/// Converts a delimited file into a rust struct for its rows named `row_name`,
+/// along with a `Vec` alias for all the rows, named by adding an `s`.
+pub fn csv_to_rust(csv: &str, row_name: &str) -> Result<String, Error> {
+    let options = ConvertOptions::default();
+    let structure = convert_sample_csv_with_options(csv, &options)?;
+    let mut output = generate_rust_with_options(&structure, row_name, &options);
+    output.push_str(&format!("\npub type {}s = Vec<{}>;\n", row_name, row_name));
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_quoted_cells() {
+        let records = read_records("a,\"b,\"\"c\"\"\"\r\n\n\"multi\nline\",d", ',').unwrap();
+
+        assert_eq!(
+            records,
+            vec![
+                (1, vec!["a".to_string(), "b,\"c\"".to_string()]),
+                (3, vec!["multi\nline".to_string(), "d".to_string()]),
+            ]
+        );
+        assert_eq!(
+            read_records("a\n\"b", ','),
+            Err(Error::InvalidCsv {
+                line: 2,
+                message: "Unclosed quoted cell".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn detects_delimiters() {
+        assert_eq!(detect_delimiter("a,b,c"), ',');
+        assert_eq!(detect_delimiter("a;b;\"c,d\""), ';');
+        assert_eq!(detect_delimiter("a\tb"), '\t');
+        assert_eq!(detect_delimiter("a"), ',');
+    }
+
+    #[test]
+    fn infers_column_types() {
+        let result = convert_sample_csv(
+            "id;price;active;name;note;ip\n1;2;true;a;;127.0.0.1\n2;2.5;FALSE;3;x;::1\n3",
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            JsonStructure::array(
+                JsonStructure::object()
+                    .field("id", JsonStructure::int())
+                    .optional_field("price", JsonStructure::float())
+                    .optional_field("active", JsonStructure::bool())
+                    .optional_field("name", JsonStructure::string())
+                    .optional_field("note", JsonStructure::string())
+                    .optional_field(
+                        "ip",
+                        JsonStructure::custom(crate::RustType::new("std::net::IpAddr"))
+                    )
+                    .build()
+            )
+        )
+    }
+
+    #[test]
+    fn reports_rows_with_too_many_cells() {
+        assert_eq!(
+            convert_sample_csv("a,b\n1,2\n1,2,3"),
+            Err(Error::InvalidCsv {
+                line: 3,
+                message: "Row has 3 cells, but the header only has 2".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn generates_a_row_struct() {
+        let result = csv_to_rust("Order Id,Total\n1,9.99\n2,", "Order").unwrap();
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(clippy::struct_field_names)]
+pub struct Order {
+    #[serde(rename = "Order Id")]
+    pub order_id: i64,
+    #[serde(rename = "Total")]
+    pub total: Option<f64>,
+}
+
+pub type Orders = Vec<Order>;
+"#
+        )
+    }
+}
@@ -0,0 +1,430 @@
+//! The `deserialize_with` helpers that coerced fields are read with, such as
+//! numbers sent as strings. They are written after the generated types, or to a
+//! shared module set with `ConvertOptions::set_helpers_module`.
+
+/// Code the helpers share, emitted once before the helpers needing it.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum Support {
+    StringOrNumber,
+    /// `StringOrNumber::parse`, for the helpers that read numbers
+    Parse,
+    BoolOrInt,
+    IntOrFloat,
+}
+
+const STRING_OR_NUMBER: &str = "
+/// A value sent either as a json string or as a json number.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(serde_json::Number),
+}
+
+impl StringOrNumber {
+    fn into_string(self) -> String {
+        match self {
+            StringOrNumber::String(s) => s,
+            StringOrNumber::Number(n) => n.to_string(),
+        }
+    }
+";
+
+const STRING_OR_NUMBER_PARSE: &str = "
+    fn parse<T, E>(self) -> Result<T, E>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+        E: serde::de::Error,
+    {
+        self.into_string().trim().parse().map_err(E::custom)
+    }
+";
+
+const BOOL_OR_INT: &str = "
+/// A value sent either as a json bool or as 0 or 1.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum BoolOrInt {
+    Bool(bool),
+    Int(i64),
+}
+
+impl BoolOrInt {
+    fn into_bool<E: serde::de::Error>(self) -> Result<bool, E> {
+        match self {
+            BoolOrInt::Bool(b) => Ok(b),
+            BoolOrInt::Int(0) => Ok(false),
+            BoolOrInt::Int(1) => Ok(true),
+            BoolOrInt::Int(n) => Err(E::custom(format!(\"expected 0 or 1, found {n}\"))),
+        }
+    }
+}
+";
+
+const INT_OR_FLOAT: &str = "
+/// A whole number sent either as a json integer or as a float, such as `3.0`.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum IntOrFloat {
+    Int(i64),
+    Float(f64),
+}
+
+impl IntOrFloat {
+    fn into_int<E: serde::de::Error>(self) -> Result<i64, E> {
+        match self {
+            IntOrFloat::Int(n) => Ok(n),
+            IntOrFloat::Float(f)
+                if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 =>
+            {
+                Ok(f as i64)
+            }
+            IntOrFloat::Float(f) => Err(E::custom(format!(\"expected a whole number, found {f}\"))),
+        }
+    }
+}
+";
+
+struct Helper {
+    name: &'static str,
+    needs: &'static [Support],
+    code: &'static str,
+}
+
+/// Every helper, in the order they are emitted.
+const HELPERS: &[Helper] = &[
+    Helper {
+        name: "number_from_string",
+        needs: &[Support::StringOrNumber, Support::Parse],
+        code: "
+/// Deserializes a number sent either as a json number or as a string.
+fn number_from_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    <StringOrNumber as serde::Deserialize>::deserialize(deserializer)?.parse()
+}
+",
+    },
+    Helper {
+        name: "optional_number_from_string",
+        needs: &[Support::StringOrNumber, Support::Parse],
+        code: "
+/// Deserializes a number sent either as a json number or as a string, or null.
+fn optional_number_from_string<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    <Option<StringOrNumber> as serde::Deserialize>::deserialize(deserializer)?
+        .map(StringOrNumber::parse)
+        .transpose()
+}
+",
+    },
+    Helper {
+        name: "string_from_number",
+        needs: &[Support::StringOrNumber],
+        code: "
+/// Deserializes a string sent either as a json string or as a number.
+fn string_from_number<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(<StringOrNumber as serde::Deserialize>::deserialize(deserializer)?.into_string())
+}
+",
+    },
+    Helper {
+        name: "optional_string_from_number",
+        needs: &[Support::StringOrNumber],
+        code: "
+/// Deserializes a string sent either as a json string or as a number, or null.
+fn optional_string_from_number<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(<Option<StringOrNumber> as serde::Deserialize>::deserialize(deserializer)?
+        .map(StringOrNumber::into_string))
+}
+",
+    },
+    Helper {
+        name: "empty_string_as_none",
+        needs: &[Support::StringOrNumber],
+        code: "
+/// Deserializes an optional value sent as a string or a number, where an empty
+/// string means the value is missing.
+fn empty_string_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match <Option<StringOrNumber> as serde::Deserialize>::deserialize(deserializer)? {
+        Some(StringOrNumber::String(s)) if s.is_empty() => Ok(None),
+        Some(value) => value
+            .into_string()
+            .parse()
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+",
+    },
+    Helper {
+        name: "bool_from_int",
+        needs: &[Support::BoolOrInt],
+        code: "
+/// Deserializes a bool sent either as a json bool or as 0 or 1.
+fn bool_from_int<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    <BoolOrInt as serde::Deserialize>::deserialize(deserializer)?.into_bool()
+}
+",
+    },
+    Helper {
+        name: "optional_bool_from_int",
+        needs: &[Support::BoolOrInt],
+        code: "
+/// Deserializes a bool sent either as a json bool or as 0 or 1, or null.
+fn optional_bool_from_int<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    <Option<BoolOrInt> as serde::Deserialize>::deserialize(deserializer)?
+        .map(BoolOrInt::into_bool)
+        .transpose()
+}
+",
+    },
+    Helper {
+        name: "int_from_float",
+        needs: &[Support::IntOrFloat],
+        code: "
+/// Deserializes an integer sent either as a json integer or as a whole float.
+fn int_from_float<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    <IntOrFloat as serde::Deserialize>::deserialize(deserializer)?.into_int()
+}
+",
+    },
+    Helper {
+        name: "optional_int_from_float",
+        needs: &[Support::IntOrFloat],
+        code: "
+/// Deserializes an integer sent either as a json integer or as a whole float,
+/// or null.
+fn optional_int_from_float<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    <Option<IntOrFloat> as serde::Deserialize>::deserialize(deserializer)?
+        .map(IntOrFloat::into_int)
+        .transpose()
+}
+",
+    },
+    Helper {
+        name: "double_option",
+        needs: &[],
+        code: "
+/// Deserializes a field that may be null as `Some(None)`, so that with
+/// `#[serde(default)]` it can be told apart from a missing field, which is
+/// `None`.
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    <Option<T> as serde::Deserialize>::deserialize(deserializer).map(Some)
+}
+",
+    },
+    Helper {
+        name: "constant_int",
+        needs: &[],
+        code: "
+/// Deserializes an integer that must always be `VALUE`.
+fn constant_int<'de, D, const VALUE: i64>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <i64 as serde::Deserialize>::deserialize(deserializer)?;
+    if value == VALUE {
+        Ok(value)
+    } else {
+        Err(serde::de::Error::custom(format!(\"expected {VALUE}, found {value}\")))
+    }
+}
+",
+    },
+    Helper {
+        name: "json_string",
+        needs: &[],
+        code: "
+/// (De)serializes a value sent as a string holding json, with
+/// `#[serde(with = \"json_string\")]`.
+mod json_string {
+    pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+        T: serde::Serialize,
+    {
+        let json = serde_json::to_string(value).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&json)
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        T: serde::de::DeserializeOwned,
+    {
+        let json = <String as serde::Deserialize>::deserialize(deserializer)?;
+        serde_json::from_str(&json).map_err(serde::de::Error::custom)
+    }
+}
+",
+    },
+    Helper {
+        name: "optional_json_string",
+        needs: &[],
+        code: "
+/// (De)serializes a value sent as a string holding json, or null, with
+/// `#[serde(default, with = \"optional_json_string\")]`.
+mod optional_json_string {
+    pub fn serialize<S, T>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+        T: serde::Serialize,
+    {
+        match value {
+            Some(value) => {
+                let json = serde_json::to_string(value).map_err(serde::ser::Error::custom)?;
+                serializer.serialize_some(&json)
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        T: serde::de::DeserializeOwned,
+    {
+        <Option<String> as serde::Deserialize>::deserialize(deserializer)?
+            .map(|json| serde_json::from_str(&json).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+",
+    },
+];
+
+/// The helpers that are modules serializing as well as deserializing, used
+/// with `#[serde(with = "...")]` instead of `deserialize_with`.
+const MODULE_HELPERS: &[&str] = &["json_string", "optional_json_string"];
+
+/// The serde attribute a field is read with `helper` through.
+pub(crate) fn helper_attribute(helper: &str) -> &'static str {
+    if MODULE_HELPERS.contains(&helper) {
+        "with"
+    } else {
+        "deserialize_with"
+    }
+}
+
+/// The code of the helpers named in `used`, along with the code they share.
+/// Helpers in a shared module are `pub`.
+pub(crate) fn helpers_code(used: &[&str], shared: bool) -> String {
+    let helpers: Vec<&Helper> = HELPERS.iter().filter(|h| used.contains(&h.name)).collect();
+    let needs = |support: Support| helpers.iter().any(|h| h.needs.contains(&support));
+    let mut output = String::new();
+    if needs(Support::StringOrNumber) {
+        output.push_str(STRING_OR_NUMBER);
+        if needs(Support::Parse) {
+            output.push_str(STRING_OR_NUMBER_PARSE);
+        }
+        output.push_str("}\n");
+    }
+    if needs(Support::BoolOrInt) {
+        output.push_str(BOOL_OR_INT);
+    }
+    if needs(Support::IntOrFloat) {
+        output.push_str(INT_OR_FLOAT);
+    }
+    for helper in helpers {
+        if shared {
+            let code = helper.code.replacen("\nfn ", "\npub fn ", 1);
+            output.push_str(&code.replacen("\nmod ", "\npub mod ", 1));
+        } else {
+            output.push_str(helper.code);
+        }
+    }
+    output
+}
+
+/// The contents of the shared helpers module that `code`, generated with
+/// `ConvertOptions::set_helpers_module`, refers to. `None` if the code doesn't
+/// use any helpers.
+pub fn de_helpers_module(code: &str) -> Option<String> {
+    let used: Vec<&str> = HELPERS
+        .iter()
+        .map(|h| h.name)
+        .filter(|name| {
+            code.contains(&format!("::{}\"", name)) || code.contains(&format!("::{}::<", name))
+        })
+        .collect();
+    if used.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "//! Helpers for deserializing values sent in more than one form, used by\n\
+         //! the generated types.\n{}",
+        helpers_code(&used, true)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emits_only_the_helpers_used_and_what_they_need() {
+        let code = helpers_code(&["bool_from_int"], false);
+
+        assert!(code.contains("enum BoolOrInt {"));
+        assert!(code.contains("\nfn bool_from_int<'de, D>"));
+        assert!(!code.contains("StringOrNumber"));
+        assert!(!code.contains("optional_bool_from_int"));
+
+        let code = helpers_code(&["string_from_number", "number_from_string"], false);
+        assert!(code.find("enum StringOrNumber").unwrap() < code.find("fn parse<T, E>").unwrap());
+        assert!(
+            code.find("fn number_from_string").unwrap()
+                < code.find("fn string_from_number").unwrap()
+        );
+    }
+
+    #[test]
+    fn builds_a_shared_module_from_the_helpers_the_code_refers_to() {
+        let code = "pub struct Root {\n    \
+                    #[serde(default, deserialize_with = \"crate::de_helpers::empty_string_as_none\")]\n    \
+                    pub name: Option<String>,\n}\n";
+        let module = de_helpers_module(code).unwrap();
+
+        assert!(module.starts_with("//! Helpers for deserializing"));
+        assert!(module.contains("\npub fn empty_string_as_none<'de, D, T>"));
+        assert!(!module.contains("number_from_string"));
+        assert_eq!(de_helpers_module("pub struct Root {}\n"), None);
+    }
+}
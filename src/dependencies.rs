@@ -0,0 +1,233 @@
+/// A crate that generated code needs, to be added to the `Cargo.toml` of the
+/// crate the code is used in.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Dependency {
+    pub name: String,
+    pub version: String,
+    pub features: Vec<String>,
+    /// Only used by generated tests, so it belongs in `[dev-dependencies]`
+    pub dev: bool,
+}
+
+impl Dependency {
+    /// The dependency as a line of a `Cargo.toml` dependencies table.
+    pub fn to_toml(&self) -> String {
+        if self.features.is_empty() {
+            format!("{} = \"{}\"", self.name, self.version)
+        } else {
+            let features: Vec<String> =
+                self.features.iter().map(|f| format!("\"{}\"", f)).collect();
+            format!(
+                "{} = {{ version = \"{}\", features = [{}] }}",
+                self.name,
+                self.version,
+                features.join(", ")
+            )
+        }
+    }
+
+    /// The arguments to `cargo add` this dependency with.
+    pub fn cargo_add_args(&self) -> Vec<String> {
+        let mut args = vec!["add".to_string(), format!("{}@{}", self.name, self.version)];
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+        if self.dev {
+            args.push("--dev".to_string());
+        }
+        args
+    }
+}
+
+/// Crates that generated code can refer to, either through the types it
+/// generates or through type mappings and detectors, as
+/// `(name, version, features)`.
+const KNOWN_CRATES: &[(&str, &str, &[&str])] = &[
+    ("serde", "1", &["derive"]),
+    ("serde_json", "1", &[]),
+    ("chrono", "0.4", &["serde"]),
+    ("time", "0.3", &["serde"]),
+    ("uuid", "1", &["serde"]),
+    ("rust_decimal", "1", &["serde"]),
+    ("url", "2", &["serde"]),
+    ("semver", "1", &["serde"]),
+    ("bson", "2", &[]),
+    ("validator", "0.20", &["derive"]),
+    ("schemars", "1", &[]),
+    ("utoipa", "5", &[]),
+    ("reqwest", "0.12", &["json"]),
+    ("jsonschema", "0.30", &[]),
+    ("serde_repr", "0.1", &[]),
+];
+
+/// The features of schemars implementing `JsonSchema` for the types of other
+/// known crates, as `(crate, feature)`, needed to derive it on types with
+/// fields such as `semver::Version`.
+const SCHEMARS_FEATURES: &[(&str, &str)] = &[
+    ("chrono", "chrono04"),
+    ("uuid", "uuid1"),
+    ("rust_decimal", "rust_decimal1"),
+    ("url", "url2"),
+    ("semver", "semver1"),
+];
+
+/// Whether `code` refers to a path in the crate `name`, such as `uuid::Uuid`.
+fn uses_crate(code: &str, name: &str) -> bool {
+    let path = format!("{}::", name);
+    code.match_indices(&path).any(|(index, _)| {
+        code[..index]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '_' || c == ':'))
+    })
+}
+
+/// Finds the crates that generated `code` refers to, in the order of
+/// `KNOWN_CRATES`. Crates only used inside a `#[cfg(test)]` module, such as the
+/// one from `generate_sample_tests`, are marked as dev dependencies. Paths into
+/// crates that aren't known, such as from a custom type mapping, are left out,
+/// as their version can't be known.
+pub fn plan_dependencies(code: &str) -> Vec<Dependency> {
+    let (main, tests) = match code.find("#[cfg(test)]") {
+        Some(index) => code.split_at(index),
+        None => (code, ""),
+    };
+    KNOWN_CRATES
+        .iter()
+        .filter_map(|(name, version, features)| {
+            let dev = if uses_crate(main, name) {
+                false
+            } else if uses_crate(tests, name) {
+                true
+            } else {
+                return None;
+            };
+            let mut features: Vec<String> = features.iter().map(|f| f.to_string()).collect();
+            if *name == "schemars" {
+                features.extend(
+                    SCHEMARS_FEATURES
+                        .iter()
+                        .filter(|(other, _)| uses_crate(main, other))
+                        .map(|(_, feature)| feature.to_string()),
+                );
+            }
+            Some(Dependency {
+                name: name.to_string(),
+                version: version.to_string(),
+                features,
+                dev,
+            })
+        })
+        .collect()
+}
+
+/// Formats dependencies as `[dependencies]` and `[dev-dependencies]` tables,
+/// ready to paste into a `Cargo.toml`.
+pub fn dependencies_toml(dependencies: &[Dependency]) -> String {
+    let mut output = String::new();
+    for (table, dev) in &[("dependencies", false), ("dev-dependencies", true)] {
+        let lines: Vec<String> = dependencies
+            .iter()
+            .filter(|d| d.dev == *dev)
+            .map(Dependency::to_toml)
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!("[{}]\n", table));
+        for line in lines {
+            output.push_str(&line);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    pub id: uuid::Uuid,
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub ip: std::net::IpAddr,
+    pub extra: my_uuid::Id,
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn sample_deserializes() {
+        serde_json::from_str::<Root>("{}").unwrap();
+    }
+}
+"#;
+
+    #[test]
+    fn finds_crates_used_by_the_code() {
+        let names: Vec<(String, bool)> = plan_dependencies(CODE)
+            .into_iter()
+            .map(|d| (d.name, d.dev))
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                ("serde".to_string(), false),
+                ("serde_json".to_string(), true),
+                ("chrono".to_string(), false),
+                ("uuid".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_a_cargo_toml_snippet() {
+        assert_eq!(
+            dependencies_toml(&plan_dependencies(CODE)),
+            r#"[dependencies]
+serde = { version = "1", features = ["derive"] }
+chrono = { version = "0.4", features = ["serde"] }
+uuid = { version = "1", features = ["serde"] }
+
+[dev-dependencies]
+serde_json = "1"
+"#
+        );
+    }
+
+    #[test]
+    fn enables_the_schemars_impls_of_the_crates_used() {
+        let code = "#[derive(Serialize, Deserialize, schemars::JsonSchema)]\n\
+                    pub struct Root {\n    pub version: semver::Version,\n    \
+                    pub id: uuid::Uuid,\n}\n";
+        let schemars = plan_dependencies(code)
+            .into_iter()
+            .find(|d| d.name == "schemars")
+            .unwrap();
+
+        assert_eq!(schemars.features, vec!["uuid1", "semver1"]);
+    }
+
+    #[test]
+    fn builds_cargo_add_arguments() {
+        let dependency = Dependency {
+            name: "serde".to_string(),
+            version: "1".to_string(),
+            features: vec!["derive".to_string()],
+            dev: true,
+        };
+
+        assert_eq!(
+            dependency.cargo_add_args(),
+            vec!["add", "serde@1", "--features", "derive", "--dev"]
+        );
+    }
+}
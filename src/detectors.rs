@@ -0,0 +1,195 @@
+use crate::RustType;
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::net::IpAddr;
+
+/// Recognizes string fields that hold a well known format, so they can be
+/// generated as a more specific type than `String`.
+///
+/// `values` contains every string observed for the field across the sample,
+/// and is never empty.
+pub trait Detector: Send + Sync {
+    fn detect(&self, field_name: &str, values: &[&str]) -> Option<RustType>;
+}
+
+impl<F> Detector for F
+where
+    F: Fn(&str, &[&str]) -> Option<RustType> + Send + Sync,
+{
+    fn detect(&self, field_name: &str, values: &[&str]) -> Option<RustType> {
+        self(field_name, values)
+    }
+}
+
+/// Detects IPv4 and IPv6 addresses, mapping them to `std::net::IpAddr`.
+pub struct IpAddrDetector;
+
+impl Detector for IpAddrDetector {
+    fn detect(&self, _field_name: &str, values: &[&str]) -> Option<RustType> {
+        if values.iter().all(|v| v.parse::<IpAddr>().is_ok()) {
+            Some(RustType::new("std::net::IpAddr"))
+        } else {
+            None
+        }
+    }
+}
+
+/// Detects semantic version strings such as `1.2.3-beta.1+build.5`, mapping
+/// them to `semver::Version`.
+pub struct SemverDetector;
+
+fn is_numeric_identifier(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) && (s == "0" || !s.starts_with('0'))
+}
+
+fn are_valid_identifiers(s: &str) -> bool {
+    s.split('.')
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+}
+
+fn is_semver(value: &str) -> bool {
+    let (version, build) = match value.find('+') {
+        Some(i) => (&value[..i], Some(&value[i + 1..])),
+        None => (value, None),
+    };
+    let (core, pre_release) = match version.find('-') {
+        Some(i) => (&version[..i], Some(&version[i + 1..])),
+        None => (version, None),
+    };
+    let parts: Vec<&str> = core.split('.').collect();
+
+    parts.len() == 3
+        && parts.iter().all(|p| is_numeric_identifier(p))
+        && pre_release.is_none_or(are_valid_identifiers)
+        && build.is_none_or(are_valid_identifiers)
+}
+
+impl Detector for SemverDetector {
+    fn detect(&self, _field_name: &str, values: &[&str]) -> Option<RustType> {
+        if values.iter().all(|v| is_semver(v)) {
+            Some(RustType::new("semver::Version"))
+        } else {
+            None
+        }
+    }
+}
+
+// Characters that group thousands in the numbers of some locale
+const THOUSANDS_SEPARATORS: &[char] = &['.', ',', ' ', '\u{a0}', '\''];
+
+/// Whether a string is a number formatted for a locale, as spreadsheets export
+/// them, such as `1.234,56`, `12,5` or `1 234 567`. Plain numbers such as `1.5`
+/// are not.
+pub(crate) fn is_locale_number(value: &str) -> bool {
+    let digits = value
+        .strip_prefix('-')
+        .or_else(|| value.strip_prefix('+'))
+        .unwrap_or(value);
+    let separators: Vec<char> = digits
+        .chars()
+        .filter(|c| THOUSANDS_SEPARATORS.contains(c))
+        .collect();
+    let groups: Vec<&str> = digits
+        .split(|c| THOUSANDS_SEPARATORS.contains(&c))
+        .collect();
+    if separators.is_empty()
+        || groups
+            .iter()
+            .any(|g| g.is_empty() || !g.chars().all(|c| c.is_ascii_digit()))
+    {
+        return false;
+    }
+
+    // The last separator is the decimal one when it differs from the others,
+    // or when it is the only one and a comma
+    let last = separators[separators.len() - 1];
+    let has_decimal = match separators.len() {
+        1 => last == ',',
+        n => separators[..n - 1].iter().all(|c| *c == separators[0]) && last != separators[0],
+    };
+    if separators.len() == 1 && last == '.' {
+        return false;
+    }
+    let thousands = if has_decimal {
+        &groups[..groups.len() - 1]
+    } else {
+        &groups[..]
+    };
+    let thousands_separators = &separators[..thousands.len() - 1];
+    (last == ',' || last == '.' || !has_decimal)
+        && thousands_separators
+            .iter()
+            .all(|c| *c == thousands_separators[0])
+        && thousands[0].len() <= 3
+        && thousands[1..].iter().all(|g| g.len() == 3)
+}
+
+/// The detectors used by `ConvertOptions::default()`, in the order they are tried.
+pub fn default_detectors() -> Vec<Box<dyn Detector>> {
+    vec![Box::new(IpAddrDetector), Box::new(SemverDetector)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_ip_addresses() {
+        let result = IpAddrDetector.detect("ip", &["127.0.0.1", "::1", "2001:db8::ff00:42:8329"]);
+
+        assert_eq!(result, Some(RustType::new("std::net::IpAddr")))
+    }
+
+    #[test]
+    fn ignores_fields_with_non_ip_values() {
+        let result = IpAddrDetector.detect("ip", &["127.0.0.1", "localhost"]);
+
+        assert_eq!(result, None)
+    }
+
+    #[test]
+    fn recognizes_locale_numbers() {
+        for value in [
+            "1.234,56",
+            "12,5",
+            "-1 234 567",
+            "1\u{a0}000,25",
+            "1'234.5",
+            "1,234,567.89",
+        ] {
+            assert!(is_locale_number(value), "{}", value);
+        }
+        for value in [
+            "1.5",
+            "12",
+            "1.2.3",
+            "1,23,4",
+            "12345,678.9",
+            "1,,2",
+            "a,5",
+            ",5",
+            "1.234.5,6,7",
+        ] {
+            assert!(!is_locale_number(value), "{}", value);
+        }
+    }
+
+    #[test]
+    fn detects_semver_strings() {
+        let result =
+            SemverDetector.detect("version", &["1.0.0", "0.2.10-alpha.1", "3.1.4+build.5"]);
+
+        assert_eq!(result, Some(RustType::new("semver::Version")))
+    }
+
+    #[test]
+    fn rejects_invalid_semver_strings() {
+        for value in &["1.0", "1.0.0.0", "01.0.0", "1.0.0-", "1.0.0+a..b", "v1.0.0"] {
+            assert_eq!(
+                SemverDetector.detect("version", &[value]),
+                None,
+                "{}",
+                value
+            );
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Checks the strict parser against serde_json, finding documents only one of
+//! them accepts, to catch where the parser strays from the json spec.
+
+use crate::parser::parse_json;
+use crate::shared::Error;
+use crate::testing::{arbitrary_json, mangled_json};
+
+/// A document the parser and serde_json disagree about.
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub json: String,
+    pub divergence: Divergence,
+    /// The seed `testing::arbitrary_json` or `testing::mangled_json` generated
+    /// the document from, to reproduce it with, or `None` for the edge cases
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Divergence {
+    /// The parser accepts the document, but serde_json rejects it with
+    /// `message`
+    OnlyParserAccepts { message: String },
+    /// serde_json accepts the document, but the parser rejects it with `error`
+    OnlySerdeJsonAccepts { error: Error },
+    /// The parser accepts a number too large for an f64, such as `1E400`, as
+    /// an infinite float, but serde_json rejects it with `message`. This one is
+    /// on purpose, as the spec allows numbers of any size
+    NumberOutOfRange { message: String },
+}
+
+/// Documents at the edges of the spec, such as numbers with leading zeros and
+/// whitespace json doesn't allow.
+pub const EDGE_CASES: &[&str] = &[
+    "0",
+    "-0",
+    "00",
+    "-00",
+    "01",
+    "0.0",
+    "0e0",
+    "0E+0",
+    "-0.0e-0",
+    "1.",
+    ".5",
+    "-",
+    "+1",
+    "1e",
+    "1e+",
+    "-e5",
+    "-E1",
+    "1E400",
+    "[0,1]",
+    "[-0]",
+    "[01]",
+    " 1 ",
+    "\t[\n1\r]\n",
+    "\u{b}1",
+    "\u{c}1",
+    "\u{a0}1",
+    "\u{feff}1",
+    "[1,]",
+    "{\"a\":1,}",
+    "\"\\u0000\"",
+    "\"\\ud800\"",
+    "\"\t\"",
+    "\"\\x41\"",
+    "true false",
+    "nul",
+];
+
+/// Compares what the parser and serde_json make of `json`.
+pub fn compare(json: &str) -> Option<Finding> {
+    let divergence = match (
+        parse_json(json),
+        serde_json::from_str::<serde_json::Value>(json),
+    ) {
+        (Ok(_), Err(e)) if e.to_string().starts_with("number out of range") => {
+            Divergence::NumberOutOfRange {
+                message: e.to_string(),
+            }
+        }
+        (Ok(_), Err(e)) => Divergence::OnlyParserAccepts {
+            message: e.to_string(),
+        },
+        (Err(error), Ok(_)) => Divergence::OnlySerdeJsonAccepts { error },
+        _ => return None,
+    };
+    Some(Finding {
+        json: json.to_string(),
+        divergence,
+        seed: None,
+    })
+}
+
+/// Compares the parser and serde_json on every document of `EDGE_CASES`, and
+/// on those of `testing::arbitrary_json` and `testing::mangled_json` for the
+/// first `seeds` seeds.
+pub fn find_divergences(seeds: u64) -> Vec<Finding> {
+    let generated = (0..seeds).flat_map(|seed| {
+        [
+            (arbitrary_json(seed), Some(seed)),
+            (mangled_json(seed), Some(seed)),
+        ]
+    });
+    EDGE_CASES
+        .iter()
+        .map(|json| (json.to_string(), None))
+        .chain(generated)
+        .filter_map(|(json, seed)| {
+            Some(Finding {
+                seed,
+                ..compare(&json)?
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_everything_serde_json_accepts() {
+        let findings = find_divergences(2000);
+        let rejected: Vec<&Finding> = findings
+            .iter()
+            .filter(|f| matches!(f.divergence, Divergence::OnlySerdeJsonAccepts { .. }))
+            .collect();
+        assert_eq!(rejected, Vec::<&Finding>::new());
+        for seed in 0..2000 {
+            assert_eq!(compare(&arbitrary_json(seed)), None);
+        }
+    }
+
+    #[test]
+    fn rejects_everything_serde_json_rejects() {
+        let findings: Vec<Finding> = find_divergences(2000)
+            .into_iter()
+            .filter(|f| !matches!(f.divergence, Divergence::NumberOutOfRange { .. }))
+            .collect();
+        assert_eq!(findings, Vec::<Finding>::new());
+        assert!(matches!(
+            compare("1E400").unwrap().divergence,
+            Divergence::NumberOutOfRange { .. }
+        ));
+        for json in [
+            "[1,]",
+            "[1 2]",
+            "{\"a\":1 \"b\":2}",
+            "[,1]",
+            "[1,,2]",
+            "1,",
+            "\"\t\"",
+            "\"\\ud800\"",
+            "\"\\udc00\"",
+            "\"\\ud800\\n\"",
+            "-e5",
+            "-E1",
+        ] {
+            assert_eq!(compare(json), None, "{}", json);
+        }
+    }
+
+    #[test]
+    fn records_the_seed_of_generated_findings() {
+        for finding in find_divergences(200) {
+            match finding.seed {
+                Some(seed) => {
+                    assert!([arbitrary_json(seed), mangled_json(seed)].contains(&finding.json))
+                }
+                None => assert!(EDGE_CASES.contains(&finding.json.as_str())),
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::parser::JsonNode;
+use alloc::string::String;
+
+/// MongoDB extended json wrappers, by the key they start with, and the rust
+/// type of the value they wrap.
+const WRAPPERS: &[(&str, &str)] = &[
+    ("$oid", "bson::oid::ObjectId"),
+    ("$date", "bson::DateTime"),
+    ("$numberDecimal", "bson::Decimal128"),
+    ("$binary", "bson::Binary"),
+    ("$uuid", "bson::Uuid"),
+    ("$timestamp", "bson::Timestamp"),
+    ("$regularExpression", "bson::Regex"),
+    ("$regex", "bson::Regex"),
+    ("$minKey", "bson::Bson"),
+    ("$maxKey", "bson::Bson"),
+];
+
+/// Replaces the wrapper objects of MongoDB extended json, such as
+/// `{"$oid": "..."}`, with the values they stand for. Wrapped numbers become
+/// plain numbers, and other wrappers become their `bson` type.
+pub fn unwrap_extended_json(node: JsonNode) -> JsonNode {
+    match node {
+        JsonNode::Object(pairs) => {
+            // Wrappers only have `$` keys, such as the legacy
+            // `{"$binary": "...", "$type": "00"}`
+            let is_wrapper = !pairs.is_empty() && pairs.iter().all(|(key, _)| key.starts_with('$'));
+            if is_wrapper {
+                if let Some(node) = unwrap(&pairs[0].0, &pairs[0].1) {
+                    return node;
+                }
+            }
+            JsonNode::Object(
+                pairs
+                    .into_iter()
+                    .map(|(key, value)| (key, unwrap_extended_json(value)))
+                    .collect(),
+            )
+        }
+        JsonNode::Array(values) => {
+            JsonNode::Array(values.into_iter().map(unwrap_extended_json).collect())
+        }
+        other => other,
+    }
+}
+
+fn unwrap(key: &str, value: &JsonNode) -> Option<JsonNode> {
+    match key {
+        "$numberLong" | "$numberInt" => Some(JsonNode::Int(
+            value.as_str().and_then(|v| v.parse().ok()).unwrap_or(0),
+        )),
+        // Also "Infinity", "-Infinity" and "NaN", which parse as floats
+        "$numberDouble" => Some(JsonNode::Float(
+            value
+                .as_str()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(f64::NAN),
+        )),
+        "$symbol" | "$code" => Some(match value {
+            JsonNode::String(s) => JsonNode::String(s.clone()),
+            _ => JsonNode::String(String::new()),
+        }),
+        _ => WRAPPERS
+            .iter()
+            .find(|(wrapper, _)| *wrapper == key)
+            .map(|(_, rust_type)| JsonNode::Custom(rust_type)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_json;
+
+    #[test]
+    fn unwraps_extended_json() {
+        let node = parse_json(
+            r#"{"_id": {"$oid": "5f1"}, "at": {"$date": {"$numberLong": "1"}},
+                "n": [{"$numberLong": "1"}, {"$numberDouble": "1.5"}],
+                "bin": {"$binary": "AA==", "$type": "00"}, "price": {"amount": 1}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            unwrap_extended_json(node),
+            JsonNode::Object(vec![
+                ("_id".into(), JsonNode::Custom("bson::oid::ObjectId")),
+                ("at".into(), JsonNode::Custom("bson::DateTime")),
+                (
+                    "n".into(),
+                    JsonNode::Array(vec![JsonNode::Int(1), JsonNode::Float(1.5)])
+                ),
+                ("bin".into(), JsonNode::Custom("bson::Binary")),
+                (
+                    "price".into(),
+                    JsonNode::Object(vec![("amount".into(), JsonNode::Int(1))])
+                ),
+            ])
+        )
+    }
+
+    #[test]
+    fn leaves_unknown_dollar_keys_alone() {
+        let node = parse_json(r#"{"$set": {"a": 1}}"#).unwrap();
+
+        assert_eq!(unwrap_extended_json(node.clone()), node);
+    }
+}
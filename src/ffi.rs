@@ -0,0 +1,202 @@
+//! A C interface to the generator, so tools written in other languages, such
+//! as editor plugins, can embed it. Build it as a shared library with
+//! `cargo rustc --release --features ffi --crate-type cdylib`, and include
+//! `include/json2rust.h`.
+
+use crate::{conversion_to_rust, convert_sample_json_with_warnings, generated_file};
+use crate::{Config, ConvertOptions, Error};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+
+/// The code was generated.
+pub const JSON2RUST_OK: c_int = 0;
+/// `input` or `out` was null.
+pub const JSON2RUST_NULL_ARGUMENT: c_int = 1;
+/// `input` or `options_json` wasn't valid UTF-8.
+pub const JSON2RUST_INVALID_UTF8: c_int = 2;
+/// `options_json` wasn't valid json, or had an unknown or invalid setting.
+pub const JSON2RUST_INVALID_OPTIONS: c_int = 3;
+/// `input` wasn't valid json, or couldn't be converted with the options.
+pub const JSON2RUST_INVALID_INPUT: c_int = 4;
+/// The generator panicked, which is a bug in json2rust.
+pub const JSON2RUST_INTERNAL_ERROR: c_int = 5;
+
+/// Generates rust types for the json sample `input`.
+///
+/// `options_json` is either null or a json object with the tables of the
+/// `json2rust.toml` config, such as `{"inference": {"chaos_threshold": 4}}`,
+/// and optionally the name of the root type as `"root_name"`, `Root` by
+/// default. The generated code is written to `out` on success, and an error
+/// message otherwise, except with `JSON2RUST_NULL_ARGUMENT`. Either must be
+/// freed with `json2rust_free_string`.
+///
+/// Hosts converting samples from untrusted users can bound their size and
+/// nesting with `{"limits": {"max_input_bytes": ..., "max_depth": ...}}`.
+///
+/// # Safety
+///
+/// `input` and `options_json`, when not null, must point to nul terminated
+/// strings, and `out`, when not null, must be valid for writing a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn json2rust_generate(
+    input: *const c_char,
+    options_json: *const c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    if input.is_null() || out.is_null() {
+        return JSON2RUST_NULL_ARGUMENT;
+    }
+    let input = CStr::from_ptr(input);
+    let options_json = if options_json.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(options_json))
+    };
+    let (code, text) = panic::catch_unwind(AssertUnwindSafe(|| generate(input, options_json)))
+        .unwrap_or_else(|_| {
+            (
+                JSON2RUST_INTERNAL_ERROR,
+                "json2rust panicked while generating code".to_string(),
+            )
+        });
+    // Nul characters can only come from `\u0000` escapes in the sample
+    *out = CString::new(text.replace('\0', ""))
+        .unwrap_or_default()
+        .into_raw();
+    code
+}
+
+/// Frees a string returned by `json2rust_generate`. Does nothing if `s` is null.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by `json2rust_generate` that hasn't
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn json2rust_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+fn generate(input: &CStr, options_json: Option<&CStr>) -> (c_int, String) {
+    let input = match input.to_str() {
+        Ok(input) => input,
+        Err(_) => {
+            return (
+                JSON2RUST_INVALID_UTF8,
+                "input isn't valid UTF-8".to_string(),
+            )
+        }
+    };
+    let mut options = ConvertOptions::default();
+    let mut root_name = "Root".to_string();
+    if let Some(options_json) = options_json {
+        let options_json = match options_json.to_str() {
+            Ok(options_json) => options_json,
+            Err(_) => {
+                return (
+                    JSON2RUST_INVALID_UTF8,
+                    "options_json isn't valid UTF-8".to_string(),
+                )
+            }
+        };
+        match Config::parse_json(options_json) {
+            Ok(config) => config.apply(&mut options),
+            Err(e) => return (JSON2RUST_INVALID_OPTIONS, format!("Invalid options: {}", e)),
+        }
+        let root = crate::parser::parse_json(options_json)
+            .ok()
+            .and_then(|node| {
+                node.get("root_name")
+                    .map(|name| name.as_str().map(str::to_string))
+            });
+        match root {
+            Some(Some(name)) => root_name = name,
+            Some(None) => {
+                return (
+                    JSON2RUST_INVALID_OPTIONS,
+                    "Invalid options: 'root_name' must be a string".to_string(),
+                )
+            }
+            None => {}
+        }
+    }
+    match convert_sample_json_with_warnings(input, &options) {
+        Ok(conversion) => {
+            let code = conversion_to_rust(&conversion, &root_name, &options);
+            (JSON2RUST_OK, generated_file(&code, &options))
+        }
+        Err(e) => (JSON2RUST_INVALID_INPUT, invalid_input(input, e)),
+    }
+}
+
+fn invalid_input(input: &str, error: Error) -> String {
+    match error.suggestion(input) {
+        Some(suggestion) => format!("Invalid json: {}\nhint: {}", error, suggestion),
+        None => format!("Invalid json: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    fn run(input: &str, options_json: Option<&str>) -> (c_int, String) {
+        let input = CString::new(input).unwrap();
+        let options_json = options_json.map(|o| CString::new(o).unwrap());
+        let mut out = ptr::null_mut();
+        unsafe {
+            let code = json2rust_generate(
+                input.as_ptr(),
+                options_json.as_ref().map_or(ptr::null(), |o| o.as_ptr()),
+                &mut out,
+            );
+            let text = CStr::from_ptr(out).to_str().unwrap().to_string();
+            json2rust_free_string(out);
+            (code, text)
+        }
+    }
+
+    #[test]
+    fn generates_code_through_the_c_interface() {
+        let (code, text) = run(r#"{"id": 1}"#, None);
+        assert_eq!(code, JSON2RUST_OK);
+        assert!(text.contains("pub struct Root {"));
+
+        let (code, text) = run(
+            r#"{"id": 1}"#,
+            Some(r#"{"root_name": "ApiResponse", "codegen": {"header": "// @generated"}}"#),
+        );
+        assert_eq!(code, JSON2RUST_OK);
+        assert!(text.starts_with("// @generated\n"));
+        assert!(text.contains("pub struct ApiResponse {"));
+    }
+
+    #[test]
+    fn reports_errors_with_codes() {
+        let (code, text) = run("{\"id\": }", None);
+        assert_eq!(code, JSON2RUST_INVALID_INPUT);
+        assert_eq!(
+            text,
+            "Invalid json: Unexpected token ObjectEnd at line 1, column 8"
+        );
+
+        let (code, text) = run("{}", Some(r#"{"inference": {"chaos": 1}}"#));
+        assert_eq!(code, JSON2RUST_INVALID_OPTIONS);
+        assert_eq!(
+            text,
+            "Invalid options: line 1: Unknown setting 'inference.chaos'"
+        );
+
+        let (code, _) = run("{}", Some(r#"{"root_name": 5}"#));
+        assert_eq!(code, JSON2RUST_INVALID_OPTIONS);
+
+        let mut out = ptr::null_mut();
+        let code = unsafe { json2rust_generate(ptr::null(), ptr::null(), &mut out) };
+        assert_eq!(code, JSON2RUST_NULL_ARGUMENT);
+        assert!(out.is_null());
+    }
+}
@@ -0,0 +1,341 @@
+use crate::hash::Fnv;
+use crate::options::ConvertOptions;
+use crate::testing::diff;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Marks the start of a region of user code, kept when the file is regenerated.
+pub const USER_CODE_BEGIN: &str = "// json2rust: begin user code";
+/// Marks the end of a region of user code.
+pub const USER_CODE_END: &str = "// json2rust: end user code";
+
+/// Wraps generated code in the header and footer set with
+/// `ConvertOptions::set_header` and `ConvertOptions::set_footer`, followed by
+/// an empty region of user code with `ConvertOptions::set_user_code_region`,
+/// ready to be written to a file.
+pub fn generated_file(code: &str, options: &ConvertOptions) -> String {
+    let mut output = String::new();
+    if let Some(header) = &options.header {
+        push_block(&mut output, header);
+        output.push('\n');
+    }
+    output.push_str(code);
+    if let Some(footer) = &options.footer {
+        output.push('\n');
+        push_block(&mut output, footer);
+    }
+    if options.user_code_region {
+        output.push_str(&format!("\n{}\n{}\n", USER_CODE_BEGIN, USER_CODE_END));
+    }
+    output
+}
+
+/// Like `generated_file`, but starting with the `Banner` of the file when
+/// `ConvertOptions::set_banner` is set. `options_key` must describe everything
+/// else the code depends on, like for `GenerationCache::get_or_insert_with`.
+pub fn generated_file_with_banner(
+    code: &str,
+    samples: &[&str],
+    options_key: &str,
+    options: &ConvertOptions,
+) -> String {
+    let file = generated_file(code, options);
+    if !options.banner {
+        return file;
+    }
+    let mut banner = Banner::new(samples, options_key);
+    if options.banner_timestamp {
+        banner.generated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|duration| duration.as_secs());
+    }
+    format!("{}\n{}", banner, file)
+}
+
+/// Where a generated file came from, written as comments at its top with
+/// `ConvertOptions::set_banner`, so tools can tell whether the file is up to
+/// date with its samples without generating it again.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Banner {
+    /// The version of json2rust the file was generated with
+    pub version: String,
+    /// A hash of the key describing the options the file was generated with
+    pub options_hash: u64,
+    /// A hash of every sample the file was generated from, in order
+    pub sample_hashes: Vec<u64>,
+    /// When the file was generated, in seconds since the unix epoch, with
+    /// `ConvertOptions::set_banner_timestamp`
+    pub generated_at: Option<u64>,
+}
+
+impl Banner {
+    /// The banner of a file generated now from `samples`, without a
+    /// timestamp.
+    pub fn new(samples: &[&str], options_key: &str) -> Banner {
+        Banner {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            options_hash: hash(options_key),
+            sample_hashes: samples.iter().map(|sample| hash(sample)).collect(),
+            generated_at: None,
+        }
+    }
+
+    /// Reads the banner at the top of a generated file, if it has one.
+    pub fn parse(code: &str) -> Option<Banner> {
+        let mut banner = Banner {
+            version: String::new(),
+            options_hash: 0,
+            sample_hashes: Vec::new(),
+            generated_at: None,
+        };
+        let mut has_options = false;
+        for line in code.lines() {
+            let entry = match line.strip_prefix(BANNER_PREFIX) {
+                Some(entry) => entry,
+                None => break,
+            };
+            if let Some(version) = entry.strip_prefix("version ") {
+                banner.version = version.to_string();
+            } else if let Some(options) = entry.strip_prefix("options ") {
+                banner.options_hash = u64::from_str_radix(options, 16).ok()?;
+                has_options = true;
+            } else if let Some(sample) = entry.strip_prefix("sample ") {
+                banner
+                    .sample_hashes
+                    .push(u64::from_str_radix(sample, 16).ok()?);
+            } else if let Some(time) = entry.strip_prefix("generated at ") {
+                banner.generated_at = Some(time.parse().ok()?);
+            } else {
+                break;
+            }
+        }
+        if banner.version.is_empty() || !has_options {
+            return None;
+        }
+        Some(banner)
+    }
+
+    /// Whether a file with this banner is what this version of json2rust
+    /// generates from `samples` with the options described by `options_key`.
+    /// The timestamp doesn't matter.
+    pub fn is_up_to_date(&self, samples: &[&str], options_key: &str) -> bool {
+        let current = Banner::new(samples, options_key);
+        self.version == current.version
+            && self.options_hash == current.options_hash
+            && self.sample_hashes == current.sample_hashes
+    }
+}
+
+impl std::fmt::Display for Banner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}version {}", BANNER_PREFIX, self.version)?;
+        writeln!(f, "{}options {:016x}", BANNER_PREFIX, self.options_hash)?;
+        for sample in &self.sample_hashes {
+            writeln!(f, "{}sample {:016x}", BANNER_PREFIX, sample)?;
+        }
+        if let Some(time) = self.generated_at {
+            writeln!(f, "{}generated at {}", BANNER_PREFIX, time)?;
+        }
+        Ok(())
+    }
+}
+
+/// Starts every line of a `Banner`.
+const BANNER_PREFIX: &str = "// json2rust: ";
+
+fn hash(text: &str) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.write_str(text);
+    hasher.finish()
+}
+
+fn push_block(output: &mut String, block: &str) {
+    output.push_str(block);
+    if !block.ends_with('\n') {
+        output.push('\n');
+    }
+}
+
+/// The lines inside every region of user code, in order.
+fn user_code_regions(code: &str) -> Vec<Vec<&str>> {
+    let mut regions = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    for line in code.lines() {
+        match line.trim() {
+            USER_CODE_BEGIN => current = Some(Vec::new()),
+            USER_CODE_END => regions.extend(current.take()),
+            _ => {
+                if let Some(region) = &mut current {
+                    region.push(line);
+                }
+            }
+        }
+    }
+    regions
+}
+
+/// Carries the user code in the regions of a previously generated file over
+/// to the same regions of a newly generated one, so code written between the
+/// markers survives regeneration. Regions are matched in order, and user code
+/// from regions the new file doesn't have is kept in a region at its end.
+pub fn merge_user_code(previous: &str, generated: &str) -> String {
+    let mut previous_regions = user_code_regions(previous).into_iter();
+    let mut output = String::new();
+    let mut in_region = false;
+    for line in generated.lines() {
+        match line.trim() {
+            USER_CODE_BEGIN => {
+                output.push_str(line);
+                output.push('\n');
+                if let Some(region) = previous_regions.next() {
+                    for user_line in region {
+                        output.push_str(user_line);
+                        output.push('\n');
+                    }
+                    in_region = true;
+                }
+            }
+            USER_CODE_END => {
+                in_region = false;
+                output.push_str(line);
+                output.push('\n');
+            }
+            _ if in_region => {}
+            _ => {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+    }
+    let leftover: Vec<&str> = previous_regions.flatten().collect();
+    if !leftover.is_empty() {
+        output.push_str(&format!("\n{}\n", USER_CODE_BEGIN));
+        for line in leftover {
+            output.push_str(line);
+            output.push('\n');
+        }
+        output.push_str(USER_CODE_END);
+        output.push('\n');
+    }
+    output
+}
+
+/// A line diff from a committed generated file to the file generated now,
+/// such as with `generated_file_with_banner`, or `None` if the committed file
+/// is up to date. User code is carried over to the new file first, and the
+/// timestamps of banners don't count.
+pub fn stale_file_diff(committed: &str, generated: &str) -> Option<String> {
+    let generated = merge_user_code(committed, generated);
+    let without_timestamp = |code: &str| -> String {
+        code.lines()
+            .filter(|line| !line.starts_with(&format!("{}generated at ", BANNER_PREFIX)))
+            .map(|line| format!("{}\n", line))
+            .collect()
+    };
+    let (committed, generated) = (without_timestamp(committed), without_timestamp(&generated));
+    if committed == generated {
+        None
+    } else {
+        Some(diff(&committed, &generated))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_code_in_a_header_and_footer() {
+        let mut options = ConvertOptions::default();
+        options
+            .set_header(Some("//! @generated\n#![allow(clippy::all)]".to_string()))
+            .set_footer(Some("pub type Id = i64;\n".to_string()))
+            .set_user_code_region(true);
+
+        assert_eq!(
+            generated_file("pub struct Root;\n", &options),
+            "//! @generated\n#![allow(clippy::all)]\n\npub struct Root;\n\n\
+             pub type Id = i64;\n\n\
+             // json2rust: begin user code\n// json2rust: end user code\n"
+        );
+        assert_eq!(
+            generated_file("pub struct Root;\n", &ConvertOptions::default()),
+            "pub struct Root;\n"
+        );
+    }
+
+    #[test]
+    fn writes_and_parses_the_banner() {
+        let mut options = ConvertOptions::default();
+        options.set_banner(true);
+
+        let file =
+            generated_file_with_banner("pub struct Root;\n", &["{}"], "--name Root", &options);
+        let banner = Banner::parse(&file).unwrap();
+
+        assert!(file.starts_with(&format!(
+            "// json2rust: version {}\n// json2rust: options ",
+            env!("CARGO_PKG_VERSION")
+        )));
+        assert!(file.ends_with("\n\npub struct Root;\n"));
+        assert_eq!(banner, Banner::new(&["{}"], "--name Root"));
+        assert!(banner.is_up_to_date(&["{}"], "--name Root"));
+        assert!(!banner.is_up_to_date(&["[]"], "--name Root"));
+        assert!(!banner.is_up_to_date(&["{}"], "--name User"));
+
+        options.set_banner_timestamp(true);
+        let file = generated_file_with_banner("", &["{}"], "", &options);
+        assert!(Banner::parse(&file).unwrap().generated_at.is_some());
+        assert_eq!(Banner::parse("pub struct Root;\n"), None);
+    }
+
+    #[test]
+    fn diffs_stale_files() {
+        let committed = "// json2rust: version 0.1.0\n// json2rust: generated at 1\n\
+                         pub struct Root;\n\n\
+                         // json2rust: begin user code\nimpl Root {}\n// json2rust: end user code\n";
+        let generated = "// json2rust: version 0.1.0\n// json2rust: generated at 2\n\
+                         pub struct Root;\n\n\
+                         // json2rust: begin user code\n// json2rust: end user code\n";
+
+        assert_eq!(stale_file_diff(committed, generated), None);
+        assert_eq!(
+            stale_file_diff(committed, &generated.replace("Root;", "User;")),
+            Some(
+                " // json2rust: version 0.1.0\n-pub struct Root;\n+pub struct User;\n \n \
+                 // json2rust: begin user code\n impl Root {}\n // json2rust: end user code\n"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_user_code_when_regenerating() {
+        let previous = "pub struct Root {\n    pub id: i64,\n}\n\n\
+                        // json2rust: begin user code\n\
+                        impl Root {\n    pub fn new() -> Root { Root { id: 0 } }\n}\n\
+                        // json2rust: end user code\n";
+        let generated = "pub struct Root {\n    pub id: u64,\n}\n\n\
+                         // json2rust: begin user code\n// json2rust: end user code\n";
+
+        assert_eq!(
+            merge_user_code(previous, generated),
+            "pub struct Root {\n    pub id: u64,\n}\n\n\
+             // json2rust: begin user code\n\
+             impl Root {\n    pub fn new() -> Root { Root { id: 0 } }\n}\n\
+             // json2rust: end user code\n"
+        );
+    }
+
+    #[test]
+    fn keeps_user_code_the_new_file_has_no_region_for() {
+        let previous =
+            "// json2rust: begin user code\nconst A: u8 = 1;\n// json2rust: end user code\n";
+
+        assert_eq!(
+            merge_user_code(previous, "pub struct Root;\n"),
+            "pub struct Root;\n\n\
+             // json2rust: begin user code\nconst A: u8 = 1;\n// json2rust: end user code\n"
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use crate::codegen::{generate_rust_module, Direction};
+use crate::naming::{to_snake_case, unique_name};
+use crate::options::ConvertOptions;
+use crate::parser::{parse_json, JsonNode};
+use crate::shared::Error;
+use crate::{convert_nodes, JsonPair, JsonStructure};
+
+/// The types inferred for one endpoint of a captured API.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Endpoint {
+    pub method: String,
+    /// The url path, with segments that look like ids replaced by `{id}`
+    pub path: String,
+    /// Merged from every json request body sent to the endpoint
+    pub request: Option<JsonStructure>,
+    /// Merged from every json response from the endpoint
+    pub response: Option<JsonStructure>,
+}
+
+impl Endpoint {
+    /// A snake case name for the endpoint, e.g. `get_users_id`.
+    pub fn module_name(&self) -> String {
+        to_snake_case(&format!("{} {}", self.method, self.path))
+    }
+}
+
+/// Parses the json body of a request or response, if it has one.
+fn json_body(content: Option<&JsonNode>) -> Option<JsonNode> {
+    let content = content?;
+    let is_json = content
+        .get("mimeType")
+        .and_then(JsonNode::as_str)
+        .is_some_and(|m| m.contains("json"));
+    // Base64 encoded bodies are binary
+    if !is_json || content.get("encoding").is_some() {
+        return None;
+    }
+    parse_json(content.get("text")?.as_str()?).ok()
+}
+
+fn is_id(segment: &str) -> bool {
+    let is_number = !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit());
+    let is_uuid = segment.len() == 36
+        && segment.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+        && segment.matches('-').count() == 4;
+    let is_hash = segment.len() >= 16 && segment.chars().all(|c| c.is_ascii_hexdigit());
+    is_number || is_uuid || is_hash
+}
+
+/// The path of a url, without the query, and with ids replaced by `{id}`, so
+/// requests for different resources are grouped as the same endpoint.
+pub fn url_pattern(url: &str) -> String {
+    let without_scheme = url.find("://").map_or(url, |i| &url[i + 3..]);
+    let path = match without_scheme.find('/') {
+        Some(i) if url.contains("://") => &without_scheme[i..],
+        Some(_) => without_scheme,
+        None => "/",
+    };
+    let path = path.split(['?', '#']).next().unwrap_or("");
+    let segments: Vec<&str> = path
+        .split('/')
+        .map(|segment| if is_id(segment) { "{id}" } else { segment })
+        .collect();
+    match segments.join("/") {
+        pattern if pattern.is_empty() => "/".to_string(),
+        pattern => pattern,
+    }
+}
+
+/// Groups the entries of an HTTP Archive (`.har`) file by endpoint, and infers
+/// a type for the json request and response bodies of each. Endpoints are
+/// returned in the order they were first requested.
+pub fn convert_har(har: &str, options: &ConvertOptions) -> Result<Vec<Endpoint>, Error> {
+    let root = parse_json(har)?;
+    let entries = match root.get("log").and_then(|log| log.get("entries")) {
+        Some(JsonNode::Array(entries)) => entries,
+        _ => {
+            return Err(Error::InvalidSchema(
+                "Expected a 'log.entries' array in the har file".to_string(),
+            ))
+        }
+    };
+
+    // Endpoints with the bodies seen for them, as (method, path, requests, responses)
+    let mut groups: Vec<(String, String, Vec<JsonNode>, Vec<JsonNode>)> = Vec::new();
+    for entry in entries {
+        let request = entry.get("request");
+        let method = request
+            .and_then(|r| r.get("method"))
+            .and_then(JsonNode::as_str);
+        let url = request
+            .and_then(|r| r.get("url"))
+            .and_then(JsonNode::as_str);
+        let (method, url) = match method.zip(url) {
+            Some(request) => request,
+            None => continue,
+        };
+        let path = url_pattern(url);
+        let index = match groups
+            .iter()
+            .position(|(m, p, _, _)| *m == method && *p == path)
+        {
+            Some(index) => index,
+            None => {
+                groups.push((method.to_string(), path, Vec::new(), Vec::new()));
+                groups.len() - 1
+            }
+        };
+        if let Some(body) = json_body(request.and_then(|r| r.get("postData"))) {
+            groups[index].2.push(body);
+        }
+        let content = entry.get("response").and_then(|r| r.get("content"));
+        if let Some(body) = json_body(content) {
+            groups[index].3.push(body);
+        }
+    }
+
+    let infer = |bodies: &[JsonNode]| {
+        if bodies.is_empty() {
+            return Ok(None);
+        }
+        let nodes: Vec<&JsonNode> = bodies.iter().collect();
+        Ok(Some(convert_nodes(&nodes, options)?.structure))
+    };
+    groups
+        .iter()
+        .map(|(method, path, requests, responses)| {
+            Ok(Endpoint {
+                method: method.clone(),
+                path: path.clone(),
+                request: infer(requests)?,
+                response: infer(responses)?,
+            })
+        })
+        .collect()
+}
+
+/// Generates a module for every endpoint with json bodies in an HTTP Archive,
+/// holding a `Request` type for the request body and a `Response` type for
+/// the response body.
+pub fn har_to_rust(har: &str, options: &ConvertOptions) -> Result<String, Error> {
+    let mut output = String::new();
+    let mut modules: Vec<String> = Vec::new();
+    for endpoint in convert_har(har, options)? {
+        let types = [
+            ("Request", Direction::Request, &endpoint.request),
+            ("Response", Direction::Response, &endpoint.response),
+        ];
+        if types.iter().all(|(_, _, s)| s.is_none()) {
+            continue;
+        }
+
+        let module = unique_name(endpoint.module_name(), &mut modules);
+        let roots: Vec<(JsonPair, Direction)> = types
+            .iter()
+            .filter_map(|(name, direction, s)| {
+                s.as_ref()
+                    .map(|s| (JsonPair::new(*name, s.clone()), *direction))
+            })
+            .collect();
+        let doc = format!("`{} {}`", endpoint.method, endpoint.path);
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&generate_rust_module(&module, &doc, &roots, options));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAR: &str = r#"{"log": {"version": "1.2", "entries": [
+        {"request": {"method": "GET", "url": "https://api.example.com/users/1?fields=all"},
+         "response": {"status": 200, "content": {"mimeType": "application/json",
+            "text": "{\"id\": 1, \"name\": \"a\"}"}}},
+        {"request": {"method": "GET", "url": "https://api.example.com/users/2"},
+         "response": {"status": 200, "content": {"mimeType": "application/json; charset=utf-8",
+            "text": "{\"id\": 2, \"name\": \"b\", \"email\": \"b@example.com\"}"}}},
+        {"request": {"method": "POST", "url": "https://api.example.com/users",
+            "postData": {"mimeType": "application/json", "text": "{\"name\": \"c\"}"}},
+         "response": {"status": 201, "content": {"mimeType": "application/json", "text": "{\"id\": 3}"}}},
+        {"request": {"method": "GET", "url": "https://api.example.com/logo.png"},
+         "response": {"status": 200, "content": {"mimeType": "image/png", "text": "AA==", "encoding": "base64"}}}
+    ]}}"#;
+
+    #[test]
+    fn builds_url_patterns() {
+        assert_eq!(
+            url_pattern("https://a.com/users/12/posts?x=1"),
+            "/users/{id}/posts"
+        );
+        assert_eq!(
+            url_pattern("http://a.com/o/123e4567-e89b-12d3-a456-426614174000#top"),
+            "/o/{id}"
+        );
+        assert_eq!(url_pattern("https://a.com"), "/");
+        assert_eq!(url_pattern("/relative/5"), "/relative/{id}");
+    }
+
+    #[test]
+    fn groups_entries_by_endpoint() {
+        let endpoints = convert_har(HAR, &ConvertOptions::default()).unwrap();
+
+        assert_eq!(
+            endpoints,
+            vec![
+                Endpoint {
+                    method: "GET".into(),
+                    path: "/users/{id}".into(),
+                    request: None,
+                    response: Some(
+                        JsonStructure::object()
+                            .field("id", JsonStructure::int())
+                            .field("name", JsonStructure::string())
+                            .optional_field("email", JsonStructure::string())
+                            .build()
+                    ),
+                },
+                Endpoint {
+                    method: "POST".into(),
+                    path: "/users".into(),
+                    request: Some(
+                        JsonStructure::object()
+                            .field("name", JsonStructure::string())
+                            .build()
+                    ),
+                    response: Some(
+                        JsonStructure::object()
+                            .field("id", JsonStructure::int())
+                            .build()
+                    ),
+                },
+                Endpoint {
+                    method: "GET".into(),
+                    path: "/logo.png".into(),
+                    request: None,
+                    response: None,
+                },
+            ]
+        )
+    }
+
+    #[test]
+    fn generates_a_module_per_endpoint() {
+        let result = har_to_rust(HAR, &ConvertOptions::default()).unwrap();
+
+        assert_eq!(
+            result,
+            r#"/// `GET /users/{id}`
+pub mod get_users_id {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Response {
+        pub id: i64,
+        pub name: String,
+        pub email: Option<String>,
+    }
+}
+
+/// `POST /users`
+pub mod post_users {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Request {
+        pub name: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Response {
+        pub id: i64,
+    }
+}
+"#
+        )
+    }
+
+    #[test]
+    fn rejects_files_without_entries() {
+        assert_eq!(
+            convert_har(r#"{"entries": []}"#, &ConvertOptions::default()),
+            Err(Error::InvalidSchema(
+                "Expected a 'log.entries' array in the har file".to_string()
+            ))
+        );
+    }
+}
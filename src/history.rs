@@ -0,0 +1,339 @@
+//! The changes between two structures, such as the structure of a snapshot
+//! before and after more samples were merged into it, kept as a record of how
+//! a document drifted over time, or two snapshots of an API, to catch changes
+//! that break its consumers.
+
+use crate::accumulator::merge_structures;
+use crate::json_writer::JsonOutput;
+use crate::pointer::{child_path, element_path};
+use crate::{JsonStructure, JsonValue};
+use std::fmt;
+
+/// How a path of a structure changed.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Change {
+    /// A field the old structure didn't have
+    Added { path: String, type_name: String },
+    /// A field the new structure doesn't have
+    Removed { path: String, type_name: String },
+    /// A value that became a wider type, such as an integer seen as a float
+    Widened {
+        path: String,
+        from: String,
+        to: String,
+    },
+    /// A value that became a narrower type, such as a float seen only as
+    /// integers
+    Narrowed {
+        path: String,
+        from: String,
+        to: String,
+    },
+    /// A value that became an unrelated type, such as a string seen as a number
+    Changed {
+        path: String,
+        from: String,
+        to: String,
+    },
+    /// A value that was always present and not null before
+    BecameOptional { path: String },
+    /// A value that could be missing or null before
+    BecameRequired { path: String },
+}
+
+impl Change {
+    /// Json pointer to the value that changed, with `*` standing in for array
+    /// elements and the values of maps.
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Widened { path, .. }
+            | Change::Narrowed { path, .. }
+            | Change::Changed { path, .. }
+            | Change::BecameOptional { path }
+            | Change::BecameRequired { path } => path,
+        }
+    }
+
+    /// Whether the change breaks consumers of the types generated from the old
+    /// structure: documents they accepted may no longer deserialize into the
+    /// new types, or a value they read is gone.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            Change::Added { type_name, .. } => !type_name.starts_with("optional "),
+            Change::Removed { .. }
+            | Change::Narrowed { .. }
+            | Change::Changed { .. }
+            | Change::BecameRequired { .. } => true,
+            Change::Widened { .. } | Change::BecameOptional { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = match self.path() {
+            "" => "/",
+            path => path,
+        };
+        match self {
+            Change::Added { type_name, .. } => write!(f, "added {}: {}", path, type_name),
+            Change::Removed { type_name, .. } => write!(f, "removed {}: {}", path, type_name),
+            Change::Widened { from, to, .. } => {
+                write!(f, "widened {} from {} to {}", path, from, to)
+            }
+            Change::Narrowed { from, to, .. } => {
+                write!(f, "narrowed {} from {} to {}", path, from, to)
+            }
+            Change::Changed { from, to, .. } => {
+                write!(f, "changed {} from {} to {}", path, from, to)
+            }
+            Change::BecameOptional { .. } => write!(f, "{} became optional", path),
+            Change::BecameRequired { .. } => write!(f, "{} became required", path),
+        }
+    }
+}
+
+/// An update of a snapshot with more samples.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Revision {
+    /// When the samples were merged in, in seconds since the unix epoch
+    pub at: u64,
+    /// The names of the samples merged in
+    pub samples: Vec<String>,
+    pub changes: Vec<Change>,
+}
+
+impl fmt::Display for Revision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}, {} sample{}",
+            utc_time(self.at),
+            self.samples.len(),
+            if self.samples.len() == 1 { "" } else { "s" }
+        )?;
+        if !self.samples.is_empty() {
+            write!(f, " ({})", self.samples.join(", "))?;
+        }
+        if self.changes.is_empty() {
+            f.write_str("\n  no changes\n")
+        } else {
+            f.write_str("\n")?;
+            self.changes
+                .iter()
+                .try_for_each(|change| writeln!(f, "  {}", change))
+        }
+    }
+}
+
+/// `seconds` since the unix epoch as a date and time in UTC, such as
+/// `2021-04-01 12:30 UTC`.
+fn utc_time(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let minutes = seconds % 86400 / 60;
+    // The civil from days algorithm of Howard Hinnant
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// A short description of a type, for the changes.
+pub(crate) fn describe(structure: &JsonStructure) -> String {
+    match structure {
+        JsonStructure::Object(_) => "object".to_string(),
+        JsonStructure::Array(element) => format!("array of {}", describe(element)),
+        JsonStructure::Dictionary(pair) => format!("map of {}", describe(pair.value())),
+        JsonStructure::Optional(inner) => format!("optional {}", describe(inner)),
+        JsonStructure::Union(variants) => {
+            let variants: Vec<String> = variants.iter().map(describe).collect();
+            format!("union of {}", variants.join(", "))
+        }
+        JsonStructure::Value(JsonValue::String) => "string".to_string(),
+        JsonStructure::Value(JsonValue::Int) => "int".to_string(),
+        JsonStructure::Value(JsonValue::Float) => "float".to_string(),
+        JsonStructure::Value(JsonValue::Bool) => "bool".to_string(),
+        JsonStructure::Value(JsonValue::Custom(rust_type)) => rust_type.path().to_string(),
+        JsonStructure::Unknown => "unknown".to_string(),
+    }
+}
+
+/// The changes from the structure `old` to the structure `new`, such as
+/// between two snapshots of the same document.
+pub fn structure_changes(old: &JsonStructure, new: &JsonStructure) -> Vec<Change> {
+    let mut changes = Vec::new();
+    compare(old, new, "", &mut changes);
+    changes
+}
+
+fn compare(old: &JsonStructure, new: &JsonStructure, path: &str, changes: &mut Vec<Change>) {
+    match (old.is_optional(), new.is_optional()) {
+        (false, true) => changes.push(Change::BecameOptional {
+            path: path.to_string(),
+        }),
+        (true, false) => changes.push(Change::BecameRequired {
+            path: path.to_string(),
+        }),
+        _ => {}
+    }
+    match (old.non_optional(), new.non_optional()) {
+        (JsonStructure::Object(old), JsonStructure::Object(new)) => {
+            for pair in old {
+                if !new.iter().any(|new| new.key() == pair.key()) {
+                    changes.push(Change::Removed {
+                        path: child_path(path, pair.key()),
+                        type_name: describe(pair.value()),
+                    });
+                }
+            }
+            for pair in new {
+                let path = child_path(path, pair.key());
+                match old.iter().find(|old| old.key() == pair.key()) {
+                    Some(old) => compare(old.value(), pair.value(), &path, changes),
+                    None => changes.push(Change::Added {
+                        path,
+                        type_name: describe(pair.value()),
+                    }),
+                }
+            }
+        }
+        (JsonStructure::Array(old), JsonStructure::Array(new)) => {
+            compare(old, new, &element_path(path), changes)
+        }
+        (JsonStructure::Dictionary(old), JsonStructure::Dictionary(new)) => {
+            compare(old.value(), new.value(), &element_path(path), changes)
+        }
+        (old, new) if old != new => {
+            let (path, from, to) = (path.to_string(), describe(old), describe(new));
+            // A type is wider than another when it describes its values as well
+            changes.push(if merge_structures(old.clone(), new.clone()) == *new {
+                Change::Widened { path, from, to }
+            } else if merge_structures(new.clone(), old.clone()) == *old {
+                Change::Narrowed { path, from, to }
+            } else {
+                Change::Changed { path, from, to }
+            })
+        }
+        _ => {}
+    }
+}
+
+/// A change as json, as it is written in the history of a snapshot.
+pub(crate) fn change_to_json(change: &Change) -> JsonOutput {
+    let mut pairs = vec![
+        (
+            "change",
+            JsonOutput::string(match change {
+                Change::Added { .. } => "added",
+                Change::Removed { .. } => "removed",
+                Change::Widened { .. } => "widened",
+                Change::Narrowed { .. } => "narrowed",
+                Change::Changed { .. } => "changed",
+                Change::BecameOptional { .. } => "optional",
+                Change::BecameRequired { .. } => "required",
+            }),
+        ),
+        ("path", JsonOutput::string(change.path())),
+    ];
+    match change {
+        Change::Added { type_name, .. } | Change::Removed { type_name, .. } => {
+            pairs.push(("type", JsonOutput::string(type_name.as_str())))
+        }
+        Change::Widened { from, to, .. }
+        | Change::Narrowed { from, to, .. }
+        | Change::Changed { from, to, .. } => {
+            pairs.push(("from", JsonOutput::string(from.as_str())));
+            pairs.push(("to", JsonOutput::string(to.as_str())));
+        }
+        Change::BecameOptional { .. } | Change::BecameRequired { .. } => {}
+    }
+    pairs.push(("breaking", JsonOutput::Bool(change.is_breaking())));
+    JsonOutput::object(pairs)
+}
+
+/// Writes changes as a json array, each with whether it is breaking.
+pub fn changes_to_json(changes: &[Change]) -> String {
+    JsonOutput::Array(changes.iter().map(change_to_json).collect()).to_pretty_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    #[test]
+    fn finds_what_changed() {
+        let old = convert_sample_json(r#"{"id": 1, "name": "a", "tags": [1]}"#).unwrap();
+        let new = crate::accumulator::merge_structures(
+            old.clone(),
+            convert_sample_json(r#"{"id": 1.5, "tags": [null], "a/b": {"c": true}}"#).unwrap(),
+        );
+
+        assert_eq!(
+            structure_changes(&old, &new)
+                .iter()
+                .map(Change::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "widened /id from int to float",
+                "/name became optional",
+                "/tags/* became optional",
+                "added /a~1b: optional object",
+            ]
+        );
+        assert!(structure_changes(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn classifies_breaking_changes() {
+        let old =
+            convert_sample_json(r#"{"id": 1.5, "name": "a", "tags": [1], "note": null}"#).unwrap();
+        let new =
+            convert_sample_json(r#"{"id": 1, "name": 2, "tags": [1.5], "note": "n", "age": 3}"#)
+                .unwrap();
+
+        let changes: Vec<(String, bool)> = structure_changes(&old, &new)
+            .iter()
+            .map(|change| (change.to_string(), change.is_breaking()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("narrowed /id from float to int".to_string(), true),
+                ("changed /name from string to int".to_string(), true),
+                ("widened /tags/* from int to float".to_string(), false),
+                ("/note became required".to_string(), true),
+                ("widened /note from unknown to string".to_string(), false),
+                ("added /age: int".to_string(), true),
+            ]
+        );
+        let removed = structure_changes(&new, &old);
+        assert!(removed.contains(&Change::Removed {
+            path: "/age".to_string(),
+            type_name: "int".to_string(),
+        }));
+    }
+
+    #[test]
+    fn writes_times_in_utc() {
+        assert_eq!(utc_time(0), "1970-01-01 00:00 UTC");
+        assert_eq!(utc_time(1_617_280_200), "2021-04-01 12:30 UTC");
+        assert_eq!(utc_time(951_782_400), "2000-02-29 00:00 UTC");
+    }
+}
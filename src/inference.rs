@@ -0,0 +1,1177 @@
+use crate::detectors::is_locale_number;
+use crate::naming::{field_name, unique_field_names};
+use crate::options::{
+    ConvertOptions, FloatPolicy, MixedNumberPolicy, UnionTagging, WholeFloatPolicy,
+};
+use crate::parser::{parse_json, JsonNode};
+use crate::pointer::{child_path, element_path};
+#[cfg(feature = "std")]
+use crate::secrets::secret_kind;
+use crate::stats::{PathStats, StringFormat};
+use crate::warnings::Warning;
+use crate::{JsonPair, JsonStructure, JsonValue, RustType};
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// Objects looked at between checks for cancellation.
+const CANCELLATION_INTERVAL: usize = 4096;
+
+/// The types of integers outside the range of an `i64`, the type of every
+/// other integer.
+pub(crate) const U64: &str = "u64";
+pub(crate) const I128: &str = "i128";
+
+/// The type of numbers with a fraction with `FloatPolicy::Decimal`.
+const DECIMAL: &str = "rust_decimal::Decimal";
+
+pub struct Inference<'a> {
+    options: &'a ConvertOptions,
+    pub warnings: Vec<Warning>,
+    pub stats: Vec<PathStats>,
+    /// Paths that were both strings and numbers, with `MixedNumberPolicy::Error`
+    pub mixed_number_paths: Vec<String>,
+    /// Paths of numbers with a fraction, with `FloatPolicy::Error`
+    pub float_paths: Vec<String>,
+    /// Whether inference stopped early, with `ConvertOptions::set_cancellation`
+    pub cancelled: bool,
+    // How many times every node of a sample weighing other than 1 counts in
+    // the stats, by address, see `set_sample_weights`
+    weights: BTreeMap<*const JsonNode, usize>,
+}
+
+impl<'a> Inference<'a> {
+    pub fn new(options: &'a ConvertOptions) -> Inference<'a> {
+        Inference {
+            options,
+            warnings: Vec::new(),
+            stats: Vec::new(),
+            mixed_number_paths: Vec::new(),
+            float_paths: Vec::new(),
+            cancelled: false,
+            weights: BTreeMap::new(),
+        }
+    }
+
+    /// Counts every value of each sample as many times as its weight in the
+    /// stats, for `convert_samples_json_weighted`, without affecting the
+    /// structure.
+    pub(crate) fn set_sample_weights(&mut self, samples: &[(&JsonNode, usize)]) {
+        for (sample, weight) in samples.iter().filter(|(_, weight)| *weight != 1) {
+            let mut pending = vec![*sample];
+            while let Some(node) = pending.pop() {
+                self.weights.insert(node as *const JsonNode, *weight);
+                match node {
+                    JsonNode::Object(pairs) => pending.extend(pairs.iter().map(|(_, v)| v)),
+                    JsonNode::Array(elements) => pending.extend(elements),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn weight(&self, node: &JsonNode) -> usize {
+        self.weights
+            .get(&(node as *const JsonNode))
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// The number of `nodes`, each counted as many times as its weight.
+    fn weighted_count<'n>(&self, nodes: impl IntoIterator<Item = &'n &'n JsonNode>) -> usize {
+        nodes.into_iter().map(|node| self.weight(node)).sum()
+    }
+
+    /// Infers a single structure describing every node in `nodes`, which are all
+    /// the values observed at `path` in the sample.
+    pub fn infer_structure(
+        &mut self,
+        nodes: &[&JsonNode],
+        path: &str,
+        field_name: &str,
+    ) -> JsonStructure {
+        if self.cancelled || self.options.is_cancelled() {
+            self.cancelled = true;
+            return JsonStructure::Unknown;
+        }
+        let mut stats = PathStats::observe(path, nodes, self.options.redact_samples);
+        if !self.weights.is_empty() {
+            stats.count = self.weighted_count(nodes);
+            stats.null_count = self.weighted_count(nodes.iter().filter(|n| ***n == JsonNode::Null));
+            stats.present_count = stats.count;
+            stats.parent_count = stats.count;
+        }
+        if let Some(kind) = self.find_secret(nodes, field_name) {
+            self.warnings.push(Warning::new(
+                path,
+                format!(
+                    "Found what looks like {}, so no examples of it are kept. Samples with \
+                     credentials shouldn't be committed along with the generated code",
+                    kind
+                ),
+            ));
+            stats.examples.clear();
+            stats.string_values = None;
+        }
+        self.stats.push(stats);
+
+        if self.options.forced_value_paths.iter().any(|p| p == path) {
+            return JsonStructure::Unknown;
+        }
+
+        let mut non_null: Vec<&JsonNode> = nodes
+            .iter()
+            .cloned()
+            .filter(|n| **n != JsonNode::Null)
+            .collect();
+        // Empty strings only count as null among other strings and numbers,
+        // which the generated helper can read
+        let with_empty_strings = non_null.clone();
+        if self.options.empty_strings_as_none
+            && non_null.iter().all(|n| {
+                matches!(
+                    n,
+                    JsonNode::String(_) | JsonNode::Int(_) | JsonNode::Float(_)
+                )
+            })
+            && non_null.iter().any(|n| n.as_str() != Some(""))
+        {
+            non_null.retain(|n| n.as_str() != Some(""));
+        }
+
+        if nodes.is_empty() {
+            return JsonStructure::Unknown;
+        }
+        if non_null.is_empty() {
+            return JsonStructure::Optional(Box::new(JsonStructure::Unknown));
+        }
+
+        if let Some(tagging) = self.options.union_tagging(path) {
+            if let Some(structure) = self.infer_tagged(&non_null, path, tagging) {
+                return if non_null.len() < nodes.len() {
+                    make_optional(structure)
+                } else {
+                    structure
+                };
+            }
+        }
+
+        if let Some(threshold) = self.options.chaos_threshold {
+            let shapes = count_shapes(&non_null);
+            if shapes > threshold {
+                self.warnings.push(Warning::new(
+                    path,
+                    format!(
+                        "Found {} different shapes, which is more than the chaos threshold of {}, \
+                         so serde_json::Value is used instead",
+                        shapes, threshold
+                    ),
+                ));
+                return JsonStructure::Unknown;
+            }
+        }
+
+        let mut structure = self.infer_non_null(&non_null, path, field_name);
+        // The helper parses values with `FromStr`, which detected types may
+        // not implement, so they keep their empty strings
+        if non_null.len() < with_empty_strings.len()
+            && matches!(structure, JsonStructure::Value(JsonValue::Custom(_)))
+        {
+            non_null = with_empty_strings;
+            structure = self.infer_non_null(&non_null, path, field_name);
+        }
+        if non_null.len() < nodes.len() {
+            make_optional(structure)
+        } else {
+            structure
+        }
+    }
+
+    /// What the first string of `nodes` that looks like a credential looks
+    /// like, with `ConvertOptions::set_detect_secrets`.
+    #[cfg(feature = "std")]
+    fn find_secret(&self, nodes: &[&JsonNode], field_name: &str) -> Option<&'static str> {
+        if !self.options.detect_secrets {
+            return None;
+        }
+        nodes
+            .iter()
+            .filter_map(|n| n.as_str())
+            .find_map(|s| secret_kind(field_name, s))
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn find_secret(&self, _: &[&JsonNode], _: &str) -> Option<&'static str> {
+        None
+    }
+
+    /// Infers the variants of a union tagged as hinted with
+    /// `ConvertOptions::tag_union`, each as an object with a single field named
+    /// after the variant, holding the value of the variant. Returns `None`,
+    /// after warning, if a node isn't tagged that way.
+    fn infer_tagged(
+        &mut self,
+        nodes: &[&JsonNode],
+        path: &str,
+        tagging: &UnionTagging,
+    ) -> Option<JsonStructure> {
+        // The values of the variants, in the order they are first seen
+        let mut groups: Vec<(&str, Vec<&JsonNode>)> = Vec::new();
+        for node in nodes {
+            let (name, value) = match tagged_variant(node, tagging) {
+                Some(variant) => variant,
+                None => {
+                    let encoding = match tagging {
+                        UnionTagging::Internal { tag } => {
+                            format!("an object with the string field \"{}\"", tag)
+                        }
+                        UnionTagging::Adjacent { tag, content } => format!(
+                            "an object with the string field \"{}\" and the field \"{}\"",
+                            tag, content
+                        ),
+                        UnionTagging::External => "an object with a single field".to_string(),
+                    };
+                    self.warnings.push(Warning::new(
+                        path,
+                        format!(
+                            "Found a value that isn't {}, so it isn't generated as a tagged union",
+                            encoding
+                        ),
+                    ));
+                    return None;
+                }
+            };
+            match groups.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, group)) => group.push(value),
+                None => groups.push((name, vec![value])),
+            }
+        }
+
+        let mut variants: Vec<JsonStructure> = Vec::new();
+        for (name, group) in groups {
+            let value = match tagging {
+                UnionTagging::Internal { tag } => match self.infer_object(&group, path, name) {
+                    JsonStructure::Object(mut pairs) => {
+                        pairs.retain(|pair| pair.key() != tag);
+                        JsonStructure::Object(pairs)
+                    }
+                    other => other,
+                },
+                UnionTagging::Adjacent { content, .. } => {
+                    self.infer_structure(&group, &child_path(path, content), name)
+                }
+                UnionTagging::External => {
+                    self.infer_structure(&group, &child_path(path, name), name)
+                }
+            };
+            variants.push(JsonStructure::Object(vec![JsonPair::new(name, value)]));
+        }
+        Some(match variants.len() {
+            1 => variants.remove(0),
+            _ => JsonStructure::Union(variants),
+        })
+    }
+
+    fn infer_non_null(
+        &mut self,
+        nodes: &[&JsonNode],
+        path: &str,
+        field_name: &str,
+    ) -> JsonStructure {
+        if self.options.json_strings {
+            if let Some(decoded) = json_strings(nodes) {
+                if let Some(stats) = self.stats.iter_mut().rev().find(|s| s.path == path) {
+                    stats.string_format = Some(StringFormat::Json);
+                }
+                let decoded: Vec<&JsonNode> = decoded.iter().collect();
+                return self.infer_non_null(&decoded, path, field_name);
+            }
+        }
+        // Group the nodes by their kind, in the order the kinds are first seen
+        let mut groups: Vec<(NodeKind, Vec<&JsonNode>)> = Vec::new();
+        for node in nodes {
+            let kind = kind_of(node);
+            match groups.iter_mut().find(|(k, _)| *k == kind) {
+                Some((_, group)) => group.push(node),
+                None => groups.push((kind, vec![node])),
+            }
+        }
+
+        let mut variants: Vec<JsonStructure> = groups
+            .iter()
+            .map(|(kind, group)| self.infer_group(*kind, group, path, field_name))
+            .collect();
+
+        if let Some(threshold) = self.options.int_bool_threshold {
+            // Only struct fields are coerced, array elements and map values
+            // have paths ending in `*`
+            let is_field = !path.is_empty() && !path.ends_with("/*");
+            if is_field
+                && variants.as_slice() == [JsonStructure::int()]
+                && nodes.len() >= threshold
+                && nodes
+                    .iter()
+                    .all(|n| matches!(n, JsonNode::Int(0) | JsonNode::Int(1)))
+            {
+                self.warnings.push(Warning::new(
+                    path,
+                    format!(
+                        "Found only 0 and 1 in {} values, so bool is used, deserialized with \
+                         bool_from_int",
+                        nodes.len()
+                    ),
+                ));
+                return JsonStructure::Union(vec![JsonStructure::bool(), JsonStructure::int()]);
+            }
+        }
+        if variants.len() == 1 {
+            return variants.remove(0);
+        }
+        let union = JsonStructure::Union(variants);
+        if self.options.numeric_bools() && union.bool_or_int() {
+            let other = nodes.iter().find_map(|n| match n {
+                JsonNode::Int(value) if *value != 0 && *value != 1 => Some(*value),
+                _ => None,
+            });
+            if let Some(value) = other {
+                self.warnings.push(Warning::new(
+                    path,
+                    format!(
+                        "Found {}, which isn't 0 or 1, so deserializing samples like this one \
+                         with bool_from_int will fail",
+                        value
+                    ),
+                ));
+            }
+            return union;
+        }
+        if union.string_or_number().is_none() {
+            return union;
+        }
+        match self.options.mixed_numbers {
+            MixedNumberPolicy::Error => self.mixed_number_paths.push(path.to_string()),
+            MixedNumberPolicy::Number => return self.number_union(union, nodes, path),
+            _ => {}
+        }
+        union
+    }
+
+    /// Widens the number in a union of a string and a number to a float if any
+    /// of the strings is one, and warns about strings that aren't numbers, which
+    /// the generated helper rejects.
+    fn number_union(
+        &mut self,
+        union: JsonStructure,
+        nodes: &[&JsonNode],
+        path: &str,
+    ) -> JsonStructure {
+        let strings: Vec<&str> = nodes.iter().filter_map(|n| n.as_str()).collect();
+        if let Some(s) = strings.iter().find(|s| s.trim().parse::<f64>().is_err()) {
+            self.warnings.push(Warning::new(
+                path,
+                format!(
+                    "Found \"{}\", which isn't a number, so deserializing samples like this \
+                     one with number_from_string will fail",
+                    s
+                ),
+            ));
+        }
+        let has_floats = strings.iter().any(|s| s.trim().parse::<i64>().is_err());
+        match union {
+            JsonStructure::Union(variants) if has_floats => JsonStructure::Union(
+                variants
+                    .into_iter()
+                    .map(|v| match v {
+                        JsonStructure::Value(JsonValue::Int) => JsonStructure::float(),
+                        other => other,
+                    })
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    fn infer_group(
+        &mut self,
+        kind: NodeKind,
+        nodes: &[&JsonNode],
+        path: &str,
+        field_name: &str,
+    ) -> JsonStructure {
+        match kind {
+            NodeKind::Object => self.infer_object(nodes, path, field_name),
+            NodeKind::Array => {
+                let elements: Vec<&JsonNode> = nodes
+                    .iter()
+                    .flat_map(|n| match n {
+                        JsonNode::Array(values) => values.iter(),
+                        _ => [].iter(),
+                    })
+                    .collect();
+                JsonStructure::Array(Box::new(self.infer_structure(
+                    &elements,
+                    &element_path(path),
+                    field_name,
+                )))
+            }
+            NodeKind::String => {
+                let values: Vec<&str> = nodes
+                    .iter()
+                    .filter_map(|n| match n {
+                        JsonNode::String(s) => Some(s.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                let detected = self
+                    .options
+                    .detectors
+                    .iter()
+                    .find_map(|d| d.detect(field_name, &values));
+                match detected {
+                    Some(rust_type) => JsonStructure::Value(JsonValue::Custom(rust_type)),
+                    None if self.options.locale_numbers
+                        && values.iter().all(|v| is_locale_number(v)) =>
+                    {
+                        if let Some(structure) = self.denied_float(path) {
+                            return structure;
+                        }
+                        self.warnings.push(Warning::new(
+                            path,
+                            format!(
+                                "Found numbers formatted for a locale, such as \"{}\", so f64 is \
+                                 used, which needs a custom deserializer to read them",
+                                values[0]
+                            ),
+                        ));
+                        JsonStructure::Value(JsonValue::Float)
+                    }
+                    None => JsonStructure::Value(JsonValue::String),
+                }
+            }
+            NodeKind::Number => {
+                if nodes
+                    .iter()
+                    .any(|n| matches!(n, JsonNode::Float(v) if !v.is_finite()))
+                {
+                    self.warnings.push(Warning::new(
+                        path,
+                        "Found NaN or Infinity, which serde_json can't read, so deserializing \
+                         samples like this one will fail",
+                    ));
+                }
+                if nodes.iter().any(|n| matches!(n, JsonNode::Float(_))) {
+                    self.denied_float(path)
+                        .unwrap_or_else(|| self.infer_float(nodes, path))
+                } else {
+                    self.infer_int(nodes, path)
+                }
+            }
+            NodeKind::Bool => JsonStructure::Value(JsonValue::Bool),
+            NodeKind::Bytes => JsonStructure::custom(RustType::new("Vec<u8>")),
+            NodeKind::Custom(rust_type) => JsonStructure::custom(RustType::new(rust_type)),
+        }
+    }
+
+    /// The type of numbers with a fraction at `path` when `FloatPolicy` rules
+    /// out `f64`, recording the path if they are an error.
+    fn denied_float(&mut self, path: &str) -> Option<JsonStructure> {
+        match self.options.floats {
+            FloatPolicy::Float => None,
+            FloatPolicy::Decimal => Some(JsonStructure::custom(RustType::new(DECIMAL))),
+            FloatPolicy::Error => {
+                self.float_paths.push(path.to_string());
+                Some(JsonStructure::float())
+            }
+        }
+    }
+
+    /// `f64`, unless every number is a whole one and `WholeFloatPolicy` says
+    /// otherwise.
+    fn infer_float(&mut self, nodes: &[&JsonNode], path: &str) -> JsonStructure {
+        let policy = self.options.whole_floats;
+        if policy == WholeFloatPolicy::Float {
+            return JsonStructure::float();
+        }
+        let whole = nodes.iter().all(|n| match n {
+            JsonNode::Int(value) => *value >= i64::MIN as i128 && *value <= i64::MAX as i128,
+            // `i64::MAX as f64` rounds up to 2^63, which doesn't fit
+            JsonNode::Float(value) => {
+                *value >= i64::MIN as f64
+                    && *value < i64::MAX as f64
+                    && (*value as i64) as f64 == *value
+            }
+            _ => false,
+        });
+        let example = nodes.iter().find_map(|n| match n {
+            JsonNode::Float(value) => Some(*value),
+            _ => None,
+        });
+        let example = match example {
+            Some(example) if whole => example,
+            _ => return JsonStructure::float(),
+        };
+        // Only struct fields are coerced, as with `int_bool_threshold`
+        let is_field = !path.is_empty() && !path.ends_with("/*");
+        if policy == WholeFloatPolicy::Int && is_field {
+            self.warnings.push(Warning::new(
+                path,
+                format!(
+                    "Found only whole numbers, such as {:?}, so i64 is used, deserialized with \
+                     int_from_float",
+                    example
+                ),
+            ));
+            return JsonStructure::Union(vec![JsonStructure::int(), JsonStructure::float()]);
+        }
+        self.warnings.push(Warning::new(
+            path,
+            format!(
+                "Found only whole numbers, such as {:?}, so these may be integers, but f64 is \
+                 used",
+                example
+            ),
+        ));
+        JsonStructure::float()
+    }
+
+    /// `i64` if every integer fits one, which is by far the most common, or
+    /// else `u64` or `i128`.
+    fn infer_int(&mut self, nodes: &[&JsonNode], path: &str) -> JsonStructure {
+        let values = nodes.iter().filter_map(|n| match n {
+            JsonNode::Int(value) => Some(*value),
+            _ => None,
+        });
+        let (min, max) = values.fold((0, 0), |(min, max), v| (v.min(min), v.max(max)));
+        if min >= i64::MIN as i128 && max <= i64::MAX as i128 {
+            return JsonStructure::int();
+        }
+        if min >= 0 && max <= u64::MAX as i128 {
+            return JsonStructure::custom(RustType::new(U64));
+        }
+        if min < 0 && max > i64::MAX as i128 && max <= u64::MAX as i128 {
+            self.warnings.push(Warning::new(
+                path,
+                format!(
+                    "Found both {} and {}, so i128 is used, as neither i64 nor u64 can hold both",
+                    min, max
+                ),
+            ));
+        }
+        JsonStructure::custom(RustType::new(I128))
+    }
+
+    fn infer_object(&mut self, nodes: &[&JsonNode], path: &str, field_name: &str) -> JsonStructure {
+        // Keys in the order they are first seen, with the values and the number of
+        // objects they were present in
+        let mut keys: Vec<(&str, Vec<&JsonNode>, usize)> = Vec::new();
+        for (i, node) in nodes.iter().enumerate() {
+            if i % CANCELLATION_INTERVAL == CANCELLATION_INTERVAL - 1 && self.options.is_cancelled()
+            {
+                self.cancelled = true;
+                return JsonStructure::Unknown;
+            }
+            let pairs = match node {
+                JsonNode::Object(pairs) => pairs,
+                _ => continue,
+            };
+            let mut seen_in_object: Vec<&str> = Vec::new();
+            for (key, value) in pairs {
+                let index = match keys.iter().position(|(k, _, _)| *k == &**key) {
+                    Some(index) => index,
+                    None => {
+                        keys.push((&**key, Vec::new(), 0));
+                        keys.len() - 1
+                    }
+                };
+                keys[index].1.push(value);
+                if !seen_in_object.contains(&&**key) {
+                    seen_in_object.push(key);
+                    keys[index].2 += 1;
+                }
+            }
+        }
+
+        // Empty and whitespace only keys rarely come from a fixed schema, so
+        // objects with them are treated as maps, if their values agree
+        if keys.iter().any(|(key, _, _)| key.trim().is_empty()) {
+            let values: Vec<&JsonNode> = keys
+                .iter()
+                .flat_map(|(_, values, _)| values.iter().cloned())
+                .collect();
+            let value = self.infer_structure(&values, &element_path(path), field_name);
+            if !is_union(&value) {
+                return JsonStructure::Dictionary(JsonPair {
+                    key: field_name.to_string(),
+                    value: Box::new(value),
+                });
+            }
+        }
+
+        self.warn_about_colliding_keys(&keys, path);
+
+        JsonStructure::Object(
+            keys.into_iter()
+                .map(|(key, values, present_count)| {
+                    let child = child_path(path, key);
+                    let structure = self.infer_structure(&values, &child, key);
+                    let (weighted_present, weighted_parents) = if self.weights.is_empty() {
+                        (present_count, nodes.len())
+                    } else {
+                        let with_key = nodes.iter().filter(|node| match node {
+                            JsonNode::Object(pairs) => pairs.iter().any(|(k, _)| **k == *key),
+                            _ => false,
+                        });
+                        (self.weighted_count(with_key), self.weighted_count(nodes))
+                    };
+                    if let Some(stats) = self.stats.iter_mut().rev().find(|s| s.path == child) {
+                        stats.present_count = weighted_present;
+                        stats.parent_count = weighted_parents;
+                    }
+                    let structure = if present_count < nodes.len() || self.options.all_optional {
+                        make_optional(structure)
+                    } else {
+                        structure
+                    };
+                    JsonPair {
+                        key: key.to_string(),
+                        value: Box::new(structure),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Warns about keys that only differ by case, such as `userId` and
+    /// `user_id`, which would otherwise become the same field.
+    fn warn_about_colliding_keys(&mut self, keys: &[(&str, Vec<&JsonNode>, usize)], path: &str) {
+        let keys: Vec<&str> = keys.iter().map(|(key, _, _)| *key).collect();
+        let names: Vec<String> = keys.iter().map(|key| field_name(key)).collect();
+        let unique = unique_field_names(&keys, &names);
+        for (i, name) in unique.iter().enumerate() {
+            if *name == names[i] {
+                continue;
+            }
+            let other = keys
+                .iter()
+                .zip(&unique)
+                .find(|(_, n)| **n == names[i])
+                .map_or("", |(key, _)| *key);
+            self.warnings.push(Warning::new(
+                path,
+                format!(
+                    "Keys {:?} and {:?} both become the field {}, so {:?} is named {} instead",
+                    other, keys[i], names[i], keys[i], name
+                ),
+            ));
+        }
+    }
+}
+
+fn is_union(structure: &JsonStructure) -> bool {
+    match structure {
+        JsonStructure::Union(_) => true,
+        JsonStructure::Optional(inner) => is_union(inner),
+        _ => false,
+    }
+}
+
+/// The json objects and arrays that `nodes` hold as strings, with
+/// `ConvertOptions::set_json_strings`, or `None` unless every node is one.
+fn json_strings(nodes: &[&JsonNode]) -> Option<Vec<JsonNode>> {
+    nodes
+        .iter()
+        .map(|node| {
+            let text = node.as_str()?.trim_start();
+            if !text.starts_with('{') && !text.starts_with('[') {
+                return None;
+            }
+            parse_json(text).ok()
+        })
+        .collect()
+}
+
+/// The name and the value of the variant of a tagged union that `node` is, or
+/// `None` if it isn't tagged as described by `tagging`.
+fn tagged_variant<'n>(
+    node: &'n JsonNode,
+    tagging: &UnionTagging,
+) -> Option<(&'n str, &'n JsonNode)> {
+    let pairs = match node {
+        JsonNode::Object(pairs) => pairs,
+        _ => return None,
+    };
+    match tagging {
+        UnionTagging::Internal { tag } => Some((node.get(tag)?.as_str()?, node)),
+        UnionTagging::Adjacent { tag, content } => {
+            if pairs
+                .iter()
+                .any(|(key, _)| **key != **tag && **key != **content)
+            {
+                return None;
+            }
+            Some((node.get(tag)?.as_str()?, node.get(content)?))
+        }
+        UnionTagging::External => match pairs.as_slice() {
+            [(key, value)] => Some((&**key, value)),
+            _ => None,
+        },
+    }
+}
+
+fn make_optional(structure: JsonStructure) -> JsonStructure {
+    match structure {
+        JsonStructure::Optional(_) | JsonStructure::Unknown => structure,
+        other => JsonStructure::Optional(Box::new(other)),
+    }
+}
+
+/// Counts the distinct shapes among the nodes, where objects with different
+/// sets of keys count as different shapes.
+fn count_shapes(nodes: &[&JsonNode]) -> usize {
+    let mut shapes: Vec<String> = Vec::new();
+    for node in nodes {
+        let shape = match node {
+            JsonNode::Object(pairs) => {
+                let mut keys: Vec<&str> = pairs.iter().map(|(k, _)| &**k).collect();
+                keys.sort_unstable();
+                keys.dedup();
+                format!("{{{}}}", keys.join(","))
+            }
+            other => format!("{:?}", kind_of(other)),
+        };
+        if !shapes.contains(&shape) {
+            shapes.push(shape);
+        }
+    }
+    shapes.len()
+}
+
+/// Describes the keys and kinds of values in `node` at every depth, so nodes
+/// with the same shape infer the same structure, barring formats detected in
+/// strings and the like.
+pub(crate) fn shape_of(node: &JsonNode) -> String {
+    match node {
+        JsonNode::Object(pairs) => {
+            let mut fields: Vec<String> = pairs
+                .iter()
+                .map(|(key, value)| format!("{:?}:{}", key, shape_of(value)))
+                .collect();
+            fields.sort_unstable();
+            fields.dedup();
+            format!("{{{}}}", fields.join(","))
+        }
+        JsonNode::Array(values) => {
+            let mut elements: Vec<String> = values.iter().map(shape_of).collect();
+            elements.sort_unstable();
+            elements.dedup();
+            format!("[{}]", elements.join("|"))
+        }
+        JsonNode::Int(_) => "int".to_string(),
+        JsonNode::Float(_) => "float".to_string(),
+        JsonNode::Null => "null".to_string(),
+        other => format!("{:?}", kind_of(other)),
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum NodeKind {
+    Object,
+    Array,
+    String,
+    Number,
+    Bool,
+    Bytes,
+    Custom(&'static str),
+}
+
+fn kind_of(node: &JsonNode) -> NodeKind {
+    match node {
+        JsonNode::Object(_) => NodeKind::Object,
+        JsonNode::Array(_) => NodeKind::Array,
+        JsonNode::String(_) => NodeKind::String,
+        JsonNode::Int(_) | JsonNode::Float(_) => NodeKind::Number,
+        JsonNode::Bool => NodeKind::Bool,
+        JsonNode::Bytes => NodeKind::Bytes,
+        JsonNode::Custom(rust_type) => NodeKind::Custom(rust_type),
+        JsonNode::Null => unreachable!("Nulls are filtered out before grouping"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_json;
+    use crate::RustType;
+
+    fn infer(json: &str) -> JsonStructure {
+        infer_with_options(json, &ConvertOptions::default()).0
+    }
+
+    fn infer_with_options(json: &str, options: &ConvertOptions) -> (JsonStructure, Vec<Warning>) {
+        let node = parse_json(json).unwrap();
+        let mut inference = Inference::new(options);
+        let structure = inference.infer_structure(&[&node], "", "root");
+        (structure, inference.warnings)
+    }
+
+    fn pair(key: &str, value: JsonStructure) -> JsonPair {
+        JsonPair {
+            key: key.into(),
+            value: Box::new(value),
+        }
+    }
+
+    #[test]
+    fn shapes_ignore_values_and_key_order() {
+        let shape = |json| shape_of(&parse_json(json).unwrap());
+
+        assert_eq!(
+            shape(r#"{"a": 1, "b": ["x", "y", null]}"#),
+            shape(r#"{"b": [null, "z"], "a": 2}"#)
+        );
+        assert_eq!(shape(r#"{"a": 1, "b": []}"#), r#"{"a":int,"b":[]}"#);
+        assert_ne!(shape(r#"{"a": 1}"#), shape(r#"{"a": 1.5}"#));
+        assert_ne!(shape(r#"{"a": 1}"#), shape(r#"{"a": null}"#));
+    }
+
+    #[test]
+    fn merges_objects_in_arrays() {
+        let result = infer(r#"[{"a": 1, "b": "x"}, {"a": 2.5, "c": true}]"#);
+
+        assert_eq!(
+            result,
+            JsonStructure::Array(Box::new(JsonStructure::Object(vec![
+                pair("a", JsonStructure::Value(JsonValue::Float)),
+                pair(
+                    "b",
+                    JsonStructure::Optional(Box::new(JsonStructure::Value(JsonValue::String)))
+                ),
+                pair(
+                    "c",
+                    JsonStructure::Optional(Box::new(JsonStructure::Value(JsonValue::Bool)))
+                ),
+            ])))
+        )
+    }
+
+    #[test]
+    fn null_values_make_fields_optional() {
+        let result = infer(r#"[{"a": null}, {"a": 5}]"#);
+
+        assert_eq!(
+            result,
+            JsonStructure::Array(Box::new(JsonStructure::Object(vec![pair(
+                "a",
+                JsonStructure::Optional(Box::new(JsonStructure::Value(JsonValue::Int)))
+            )])))
+        )
+    }
+
+    #[test]
+    fn empty_arrays_have_unknown_elements() {
+        let result = infer(r#"{"a": []}"#);
+
+        assert_eq!(
+            result,
+            JsonStructure::Object(vec![pair(
+                "a",
+                JsonStructure::Array(Box::new(JsonStructure::Unknown))
+            )])
+        )
+    }
+
+    #[test]
+    fn mixed_kinds_become_a_union() {
+        let result = infer(r#"["a", 1, {"b": true}]"#);
+
+        assert_eq!(
+            result,
+            JsonStructure::Array(Box::new(JsonStructure::Union(vec![
+                JsonStructure::Value(JsonValue::String),
+                JsonStructure::Value(JsonValue::Int),
+                JsonStructure::Object(vec![pair("b", JsonStructure::Value(JsonValue::Bool))]),
+            ])))
+        )
+    }
+
+    #[test]
+    fn widens_integers_outside_i64() {
+        let (result, warnings) = infer_with_options(
+            r#"{"small": [-1, 9223372036854775807], "id": [0, 18446744073709551615],
+                "mixed": [-1, 18446744073709551615], "huge": [1, 18446744073709551616]}"#,
+            &ConvertOptions::default(),
+        );
+        let int = |rust_type| JsonStructure::array(JsonStructure::custom(RustType::new(rust_type)));
+
+        assert_eq!(
+            result,
+            JsonStructure::Object(vec![
+                pair("small", JsonStructure::array(JsonStructure::int())),
+                pair("id", int("u64")),
+                pair("mixed", int("i128")),
+                pair("huge", int("i128")),
+            ])
+        );
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, "/mixed/*");
+    }
+
+    #[test]
+    fn runs_detectors_on_string_fields() {
+        let result = infer(r#"{"host": "10.0.0.1", "version": "1.2.3"}"#);
+
+        assert_eq!(
+            result,
+            JsonStructure::Object(vec![
+                pair(
+                    "host",
+                    JsonStructure::Value(JsonValue::Custom(RustType::new("std::net::IpAddr")))
+                ),
+                pair(
+                    "version",
+                    JsonStructure::Value(JsonValue::Custom(RustType::new("semver::Version")))
+                ),
+            ])
+        )
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn keeps_credentials_out_of_the_stats() {
+        let node =
+            parse_json(r#"{"apiKey": "abc123", "auth": "Bearer 4eC39HqLyjWDarjt"}"#).unwrap();
+        let options = ConvertOptions::default();
+        let mut inference = Inference::new(&options);
+        inference.infer_structure(&[&node], "", "root");
+
+        let paths: Vec<&str> = inference.warnings.iter().map(|w| w.path.as_str()).collect();
+        assert_eq!(paths, vec!["/apiKey", "/auth"]);
+        assert!(inference.warnings[1].message.contains("a bearer token"));
+        assert!(inference
+            .stats
+            .iter()
+            .all(|s| s.examples.is_empty() && s.string_values.is_none()));
+
+        let mut options = ConvertOptions::default();
+        options.set_detect_secrets(false);
+        assert_eq!(
+            infer_with_options(r#"{"apiKey": "abc123"}"#, &options).1,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn gathers_stats_for_every_path() {
+        let node =
+            parse_json(r#"[{"a": 1, "b": null}, {"a": 2}, {"a": null, "b": [1, 2]}]"#).unwrap();
+        let options = ConvertOptions::default();
+        let mut inference = Inference::new(&options);
+        inference.infer_structure(&[&node], "", "root");
+
+        assert_eq!(
+            inference.stats,
+            vec![
+                PathStats {
+                    element_counts: Some((3, 3)),
+                    ..PathStats::new("", 1, 0)
+                },
+                PathStats::new("/*", 3, 0),
+                PathStats {
+                    parent_count: 3,
+                    value_range: Some((1.0, 2.0)),
+                    int_values: Some(vec![1, 2]),
+                    examples: vec!["1".to_string(), "2".to_string()],
+                    ..PathStats::new("/*/a", 3, 1)
+                },
+                PathStats {
+                    parent_count: 3,
+                    unique_elements: Some(true),
+                    element_counts: Some((2, 2)),
+                    ..PathStats::new("/*/b", 2, 1)
+                },
+                PathStats {
+                    value_range: Some((1.0, 2.0)),
+                    int_values: Some(vec![1, 2]),
+                    examples: vec!["1".to_string(), "2".to_string()],
+                    ..PathStats::new("/*/b/*", 2, 0)
+                },
+            ]
+        )
+    }
+
+    #[test]
+    fn falls_back_to_value_for_chaotic_subtrees() {
+        let mut options = ConvertOptions::default();
+        options.set_chaos_threshold(Some(2));
+
+        let (result, warnings) = infer_with_options(
+            r#"{"events": [{"a": 1}, {"b": 1}, {"c": 1}], "ok": [{"a": 1}, {"b": 1}]}"#,
+            &options,
+        );
+
+        assert_eq!(
+            result,
+            JsonStructure::Object(vec![
+                pair(
+                    "events",
+                    JsonStructure::Array(Box::new(JsonStructure::Unknown))
+                ),
+                pair(
+                    "ok",
+                    JsonStructure::Array(Box::new(JsonStructure::Object(vec![
+                        pair(
+                            "a",
+                            JsonStructure::Optional(Box::new(JsonStructure::Value(JsonValue::Int)))
+                        ),
+                        pair(
+                            "b",
+                            JsonStructure::Optional(Box::new(JsonStructure::Value(JsonValue::Int)))
+                        ),
+                    ])))
+                ),
+            ])
+        );
+        assert_eq!(
+            warnings,
+            vec![Warning::new(
+                "/events/*",
+                "Found 3 different shapes, which is more than the chaos threshold of 2, \
+                 so serde_json::Value is used instead"
+            )]
+        );
+    }
+
+    #[test]
+    fn warns_about_keys_differing_only_by_case() {
+        let (_, warnings) = infer_with_options(
+            r#"{"user": {"userId": 1, "user_id": 2}}"#,
+            &ConvertOptions::default(),
+        );
+
+        assert_eq!(
+            warnings,
+            vec![Warning::new(
+                "/user",
+                "Keys \"user_id\" and \"userId\" both become the field user_id, so \"userId\" \
+                 is named user_id_2 instead"
+            )]
+        );
+    }
+
+    #[test]
+    fn makes_every_field_optional() {
+        let mut options = ConvertOptions::default();
+        options.set_all_optional(true);
+
+        let (result, _) = infer_with_options(r#"{"a": 1, "b": [{"c": true}]}"#, &options);
+
+        assert_eq!(
+            result,
+            JsonStructure::object()
+                .optional_field("a", JsonStructure::int())
+                .optional_field(
+                    "b",
+                    JsonStructure::array(
+                        JsonStructure::object()
+                            .optional_field("c", JsonStructure::bool())
+                            .build()
+                    )
+                )
+                .build()
+        );
+    }
+
+    #[test]
+    fn forces_value_for_hinted_paths() {
+        let mut options = ConvertOptions::default();
+        options.force_json_value("/data/*/extra");
+
+        let (result, _) = infer_with_options(r#"{"data": [{"extra": {"a": 1}}]}"#, &options);
+
+        assert_eq!(
+            result,
+            JsonStructure::Object(vec![pair(
+                "data",
+                JsonStructure::Array(Box::new(JsonStructure::Object(vec![pair(
+                    "extra",
+                    JsonStructure::Unknown
+                )])))
+            )])
+        )
+    }
+
+    #[test]
+    fn infers_the_structure_of_json_strings() {
+        let json = r#"[{"payload": "{\"a\": 1}", "note": "{not json"},
+                       {"payload": " {\"a\": 2, \"b\": [true]}", "note": "[]"}]"#;
+        let mut options = ConvertOptions::default();
+        options.set_json_strings(true);
+
+        let mut inference = Inference::new(&options);
+        let node = parse_json(json).unwrap();
+        let result = inference.infer_structure(&[&node], "", "root");
+
+        assert_eq!(
+            result,
+            JsonStructure::array(
+                JsonStructure::object()
+                    .field(
+                        "payload",
+                        JsonStructure::object()
+                            .field("a", JsonStructure::int())
+                            .optional_field("b", JsonStructure::array(JsonStructure::bool()))
+                            .build()
+                    )
+                    .field("note", JsonStructure::string())
+                    .build()
+            )
+        );
+        let format = |path: &str| {
+            inference
+                .stats
+                .iter()
+                .find(|s| s.path == path)
+                .and_then(|s| s.string_format)
+        };
+        assert_eq!(format("/*/payload"), Some(StringFormat::Json));
+        assert_eq!(format("/*/note"), None);
+
+        assert_eq!(
+            infer(r#"{"payload": "{\"a\": 1}"}"#),
+            JsonStructure::object()
+                .field("payload", JsonStructure::string())
+                .build()
+        );
+    }
+
+    #[test]
+    fn objects_with_blank_keys_become_dictionaries() {
+        let result = infer(r#"{"weights": {"": 1, "  ": 2, "a": 3}}"#);
+
+        assert_eq!(
+            result,
+            JsonStructure::Object(vec![pair(
+                "weights",
+                JsonStructure::Dictionary(pair("weights", JsonStructure::Value(JsonValue::Int)))
+            )])
+        )
+    }
+
+    #[test]
+    fn objects_with_blank_keys_and_mixed_values_stay_objects() {
+        let result = infer(r#"{"": 1, " ": "x"}"#);
+
+        assert_eq!(
+            result,
+            JsonStructure::Object(vec![
+                pair("", JsonStructure::Value(JsonValue::Int)),
+                pair(" ", JsonStructure::Value(JsonValue::String)),
+            ])
+        )
+    }
+}
@@ -0,0 +1,203 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+const IRREGULAR: &[(&str, &str)] = &[
+    ("people", "person"),
+    ("children", "child"),
+    ("men", "man"),
+    ("women", "woman"),
+    ("mice", "mouse"),
+    ("geese", "goose"),
+    ("feet", "foot"),
+    ("teeth", "tooth"),
+    ("oxen", "ox"),
+    ("indices", "index"),
+    ("matrices", "matrix"),
+    ("vertices", "vertex"),
+    ("criteria", "criterion"),
+    ("phenomena", "phenomenon"),
+    ("analyses", "analysis"),
+    ("theses", "thesis"),
+    ("crises", "crisis"),
+    ("diagnoses", "diagnosis"),
+    ("axes", "axis"),
+    ("cacti", "cactus"),
+    ("fungi", "fungus"),
+    ("radii", "radius"),
+    ("leaves", "leaf"),
+    ("lives", "life"),
+    ("knives", "knife"),
+    ("wives", "wife"),
+    ("wolves", "wolf"),
+    ("halves", "half"),
+    ("shelves", "shelf"),
+    ("selves", "self"),
+    ("movies", "movie"),
+    ("cookies", "cookie"),
+    ("quizzes", "quiz"),
+    ("heroes", "hero"),
+    ("potatoes", "potato"),
+    ("tomatoes", "tomato"),
+    ("echoes", "echo"),
+];
+
+// Words that are the same in singular and plural, or are never pluralized
+const UNCOUNTABLE: &[&str] = &[
+    "data",
+    "metadata",
+    "information",
+    "equipment",
+    "news",
+    "series",
+    "species",
+    "sheep",
+    "fish",
+    "deer",
+    "media",
+    "software",
+    "hardware",
+    "feedback",
+    "staff",
+    "status",
+    "analytics",
+];
+
+/// Converts english plurals to their singular form, used to name the element
+/// types of arrays.
+///
+/// Besides the built-in rules, a dictionary of domain specific words can be
+/// added, which take priority over the built-in rules.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct Inflector {
+    dictionary: Vec<(String, String)>,
+}
+
+impl Inflector {
+    pub fn new() -> Inflector {
+        Inflector::default()
+    }
+
+    /// Adds a word to the dictionary, e.g. `("octopodes", "octopus")`. Words are
+    /// matched case insensitively.
+    pub fn add_word<P: Into<String>, S: Into<String>>(
+        &mut self,
+        plural: P,
+        singular: S,
+    ) -> &mut Inflector {
+        self.dictionary
+            .push((plural.into().to_lowercase(), singular.into()));
+        self
+    }
+
+    /// Singularizes a single word, keeping the casing of the first letter.
+    pub fn singularize(&self, word: &str) -> String {
+        let lower = word.to_lowercase();
+
+        let singular =
+            if let Some((_, singular)) = self.dictionary.iter().find(|(p, _)| *p == lower) {
+                singular.clone()
+            } else if let Some((_, singular)) = IRREGULAR.iter().find(|(p, _)| *p == lower) {
+                singular.to_string()
+            } else if UNCOUNTABLE.contains(&lower.as_str()) {
+                return word.to_string();
+            } else {
+                return apply_rules(word);
+            };
+
+        match_first_letter_case(word, &singular)
+    }
+}
+
+fn apply_rules(word: &str) -> String {
+    let lower = word.to_lowercase();
+    let strip = |n: usize| word[..word.len() - n].to_string();
+
+    if lower.len() <= 2 || lower.ends_with("ss") || lower.ends_with("us") || lower.ends_with("is") {
+        word.to_string()
+    } else if lower.ends_with("ies") && !is_vowel_before(&lower, 3) {
+        format!(
+            "{}{}",
+            strip(3),
+            if word.ends_with("IES") { "Y" } else { "y" }
+        )
+    } else if lower.ends_with("sses")
+        || lower.ends_with("shes")
+        || lower.ends_with("ches")
+        || lower.ends_with("xes")
+        || lower.ends_with("zzes")
+    {
+        strip(2)
+    } else if lower.ends_with('s') {
+        strip(1)
+    } else {
+        word.to_string()
+    }
+}
+
+fn is_vowel_before(word: &str, suffix_length: usize) -> bool {
+    word[..word.len() - suffix_length]
+        .chars()
+        .last()
+        .is_some_and(|c| "aeiou".contains(c))
+}
+
+fn match_first_letter_case(original: &str, replacement: &str) -> String {
+    let mut chars = replacement.chars();
+    match (original.chars().next(), chars.next()) {
+        (Some(o), Some(r)) if o.is_uppercase() => r.to_uppercase().chain(chars).collect(),
+        _ => replacement.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn singularize(word: &str) -> String {
+        Inflector::new().singularize(word)
+    }
+
+    #[test]
+    fn singularizes_regular_plurals() {
+        assert_eq!(singularize("users"), "user");
+        assert_eq!(singularize("categories"), "category");
+        assert_eq!(singularize("boxes"), "box");
+        assert_eq!(singularize("addresses"), "address");
+        assert_eq!(singularize("branches"), "branch");
+        assert_eq!(singularize("keys"), "key");
+        assert_eq!(singularize("Items"), "Item");
+    }
+
+    #[test]
+    fn singularizes_irregular_plurals() {
+        assert_eq!(singularize("people"), "person");
+        assert_eq!(singularize("Children"), "Child");
+        assert_eq!(singularize("indices"), "index");
+        assert_eq!(singularize("leaves"), "leaf");
+        assert_eq!(singularize("analyses"), "analysis");
+    }
+
+    #[test]
+    fn leaves_singular_and_uncountable_words_alone() {
+        assert_eq!(singularize("address"), "address");
+        assert_eq!(singularize("status"), "status");
+        assert_eq!(singularize("data"), "data");
+        assert_eq!(singularize("series"), "series");
+        assert_eq!(singularize("user"), "user");
+        assert_eq!(singularize("basis"), "basis");
+    }
+
+    #[test]
+    fn uses_the_user_dictionary_first() {
+        let mut inflector = Inflector::new();
+        inflector
+            .add_word("octopodes", "octopus")
+            .add_word("data", "datum");
+
+        assert_eq!(inflector.singularize("Octopodes"), "Octopus");
+        assert_eq!(inflector.singularize("data"), "datum");
+    }
+}
@@ -0,0 +1,177 @@
+use crate::dependencies::{plan_dependencies, Dependency};
+
+/// Generated code split up for splicing into an existing module, as editor
+/// extensions do, rather than written out as a file of its own.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Insertion {
+    /// `use` lines to add to the module, importing only what it doesn't
+    /// import already
+    pub uses: Vec<String>,
+    /// The generated types and helpers, without their `use` lines
+    pub code: String,
+    /// Crates the code needs, to add to `Cargo.toml`
+    pub dependencies: Vec<Dependency>,
+    /// Names of generated types that the module already defines
+    pub conflicts: Vec<String>,
+}
+
+impl Insertion {
+    /// Whether the code derives or refers to serde, which is the case for
+    /// everything but custom backends.
+    pub fn needs_serde(&self) -> bool {
+        self.dependencies.iter().any(|d| d.name == "serde")
+    }
+}
+
+/// Plans inserting generated `code` into the existing `module` source.
+pub fn plan_insertion(code: &str, module: &str) -> Insertion {
+    let imported = imported_paths(module);
+    let is_imported = |prefix: &str, name: &str| {
+        imported.contains(&format!("{}::{}", prefix, name))
+            || imported.contains(&format!("{}::*", prefix))
+    };
+    let mut uses = Vec::new();
+    let mut body = Vec::new();
+    for line in code.lines() {
+        if !line.starts_with("use ") {
+            body.push(line);
+            continue;
+        }
+        for (prefix, names) in use_statement_paths(line) {
+            let missing: Vec<&str> = names
+                .iter()
+                .map(String::as_str)
+                .filter(|name| !is_imported(&prefix, name))
+                .collect();
+            match missing.as_slice() {
+                [] => {}
+                [name] => uses.push(format!("use {}::{};", prefix, name)),
+                names => uses.push(format!("use {}::{{{}}};", prefix, names.join(", "))),
+            }
+        }
+    }
+    let existing = defined_types(module);
+    let conflicts = defined_types(code)
+        .into_iter()
+        .filter(|name| existing.contains(name))
+        .collect();
+    Insertion {
+        uses,
+        code: body.join("\n").trim_start_matches('\n').to_string() + "\n",
+        // From the whole code, as the derives only name serde in its imports
+        dependencies: plan_dependencies(code),
+        conflicts,
+    }
+}
+
+/// Every path imported by the `use` statements in `module`, such as
+/// `serde::Deserialize`, with `::*` for glob imports.
+fn imported_paths(module: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut statement = String::new();
+    for line in module.lines() {
+        let line = line.trim();
+        let line = line.strip_prefix("pub ").unwrap_or(line);
+        if statement.is_empty() && !line.starts_with("use ") {
+            continue;
+        }
+        statement.push_str(line);
+        if line.ends_with(';') {
+            for (prefix, names) in use_statement_paths(&statement) {
+                paths.extend(names.iter().map(|name| format!("{}::{}", prefix, name)));
+            }
+            statement.clear();
+        }
+    }
+    paths
+}
+
+/// The paths a single `use` statement imports, as the prefix and the names
+/// imported from it. Only one level of braces is supported, which is all the
+/// generated code uses.
+fn use_statement_paths(statement: &str) -> Vec<(String, Vec<String>)> {
+    let path = statement
+        .trim()
+        .trim_start_matches("use ")
+        .trim_end_matches(';')
+        .trim();
+    match path.find("::{") {
+        Some(brace) => {
+            let names = path[brace + 3..]
+                .trim_end_matches('}')
+                .split(',')
+                .map(|name| name.split(" as ").next().unwrap_or(name).trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect();
+            vec![(path[..brace].to_string(), names)]
+        }
+        None => match path.rfind("::") {
+            Some(separator) => vec![(
+                path[..separator].to_string(),
+                vec![path[separator + 2..]
+                    .split(" as ")
+                    .next()
+                    .unwrap_or("")
+                    .trim()
+                    .to_string()],
+            )],
+            None => Vec::new(),
+        },
+    }
+}
+
+/// Names of the structs, enums and type aliases defined in `code`.
+fn defined_types(code: &str) -> Vec<String> {
+    code.lines()
+        .filter_map(|line| {
+            let line = line.trim_start();
+            let line = line.strip_prefix("pub ").unwrap_or(line);
+            let rest = ["struct ", "enum ", "type "]
+                .iter()
+                .find_map(|keyword| line.strip_prefix(keyword))?;
+            let name: String = rest
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            Some(name).filter(|name| !name.is_empty())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_to_rust;
+
+    #[test]
+    fn adds_only_the_missing_imports() {
+        let code = json_to_rust(r#"{"id": 1, "scores": {"": 1.5, " ": 2}}"#, "Root").unwrap();
+        let module =
+            "use serde::Serialize;\nuse std::collections::{\n    BTreeMap,\n    HashMap,\n};\n";
+        let insertion = plan_insertion(&code, module);
+
+        assert_eq!(insertion.uses, vec!["use serde::Deserialize;"]);
+        assert!(insertion.code.starts_with("#[derive("));
+        assert!(insertion.needs_serde());
+        assert!(insertion.conflicts.is_empty());
+
+        let insertion = plan_insertion(&code, "use serde::*;\n");
+        assert_eq!(insertion.uses, vec!["use std::collections::HashMap;"]);
+        let insertion = plan_insertion(&code, "");
+        assert_eq!(
+            insertion.uses,
+            vec![
+                "use serde::{Deserialize, Serialize};",
+                "use std::collections::HashMap;"
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_types_the_module_already_defines() {
+        let code = json_to_rust(r#"{"user": {"id": 1}}"#, "Root").unwrap();
+        let insertion = plan_insertion(&code, "pub struct User(u64);\nenum Other {}\n");
+
+        assert_eq!(insertion.conflicts, vec!["User"]);
+    }
+}
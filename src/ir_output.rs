@@ -0,0 +1,225 @@
+use crate::json_writer::JsonOutput;
+use crate::pointer::{child_path, element_path};
+use crate::{Conversion, JsonStructure, JsonValue};
+
+fn kind(kind: &str) -> (&str, JsonOutput) {
+    ("kind", JsonOutput::string(kind))
+}
+
+/// Describes a structure as json, keeping the paths of nested values.
+fn describe(structure: &JsonStructure, path: &str) -> JsonOutput {
+    match structure {
+        JsonStructure::Object(pairs) => JsonOutput::object(vec![
+            kind("object"),
+            ("path", JsonOutput::string(path)),
+            (
+                "fields",
+                JsonOutput::Array(
+                    pairs
+                        .iter()
+                        .map(|pair| {
+                            let field_path = child_path(path, pair.key());
+                            JsonOutput::object(vec![
+                                ("key", JsonOutput::string(pair.key())),
+                                ("optional", JsonOutput::Bool(pair.value().is_optional())),
+                                ("type", describe(pair.value(), &field_path)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ]),
+        JsonStructure::Array(inner) => JsonOutput::object(vec![
+            kind("array"),
+            ("path", JsonOutput::string(path)),
+            ("element", describe(inner, &element_path(path))),
+        ]),
+        JsonStructure::Dictionary(pair) => JsonOutput::object(vec![
+            kind("dictionary"),
+            ("path", JsonOutput::string(path)),
+            ("key", JsonOutput::string(pair.key())),
+            ("value", describe(pair.value(), &element_path(path))),
+        ]),
+        JsonStructure::Optional(inner) => JsonOutput::object(vec![
+            kind("optional"),
+            ("path", JsonOutput::string(path)),
+            ("inner", describe(inner, path)),
+        ]),
+        JsonStructure::Union(variants) => JsonOutput::object(vec![
+            kind("union"),
+            ("path", JsonOutput::string(path)),
+            (
+                "variants",
+                JsonOutput::Array(variants.iter().map(|v| describe(v, path)).collect()),
+            ),
+        ]),
+        JsonStructure::Value(JsonValue::Custom(rust_type)) => JsonOutput::object(vec![
+            kind("custom"),
+            ("path", JsonOutput::string(path)),
+            ("rust_type", JsonOutput::string(rust_type.path())),
+        ]),
+        JsonStructure::Value(value) => JsonOutput::object(vec![
+            kind(match value {
+                JsonValue::String => "string",
+                JsonValue::Float => "float",
+                JsonValue::Int => "int",
+                JsonValue::Bool => "bool",
+                JsonValue::Custom(_) => unreachable!("Custom values are handled above"),
+            }),
+            ("path", JsonOutput::string(path)),
+        ]),
+        JsonStructure::Unknown => {
+            JsonOutput::object(vec![kind("unknown"), ("path", JsonOutput::string(path))])
+        }
+    }
+}
+
+/// Describes a structure as json, like the `structure` of
+/// `conversion_to_json`.
+pub(crate) fn structure_to_json(structure: &JsonStructure) -> JsonOutput {
+    describe(structure, "")
+}
+
+/// Short name of the type at a leaf, e.g. `int` or `optional<string>`.
+fn leaf_type(structure: &JsonStructure) -> String {
+    match structure {
+        JsonStructure::Optional(inner) => format!("optional<{}>", leaf_type(inner)),
+        JsonStructure::Value(JsonValue::String) => "string".to_string(),
+        JsonStructure::Value(JsonValue::Float) => "float".to_string(),
+        JsonStructure::Value(JsonValue::Int) => "int".to_string(),
+        JsonStructure::Value(JsonValue::Bool) => "bool".to_string(),
+        JsonStructure::Value(JsonValue::Custom(rust_type)) => rust_type.path().to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Dumps the result of a conversion as json, so tools that don't read rust can
+/// use the inferred structure. The document contains the full structure, every
+/// leaf path with its type, stats and confidence, and the warnings.
+pub fn conversion_to_json(conversion: &Conversion, root_name: &str) -> String {
+    let paths = conversion
+        .structure
+        .leaf_paths()
+        .into_iter()
+        .map(|(path, structure)| {
+            let stats = match conversion.stats.iter().find(|s| s.path == path) {
+                Some(stats) => JsonOutput::object(vec![
+                    ("count", JsonOutput::Int(stats.count as i64)),
+                    ("null_count", JsonOutput::Int(stats.null_count as i64)),
+                    ("presence", JsonOutput::Float(stats.presence())),
+                    ("null_rate", JsonOutput::Float(stats.null_rate())),
+                    ("confidence", JsonOutput::Float(stats.confidence())),
+                ]),
+                None => JsonOutput::Null,
+            };
+            JsonOutput::object(vec![
+                ("path", JsonOutput::string(path.as_str())),
+                ("type", JsonOutput::string(leaf_type(structure))),
+                ("optional", JsonOutput::Bool(structure.is_optional())),
+                ("stats", stats),
+            ])
+        })
+        .collect();
+
+    let warnings = conversion
+        .warnings
+        .iter()
+        .map(|w| {
+            JsonOutput::object(vec![
+                ("path", JsonOutput::string(w.path.as_str())),
+                ("message", JsonOutput::string(w.message.as_str())),
+            ])
+        })
+        .collect();
+
+    JsonOutput::object(vec![
+        ("root", JsonOutput::string(root_name)),
+        ("structure", describe(&conversion.structure, "")),
+        ("paths", JsonOutput::Array(paths)),
+        ("warnings", JsonOutput::Array(warnings)),
+    ])
+    .to_pretty_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert_sample_json_with_warnings, ConvertOptions};
+
+    #[test]
+    fn dumps_the_conversion_as_json() {
+        let conversion = convert_sample_json_with_warnings(
+            r#"[{"id": 1, "tags": ["a"]}, {"id": 2, "tags": null}]"#,
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            conversion_to_json(&conversion, "Root"),
+            r#"{
+  "root": "Root",
+  "structure": {
+    "kind": "array",
+    "path": "",
+    "element": {
+      "kind": "object",
+      "path": "/*",
+      "fields": [
+        {
+          "key": "id",
+          "optional": false,
+          "type": {
+            "kind": "int",
+            "path": "/*/id"
+          }
+        },
+        {
+          "key": "tags",
+          "optional": true,
+          "type": {
+            "kind": "optional",
+            "path": "/*/tags",
+            "inner": {
+              "kind": "array",
+              "path": "/*/tags",
+              "element": {
+                "kind": "string",
+                "path": "/*/tags/*"
+              }
+            }
+          }
+        }
+      ]
+    }
+  },
+  "paths": [
+    {
+      "path": "/*/id",
+      "type": "int",
+      "optional": false,
+      "stats": {
+        "count": 2,
+        "null_count": 0,
+        "presence": 1.0,
+        "null_rate": 0.0,
+        "confidence": 0.4
+      }
+    },
+    {
+      "path": "/*/tags/*",
+      "type": "string",
+      "optional": false,
+      "stats": {
+        "count": 1,
+        "null_count": 0,
+        "presence": 1.0,
+        "null_rate": 0.0,
+        "confidence": 0.25
+      }
+    }
+  ],
+  "warnings": []
+}"#
+        )
+    }
+}
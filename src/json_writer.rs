@@ -0,0 +1,198 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Write;
+
+/// A json document built up in memory, for the machine readable outputs.
+#[derive(Debug, PartialEq, Clone)]
+pub enum JsonOutput {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<JsonOutput>),
+    Object(Vec<(String, JsonOutput)>),
+}
+
+impl JsonOutput {
+    pub fn string<S: Into<String>>(s: S) -> JsonOutput {
+        JsonOutput::String(s.into())
+    }
+
+    pub fn object(pairs: Vec<(&str, JsonOutput)>) -> JsonOutput {
+        JsonOutput::Object(pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    /// Formats the document with two space indentation.
+    pub fn to_pretty_string(&self) -> String {
+        let mut output = String::new();
+        self.write(&mut output, 0);
+        output
+    }
+
+    /// Formats the document on a single line, for protocols sending a document
+    /// per line.
+    pub fn to_compact_string(&self) -> String {
+        let mut output = String::new();
+        self.write_compact(&mut output);
+        output
+    }
+
+    fn write_compact(&self, output: &mut String) {
+        match self {
+            JsonOutput::Array(values) => {
+                output.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        output.push(',');
+                    }
+                    value.write_compact(output);
+                }
+                output.push(']');
+            }
+            JsonOutput::Object(pairs) => {
+                output.push('{');
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        output.push(',');
+                    }
+                    write_string(output, key);
+                    output.push(':');
+                    value.write_compact(output);
+                }
+                output.push('}');
+            }
+            value => value.write(output, 0),
+        }
+    }
+
+    fn write(&self, output: &mut String, indent: usize) {
+        match self {
+            JsonOutput::Null => output.push_str("null"),
+            JsonOutput::Bool(b) => output.push_str(if *b { "true" } else { "false" }),
+            JsonOutput::Int(i) => output.push_str(&i.to_string()),
+            JsonOutput::Float(f) if f.is_finite() => {
+                // Always keep a fraction, so floats stay floats when read back
+                if f % 1.0 == 0.0 && f.abs() < 1e15 {
+                    write!(output, "{:.1}", f).unwrap();
+                } else {
+                    write!(output, "{}", f).unwrap();
+                }
+            }
+            JsonOutput::Float(_) => output.push_str("null"),
+            JsonOutput::String(s) => write_string(output, s),
+            JsonOutput::Array(values) if values.is_empty() => output.push_str("[]"),
+            JsonOutput::Array(values) => {
+                output.push_str("[\n");
+                for (i, value) in values.iter().enumerate() {
+                    push_indent(output, indent + 1);
+                    value.write(output, indent + 1);
+                    output.push_str(if i + 1 < values.len() { ",\n" } else { "\n" });
+                }
+                push_indent(output, indent);
+                output.push(']');
+            }
+            JsonOutput::Object(pairs) if pairs.is_empty() => output.push_str("{}"),
+            JsonOutput::Object(pairs) => {
+                output.push_str("{\n");
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    push_indent(output, indent + 1);
+                    write_string(output, key);
+                    output.push_str(": ");
+                    value.write(output, indent + 1);
+                    output.push_str(if i + 1 < pairs.len() { ",\n" } else { "\n" });
+                }
+                push_indent(output, indent);
+                output.push('}');
+            }
+        }
+    }
+}
+
+fn push_indent(output: &mut String, indent: usize) {
+    for _ in 0..indent {
+        output.push_str("  ");
+    }
+}
+
+fn write_string(output: &mut String, s: &str) {
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(output, "\\u{:04x}", c as u32).unwrap(),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_pretty_json() {
+        let output = JsonOutput::object(vec![
+            ("name", JsonOutput::string("a \"quoted\"\nline")),
+            (
+                "values",
+                JsonOutput::Array(vec![
+                    JsonOutput::Int(1),
+                    JsonOutput::Float(2.0),
+                    JsonOutput::Float(0.25),
+                    JsonOutput::Bool(true),
+                    JsonOutput::Null,
+                ]),
+            ),
+            ("empty", JsonOutput::Object(vec![])),
+        ]);
+
+        assert_eq!(
+            output.to_pretty_string(),
+            r#"{
+  "name": "a \"quoted\"\nline",
+  "values": [
+    1,
+    2.0,
+    0.25,
+    true,
+    null
+  ],
+  "empty": {}
+}"#
+        )
+    }
+
+    #[test]
+    fn output_can_be_parsed_back() {
+        let output = JsonOutput::Array(vec![
+            JsonOutput::string("\u{1}\t"),
+            JsonOutput::Float(f64::NAN),
+        ]);
+
+        assert!(crate::parser::parse_json(&output.to_pretty_string()).is_ok());
+    }
+
+    #[test]
+    fn writes_compact_json() {
+        let output = JsonOutput::object(vec![
+            (
+                "values",
+                JsonOutput::Array(vec![JsonOutput::Int(1), JsonOutput::Float(2.0)]),
+            ),
+            ("text", JsonOutput::string("two\nlines")),
+        ]);
+
+        assert_eq!(
+            output.to_compact_string(),
+            r#"{"values":[1,2.0],"text":"two\nlines"}"#
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::pointer::{child_path, element_path};
+use crate::type_mapping::glob_matches;
+use crate::JsonStructure;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Fields to leave out of the generated types, such as debug info.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub(crate) struct KeyFilter {
+    /// Json pointers of the fields, with `*` for array elements and dictionary
+    /// values
+    pub(crate) paths: Vec<String>,
+    /// Globs matched against the key of every field, at any depth
+    pub(crate) keys: Vec<String>,
+}
+
+impl KeyFilter {
+    fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.keys.is_empty()
+    }
+
+    fn ignores(&self, path: &str, key: &str) -> bool {
+        self.paths.iter().any(|p| p == path) || self.keys.iter().any(|k| glob_matches(k, key))
+    }
+
+    /// Removes the ignored fields from `structure`, returning their paths.
+    pub(crate) fn apply(&self, structure: &mut JsonStructure) -> Vec<String> {
+        let mut removed = Vec::new();
+        if !self.is_empty() {
+            self.filter(structure, "", &mut removed);
+        }
+        removed
+    }
+
+    fn filter(&self, structure: &mut JsonStructure, path: &str, removed: &mut Vec<String>) {
+        match structure {
+            JsonStructure::Object(pairs) => {
+                pairs.retain(|pair| {
+                    let field_path = child_path(path, pair.key());
+                    let ignored = self.ignores(&field_path, pair.key());
+                    if ignored && !removed.contains(&field_path) {
+                        removed.push(field_path);
+                    }
+                    !ignored
+                });
+                for pair in pairs {
+                    let field_path = child_path(path, pair.key());
+                    self.filter(pair.value_mut(), &field_path, removed);
+                }
+            }
+            JsonStructure::Array(inner) => self.filter(inner, &element_path(path), removed),
+            JsonStructure::Dictionary(pair) => {
+                self.filter(pair.value_mut(), &element_path(path), removed)
+            }
+            JsonStructure::Optional(inner) => self.filter(inner, path, removed),
+            JsonStructure::Union(variants) => {
+                for variant in variants {
+                    self.filter(variant, path, removed);
+                }
+            }
+            JsonStructure::Value(_) | JsonStructure::Unknown => {}
+        }
+    }
+}
+
+/// Reads a path given either as a json pointer, such as `/items/*/debug`, or
+/// as a JSONPath, such as `$.items[*].debug` or `$['a.b']`. Array indices
+/// become `*`, as every element shares one structure.
+pub(crate) fn ignored_path(path: &str) -> String {
+    let rest = match path.strip_prefix('$') {
+        Some(rest) => rest,
+        None => return path.to_string(),
+    };
+    let mut pointer = String::new();
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut key = String::new();
+                while let Some(c) = chars.next_if(|c| *c != '.' && *c != '[') {
+                    key.push(c);
+                }
+                pointer = child_path(&pointer, &key);
+            }
+            '[' => {
+                let mut segment = String::new();
+                for c in chars.by_ref().take_while(|c| *c != ']') {
+                    segment.push(c);
+                }
+                let quoted = segment
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| segment.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+                pointer = match quoted {
+                    Some(key) => child_path(&pointer, key),
+                    None => element_path(&pointer),
+                };
+            }
+            _ => {}
+        }
+    }
+    pointer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    #[test]
+    fn reads_json_paths_and_pointers() {
+        assert_eq!(ignored_path("/items/*/debug"), "/items/*/debug");
+        assert_eq!(ignored_path("$.debug"), "/debug");
+        assert_eq!(ignored_path("$.items[*].debug"), "/items/*/debug");
+        assert_eq!(ignored_path("$.items[0]['a.b']"), "/items/*/a.b");
+        assert_eq!(ignored_path("$"), "");
+    }
+
+    #[test]
+    fn removes_ignored_fields() {
+        let mut structure = convert_sample_json(
+            r#"{"id": 1, "debug": {"trace": "x"}, "items": [{"_internal_id": 2, "name": "a"}]}"#,
+        )
+        .unwrap();
+        let filter = KeyFilter {
+            paths: vec!["/debug".to_string()],
+            keys: vec!["_internal*".to_string()],
+        };
+
+        assert_eq!(
+            filter.apply(&mut structure),
+            vec!["/debug", "/items/*/_internal_id"]
+        );
+        assert_eq!(
+            structure,
+            convert_sample_json(r#"{"id": 1, "items": [{"name": "a"}]}"#).unwrap()
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! Estimating the size and alignment of generated types, on 64-bit targets,
+//! from the Rust types of their fields. The estimates follow how rustc lays
+//! out the standard types, and take the types of other crates to be laid out
+//! like their current versions are.
+
+use std::cmp::Reverse;
+
+/// The size and alignment of a type, in bytes.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub(crate) struct Layout {
+    pub(crate) size: usize,
+    pub(crate) align: usize,
+    /// Whether the type has invalid bit patterns, which `Option` uses for
+    /// `None` instead of adding a tag
+    pub(crate) niche: bool,
+}
+
+/// Layouts of the types fields can have, by the name of the type without its
+/// path or generic arguments.
+const KNOWN_LAYOUTS: &[(&str, Layout)] = &[
+    ("bool", Layout::new(1, 1, true)),
+    ("i64", Layout::new(8, 8, false)),
+    ("u64", Layout::new(8, 8, false)),
+    ("f64", Layout::new(8, 8, false)),
+    ("i128", Layout::new(16, 16, false)),
+    ("()", Layout::new(0, 1, false)),
+    ("String", Layout::new(24, 8, true)),
+    ("Vec", Layout::new(24, 8, true)),
+    ("BTreeSet", Layout::new(24, 8, true)),
+    ("BTreeMap", Layout::new(24, 8, true)),
+    ("HashSet", Layout::new(48, 8, true)),
+    ("HashMap", Layout::new(48, 8, true)),
+    ("Box", Layout::new(8, 8, true)),
+    ("Value", Layout::new(32, 8, true)),
+    ("DateTime", Layout::new(12, 4, false)),
+    ("NaiveDateTime", Layout::new(12, 4, false)),
+    ("NaiveDate", Layout::new(4, 4, false)),
+    ("NaiveTime", Layout::new(8, 4, false)),
+    ("Uuid", Layout::new(16, 1, false)),
+    ("Decimal", Layout::new(16, 4, false)),
+    ("IpAddr", Layout::new(17, 1, true)),
+];
+
+/// The layout of types that aren't known, which is that of a `String`, as most
+/// custom types wrap one.
+const UNKNOWN_LAYOUT: Layout = Layout::new(24, 8, true);
+
+impl Layout {
+    pub(crate) const fn new(size: usize, align: usize, niche: bool) -> Layout {
+        Layout { size, align, niche }
+    }
+
+    /// The layout of `rust_type`, with `generated` giving the layouts of the
+    /// generated types, by their name.
+    pub(crate) fn of(rust_type: &str, generated: &dyn Fn(&str) -> Option<Layout>) -> Layout {
+        let rust_type = rust_type.trim();
+        if let Some(array) = rust_type
+            .strip_prefix('[')
+            .and_then(|t| t.strip_suffix(']'))
+        {
+            if let Some((element, length)) = array.rsplit_once(';') {
+                let element = Layout::of(element, generated);
+                let length: usize = length.trim().parse().unwrap_or_default();
+                return Layout::new(element.size * length, element.align, element.niche);
+            }
+        }
+        if let Some(inner) = rust_type
+            .strip_prefix("Option<")
+            .and_then(|t| t.strip_suffix('>'))
+        {
+            let inner = Layout::of(inner, generated);
+            return match inner.niche {
+                true => inner,
+                false => Layout::new(round_up(inner.size + 1, inner.align), inner.align, true),
+            };
+        }
+        // Generic arguments don't matter for the types that have them, which
+        // only point to their values
+        let path = rust_type.split('<').next().unwrap_or_default();
+        let name = path.rsplit("::").next().unwrap_or_default();
+        // Generated types are never written with a path, unlike `serde_json::Value`
+        let generated = match path.contains("::") {
+            true => None,
+            false => generated(name),
+        };
+        generated
+            .or_else(|| {
+                KNOWN_LAYOUTS
+                    .iter()
+                    .find(|(known, _)| *known == name)
+                    .map(|(_, layout)| *layout)
+            })
+            .unwrap_or(UNKNOWN_LAYOUT)
+    }
+
+    /// The layout of a struct with fields laid out as `fields`, in the order
+    /// they are declared, as `#[repr(C)]` structs are.
+    pub(crate) fn declared(fields: &[Layout]) -> Layout {
+        let mut size = 0;
+        let mut align = 1;
+        for field in fields {
+            size = round_up(size, field.align) + field.size;
+            align = align.max(field.align);
+        }
+        let niche = fields.iter().any(|field| field.niche);
+        Layout::new(round_up(size, align), align, niche)
+    }
+
+    /// The layout of a struct with fields laid out as `fields`, which rustc
+    /// orders so there is as little padding between them as it can.
+    pub(crate) fn reordered(fields: &[Layout]) -> Layout {
+        let ordered: Vec<Layout> = field_order(fields).into_iter().map(|i| fields[i]).collect();
+        Layout::declared(&ordered)
+    }
+
+    /// The layout of an enum with variants holding values laid out as
+    /// `variants`, and unit variants if there are none.
+    pub(crate) fn enumeration(variants: &[Layout]) -> Layout {
+        match variants {
+            [] => Layout::new(1, 1, true),
+            [only] => *only,
+            variants => {
+                let size = variants.iter().map(|v| v.size).max().unwrap_or_default();
+                let align = variants.iter().map(|v| v.align).max().unwrap_or(1);
+                Layout::new(round_up(size + 1, align), align, true)
+            }
+        }
+    }
+}
+
+/// The indices of the fields laid out as `fields` by descending alignment and
+/// then size, which leaves no padding between them. Fields that compare equal
+/// keep their order.
+pub(crate) fn field_order(fields: &[Layout]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..fields.len()).collect();
+    order.sort_by_key(|&i| (Reverse(fields[i].align), Reverse(fields[i].size)));
+    order
+}
+
+fn round_up(size: usize, align: usize) -> usize {
+    size.div_ceil(align) * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(rust_type: &str) -> Layout {
+        Layout::of(rust_type, &|name| {
+            (name == "Address" || name == "Value").then(|| Layout::new(56, 8, true))
+        })
+    }
+
+    #[test]
+    fn estimates_the_layouts_of_field_types() {
+        assert_eq!(layout("bool"), Layout::new(1, 1, true));
+        assert_eq!(layout("Option<i64>"), Layout::new(16, 8, true));
+        assert_eq!(layout("Option<String>"), Layout::new(24, 8, true));
+        assert_eq!(layout("Option<uuid::Uuid>").size, 17);
+        assert_eq!(layout("chrono::DateTime<chrono::Utc>").size, 12);
+        assert_eq!(layout("[f64; 3]"), Layout::new(24, 8, false));
+        assert_eq!(
+            layout("std::collections::HashMap<String, Address>").size,
+            48
+        );
+        assert_eq!(layout("Option<Address>").size, 56);
+        assert_eq!(layout("serde_json::Value").size, 32);
+        assert_eq!(layout("semver::Version"), UNKNOWN_LAYOUT);
+    }
+
+    #[test]
+    fn orders_fields_to_avoid_padding() {
+        let fields = [layout("bool"), layout("i64"), layout("bool"), layout("f64")];
+
+        assert_eq!(field_order(&fields), vec![1, 3, 0, 2]);
+        assert_eq!(Layout::declared(&fields).size, 32);
+        assert_eq!(Layout::reordered(&fields).size, 24);
+    }
+}
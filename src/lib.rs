@@ -1,42 +1,768 @@
-use shared::{Error, JsonTokenInfo};
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    vec,
+    vec::Vec,
+};
+use hash::Fnv;
+
+pub use accumulator::SchemaAccumulator;
+#[cfg(feature = "arrow")]
+pub use arrow::{conversion_to_arrow_schema, ArrowBackend};
+#[cfg(feature = "avro")]
+pub use avro::{avro_schema_to_structure, AvroBackend};
+#[cfg(feature = "std")]
+pub use backend::{Artifact, CodegenBackend, RustSerdeBackend};
+pub use builder::ObjectBuilder;
+#[cfg(feature = "std")]
+pub use cache::GenerationCache;
+#[cfg(feature = "std")]
+pub use check::{check_code, check_conversion, lint_code, CompileError};
+#[cfg(feature = "client-stubs")]
+pub use client::{client_to_rust, ClientStub};
+#[cfg(feature = "std")]
+pub use codegen::{
+    conversion_to_rust, field_order_savings, generate_rust, generate_rust_with_options, type_sizes,
+    TypeSize,
+};
+#[cfg(feature = "std")]
+pub use collection::{collection_to_rust, convert_collection, CollectionRequest};
+pub use compact::{CompactStructure, MemoryUsage};
+#[cfg(feature = "std")]
+pub use config::{resolved_config, Config, ConfigError};
+pub use converter::Converter;
+#[cfg(feature = "std")]
+pub use csv::{
+    convert_sample_csv, convert_sample_csv_with_options, convert_sample_csv_with_warnings,
+    csv_to_rust,
+};
+#[cfg(feature = "std")]
+pub use de_helpers::de_helpers_module;
+#[cfg(feature = "std")]
+pub use dependencies::{dependencies_toml, plan_dependencies, Dependency};
+pub use detectors::{Detector, IpAddrDetector, SemverDetector};
+#[cfg(feature = "std")]
+pub use generated_file::{
+    generated_file, generated_file_with_banner, merge_user_code, stale_file_diff, Banner,
+    USER_CODE_BEGIN, USER_CODE_END,
+};
+#[cfg(feature = "std")]
+pub use har::{convert_har, har_to_rust, url_pattern, Endpoint};
+#[cfg(feature = "std")]
+pub use history::{changes_to_json, structure_changes, Change, Revision};
+pub use inflection::Inflector;
+#[cfg(feature = "std")]
+pub use insertion::{plan_insertion, Insertion};
+#[cfg(feature = "std")]
+pub use ir_output::conversion_to_json;
+#[cfg(feature = "lsp")]
+pub use lsp::run_language_server;
+pub use naming::{
+    AcronymStyle, FieldNameStrategy, FullPathStrategy, NameContext, NamingStrategy,
+    ParentPrefixedStrategy, SingularizedStrategy,
+};
+pub use options::{
+    ArrayRoot, ConvertOptions, FloatPolicy, IntEnumStyle, MixedNumberPolicy, UnionTagging,
+    WholeFloatPolicy, DEFAULT_CHAOS_THRESHOLD,
+};
+#[cfg(feature = "std")]
+pub use origins::{conversion_to_rust_with_origins, origins_to_json, Origin};
+pub use output_version::OutputVersion;
+#[cfg(feature = "std")]
+pub use pairing::RequestResponse;
+pub use parser::extract_samples;
+pub use passes::{DedupePass, IgnorePass, PassContext, StructurePass, TypeMappingPass};
+#[cfg(feature = "std")]
+pub use plugin::Plugin;
+pub use progress::Progress;
+#[cfg(feature = "std")]
+pub use protobuf::ProtobufBackend;
+#[cfg(feature = "std")]
+pub use repl::run_repl;
+#[cfg(feature = "std")]
+pub use report::{conversion_report, shared_types_report, ReportFormat};
+pub use rewrite::{rewrite, Edit};
+#[cfg(feature = "std")]
+pub use sample_test::generate_sample_tests;
+#[cfg(feature = "std")]
+pub use server::run_server;
+pub use shared::{Error, ErrorCode, JsonTokenInfo, Span};
+#[cfg(feature = "std")]
+pub use snapshot::{Snapshot, SnapshotError, SnapshotSample};
+#[cfg(feature = "std")]
+pub use sql::{structure_to_sql, SqlBackend};
+#[cfg(feature = "std")]
+pub use standalone::StandaloneBackend;
+pub use stats::{PathStats, StringFormat};
+#[cfg(feature = "std")]
+pub use template::{TemplateBackend, TemplateError, TemplateKind};
+pub use type_mapping::TypeMapping;
+#[cfg(feature = "std")]
+pub use value_view::ValueViewBackend;
+pub use warnings::Warning;
+
+mod accumulator;
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(feature = "avro")]
+mod avro;
+#[cfg(feature = "std")]
+mod backend;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+mod binary;
+mod builder;
+#[cfg(feature = "std")]
+mod cache;
+#[cfg(feature = "cbor")]
+mod cbor;
+#[cfg(feature = "std")]
+mod check;
+#[cfg(feature = "client-stubs")]
+mod client;
+#[cfg(feature = "std")]
+mod codegen;
+#[cfg(feature = "std")]
+mod collection;
+mod compact;
+#[cfg(feature = "std")]
+mod config;
+mod converter;
+#[cfg(feature = "std")]
+mod csv;
+#[cfg(feature = "std")]
+mod de_helpers;
+#[cfg(feature = "std")]
+mod dependencies;
+mod detectors;
+#[cfg(feature = "differential")]
+pub mod differential;
+mod extended_json;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+mod generated_file;
+#[cfg(feature = "std")]
+mod har;
+mod hash;
+#[cfg(feature = "std")]
+mod history;
+mod inference;
+mod inflection;
+#[cfg(feature = "std")]
+mod insertion;
+#[cfg(feature = "std")]
+mod ir_output;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+mod json_writer;
+mod key_filter;
+#[cfg(feature = "std")]
+mod layout;
+#[cfg(feature = "lsp")]
+mod lsp;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub mod naming;
+mod options;
+#[cfg(feature = "std")]
+mod origins;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+mod output_version;
+#[cfg(feature = "std")]
+mod pairing;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+mod parser;
+mod passes;
+#[cfg(feature = "std")]
+mod plugin;
+mod pointer;
+mod progress;
+#[cfg(feature = "std")]
+mod protobuf;
+#[cfg(feature = "std")]
+mod repl;
+#[cfg(feature = "std")]
+mod report;
+mod rewrite;
+#[cfg(feature = "std")]
+mod sample_test;
+#[cfg(feature = "std")]
+mod secrets;
+#[cfg(feature = "std")]
+mod server;
+#[cfg(feature = "std")]
+mod shape_check;
 mod shared;
+#[cfg(feature = "std")]
+mod snapshot;
+#[cfg(feature = "std")]
+mod sql;
+#[cfg(feature = "std")]
+mod standalone;
+mod stats;
+#[cfg(feature = "std")]
+mod style;
+#[cfg(feature = "std")]
+mod suggestions;
+#[cfg(feature = "std")]
+mod symbols;
+#[cfg(feature = "std")]
+mod temp_dir;
+#[cfg(feature = "std")]
+mod template;
+#[cfg(feature = "std")]
+pub mod testing;
 mod tokenizer;
+mod type_mapping;
+#[cfg(feature = "std")]
+mod value_view;
+mod warnings;
 
-#[derive(Debug, Eq, PartialEq)]
+/// A path to a rust type used for a value, such as `std::net::IpAddr`.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RustType {
+    path: String,
+}
+
+impl RustType {
+    pub fn new<S: Into<String>>(path: S) -> RustType {
+        RustType { path: path.into() }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum JsonValue {
     String,
     Float,
     Int,
     Bool,
+    // A value recognized by a detector
+    Custom(RustType),
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JsonPair {
     key: String,
     value: Box<JsonStructure>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+impl JsonPair {
+    pub fn new<S: Into<String>>(key: S, value: JsonStructure) -> JsonPair {
+        JsonPair {
+            key: key.into(),
+            value: Box::new(value),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn value(&self) -> &JsonStructure {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut JsonStructure {
+        &mut self.value
+    }
+
+    pub fn into_parts(self) -> (String, JsonStructure) {
+        (self.key, *self.value)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum JsonStructure {
     Array(Box<JsonStructure>),
     // We have dictionary and object distinct, as we should output them as
-    // slightly different types
+    // slightly different types. The key of the pair is the name of the field
+    // holding the dictionary, and the value describes every value in it.
     Dictionary(JsonPair),
     Object(Vec<JsonPair>),
     Value(JsonValue),
+    // The value was missing or null in some of the samples
+    Optional(Box<JsonStructure>),
+    // The samples had values of different kinds
+    Union(Vec<JsonStructure>),
     Unknown,
 }
 
+impl JsonStructure {
+    pub fn string() -> JsonStructure {
+        JsonStructure::Value(JsonValue::String)
+    }
+
+    pub fn float() -> JsonStructure {
+        JsonStructure::Value(JsonValue::Float)
+    }
+
+    pub fn int() -> JsonStructure {
+        JsonStructure::Value(JsonValue::Int)
+    }
+
+    pub fn bool() -> JsonStructure {
+        JsonStructure::Value(JsonValue::Bool)
+    }
+
+    pub fn custom(rust_type: RustType) -> JsonStructure {
+        JsonStructure::Value(JsonValue::Custom(rust_type))
+    }
+
+    pub fn array(element: JsonStructure) -> JsonStructure {
+        JsonStructure::Array(Box::new(element))
+    }
+
+    pub fn optional(inner: JsonStructure) -> JsonStructure {
+        JsonStructure::Optional(Box::new(inner))
+    }
+
+    pub fn dictionary<S: Into<String>>(name: S, value: JsonStructure) -> JsonStructure {
+        JsonStructure::Dictionary(JsonPair::new(name, value))
+    }
+
+    /// Starts building an object structure, field by field.
+    pub fn object() -> ObjectBuilder {
+        ObjectBuilder::new()
+    }
+
+    /// The fields of an object, or `None` if this isn't an object.
+    pub fn fields(&self) -> Option<&[JsonPair]> {
+        match self {
+            JsonStructure::Object(pairs) => Some(pairs),
+            _ => None,
+        }
+    }
+
+    /// The structure of the field named `key`, if this is an object with such a field.
+    pub fn field(&self, key: &str) -> Option<&JsonStructure> {
+        self.fields()?
+            .iter()
+            .find(|pair| pair.key == key)
+            .map(|pair| pair.value())
+    }
+
+    /// The element structure, if this is an array.
+    pub fn element(&self) -> Option<&JsonStructure> {
+        match self {
+            JsonStructure::Array(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
+    pub fn is_optional(&self) -> bool {
+        matches!(self, JsonStructure::Optional(_))
+    }
+
+    /// The structure without any optional wrapper.
+    pub fn non_optional(&self) -> &JsonStructure {
+        match self {
+            JsonStructure::Optional(inner) => inner.non_optional(),
+            other => other,
+        }
+    }
+
+    /// The kind of number, if this is a union of a string and a number, which
+    /// `MixedNumberPolicy` decides how to generate.
+    pub(crate) fn string_or_number(&self) -> Option<&JsonValue> {
+        match self {
+            JsonStructure::Union(variants) => match variants.as_slice() {
+                [JsonStructure::Value(JsonValue::String), JsonStructure::Value(number)]
+                | [JsonStructure::Value(number), JsonStructure::Value(JsonValue::String)]
+                    if matches!(number, JsonValue::Int | JsonValue::Float) =>
+                {
+                    Some(number)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// A hash of the structure that is the same across runs, platforms and
+    /// releases, e.g. to embed in generated code and compare with the structure
+    /// of incoming payloads. The order of fields and of union variants doesn't
+    /// affect it.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv::new();
+        let mut write_all = |tag: &str, mut hashes: Vec<(&str, u64)>| {
+            hasher.write_str(tag);
+            hashes.sort_unstable();
+            for (key, hash) in hashes {
+                hasher.write_str(key);
+                hasher.write(&hash.to_le_bytes());
+            }
+        };
+        match self {
+            JsonStructure::Array(inner) => write_all("array", vec![("", inner.fingerprint())]),
+            // The key only names the field holding the dictionary
+            JsonStructure::Dictionary(pair) => {
+                write_all("dictionary", vec![("", pair.value.fingerprint())])
+            }
+            JsonStructure::Object(pairs) => write_all(
+                "object",
+                pairs
+                    .iter()
+                    .map(|pair| (pair.key(), pair.value.fingerprint()))
+                    .collect(),
+            ),
+            JsonStructure::Value(JsonValue::String) => write_all("string", Vec::new()),
+            JsonStructure::Value(JsonValue::Float) => write_all("float", Vec::new()),
+            JsonStructure::Value(JsonValue::Int) => write_all("int", Vec::new()),
+            JsonStructure::Value(JsonValue::Bool) => write_all("bool", Vec::new()),
+            JsonStructure::Value(JsonValue::Custom(rust_type)) => {
+                write_all("custom", vec![(rust_type.path(), 0)])
+            }
+            JsonStructure::Optional(inner) => {
+                write_all("optional", vec![("", inner.fingerprint())])
+            }
+            JsonStructure::Union(variants) => write_all(
+                "union",
+                variants.iter().map(|v| ("", v.fingerprint())).collect(),
+            ),
+            JsonStructure::Unknown => write_all("unknown", Vec::new()),
+        }
+        hasher.finish()
+    }
+
+    /// Whether this is a union of an int and a float, which is generated as an
+    /// `i64` with `WholeFloatPolicy::Int`.
+    #[cfg(feature = "std")]
+    pub(crate) fn int_or_float(&self) -> bool {
+        match self {
+            JsonStructure::Union(variants) => matches!(
+                variants.as_slice(),
+                [
+                    JsonStructure::Value(JsonValue::Int),
+                    JsonStructure::Value(JsonValue::Float)
+                ]
+            ),
+            _ => false,
+        }
+    }
+
+    /// Whether this is a union of a bool and an int, which is generated as a
+    /// `bool` with `ConvertOptions::set_numeric_bools`.
+    pub(crate) fn bool_or_int(&self) -> bool {
+        match self {
+            JsonStructure::Union(variants) => matches!(
+                variants.as_slice(),
+                [
+                    JsonStructure::Value(JsonValue::Bool),
+                    JsonStructure::Value(JsonValue::Int)
+                ] | [
+                    JsonStructure::Value(JsonValue::Int),
+                    JsonStructure::Value(JsonValue::Bool)
+                ]
+            ),
+            _ => false,
+        }
+    }
+}
 
 pub fn convert_sample_json(json: &str) -> Result<JsonStructure, Error> {
-    //    json.chars()
-    Err(Error::InvalidJson {
-        location: JsonTokenInfo::new(0, 0, 0),
-        message: "Not implemented".to_string(),
+    convert_sample_json_with_options(json, &ConvertOptions::default())
+}
+
+pub fn convert_sample_json_with_options(
+    json: &str,
+    options: &ConvertOptions,
+) -> Result<JsonStructure, Error> {
+    Ok(convert_sample_json_with_warnings(json, options)?.structure)
+}
+
+/// The inferred structure of a sample, along with any warnings raised while
+/// inferring it.
+#[derive(Debug, PartialEq)]
+pub struct Conversion {
+    pub structure: JsonStructure,
+    pub warnings: Vec<Warning>,
+    /// Counts for every path in the sample, in the order they were visited
+    pub stats: Vec<PathStats>,
+    /// The number of differently shaped samples, when converting several with
+    /// `ConvertOptions::set_dedupe_samples`
+    pub unique_shapes: Option<usize>,
+}
+
+pub fn convert_sample_json_with_warnings(
+    json: &str,
+    options: &ConvertOptions,
+) -> Result<Conversion, Error> {
+    let parsed = parse_sample(json, options, &mut parser::KeyInterner::default())?;
+    let mut conversion = convert_node(&parsed.node, options)?;
+    for stats in &mut conversion.stats {
+        stats.span = parsed.spans.get(&stats.path).copied();
+    }
+    warn_about_exponent_integers(&mut conversion, &parsed.exponent_integers);
+    Ok(conversion)
+}
+
+/// Infers a single structure describing several json samples of the same
+/// document, such as a few responses from one endpoint, so values missing
+/// from some of them become optional.
+pub fn convert_samples_json_with_warnings(
+    samples: &[&str],
+    options: &ConvertOptions,
+) -> Result<Conversion, Error> {
+    let samples: Vec<(&str, usize)> = samples.iter().map(|json| (*json, 1)).collect();
+    convert_samples_json_weighted(&samples, options)
+}
+
+/// Like `convert_samples_json_with_warnings`, but with each sample counted
+/// `weight` times in the stats, so a canonical example can outweigh noisy
+/// captures in how often fields are present. A weight of 0 leaves the sample
+/// out.
+pub fn convert_samples_json_weighted(
+    samples: &[(&str, usize)],
+    options: &ConvertOptions,
+) -> Result<Conversion, Error> {
+    // Shared by the samples, which are all kept until inferred
+    let mut keys = parser::KeyInterner::default();
+    let mut nodes = Vec::new();
+    let mut exponent_integers = BTreeMap::new();
+    for (json, weight) in samples {
+        let parsed = parse_sample(json, options, &mut keys)?;
+        for (path, value) in parsed.exponent_integers {
+            exponent_integers.entry(path).or_insert(value);
+        }
+        nodes.push((parsed.node, *weight));
+        options.report_progress(Progress::Parsed {
+            samples: nodes.len(),
+            total: samples.len(),
+        });
+    }
+    let weighted: Vec<(&parser::JsonNode, usize)> = nodes
+        .iter()
+        .filter(|(_, weight)| *weight > 0)
+        .map(|(node, weight)| (node, *weight))
+        .collect();
+    let mut conversion = convert_weighted_nodes(&weighted, options)?;
+    options.report_progress(Progress::Merged {
+        samples: weighted.len(),
+    });
+    warn_about_exponent_integers(&mut conversion, &exponent_integers);
+    Ok(conversion)
+}
+
+/// Parses json within the limits of the options, reporting the progress of
+/// tokenizing it and stopping if the conversion is cancelled.
+fn parse_reporting(
+    json: &str,
+    options: &ConvertOptions,
+    keys: &mut parser::KeyInterner,
+) -> Result<parser::Parsed, Error> {
+    parser::parse_json_reporting(
+        json,
+        options.leniency(),
+        options.limits(),
+        keys,
+        &mut |bytes| {
+            options.report_progress(Progress::Tokenized {
+                bytes,
+                total: json.len(),
+            });
+            options.check_cancelled()
+        },
+    )
+}
+
+/// Parses a json sample the way `options` ask for.
+fn parse_sample(
+    json: &str,
+    options: &ConvertOptions,
+    keys: &mut parser::KeyInterner,
+) -> Result<parser::Parsed, Error> {
+    let mut parsed = parse_reporting(json, options, keys)?;
+    if options.extended_json {
+        parsed.node = extended_json::unwrap_extended_json(parsed.node);
+    }
+    Ok(parsed)
+}
+
+/// Warns about the paths typed as integers only because numbers written with
+/// an exponent were read as integers, with
+/// `ConvertOptions::set_exponent_integers`.
+fn warn_about_exponent_integers(
+    conversion: &mut Conversion,
+    exponent_integers: &BTreeMap<String, i128>,
+) {
+    let is_int = |structure: &JsonStructure| match structure.non_optional() {
+        JsonStructure::Value(JsonValue::Int) => true,
+        JsonStructure::Value(JsonValue::Custom(rust_type)) => {
+            [inference::U64, inference::I128].contains(&rust_type.path())
+        }
+        _ => false,
+    };
+    for (path, value) in exponent_integers {
+        let typed_as_int = match conversion
+            .structure
+            .at(path)
+            .map(JsonStructure::non_optional)
+        {
+            Some(JsonStructure::Union(variants)) => variants.iter().any(is_int),
+            Some(structure) => is_int(structure),
+            None => false,
+        };
+        if typed_as_int {
+            conversion.warnings.push(Warning::new(
+                path.as_str(),
+                alloc::format!(
+                    "Found {}, written with an exponent, so an integer type is used, which \
+                     serde_json can't read numbers written like this into",
+                    value
+                ),
+            ));
+        }
+    }
+}
+
+/// Infers the structure of a parsed sample, whichever format it was read from.
+fn convert_node(node: &parser::JsonNode, options: &ConvertOptions) -> Result<Conversion, Error> {
+    convert_nodes(&[node], options)
+}
+
+/// Infers a single structure describing several samples of the same value.
+fn convert_nodes(
+    nodes: &[&parser::JsonNode],
+    options: &ConvertOptions,
+) -> Result<Conversion, Error> {
+    let weighted: Vec<(&parser::JsonNode, usize)> = nodes.iter().map(|node| (*node, 1)).collect();
+    convert_weighted_nodes(&weighted, options)
+}
+
+/// Like `convert_nodes`, but with every sample counted as many times as its
+/// weight in the stats.
+fn convert_weighted_nodes(
+    nodes: &[(&parser::JsonNode, usize)],
+    options: &ConvertOptions,
+) -> Result<Conversion, Error> {
+    let mut unique_shapes = None;
+    let mut unique_nodes = Vec::new();
+    let nodes = if options.dedupe_samples && nodes.len() > 1 {
+        let mut shapes = BTreeSet::new();
+        for (i, (node, weight)) in nodes.iter().enumerate() {
+            if shapes.insert(inference::shape_of(node)) {
+                unique_nodes.push((*node, *weight));
+            }
+            options.report_progress(Progress::Deduped {
+                samples: i + 1,
+                unique: shapes.len(),
+            });
+            options.check_cancelled()?;
+        }
+        unique_shapes = Some(shapes.len());
+        &unique_nodes
+    } else {
+        nodes
+    };
+    let mut inference = inference::Inference::new(options);
+    inference.set_sample_weights(nodes);
+    let samples: Vec<&parser::JsonNode> = nodes.iter().map(|(node, _)| *node).collect();
+    let mut structure = inference.infer_structure(&samples, "", "root");
+    if inference.cancelled {
+        return Err(Error::Cancelled);
+    }
+    if let Some(path) = inference.mixed_number_paths.into_iter().next() {
+        return Err(Error::MixedStringsAndNumbers { path });
+    }
+    if !inference.float_paths.is_empty() {
+        return Err(Error::FloatsNotAllowed {
+            paths: inference.float_paths,
+        });
+    }
+    let mut context = passes::PassContext::new(options, inference.stats);
+    for pass in &options.passes {
+        pass.run(&mut structure, &mut context);
+    }
+    let stats = core::mem::take(&mut context.stats);
+    Ok(Conversion {
+        structure,
+        warnings: inference
+            .warnings
+            .into_iter()
+            .filter(|w| !context.is_removed(&w.path))
+            .collect(),
+        stats: stats
+            .into_iter()
+            .filter(|s| !context.is_removed(&s.path))
+            .collect(),
+        unique_shapes,
     })
 }
 
+/// Infers the structure of a MessagePack sample. Binary and extension values
+/// become `Vec<u8>`, and integer map keys are used as their decimal text.
+#[cfg(feature = "msgpack")]
+pub fn convert_sample_msgpack(bytes: &[u8]) -> Result<JsonStructure, Error> {
+    convert_sample_msgpack_with_options(bytes, &ConvertOptions::default())
+}
+
+#[cfg(feature = "msgpack")]
+pub fn convert_sample_msgpack_with_options(
+    bytes: &[u8],
+    options: &ConvertOptions,
+) -> Result<JsonStructure, Error> {
+    let node = msgpack::parse_msgpack(bytes)?;
+    Ok(convert_node(&node, options)?.structure)
+}
+
+/// Infers the structure of a CBOR sample. Byte strings become `Vec<u8>`,
+/// tags are looked through, and integer map keys are used as their decimal
+/// text.
+#[cfg(feature = "cbor")]
+pub fn convert_sample_cbor(bytes: &[u8]) -> Result<JsonStructure, Error> {
+    convert_sample_cbor_with_options(bytes, &ConvertOptions::default())
+}
+
+#[cfg(feature = "cbor")]
+pub fn convert_sample_cbor_with_options(
+    bytes: &[u8],
+    options: &ConvertOptions,
+) -> Result<JsonStructure, Error> {
+    let node = cbor::parse_cbor(bytes)?;
+    Ok(convert_node(&node, options)?.structure)
+}
+
+/// Converts a sample directly into rust source code, with the root type named `root_name`.
+#[cfg(feature = "std")]
+pub fn json_to_rust(json: &str, root_name: &str) -> Result<String, Error> {
+    let structure = convert_sample_json(json)?;
+    Ok(generate_rust(&structure, root_name))
+}
+
+/// Generates the types for several samples at once, such as the responses of
+/// the endpoints of an API, with a root type named after the key of every
+/// sample. Names are resolved across all of them, so objects with the same
+/// name and shape in several samples, such as an `Owner` returned by every
+/// endpoint, share a single type.
+#[cfg(feature = "std")]
+pub fn convert_named_samples(
+    samples: std::collections::HashMap<String, &str>,
+    options: &ConvertOptions,
+) -> Result<String, Error> {
+    // Sorted, so the output doesn't depend on the order of the map
+    let mut samples: Vec<(String, &str)> = samples.into_iter().collect();
+    samples.sort();
+    let mut roots = Vec::new();
+    for (name, json) in samples {
+        let structure = convert_sample_json_with_options(json, options)?;
+        roots.push((JsonPair::new(name, structure), codegen::Direction::Both));
+    }
+    Ok(codegen::generate_rust_roots(&roots, options))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -80,6 +806,257 @@ mod tests {
         )
     }
 
+    #[test]
+    fn structures_can_be_deduplicated() {
+        use std::collections::HashSet;
+
+        let a = convert_sample_json(r#"{"foo": [1, 2]}"#).unwrap();
+        let b = convert_sample_json(r#"{"foo": [3]}"#).unwrap();
+        let c = convert_sample_json(r#"{"foo": ["x"]}"#).unwrap();
+
+        let unique: HashSet<JsonStructure> = vec![a.clone(), b, c].into_iter().collect();
+        assert_eq!(unique.len(), 2);
+        assert!(unique.contains(&a));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn structures_roundtrip_through_serde() {
+        let structure = convert_sample_json(
+            r#"{"ip": "::1", "items": [{"a": null}, {"a": 1, "b": [true, "x"]}]}"#,
+        )
+        .unwrap();
+
+        let json = serde_json::to_string(&structure).unwrap();
+        let result: JsonStructure = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(result, structure);
+    }
+
+    #[test]
+    fn structures_can_be_inspected() {
+        let result = convert_sample_json(r#"{"foo": [{"bar": null}]}"#).unwrap();
+
+        let element = result.field("foo").and_then(|f| f.element()).unwrap();
+        let pair = &element.fields().unwrap()[0];
+        assert_eq!(pair.key(), "bar");
+        assert!(pair.value().is_optional());
+        assert_eq!(pair.value().non_optional(), &JsonStructure::Unknown);
+        assert_eq!(result.field("baz"), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn shares_types_between_named_samples() {
+        let samples = vec![
+            (
+                "Orders".to_string(),
+                r#"[{"id": 1, "owner": {"id": 2, "name": "a"}}]"#,
+            ),
+            (
+                "Repository".to_string(),
+                r#"{"stars": 3, "owner": {"id": 4, "name": "b"}}"#,
+            ),
+        ];
+
+        let result =
+            convert_named_samples(samples.into_iter().collect(), &ConvertOptions::default())
+                .unwrap();
+
+        assert!(result.contains("pub struct Orders {\n    pub id: i64,\n    pub owner: Owner,"));
+        assert!(
+            result.contains("pub struct Repository {\n    pub stars: i64,\n    pub owner: Owner,")
+        );
+        assert_eq!(result.matches("pub struct Owner {").count(), 1);
+        assert!(
+            result.find("pub struct Orders").unwrap()
+                < result.find("pub struct Repository").unwrap()
+        );
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn converts_msgpack_samples() {
+        // [{"data": bin(1)}, {"data": nil}]
+        let result = convert_sample_msgpack(&[
+            0x92, 0x81, 0xa4, b'd', b'a', b't', b'a', 0xc4, 0x01, 0x00, 0x81, 0xa4, b'd', b'a',
+            b't', b'a', 0xc0,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            result,
+            JsonStructure::array(
+                JsonStructure::object()
+                    .optional_field("data", JsonStructure::custom(RustType::new("Vec<u8>")))
+                    .build()
+            )
+        )
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn converts_cbor_samples() {
+        // {"data": h'00', "ok": true}
+        let result = convert_sample_cbor(&[
+            0xa2, 0x64, b'd', b'a', b't', b'a', 0x41, 0x00, 0x62, b'o', b'k', 0xf5,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            result,
+            JsonStructure::object()
+                .field("data", JsonStructure::custom(RustType::new("Vec<u8>")))
+                .field("ok", JsonStructure::bool())
+                .build()
+        )
+    }
+
+    #[test]
+    fn fingerprints_are_stable_and_ignore_field_order() {
+        let a = convert_sample_json(r#"{"id": 1, "tags": ["x"], "v": [1, "a"]}"#).unwrap();
+        let b = convert_sample_json(r#"{"v": ["a", 1], "tags": ["y"], "id": 2}"#).unwrap();
+        let c = convert_sample_json(r#"{"id": "1", "tags": ["x"], "v": [1, "a"]}"#).unwrap();
+
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a.fingerprint(), c.fingerprint());
+        // Pinned, as it must never change
+        assert_eq!(a.fingerprint(), 0x7a49_9b06_956f_e09a);
+    }
+
+    #[test]
+    fn weights_samples_in_the_stats() {
+        let conversion = convert_samples_json_weighted(
+            &[
+                (r#"{"id": 1, "name": "a"}"#, 9),
+                (r#"{"id": 2}"#, 1),
+                (r#"{"id": "x"}"#, 0),
+            ],
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            conversion.structure,
+            JsonStructure::object()
+                .field("id", JsonStructure::int())
+                .optional_field("name", JsonStructure::string())
+                .build()
+        );
+        let name = conversion.stats.iter().find(|s| s.path == "/name").unwrap();
+        assert_eq!((name.present_count, name.parent_count), (9, 10));
+    }
+
+    #[test]
+    fn large_weights_are_not_expanded() {
+        let conversion = convert_samples_json_weighted(
+            &[
+                (r#"{"id": 1, "name": null}"#, usize::MAX / 2),
+                (r#"{"id": 2}"#, 3),
+            ],
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+
+        let name = conversion.stats.iter().find(|s| s.path == "/name").unwrap();
+        assert_eq!(
+            (name.present_count, name.null_count, name.parent_count),
+            (usize::MAX / 2, usize::MAX / 2, usize::MAX / 2 + 3)
+        );
+    }
+
+    #[test]
+    fn dedupes_samples_by_shape() {
+        let samples = [
+            r#"{"id": 1, "tags": ["a", "b"]}"#,
+            r#"{"tags": ["c"], "id": 2}"#,
+            r#"{"id": 3, "tags": []}"#,
+            r#"{"id": 4.5, "tags": ["d"]}"#,
+        ];
+        let mut options = ConvertOptions::default();
+        options.set_dedupe_samples(true);
+        let conversion = convert_samples_json_with_warnings(&samples, &options).unwrap();
+
+        assert_eq!(conversion.unique_shapes, Some(3));
+        assert_eq!(
+            conversion.structure,
+            convert_samples_json_with_warnings(&samples, &ConvertOptions::default())
+                .unwrap()
+                .structure
+        );
+        let id = conversion.stats.iter().find(|s| s.path == "/id").unwrap();
+        assert_eq!(id.count, 3);
+    }
+
+    #[test]
+    fn reports_progress() {
+        use std::sync::{Arc, Mutex};
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let mut options = ConvertOptions::default();
+        let sink = Arc::clone(&reported);
+        options
+            .set_dedupe_samples(true)
+            .set_progress_callback(move |progress| sink.lock().unwrap().push(progress));
+        let big = format!("[{}1]", "1, ".repeat(400_000));
+        convert_samples_json_with_warnings(&[&big, "[2]"], &options).unwrap();
+
+        assert_eq!(
+            *reported.lock().unwrap(),
+            vec![
+                Progress::Tokenized {
+                    bytes: 1 << 20,
+                    total: big.len()
+                },
+                Progress::Tokenized {
+                    bytes: big.len(),
+                    total: big.len()
+                },
+                Progress::Parsed {
+                    samples: 1,
+                    total: 2
+                },
+                Progress::Tokenized { bytes: 3, total: 3 },
+                Progress::Parsed {
+                    samples: 2,
+                    total: 2
+                },
+                Progress::Deduped {
+                    samples: 1,
+                    unique: 1
+                },
+                Progress::Deduped {
+                    samples: 2,
+                    unique: 1
+                },
+                Progress::Merged { samples: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let flag = Arc::new(AtomicBool::new(true));
+        let mut options = ConvertOptions::default();
+        let is_set = Arc::clone(&flag);
+        options.set_cancellation(move || is_set.load(Ordering::Relaxed));
+        assert_eq!(
+            convert_sample_json_with_warnings("[1]", &options),
+            Err(Error::Cancelled)
+        );
+        flag.store(false, Ordering::Relaxed);
+        assert!(convert_sample_json_with_warnings("[1]", &options).is_ok());
+
+        // Cancelled after tokenizing, during inference
+        let checks = AtomicUsize::new(0);
+        options.set_cancellation(move || checks.fetch_add(1, Ordering::Relaxed) > 0);
+        let error = convert_sample_json_with_warnings(r#"{"a": [1]}"#, &options).unwrap_err();
+        assert_eq!(error.code().as_str(), "cancelled");
+    }
+
     #[test]
     fn converts_a_simple_array() {
         let result = convert_sample_json(r#"["foo", "bar"]"#).expect("Json conversion failed");
@@ -0,0 +1,572 @@
+//! A language server for json sample files, run by the `json2rust-lsp` binary.
+//! It reports syntax errors and inference warnings as diagnostics, shows the
+//! rust type generated for the value under the cursor on hover, and offers a
+//! code action writing the types for the whole file next to it.
+
+use crate::codegen::conversion_to_rust_with_source_map;
+use crate::json_writer::JsonOutput;
+use crate::naming::to_pascal_case;
+use crate::parser::{parse_json, parse_json_with_spans, JsonNode};
+use crate::pointer::{child_path, element_path};
+use crate::shared::{Error, JsonTokenInfo, Span};
+use crate::tokenizer::{tokenize_json, JsonTokenType, Leniency};
+use crate::{convert_sample_json_with_warnings, generated_file, Config, ConvertOptions};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Serves the language server protocol over `input` and `output`, usually
+/// stdin and stdout, until the client sends `exit` or closes `input`.
+pub fn run_language_server<R: BufRead, W: Write>(mut input: R, mut output: W) -> io::Result<()> {
+    let mut server = Server {
+        documents: HashMap::new(),
+        options: ConvertOptions::default(),
+    };
+    while let Some(body) = read_message(&mut input)? {
+        let message = match parse_json(&body) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        let method = message.get("method").and_then(JsonNode::as_str);
+        if method == Some("exit") {
+            break;
+        }
+        let params = message.get("params");
+        let (result, notifications) = server.handle(method.unwrap_or(""), params);
+        for notification in notifications {
+            write_message(&mut output, &notification)?;
+        }
+        if let Some(id) = message.get("id") {
+            let id = match id {
+                JsonNode::Int(id) => JsonOutput::Int(*id as i64),
+                JsonNode::String(id) => JsonOutput::string(id.as_str()),
+                _ => JsonOutput::Null,
+            };
+            let response = match result {
+                Some(result) => JsonOutput::object(vec![
+                    ("jsonrpc", JsonOutput::string("2.0")),
+                    ("id", id),
+                    ("result", result),
+                ]),
+                None => JsonOutput::object(vec![
+                    ("jsonrpc", JsonOutput::string("2.0")),
+                    ("id", id),
+                    (
+                        "error",
+                        JsonOutput::object(vec![
+                            ("code", JsonOutput::Int(METHOD_NOT_FOUND)),
+                            ("message", JsonOutput::string("Method not found")),
+                        ]),
+                    ),
+                ]),
+            };
+            write_message(&mut output, &response)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the body of the next message, or `None` at the end of the input.
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+    let length = length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message<W: Write>(output: &mut W, message: &JsonOutput) -> io::Result<()> {
+    let body = message.to_pretty_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn notification(method: &str, params: JsonOutput) -> JsonOutput {
+    JsonOutput::object(vec![
+        ("jsonrpc", JsonOutput::string("2.0")),
+        ("method", JsonOutput::string(method)),
+        ("params", params),
+    ])
+}
+
+struct Server {
+    /// The text of every open document, by uri
+    documents: HashMap<String, String>,
+    options: ConvertOptions,
+}
+
+impl Server {
+    /// Handles a request or notification, returning the result of a request,
+    /// `None` for unknown methods, along with notifications to send.
+    fn handle(
+        &mut self,
+        method: &str,
+        params: Option<&JsonNode>,
+    ) -> (Option<JsonOutput>, Vec<JsonOutput>) {
+        let uri = params
+            .and_then(|p| p.get("textDocument"))
+            .and_then(|d| d.get("uri"))
+            .and_then(JsonNode::as_str)
+            .unwrap_or("")
+            .to_string();
+        match method {
+            "initialize" => {
+                let root = params
+                    .and_then(|p| p.get("rootUri"))
+                    .and_then(JsonNode::as_str);
+                let notifications = root
+                    .and_then(|root| self.load_config(root))
+                    .map(|message| {
+                        notification(
+                            "window/showMessage",
+                            JsonOutput::object(vec![
+                                ("type", JsonOutput::Int(1)),
+                                ("message", JsonOutput::string(message)),
+                            ]),
+                        )
+                    })
+                    .into_iter()
+                    .collect();
+                (Some(capabilities()), notifications)
+            }
+            "shutdown" => (Some(JsonOutput::Null), Vec::new()),
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let text = params.and_then(|p| match method {
+                    "textDocument/didOpen" => p.get("textDocument")?.get("text"),
+                    // Full document sync, so the last change is the whole text
+                    _ => match p.get("contentChanges")? {
+                        JsonNode::Array(changes) => changes.last()?.get("text"),
+                        _ => None,
+                    },
+                });
+                if let Some(text) = text.and_then(JsonNode::as_str) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                let diagnostics = self
+                    .documents
+                    .get(&uri)
+                    .map_or_else(Vec::new, |text| self.diagnostics(text));
+                (None, vec![publish_diagnostics(&uri, diagnostics)])
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                (None, vec![publish_diagnostics(&uri, Vec::new())])
+            }
+            "textDocument/hover" => {
+                let position = params.and_then(|p| p.get("position"));
+                let hover = self.documents.get(&uri).and_then(|text| {
+                    let line = int(position?.get("line")?)?;
+                    let character = int(position?.get("character")?)?;
+                    self.hover(&uri, text, line, character)
+                });
+                let result = match hover {
+                    Some(value) => JsonOutput::object(vec![(
+                        "contents",
+                        JsonOutput::object(vec![
+                            ("kind", JsonOutput::string("markdown")),
+                            ("value", JsonOutput::string(value)),
+                        ]),
+                    )]),
+                    None => JsonOutput::Null,
+                };
+                (Some(result), Vec::new())
+            }
+            "textDocument/codeAction" => {
+                let actions = self
+                    .documents
+                    .get(&uri)
+                    .and_then(|text| self.generate_action(&uri, text));
+                (
+                    Some(JsonOutput::Array(actions.into_iter().collect())),
+                    Vec::new(),
+                )
+            }
+            _ => (None, Vec::new()),
+        }
+    }
+
+    /// Applies `json2rust.toml` from the workspace root, returning a message
+    /// if it is invalid.
+    fn load_config(&mut self, root_uri: &str) -> Option<String> {
+        let root = root_uri.strip_prefix("file://")?;
+        let text = fs::read_to_string(format!("{}/json2rust.toml", root)).ok()?;
+        match Config::parse(&text) {
+            Ok(config) => {
+                config.apply(&mut self.options);
+                None
+            }
+            Err(e) => Some(format!("Invalid json2rust.toml: {}", e)),
+        }
+    }
+
+    fn diagnostics(&self, text: &str) -> Vec<JsonOutput> {
+        let conversion = match convert_sample_json_with_warnings(text, &self.options) {
+            Ok(conversion) => conversion,
+            Err(error) => {
+                // Errors about a path are shown on its first value
+                let path = match &error {
+                    Error::MixedStringsAndNumbers { path } => Some(path),
+                    Error::FloatsNotAllowed { paths } => paths.first(),
+                    _ => None,
+                };
+                let range = match (path, error.location()) {
+                    (_, Some(location)) => Some(range(text, location, location)),
+                    (Some(path), None) => {
+                        match parse_json_with_spans(text, self.options.leniency()) {
+                            Ok((_, spans)) => spans
+                                .get(path)
+                                .map(|span| range(text, span.start, span.end)),
+                            Err(_) => None,
+                        }
+                    }
+                    _ => None,
+                };
+                let mut message = error.message();
+                if let Some(suggestion) = error.suggestion(text) {
+                    message.push_str(&format!("\nhint: {}", suggestion));
+                }
+                return vec![diagnostic(range, 1, message, Some(error.code().as_str()))];
+            }
+        };
+        let spans: HashMap<&str, Span> = conversion
+            .stats
+            .iter()
+            .filter_map(|stats| Some((stats.path.as_str(), stats.span?)))
+            .collect();
+        conversion
+            .warnings
+            .iter()
+            .map(|warning| {
+                let range = spans
+                    .get(warning.path.as_str())
+                    .map(|span| range(text, span.start, span.end));
+                diagnostic(range, 2, warning.message.clone(), None)
+            })
+            .collect()
+    }
+
+    /// The generated code for the value or key at the cursor, as markdown.
+    fn hover(&self, uri: &str, text: &str, line: i64, character: i64) -> Option<String> {
+        let conversion = convert_sample_json_with_warnings(text, &self.options).ok()?;
+        let column = column_from_utf16(text, line, character)?;
+        let path = path_at(text, self.options.leniency(), line + 1, column + 1)?;
+        let (code, source_map) =
+            conversion_to_rust_with_source_map(&conversion, &root_name(uri), &self.options);
+        let lines: Vec<&str> = code.lines().collect();
+        let snippets: Vec<String> = source_map
+            .iter()
+            .filter(|(_, pointer)| *pointer == path)
+            .filter_map(|(line, _)| {
+                let rest = lines.get(line - 1..)?;
+                if rest[0].starts_with("#[derive") {
+                    // A whole definition, up to its closing brace
+                    let end = rest.iter().position(|l| *l == "}")?;
+                    Some(rest[..=end].join("\n"))
+                } else {
+                    // A field, whose attributes come first
+                    let field = rest.iter().find(|l| l.trim_start().starts_with("pub "))?;
+                    Some(field.trim().trim_end_matches(',').to_string())
+                }
+            })
+            .collect();
+        if snippets.is_empty() {
+            return None;
+        }
+        Some(format!("```rust\n{}\n```", snippets.join("\n\n")))
+    }
+
+    /// A code action creating a rust file next to the sample with its types.
+    fn generate_action(&self, uri: &str, text: &str) -> Option<JsonOutput> {
+        let conversion = convert_sample_json_with_warnings(text, &self.options).ok()?;
+        let (code, _) =
+            conversion_to_rust_with_source_map(&conversion, &root_name(uri), &self.options);
+        let target = match uri.strip_suffix(".json") {
+            Some(stem) => format!("{}.rs", stem),
+            None => format!("{}.rs", uri),
+        };
+        let start = JsonOutput::object(vec![
+            ("line", JsonOutput::Int(0)),
+            ("character", JsonOutput::Int(0)),
+        ]);
+        let edit = JsonOutput::object(vec![(
+            "documentChanges",
+            JsonOutput::Array(vec![
+                JsonOutput::object(vec![
+                    ("kind", JsonOutput::string("create")),
+                    ("uri", JsonOutput::string(target.as_str())),
+                    (
+                        "options",
+                        JsonOutput::object(vec![("overwrite", JsonOutput::Bool(true))]),
+                    ),
+                ]),
+                JsonOutput::object(vec![
+                    (
+                        "textDocument",
+                        JsonOutput::object(vec![
+                            ("uri", JsonOutput::string(target.as_str())),
+                            ("version", JsonOutput::Null),
+                        ]),
+                    ),
+                    (
+                        "edits",
+                        JsonOutput::Array(vec![JsonOutput::object(vec![
+                            (
+                                "range",
+                                JsonOutput::object(vec![("start", start.clone()), ("end", start)]),
+                            ),
+                            (
+                                "newText",
+                                JsonOutput::string(generated_file(&code, &self.options)),
+                            ),
+                        ])]),
+                    ),
+                ]),
+            ]),
+        )]);
+        Some(JsonOutput::object(vec![
+            (
+                "title",
+                JsonOutput::string("Generate Rust types from this file"),
+            ),
+            ("kind", JsonOutput::string("refactor")),
+            ("edit", edit),
+        ]))
+    }
+}
+
+fn capabilities() -> JsonOutput {
+    JsonOutput::object(vec![
+        (
+            "capabilities",
+            JsonOutput::object(vec![
+                // Full document sync
+                ("textDocumentSync", JsonOutput::Int(1)),
+                ("hoverProvider", JsonOutput::Bool(true)),
+                ("codeActionProvider", JsonOutput::Bool(true)),
+            ]),
+        ),
+        (
+            "serverInfo",
+            JsonOutput::object(vec![("name", JsonOutput::string("json2rust-lsp"))]),
+        ),
+    ])
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<JsonOutput>) -> JsonOutput {
+    notification(
+        "textDocument/publishDiagnostics",
+        JsonOutput::object(vec![
+            ("uri", JsonOutput::string(uri)),
+            ("diagnostics", JsonOutput::Array(diagnostics)),
+        ]),
+    )
+}
+
+/// A diagnostic with the given severity, 1 for errors and 2 for warnings, at
+/// `range`, or the start of the document.
+fn diagnostic(
+    range: Option<JsonOutput>,
+    severity: i64,
+    message: String,
+    code: Option<&str>,
+) -> JsonOutput {
+    let start = JsonTokenInfo::new(1, 1, 0);
+    let mut pairs = vec![
+        (
+            "range",
+            range.unwrap_or_else(|| self::range("", start, start)),
+        ),
+        ("severity", JsonOutput::Int(severity)),
+        ("source", JsonOutput::string("json2rust")),
+        ("message", JsonOutput::string(message)),
+    ];
+    if let Some(code) = code {
+        pairs.push(("code", JsonOutput::string(code)));
+    }
+    JsonOutput::object(pairs)
+}
+
+fn range(text: &str, start: JsonTokenInfo, end: JsonTokenInfo) -> JsonOutput {
+    JsonOutput::object(vec![
+        ("start", position(text, start)),
+        ("end", position(text, end)),
+    ])
+}
+
+/// The protocol position of a location, which counts from 0 and in UTF-16
+/// code units.
+fn position(text: &str, location: JsonTokenInfo) -> JsonOutput {
+    let line = text
+        .lines()
+        .nth((location.line() - 1) as usize)
+        .unwrap_or("");
+    let character: usize = line
+        .chars()
+        .take((location.column() - 1) as usize)
+        .map(char::len_utf16)
+        .sum();
+    JsonOutput::object(vec![
+        ("line", JsonOutput::Int(location.line() - 1)),
+        ("character", JsonOutput::Int(character as i64)),
+    ])
+}
+
+/// The column in characters, counting from 0, of a protocol position on `line`.
+fn column_from_utf16(text: &str, line: i64, character: i64) -> Option<i64> {
+    let mut units = 0;
+    let mut column = 0;
+    for c in text.lines().nth(line as usize)?.chars() {
+        if units >= character as usize {
+            break;
+        }
+        units += c.len_utf16();
+        column += 1;
+    }
+    Some(column)
+}
+
+fn int(node: &JsonNode) -> Option<i64> {
+    match node {
+        JsonNode::Int(i) => Some(*i as i64),
+        _ => None,
+    }
+}
+
+/// The name of the root type for a document, after its file name.
+fn root_name(uri: &str) -> String {
+    let file = uri.rsplit('/').next().unwrap_or(uri);
+    let stem = file.split('.').next().unwrap_or(file);
+    match to_pascal_case(stem) {
+        name if name.is_empty() => "Root".to_string(),
+        name => name,
+    }
+}
+
+/// The json pointer of the innermost value or key at `line` and `column`,
+/// both counting from 1, with `*` for array elements.
+fn path_at(text: &str, leniency: Leniency, line: i64, column: i64) -> Option<String> {
+    struct Frame {
+        path: String,
+        start: JsonTokenInfo,
+        is_object: bool,
+        /// The key of the value being read, in an object
+        key: Option<String>,
+    }
+
+    let at = (line, column);
+    let contains = |start: JsonTokenInfo, end: JsonTokenInfo| {
+        (start.line(), start.column()) <= at && at < (end.line(), end.column())
+    };
+    let mut stack: Vec<Frame> = Vec::new();
+    for token in tokenize_json(text, leniency).ok()? {
+        match &token.token_type {
+            JsonTokenType::Colon | JsonTokenType::Comma => continue,
+            JsonTokenType::ObjectEnd | JsonTokenType::ArrayEnd => {
+                let frame = stack.pop()?;
+                if contains(frame.start, token.span.end) {
+                    return Some(frame.path);
+                }
+                continue;
+            }
+            JsonTokenType::Key(key) => {
+                if let Some(frame) = stack.last_mut().filter(|f| f.is_object && f.key.is_none()) {
+                    let path = child_path(&frame.path, key);
+                    if contains(token.span.start, token.span.end) {
+                        return Some(path);
+                    }
+                    frame.key = Some(key.to_string());
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        let path = match stack.last_mut() {
+            None => String::new(),
+            Some(frame) if frame.is_object => child_path(&frame.path, &frame.key.take()?),
+            Some(frame) => element_path(&frame.path),
+        };
+        match token.token_type {
+            JsonTokenType::ObjectStart | JsonTokenType::ArrayStart => stack.push(Frame {
+                path,
+                start: token.span.start,
+                is_object: token.token_type == JsonTokenType::ObjectStart,
+                key: None,
+            }),
+            _ if contains(token.span.start, token.span.end) => return Some(path),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(body: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    #[test]
+    fn finds_the_path_at_a_position() {
+        let text = "{\n  \"users\": [\n    {\"id\": 1},\n    {\"id\": 2, \"name\": \"x\"}\n  ]\n}";
+        let at = |line, column| path_at(text, Leniency::default(), line, column);
+
+        assert_eq!(at(2, 4), Some("/users".to_string()));
+        assert_eq!(at(3, 12), Some("/users/*/id".to_string()));
+        assert_eq!(at(4, 23), Some("/users/*/name".to_string()));
+        assert_eq!(at(3, 5), Some("/users/*".to_string()));
+        assert_eq!(at(6, 1), Some(String::new()));
+    }
+
+    #[test]
+    fn serves_diagnostics_hovers_and_code_actions() {
+        let input = [
+            message(r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}"#),
+            message(
+                r#"{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {"textDocument": {"uri": "file:///w/users.json", "text": "{\"id\": 1, \"name\": 'x'}"}}}"#,
+            ),
+            message(
+                r#"{"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {"textDocument": {"uri": "file:///w/users.json"}, "contentChanges": [{"text": "{\"id\": 1,\n \"name\": \"x\"}"}]}}"#,
+            ),
+            message(
+                r#"{"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {"textDocument": {"uri": "file:///w/users.json"}, "position": {"line": 1, "character": 3}}}"#,
+            ),
+            message(
+                r#"{"jsonrpc": "2.0", "id": 3, "method": "textDocument/codeAction", "params": {"textDocument": {"uri": "file:///w/users.json"}}}"#,
+            ),
+            message(r#"{"jsonrpc": "2.0", "id": 4, "method": "unknown"}"#),
+            message(r#"{"jsonrpc": "2.0", "method": "exit"}"#),
+        ]
+        .concat();
+        let mut output = Vec::new();
+        run_language_server(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("\"hoverProvider\": true"));
+        assert!(output.contains("JSON strings must use double quotes"));
+        assert!(output.contains("\"code\": \"unknown_json_character\""));
+        assert!(output.contains("```rust\\npub name: String\\n```"));
+        assert!(output.contains("\"uri\": \"file:///w/users.rs\""));
+        assert!(output.contains("pub struct Users {"));
+        assert!(output.contains("\"code\": -32601"));
+    }
+}
@@ -0,0 +1,1632 @@
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
+use clap_complete::Shell;
+#[cfg(feature = "client-stubs")]
+use json2rust::client_to_rust;
+use json2rust::{
+    changes_to_json, check_code, check_conversion, collection_to_rust, conversion_report,
+    conversion_to_json, conversion_to_rust, conversion_to_rust_with_origins,
+    convert_sample_csv_with_warnings, convert_sample_json_with_warnings,
+    convert_samples_json_with_warnings, de_helpers_module, dependencies_toml, field_order_savings,
+    generate_sample_tests, generated_file, generated_file_with_banner, har_to_rust,
+    merge_user_code, origins_to_json, plan_dependencies, resolved_config, run_repl, run_server,
+    stale_file_diff, structure_changes, type_sizes, Change, CodegenBackend, CompileError, Config,
+    Conversion, ConvertOptions, Dependency, Error, Origin, OutputVersion, Plugin, ReportFormat,
+    RequestResponse, Snapshot, TemplateBackend, TemplateKind, USER_CODE_BEGIN,
+};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The command line. Without a command it generates code, from the flags of
+/// `Args`.
+#[derive(Parser)]
+#[command(
+    name = "json2rust",
+    version,
+    about = "Generate rust types from a sample json document",
+    long_about = "Generates rust types from a sample json document, read from FILE or stdin.",
+    args_conflicts_with_subcommands = true,
+    after_help = exit_codes()
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+    #[command(flatten)]
+    args: Args,
+    /// Print the result, or the error, as a json object
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Print the paths of samples with their types and example values
+    #[command(
+        long_about = "Prints a table of every path in one or more samples of the same document, \
+                      with its inferred type, how often it is present and null, and example \
+                      values."
+    )]
+    Report(ReportArgs),
+    /// Save the structure inferred from samples to a snapshot
+    #[command(
+        long_about = "Infers the structure of one or more samples of the same document, and of \
+                      the .json files of each DIR, and writes it to a snapshot along with its \
+                      statistics, warnings and a hash of each sample. Code can then be generated \
+                      from the snapshot with `emit`, without analyzing the samples again."
+    )]
+    Analyze(AnalyzeArgs),
+    /// Print how the structure of a snapshot changed
+    #[command(
+        long_about = "Prints how the structure of a SNAPSHOT changed each time samples were \
+                      merged into it with `analyze --update`: the fields added, the types \
+                      widened and the values that became optional."
+    )]
+    History(HistoryArgs),
+    /// Compare two snapshots and fail on breaking changes
+    #[command(
+        long_about = "Compares the structures of the snapshots OLD and NEW, and lists how each \
+                      path changed. Removed fields, new required fields, values that became \
+                      required and types that were narrowed or changed are breaking, as \
+                      documents the types of OLD accepted may no longer deserialize into the \
+                      types of NEW, and fail the command with their own exit code, such as for \
+                      contract tests."
+    )]
+    Diff(DiffArgs),
+    /// Generate rust types from a snapshot made by analyze
+    #[command(
+        long_about = "Generates rust types from a SNAPSHOT written by `analyze`, with the options \
+                      of the config file and the flags."
+    )]
+    Emit(EmitArgs),
+    /// Fail with a diff if a generated file is out of date
+    #[command(
+        long_about = "Generates the code in memory, with the same options as when GENERATED was \
+                      written, and fails with a diff if GENERATED differs from it, such as to \
+                      check in CI that committed code is up to date with its samples. User code \
+                      regions and the timestamp of the banner are kept."
+    )]
+    Verify(Box<VerifyArgs>),
+    /// Show the config files read and the settings they resolve to
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Print the types of samples typed into stdin
+    #[command(
+        long_about = "Reads samples from stdin, each ending with a blank line, and prints their \
+                      types, with the options changed by `:set` commands, see `:help`."
+    )]
+    Repl,
+    /// Serve conversions over HTTP
+    #[command(
+        long_about = "Listens for HTTP requests converting samples: `POST /convert` with a json \
+                      object holding the `sample` as a string, and optionally the `name` of the \
+                      root type and a `config` in the format of json2rust.toml, responds with the \
+                      generated `code` and the `warnings`, or with the `error` of an invalid \
+                      sample. The `[limits]` of the config bound the size, nesting and time of \
+                      every request, whatever the config of the request says."
+    )]
+    Serve(ServeArgs),
+    /// Print the completions of a shell
+    #[command(long_about = "Prints the completions of SHELL, such as for \
+                      `json2rust completions bash > /etc/bash_completion.d/json2rust`.")]
+    Completions { shell: Shell },
+    /// Print the manpage
+    #[command(
+        long_about = "Prints the help as a manpage, such as for `json2rust manpage > json2rust.1`."
+    )]
+    Manpage,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// List the config files read, in the order they are read in
+    #[command(
+        long_about = "Lists the config files read, in the order they are read in: the user's \
+                      ~/.config/json2rust/config.toml, then the project's json2rust.toml or the \
+                      one given with --config. Settings of later files take precedence, and the \
+                      flags of the other commands take precedence over every file. With \
+                      --resolved it prints the settings the files resolve to instead, each with \
+                      the file it came from."
+    )]
+    Show(ConfigShowArgs),
+}
+
+/// The exit codes of the failures, for the end of the help.
+fn exit_codes() -> String {
+    let mut codes = String::from("Exit codes:\n  0  Success\n");
+    for kind in FailureKind::ALL {
+        codes.push_str(&format!("  {}  {}\n", kind.exit_code(), kind.description()));
+    }
+    codes
+}
+
+/// The command line `args` with every `--json` before `--` moved to the end.
+/// Clap stops looking for a command once it has seen a flag, with
+/// `args_conflicts_with_subcommands`, even a global one, so `--json verify`
+/// would take `verify` for the sample file.
+fn json_last(mut args: Vec<OsString>) -> Vec<OsString> {
+    let end = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    let rest = args.split_off(end);
+    let flags = args.len();
+    args.retain(|arg| arg != "--json");
+    if args.len() < flags {
+        args.push("--json".into());
+    }
+    args.extend(rest);
+    args
+}
+
+/// The failure of invalid arguments, for `--json`.
+fn usage_failure(error: clap::Error) -> Failure {
+    let message = error.to_string();
+    let message = message.lines().next().unwrap_or_default();
+    Failure::new(
+        FailureKind::Usage,
+        message.strip_prefix("error: ").unwrap_or(message),
+    )
+}
+
+fn parse_output_version(value: &str) -> Result<OutputVersion, String> {
+    value
+        .parse()
+        .ok()
+        .and_then(OutputVersion::from_number)
+        .ok_or_else(|| format!("unknown version '{}', expected 1", value))
+}
+
+const DEFAULT_CONFIG_FILE: &str = "json2rust.toml";
+
+/// Why a command failed. Each kind exits with its own code, which scripts can
+/// rely on, see the end of the help.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FailureKind {
+    Usage,
+    InvalidInput,
+    Io,
+    Stale,
+    Compile,
+    Breaking,
+}
+
+impl FailureKind {
+    const ALL: [FailureKind; 6] = [
+        FailureKind::Usage,
+        FailureKind::InvalidInput,
+        FailureKind::Io,
+        FailureKind::Stale,
+        FailureKind::Compile,
+        FailureKind::Breaking,
+    ];
+
+    fn exit_code(self) -> i32 {
+        match self {
+            FailureKind::Usage => 2,
+            FailureKind::InvalidInput => 3,
+            FailureKind::Io => 4,
+            FailureKind::Stale => 5,
+            FailureKind::Compile => 6,
+            FailureKind::Breaking => 7,
+        }
+    }
+
+    /// What the exit code means, for the help.
+    fn description(self) -> &'static str {
+        match self {
+            FailureKind::Usage => "Invalid command line arguments",
+            FailureKind::InvalidInput => "A sample, config or other input that couldn't be parsed",
+            FailureKind::Io => "A file or command that couldn't be read, written or run",
+            FailureKind::Stale => "`verify` found the generated file out of date",
+            FailureKind::Compile => "The generated code doesn't compile, with --check",
+            FailureKind::Breaking => "`diff` found changes that break consumers of the old types",
+        }
+    }
+
+    /// The name of the kind in `--json` output.
+    fn name(self) -> &'static str {
+        match self {
+            FailureKind::Usage => "usage",
+            FailureKind::InvalidInput => "invalid_input",
+            FailureKind::Io => "io",
+            FailureKind::Stale => "stale",
+            FailureKind::Compile => "compile",
+            FailureKind::Breaking => "breaking",
+        }
+    }
+}
+
+struct Failure {
+    kind: FailureKind,
+    message: String,
+    // The changes `verify` or `diff` found, for `FailureKind::Stale` and
+    // `FailureKind::Breaking`
+    diff: Option<String>,
+}
+
+impl Failure {
+    fn new<S: Into<String>>(kind: FailureKind, message: S) -> Failure {
+        Failure {
+            kind,
+            message: message.into(),
+            diff: None,
+        }
+    }
+
+    fn input<S: Into<String>>(message: S) -> Failure {
+        Failure::new(FailureKind::InvalidInput, message)
+    }
+
+    fn io<S: Into<String>>(message: S) -> Failure {
+        Failure::new(FailureKind::Io, message)
+    }
+}
+
+/// Errors of the arguments, which is where plain messages come from.
+impl From<String> for Failure {
+    fn from(message: String) -> Failure {
+        Failure::new(FailureKind::Usage, message)
+    }
+}
+
+impl From<&str> for Failure {
+    fn from(message: &str) -> Failure {
+        Failure::new(FailureKind::Usage, message)
+    }
+}
+
+/// What was read and reported while generating the code, besides the code.
+#[derive(Default)]
+struct Generation {
+    // The samples read, which the banner records
+    samples: Vec<String>,
+    warnings: Vec<String>,
+    // What the generated code does differently from the sample, such as
+    // reordering fields
+    notes: Vec<String>,
+    // Where the generated types came from in the sample, for `--source-map`
+    origins: Vec<Origin>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+    /// A browser capture, with types for every endpoint
+    Har,
+    /// A Postman or Insomnia collection
+    Collection,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Emit {
+    /// Rust code
+    Rust,
+    /// The inferred structure, as json
+    Ir,
+}
+
+/// The options of generating code, of the command line without a command and
+/// of `verify`.
+#[derive(clap::Args)]
+struct Args {
+    /// The arguments, which the banner describes the options with
+    #[arg(skip)]
+    raw: Vec<String>,
+    /// Name of the root type
+    #[arg(short, long, default_value = "Root")]
+    name: String,
+    /// Format of the input
+    #[arg(short, long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+    /// What to output
+    #[arg(long, value_name = "KIND", value_enum, default_value_t = Emit::Rust)]
+    emit: Emit,
+    /// Config file, read over the global ~/.config/json2rust/config.toml
+    /// [default: json2rust.toml, if it exists]
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    config: Option<String>,
+    /// Sample json document [default: stdin]
+    #[arg(value_name = "FILE", value_hint = ValueHint::FilePath)]
+    input: Option<String>,
+    /// Sample request body, generating a `<NAME>Request` type
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    request: Option<String>,
+    /// Sample response body, generating a `<NAME>Response` type
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    response: Option<String>,
+    /// Also generate a reqwest function calling the request and response
+    /// types' endpoint, given as e.g. "POST /users"
+    #[arg(long, value_name = "ENDPOINT")]
+    client: Option<String>,
+    /// Generate a reqwest function and response type for an endpoint, given as
+    /// a method, path and sample response file, e.g. "GET /users/{id}
+    /// user.json". Can be repeated
+    #[arg(long = "endpoint", value_name = "ENDPOINT")]
+    endpoints: Vec<String>,
+    /// Leave the field at PATH out of the generated types, given as a json
+    /// pointer such as /items/*/debug or a JSONPath such as $.items[*].debug.
+    /// Can be repeated
+    #[arg(long = "ignore-path", value_name = "PATH")]
+    ignore_paths: Vec<String>,
+    /// Leave every field whose key matches the glob PATTERN out of the
+    /// generated types, e.g. "_internal*". Can be repeated
+    #[arg(long = "ignore-key", value_name = "PATTERN")]
+    ignore_keys: Vec<String>,
+    /// Detect the types of string fields with the plugin json2rust-plugin-NAME
+    /// on the PATH, before the built-in detectors. Can be repeated
+    #[arg(long = "plugin", value_name = "NAME")]
+    plugins: Vec<String>,
+    /// Print the files the plugin json2rust-plugin-NAME on the PATH generates
+    /// from the inferred structure, instead of rust code
+    #[arg(long, value_name = "NAME")]
+    backend_plugin: Option<String>,
+    /// Render the types through the templates in DIR instead, any of
+    /// module.tmpl, struct.tmpl, field.tmpl, enum.tmpl and variant.tmpl, in a
+    /// subset of Handlebars with {{variable}} and
+    /// {{#if variable}}...{{else}}...{{/if}}
+    #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+    templates: Option<String>,
+    /// Tailor request and response types for axum or actix handlers, only
+    /// deriving the serde traits each needs
+    #[arg(long)]
+    server: bool,
+    /// Also derive utoipa::ToSchema on generated types
+    #[arg(long)]
+    openapi: bool,
+    /// Derive validator::Validate, with rules from the lengths, ranges and
+    /// formats of the values in the sample
+    #[arg(long)]
+    validate: bool,
+    /// Derive schemars::JsonSchema on generated types
+    #[arg(long)]
+    schema: bool,
+    /// Accept samples pasted from javascript or python, with single quoted
+    /// strings, unquoted keys, trailing commas, True, None and undefined
+    #[arg(long)]
+    relaxed: bool,
+    /// Treat empty strings in string and number fields as missing values
+    #[arg(long)]
+    empty_strings_as_none: bool,
+    /// Infer the structure of json sent as strings, and decode it when
+    /// deserializing
+    #[arg(long)]
+    json_strings: bool,
+    /// Turn description keys of the sample into doc comments on what they
+    /// describe
+    #[arg(long)]
+    field_descriptions: bool,
+    /// Make every field optional, for APIs that leave out fields arbitrarily
+    #[arg(long)]
+    all_optional: bool,
+    /// Declare fields by descending alignment with #[repr(C)] when that saves
+    /// padding, noting the bytes saved
+    #[arg(long)]
+    reorder_fields: bool,
+    /// Estimate the size of each generated type, warning about the ones much
+    /// larger than they look
+    #[arg(long)]
+    type_sizes: bool,
+    /// Append a test module checking that the json sample deserializes, and
+    /// matches the schema with --schema
+    #[arg(long)]
+    sample_tests: bool,
+    /// Print the dependencies the generated code needs to stderr, as a snippet
+    /// to paste into Cargo.toml
+    #[arg(long)]
+    deps: bool,
+    /// Add the dependencies the generated code needs to the crate in the
+    /// current directory, with `cargo add`
+    #[arg(long)]
+    cargo_add: bool,
+    /// Check that the generated code compiles with `cargo check`, along with a
+    /// test deserializing a json sample, reporting errors with the json path
+    /// they came from
+    #[arg(long)]
+    check: bool,
+    /// Write the deserialize_with helpers of coerced fields to FILE, a module
+    /// next to the generated code, instead of after the generated types
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    de_helpers: Option<String>,
+    /// Pipe the generated file through COMMAND, such as `rustfmt --edition
+    /// 2021`, instead of the formatter of style.formatter in the config
+    #[arg(long, value_name = "COMMAND")]
+    formatter: Option<String>,
+    /// Write the json pointer and place in the sample of every generated type
+    /// and field to FILE, as json
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    source_map: Option<String>,
+    /// Write the generated code to FILE instead of printing it, keeping the
+    /// code between the user code markers of the existing FILE
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    update: Option<String>,
+    /// Version of the generated code to pin, so upgrading json2rust doesn't
+    /// change it [default: latest]
+    #[arg(long, value_name = "VERSION", value_parser = parse_output_version)]
+    output_version: Option<OutputVersion>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportOutput {
+    Text,
+    Html,
+    Json,
+}
+
+#[derive(clap::Args)]
+struct ReportArgs {
+    /// Format of the report
+    #[arg(short, long, value_name = "FORMAT", value_enum, default_value_t = ReportOutput::Text)]
+    output: ReportOutput,
+    /// Config file, read over the global ~/.config/json2rust/config.toml
+    /// [default: json2rust.toml, if it exists]
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    config: Option<String>,
+    /// Accept samples pasted from javascript or python
+    #[arg(long)]
+    relaxed: bool,
+    /// Hash example strings and zero example numbers, for samples holding
+    /// personal data
+    #[arg(long)]
+    redact: bool,
+    /// Samples of the same document [default: stdin]
+    #[arg(value_name = "FILE", value_hint = ValueHint::FilePath)]
+    inputs: Vec<String>,
+}
+
+#[derive(clap::Args)]
+struct AnalyzeArgs {
+    /// File to write the snapshot to [default: stdout]
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    output: Option<String>,
+    /// Config file, read over the global ~/.config/json2rust/config.toml
+    /// [default: json2rust.toml, if it exists]
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    config: Option<String>,
+    /// Snapshot to merge the samples it doesn't have yet into, recording how
+    /// its structure changed in its history. It is written back, unless
+    /// --output is given
+    #[arg(long, value_name = "SNAPSHOT", value_hint = ValueHint::FilePath)]
+    update: Option<String>,
+    /// Accept samples pasted from javascript or python
+    #[arg(long)]
+    relaxed: bool,
+    /// Samples of the same document, or directories of them [default: stdin]
+    #[arg(value_name = "FILE|DIR", value_hint = ValueHint::AnyPath)]
+    inputs: Vec<String>,
+}
+
+#[derive(clap::Args)]
+struct HistoryArgs {
+    /// Snapshot written by `analyze`
+    #[arg(value_hint = ValueHint::FilePath)]
+    snapshot: String,
+}
+
+#[derive(clap::Args)]
+struct DiffArgs {
+    /// Snapshot the old types were generated from
+    #[arg(value_hint = ValueHint::FilePath)]
+    old: String,
+    /// Snapshot to compare to it
+    #[arg(value_hint = ValueHint::FilePath)]
+    new: String,
+}
+
+#[derive(clap::Args)]
+struct EmitArgs {
+    /// Snapshot written by `analyze`
+    #[arg(value_hint = ValueHint::FilePath)]
+    snapshot: String,
+    /// Name of the root type
+    #[arg(short, long, default_value = "Root")]
+    name: String,
+    /// File to write the code to [default: stdout]
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    output: Option<String>,
+    /// Config file, read over the global ~/.config/json2rust/config.toml
+    /// [default: json2rust.toml, if it exists]
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    config: Option<String>,
+    /// Tailor request and response types for axum or actix handlers, only
+    /// deriving the serde traits each needs
+    #[arg(long)]
+    server: bool,
+    /// Also derive utoipa::ToSchema on generated types
+    #[arg(long)]
+    openapi: bool,
+    /// Derive validator::Validate, with rules from the lengths, ranges and
+    /// formats of the values in the samples
+    #[arg(long)]
+    validate: bool,
+    /// Derive schemars::JsonSchema on generated types
+    #[arg(long)]
+    schema: bool,
+}
+
+#[derive(clap::Args)]
+struct VerifyArgs {
+    /// The generated file to check
+    #[arg(value_hint = ValueHint::FilePath)]
+    generated: String,
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(clap::Args)]
+struct ConfigShowArgs {
+    /// Config file read over the global one [default: json2rust.toml, if it
+    /// exists]
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    config: Option<String>,
+    /// Print the settings the config files resolve to, instead of the files
+    #[arg(long)]
+    resolved: bool,
+}
+
+#[derive(clap::Args)]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    address: String,
+    /// Config file the options of every request start from [default:
+    /// json2rust.toml, if it exists]
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    config: Option<String>,
+}
+
+fn load_config(path: Option<&str>) -> Result<Config, Failure> {
+    load_config_with_text(path).map(|(config, _)| config)
+}
+
+/// The user's own config, which project configs are read over, in
+/// `$XDG_CONFIG_HOME` or `~/.config`.
+fn global_config_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(dir.join("json2rust").join("config.toml"))
+}
+
+/// The paths and texts of the config files to read, in the order they are read
+/// in: the global config, then the project's or the one given with `--config`.
+fn config_layers(path: Option<&str>) -> Result<Vec<(String, String)>, Failure> {
+    let mut paths = Vec::new();
+    if let Some(global) = global_config_path().filter(|global| global.exists()) {
+        paths.push(global.display().to_string());
+    }
+    match path {
+        Some(path) => paths.push(path.to_string()),
+        None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+            paths.push(DEFAULT_CONFIG_FILE.to_string())
+        }
+        None => {}
+    }
+    paths
+        .into_iter()
+        .map(|path| match fs::read_to_string(&path) {
+            Ok(text) => Ok((path, text)),
+            Err(e) => Err(Failure::io(format!("Failed to read {}: {}", path, e))),
+        })
+        .collect()
+}
+
+/// The config, along with the text of the files it was read from.
+fn load_config_with_text(path: Option<&str>) -> Result<(Config, String), Failure> {
+    let mut config = Config::default();
+    let mut text = String::new();
+    for (path, layer) in config_layers(path)? {
+        config
+            .merge(&layer)
+            .map_err(|e| Failure::input(format!("Invalid config {}: {}", path, e)))?;
+        text.push_str(&layer);
+    }
+    Ok((config, text))
+}
+
+/// Lists the config files read, or the settings they resolve to with
+/// `--resolved`, for `json2rust config show`.
+fn run_config(args: ConfigShowArgs) -> Result<(), Failure> {
+    let layers = config_layers(args.config.as_deref())?;
+    if !args.resolved {
+        for (path, _) in &layers {
+            println!("{}", path);
+        }
+        return Ok(());
+    }
+    let layers: Vec<(&str, &str)> = layers
+        .iter()
+        .map(|(path, text)| (path.as_str(), text.as_str()))
+        .collect();
+    for (path, text) in &layers {
+        Config::parse(text)
+            .map_err(|e| Failure::input(format!("Invalid config {}: {}", path, e)))?;
+    }
+    let toml =
+        resolved_config(&layers).map_err(|e| Failure::input(format!("Invalid config: {}", e)))?;
+    print!("{}", toml);
+    Ok(())
+}
+
+/// Describes the options of the banner of the generated file: the config and
+/// the command line, apart from the input and output files and the flags that
+/// don't change the code.
+fn options_key(config: &str, args: &Args) -> String {
+    let mut key = config.to_string();
+    let mut iter = args.raw.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--update" => {
+                iter.next();
+            }
+            "--json" | "--deps" | "--cargo-add" | "--check" => {}
+            _ if args.input.as_ref() == Some(arg) => {}
+            _ => {
+                key.push(' ');
+                key.push_str(arg);
+            }
+        }
+    }
+    key
+}
+
+fn read_input(input: Option<&str>) -> Result<String, Failure> {
+    match input {
+        Some(path) if path != "-" => fs::read_to_string(path)
+            .map_err(|e| Failure::io(format!("Failed to read {}: {}", path, e))),
+        _ => {
+            let mut json = String::new();
+            io::stdin()
+                .read_to_string(&mut json)
+                .map_err(|e| Failure::io(format!("Failed to read stdin: {}", e)))?;
+            Ok(json)
+        }
+    }
+}
+
+/// Generates the request and response types of `--request` and `--response`.
+fn run_pairing(
+    args: &Args,
+    options: &ConvertOptions,
+    generation: &mut Generation,
+) -> Result<String, Failure> {
+    let request = args
+        .request
+        .as_deref()
+        .map(|path| read_input(Some(path)))
+        .transpose()?;
+    let response = args
+        .response
+        .as_deref()
+        .map(|path| read_input(Some(path)))
+        .transpose()?;
+    generation
+        .samples
+        .extend(request.iter().chain(&response).cloned());
+    let call =
+        RequestResponse::from_samples(&args.name, request.as_deref(), response.as_deref(), options)
+            .map_err(|e| Failure::input(format!("Invalid json: {}", e)))?;
+    #[allow(unused_mut)]
+    let mut code = call.to_rust(options);
+
+    #[cfg(feature = "client-stubs")]
+    if let Some(endpoint) = &args.client {
+        let (method, path) = endpoint
+            .split_once(' ')
+            .ok_or("--client must be a method and a path, such as \"POST /users\"")?;
+        code.push('\n');
+        code.push_str(&call.client_stub(method, path.trim()).to_rust());
+    }
+    #[cfg(not(feature = "client-stubs"))]
+    if args.client.is_some() {
+        return Err("json2rust was built without the client-stubs feature".into());
+    }
+    Ok(code)
+}
+
+/// Generates a client for the endpoints given with `--endpoint`.
+#[cfg(feature = "client-stubs")]
+fn run_client(
+    args: &Args,
+    options: &ConvertOptions,
+    generation: &mut Generation,
+) -> Result<String, Failure> {
+    let mut endpoints = Vec::new();
+    for endpoint in &args.endpoints {
+        let parts: Vec<&str> = endpoint.split_whitespace().collect();
+        match parts.as_slice() {
+            [method, path, file] => {
+                endpoints.push((*method, *path, read_input(Some(file))?));
+            }
+            _ => {
+                return Err(format!(
+                    "--endpoint must be a method, path and file, got '{}'",
+                    endpoint
+                )
+                .into())
+            }
+        }
+    }
+    generation
+        .samples
+        .extend(endpoints.iter().map(|(_, _, sample)| sample.clone()));
+    let endpoints: Vec<(&str, &str, &str)> = endpoints
+        .iter()
+        .map(|(method, path, sample)| (*method, *path, sample.as_str()))
+        .collect();
+    client_to_rust(&endpoints, options).map_err(|e| Failure::input(format!("Invalid json: {}", e)))
+}
+
+#[cfg(not(feature = "client-stubs"))]
+fn run_client(_: &Args, _: &ConvertOptions, _: &mut Generation) -> Result<String, Failure> {
+    Err("json2rust was built without the client-stubs feature".into())
+}
+
+fn invalid_json(json: &str, error: Error) -> String {
+    if let Error::MixedStringsAndNumbers { path } = &error {
+        return format!(
+            "{} is a string in some samples and a number in others, see \
+             inference.mixed_numbers in the config",
+            path
+        );
+    }
+    if let Error::FloatsNotAllowed { paths } = &error {
+        return format!(
+            "Found numbers with a fraction, which inference.floats in the config doesn't \
+             allow, at:\n  {}",
+            paths.join("\n  ")
+        );
+    }
+    match error.suggestion(json) {
+        Some(suggestion) => format!("Invalid json: {}\nhint: {}", error, suggestion),
+        None => format!("Invalid json: {}", error),
+    }
+}
+
+/// Generates the code for a sample read from the input, in any `--format`.
+/// Returns `None` when the inferred structure was printed instead, with
+/// `--emit ir`.
+fn run_sample(
+    args: &Args,
+    options: &ConvertOptions,
+    generation: &mut Generation,
+) -> Result<Option<String>, Failure> {
+    let input = read_input(args.input.as_deref())?;
+    generation.samples.push(input.clone());
+    let conversion = match args.format {
+        Format::Json => convert_sample_json_with_warnings(&input, options)
+            .map_err(|e| Failure::input(invalid_json(&input, e)))?,
+        Format::Csv => convert_sample_csv_with_warnings(&input, options)
+            .map_err(|e| Failure::input(format!("Invalid csv: {}", e)))?,
+        Format::Har | Format::Collection if args.emit == Emit::Ir => {
+            return Err("--emit ir is only supported for json and csv input".into())
+        }
+        Format::Har => {
+            let code = har_to_rust(&input, options)
+                .map_err(|e| Failure::input(format!("Invalid har: {}", e)))?;
+            return check(args, code).map(Some);
+        }
+        Format::Collection => {
+            let code = collection_to_rust(&input, options)
+                .map_err(|e| Failure::input(format!("Invalid collection: {}", e)))?;
+            return check(args, code).map(Some);
+        }
+    };
+    generation
+        .warnings
+        .extend(conversion.warnings.iter().map(|w| w.to_string()));
+    if args.emit == Emit::Ir {
+        println!("{}", conversion_to_json(&conversion, &args.name));
+        return Ok(None);
+    }
+    if let Some(dir) = &args.templates {
+        let backend = load_templates(dir)?;
+        for artifact in backend.generate(&conversion.structure, &args.name, options) {
+            print!("{}", artifact.contents);
+        }
+        return Ok(None);
+    }
+    if let Some(name) = &args.backend_plugin {
+        let plugin = Plugin::named(name);
+        let artifacts = plugin.generate(&conversion.structure, &args.name, options);
+        if let Some(error) = plugin.errors().into_iter().next() {
+            return Err(Failure::io(error));
+        }
+        for artifact in &artifacts {
+            // Like `head`, name the files when there are several
+            if artifacts.len() > 1 {
+                println!("==> {} <==", artifact.file_name);
+            }
+            print!("{}", artifact.contents);
+        }
+        return Ok(None);
+    }
+    if args.check && args.format == Format::Json {
+        let errors = check_conversion(&conversion, &input, &args.name, options)
+            .map_err(|e| Failure::io(format!("Failed to run cargo check: {}", e)))?;
+        report_compile_errors(&errors)?;
+    }
+    let (mut code, origins) = conversion_to_rust_with_origins(&conversion, &args.name, options);
+    generation.origins = origins;
+    for (name, saving) in field_order_savings(&conversion, &args.name, options) {
+        generation.notes.push(format!(
+            "reordered the fields of {} with #[repr(C)], saving an estimated {} bytes",
+            name, saving
+        ));
+    }
+    if args.type_sizes {
+        let (sizes, warnings) = type_sizes(&conversion, &args.name, options);
+        for size in sizes {
+            generation
+                .notes
+                .push(format!("{} is an estimated {} bytes", size.name, size.size));
+        }
+        generation
+            .warnings
+            .extend(warnings.iter().map(|w| w.to_string()));
+    }
+    if args.format == Format::Csv {
+        code = check(args, code)?;
+    }
+    if args.sample_tests {
+        code.push_str(&generate_sample_tests(
+            &input,
+            &conversion.structure,
+            &args.name,
+            options,
+        ));
+    }
+    Ok(Some(code))
+}
+
+/// The templates of `--templates`, with the defaults for the files `dir`
+/// doesn't have.
+fn load_templates(dir: &str) -> Result<TemplateBackend, Failure> {
+    let mut backend = TemplateBackend::new();
+    for kind in TemplateKind::ALL {
+        let path = Path::new(dir).join(kind.file_name());
+        let template = match fs::read_to_string(&path) {
+            Ok(template) => template,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(Failure::io(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        backend
+            .set_template(kind, &template)
+            .map_err(|e| Failure::input(format!("Invalid template {}: {}", dir, e)))?;
+    }
+    Ok(backend)
+}
+
+/// Fails with the errors from checking the generated code, if there are any.
+fn report_compile_errors(errors: &[CompileError]) -> Result<(), Failure> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+    Err(Failure::new(
+        FailureKind::Compile,
+        format!("The generated code doesn't compile:\n{}", errors.join("\n")),
+    ))
+}
+
+/// Checks that the generated code compiles when `--check` is given.
+fn check(args: &Args, code: String) -> Result<String, Failure> {
+    if args.check {
+        let errors = check_code(&code)
+            .map_err(|e| Failure::io(format!("Failed to run cargo check: {}", e)))?;
+        report_compile_errors(&errors)?;
+    }
+    Ok(code)
+}
+
+/// Pipes a generated file through the `formatter` command, returning what it
+/// printed.
+fn format_file(formatter: &str, file: &str) -> Result<String, Failure> {
+    let mut words = formatter.split_whitespace();
+    let program = words.next().ok_or("The formatter command is empty")?;
+    let mut child = process::Command::new(program)
+        .args(words)
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .spawn()
+        .map_err(|e| Failure::io(format!("Failed to run {}: {}", program, e)))?;
+    // Written from another thread, so a formatter printing as it reads can't
+    // block on a full pipe
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = file.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| Failure::io(format!("Failed to run {}: {}", program, e)))?;
+    let written = writer.join().expect("writing to the formatter panicked");
+    if !output.status.success() {
+        return Err(Failure::io(format!("{} failed", formatter)));
+    }
+    written.map_err(|e| Failure::io(format!("Failed to write to {}: {}", program, e)))?;
+    String::from_utf8(output.stdout)
+        .map_err(|_| Failure::io(format!("{} printed invalid UTF-8", program)))
+}
+
+/// Adds the dependencies of the generated code to the crate in the current
+/// directory.
+fn cargo_add(dependencies: &[Dependency]) -> Result<(), Failure> {
+    for dependency in dependencies {
+        let status = process::Command::new("cargo")
+            .args(dependency.cargo_add_args())
+            .status()
+            .map_err(|e| Failure::io(format!("Failed to run cargo add: {}", e)))?;
+        if !status.success() {
+            return Err(Failure::io(format!("cargo add {} failed", dependency.name)));
+        }
+    }
+    Ok(())
+}
+
+/// Serves conversions over HTTP until the process is stopped.
+fn run_serve(args: ServeArgs) -> Result<(), Failure> {
+    let config = load_config(args.config.as_deref())?;
+    let listener = TcpListener::bind(&args.address)
+        .map_err(|e| Failure::io(format!("Failed to listen on {}: {}", args.address, e)))?;
+    eprintln!("Listening on http://{}", args.address);
+    run_server(listener, config).map_err(|e| Failure::io(format!("Failed to serve: {}", e)))
+}
+
+/// Prints a report of the paths in the samples given to `json2rust report`.
+fn run_report(args: ReportArgs, json: bool) -> Result<(), Failure> {
+    let mut options = ConvertOptions::default();
+    load_config(args.config.as_deref())?.apply(&mut options);
+    if args.relaxed {
+        options.set_relaxed_json(true);
+    }
+    if args.redact {
+        options.set_redact_samples(true);
+    }
+    let format = match args.output {
+        _ if json => ReportFormat::Json,
+        ReportOutput::Text => ReportFormat::Text,
+        ReportOutput::Html => ReportFormat::Html,
+        ReportOutput::Json => ReportFormat::Json,
+    };
+    let inputs = if args.inputs.is_empty() {
+        vec![read_input(None)?]
+    } else {
+        args.inputs
+            .iter()
+            .map(|input| read_input(Some(input)))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let conversion = convert_samples(&args.inputs, &inputs, &options)?;
+    print!("{}", conversion_report(&conversion, format));
+    Ok(())
+}
+
+/// Infers the structure of the samples read from the files `paths`.
+fn convert_samples(
+    paths: &[String],
+    inputs: &[String],
+    options: &ConvertOptions,
+) -> Result<Conversion, Failure> {
+    let samples: Vec<&str> = inputs.iter().map(String::as_str).collect();
+    convert_samples_json_with_warnings(&samples, options).map_err(|error| {
+        // Point at the sample that failed, since the error doesn't say which
+        Failure::input(
+            match samples
+                .iter()
+                .position(|json| convert_samples_json_with_warnings(&[json], options).is_err())
+            {
+                Some(i) if inputs.len() > 1 => {
+                    format!("{}: {}", paths[i], invalid_json(samples[i], error))
+                }
+                _ => invalid_json(samples[0], error),
+            },
+        )
+    })
+}
+
+/// The files given to `analyze`, with directories replaced by the `.json`
+/// files in them, in the order of their names.
+fn sample_paths(inputs: &[String]) -> Result<Vec<String>, Failure> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        if !Path::new(input).is_dir() {
+            paths.push(input.clone());
+            continue;
+        }
+        let entries = fs::read_dir(input)
+            .map_err(|e| Failure::io(format!("Failed to read {}: {}", input, e)))?;
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|e| Failure::io(format!("Failed to read {}: {}", input, e)))?
+                .path();
+            if path.is_file() && path.extension() == Some("json".as_ref()) {
+                files.push(path.display().to_string());
+            }
+        }
+        if files.is_empty() {
+            return Err(Failure::input(format!("No .json files in {}", input)));
+        }
+        files.sort();
+        paths.extend(files);
+    }
+    Ok(paths)
+}
+
+/// Writes `contents` to `output`, or prints it when there is none.
+fn write_output(output: Option<&str>, contents: &str) -> Result<(), Failure> {
+    match output {
+        Some(file) => fs::write(file, contents)
+            .map_err(|e| Failure::io(format!("Failed to write {}: {}", file, e))),
+        None => {
+            print!("{}", contents);
+            Ok(())
+        }
+    }
+}
+
+/// Infers the structure of samples and saves it to a snapshot, for
+/// `json2rust analyze`.
+fn run_analyze(args: AnalyzeArgs) -> Result<(), Failure> {
+    let mut options = ConvertOptions::default();
+    load_config(args.config.as_deref())?.apply(&mut options);
+    if args.relaxed {
+        options.set_relaxed_json(true);
+    }
+    let paths = if args.inputs.is_empty() {
+        vec!["-".to_string()]
+    } else {
+        sample_paths(&args.inputs)?
+    };
+    let samples = paths
+        .iter()
+        .map(|path| read_input(Some(path)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut snapshot = match &args.update {
+        Some(file) => Some(read_snapshot(file)?),
+        None => None,
+    };
+    // Samples merged in before would count twice in the stats
+    let (paths, samples): (Vec<String>, Vec<String>) = paths
+        .into_iter()
+        .zip(samples)
+        .filter(|(_, sample)| snapshot.as_ref().is_none_or(|s| !s.has_sample(sample)))
+        .unzip();
+    if samples.is_empty() {
+        eprintln!("The snapshot already has every sample");
+        return Ok(());
+    }
+    let conversion = convert_samples(&paths, &samples, &options)?;
+    for warning in &conversion.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    let named: Vec<(&str, &str)> = paths
+        .iter()
+        .zip(&samples)
+        .map(|(path, sample)| (path.as_str(), sample.as_str()))
+        .collect();
+    let snapshot = match snapshot.as_mut() {
+        Some(snapshot) => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs());
+            snapshot.update(conversion, &named, now);
+            snapshot
+        }
+        None => snapshot.insert(Snapshot::new(conversion, &named)),
+    };
+    let output = args.output.as_deref().or(args.update.as_deref());
+    write_output(output, &format!("{}\n", snapshot.to_json()))
+}
+
+fn read_snapshot(file: &str) -> Result<Snapshot, Failure> {
+    let text = read_input(Some(file))?;
+    Snapshot::parse(&text).map_err(|e| Failure::input(format!("Invalid snapshot {}: {}", file, e)))
+}
+
+/// Prints how the structure of a snapshot changed, for `json2rust history`.
+fn run_history(args: HistoryArgs, json: bool) -> Result<(), Failure> {
+    let file = &args.snapshot;
+    let snapshot = read_snapshot(file)?;
+    if json {
+        println!("{}", snapshot.history_json());
+    } else if snapshot.history.is_empty() {
+        println!("{} hasn't been updated with `analyze --update`", file);
+    } else {
+        for revision in &snapshot.history {
+            print!("{}", revision);
+        }
+    }
+    Ok(())
+}
+
+/// Compares two snapshots, failing if the changes break consumers of the types
+/// of the old one, for `json2rust diff`.
+fn run_diff(args: DiffArgs, json: bool) -> Result<(), Failure> {
+    let (old, new) = (read_snapshot(&args.old)?, read_snapshot(&args.new)?);
+    let changes = structure_changes(&old.conversion.structure, &new.conversion.structure);
+    let (breaking, other): (Vec<&Change>, Vec<&Change>) =
+        changes.iter().partition(|change| change.is_breaking());
+    let mut listing = String::new();
+    for (heading, changes) in [("Breaking changes", &breaking), ("Other changes", &other)] {
+        if !changes.is_empty() {
+            listing.push_str(&format!("{}:\n", heading));
+            for change in changes {
+                listing.push_str(&format!("  {}\n", change));
+            }
+        }
+    }
+    if !breaking.is_empty() {
+        return Err(Failure {
+            diff: Some(listing),
+            ..Failure::new(
+                FailureKind::Breaking,
+                format!(
+                    "{} breaking change{}",
+                    breaking.len(),
+                    if breaking.len() == 1 { "" } else { "s" }
+                ),
+            )
+        });
+    }
+    if json {
+        println!(
+            "{{\"ok\": true, \"changes\": {}}}",
+            changes_to_json(&changes)
+        );
+    } else if changes.is_empty() {
+        println!("No changes");
+    } else {
+        print!("{}", listing);
+    }
+    Ok(())
+}
+
+/// Generates the code of a snapshot written by `analyze`, for `json2rust emit`.
+fn run_emit(args: EmitArgs) -> Result<(), Failure> {
+    let mut options = ConvertOptions::default();
+    load_config(args.config.as_deref())?.apply(&mut options);
+    if args.server {
+        options.set_server_types(true);
+    }
+    if args.openapi {
+        options.set_openapi_schemas(true);
+    }
+    if args.validate {
+        options.set_validation(true);
+    }
+    if args.schema {
+        options.set_json_schemas(true);
+    }
+    let snapshot = read_snapshot(&args.snapshot)?;
+    let code = conversion_to_rust(&snapshot.conversion, &args.name, &options);
+    write_output(args.output.as_deref(), &generated_file(&code, &options))
+}
+
+/// Generates the file for the arguments, or returns `None` when the inferred
+/// structure was printed instead, with `--emit ir`.
+fn generate_file(
+    args: &Args,
+    user_code_region: bool,
+    generation: &mut Generation,
+) -> Result<Option<String>, Failure> {
+    let mut options = ConvertOptions::default();
+    let (config, config_text) = load_config_with_text(args.config.as_deref())?;
+    config.apply(&mut options);
+    if args.server {
+        options.set_server_types(true);
+    }
+    if args.openapi {
+        options.set_openapi_schemas(true);
+    }
+    if args.validate {
+        options.set_validation(true);
+    }
+    if args.schema {
+        options.set_json_schemas(true);
+    }
+    if args.relaxed {
+        options.set_relaxed_json(true);
+    }
+    if args.empty_strings_as_none {
+        options.set_empty_strings_as_none(true);
+    }
+    if args.json_strings {
+        options.set_json_strings(true);
+    }
+    if args.field_descriptions {
+        options.set_field_descriptions(true);
+    }
+    if args.all_optional {
+        options.set_all_optional(true);
+    }
+    if args.reorder_fields {
+        options.set_reorder_fields(true);
+    }
+    for path in &args.ignore_paths {
+        options.ignore_path(path);
+    }
+    for key in &args.ignore_keys {
+        options.ignore_key(key.as_str());
+    }
+    let plugins: Vec<Plugin> = args
+        .plugins
+        .iter()
+        .map(|name| Plugin::named(name))
+        .collect();
+    for plugin in &plugins {
+        options.register_detector(plugin.clone());
+    }
+    if let Some(version) = args.output_version {
+        options.set_output_version(version);
+    }
+    if user_code_region {
+        options.set_user_code_region(true);
+    }
+    if let Some(file) = &args.de_helpers {
+        if args.check {
+            return Err("--check can't be combined with --de-helpers".into());
+        }
+        let module = Path::new(file)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| format!("Invalid --de-helpers file '{}'", file))?;
+        options.set_helpers_module(Some(format!("crate::{}", module)));
+    }
+    if args.sample_tests && args.format != Format::Json {
+        return Err("--sample-tests is only supported for json input".into());
+    }
+
+    for (flag, given) in [
+        ("--backend-plugin", args.backend_plugin.is_some()),
+        ("--templates", args.templates.is_some()),
+    ] {
+        if given
+            && (args.format != Format::Json || args.request.is_some() || args.response.is_some())
+        {
+            return Err(format!("{} is only supported for a single json sample", flag).into());
+        }
+    }
+
+    let code = if !args.endpoints.is_empty() {
+        Some(check(args, run_client(args, &options, generation)?)?)
+    } else if args.request.is_some() || args.response.is_some() {
+        Some(check(args, run_pairing(args, &options, generation)?)?)
+    } else if args.client.is_some() {
+        return Err("--client needs a --request or --response sample".into());
+    } else {
+        run_sample(args, &options, generation)?
+    };
+    // Types a failing plugin didn't detect would silently differ, so its
+    // errors fail the command instead of being warnings
+    if let Some(error) = plugins.iter().flat_map(Plugin::errors).next() {
+        return Err(Failure::io(error));
+    }
+    let code = match code {
+        Some(code) => code,
+        None => return Ok(None),
+    };
+    let samples: Vec<&str> = generation.samples.iter().map(String::as_str).collect();
+    let file =
+        generated_file_with_banner(&code, &samples, &options_key(&config_text, args), &options);
+    // The origins are of lines of the generated types, which follow the
+    // banner and header in the file
+    let offset = file
+        .find(code.as_str())
+        .map(|i| file[..i].matches('\n').count());
+    for origin in &mut generation.origins {
+        origin.line += offset.unwrap_or(0);
+    }
+    match args.formatter.as_ref().or(config.formatter.as_ref()) {
+        Some(_) if args.source_map.is_some() => {
+            Err("--source-map can't be combined with a formatter".into())
+        }
+        Some(formatter) => format_file(formatter, &file).map(Some),
+        None => Ok(Some(file)),
+    }
+}
+
+/// Checks the generated file given to `json2rust verify` against the code
+/// generated now, failing with a diff if it is stale.
+fn run_verify(verify: VerifyArgs, json: bool) -> Result<(), Failure> {
+    let (file, args) = (verify.generated, verify.args);
+    if args.emit == Emit::Ir {
+        return Err("--emit ir can't be verified".into());
+    }
+    let committed = fs::read_to_string(&file)
+        .map_err(|e| Failure::io(format!("Failed to read {}: {}", file, e)))?;
+    let mut generation = Generation::default();
+    let generated =
+        match generate_file(&args, committed.contains(USER_CODE_BEGIN), &mut generation)? {
+            Some(generated) => generated,
+            None => return Ok(()),
+        };
+    match stale_file_diff(&committed, &generated) {
+        Some(diff) => Err(Failure {
+            diff: Some(diff),
+            ..Failure::new(
+                FailureKind::Stale,
+                format!("{} is out of date with its samples, regenerate it", file),
+            )
+        }),
+        None if json => {
+            println!("{{\"ok\": true, \"stale\": false}}");
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+fn run() -> Result<(), Failure> {
+    let cli = match Cli::try_parse_from(json_last(env::args_os().collect())) {
+        Ok(cli) => cli,
+        Err(error) if error.use_stderr() && env::args().any(|arg| arg == "--json") => {
+            return Err(usage_failure(error))
+        }
+        Err(error) => error.exit(),
+    };
+    let json = cli.json;
+    let mut args = match cli.command {
+        None => cli.args,
+        Some(CliCommand::Report(args)) => return run_report(args, json),
+        Some(CliCommand::Analyze(args)) => return run_analyze(args),
+        Some(CliCommand::Emit(args)) => return run_emit(args),
+        Some(CliCommand::History(args)) => return run_history(args, json),
+        Some(CliCommand::Diff(args)) => return run_diff(args, json),
+        Some(CliCommand::Verify(mut verify)) => {
+            // Neither the command nor the file verified are options of the
+            // code, and `--json` may come before the command
+            let mut raw: Vec<String> = env::args().skip(1).collect();
+            for removed in ["verify", verify.generated.as_str()] {
+                if let Some(index) = raw.iter().position(|arg| arg == removed) {
+                    raw.remove(index);
+                }
+            }
+            verify.args.raw = raw;
+            return run_verify(*verify, json);
+        }
+        Some(CliCommand::Config {
+            command: ConfigCommand::Show(args),
+        }) => return run_config(args),
+        Some(CliCommand::Serve(args)) => return run_serve(args),
+        Some(CliCommand::Completions { shell }) => {
+            // Generated into a buffer, as clap_complete panics on write errors
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut Cli::command(), "json2rust", &mut script);
+            return io::stdout()
+                .write_all(&script)
+                .map_err(|e| Failure::io(format!("Failed to write the completions: {}", e)));
+        }
+        Some(CliCommand::Manpage) => {
+            return clap_mangen::Man::new(Cli::command())
+                .render(&mut io::stdout())
+                .map_err(|e| Failure::io(format!("Failed to write the manpage: {}", e)))
+        }
+        Some(CliCommand::Repl) => {
+            return run_repl(io::stdin().lock(), io::stdout())
+                .map_err(|e| Failure::io(format!("Failed to read stdin: {}", e)))
+        }
+    };
+    args.raw = env::args().skip(1).collect();
+    let mut generation = Generation::default();
+    let code = match generate_file(&args, args.update.is_some(), &mut generation)? {
+        Some(code) => code,
+        None => return Ok(()),
+    };
+    if !json {
+        for warning in &generation.warnings {
+            eprintln!("warning: {}", warning);
+        }
+        for note in &generation.notes {
+            eprintln!("note: {}", note);
+        }
+    }
+    match &args.update {
+        Some(file) => {
+            let previous = match fs::read_to_string(file) {
+                Ok(previous) => previous,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(Failure::io(format!("Failed to read {}: {}", file, e))),
+            };
+            fs::write(file, merge_user_code(&previous, &code))
+                .map_err(|e| Failure::io(format!("Failed to write {}: {}", file, e)))?;
+        }
+        None if !json => print!("{}", code),
+        None => {}
+    }
+
+    if let Some(file) = &args.source_map {
+        fs::write(file, origins_to_json(&generation.origins))
+            .map_err(|e| Failure::io(format!("Failed to write {}: {}", file, e)))?;
+    }
+    let helpers = match &args.de_helpers {
+        Some(file) => match de_helpers_module(&code) {
+            Some(helpers) => {
+                fs::write(file, &helpers)
+                    .map_err(|e| Failure::io(format!("Failed to write {}: {}", file, e)))?;
+                helpers
+            }
+            None => String::new(),
+        },
+        None => String::new(),
+    };
+    let dependencies = plan_dependencies(&format!("{}{}", code, helpers));
+    if args.cargo_add {
+        cargo_add(&dependencies)?;
+    }
+    if json {
+        let warnings: Vec<String> = generation.warnings.iter().map(|w| json_string(w)).collect();
+        let dependencies: Vec<String> = dependencies.iter().map(|d| json_string(&d.name)).collect();
+        println!(
+            "{{\"ok\": true, \"code\": {}, \"warnings\": [{}], \"dependencies\": [{}]}}",
+            match &args.update {
+                Some(_) => "null".to_string(),
+                None => json_string(&code),
+            },
+            warnings.join(", "),
+            dependencies.join(", ")
+        );
+    } else if args.deps {
+        // The snippet goes to stderr, so the code can still be redirected to a
+        // file
+        eprint!("\n{}", dependencies_toml(&dependencies));
+    }
+    Ok(())
+}
+
+/// `s` as a json string, for the output of `--json`.
+fn json_string(s: &str) -> String {
+    let mut output = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+fn main() {
+    if let Err(failure) = run() {
+        if std::env::args().any(|arg| arg == "--json") {
+            println!(
+                "{{\"ok\": false, \"error\": {{\"kind\": \"{}\", \"exit_code\": {}, \"message\": {}}}, \"diff\": {}}}",
+                failure.kind.name(),
+                failure.kind.exit_code(),
+                json_string(&failure.message),
+                failure.diff.as_deref().map_or("null".to_string(), json_string)
+            );
+        } else {
+            if let Some(diff) = &failure.diff {
+                print!("{}", diff);
+            }
+            eprintln!("error: {}", failure.message);
+        }
+        process::exit(failure.kind.exit_code());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_command_line() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["json2rust", "-n", "User", "sample.json"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.args.name, "User");
+        assert_eq!(cli.args.input.as_deref(), Some("sample.json"));
+
+        let cli = Cli::try_parse_from(["json2rust", "verify", "types.rs", "--relaxed", "a.json"])
+            .unwrap();
+        match cli.command {
+            Some(CliCommand::Verify(verify)) => {
+                assert_eq!(verify.generated, "types.rs");
+                assert!(verify.args.relaxed);
+                assert_eq!(verify.args.input.as_deref(), Some("a.json"));
+            }
+            _ => panic!("expected verify"),
+        }
+
+        assert!(Cli::try_parse_from(["json2rust", "--name"]).is_err());
+        assert!(Cli::try_parse_from(["json2rust", "--emit", "python"]).is_err());
+        assert!(Cli::try_parse_from(["json2rust", "--output-version", "9"]).is_err());
+        assert!(Cli::try_parse_from(["json2rust", "a.json", "b.json"]).is_err());
+    }
+
+    #[test]
+    fn takes_json_before_or_after_the_command() {
+        let parse = |args: &[&str]| {
+            Cli::try_parse_from(json_last(args.iter().map(OsString::from).collect())).unwrap()
+        };
+
+        for args in [
+            &["json2rust", "--json", "verify", "types.rs", "a.json"][..],
+            &["json2rust", "verify", "--json", "types.rs", "a.json"],
+            &["json2rust", "verify", "types.rs", "a.json", "--json"],
+        ] {
+            let cli = parse(args);
+            assert!(cli.json, "{:?}", args);
+            match cli.command {
+                Some(CliCommand::Verify(verify)) => {
+                    assert_eq!(verify.generated, "types.rs");
+                    assert_eq!(verify.args.input.as_deref(), Some("a.json"));
+                }
+                _ => panic!("expected verify for {:?}", args),
+            }
+        }
+        for args in [
+            &["json2rust", "--json", "diff", "old.json", "new.json"][..],
+            &["json2rust", "diff", "old.json", "new.json", "--json"],
+        ] {
+            let cli = parse(args);
+            assert!(cli.json, "{:?}", args);
+            assert!(
+                matches!(cli.command, Some(CliCommand::Diff(_))),
+                "{:?}",
+                args
+            );
+        }
+        assert!(parse(&["json2rust", "--json", "history", "snapshot.json"]).json);
+        assert!(parse(&["json2rust", "report", "--json"]).json);
+
+        let cli = parse(&["json2rust", "--json", "-n", "User", "sample.json"]);
+        assert!(cli.json && cli.command.is_none());
+        assert_eq!(cli.args.input.as_deref(), Some("sample.json"));
+        assert!(!parse(&["json2rust", "verify", "types.rs"]).json);
+
+        // After `--` it is the sample file
+        let cli = parse(&["json2rust", "--", "--json"]);
+        assert!(!cli.json);
+        assert_eq!(cli.args.input.as_deref(), Some("--json"));
+    }
+
+    #[test]
+    fn completions_and_manpage_cover_every_flag() {
+        let command = Cli::command();
+        let mut manpage = Vec::new();
+        clap_mangen::Man::new(command.clone())
+            .render(&mut manpage)
+            .unwrap();
+        let manpage = String::from_utf8(manpage).unwrap();
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut Cli::command(), "json2rust", &mut script);
+            let script = String::from_utf8(script).unwrap();
+            for subcommand in command.get_subcommands() {
+                assert!(script.contains(subcommand.get_name()), "{:?}", shell);
+                for arg in subcommand.get_arguments() {
+                    if let Some(long) = arg.get_long() {
+                        assert!(script.contains(long), "{:?} --{}", shell, long);
+                    }
+                }
+            }
+        }
+        for arg in command.get_arguments() {
+            if let Some(long) = arg.get_long() {
+                assert!(manpage.contains(&format!("\\-\\-{}", long.replace('-', "\\-"))));
+            }
+        }
+        assert!(manpage.contains("Exit codes"));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::binary::ByteReader;
+use crate::parser::{JsonNode, KeyInterner};
+use crate::shared::Error;
+
+struct MsgpackDecoder<'a> {
+    reader: ByteReader<'a>,
+    keys: KeyInterner,
+}
+
+impl<'a> MsgpackDecoder<'a> {
+    fn value(&mut self) -> Result<JsonNode, Error> {
+        let marker = self.reader.byte()?;
+        Ok(match marker {
+            0x00..=0x7f => JsonNode::Int(i128::from(marker)),
+            0xe0..=0xff => JsonNode::Int(i128::from(marker as i8)),
+            0x80..=0x8f => self.map(u64::from(marker & 0x0f))?,
+            0x90..=0x9f => self.array(u64::from(marker & 0x0f))?,
+            0xa0..=0xbf => JsonNode::String(self.reader.text(usize::from(marker & 0x1f))?),
+            0xc0 => JsonNode::Null,
+            0xc2 | 0xc3 => JsonNode::Bool,
+            0xc4..=0xc6 => {
+                let len = self.length(1 << (marker - 0xc4))?;
+                self.reader.take(len)?;
+                JsonNode::Bytes
+            }
+            // Extension types are application specific, so they are kept as bytes
+            0xc7..=0xc9 => {
+                let len = self.length(1 << (marker - 0xc7))?;
+                self.reader.take(len + 1)?;
+                JsonNode::Bytes
+            }
+            0xca => JsonNode::Float(self.reader.float(4)?),
+            0xcb => JsonNode::Float(self.reader.float(8)?),
+            0xcc..=0xcf => JsonNode::Int(i128::from(self.reader.uint(1 << (marker - 0xcc))?)),
+            0xd0..=0xd3 => JsonNode::Int(i128::from(self.reader.int(1 << (marker - 0xd0))?)),
+            0xd4..=0xd8 => {
+                self.reader.take((1 << (marker - 0xd4)) + 1)?;
+                JsonNode::Bytes
+            }
+            0xd9..=0xdb => {
+                let len = self.length(1 << (marker - 0xd9))?;
+                JsonNode::String(self.reader.text(len)?)
+            }
+            0xdc | 0xdd => {
+                let len = self.reader.uint(2 << (marker - 0xdc))?;
+                self.array(len)?
+            }
+            0xde | 0xdf => {
+                let len = self.reader.uint(2 << (marker - 0xde))?;
+                self.map(len)?
+            }
+            0xc1 => {
+                self.reader.position -= 1;
+                return Err(self.reader.error("Invalid marker 0xc1"));
+            }
+        })
+    }
+
+    fn length(&mut self, size: usize) -> Result<usize, Error> {
+        let len = self.reader.uint(size)?;
+        self.reader.length(len)
+    }
+
+    fn array(&mut self, len: u64) -> Result<JsonNode, Error> {
+        // Every value takes at least one byte
+        let len = self.reader.length(len)?;
+        let mut values = Vec::with_capacity(len);
+        for _ in 0..len {
+            values.push(self.value()?);
+        }
+        Ok(JsonNode::Array(values))
+    }
+
+    fn map(&mut self, len: u64) -> Result<JsonNode, Error> {
+        let len = self.reader.length(len)?;
+        let mut pairs = Vec::with_capacity(len);
+        for _ in 0..len {
+            let key = self.key()?;
+            pairs.push((self.keys.intern(&key), self.value()?));
+        }
+        Ok(JsonNode::Object(pairs))
+    }
+
+    /// Reads a map key. Integer keys are used as their decimal text, like they
+    /// would be in json.
+    fn key(&mut self) -> Result<String, Error> {
+        let start = self.reader.position;
+        let marker = self.reader.byte()?;
+        match marker {
+            0x00..=0x7f => Ok(marker.to_string()),
+            0xe0..=0xff => Ok((marker as i8).to_string()),
+            0xa0..=0xbf => self.reader.text(usize::from(marker & 0x1f)),
+            0xd9..=0xdb => {
+                let len = self.length(1 << (marker - 0xd9))?;
+                self.reader.text(len)
+            }
+            0xcc..=0xcf => Ok(self.reader.uint(1 << (marker - 0xcc))?.to_string()),
+            0xd0..=0xd3 => {
+                let size = 1 << (marker - 0xd0);
+                let value = self.reader.uint(size)?;
+                // Sign extend the value from its size
+                let shift = 64 - 8 * size as u32;
+                Ok((((value << shift) as i64) >> shift).to_string())
+            }
+            _ => {
+                self.reader.position = start;
+                Err(self.reader.error("Map keys must be strings or integers"))
+            }
+        }
+    }
+}
+
+/// Decodes a MessagePack document into a node tree.
+pub fn parse_msgpack(bytes: &[u8]) -> Result<JsonNode, Error> {
+    let mut decoder = MsgpackDecoder {
+        reader: ByteReader::new(bytes),
+        keys: KeyInterner::default(),
+    };
+    let node = decoder.value()?;
+    if !decoder.reader.is_at_end() {
+        return Err(decoder.reader.error("Unexpected data after the value"));
+    }
+    Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_msgpack() {
+        // {"id": 7, "name": "a", "tags": [true, nil], 1: 1.5, "raw": bin(2), -2: {}}
+        let bytes = [
+            0x86, 0xa2, b'i', b'd', 0x07, 0xa4, b'n', b'a', b'm', b'e', 0xa1, b'a', 0xa4, b't',
+            b'a', b'g', b's', 0x92, 0xc3, 0xc0, 0x01, 0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0xa3,
+            b'r', b'a', b'w', 0xc4, 0x02, 0xff, 0x00, 0xd0, 0xfe, 0x80,
+        ];
+
+        assert_eq!(
+            parse_msgpack(&bytes),
+            Ok(JsonNode::Object(vec![
+                ("id".into(), JsonNode::Int(7)),
+                ("name".into(), JsonNode::String("a".to_string())),
+                (
+                    "tags".into(),
+                    JsonNode::Array(vec![JsonNode::Bool, JsonNode::Null])
+                ),
+                ("1".into(), JsonNode::Float(1.5)),
+                ("raw".into(), JsonNode::Bytes),
+                ("-2".into(), JsonNode::Object(vec![])),
+            ]))
+        )
+    }
+
+    #[test]
+    fn reports_invalid_msgpack() {
+        assert_eq!(
+            parse_msgpack(&[0x91, 0xc1]),
+            Err(Error::InvalidBinary {
+                offset: 1,
+                message: "Invalid marker 0xc1".to_string()
+            })
+        );
+        assert_eq!(
+            parse_msgpack(&[0xdd, 0xff, 0xff, 0xff, 0xff]),
+            Err(Error::InvalidBinary {
+                offset: 5,
+                message: "Length is larger than the rest of the input".to_string()
+            })
+        );
+        assert_eq!(
+            parse_msgpack(&[0x81, 0x90, 0x01]),
+            Err(Error::InvalidBinary {
+                offset: 1,
+                message: "Map keys must be strings or integers".to_string()
+            })
+        );
+    }
+}
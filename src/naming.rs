@@ -0,0 +1,806 @@
+//! The case conversions used to name the generated types and fields, for
+//! tools that want names consistent with the generated code. They don't depend
+//! on the locale, and give the same name for a key on every platform.
+//!
+//! ```
+//! use json2rust::naming::{field_name, to_pascal_case, to_snake_case, type_name};
+//!
+//! assert_eq!(to_snake_case("HTTPServer"), "http_server");
+//! assert_eq!(to_pascal_case("HTTPServer"), "HttpServer");
+//! assert_eq!(field_name("type"), "r#type");
+//! assert_eq!(type_name("3d_model"), "_3dModel");
+//! ```
+
+use crate::inflection::Inflector;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+// These can't be used as raw identifiers either
+const NON_RAW_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Splits a key into its words, breaking on separators and on lower to upper
+/// case transitions, so `fooBar_baz` and `FooBarBaz` both give three words.
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let chars: Vec<char> = name.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(current);
+                current = String::new();
+            }
+            continue;
+        }
+        if c.is_uppercase() && !current.is_empty() {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            // Break on `fooBar`, and on the last capital of an acronym in `HTTPServer`
+            if previous.is_lowercase()
+                || previous.is_numeric()
+                || (previous.is_uppercase() && next_is_lower)
+            {
+                words.push(current);
+                current = String::new();
+            }
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Turns a key into snake_case, splitting it into words on separators, on a
+/// lower case letter or digit followed by an upper case one, and before the
+/// last capital of an acronym followed by a lower case letter.
+pub fn to_snake_case(name: &str) -> String {
+    split_words(name)
+        .iter()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Turns a key into PascalCase, with the same words as `to_snake_case`, so
+/// acronyms only keep their first letter capitalized.
+pub fn to_pascal_case(name: &str) -> String {
+    split_words(name)
+        .iter()
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Words written in upper case in type names with `AcronymStyle::Uppercase`.
+pub const ACRONYMS: &[&str] = &[
+    "api", "ascii", "cpu", "css", "csv", "dns", "eta", "faq", "gpu", "guid", "html", "http",
+    "https", "id", "ip", "json", "jwt", "os", "pdf", "rpc", "sdk", "sku", "sql", "ssh", "tcp",
+    "tls", "ttl", "udp", "ui", "uri", "url", "utc", "uuid", "vat", "xml",
+];
+
+/// How acronyms such as `URL` and `ID` are written in type names.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum AcronymStyle {
+    /// Only the first letter is upper case, as in `UserId` and `HttpServer`,
+    /// which is the rust convention
+    Capitalized,
+    /// Every letter of the words in `ACRONYMS` is upper case, as in `UserID`
+    /// and `HTTPServer`, including their plurals, as in `UserIDs`
+    Uppercase,
+}
+
+/// Turns a key into PascalCase like `to_pascal_case`, writing the words in
+/// `ACRONYMS` the way `acronyms` asks for.
+pub fn to_pascal_case_with_acronyms(name: &str, acronyms: AcronymStyle) -> String {
+    if acronyms == AcronymStyle::Capitalized {
+        return to_pascal_case(name);
+    }
+    split_words(name)
+        .iter()
+        .map(|w| {
+            let lower = w.to_lowercase();
+            let singular = lower.strip_suffix('s').unwrap_or(&lower);
+            if ACRONYMS.contains(&lower.as_str()) {
+                lower.to_uppercase()
+            } else if ACRONYMS.contains(&singular) {
+                format!("{}s", singular.to_uppercase())
+            } else {
+                to_pascal_case(w)
+            }
+        })
+        .collect()
+}
+
+/// Whether `name` is a rust keyword, including the reserved ones.
+pub fn is_keyword(name: &str) -> bool {
+    KEYWORDS.contains(&name)
+}
+
+// Latin letters with diacritics and ligatures, and their ascii transliteration
+const TRANSLITERATIONS: &[(char, &str)] = &[
+    ('à', "a"),
+    ('á', "a"),
+    ('â', "a"),
+    ('ã', "a"),
+    ('ä', "a"),
+    ('å', "a"),
+    ('ā', "a"),
+    ('ă', "a"),
+    ('ą', "a"),
+    ('æ', "ae"),
+    ('ç', "c"),
+    ('ć', "c"),
+    ('č', "c"),
+    ('ď', "d"),
+    ('đ', "d"),
+    ('ð', "d"),
+    ('è', "e"),
+    ('é', "e"),
+    ('ê', "e"),
+    ('ë', "e"),
+    ('ē', "e"),
+    ('ė', "e"),
+    ('ę', "e"),
+    ('ě', "e"),
+    ('ğ', "g"),
+    ('ì', "i"),
+    ('í', "i"),
+    ('î', "i"),
+    ('ï', "i"),
+    ('ī', "i"),
+    ('į', "i"),
+    ('ı', "i"),
+    ('ķ', "k"),
+    ('ĺ', "l"),
+    ('ļ', "l"),
+    ('ľ', "l"),
+    ('ł', "l"),
+    ('ñ', "n"),
+    ('ń', "n"),
+    ('ņ', "n"),
+    ('ň', "n"),
+    ('ò', "o"),
+    ('ó', "o"),
+    ('ô', "o"),
+    ('õ', "o"),
+    ('ö', "o"),
+    ('ø', "o"),
+    ('ō', "o"),
+    ('ő', "o"),
+    ('œ', "oe"),
+    ('ŕ', "r"),
+    ('ř', "r"),
+    ('ś', "s"),
+    ('ş', "s"),
+    ('š', "s"),
+    ('ß', "ss"),
+    ('ţ', "t"),
+    ('ť', "t"),
+    ('þ', "th"),
+    ('ù', "u"),
+    ('ú', "u"),
+    ('û', "u"),
+    ('ü', "u"),
+    ('ū', "u"),
+    ('ů', "u"),
+    ('ű', "u"),
+    ('ų', "u"),
+    ('ý', "y"),
+    ('ÿ', "y"),
+    ('ź', "z"),
+    ('ż', "z"),
+    ('ž', "z"),
+];
+
+/// Replaces characters that can't be part of an ascii identifier. Latin letters
+/// with diacritics lose them, combining marks are dropped, and everything else
+/// becomes a word separator.
+pub fn transliterate(key: &str) -> String {
+    transliterate_with_mapping(key).0
+}
+
+/// Like `transliterate`, along with what each character that was replaced
+/// became, in order: its transliteration, nothing for a combining mark, or a
+/// space for a word separator. Keys with no replaced characters are used as
+/// they are.
+pub fn transliterate_with_mapping(key: &str) -> (String, Vec<(char, String)>) {
+    let mut result = String::with_capacity(key.len());
+    let mut mapping = Vec::new();
+    for c in key.chars() {
+        if c.is_ascii() {
+            result.push(c);
+            continue;
+        }
+        let start = result.len();
+        // Combining diacritical marks, as found in decomposed (NFD) text
+        if !('\u{300}'..='\u{36f}').contains(&c) {
+            let lower = c.to_lowercase().next().unwrap_or(c);
+            match TRANSLITERATIONS.iter().find(|(from, _)| *from == lower) {
+                Some((_, to)) if lower != c => {
+                    // Keep the case of uppercase letters, so word boundaries survive
+                    let mut chars = to.chars();
+                    result.extend(chars.next().map(|f| f.to_ascii_uppercase()));
+                    result.extend(chars);
+                }
+                Some((_, to)) => result.push_str(to),
+                None => result.push(' '),
+            }
+        }
+        mapping.push((c, result[start..].to_string()));
+    }
+    (result, mapping)
+}
+
+/// Turns a json key into a snake_case rust field identifier. Returns an empty
+/// string if the key has nothing that can be used in an identifier, such as
+/// keys written in a non-latin script.
+pub fn field_name(key: &str) -> String {
+    escape_field_name(to_snake_case(&transliterate(key)))
+}
+
+fn escape_field_name(name: String) -> String {
+    if name.is_empty() {
+        name
+    } else if NON_RAW_KEYWORDS.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else if is_keyword(&name) {
+        format!("r#{}", name)
+    } else if name.chars().next().is_some_and(|c| c.is_numeric()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// Replaces the words of `name` that are abbreviations with their expansion,
+/// for `(abbreviation, expansion)` pairs such as `("amt", "amount")`, ignoring
+/// case. The words are joined with `_`, to be turned into a field or type name.
+pub fn expand_abbreviations(name: &str, abbreviations: &[(String, String)]) -> String {
+    if abbreviations.is_empty() {
+        return name.to_string();
+    }
+    split_words(name)
+        .into_iter()
+        .map(|word| {
+            abbreviations
+                .iter()
+                .find(|(abbreviation, _)| abbreviation.to_lowercase() == word.to_lowercase())
+                .map_or(word, |(_, expansion)| expansion.clone())
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Field names for the keys of an object, without the words all of them start
+/// with, so `user_name` and `user_age` become `name` and `age`. Every name keeps
+/// at least one word, and a single key is kept whole, as are names that would
+/// be left starting with a digit.
+pub fn field_names_without_common_prefix(keys: &[&str]) -> Vec<String> {
+    let words: Vec<Vec<String>> = keys
+        .iter()
+        .map(|key| {
+            to_snake_case(&transliterate(key))
+                .split('_')
+                .filter(|word| !word.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .collect();
+    let shortest = words.iter().map(Vec::len).min().unwrap_or(0);
+    let mut prefix = 0;
+    if words.len() > 1 {
+        while prefix + 1 < shortest && words.iter().all(|w| w[prefix] == words[0][prefix]) {
+            prefix += 1;
+        }
+        while prefix > 0
+            && words
+                .iter()
+                .any(|w| w[prefix].starts_with(char::is_numeric))
+        {
+            prefix -= 1;
+        }
+    }
+    words
+        .iter()
+        .map(|w| escape_field_name(w[prefix..].join("_")))
+        .collect()
+}
+
+/// Makes `name` unique among `taken` by adding a numeric suffix, and adds it
+/// to `taken`.
+pub fn unique_name(name: String, taken: &mut Vec<String>) -> String {
+    let mut unique = name.clone();
+    let mut counter = 2;
+    while taken.contains(&unique) {
+        unique = format!("{}_{}", name, counter);
+        counter += 1;
+    }
+    taken.push(unique.clone());
+    unique
+}
+
+/// Makes the field names of an object unique, for keys such as `userId` and
+/// `user_id` that only differ by case. A key that is already its field name
+/// keeps it, and otherwise the first key does, while the others get a numeric
+/// suffix. Empty names, which are named by position, are left alone.
+pub fn unique_field_names(keys: &[&str], names: &[String]) -> Vec<String> {
+    let mut taken: Vec<String> = Vec::new();
+    let mut unique: Vec<Option<String>> = vec![None; names.len()];
+    for (i, name) in names.iter().enumerate() {
+        if name.trim_start_matches("r#") == keys[i] && !taken.contains(name) {
+            taken.push(name.clone());
+            unique[i] = Some(name.clone());
+        }
+    }
+    for (i, name) in names.iter().enumerate() {
+        if unique[i].is_some() || name.is_empty() {
+            continue;
+        }
+        if taken.contains(name) {
+            // A raw keyword with a suffix is no longer a keyword
+            let base = name.trim_start_matches("r#").to_string();
+            if base != *name {
+                taken.push(base.clone());
+            }
+            unique[i] = Some(unique_name(base, &mut taken));
+        } else {
+            taken.push(name.clone());
+            unique[i] = Some(name.clone());
+        }
+    }
+    unique.into_iter().map(Option::unwrap_or_default).collect()
+}
+
+/// Names the variants of an enum of the strings `values`, in the same order.
+/// Values starting with a digit get a `Value` prefix, since variants can't
+/// start with one, and values that only differ in casing or punctuation, such
+/// as `a-b` and `a_b`, are numbered after the first like type names are.
+pub fn variant_names(values: &[&str], acronyms: AcronymStyle) -> Vec<String> {
+    let mut taken: Vec<String> = Vec::new();
+    values
+        .iter()
+        .map(|value| {
+            let name = match to_pascal_case_with_acronyms(&transliterate(value), acronyms) {
+                name if name.is_empty() => "Empty".to_string(),
+                name if name.starts_with(|c: char| c.is_numeric()) => format!("Value{}", name),
+                name if is_keyword(&name) => format!("{}_", name),
+                name => name,
+            };
+            let mut unique = name.clone();
+            let mut counter = 2;
+            while taken.contains(&unique) {
+                unique = format!("{}{}", name, counter);
+                counter += 1;
+            }
+            taken.push(unique.clone());
+            unique
+        })
+        .collect()
+}
+
+/// Turns a json key into a PascalCase rust type identifier, falling back to
+/// `Unnamed` if the key has nothing that can be used in an identifier.
+pub fn type_name(key: &str) -> String {
+    type_name_with_acronyms(key, AcronymStyle::Capitalized)
+}
+
+/// Like `type_name`, writing the words in `ACRONYMS` the way `acronyms` asks
+/// for.
+pub fn type_name_with_acronyms(key: &str, acronyms: AcronymStyle) -> String {
+    let name = to_pascal_case_with_acronyms(&transliterate(key), acronyms);
+    if name.is_empty() {
+        "Unnamed".to_string()
+    } else if is_keyword(&name) {
+        format!("{}_", name)
+    } else if name.chars().next().is_some_and(|c| c.is_numeric()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// Everything known about a type when it needs a name.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct NameContext<'a> {
+    /// The keys leading from the root to the value, not including array elements
+    pub path: &'a [String],
+    /// The key of the field holding the value, or the root name for the root type
+    pub field_name: &'a str,
+    /// The name of the type containing the field, if any
+    pub parent: Option<&'a str>,
+    /// Whether the value is an element of an array
+    pub is_array_element: bool,
+}
+
+/// Derives the names of the generated types. The returned name is converted to
+/// PascalCase, so strategies can return words in any casing.
+pub trait NamingStrategy: Send + Sync {
+    fn type_name(&self, context: &NameContext) -> String;
+}
+
+/// Names types after the field holding them, e.g. `Address` for `user.address`.
+pub struct FieldNameStrategy;
+
+impl NamingStrategy for FieldNameStrategy {
+    fn type_name(&self, context: &NameContext) -> String {
+        context.field_name.to_string()
+    }
+}
+
+/// Names types after the full path of keys leading to them, e.g. `UserAddress`
+/// for `user.address`.
+pub struct FullPathStrategy;
+
+impl NamingStrategy for FullPathStrategy {
+    fn type_name(&self, context: &NameContext) -> String {
+        if context.path.is_empty() {
+            context.field_name.to_string()
+        } else {
+            context.path.join(" ")
+        }
+    }
+}
+
+/// Names types after their parent type and the field holding them, e.g.
+/// `RootUserAddress` for `user.address`.
+pub struct ParentPrefixedStrategy;
+
+impl NamingStrategy for ParentPrefixedStrategy {
+    fn type_name(&self, context: &NameContext) -> String {
+        match context.parent {
+            Some(parent) => format!("{} {}", parent, context.field_name),
+            None => context.field_name.to_string(),
+        }
+    }
+}
+
+/// Names types after the field holding them, singularizing the name of array
+/// fields, e.g. `User` for the elements of `users`. This is the default strategy.
+#[derive(Debug, Clone, Default)]
+pub struct SingularizedStrategy {
+    inflector: Inflector,
+}
+
+impl SingularizedStrategy {
+    pub fn new() -> SingularizedStrategy {
+        SingularizedStrategy::default()
+    }
+
+    /// Uses an inflector with a dictionary of domain specific plurals.
+    pub fn with_inflector(inflector: Inflector) -> SingularizedStrategy {
+        SingularizedStrategy { inflector }
+    }
+}
+
+impl NamingStrategy for SingularizedStrategy {
+    fn type_name(&self, context: &NameContext) -> String {
+        if !context.is_array_element {
+            return context.field_name.to_string();
+        }
+        // Only the last word is plural in names like `line_items`
+        let mut words = split_words(context.field_name);
+        if let Some(last) = words.pop() {
+            words.push(self.inflector.singularize(&last));
+        }
+        words.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_camel_case_to_snake_case() {
+        assert_eq!(to_snake_case("fooBarBaz"), "foo_bar_baz");
+        assert_eq!(to_snake_case("HTTPServer"), "http_server");
+        assert_eq!(to_snake_case("already_snake"), "already_snake");
+        assert_eq!(to_snake_case("kebab-case key"), "kebab_case_key");
+    }
+
+    #[test]
+    fn names_enum_variants() {
+        assert_eq!(
+            variant_names(
+                &["IN_PROGRESS", "a-b", "a_b", "A B", "404", "", "Self", "ÉTÉ"],
+                AcronymStyle::Capitalized
+            ),
+            vec![
+                "InProgress",
+                "AB",
+                "AB2",
+                "AB3",
+                "Value404",
+                "Empty",
+                "Self_",
+                "Ete"
+            ]
+        );
+        assert_eq!(
+            variant_names(&["json", "api_v2"], AcronymStyle::Uppercase),
+            vec!["JSON", "APIV2"]
+        );
+    }
+
+    #[test]
+    fn converts_to_pascal_case() {
+        assert_eq!(to_pascal_case("foo_bar"), "FooBar");
+        assert_eq!(to_pascal_case("fooBar"), "FooBar");
+        assert_eq!(to_pascal_case("user-ID"), "UserId");
+    }
+
+    #[test]
+    fn splits_words_consistently() {
+        let cases = [
+            ("", "", ""),
+            ("a", "a", "A"),
+            ("userName", "user_name", "UserName"),
+            ("UserName", "user_name", "UserName"),
+            ("user_name", "user_name", "UserName"),
+            ("USER_NAME", "user_name", "UserName"),
+            ("user-name", "user_name", "UserName"),
+            ("user.name", "user_name", "UserName"),
+            ("  user  name  ", "user_name", "UserName"),
+            ("__user__", "user", "User"),
+            ("ID", "id", "Id"),
+            ("userID", "user_id", "UserId"),
+            ("HTTPServer", "http_server", "HttpServer"),
+            (
+                "getHTTPResponseCode",
+                "get_http_response_code",
+                "GetHttpResponseCode",
+            ),
+            ("XMLHttpRequest", "xml_http_request", "XmlHttpRequest"),
+            ("v2Api", "v2_api", "V2Api"),
+            ("address2", "address2", "Address2"),
+            ("address_2", "address_2", "Address2"),
+            ("2fa", "2fa", "2fa"),
+            ("sha256Hash", "sha256_hash", "Sha256Hash"),
+            ("Ünïcode", "ünïcode", "Ünïcode"),
+        ];
+        for (key, snake, pascal) in cases {
+            assert_eq!(to_snake_case(key), snake, "snake_case of {:?}", key);
+            assert_eq!(to_pascal_case(key), pascal, "PascalCase of {:?}", key);
+        }
+    }
+
+    #[test]
+    fn writes_acronyms_in_upper_case() {
+        let cases = [
+            ("userId", "UserId", "UserID"),
+            ("user_id", "UserId", "UserID"),
+            ("HTTPServer", "HttpServer", "HTTPServer"),
+            ("api_url", "ApiUrl", "APIURL"),
+            ("userIds", "UserIds", "UserIDs"),
+            ("identity", "Identity", "Identity"),
+            ("os", "Os", "OS"),
+            ("USER_NAME", "UserName", "UserName"),
+        ];
+        for (key, capitalized, uppercase) in cases {
+            assert_eq!(
+                type_name_with_acronyms(key, AcronymStyle::Capitalized),
+                capitalized
+            );
+            assert_eq!(
+                type_name_with_acronyms(key, AcronymStyle::Uppercase),
+                uppercase
+            );
+        }
+        assert_eq!(type_name_with_acronyms("id", AcronymStyle::Uppercase), "ID");
+        assert_eq!(
+            type_name_with_acronyms("self", AcronymStyle::Uppercase),
+            "Self_"
+        );
+    }
+
+    #[test]
+    fn escapes_keywords_in_field_names() {
+        assert_eq!(field_name("type"), "r#type");
+        assert_eq!(field_name("self"), "self_");
+        assert_eq!(field_name("2fa"), "_2fa");
+    }
+
+    #[test]
+    fn expands_abbreviations() {
+        let abbreviations = vec![
+            ("amt".to_string(), "amount".to_string()),
+            ("cfg".to_string(), "config".to_string()),
+            ("qty".to_string(), "total quantity".to_string()),
+        ];
+
+        assert_eq!(
+            expand_abbreviations("total_amt", &abbreviations),
+            "total_amount"
+        );
+        assert_eq!(
+            expand_abbreviations("UserCfg", &abbreviations),
+            "User_config"
+        );
+        assert_eq!(
+            field_name(&expand_abbreviations("itemQty", &abbreviations)),
+            "item_total_quantity"
+        );
+        assert_eq!(expand_abbreviations("amtx", &abbreviations), "amtx");
+        assert_eq!(expand_abbreviations("a-b", &[]), "a-b");
+    }
+
+    #[test]
+    fn strips_common_prefixes() {
+        assert_eq!(
+            field_names_without_common_prefix(&["user_name", "userAge", "user_type"]),
+            vec!["name", "age", "r#type"]
+        );
+        assert_eq!(
+            field_names_without_common_prefix(&["a_b_c", "a_b"]),
+            vec!["b_c", "b"]
+        );
+        assert_eq!(
+            field_names_without_common_prefix(&["user_name", "name"]),
+            vec!["user_name", "name"]
+        );
+        assert_eq!(
+            field_names_without_common_prefix(&["user_name"]),
+            vec!["user_name"]
+        );
+        assert_eq!(
+            field_names_without_common_prefix(&["user_1", "user_2", ""]),
+            vec!["user_1", "user_2", ""]
+        );
+        assert_eq!(
+            field_names_without_common_prefix(&["user_1", "user_2"]),
+            vec!["user_1", "user_2"]
+        );
+    }
+
+    #[test]
+    fn transliterates_non_ascii_keys() {
+        assert_eq!(field_name("größe"), "grosse");
+        assert_eq!(field_name("Æble_Øl"), "aeble_ol");
+        assert_eq!(field_name("straßeName"), "strasse_name");
+        assert_eq!(type_name("çafé"), "Cafe");
+    }
+
+    #[test]
+    fn reports_what_characters_became() {
+        assert_eq!(transliterate_with_mapping("id"), ("id".to_string(), vec![]));
+        assert_eq!(
+            transliterate_with_mapping("Øre\u{301}名"),
+            (
+                "Ore ".to_string(),
+                vec![
+                    ('Ø', "O".to_string()),
+                    ('\u{301}', "".to_string()),
+                    ('名', " ".to_string())
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn handles_composed_and_decomposed_keys_alike() {
+        assert_eq!(field_name("caf\u{e9}"), "cafe");
+        assert_eq!(field_name("cafe\u{301}"), "cafe");
+        assert_eq!(field_name("Cafe\u{301}Au"), "cafe_au");
+    }
+
+    #[test]
+    fn drops_characters_without_transliteration() {
+        assert_eq!(field_name("名前"), "");
+        assert_eq!(field_name("🎉"), "");
+        assert_eq!(field_name("🎉party"), "party");
+        assert_eq!(field_name("名前_id"), "id");
+        assert_eq!(type_name("名前"), "Unnamed");
+        assert_eq!(type_name("🔥hot🔥deals"), "HotDeals");
+    }
+
+    #[test]
+    fn escapes_keywords_in_type_names() {
+        assert_eq!(type_name("self"), "Self_");
+        assert_eq!(type_name("3d_model"), "_3dModel");
+    }
+
+    #[test]
+    fn makes_colliding_field_names_unique() {
+        let keys = ["userId", "user_id", "UserID", "type", "Type"];
+        let names: Vec<String> = keys.iter().map(|key| field_name(key)).collect();
+
+        assert_eq!(
+            unique_field_names(&keys, &names),
+            vec!["user_id_2", "user_id", "user_id_3", "r#type", "type_2"]
+        );
+    }
+
+    fn context<'a>(
+        path: &'a [String],
+        parent: Option<&'a str>,
+        is_array_element: bool,
+    ) -> NameContext<'a> {
+        NameContext {
+            path,
+            field_name: path.last().map(|s| s.as_str()).unwrap_or("Root"),
+            parent,
+            is_array_element,
+        }
+    }
+
+    #[test]
+    fn built_in_strategies_name_nested_types() {
+        let path = vec!["order".to_string(), "line_items".to_string()];
+        let ctx = context(&path, Some("RootOrder"), true);
+
+        assert_eq!(type_name(&FieldNameStrategy.type_name(&ctx)), "LineItems");
+        assert_eq!(
+            type_name(&FullPathStrategy.type_name(&ctx)),
+            "OrderLineItems"
+        );
+        assert_eq!(
+            type_name(&ParentPrefixedStrategy.type_name(&ctx)),
+            "RootOrderLineItems"
+        );
+        assert_eq!(
+            type_name(&SingularizedStrategy::new().type_name(&ctx)),
+            "LineItem"
+        );
+    }
+
+    #[test]
+    fn built_in_strategies_keep_the_root_name() {
+        let ctx = context(&[], None, false);
+
+        assert_eq!(FieldNameStrategy.type_name(&ctx), "Root");
+        assert_eq!(FullPathStrategy.type_name(&ctx), "Root");
+        assert_eq!(ParentPrefixedStrategy.type_name(&ctx), "Root");
+        assert_eq!(SingularizedStrategy::new().type_name(&ctx), "Root");
+    }
+
+    #[test]
+    fn singularizes_the_last_word_of_array_fields() {
+        let path = vec!["people".to_string()];
+        let strategy = SingularizedStrategy::new();
+
+        let mut ctx = context(&path, None, true);
+        assert_eq!(type_name(&strategy.type_name(&ctx)), "Person");
+
+        ctx.field_name = "recentOrderItems";
+        assert_eq!(type_name(&strategy.type_name(&ctx)), "RecentOrderItem");
+
+        ctx.is_array_element = false;
+        assert_eq!(type_name(&strategy.type_name(&ctx)), "RecentOrderItems");
+    }
+
+    #[test]
+    fn singularizes_with_the_user_dictionary() {
+        let mut inflector = Inflector::new();
+        inflector.add_word("pokemon", "pokemon");
+        let strategy = SingularizedStrategy::with_inflector(inflector);
+        let path = vec!["caught_pokemon".to_string()];
+
+        assert_eq!(
+            type_name(&strategy.type_name(&context(&path, None, true))),
+            "CaughtPokemon"
+        );
+    }
+}
@@ -0,0 +1,1447 @@
+use crate::detectors::{default_detectors, Detector};
+use crate::key_filter::{ignored_path, KeyFilter};
+use crate::naming::{AcronymStyle, NamingStrategy, SingularizedStrategy};
+use crate::output_version::OutputVersion;
+use crate::passes::{default_passes, StructurePass};
+use crate::progress::Progress;
+use crate::shared::Error;
+use crate::tokenizer::{Leniency, Limits};
+use crate::type_mapping::TypeMapping;
+use crate::RustType;
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+/// How fields that are sometimes a string and sometimes a number, such as
+/// `"42"` and `42`, are generated.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum MixedNumberPolicy {
+    /// An untagged enum with a variant for each
+    Union,
+    /// A `String`, deserialized with a generated helper that also accepts
+    /// numbers
+    String,
+    /// An `i64` or `f64`, deserialized with a generated helper that also
+    /// accepts numbers written as strings
+    Number,
+    /// Fails the conversion, naming the first such field
+    Error,
+}
+
+/// How number fields whose every value is a whole number, with at least one
+/// written as a float such as `3.0`, are generated.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum WholeFloatPolicy {
+    /// An `f64`, as the json says
+    Float,
+    /// An `f64`, with a warning that the field may hold integers
+    Warn,
+    /// An `i64`, deserialized with a generated helper that also accepts whole
+    /// floats
+    Int,
+}
+
+/// How numbers with a fraction are generated, for teams that can't use `f64`,
+/// such as for amounts of money.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum FloatPolicy {
+    /// An `f64`
+    Float,
+    /// A `rust_decimal::Decimal`, which keeps the exact value
+    Decimal,
+    /// Fails the conversion, listing the paths of every number with a fraction
+    Error,
+}
+
+/// How integer fields that only took a few values, such as status codes, are
+/// generated with `ConvertOptions::set_int_enums`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum IntEnumStyle {
+    /// A `#[repr(i64)]` enum with a variant for each value, deriving
+    /// `serde_repr::Serialize_repr` and `Deserialize_repr`, which rejects other
+    /// values
+    Repr,
+    /// A `#[serde(transparent)]` newtype over `i64` with a constant for each
+    /// value, which still accepts other values
+    Newtype,
+}
+
+/// How the types of a sample that is an array of objects are named, with
+/// `ConvertOptions::set_array_root`.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ArrayRoot {
+    /// The elements are a struct with the root name, such as `Root`
+    Struct,
+    /// The elements are named after the root name as an element, such as
+    /// `User` for `Users`, along with `pub type Users = Vec<User>;`. Arrays of
+    /// other values get the alias too, such as `pub type Names = Vec<String>;`
+    Alias,
+    /// The elements are named as with `Alias`, without the alias
+    Element,
+}
+
+/// How the variants of a union of objects are told apart, hinted for a path
+/// with `ConvertOptions::tag_union`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum UnionTagging {
+    /// `{"type": "circle", "radius": 1}`, with the name of the variant in the
+    /// field `tag`, next to the fields of the variant
+    Internal { tag: String },
+    /// `{"type": "circle", "payload": {"radius": 1}}`, with the name of the
+    /// variant in the field `tag` and its value in the field `content`
+    Adjacent { tag: String, content: String },
+    /// `{"circle": {"radius": 1}}`, an object with a single field named after
+    /// the variant
+    External,
+}
+
+/// Options controlling how a sample is converted.
+///
+/// Options are `Send + Sync`, so the detectors, passes, naming strategy and
+/// callbacks they hold must be too. See `Converter` to share them between
+/// threads.
+pub struct ConvertOptions {
+    // Detectors tried, in order, on every string field. The first one to
+    // return a type wins. User supplied detectors come before the built-ins.
+    pub(crate) detectors: Vec<Box<dyn Detector>>,
+    user_detector_count: usize,
+    // Applied to the inferred structure, before code is generated
+    pub(crate) type_mappings: Vec<TypeMapping>,
+    pub(crate) chaos_threshold: Option<usize>,
+    pub(crate) forced_value_paths: Vec<String>,
+    pub(crate) tagged_unions: Vec<(String, UnionTagging)>,
+    pub(crate) key_filter: KeyFilter,
+    // Run in order on the inferred structure
+    pub(crate) passes: Vec<Box<dyn StructurePass>>,
+    pub(crate) naming_strategy: Box<dyn NamingStrategy>,
+    pub(crate) acronyms: AcronymStyle,
+    pub(crate) extended_json: bool,
+    pub(crate) lenient_numbers: bool,
+    pub(crate) relaxed_json: bool,
+    pub(crate) non_finite_numbers: bool,
+    pub(crate) exponent_integers: bool,
+    pub(crate) locale_numbers: bool,
+    pub(crate) mixed_numbers: MixedNumberPolicy,
+    pub(crate) whole_floats: WholeFloatPolicy,
+    pub(crate) floats: FloatPolicy,
+    pub(crate) empty_strings_as_none: bool,
+    pub(crate) json_strings: bool,
+    pub(crate) field_descriptions: bool,
+    pub(crate) numeric_bools: bool,
+    pub(crate) int_bool_threshold: Option<usize>,
+    pub(crate) helpers_module: Option<String>,
+    pub(crate) type_attributes: Vec<String>,
+    // Json pointers of fields, with the attributes added to them
+    pub(crate) field_attributes: Vec<(String, String)>,
+    pub(crate) header: Option<String>,
+    pub(crate) footer: Option<String>,
+    pub(crate) user_code_region: bool,
+    pub(crate) banner: bool,
+    pub(crate) banner_timestamp: bool,
+    pub(crate) server_types: bool,
+    pub(crate) openapi_schemas: bool,
+    pub(crate) validation: bool,
+    pub(crate) json_schemas: bool,
+    pub(crate) output_version: OutputVersion,
+    pub(crate) sample_locations: bool,
+    pub(crate) schema_fingerprint: bool,
+    pub(crate) shape_check: bool,
+    pub(crate) redact_samples: bool,
+    pub(crate) detect_secrets: bool,
+    pub(crate) dedupe_samples: bool,
+    pub(crate) all_optional: bool,
+    progress_callback: Option<Box<dyn Fn(Progress) + Send + Sync>>,
+    cancellation: Option<Box<dyn Fn() -> bool + Send + Sync>>,
+    pub(crate) wrapper_keys: Vec<String>,
+    pub(crate) strip_common_prefix: bool,
+    pub(crate) abbreviations: Vec<(String, String)>,
+    pub(crate) constant_fields: bool,
+    pub(crate) int_enums: Option<IntEnumStyle>,
+    pub(crate) set_fields: Vec<String>,
+    pub(crate) hash_sets: bool,
+    pub(crate) fixed_size_arrays: Option<usize>,
+    pub(crate) distinguish_null: bool,
+    pub(crate) patch_types: bool,
+    pub(crate) server_generated: Vec<String>,
+    pub(crate) conversions: bool,
+    pub(crate) display_impls: bool,
+    pub(crate) ordering_derives: bool,
+    pub(crate) forward_compatible: bool,
+    pub(crate) type_aliases: Vec<String>,
+    pub(crate) root_alias: bool,
+    pub(crate) array_root: ArrayRoot,
+    pub(crate) columnar: bool,
+    pub(crate) sqlx_derives: bool,
+    pub(crate) diesel_table: Option<String>,
+    pub(crate) reorder_fields: bool,
+    pub(crate) box_threshold: Option<usize>,
+    pub(crate) comment_unknown_fields: bool,
+    pub(crate) indent_width: usize,
+    pub(crate) hard_tabs: bool,
+    pub(crate) trailing_commas: bool,
+    pub(crate) inline_field_attributes: bool,
+    pub(crate) split_derives: bool,
+    pub(crate) max_input_bytes: Option<usize>,
+    pub(crate) max_depth: Option<usize>,
+}
+
+/// The default maximum number of different shapes a value may have before it is
+/// considered too chaotic to generate types for.
+pub const DEFAULT_CHAOS_THRESHOLD: usize = 10;
+
+impl ConvertOptions {
+    /// Adds a detector written as a closure, taking the field name and every
+    /// string value observed for it.
+    ///
+    /// ```
+    /// use json2rust::{ConvertOptions, RustType};
+    ///
+    /// let mut options = ConvertOptions::default();
+    /// options.add_detector(|field_name, _values| {
+    ///     if field_name.ends_with("_id") {
+    ///         Some(RustType::new("Id"))
+    ///     } else {
+    ///         None
+    ///     }
+    /// });
+    /// ```
+    pub fn add_detector<F>(&mut self, detector: F) -> &mut ConvertOptions
+    where
+        F: Fn(&str, &[&str]) -> Option<RustType> + Send + Sync + 'static,
+    {
+        self.register_detector(detector)
+    }
+
+    /// Adds a detector. Detectors added by the user are tried before the
+    /// built-in ones, in the order they were added.
+    pub fn register_detector<D: Detector + 'static>(&mut self, detector: D) -> &mut ConvertOptions {
+        self.detectors
+            .insert(self.user_detector_count, Box::new(detector));
+        self.user_detector_count += 1;
+        self
+    }
+
+    /// Removes all detectors, including the built-in ones.
+    pub fn clear_detectors(&mut self) -> &mut ConvertOptions {
+        self.detectors.clear();
+        self.user_detector_count = 0;
+        self
+    }
+
+    /// Maps every field whose name matches the glob `pattern` to `rust_type`,
+    /// e.g. `"*_at"` to `chrono::DateTime<Utc>`. Mappings are tried in the
+    /// order they were added.
+    pub fn add_type_mapping<S: Into<String>>(
+        &mut self,
+        pattern: S,
+        rust_type: RustType,
+    ) -> &mut ConvertOptions {
+        self.type_mappings
+            .push(TypeMapping::new(pattern, rust_type));
+        self
+    }
+
+    /// Sets the maximum number of different shapes a value may have, before it is
+    /// generated as `serde_json::Value` instead. Objects with different sets of
+    /// keys count as different shapes. `None` disables the check.
+    pub fn set_chaos_threshold(&mut self, threshold: Option<usize>) -> &mut ConvertOptions {
+        self.chaos_threshold = threshold;
+        self
+    }
+
+    /// Always generates the value at `path` as `serde_json::Value`. The path is a
+    /// json pointer, using `*` for array elements, e.g. `/items/*/metadata`.
+    pub fn force_json_value<S: Into<String>>(&mut self, path: S) -> &mut ConvertOptions {
+        self.forced_value_paths.push(path.into());
+        self
+    }
+
+    /// Reads the objects at `path` as a tagged union, encoded as described by
+    /// `tagging`, generating an enum with a variant for every name seen
+    /// instead of a struct with the fields of all of them. The path is a json
+    /// pointer, using `*` for array elements, e.g. `/events/*`. The hint is
+    /// ignored, with a warning, if a value at the path isn't encoded that way.
+    pub fn tag_union<S: Into<String>>(
+        &mut self,
+        path: S,
+        tagging: UnionTagging,
+    ) -> &mut ConvertOptions {
+        self.tagged_unions.push((path.into(), tagging));
+        self
+    }
+
+    /// How the union at `path` is tagged, with `tag_union`.
+    pub(crate) fn union_tagging(&self, path: &str) -> Option<&UnionTagging> {
+        self.tagged_unions
+            .iter()
+            .rev()
+            .find(|(p, _)| p == path)
+            .map(|(_, tagging)| tagging)
+    }
+
+    /// Leaves the field at `path` out of the generated types. The path is a
+    /// json pointer, using `*` for array elements, e.g. `/items/*/debug`, or a
+    /// JSONPath such as `$.items[*].debug`.
+    pub fn ignore_path<S: AsRef<str>>(&mut self, path: S) -> &mut ConvertOptions {
+        self.key_filter.paths.push(ignored_path(path.as_ref()));
+        self
+    }
+
+    /// Leaves every field whose key matches the glob `pattern` out of the
+    /// generated types, at any depth, e.g. `"_internal*"`.
+    pub fn ignore_key<S: Into<String>>(&mut self, pattern: S) -> &mut ConvertOptions {
+        self.key_filter.keys.push(pattern.into());
+        self
+    }
+
+    /// Adds a pass run on the inferred structure, after the ones already
+    /// added.
+    pub fn add_pass<P: StructurePass + 'static>(&mut self, pass: P) -> &mut ConvertOptions {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Adds a pass run right before the pass named `before`, or after all of
+    /// them if there is no such pass.
+    pub fn insert_pass_before<P: StructurePass + 'static>(
+        &mut self,
+        before: &str,
+        pass: P,
+    ) -> &mut ConvertOptions {
+        let index = self
+            .passes
+            .iter()
+            .position(|p| p.name() == before)
+            .unwrap_or(self.passes.len());
+        self.passes.insert(index, Box::new(pass));
+        self
+    }
+
+    /// Removes the first pass named `name`, built-in or not, returning it so
+    /// it can be added back elsewhere.
+    pub fn remove_pass(&mut self, name: &str) -> Option<Box<dyn StructurePass>> {
+        let index = self.passes.iter().position(|p| p.name() == name)?;
+        Some(self.passes.remove(index))
+    }
+
+    /// The names of the passes, in the order they run. The built-in ones are
+    /// `type_mappings`, `ignore`, `descriptions` and `dedupe`.
+    pub fn pass_names(&self) -> Vec<&str> {
+        self.passes.iter().map(|p| p.name()).collect()
+    }
+
+    /// Adds an attribute, such as `#[cfg_attr(feature = "openapi",
+    /// derive(ToSchema))]`, to every generated struct and enum.
+    pub fn add_type_attribute<S: Into<String>>(&mut self, attribute: S) -> &mut ConvertOptions {
+        self.type_attributes.push(attribute.into());
+        self
+    }
+
+    /// Adds an attribute, such as `#[deprecated]`, to the field at `path`. The
+    /// path is a json pointer, using `*` for array elements, e.g.
+    /// `/items/*/legacy_id`.
+    pub fn add_field_attribute<P: Into<String>, S: Into<String>>(
+        &mut self,
+        path: P,
+        attribute: S,
+    ) -> &mut ConvertOptions {
+        self.field_attributes.push((path.into(), attribute.into()));
+        self
+    }
+
+    /// Sets how generated types are named. Defaults to `SingularizedStrategy`.
+    pub fn set_naming_strategy<N: NamingStrategy + 'static>(
+        &mut self,
+        strategy: N,
+    ) -> &mut ConvertOptions {
+        self.naming_strategy = Box::new(strategy);
+        self
+    }
+
+    /// Sets how acronyms such as `URL` and `ID` are written in the names of
+    /// generated types and enum variants. Defaults to
+    /// `AcronymStyle::Capitalized`. Field names are snake_case either way, and
+    /// the json keys are kept with `#[serde(rename)]`.
+    pub fn set_acronym_style(&mut self, acronyms: AcronymStyle) -> &mut ConvertOptions {
+        self.acronyms = acronyms;
+        self
+    }
+
+    /// Leaves out the words that every key of an object starts with from its
+    /// field names, so `user_name` and `user_age` become `name` and `age`. The
+    /// keys are kept with `#[serde(rename)]`.
+    pub fn set_strip_common_prefix(&mut self, strip_common_prefix: bool) -> &mut ConvertOptions {
+        self.strip_common_prefix = strip_common_prefix;
+        self
+    }
+
+    /// Expands the word `abbreviation` to `expansion` wherever it appears in
+    /// the names of fields and types, ignoring case, e.g. `"amt"` to
+    /// `"amount"`. The root type keeps the name it is given.
+    pub fn add_abbreviation<A: Into<String>, E: Into<String>>(
+        &mut self,
+        abbreviation: A,
+        expansion: E,
+    ) -> &mut ConvertOptions {
+        self.abbreviations
+            .push((abbreviation.into(), expansion.into()));
+        self
+    }
+
+    /// Reads samples as MongoDB extended json, so wrappers such as
+    /// `{"$oid": "..."}` and `{"$date": ...}` become `bson` types, and
+    /// `{"$numberLong": "..."}` becomes an integer, instead of nested structs.
+    pub fn set_extended_json(&mut self, extended_json: bool) -> &mut ConvertOptions {
+        self.extended_json = extended_json;
+        self
+    }
+
+    /// Accepts numbers without digits on one side of the decimal separator,
+    /// such as `.5` and `5.`, which json5 and some hand written samples use.
+    /// Otherwise they are rejected as invalid json.
+    pub fn set_lenient_numbers(&mut self, lenient_numbers: bool) -> &mut ConvertOptions {
+        self.lenient_numbers = lenient_numbers;
+        self
+    }
+
+    /// Accepts samples pasted from javascript or python, with single quoted
+    /// strings, unquoted keys, trailing commas, `True`, `False`, `None` and
+    /// `undefined`.
+    pub fn set_relaxed_json(&mut self, relaxed_json: bool) -> &mut ConvertOptions {
+        self.relaxed_json = relaxed_json;
+        self
+    }
+
+    /// Reads `NaN`, `Infinity` and `-Infinity`, which some python and
+    /// javascript serializers write, as floats instead of rejecting the sample.
+    /// Fields with them are reported in the warnings, as serde_json can't read
+    /// them.
+    pub fn set_non_finite_numbers(&mut self, non_finite_numbers: bool) -> &mut ConvertOptions {
+        self.non_finite_numbers = non_finite_numbers;
+        self
+    }
+
+    /// Reads numbers written with an exponent as integers when they are exactly
+    /// one, such as `1e3` but not `1.5e-1`, instead of always as floats. Fields
+    /// typed as integers because of this are reported in the warnings, as
+    /// serde_json only reads such numbers as floats.
+    pub fn set_exponent_integers(&mut self, exponent_integers: bool) -> &mut ConvertOptions {
+        self.exponent_integers = exponent_integers;
+        self
+    }
+
+    /// Types string fields whose every value is a number formatted for a
+    /// locale, such as `"1.234,56"` from a spreadsheet export, as `f64`. Such
+    /// fields are reported in the warnings, as they need a custom deserializer.
+    pub fn set_locale_numbers(&mut self, locale_numbers: bool) -> &mut ConvertOptions {
+        self.locale_numbers = locale_numbers;
+        self
+    }
+
+    /// Sets how fields that are sometimes a string and sometimes a number are
+    /// generated. Defaults to `MixedNumberPolicy::Union`. Only struct fields
+    /// are coerced with the `String` and `Number` policies, array elements and
+    /// map values stay enums.
+    pub fn set_mixed_number_policy(&mut self, policy: MixedNumberPolicy) -> &mut ConvertOptions {
+        self.mixed_numbers = policy;
+        self
+    }
+
+    /// How to generate number fields that only had whole numbers, some of them
+    /// written as floats, such as `3.0` and `7`. Defaults to
+    /// `WholeFloatPolicy::Float`. Only struct fields become integers with
+    /// `WholeFloatPolicy::Int`, array elements and map values get the warning
+    /// of `WholeFloatPolicy::Warn` instead.
+    pub fn set_whole_float_policy(&mut self, policy: WholeFloatPolicy) -> &mut ConvertOptions {
+        self.whole_floats = policy;
+        self
+    }
+
+    /// Sets how numbers with a fraction, including numbers formatted for a
+    /// locale with `set_locale_numbers`, are generated. Defaults to
+    /// `FloatPolicy::Float`. Whole numbers written as floats, such as `3.0`,
+    /// count as numbers with a fraction with the other policies.
+    pub fn set_float_policy(&mut self, policy: FloatPolicy) -> &mut ConvertOptions {
+        self.floats = policy;
+        self
+    }
+
+    /// Treats empty strings in string and number fields like null, as many APIs
+    /// send `""` for a missing value. Such fields become optional, and are
+    /// deserialized with the generated `empty_string_as_none` helper, which
+    /// reads `""` as `None` and parses the other values with `FromStr`. Fields
+    /// whose other strings a detector recognizes keep their empty strings, as
+    /// the detected type may not implement `FromStr`.
+    pub fn set_empty_strings_as_none(
+        &mut self,
+        empty_strings_as_none: bool,
+    ) -> &mut ConvertOptions {
+        self.empty_strings_as_none = empty_strings_as_none;
+        self
+    }
+
+    /// Infers the structure of json sent as strings, such as
+    /// `"payload": "{\"a\": 1}"` in queue messages, when every string seen for
+    /// a value is a json object or array. The value gets a nested type,
+    /// read and written with the generated `json_string` helper module, which
+    /// decodes the string and encodes it back. The helper is only added when generating with
+    /// `conversion_to_rust`.
+    pub fn set_json_strings(&mut self, json_strings: bool) -> &mut ConvertOptions {
+        self.json_strings = json_strings;
+        self
+    }
+
+    /// Lifts descriptions that samples carry about themselves into the doc
+    /// comments of the generated fields and types, removing the keys they were
+    /// in: a `description` string among the fields of an object describes the
+    /// object, and a `<key>_description` or `<key>Description` string the
+    /// field `key` next to it. Only known when generating with
+    /// `conversion_to_rust`.
+    pub fn set_field_descriptions(&mut self, field_descriptions: bool) -> &mut ConvertOptions {
+        self.field_descriptions = field_descriptions;
+        self
+    }
+
+    /// Redacts the example values kept in the stats, and so in reports, so
+    /// samples holding personal data can be shared: strings are replaced by a
+    /// hash of themselves and numbers by zero. Lengths, ranges and formats are
+    /// kept.
+    pub fn set_redact_samples(&mut self, redact_samples: bool) -> &mut ConvertOptions {
+        self.redact_samples = redact_samples;
+        self
+    }
+
+    /// Warns about strings that look like credentials, such as the values of
+    /// fields named like `api_key`, bearer tokens and long random tokens, and
+    /// keeps no examples of them in the stats, so they don't end up in reports
+    /// or in the generated code. On by default, and only with the `std`
+    /// feature.
+    pub fn set_detect_secrets(&mut self, detect_secrets: bool) -> &mut ConvertOptions {
+        self.detect_secrets = detect_secrets;
+        self
+    }
+
+    /// Makes every field of every object optional, as if it was missing from
+    /// some of the samples, for APIs known to leave out fields arbitrarily when
+    /// there is only a single sample to infer from.
+    pub fn set_all_optional(&mut self, all_optional: bool) -> &mut ConvertOptions {
+        self.all_optional = all_optional;
+        self
+    }
+
+    /// Infers the structure of several samples from only the first sample of
+    /// each shape, where samples with the same keys and kinds of values at
+    /// every depth share a shape. This is much faster for many similar records,
+    /// such as logs, but the values of the skipped samples are never looked at,
+    /// so neither their stats nor the formats detected in their strings count.
+    /// The number of shapes found is in `Conversion::unique_shapes`.
+    ///
+    /// `SchemaAccumulator` remembers a 64 bit hash of each shape rather than
+    /// the shape itself, and only the first 65,536 shapes, around a megabyte
+    /// at most, however many samples it is given. Samples of shapes beyond
+    /// those are merged like samples of new shapes, every time.
+    pub fn set_dedupe_samples(&mut self, dedupe_samples: bool) -> &mut ConvertOptions {
+        self.dedupe_samples = dedupe_samples;
+        self
+    }
+
+    /// Calls `callback` as samples are tokenized, parsed, deduped and merged,
+    /// such as to drive a progress bar while converting huge inputs.
+    pub fn set_progress_callback<F: Fn(Progress) + Send + Sync + 'static>(
+        &mut self,
+        callback: F,
+    ) -> &mut ConvertOptions {
+        self.progress_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Stops the conversion with `Error::Cancelled` once `is_cancelled` returns
+    /// true, which is checked every megabyte tokenized and regularly while
+    /// merging samples, such as to abort a stale request. An `Arc<AtomicBool>`
+    /// set from another thread can be checked with
+    /// `move || flag.load(Ordering::Relaxed)`.
+    pub fn set_cancellation<F: Fn() -> bool + Send + Sync + 'static>(
+        &mut self,
+        is_cancelled: F,
+    ) -> &mut ConvertOptions {
+        self.cancellation = Some(Box::new(is_cancelled));
+        self
+    }
+
+    /// Fails json samples longer than `max_input_bytes` with
+    /// `Error::InputTooLarge` before reading them, such as for samples from
+    /// untrusted users. Unlimited by default.
+    pub fn set_max_input_bytes(&mut self, max_input_bytes: Option<usize>) -> &mut ConvertOptions {
+        self.max_input_bytes = max_input_bytes;
+        self
+    }
+
+    /// Fails json samples with objects and arrays nested more than `max_depth`
+    /// levels deep with `Error::TooDeep`, such as for samples from untrusted
+    /// users. Samples may nest 128 levels deep by default, as deeper nesting
+    /// could overflow the stack.
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) -> &mut ConvertOptions {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Generates fields that are sometimes a bool and sometimes `0` or `1` as
+    /// `bool`, deserialized with a generated helper that accepts both. Other
+    /// numbers are reported in the warnings.
+    pub fn set_numeric_bools(&mut self, numeric_bools: bool) -> &mut ConvertOptions {
+        self.numeric_bools = numeric_bools;
+        self
+    }
+
+    /// Infers fields whose every value is `0` or `1`, in at least `threshold`
+    /// values, as `bool`, deserialized with the same helper as
+    /// `set_numeric_bools`, which this implies. Such fields are reported in the
+    /// warnings. `None`, the default, keeps them as integers.
+    pub fn set_int_bool_threshold(&mut self, threshold: Option<usize>) -> &mut ConvertOptions {
+        self.int_bool_threshold = threshold;
+        self
+    }
+
+    /// Refers to the `deserialize_with` helpers of coerced fields in a shared
+    /// module, such as `crate::de_helpers`, instead of writing them after the
+    /// generated types. `de_helpers_module` writes the module.
+    pub fn set_helpers_module(&mut self, helpers_module: Option<String>) -> &mut ConvertOptions {
+        self.helpers_module = helpers_module;
+        self
+    }
+
+    /// Code written at the top of generated files, such as a license,
+    /// `//! @generated` or `#![allow(clippy::all)]`. Added by
+    /// `generated_file`.
+    pub fn set_header(&mut self, header: Option<String>) -> &mut ConvertOptions {
+        self.header = header;
+        self
+    }
+
+    /// Code written at the end of generated files, added by `generated_file`.
+    pub fn set_footer(&mut self, footer: Option<String>) -> &mut ConvertOptions {
+        self.footer = footer;
+        self
+    }
+
+    /// Ends generated files with an empty region of user code, which
+    /// `merge_user_code` carries over when the file is regenerated.
+    pub fn set_user_code_region(&mut self, user_code_region: bool) -> &mut ConvertOptions {
+        self.user_code_region = user_code_region;
+        self
+    }
+
+    /// Starts files written with `generated_file_with_banner` with a `Banner`
+    /// of comments recording the version of json2rust, and hashes of the
+    /// options and samples, so checks can tell whether they are up to date.
+    pub fn set_banner(&mut self, banner: bool) -> &mut ConvertOptions {
+        self.banner = banner;
+        self
+    }
+
+    /// Also records when the file was generated in the `Banner`, which makes
+    /// the generated files differ every time.
+    pub fn set_banner_timestamp(&mut self, banner_timestamp: bool) -> &mut ConvertOptions {
+        self.banner_timestamp = banner_timestamp;
+        self
+    }
+
+    /// Tailors the types generated for requests and responses, such as from a
+    /// har file, for axum or actix handlers. Request types only derive
+    /// `Deserialize` and reject unknown fields, and response types only derive
+    /// `Serialize`. Types used by both still derive both.
+    pub fn set_server_types(&mut self, server_types: bool) -> &mut ConvertOptions {
+        self.server_types = server_types;
+        self
+    }
+
+    /// Also derives `utoipa::ToSchema` on generated types, for servers
+    /// documenting their API with OpenAPI.
+    pub fn set_openapi_schemas(&mut self, openapi_schemas: bool) -> &mut ConvertOptions {
+        self.openapi_schemas = openapi_schemas;
+        self
+    }
+
+    /// Derives `validator::Validate` on generated structs, with rules taken
+    /// from the sample: the observed length range of strings, the observed
+    /// range of numbers, and `email` or `url` when every value was one.
+    /// Nested structs are validated too. The observed values are only known
+    /// when generating with `conversion_to_rust`.
+    pub fn set_validation(&mut self, validation: bool) -> &mut ConvertOptions {
+        self.validation = validation;
+        self
+    }
+
+    /// Derives `schemars::JsonSchema` on generated types. `generate_sample_tests`
+    /// then also checks the sample against the schema.
+    pub fn set_json_schemas(&mut self, json_schemas: bool) -> &mut ConvertOptions {
+        self.json_schemas = json_schemas;
+        self
+    }
+
+    /// Pins the version of the generated code, so upgrading json2rust doesn't
+    /// change it. Defaults to `OutputVersion::LATEST`.
+    pub fn set_output_version(&mut self, output_version: OutputVersion) -> &mut ConvertOptions {
+        self.output_version = output_version;
+        self
+    }
+
+    /// Documents every generated type and field with the json pointer and the
+    /// line of the sample its first value was on. Only known when generating
+    /// with `conversion_to_rust`.
+    pub fn set_sample_locations(&mut self, sample_locations: bool) -> &mut ConvertOptions {
+        self.sample_locations = sample_locations;
+        self
+    }
+
+    /// Adds a `<ROOT>_FINGERPRINT` constant after the types, holding the
+    /// `JsonStructure::fingerprint` of the structure they were generated from.
+    pub fn set_schema_fingerprint(&mut self, schema_fingerprint: bool) -> &mut ConvertOptions {
+        self.schema_fingerprint = schema_fingerprint;
+        self
+    }
+
+    /// Adds a `matches_schema` function after the types, which checks a
+    /// `serde_json::Value` against the structure they were generated from and
+    /// lists the mismatches as `SchemaViolation`s, to detect drift at runtime.
+    pub fn set_shape_check(&mut self, shape_check: bool) -> &mut ConvertOptions {
+        self.shape_check = shape_check;
+        self
+    }
+
+    /// Generates fields that had the same string or integer in every object of
+    /// the sample, such as `"object": "charge"`, so that other values are
+    /// rejected: strings as an enum with the value as its only variant, and
+    /// integers with a `deserialize_with` check. Fields seen only once are left
+    /// as they are, as is everything with `set_redact_samples`.
+    pub fn set_constant_fields(&mut self, constant_fields: bool) -> &mut ConvertOptions {
+        self.constant_fields = constant_fields;
+        self
+    }
+
+    /// Generates integer fields that took a small fixed set of values, such as
+    /// status codes of 1, 2 and 3, in the style `style` instead of as `i64`. A
+    /// set is small when it has at most 16 values and every value was seen at
+    /// least twice on average, so ids aren't mistaken for one. Only known when
+    /// generating with `conversion_to_rust`, and not with
+    /// `set_redact_samples`.
+    pub fn set_int_enums(&mut self, style: Option<IntEnumStyle>) -> &mut ConvertOptions {
+        self.int_enums = style;
+        self
+    }
+
+    /// Generates array fields whose key matches the glob `pattern` as a
+    /// `BTreeSet`, when their elements are strings or integers and no array in
+    /// the sample had the same element twice, such as tag lists. Repeated
+    /// elements are dropped when deserializing a set. Only known when
+    /// generating with `conversion_to_rust`.
+    pub fn add_set_field<S: Into<String>>(&mut self, pattern: S) -> &mut ConvertOptions {
+        self.set_fields.push(pattern.into());
+        self
+    }
+
+    /// Generates the fields added with `add_set_field` as a `HashSet` instead
+    /// of a `BTreeSet`.
+    pub fn set_hash_sets(&mut self, hash_sets: bool) -> &mut ConvertOptions {
+        self.hash_sets = hash_sets;
+        self
+    }
+
+    /// Generates arrays that had the same number of elements every time, and
+    /// were seen at least `min_observations` times, as `[T; N]`, such as
+    /// `[f64; 3]` for rgb triples. Arrays of more than 32 elements stay a
+    /// `Vec`, as serde only implements arrays up to that length. Only known
+    /// when generating with `conversion_to_rust`. `None` disables it, which is
+    /// the default.
+    pub fn set_fixed_size_arrays(
+        &mut self,
+        min_observations: Option<usize>,
+    ) -> &mut ConvertOptions {
+        self.fixed_size_arrays = min_observations;
+        self
+    }
+
+    /// Generates fields that were missing from some objects of the sample and
+    /// null in others as `Option<Option<T>>`, so a missing field (`None`) can
+    /// be told apart from a null one (`Some(None)`), as partial updates need
+    /// to. Missing fields are left out again when serializing. Only known when
+    /// generating with `conversion_to_rust`.
+    pub fn set_distinguish_null(&mut self, distinguish_null: bool) -> &mut ConvertOptions {
+        self.distinguish_null = distinguish_null;
+        self
+    }
+
+    /// Also generates a `<Root>Patch` struct after the types, for building
+    /// json merge patch bodies of partial updates to the root type. Every
+    /// field of it is optional and left out when `None`, and fields that can
+    /// be null are `Option<Option<T>>`, so they can be set to null. Fields
+    /// unwrapped with `unwrap_wrapper` are left out.
+    pub fn set_patch_types(&mut self, patch_types: bool) -> &mut ConvertOptions {
+        self.patch_types = patch_types;
+        self
+    }
+
+    /// Marks the field at `path` as generated by the server, such as `/id` or
+    /// `/created_at`. Structs with such fields also get a `<Name>Create`
+    /// struct without them, for the body of requests creating one, along with
+    /// `From<Name> for <Name>Create`. The path is a json pointer, using `*`
+    /// for array elements, e.g. `/items/*/id`.
+    pub fn mark_server_generated<S: Into<String>>(&mut self, path: S) -> &mut ConvertOptions {
+        self.server_generated.push(path.into());
+        self
+    }
+
+    /// Adds `From` impls after the types between structs that share most of
+    /// their fields, such as the same resource returned by two endpoints. One
+    /// struct converts into another when it has every field of it with the
+    /// same type, except optional ones, which are set to `None`.
+    pub fn set_conversions(&mut self, conversions: bool) -> &mut ConvertOptions {
+        self.conversions = conversions;
+        self
+    }
+
+    /// Adds a compact `impl Display` after the types for every struct, for
+    /// log lines, such as `User(id=1, name="Al")`. It shows the id and
+    /// name-like fields of the struct, or its first fields if it has none, as
+    /// long as they are strings, numbers or bools.
+    pub fn set_display_impls(&mut self, display_impls: bool) -> &mut ConvertOptions {
+        self.display_impls = display_impls;
+        self
+    }
+
+    /// Also derives `Eq`, `PartialOrd` and `Ord` on generated types whose
+    /// fields all support them, so they can be sorted and used as `BTreeMap`
+    /// keys. Types holding floats, maps, `serde_json::Value` or custom types,
+    /// directly or through other types, don't.
+    pub fn set_ordering_derives(&mut self, ordering_derives: bool) -> &mut ConvertOptions {
+        self.ordering_derives = ordering_derives;
+        self
+    }
+
+    /// Keeps code built with the generated types working when the json gains
+    /// values: enums get a catch-all variant, `#[serde(other)] Other` for
+    /// enums of strings and `Other(serde_json::Value)` for untagged ones, and
+    /// structs are marked `#[non_exhaustive]`, so adding fields to them later
+    /// doesn't break other crates.
+    pub fn set_forward_compatible(&mut self, forward_compatible: bool) -> &mut ConvertOptions {
+        self.forward_compatible = forward_compatible;
+        self
+    }
+
+    /// Generates fields whose key matches the glob `pattern`, such as
+    /// `"*_id"`, and that hold a string, number, bool or detected type, with a
+    /// type alias named after the key, such as `pub type UserId = uuid::Uuid;`
+    /// for `user_id`, written after the types. Fields with the same key and
+    /// type share the alias, so signatures taking them say what they hold.
+    pub fn add_type_alias<S: Into<String>>(&mut self, pattern: S) -> &mut ConvertOptions {
+        self.type_aliases.push(pattern.into());
+        self
+    }
+
+    /// Generates a type alias with the name of the root type when the sample
+    /// isn't an object, such as `pub type Root = Vec<String>;` for an array of
+    /// strings, so the root name can be used whatever the sample holds.
+    pub fn set_root_alias(&mut self, root_alias: bool) -> &mut ConvertOptions {
+        self.root_alias = root_alias;
+        self
+    }
+
+    /// How to name the types of a sample that is an array of objects. By
+    /// default the elements take the root name, so `Users` for a list of users
+    /// is a single user.
+    pub fn set_array_root(&mut self, array_root: ArrayRoot) -> &mut ConvertOptions {
+        self.array_root = array_root;
+        self
+    }
+
+    /// For samples that are an array of objects, also generates the rows as
+    /// columns, such as `OrderColumns { id: Vec<i64>, name: Vec<String> }`
+    /// for `Order`, with `FromIterator<Order>` and `From<Vec<Order>>` impls,
+    /// for feeding them to columnar libraries such as Arrow or Polars.
+    pub fn set_columnar(&mut self, columnar: bool) -> &mut ConvertOptions {
+        self.columnar = columnar;
+        self
+    }
+
+    /// Also derives `sqlx::FromRow` on the struct of the records of the
+    /// sample, the root or the elements of a root array, when its fields are
+    /// all scalars, or `Option`s and `Vec`s of them, which columns can hold.
+    pub fn set_sqlx_derives(&mut self, sqlx_derives: bool) -> &mut ConvertOptions {
+        self.sqlx_derives = sqlx_derives;
+        self
+    }
+
+    /// Maps the struct of the records of the sample, when it is flat like for
+    /// `set_sqlx_derives`, to the Diesel table `table`, such as
+    /// `crate::schema::users`, deriving `Queryable` and `Selectable`. Its
+    /// fields are the columns `structure_to_sql` creates for the sample.
+    pub fn set_diesel_table(&mut self, table: Option<String>) -> &mut ConvertOptions {
+        self.diesel_table = table;
+        self
+    }
+
+    /// Declares the fields of structs by descending alignment and size, with
+    /// `#[repr(C)]`, when that leaves less padding between them than the order
+    /// of the sample, for large structs shared over FFI or mapped from memory
+    /// that need a stable layout. The bytes saved are noted on the struct along
+    /// with the order of the sample. A `repr` added with `add_type_attribute`
+    /// is kept instead. Serialized values have their fields in the new order.
+    pub fn set_reorder_fields(&mut self, reorder_fields: bool) -> &mut ConvertOptions {
+        self.reorder_fields = reorder_fields;
+        self
+    }
+
+    /// Generates optional fields whose type is estimated to be at least
+    /// `threshold` bytes, on 64-bit targets, as `Option<Box<T>>`, so they take
+    /// the space of a pointer in their struct rather than the space of the
+    /// whole value, even when they are `None`. The sizes are those
+    /// `type_sizes` reports. `None` disables it, which is the default.
+    pub fn set_box_threshold(&mut self, threshold: Option<usize>) -> &mut ConvertOptions {
+        self.box_threshold = threshold;
+        self
+    }
+
+    /// Writes fields whose type couldn't be determined, as they were only null
+    /// or an empty array in the sample, commented out with a `TODO` naming the
+    /// line of the sample they were on, instead of as `serde_json::Value`. The
+    /// rest of the types still compile, and serde ignores the commented keys,
+    /// except in request types of `set_server_types`, which reject them.
+    pub fn set_comment_unknown_fields(
+        &mut self,
+        comment_unknown_fields: bool,
+    ) -> &mut ConvertOptions {
+        self.comment_unknown_fields = comment_unknown_fields;
+        self
+    }
+
+    /// The number of spaces a level of the generated code is indented with,
+    /// 4 by default like rustfmt's `tab_spaces`.
+    pub fn set_indent_width(&mut self, indent_width: usize) -> &mut ConvertOptions {
+        self.indent_width = indent_width;
+        self
+    }
+
+    /// Indents the generated code with a tab a level, like rustfmt's
+    /// `hard_tabs`.
+    pub fn set_hard_tabs(&mut self, hard_tabs: bool) -> &mut ConvertOptions {
+        self.hard_tabs = hard_tabs;
+        self
+    }
+
+    /// Whether the last field of a type, variant of an enum or arm of a match
+    /// has a trailing comma, which it does by default. Turning it off matches
+    /// rustfmt's `trailing_comma = "Never"`.
+    pub fn set_trailing_commas(&mut self, trailing_commas: bool) -> &mut ConvertOptions {
+        self.trailing_commas = trailing_commas;
+        self
+    }
+
+    /// Puts the attributes of fields and variants, such as
+    /// `#[serde(rename = "...")]`, on the line of the field instead of on
+    /// lines of their own.
+    pub fn set_inline_field_attributes(
+        &mut self,
+        inline_field_attributes: bool,
+    ) -> &mut ConvertOptions {
+        self.inline_field_attributes = inline_field_attributes;
+        self
+    }
+
+    /// Derives the traits of the standard library and those of other crates,
+    /// such as serde's, in separate `#[derive]` attributes.
+    pub fn set_split_derives(&mut self, split_derives: bool) -> &mut ConvertOptions {
+        self.split_derives = split_derives;
+        self
+    }
+
+    /// Unwraps fields holding an object with a single field whose key matches
+    /// the glob `key_pattern`, such as `{"price": {"value": 5}}`, to the type
+    /// of that field, reading and writing the wrapper with a generated
+    /// `#[serde(with = ...)]` module. `"*"` unwraps every single-field object.
+    pub fn unwrap_wrapper<S: Into<String>>(&mut self, key_pattern: S) -> &mut ConvertOptions {
+        self.wrapper_keys.push(key_pattern.into());
+        self
+    }
+
+    pub(crate) fn numeric_bools(&self) -> bool {
+        self.numeric_bools || self.int_bool_threshold.is_some()
+    }
+
+    pub(crate) fn report_progress(&self, progress: Progress) {
+        if let Some(callback) = &self.progress_callback {
+            callback(progress);
+        }
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(|is_cancelled| is_cancelled())
+    }
+
+    pub(crate) fn check_cancelled(&self) -> Result<(), Error> {
+        match self.is_cancelled() {
+            true => Err(Error::Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    pub(crate) fn limits(&self) -> Limits {
+        Limits {
+            max_input_bytes: self.max_input_bytes,
+            max_depth: self.max_depth,
+        }
+    }
+
+    pub(crate) fn leniency(&self) -> Leniency {
+        Leniency {
+            numbers: self.lenient_numbers,
+            relaxed: self.relaxed_json,
+            non_finite: self.non_finite_numbers,
+            exponent_integers: self.exponent_integers,
+        }
+    }
+}
+
+impl Default for ConvertOptions {
+    fn default() -> ConvertOptions {
+        ConvertOptions {
+            detectors: default_detectors(),
+            user_detector_count: 0,
+            type_mappings: Vec::new(),
+            chaos_threshold: Some(DEFAULT_CHAOS_THRESHOLD),
+            forced_value_paths: Vec::new(),
+            tagged_unions: Vec::new(),
+            key_filter: KeyFilter::default(),
+            passes: default_passes(),
+            naming_strategy: Box::new(SingularizedStrategy::new()),
+            acronyms: AcronymStyle::Capitalized,
+            extended_json: false,
+            lenient_numbers: false,
+            relaxed_json: false,
+            non_finite_numbers: false,
+            exponent_integers: false,
+            locale_numbers: false,
+            mixed_numbers: MixedNumberPolicy::Union,
+            whole_floats: WholeFloatPolicy::Float,
+            floats: FloatPolicy::Float,
+            empty_strings_as_none: false,
+            json_strings: false,
+            field_descriptions: false,
+            numeric_bools: false,
+            int_bool_threshold: None,
+            helpers_module: None,
+            type_attributes: Vec::new(),
+            field_attributes: Vec::new(),
+            header: None,
+            footer: None,
+            user_code_region: false,
+            banner: false,
+            banner_timestamp: false,
+            server_types: false,
+            openapi_schemas: false,
+            validation: false,
+            json_schemas: false,
+            output_version: OutputVersion::LATEST,
+            sample_locations: false,
+            schema_fingerprint: false,
+            shape_check: false,
+            redact_samples: false,
+            detect_secrets: true,
+            dedupe_samples: false,
+            all_optional: false,
+            progress_callback: None,
+            cancellation: None,
+            wrapper_keys: Vec::new(),
+            strip_common_prefix: false,
+            abbreviations: Vec::new(),
+            constant_fields: false,
+            int_enums: None,
+            set_fields: Vec::new(),
+            hash_sets: false,
+            fixed_size_arrays: None,
+            distinguish_null: false,
+            patch_types: false,
+            server_generated: Vec::new(),
+            conversions: false,
+            display_impls: false,
+            ordering_derives: false,
+            forward_compatible: false,
+            type_aliases: Vec::new(),
+            root_alias: false,
+            array_root: ArrayRoot::Struct,
+            columnar: false,
+            sqlx_derives: false,
+            diesel_table: None,
+            reorder_fields: false,
+            box_threshold: None,
+            comment_unknown_fields: false,
+            indent_width: 4,
+            hard_tabs: false,
+            trailing_commas: true,
+            inline_field_attributes: false,
+            split_derives: false,
+            max_input_bytes: None,
+            max_depth: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert_sample_json_with_options, JsonStructure, JsonValue};
+
+    fn field_type(json: &str, options: &ConvertOptions) -> JsonStructure {
+        match convert_sample_json_with_options(json, options).unwrap() {
+            JsonStructure::Object(mut pairs) => *pairs.remove(0).value,
+            other => panic!("Expected an object, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn user_detectors_take_priority_over_built_ins() {
+        let mut options = ConvertOptions::default();
+        options.add_detector(|field_name, _| {
+            if field_name == "ip" {
+                Some(RustType::new("MyIp"))
+            } else {
+                None
+            }
+        });
+
+        assert_eq!(
+            field_type(r#"{"ip": "127.0.0.1"}"#, &options),
+            JsonStructure::Value(JsonValue::Custom(RustType::new("MyIp")))
+        )
+    }
+
+    #[test]
+    fn user_detectors_run_in_the_order_they_were_added() {
+        let mut options = ConvertOptions::default();
+        options
+            .add_detector(|_, values| {
+                if values.iter().all(|v| v.starts_with("usr_")) {
+                    Some(RustType::new("UserId"))
+                } else {
+                    None
+                }
+            })
+            .add_detector(|_, _| Some(RustType::new("Fallback")));
+
+        assert_eq!(
+            field_type(r#"{"owner": "usr_1"}"#, &options),
+            JsonStructure::Value(JsonValue::Custom(RustType::new("UserId")))
+        );
+        assert_eq!(
+            field_type(r#"{"owner": "grp_1"}"#, &options),
+            JsonStructure::Value(JsonValue::Custom(RustType::new("Fallback")))
+        );
+    }
+
+    #[test]
+    fn extended_json_wrappers_become_bson_types() {
+        let mut options = ConvertOptions::default();
+        options.set_extended_json(true);
+
+        assert_eq!(
+            field_type(r#"{"_id": {"$oid": "5f1"}}"#, &options),
+            JsonStructure::Value(JsonValue::Custom(RustType::new("bson::oid::ObjectId")))
+        );
+        assert_eq!(
+            field_type(r#"{"n": [{"$numberLong": "1"}, 2]}"#, &options),
+            JsonStructure::array(JsonStructure::int())
+        );
+    }
+
+    #[test]
+    fn lenient_numbers_accept_missing_digits() {
+        let sample = r#"{"n": .5}"#;
+        assert!(convert_sample_json_with_options(sample, &ConvertOptions::default()).is_err());
+
+        let mut options = ConvertOptions::default();
+        options.set_lenient_numbers(true);
+        assert_eq!(
+            field_type(sample, &options),
+            JsonStructure::Value(JsonValue::Float)
+        );
+    }
+
+    #[test]
+    fn non_finite_numbers_are_floats_with_a_warning() {
+        let sample = r#"{"n": [1.5, NaN], "m": 2}"#;
+        assert!(convert_sample_json_with_options(sample, &ConvertOptions::default()).is_err());
+
+        let mut options = ConvertOptions::default();
+        options.set_non_finite_numbers(true);
+        let conversion = crate::convert_sample_json_with_warnings(sample, &options).unwrap();
+        assert_eq!(
+            conversion.structure.field("n"),
+            Some(&JsonStructure::array(JsonStructure::float()))
+        );
+        assert_eq!(
+            conversion
+                .warnings
+                .iter()
+                .map(|w| w.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/n/*"]
+        );
+    }
+
+    #[test]
+    fn exact_exponents_are_integers_with_a_warning() {
+        let sample = r#"[{"n": 1e3, "m": 1.5e-1}, {"n": 2, "m": 1}]"#;
+        let element = |structure: &JsonStructure, key| match structure {
+            JsonStructure::Array(inner) => inner.field(key).cloned(),
+            _ => None,
+        };
+        let conversion =
+            crate::convert_sample_json_with_warnings(sample, &ConvertOptions::default()).unwrap();
+        assert_eq!(
+            element(&conversion.structure, "n"),
+            Some(JsonStructure::float())
+        );
+        assert_eq!(conversion.warnings, vec![]);
+
+        let mut options = ConvertOptions::default();
+        options.set_exponent_integers(true);
+        let conversion = crate::convert_sample_json_with_warnings(sample, &options).unwrap();
+        assert_eq!(
+            element(&conversion.structure, "n"),
+            Some(JsonStructure::int())
+        );
+        assert_eq!(
+            element(&conversion.structure, "m"),
+            Some(JsonStructure::float())
+        );
+        assert_eq!(
+            conversion
+                .warnings
+                .iter()
+                .map(|w| w.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/*/n"]
+        );
+    }
+
+    #[test]
+    fn locale_numbers_are_floats_with_a_warning() {
+        let sample = r#"{"price": ["1.234,56", "12,5"], "id": "1.5"}"#;
+        let conversion =
+            crate::convert_sample_json_with_warnings(sample, &ConvertOptions::default()).unwrap();
+        assert_eq!(
+            conversion.structure.field("price"),
+            Some(&JsonStructure::array(JsonStructure::string()))
+        );
+
+        let mut options = ConvertOptions::default();
+        options.set_locale_numbers(true);
+        let conversion = crate::convert_sample_json_with_warnings(sample, &options).unwrap();
+        assert_eq!(
+            conversion.structure.field("price"),
+            Some(&JsonStructure::array(JsonStructure::float()))
+        );
+        assert_eq!(
+            conversion.structure.field("id"),
+            Some(&JsonStructure::string())
+        );
+        assert_eq!(conversion.warnings.len(), 1);
+        assert_eq!(conversion.warnings[0].path, "/price/*");
+    }
+
+    #[test]
+    fn empty_strings_count_as_null() {
+        let samples = [
+            r#"{"name": "a", "id": 1, "ip": "127.0.0.1", "flag": true}"#,
+            r#"{"name": "", "id": "", "ip": "", "flag": ""}"#,
+        ];
+        let mut options = ConvertOptions::default();
+        options.set_empty_strings_as_none(true);
+        let conversion = crate::convert_samples_json_with_warnings(&samples, &options).unwrap();
+        let structure = &conversion.structure;
+
+        assert_eq!(
+            structure.field("name"),
+            Some(&JsonStructure::optional(JsonStructure::string()))
+        );
+        assert_eq!(
+            structure.field("id"),
+            Some(&JsonStructure::optional(JsonStructure::int()))
+        );
+        // Detected types keep their empty strings, as they may not implement
+        // the `FromStr` the helper parses with
+        assert_eq!(structure.field("ip"), Some(&JsonStructure::string()));
+        assert_eq!(
+            structure.field("flag"),
+            Some(&JsonStructure::Union(vec![
+                JsonStructure::bool(),
+                JsonStructure::string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn numeric_bools_warn_about_other_numbers() {
+        let sample = r#"[{"a": true, "b": 1}, {"a": 0, "b": 2}, {"b": false}]"#;
+        let mut options = ConvertOptions::default();
+        options.set_numeric_bools(true);
+        let conversion = crate::convert_sample_json_with_warnings(sample, &options).unwrap();
+
+        assert_eq!(conversion.warnings.len(), 1);
+        assert_eq!(conversion.warnings[0].path, "/*/b");
+    }
+
+    #[test]
+    fn int_bools_need_enough_observations() {
+        let sample = r#"[{"a": 1, "b": 0, "c": [1]}, {"a": 0, "b": 2, "c": [0]}, {"a": 1}]"#;
+        let mut options = ConvertOptions::default();
+        options.set_int_bool_threshold(Some(3));
+        let conversion = crate::convert_sample_json_with_warnings(sample, &options).unwrap();
+        let element = conversion.structure.element().unwrap();
+
+        assert_eq!(
+            element.field("a"),
+            Some(&JsonStructure::Union(vec![
+                JsonStructure::bool(),
+                JsonStructure::int()
+            ]))
+        );
+        assert_eq!(
+            element.field("b"),
+            Some(&JsonStructure::optional(JsonStructure::int()))
+        );
+        assert_eq!(
+            element.field("c"),
+            Some(&JsonStructure::optional(JsonStructure::array(
+                JsonStructure::int()
+            )))
+        );
+        assert_eq!(conversion.warnings.len(), 1);
+        assert_eq!(conversion.warnings[0].path, "/*/a");
+
+        options.set_int_bool_threshold(Some(4));
+        let conversion = crate::convert_sample_json_with_warnings(sample, &options).unwrap();
+        assert_eq!(
+            conversion.structure.element().unwrap().field("a"),
+            Some(&JsonStructure::int())
+        );
+    }
+
+    #[test]
+    fn floats_follow_the_policy() {
+        let sample = r#"[{"price": 9.99, "count": 1, "rates": [0.5]}]"#;
+        let mut options = ConvertOptions::default();
+        options.set_float_policy(FloatPolicy::Decimal);
+
+        let structure = crate::convert_sample_json_with_options(sample, &options).unwrap();
+        let element = structure.element().unwrap();
+        let decimal = JsonStructure::custom(RustType::new("rust_decimal::Decimal"));
+        assert_eq!(element.field("price"), Some(&decimal));
+        assert_eq!(element.field("count"), Some(&JsonStructure::int()));
+        assert_eq!(
+            element.field("rates"),
+            Some(&JsonStructure::array(decimal.clone()))
+        );
+
+        options.set_float_policy(FloatPolicy::Error);
+        assert_eq!(
+            crate::convert_sample_json_with_options(sample, &options).unwrap_err(),
+            crate::Error::FloatsNotAllowed {
+                paths: vec!["/*/price".to_string(), "/*/rates/*".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn whole_floats_follow_the_policy() {
+        let sample =
+            r#"[{"count": 3.0, "ratio": 0.5, "sizes": [1.0]}, {"count": 7, "ratio": 1.0}]"#;
+        let field = |options: &ConvertOptions, key| {
+            let conversion = crate::convert_sample_json_with_warnings(sample, options).unwrap();
+            let field = conversion.structure.element().unwrap().field(key).cloned();
+            let warnings: Vec<String> = conversion.warnings.into_iter().map(|w| w.path).collect();
+            (field, warnings)
+        };
+        let mut options = ConvertOptions::default();
+        assert_eq!(
+            field(&options, "count"),
+            (Some(JsonStructure::float()), vec![])
+        );
+
+        options.set_whole_float_policy(WholeFloatPolicy::Warn);
+        assert_eq!(
+            field(&options, "count"),
+            (
+                Some(JsonStructure::float()),
+                vec!["/*/count".to_string(), "/*/sizes/*".to_string()]
+            )
+        );
+
+        options.set_whole_float_policy(WholeFloatPolicy::Int);
+        let (count, warnings) = field(&options, "count");
+        assert_eq!(
+            count,
+            Some(JsonStructure::Union(vec![
+                JsonStructure::int(),
+                JsonStructure::float()
+            ]))
+        );
+        assert_eq!(warnings, vec!["/*/count", "/*/sizes/*"]);
+        assert_eq!(field(&options, "ratio").0, Some(JsonStructure::float()));
+        assert_eq!(
+            field(&options, "sizes").0,
+            Some(JsonStructure::optional(JsonStructure::array(
+                JsonStructure::float()
+            )))
+        );
+    }
+
+    #[test]
+    fn mixed_numbers_follow_the_policy() {
+        let sample =
+            r#"[{"id": "1", "price": 2, "code": 3}, {"id": 2, "price": "2.5", "code": "x"}]"#;
+        let union = |number| JsonStructure::Union(vec![JsonStructure::string(), number]);
+        let conversion =
+            crate::convert_sample_json_with_warnings(sample, &ConvertOptions::default()).unwrap();
+        assert_eq!(
+            conversion.structure.element().unwrap().field("price"),
+            Some(&JsonStructure::Union(vec![
+                JsonStructure::int(),
+                JsonStructure::string()
+            ]))
+        );
+
+        let mut options = ConvertOptions::default();
+        options.set_mixed_number_policy(MixedNumberPolicy::Number);
+        let conversion = crate::convert_sample_json_with_warnings(sample, &options).unwrap();
+        let element = conversion.structure.element().unwrap();
+        assert_eq!(element.field("id"), Some(&union(JsonStructure::int())));
+        assert_eq!(
+            element.field("price"),
+            Some(&JsonStructure::Union(vec![
+                JsonStructure::float(),
+                JsonStructure::string()
+            ]))
+        );
+        assert_eq!(conversion.warnings.len(), 1);
+        assert_eq!(conversion.warnings[0].path, "/*/code");
+
+        options.set_mixed_number_policy(MixedNumberPolicy::Error);
+        assert_eq!(
+            crate::convert_sample_json_with_warnings(sample, &options).unwrap_err(),
+            crate::Error::MixedStringsAndNumbers {
+                path: "/*/id".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn clearing_detectors_removes_built_ins() {
+        let mut options = ConvertOptions::default();
+        options.clear_detectors();
+
+        assert_eq!(
+            field_type(r#"{"ip": "127.0.0.1"}"#, &options),
+            JsonStructure::Value(JsonValue::String)
+        )
+    }
+}
@@ -0,0 +1,125 @@
+//! Where generated code came from: the json pointer and the place in the
+//! sample of the value every generated type and field was generated for, for
+//! tooling that goes from generated code to the sample, such as editors and
+//! the errors of checking the generated code.
+
+use crate::codegen::conversion_to_rust_with_source_map;
+use crate::json_writer::JsonOutput;
+use crate::options::ConvertOptions;
+use crate::shared::{JsonTokenInfo, Span};
+use crate::Conversion;
+
+/// The value of the sample a generated type or field came from.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Origin {
+    /// The line of the generated code the type or field starts on, starting
+    /// at 1. It applies up to the line of the next origin.
+    pub line: usize,
+    /// Json pointer of the value, using `*` for array elements
+    pub pointer: String,
+    /// Where the first value at the pointer is in the sample, for samples read
+    /// from json
+    pub span: Option<Span>,
+}
+
+/// Generates the types for a converted sample like `conversion_to_rust`,
+/// along with the origin of every generated type and field, in the order of
+/// their lines.
+pub fn conversion_to_rust_with_origins(
+    conversion: &Conversion,
+    root_name: &str,
+    options: &ConvertOptions,
+) -> (String, Vec<Origin>) {
+    let (code, source_map) = conversion_to_rust_with_source_map(conversion, root_name, options);
+    let origins = source_map
+        .into_iter()
+        .map(|(line, pointer)| {
+            let span = conversion
+                .stats
+                .iter()
+                .find(|s| s.path == pointer)
+                .and_then(|s| s.span);
+            Origin {
+                line,
+                pointer,
+                span,
+            }
+        })
+        .collect();
+    (code, origins)
+}
+
+/// The origin of line `line` of generated code, if it came from the sample.
+pub(crate) fn origin_at(origins: &[Origin], line: usize) -> Option<&Origin> {
+    origins
+        .iter()
+        .take_while(|origin| origin.line <= line)
+        .last()
+}
+
+/// Writes origins as a json array, with the start and end of their span as a
+/// line and a column, both starting at 1, and an offset in characters.
+pub fn origins_to_json(origins: &[Origin]) -> String {
+    let position = |info: &JsonTokenInfo| {
+        JsonOutput::object(vec![
+            ("line", JsonOutput::Int(info.line())),
+            ("column", JsonOutput::Int(info.column())),
+            ("char", JsonOutput::Int(info.char())),
+        ])
+    };
+    JsonOutput::Array(
+        origins
+            .iter()
+            .map(|origin| {
+                JsonOutput::object(vec![
+                    ("line", JsonOutput::Int(origin.line as i64)),
+                    ("pointer", JsonOutput::string(origin.pointer.as_str())),
+                    (
+                        "span",
+                        match &origin.span {
+                            Some(span) => JsonOutput::object(vec![
+                                ("start", position(&span.start)),
+                                ("end", position(&span.end)),
+                            ]),
+                            None => JsonOutput::Null,
+                        },
+                    ),
+                ])
+            })
+            .collect(),
+    )
+    .to_pretty_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json_with_warnings;
+
+    #[test]
+    fn records_where_types_and_fields_came_from() {
+        let options = ConvertOptions::default();
+        let conversion = convert_sample_json_with_warnings(
+            "{\"id\": 1,\n \"user\": {\"name\": \"a\"}}",
+            &options,
+        )
+        .unwrap();
+        let (code, origins) = conversion_to_rust_with_origins(&conversion, "Root", &options);
+        let line_of = |text: &str| code.lines().position(|l| l.contains(text)).unwrap() + 1;
+        let origin = |text: &str| {
+            let origin = origin_at(&origins, line_of(text)).unwrap();
+            let line = origin.span.map(|span| span.start.line());
+            (origin.pointer.as_str(), line)
+        };
+
+        assert_eq!(origin("pub struct Root"), ("", Some(1)));
+        assert_eq!(origin("pub id"), ("/id", Some(1)));
+        assert_eq!(origin("pub struct User"), ("/user", Some(2)));
+        assert_eq!(origin("pub name"), ("/user/name", Some(2)));
+        assert_eq!(origin_at(&origins, 1), None);
+
+        let json = origins_to_json(&origins[1..2]);
+        assert!(json.contains("\"pointer\": \"/id\""));
+        assert!(json.contains("\"start\": {\n"));
+    }
+}
@@ -0,0 +1,107 @@
+use alloc::{format, string::String};
+
+/// Versions of the generated code. Each version keeps generating exactly the
+/// same code from the same sample, so pinning one with
+/// `ConvertOptions::set_output_version` avoids changes to the output when
+/// upgrading json2rust. Improvements to the output ship as new versions.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OutputVersion {
+    V1,
+}
+
+impl OutputVersion {
+    /// The newest version, used unless another one is set.
+    pub const LATEST: OutputVersion = OutputVersion::V1;
+
+    /// The version with the given number, such as 1 for `V1`.
+    pub fn from_number(number: u32) -> Option<OutputVersion> {
+        match number {
+            1 => Some(OutputVersion::V1),
+            _ => None,
+        }
+    }
+
+    pub fn number(self) -> u32 {
+        match self {
+            OutputVersion::V1 => 1,
+        }
+    }
+
+    pub(crate) fn strategy(self) -> &'static dyn OutputStrategy {
+        match self {
+            OutputVersion::V1 => &V1,
+        }
+    }
+}
+
+impl Default for OutputVersion {
+    fn default() -> OutputVersion {
+        OutputVersion::LATEST
+    }
+}
+
+/// The choices about the shape of the generated code that a version pins.
+/// Changing how code is generated means adding a new version with its own
+/// strategy, rather than changing an existing one.
+pub(crate) trait OutputStrategy {
+    /// Derived on every generated type, before the serde traits.
+    fn base_derives(&self) -> &'static [&'static str];
+
+    /// The name of a field whose key gives no usable rust name, by its
+    /// position in the struct, starting at 0.
+    fn unnamed_field(&self, position: usize) -> String;
+}
+
+struct V1;
+
+impl OutputStrategy for V1 {
+    fn base_derives(&self) -> &'static [&'static str] {
+        &["Debug", "Clone", "PartialEq"]
+    }
+
+    fn unnamed_field(&self, position: usize) -> String {
+        format!("field_{}", position + 1)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::testing::assert_generates;
+    use crate::ConvertOptions;
+
+    #[test]
+    fn numbers_versions() {
+        assert_eq!(OutputVersion::from_number(1), Some(OutputVersion::V1));
+        assert_eq!(OutputVersion::from_number(0), None);
+        assert_eq!(OutputVersion::LATEST.number(), 1);
+    }
+
+    // The output of a version must never change
+    #[test]
+    fn pins_v1_output() {
+        let mut options = ConvertOptions::default();
+        options.set_output_version(OutputVersion::V1);
+
+        assert_generates(
+            r#"{"id": 1, "": "a", "tags": [{"name": "b"}]}"#,
+            "Root",
+            &options,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    pub id: i64,
+    #[serde(rename = "")]
+    pub field_2: String,
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+}
+"#,
+        );
+    }
+}
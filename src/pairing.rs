@@ -0,0 +1,196 @@
+#[cfg(feature = "client-stubs")]
+use crate::client::ClientStub;
+#[cfg(feature = "client-stubs")]
+use crate::codegen::root_type;
+use crate::codegen::{generate_rust_roots, Direction};
+#[cfg(feature = "client-stubs")]
+use crate::naming::field_name;
+use crate::naming::type_name;
+use crate::options::ConvertOptions;
+use crate::shared::Error;
+use crate::{convert_sample_json_with_options, JsonPair, JsonStructure};
+
+/// The request and response bodies of one API call, such as creating a user,
+/// from which `<Name>Request` and `<Name>Response` types are generated.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct RequestResponse {
+    pub name: String,
+    pub request: Option<JsonStructure>,
+    pub response: Option<JsonStructure>,
+}
+
+impl RequestResponse {
+    /// Infers the request and response types from a sample of each. Either
+    /// sample may be left out, for calls without a body.
+    pub fn from_samples(
+        name: &str,
+        request: Option<&str>,
+        response: Option<&str>,
+        options: &ConvertOptions,
+    ) -> Result<RequestResponse, Error> {
+        let convert = |sample: Option<&str>| {
+            sample
+                .map(|json| convert_sample_json_with_options(json, options))
+                .transpose()
+        };
+        Ok(RequestResponse {
+            name: name.to_string(),
+            request: convert(request)?,
+            response: convert(response)?,
+        })
+    }
+
+    pub fn request_type_name(&self) -> String {
+        format!("{}Request", type_name(&self.name))
+    }
+
+    pub fn response_type_name(&self) -> String {
+        format!("{}Response", type_name(&self.name))
+    }
+
+    /// Generates the request and response types. Nested types are named
+    /// across both, so they don't collide.
+    pub fn to_rust(&self, options: &ConvertOptions) -> String {
+        let mut roots = Vec::new();
+        if let Some(request) = &self.request {
+            roots.push((
+                JsonPair::new(self.request_type_name(), request.clone()),
+                Direction::Request,
+            ));
+        }
+        if let Some(response) = &self.response {
+            roots.push((
+                JsonPair::new(self.response_type_name(), response.clone()),
+                Direction::Response,
+            ));
+        }
+        generate_rust_roots(&roots, options)
+    }
+
+    /// A `reqwest` function sending the request to `method` `path`, and
+    /// reading the response.
+    #[cfg(feature = "client-stubs")]
+    pub fn client_stub(&self, method: &str, path: &str) -> ClientStub {
+        let function_name = match field_name(&self.name) {
+            name if name.is_empty() => "call".to_string(),
+            name => name,
+        };
+        let mut stub = ClientStub::new(function_name, method, path);
+        stub.request_type = self
+            .request
+            .as_ref()
+            .map(|request| root_type(request, &self.request_type_name()));
+        stub.response_type = self
+            .response
+            .as_ref()
+            .map(|response| root_type(response, &self.response_type_name()));
+        stub
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_request_and_response_types() {
+        let call = RequestResponse::from_samples(
+            "create user",
+            Some(r#"{"name": "a", "tags": [{"label": "x"}]}"#),
+            Some(r#"[{"id": 1, "tags": [{"id": 2}]}]"#),
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+
+        let result = call.to_rust(&ConvertOptions::default());
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateUserRequest {
+    pub name: String,
+    pub tags: Vec<CreateUserRequestTag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateUserRequestTag {
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateUserResponse {
+    pub id: i64,
+    pub tags: Vec<CreateUserResponseTag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateUserResponseTag {
+    pub id: i64,
+}
+"#
+        )
+    }
+
+    #[test]
+    fn generates_server_types() {
+        let mut options = ConvertOptions::default();
+        options.set_server_types(true).set_openapi_schemas(true);
+        let call = RequestResponse::from_samples(
+            "update user",
+            Some(r#"{"name": "a", "address": {"city": "x"}}"#),
+            Some(r#"{"id": 1, "address": {"city": "y"}}"#),
+            &options,
+        )
+        .unwrap();
+
+        let result = call.to_rust(&options);
+
+        assert_eq!(
+            result,
+            r#"use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateUserRequest {
+    pub name: String,
+    pub address: Address,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Address {
+    pub city: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct UpdateUserResponse {
+    pub id: i64,
+    pub address: Address,
+}
+"#
+        )
+    }
+
+    #[cfg(feature = "client-stubs")]
+    #[test]
+    fn creates_client_stubs() {
+        let call = RequestResponse::from_samples(
+            "CreateUser",
+            Some(r#"{"name": "a"}"#),
+            Some(r#"[{"id": 1}]"#),
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+
+        let stub = call.client_stub("POST", "/users");
+
+        assert_eq!(stub.function_name, "create_user");
+        assert_eq!(stub.request_type, Some("CreateUserRequest".to_string()));
+        assert_eq!(
+            stub.response_type,
+            Some("Vec<CreateUserResponse>".to_string())
+        );
+    }
+}
@@ -0,0 +1,526 @@
+use crate::key_filter::ignored_path;
+use crate::pointer::{child_path, element_path};
+use crate::shared::{Error, Span};
+pub(crate) use crate::tokenizer::KeyInterner;
+use crate::tokenizer::{
+    end_location, tokenize_json_reporting, JsonToken, JsonTokenType, Leniency, Limits,
+};
+use alloc::{
+    collections::BTreeMap,
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// A parsed sample document. Only the information needed for inference is kept,
+/// so booleans don't carry their values.
+#[derive(Debug, PartialEq, Clone)]
+pub enum JsonNode {
+    /// Keys are shared between objects, see `KeyInterner`
+    Object(Vec<(Rc<str>, JsonNode)>),
+    Array(Vec<JsonNode>),
+    String(String),
+    Float(f64),
+    /// Wide enough for both signed and unsigned 64 bit integers
+    Int(i128),
+    Bool,
+    Null,
+    /// Binary data, which only binary sample formats have
+    #[cfg_attr(not(any(feature = "msgpack", feature = "cbor")), allow(dead_code))]
+    Bytes,
+    /// A value whose rust type is already known, such as an ObjectId in
+    /// MongoDB extended json
+    Custom(&'static str),
+}
+
+impl JsonNode {
+    /// The value of `key`, if this is an object with such a key.
+    pub fn get(&self, key: &str) -> Option<&JsonNode> {
+        match self {
+            JsonNode::Object(pairs) => pairs.iter().find(|(k, _)| **k == *key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonNode::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    json: &'a str,
+    tokens: &'a [JsonToken],
+    position: usize,
+    // The span of the first value at every json pointer, with `*` for array
+    // elements
+    spans: BTreeMap<String, Span>,
+    exponent_integers: BTreeMap<String, i128>,
+    // The json pointer whose every value is extracted, and their spans
+    extract: Option<&'a str>,
+    extracted: Vec<Span>,
+    // Whether the last member or element may be followed by a comma, as in
+    // relaxed json
+    trailing_commas: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn new(json: &'a str, tokens: &'a [JsonToken]) -> Parser<'a> {
+        Parser {
+            json,
+            tokens,
+            position: 0,
+            spans: BTreeMap::new(),
+            exponent_integers: BTreeMap::new(),
+            extract: None,
+            extracted: Vec::new(),
+            trailing_commas: false,
+        }
+    }
+
+    /// The next token, where `expected` describes it in case the input ends.
+    fn next(&mut self, expected: &str) -> Result<&'a JsonToken, Error> {
+        match self.tokens.get(self.position) {
+            Some(token) => {
+                self.position += 1;
+                Ok(token)
+            }
+            None => Err(self.unexpected_end(expected)),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a JsonToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn unexpected_end(&self, expected: &str) -> Error {
+        Error::UnexpectedEndOfInput {
+            expected: expected.to_string(),
+            last_location: end_location(self.json),
+        }
+    }
+
+    /// Parses the value at the json pointer `path`.
+    fn parse_value(&mut self, path: &str) -> Result<JsonNode, Error> {
+        let token = self.next("a value")?;
+        let node = match token.token_type {
+            JsonTokenType::ObjectStart => self.parse_object(path)?,
+            JsonTokenType::ArrayStart => self.parse_array(path)?,
+            JsonTokenType::String(ref s) => JsonNode::String(s.clone()),
+            JsonTokenType::Float(value) => JsonNode::Float(value),
+            JsonTokenType::Int(value) => JsonNode::Int(value),
+            JsonTokenType::ExponentInt(value) => {
+                if !self.exponent_integers.contains_key(path) {
+                    self.exponent_integers.insert(path.to_string(), value);
+                }
+                JsonNode::Int(value)
+            }
+            JsonTokenType::Bool => JsonNode::Bool,
+            JsonTokenType::Null => JsonNode::Null,
+            ref other => return Err(unexpected_token(token, other)),
+        };
+        let span = Span::new(token.span.start, self.tokens[self.position - 1].span.end);
+        if !self.spans.contains_key(path) {
+            self.spans.insert(path.to_string(), span);
+        }
+        if self.extract == Some(path) {
+            self.extracted.push(span);
+        }
+        Ok(node)
+    }
+
+    /// Consumes the next token if it is `token_type`.
+    fn next_if(&mut self, token_type: &JsonTokenType) -> bool {
+        let found = self
+            .peek()
+            .is_some_and(|token| token.token_type == *token_type);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    /// Consumes the comma after a member or element, returning false if
+    /// instead the object or array ends with `end`, or with a trailing comma
+    /// and `end` where those are allowed.
+    fn separator(&mut self, end: &JsonTokenType, expected: &str) -> Result<bool, Error> {
+        let token = self.next(expected)?;
+        match token.token_type {
+            JsonTokenType::Comma => Ok(!(self.trailing_commas && self.next_if(end))),
+            ref other if other == end => Ok(false),
+            ref other => Err(unexpected_token(token, other)),
+        }
+    }
+
+    fn parse_object(&mut self, path: &str) -> Result<JsonNode, Error> {
+        let mut pairs = Vec::new();
+        if self.next_if(&JsonTokenType::ObjectEnd) {
+            return Ok(JsonNode::Object(pairs));
+        }
+        loop {
+            let token = self.next("a key")?;
+            let key = match token.token_type {
+                JsonTokenType::Key(ref key) => Rc::clone(key),
+                ref other => return Err(unexpected_token(token, other)),
+            };
+            let colon = self.next("':'")?;
+            if colon.token_type != JsonTokenType::Colon {
+                return Err(unexpected_token(colon, &colon.token_type));
+            }
+            let value = self.parse_value(&child_path(path, &key))?;
+            pairs.push((key, value));
+            if !self.separator(&JsonTokenType::ObjectEnd, "',' or '}'")? {
+                return Ok(JsonNode::Object(pairs));
+            }
+        }
+    }
+
+    fn parse_array(&mut self, path: &str) -> Result<JsonNode, Error> {
+        let element = element_path(path);
+        let mut values = Vec::new();
+        if self.next_if(&JsonTokenType::ArrayEnd) {
+            return Ok(JsonNode::Array(values));
+        }
+        loop {
+            values.push(self.parse_value(&element)?);
+            if !self.separator(&JsonTokenType::ArrayEnd, "',' or ']'")? {
+                return Ok(JsonNode::Array(values));
+            }
+        }
+    }
+}
+
+fn unexpected_token(token: &JsonToken, token_type: &JsonTokenType) -> Error {
+    Error::InvalidJson {
+        location: token.span.start,
+        message: format!("Unexpected token {:?}", token_type),
+    }
+}
+
+/// A parsed document, with what else was learned about it while parsing.
+pub(crate) struct Parsed {
+    pub node: JsonNode,
+    /// The span of the first value at every json pointer, using `*` for array
+    /// elements
+    pub spans: BTreeMap<String, Span>,
+    /// The first number written with an exponent and read as an integer at
+    /// every json pointer, with `Leniency::exponent_integers`
+    pub exponent_integers: BTreeMap<String, i128>,
+}
+
+pub fn parse_json(json: &str) -> Result<JsonNode, Error> {
+    parse_json_with(json, Leniency::default())
+}
+
+/// Parses json, accepting the deviations from strict json in `leniency`.
+pub fn parse_json_with(json: &str, leniency: Leniency) -> Result<JsonNode, Error> {
+    parse_json_with_spans(json, leniency).map(|(node, _)| node)
+}
+
+/// Parses json along with the span of the first value at every json pointer,
+/// using `*` for array elements.
+pub fn parse_json_with_spans(
+    json: &str,
+    leniency: Leniency,
+) -> Result<(JsonNode, BTreeMap<String, Span>), Error> {
+    let parsed = parse_json_reporting(
+        json,
+        leniency,
+        Limits::default(),
+        &mut KeyInterner::default(),
+        &mut |_| Ok(()),
+    )?;
+    Ok((parsed.node, parsed.spans))
+}
+
+/// Like `parse_json_with_spans`, but within `limits` and calling `report` with
+/// the number of bytes tokenized as it goes, as `tokenize_json_reporting`
+/// does, sharing keys through `keys`, and keeping everything in `Parsed`.
+pub(crate) fn parse_json_reporting(
+    json: &str,
+    leniency: Leniency,
+    limits: Limits,
+    keys: &mut KeyInterner,
+    report: &mut dyn FnMut(usize) -> Result<(), Error>,
+) -> Result<Parsed, Error> {
+    let tokens = tokenize_json_reporting(json, leniency, limits, keys, report)?;
+    let mut parser = Parser::new(json, &tokens);
+    parser.trailing_commas = leniency.relaxed;
+    let node = parser.parse_value("")?;
+    if let Some(token) = parser.peek() {
+        return Err(unexpected_token(token, &token.token_type));
+    }
+    Ok(Parsed {
+        node,
+        spans: parser.spans,
+        exponent_integers: parser.exponent_integers,
+    })
+}
+
+/// The text of every value at `path` in `json`, in the order they appear,
+/// such as every user of every item with `/items/*/user`, so a deeply nested,
+/// repeated fragment can be converted on its own. The path is a json pointer,
+/// using `*` for array elements, or a JSONPath such as `$.items[*].user`.
+pub fn extract_samples<'j>(json: &'j str, path: &str) -> Result<Vec<&'j str>, Error> {
+    let path = ignored_path(path);
+    let tokens = tokenize_json_reporting(
+        json,
+        Leniency::default(),
+        Limits::default(),
+        &mut KeyInterner::default(),
+        &mut |_| Ok(()),
+    )?;
+    let mut parser = Parser::new(json, &tokens);
+    parser.extract = Some(&path);
+    parser.parse_value("")?;
+    if let Some(token) = parser.peek() {
+        return Err(unexpected_token(token, &token.token_type));
+    }
+    // Spans are in characters
+    let offsets: Vec<usize> = json
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(core::iter::once(json.len()))
+        .collect();
+    Ok(parser
+        .extracted
+        .iter()
+        .map(|span| &json[offsets[span.start.char() as usize]..offsets[span.end.char() as usize]])
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::JsonTokenInfo;
+
+    #[test]
+    fn parses_nested_objects() {
+        let result = parse_json(r#"{"foo": {"bar": [1, 2.5, true, null]}}"#).unwrap();
+
+        assert_eq!(
+            result,
+            JsonNode::Object(vec![(
+                "foo".into(),
+                JsonNode::Object(vec![(
+                    "bar".into(),
+                    JsonNode::Array(vec![
+                        JsonNode::Int(1),
+                        JsonNode::Float(2.5),
+                        JsonNode::Bool,
+                        JsonNode::Null
+                    ])
+                )])
+            )])
+        )
+    }
+
+    #[test]
+    fn shares_keys_between_objects() {
+        let mut keys = KeyInterner::default();
+        let parse = |json, keys: &mut KeyInterner| {
+            parse_json_reporting(
+                json,
+                Leniency::default(),
+                Limits::default(),
+                keys,
+                &mut |_| Ok(()),
+            )
+            .unwrap()
+            .node
+        };
+        let first = parse(r#"[{"id": 1}, {"id": 2}]"#, &mut keys);
+        let second = parse(r#"{"id": 3}"#, &mut keys);
+
+        let key = |node: &JsonNode| match node {
+            JsonNode::Object(pairs) => Rc::clone(&pairs[0].0),
+            _ => unreachable!(),
+        };
+        let elements = match &first {
+            JsonNode::Array(elements) => elements,
+            _ => unreachable!(),
+        };
+        assert!(Rc::ptr_eq(&key(&elements[0]), &key(&elements[1])));
+        assert!(Rc::ptr_eq(&key(&elements[0]), &key(&second)));
+    }
+
+    #[test]
+    fn parses_multiline_documents() {
+        let result =
+            parse_json("{\n\t\"foo\": false,\r\n\t\"bar\": -0.5e-3,\n\t\"baz\": null\n}").unwrap();
+
+        assert_eq!(
+            result,
+            JsonNode::Object(vec![
+                ("foo".into(), JsonNode::Bool),
+                ("bar".into(), JsonNode::Float(-0.5e-3)),
+                ("baz".into(), JsonNode::Null)
+            ])
+        )
+    }
+
+    #[test]
+    fn decodes_escape_sequences() {
+        let result = parse_json(r#"["a\nbé😀"]"#).unwrap();
+
+        assert_eq!(
+            result,
+            JsonNode::Array(vec![JsonNode::String("a\nbé😀".into())])
+        )
+    }
+
+    #[test]
+    fn records_the_span_of_the_first_value_at_every_path() {
+        let (_, spans) = parse_json_with_spans(
+            "{\n  \"a\": [1, {\"b\": true}],\n  \"c\": -2.5\n}",
+            Leniency::default(),
+        )
+        .unwrap();
+        let span = |path: &str| {
+            let span = spans[path];
+            (
+                (span.start.line(), span.start.column()),
+                (span.end.line(), span.end.column()),
+            )
+        };
+
+        assert_eq!(spans.len(), 5);
+        assert_eq!(span(""), ((1, 1), (4, 2)));
+        assert_eq!(span("/a"), ((2, 8), (2, 24)));
+        assert_eq!(span("/a/*"), ((2, 9), (2, 10)));
+        assert_eq!(span("/a/*/b"), ((2, 18), (2, 22)));
+        assert_eq!(span("/c"), ((3, 8), (3, 12)));
+    }
+
+    #[test]
+    fn error_on_missing_colon() {
+        let result = parse_json(r#"{"foo" "bar"}"#);
+
+        assert_eq!(
+            result,
+            Err(Error::InvalidJson {
+                location: JsonTokenInfo::new(1, 8, 7),
+                message: "Unexpected token String(\"bar\")".to_string(),
+            })
+        )
+    }
+
+    #[test]
+    fn error_on_unclosed_array() {
+        let result = parse_json("[1, 2\n");
+
+        assert_eq!(
+            result,
+            Err(Error::UnexpectedEndOfInput {
+                expected: "',' or ']'".to_string(),
+                last_location: JsonTokenInfo::new(2, 1, 6),
+            })
+        )
+    }
+
+    #[test]
+    fn error_on_truncated_object() {
+        assert_eq!(
+            parse_json(r#"{"foo""#),
+            Err(Error::UnexpectedEndOfInput {
+                expected: "':'".to_string(),
+                last_location: JsonTokenInfo::new(1, 7, 6),
+            })
+        );
+        assert_eq!(
+            parse_json(r#"{"foo": "#),
+            Err(Error::UnexpectedEndOfInput {
+                expected: "a value".to_string(),
+                last_location: JsonTokenInfo::new(1, 9, 8),
+            })
+        );
+    }
+
+    #[test]
+    fn error_on_missing_and_extra_commas() {
+        let error = |column: i64, token: &str| {
+            Err(Error::InvalidJson {
+                location: JsonTokenInfo::new(1, column, column - 1),
+                message: format!("Unexpected token {}", token),
+            })
+        };
+
+        assert_eq!(parse_json("[1 2]"), error(4, "Int(2)"));
+        assert_eq!(parse_json(r#"{"a":1 "b":2}"#), error(8, "String(\"b\")"));
+        assert_eq!(parse_json("[,1]"), error(2, "Comma"));
+        assert_eq!(parse_json("[1,,2]"), error(4, "Comma"));
+        assert_eq!(parse_json("[1,]"), error(4, "ArrayEnd"));
+        assert_eq!(parse_json(r#"{"a":1,}"#), error(8, "ObjectEnd"));
+        assert_eq!(parse_json("1,"), error(2, "Comma"));
+    }
+
+    #[test]
+    fn relaxed_json_accepts_trailing_commas() {
+        let relaxed = Leniency {
+            relaxed: true,
+            ..Leniency::default()
+        };
+
+        assert_eq!(
+            parse_json_with("{a: [1, 2,],}", relaxed),
+            Ok(JsonNode::Object(vec![(
+                "a".into(),
+                JsonNode::Array(vec![JsonNode::Int(1), JsonNode::Int(2)])
+            )]))
+        );
+        assert!(parse_json_with("[1,,]", relaxed).is_err());
+        assert!(parse_json_with("[,]", relaxed).is_err());
+    }
+
+    #[test]
+    fn error_on_trailing_tokens() {
+        let result = parse_json(r#"{} []"#);
+
+        assert_eq!(
+            result,
+            Err(Error::InvalidJson {
+                location: JsonTokenInfo::new(1, 4, 3),
+                message: "Unexpected token ArrayStart".to_string(),
+            })
+        )
+    }
+
+    #[test]
+    fn error_on_nesting_deeper_than_the_default_limit() {
+        let json = format!("{}{}", "[".repeat(5000), "]".repeat(5000));
+
+        assert_eq!(
+            parse_json(&json),
+            Err(Error::TooDeep {
+                location: JsonTokenInfo::new(1, 129, 128),
+                limit: 128,
+            })
+        );
+        let json = format!("{}{}", "[".repeat(128), "]".repeat(128));
+        assert!(parse_json(&json).is_ok());
+    }
+
+    #[test]
+    fn extracts_every_value_at_a_path() {
+        let json = r#"{"items": [{"user": {"name": "Zoë"}}, {"user": null}, {"id": 3},
+                     {"user": {"name": "b", "tags": [1, 2]}}]}"#;
+
+        assert_eq!(
+            extract_samples(json, "/items/*/user").unwrap(),
+            vec![
+                r#"{"name": "Zoë"}"#,
+                "null",
+                r#"{"name": "b", "tags": [1, 2]}"#
+            ]
+        );
+        assert_eq!(
+            extract_samples(json, "$.items[*].user.tags").unwrap(),
+            vec!["[1, 2]"]
+        );
+        assert!(extract_samples(json, "/missing").unwrap().is_empty());
+        assert!(extract_samples("{", "/a").is_err());
+    }
+}
@@ -0,0 +1,334 @@
+use crate::options::ConvertOptions;
+use crate::parser::{parse_json, JsonNode};
+use crate::pointer::{child_path, element_path};
+use crate::stats::PathStats;
+use crate::type_mapping::apply_type_mappings;
+use crate::JsonStructure;
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+/// A transformation of the inferred structure, run after inference and before
+/// code is generated, such as mapping types or removing fields.
+///
+/// Passes run in the order of `ConvertOptions::pass_names`, starting with the
+/// built-in ones, and are added, reordered and removed by their name.
+pub trait StructurePass: Send + Sync {
+    fn name(&self) -> &str;
+    fn run(&self, structure: &mut JsonStructure, context: &mut PassContext);
+}
+
+/// What a pass can see of the conversion, and report back.
+pub struct PassContext<'a> {
+    pub(crate) options: &'a ConvertOptions,
+    pub(crate) removed_paths: Vec<String>,
+    pub(crate) stats: Vec<PathStats>,
+}
+
+impl<'a> PassContext<'a> {
+    pub(crate) fn new(options: &'a ConvertOptions, stats: Vec<PathStats>) -> PassContext<'a> {
+        PassContext {
+            options,
+            removed_paths: Vec::new(),
+            stats,
+        }
+    }
+
+    /// Records that the value at the json pointer `path` was removed, so its
+    /// stats and warnings are dropped too.
+    pub fn remove_path<S: Into<String>>(&mut self, path: S) {
+        let path = path.into();
+        if !self.removed_paths.contains(&path) {
+            self.removed_paths.push(path);
+        }
+    }
+
+    /// Whether the value at `path` is gone, having been removed itself or
+    /// along with one of its parents.
+    pub fn is_removed(&self, path: &str) -> bool {
+        self.removed_paths.iter().any(|removed| {
+            path.strip_prefix(removed.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+/// Applies the mappings of `ConvertOptions::add_type_mapping`.
+pub struct TypeMappingPass;
+
+impl StructurePass for TypeMappingPass {
+    fn name(&self) -> &str {
+        "type_mappings"
+    }
+
+    fn run(&self, structure: &mut JsonStructure, context: &mut PassContext) {
+        apply_type_mappings(structure, &context.options.type_mappings);
+    }
+}
+
+/// Removes the fields of `ConvertOptions::ignore_path` and
+/// `ConvertOptions::ignore_key`.
+pub struct IgnorePass;
+
+impl StructurePass for IgnorePass {
+    fn name(&self) -> &str {
+        "ignore"
+    }
+
+    fn run(&self, structure: &mut JsonStructure, context: &mut PassContext) {
+        for path in context.options.key_filter.apply(structure) {
+            context.remove_path(path);
+        }
+    }
+}
+
+/// Lifts the descriptions of `ConvertOptions::set_field_descriptions` out of
+/// the structure and into the stats of what they describe.
+pub struct DescriptionPass;
+
+/// Suffixes of the keys describing the key before the suffix.
+const DESCRIPTION_SUFFIXES: [&str; 2] = ["_description", "Description"];
+
+impl StructurePass for DescriptionPass {
+    fn name(&self) -> &str {
+        "descriptions"
+    }
+
+    fn run(&self, structure: &mut JsonStructure, context: &mut PassContext) {
+        if !context.options.field_descriptions {
+            return;
+        }
+        let mut descriptions = Vec::new();
+        lift_descriptions(structure, "", context, &mut descriptions);
+        for (path, description) in descriptions {
+            if let Some(stats) = context.stats.iter_mut().find(|s| s.path == path) {
+                stats.description.get_or_insert(description);
+            }
+        }
+    }
+}
+
+/// Removes the description keys of the objects in `structure`, which is at
+/// `path`, adding the path each describes and its description, read from the
+/// stats, to `descriptions`.
+fn lift_descriptions(
+    structure: &mut JsonStructure,
+    path: &str,
+    context: &mut PassContext,
+    descriptions: &mut Vec<(String, String)>,
+) {
+    match structure {
+        JsonStructure::Object(pairs) => {
+            let keys: Vec<String> = pairs.iter().map(|pair| pair.key().to_string()).collect();
+            pairs.retain(|pair| {
+                if *pair.value() != JsonStructure::string() {
+                    return true;
+                }
+                let described = match pair.key() {
+                    "description" if keys.len() > 1 => Some(path.to_string()),
+                    key => DESCRIPTION_SUFFIXES
+                        .iter()
+                        .filter_map(|suffix| key.strip_suffix(suffix))
+                        .find(|described| keys.iter().any(|key| key == described))
+                        .map(|described| child_path(path, described)),
+                };
+                let described = match described {
+                    Some(described) => described,
+                    None => return true,
+                };
+                let key_path = child_path(path, pair.key());
+                if let Some(description) = first_string(&context.stats, &key_path)
+                    .filter(|_| !context.options.redact_samples)
+                {
+                    descriptions.push((described, description));
+                }
+                context.remove_path(key_path);
+                false
+            });
+            for pair in pairs {
+                let path = child_path(path, pair.key());
+                lift_descriptions(pair.value_mut(), &path, context, descriptions);
+            }
+        }
+        JsonStructure::Array(inner) => {
+            lift_descriptions(inner, &element_path(path), context, descriptions)
+        }
+        JsonStructure::Optional(inner) => lift_descriptions(inner, path, context, descriptions),
+        JsonStructure::Union(variants) => {
+            for variant in variants {
+                lift_descriptions(variant, path, context, descriptions);
+            }
+        }
+        JsonStructure::Dictionary(_) | JsonStructure::Value(_) | JsonStructure::Unknown => {}
+    }
+}
+
+/// The first string observed at `path`, from its examples.
+fn first_string(stats: &[PathStats], path: &str) -> Option<String> {
+    let stats = stats.iter().find(|s| s.path == path)?;
+    match parse_json(stats.examples.first()?).ok()? {
+        JsonNode::String(s) if !s.trim().is_empty() => Some(s),
+        _ => None,
+    }
+}
+
+/// Removes union variants that are the same as an earlier one, as earlier
+/// passes can leave behind, replacing unions left with a single variant by
+/// that variant.
+pub struct DedupePass;
+
+fn dedupe(structure: &mut JsonStructure) {
+    match structure {
+        JsonStructure::Object(pairs) => {
+            for pair in pairs {
+                dedupe(pair.value_mut());
+            }
+        }
+        JsonStructure::Dictionary(pair) => dedupe(pair.value_mut()),
+        JsonStructure::Array(inner) | JsonStructure::Optional(inner) => dedupe(inner),
+        JsonStructure::Union(variants) => {
+            for variant in variants.iter_mut() {
+                dedupe(variant);
+            }
+            let mut unique: Vec<JsonStructure> = Vec::new();
+            for variant in variants.drain(..) {
+                if !unique.contains(&variant) {
+                    unique.push(variant);
+                }
+            }
+            *structure = match unique.len() {
+                1 => unique.remove(0),
+                _ => JsonStructure::Union(unique),
+            };
+        }
+        JsonStructure::Value(_) | JsonStructure::Unknown => {}
+    }
+}
+
+impl StructurePass for DedupePass {
+    fn name(&self) -> &str {
+        "dedupe"
+    }
+
+    fn run(&self, structure: &mut JsonStructure, _context: &mut PassContext) {
+        dedupe(structure);
+    }
+}
+
+impl<P: StructurePass + ?Sized> StructurePass for Box<P> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn run(&self, structure: &mut JsonStructure, context: &mut PassContext) {
+        (**self).run(structure, context)
+    }
+}
+
+pub(crate) fn default_passes() -> Vec<Box<dyn StructurePass>> {
+    vec![
+        Box::new(TypeMappingPass),
+        Box::new(IgnorePass),
+        Box::new(DescriptionPass),
+        Box::new(DedupePass),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert_sample_json, convert_sample_json_with_warnings, JsonPair};
+
+    struct Rename;
+
+    impl StructurePass for Rename {
+        fn name(&self) -> &str {
+            "rename"
+        }
+
+        fn run(&self, structure: &mut JsonStructure, context: &mut PassContext) {
+            if let JsonStructure::Object(pairs) = structure {
+                for pair in pairs.iter_mut().filter(|pair| pair.key() == "old") {
+                    *pair = JsonPair::new("new", pair.value().clone());
+                    context.remove_path("/old");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn removes_duplicate_union_variants() {
+        let mut structure = JsonStructure::Union(vec![
+            JsonStructure::int(),
+            JsonStructure::array(JsonStructure::Union(vec![
+                JsonStructure::string(),
+                JsonStructure::string(),
+            ])),
+            JsonStructure::int(),
+        ]);
+        dedupe(&mut structure);
+
+        assert_eq!(
+            structure,
+            JsonStructure::Union(vec![
+                JsonStructure::int(),
+                JsonStructure::array(JsonStructure::string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn runs_user_passes_in_order() {
+        let mut options = ConvertOptions::default();
+        options.add_pass(Rename).ignore_key("new");
+        assert_eq!(
+            options.pass_names(),
+            vec![
+                "type_mappings",
+                "ignore",
+                "descriptions",
+                "dedupe",
+                "rename"
+            ]
+        );
+        let conversion = convert_sample_json_with_warnings(r#"{"old": 1}"#, &options).unwrap();
+        assert_eq!(
+            conversion.structure,
+            convert_sample_json(r#"{"new": 1}"#).unwrap()
+        );
+        assert!(conversion.stats.iter().all(|s| s.path != "/old"));
+
+        // Moving the ignore pass after the rename removes the renamed field
+        let ignore = options.remove_pass("ignore").unwrap();
+        options.add_pass(ignore);
+        assert_eq!(
+            options.pass_names(),
+            vec![
+                "type_mappings",
+                "descriptions",
+                "dedupe",
+                "rename",
+                "ignore"
+            ]
+        );
+        let conversion = convert_sample_json_with_warnings(r#"{"old": 1}"#, &options).unwrap();
+        assert_eq!(conversion.structure, convert_sample_json("{}").unwrap());
+        assert!(options.remove_pass("missing").is_none());
+
+        options.insert_pass_before("type_mappings", DedupePass);
+        assert_eq!(
+            options.pass_names(),
+            vec![
+                "dedupe",
+                "type_mappings",
+                "descriptions",
+                "dedupe",
+                "rename",
+                "ignore"
+            ]
+        );
+    }
+}
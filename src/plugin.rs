@@ -0,0 +1,280 @@
+//! Detectors and codegen backends run as separate programs, so organizations
+//! can use their own type mapping rules or output formats without forking.
+//!
+//! A plugin is a program, usually named `json2rust-plugin-<name>` and found on
+//! the `PATH`, that reads requests from stdin and writes a response to each on
+//! stdout, both as json documents on a single line. It is started on the first
+//! request and runs until its stdin is closed. The requests are
+//!
+//! - `{"method": "detect", "field": "created", "values": ["2021-04-01"]}`, with
+//!   every string seen for a field, answered with `{"type": "chrono::NaiveDate"}`
+//!   or `{"type": null}` to leave the field to the other detectors
+//! - `{"method": "generate", "root_name": "Root", "structure": {...}}`, with the
+//!   inferred structure as described by `conversion_to_json`, answered with
+//!   `{"artifacts": [{"file_name": "root.ts", "contents": "..."}]}`
+//!
+//! Any response may instead be `{"error": "..."}`. Plugins compiled to WASI
+//! modules aren't supported, since running them would need a wasm runtime.
+
+use crate::backend::{Artifact, CodegenBackend};
+use crate::detectors::Detector;
+use crate::ir_output::structure_to_json;
+use crate::json_writer::JsonOutput;
+use crate::options::ConvertOptions;
+use crate::parser::{parse_json, JsonNode};
+use crate::{JsonStructure, RustType};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// A plugin program, used as a [`Detector`] or a [`CodegenBackend`].
+///
+/// Clones share the running program, so a clone can be registered as a
+/// detector while the original is kept to read the [`errors`](Plugin::errors)
+/// afterwards, since detectors and backends can't return them.
+#[derive(Clone)]
+pub struct Plugin {
+    program: String,
+    args: Vec<String>,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    running: Option<Running>,
+    // Whether the program couldn't be started or stopped answering, after
+    // which it isn't sent any more requests
+    failed: bool,
+    errors: Vec<String>,
+}
+
+struct Running {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        // Closing stdin tells the plugin to exit
+        self.stdin.take();
+        let _ = self.child.wait();
+    }
+}
+
+impl Plugin {
+    /// The plugin `json2rust-plugin-<name>` on the `PATH`.
+    pub fn named(name: &str) -> Plugin {
+        Plugin::new(format!("json2rust-plugin-{}", name), Vec::new())
+    }
+
+    /// A plugin run as `program` with `args`.
+    pub fn new<S: Into<String>>(program: S, args: Vec<String>) -> Plugin {
+        Plugin {
+            program: program.into(),
+            args,
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// What went wrong talking to the plugin so far, such as errors it
+    /// responded with or the program not being found.
+    pub fn errors(&self) -> Vec<String> {
+        self.lock().errors.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        // A panic while the lock was held leaves nothing half updated that
+        // matters more than the errors
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sends `request` and returns the response, or `None` after recording why
+    /// there is none.
+    fn request(&self, request: &JsonOutput) -> Option<JsonNode> {
+        let mut state = self.lock();
+        if state.failed {
+            return None;
+        }
+        match self.exchange(&mut state, request) {
+            Ok(response) => match response.get("error") {
+                Some(error) => {
+                    let message = error.as_str().unwrap_or("unknown error").to_string();
+                    state
+                        .errors
+                        .push(format!("plugin {}: {}", self.program, message));
+                    None
+                }
+                None => Some(response),
+            },
+            Err(message) => {
+                state.failed = true;
+                state.running = None;
+                state
+                    .errors
+                    .push(format!("plugin {}: {}", self.program, message));
+                None
+            }
+        }
+    }
+
+    fn exchange(&self, state: &mut State, request: &JsonOutput) -> Result<JsonNode, String> {
+        if state.running.is_none() {
+            let mut child = Command::new(&self.program)
+                .args(&self.args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("failed to start: {}", e))?;
+            let stdin = child.stdin.take();
+            let stdout = child.stdout.take().map(BufReader::new);
+            state.running = Some(Running {
+                child,
+                stdin,
+                stdout: stdout.ok_or("failed to read its output")?,
+            });
+        }
+        let running = state
+            .running
+            .as_mut()
+            .expect("the plugin was started above");
+        let stdin = running.stdin.as_mut().ok_or("failed to write to it")?;
+        writeln!(stdin, "{}", request.to_compact_string())
+            .and_then(|_| stdin.flush())
+            .map_err(|e| format!("failed to send a request: {}", e))?;
+        let mut line = String::new();
+        match running.stdout.read_line(&mut line) {
+            Ok(0) => Err("exited without responding".to_string()),
+            Ok(_) => parse_json(&line).map_err(|e| format!("invalid response: {}", e)),
+            Err(e) => Err(format!("failed to read a response: {}", e)),
+        }
+    }
+}
+
+impl Detector for Plugin {
+    fn detect(&self, field_name: &str, values: &[&str]) -> Option<RustType> {
+        let request = JsonOutput::object(vec![
+            ("method", JsonOutput::string("detect")),
+            ("field", JsonOutput::string(field_name)),
+            (
+                "values",
+                JsonOutput::Array(values.iter().map(|v| JsonOutput::string(*v)).collect()),
+            ),
+        ]);
+        let response = self.request(&request)?;
+        response
+            .get("type")
+            .and_then(JsonNode::as_str)
+            .map(RustType::new)
+    }
+}
+
+impl CodegenBackend for Plugin {
+    fn generate(
+        &self,
+        structure: &JsonStructure,
+        root_name: &str,
+        _options: &ConvertOptions,
+    ) -> Vec<Artifact> {
+        let request = JsonOutput::object(vec![
+            ("method", JsonOutput::string("generate")),
+            ("root_name", JsonOutput::string(root_name)),
+            ("structure", structure_to_json(structure)),
+        ]);
+        let artifacts = match self.request(&request) {
+            Some(JsonNode::Object(pairs)) => pairs
+                .into_iter()
+                .find(|(key, _)| &**key == "artifacts")
+                .map(|(_, artifacts)| artifacts),
+            _ => return Vec::new(),
+        };
+        let artifacts = match artifacts {
+            Some(JsonNode::Array(artifacts)) => artifacts,
+            _ => {
+                self.lock().errors.push(format!(
+                    "plugin {}: expected an array of artifacts",
+                    self.program
+                ));
+                return Vec::new();
+            }
+        };
+        artifacts
+            .iter()
+            .filter_map(|artifact| {
+                let file_name = artifact.get("file_name").and_then(JsonNode::as_str);
+                let contents = artifact.get("contents").and_then(JsonNode::as_str);
+                match (file_name, contents) {
+                    (Some(file_name), Some(contents)) => Some(Artifact::new(file_name, contents)),
+                    _ => {
+                        self.lock().errors.push(format!(
+                            "plugin {}: artifacts need a file_name and contents",
+                            self.program
+                        ));
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json_with_options;
+
+    /// A plugin written in shell, answering every request with `response`.
+    fn shell_plugin(response: &str) -> Plugin {
+        Plugin::new(
+            "sh",
+            vec![
+                "-c".to_string(),
+                format!("while read -r line; do echo '{}'; done", response),
+            ],
+        )
+    }
+
+    #[test]
+    fn detects_types_with_a_plugin() {
+        let plugin = shell_plugin(r#"{"type": "acme::Id"}"#);
+        let mut options = ConvertOptions::default();
+        options.register_detector(plugin.clone());
+        let structure =
+            convert_sample_json_with_options(r#"{"id": "a-1", "owner": "b-2"}"#, &options).unwrap();
+
+        assert_eq!(
+            structure.fields().unwrap()[0].value(),
+            &JsonStructure::Value(crate::JsonValue::Custom(RustType::new("acme::Id")))
+        );
+        assert!(plugin.errors().is_empty());
+    }
+
+    #[test]
+    fn generates_artifacts_with_a_plugin() {
+        let plugin = shell_plugin(
+            r#"{"artifacts": [{"file_name": "root.ts", "contents": "type Root = {}"}]}"#,
+        );
+        let structure = crate::convert_sample_json(r#"{"id": 1}"#).unwrap();
+
+        assert_eq!(
+            plugin.generate(&structure, "Root", &ConvertOptions::default()),
+            vec![Artifact::new("root.ts", "type Root = {}")]
+        );
+    }
+
+    #[test]
+    fn records_errors_of_plugins() {
+        let failing = shell_plugin(r#"{"error": "no rules for this field"}"#);
+        assert_eq!(failing.detect("id", &["a"]), None);
+        assert_eq!(
+            failing.errors(),
+            vec!["plugin sh: no rules for this field".to_string()]
+        );
+
+        let missing = Plugin::named("that-does-not-exist");
+        assert_eq!(missing.detect("id", &["a"]), None);
+        assert_eq!(missing.detect("name", &["b"]), None);
+        assert_eq!(missing.errors().len(), 1);
+        assert!(missing.errors()[0].contains("failed to start"));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::JsonStructure;
+use alloc::{format, string::String, vec::Vec};
+
+/// Appends a key to a json pointer, escaping it as described in RFC 6901.
+pub fn child_path(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Appends the element marker of an array to a json pointer.
+pub fn element_path(path: &str) -> String {
+    format!("{}/*", path)
+}
+
+fn unescape_segment(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+impl JsonStructure {
+    /// Looks up the structure at a json pointer, such as `/items/0/user`.
+    ///
+    /// Array indices all point to the element structure, and `*` can be used in
+    /// place of an index. Optional values are looked through, and for unions the
+    /// first variant containing the path is used.
+    pub fn at(&self, pointer: &str) -> Option<&JsonStructure> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        let rest = pointer.strip_prefix('/')?;
+        let segments: Vec<String> = rest.split('/').map(unescape_segment).collect();
+        self.at_segments(&segments)
+    }
+
+    fn at_segments(&self, segments: &[String]) -> Option<&JsonStructure> {
+        let (segment, rest) = match segments.split_first() {
+            Some(split) => split,
+            None => return Some(self),
+        };
+        match self {
+            JsonStructure::Optional(inner) => inner.at_segments(segments),
+            JsonStructure::Union(variants) => variants.iter().find_map(|v| v.at_segments(segments)),
+            JsonStructure::Object(_) => self.field(segment)?.at_segments(rest),
+            JsonStructure::Dictionary(pair) => pair.value().at_segments(rest),
+            JsonStructure::Array(inner)
+                if segment == "*" || segment.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                inner.at_segments(rest)
+            }
+            _ => None,
+        }
+    }
+
+    /// Every path ending in a value, along with the structure of that value.
+    /// Array elements and dictionary keys are written as `*` in the paths.
+    pub fn leaf_paths(&self) -> Vec<(String, &JsonStructure)> {
+        let mut leaves = Vec::new();
+        self.collect_leaves(String::new(), &mut leaves);
+        leaves
+    }
+
+    fn collect_leaves<'a>(&'a self, path: String, leaves: &mut Vec<(String, &'a JsonStructure)>) {
+        match self {
+            JsonStructure::Object(pairs) => {
+                for pair in pairs {
+                    pair.value()
+                        .collect_leaves(child_path(&path, pair.key()), leaves);
+                }
+            }
+            JsonStructure::Array(inner) => inner.collect_leaves(element_path(&path), leaves),
+            JsonStructure::Dictionary(pair) => {
+                pair.value().collect_leaves(element_path(&path), leaves)
+            }
+            JsonStructure::Union(variants) => {
+                for variant in variants {
+                    variant.collect_leaves(path.clone(), leaves);
+                }
+            }
+            JsonStructure::Optional(inner) => match **inner {
+                JsonStructure::Value(_) | JsonStructure::Unknown => leaves.push((path, self)),
+                ref inner => inner.collect_leaves(path, leaves),
+            },
+            JsonStructure::Value(_) | JsonStructure::Unknown => leaves.push((path, self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    fn sample() -> JsonStructure {
+        convert_sample_json(
+            r#"{"items": [{"user": {"name": "x", "a/b": 1}}, {"user": null}], "tags": {"": 1}}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn escapes_pointer_segments() {
+        assert_eq!(child_path("/a", "b/c~d"), "/a/b~1c~0d");
+        assert_eq!(element_path(""), "/*");
+    }
+
+    #[test]
+    fn looks_up_json_pointers() {
+        let structure = sample();
+
+        assert_eq!(structure.at(""), Some(&structure));
+        assert_eq!(
+            structure.at("/items/0/user/name"),
+            Some(&JsonStructure::string())
+        );
+        assert_eq!(
+            structure.at("/items/*/user/a~1b"),
+            Some(&JsonStructure::int())
+        );
+        assert_eq!(structure.at("/tags/anything"), Some(&JsonStructure::int()));
+        assert!(structure.at("/items/3/user").unwrap().is_optional());
+    }
+
+    #[test]
+    fn returns_none_for_missing_paths() {
+        let structure = sample();
+
+        assert_eq!(structure.at("/missing"), None);
+        assert_eq!(structure.at("/items/user"), None);
+        assert_eq!(structure.at("items"), None);
+    }
+
+    #[test]
+    fn enumerates_leaf_paths() {
+        let structure = sample();
+        let leaves: Vec<(String, &JsonStructure)> = structure.leaf_paths();
+
+        assert_eq!(
+            leaves,
+            vec![
+                ("/items/*/user/name".to_string(), &JsonStructure::string()),
+                ("/items/*/user/a~1b".to_string(), &JsonStructure::int()),
+                ("/tags/*".to_string(), &JsonStructure::int()),
+            ]
+        )
+    }
+}
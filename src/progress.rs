@@ -0,0 +1,16 @@
+/// How far a conversion has come, passed to the callback of
+/// `ConvertOptions::set_progress_callback` during the slow parts of it.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Progress {
+    /// `bytes` of a sample of `total` bytes were tokenized, reported every
+    /// megabyte and once the sample is done
+    Tokenized { bytes: usize, total: usize },
+    /// `samples` of the `total` samples given were parsed
+    Parsed { samples: usize, total: usize },
+    /// `samples` were compared by their shape, with
+    /// `ConvertOptions::set_dedupe_samples`, `unique` of them having a shape
+    /// not seen before
+    Deduped { samples: usize, unique: usize },
+    /// The structures of `samples` samples were merged into one
+    Merged { samples: usize },
+}
@@ -0,0 +1,272 @@
+use crate::backend::{Artifact, CodegenBackend};
+use crate::codegen::union_variant_names;
+use crate::inference::U64;
+use crate::naming::{to_snake_case, transliterate};
+use crate::options::ConvertOptions;
+use crate::symbols::SymbolTable;
+use crate::{JsonPair, JsonStructure, JsonValue};
+
+/// Well known type used for values protobuf can't describe, such as arrays of
+/// arrays and values of an unknown type.
+const ANY_VALUE: &str = "google.protobuf.Value";
+
+struct ProtoGenerator<'a> {
+    symbols: &'a SymbolTable,
+    messages: Vec<String>,
+    defined: Vec<String>,
+    path: Vec<String>,
+    uses_well_known_types: bool,
+}
+
+impl<'a> ProtoGenerator<'a> {
+    /// The type of a field holding `structure`, along with its label.
+    fn field_type(&mut self, structure: &JsonStructure) -> (&'static str, String) {
+        match structure {
+            JsonStructure::Array(inner) => match inner.non_optional() {
+                // Fields can't be repeated twice, or be repeated maps
+                JsonStructure::Array(_) | JsonStructure::Dictionary(_) => {
+                    ("repeated ", self.any_value())
+                }
+                element => ("repeated ", self.element_type(element)),
+            },
+            JsonStructure::Dictionary(pair) => {
+                let value_type = match pair.value().non_optional() {
+                    JsonStructure::Array(_) | JsonStructure::Dictionary(_) => self.any_value(),
+                    value => self.element_type(value),
+                };
+                ("", format!("map<string, {}>", value_type))
+            }
+            JsonStructure::Optional(inner) => match inner.as_ref() {
+                JsonStructure::Array(_) | JsonStructure::Dictionary(_) => self.field_type(inner),
+                // Value already has a null of its own
+                JsonStructure::Unknown => ("", self.any_value()),
+                other => ("optional ", self.element_type(other)),
+            },
+            other => ("", self.element_type(other)),
+        }
+    }
+
+    /// The type of a single value, which can't be repeated or a map.
+    fn element_type(&mut self, structure: &JsonStructure) -> String {
+        match structure {
+            JsonStructure::Object(pairs) => self.emit_message(pairs),
+            JsonStructure::Union(variants) => self.emit_oneof(variants),
+            JsonStructure::Value(JsonValue::String) => "string".to_string(),
+            JsonStructure::Value(JsonValue::Int) => "int64".to_string(),
+            JsonStructure::Value(JsonValue::Float) => "double".to_string(),
+            JsonStructure::Value(JsonValue::Bool) => "bool".to_string(),
+            JsonStructure::Value(JsonValue::Custom(rust_type)) if rust_type.path() == U64 => {
+                "uint64".to_string()
+            }
+            // Other custom rust types are usually serialized as strings, such
+            // as ip addresses and timestamps, and protobuf has no 128 bit
+            // integers
+            JsonStructure::Value(JsonValue::Custom(_)) => "string".to_string(),
+            JsonStructure::Optional(inner) => self.element_type(inner),
+            JsonStructure::Array(_) | JsonStructure::Dictionary(_) | JsonStructure::Unknown => {
+                self.any_value()
+            }
+        }
+    }
+
+    fn any_value(&mut self) -> String {
+        self.uses_well_known_types = true;
+        ANY_VALUE.to_string()
+    }
+
+    fn reserve_definition(&mut self) -> (String, Option<usize>) {
+        let name = self.symbols.name_at(&self.path).to_string();
+        if self.defined.contains(&name) {
+            return (name, None);
+        }
+        self.defined.push(name.clone());
+        self.messages.push(String::new());
+        (name, Some(self.messages.len() - 1))
+    }
+
+    fn emit_message(&mut self, pairs: &[JsonPair]) -> String {
+        let (message_name, index) = match self.reserve_definition() {
+            (name, Some(index)) => (name, index),
+            (name, None) => return name,
+        };
+
+        let mut body = format!("message {} {{\n", message_name);
+        for (number, pair) in pairs.iter().enumerate() {
+            let field = proto_field_name(pair.key(), number);
+            self.path.push(pair.key().to_string());
+            let (label, field_type) = self.field_type(pair.value());
+            self.path.pop();
+            body.push_str(&format!(
+                "  {}{} {} = {}",
+                label,
+                field_type,
+                field,
+                number + 1
+            ));
+            if field != pair.key() {
+                body.push_str(&format!(" [json_name = \"{}\"]", pair.key().escape_debug()));
+            }
+            body.push_str(";\n");
+        }
+        body.push_str("}\n");
+
+        self.messages[index] = body;
+        message_name
+    }
+
+    /// Unions become a message with a `oneof`, as a `oneof` can't be repeated.
+    fn emit_oneof(&mut self, variants: &[JsonStructure]) -> String {
+        let (message_name, index) = match self.reserve_definition() {
+            (name, Some(index)) => (name, index),
+            (name, None) => return name,
+        };
+
+        let mut body = format!("message {} {{\n  oneof value {{\n", message_name);
+        let variant_names = union_variant_names(variants);
+        for (number, (variant, variant_name)) in variants.iter().zip(variant_names).enumerate() {
+            self.path.push(variant_name.clone());
+            let variant_type = self.element_type(variant);
+            self.path.pop();
+            body.push_str(&format!(
+                "    {} {}_value = {};\n",
+                variant_type,
+                to_snake_case(&variant_name),
+                number + 1
+            ));
+        }
+        body.push_str("  }\n}\n");
+
+        self.messages[index] = body;
+        message_name
+    }
+}
+
+/// Field names are snake case, which is what protobuf style guides use. The
+/// original key is kept with `json_name` when it differs.
+fn proto_field_name(key: &str, index: usize) -> String {
+    match to_snake_case(&transliterate(key)) {
+        name if name.is_empty() => format!("field_{}", index + 1),
+        name if name.starts_with(|c: char| c.is_ascii_digit()) => format!("field_{}", name),
+        name => name,
+    }
+}
+
+/// Emits proto3 message definitions for the structure, as a single
+/// `<root_name>.proto` file. Messages are named the same way as the rust
+/// types would be.
+pub struct ProtobufBackend;
+
+impl CodegenBackend for ProtobufBackend {
+    fn generate(
+        &self,
+        structure: &JsonStructure,
+        root_name: &str,
+        options: &ConvertOptions,
+    ) -> Vec<Artifact> {
+        // Protobuf messages can't be arrays, so like the rust types, arrays at
+        // the root produce a message for their elements
+        let mut root = structure.non_optional();
+        while let JsonStructure::Array(inner) = root {
+            root = inner.non_optional();
+        }
+
+        let symbols = SymbolTable::build(
+            root,
+            root_name,
+            options.naming_strategy.as_ref(),
+            &options.abbreviations,
+            options.acronyms,
+        );
+        let mut generator = ProtoGenerator {
+            symbols: &symbols,
+            messages: Vec::new(),
+            defined: Vec::new(),
+            path: Vec::new(),
+            uses_well_known_types: false,
+        };
+        if let JsonStructure::Object(_) | JsonStructure::Union(_) = root {
+            generator.element_type(root);
+        }
+
+        let mut output = "syntax = \"proto3\";\n".to_string();
+        if generator.uses_well_known_types {
+            output.push_str("\nimport \"google/protobuf/struct.proto\";\n");
+        }
+        for message in generator.messages {
+            output.push('\n');
+            output.push_str(&message);
+        }
+        vec![Artifact::new(
+            format!("{}.proto", to_snake_case(root_name)),
+            output,
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    fn generate(json: &str) -> String {
+        let structure = convert_sample_json(json).unwrap();
+        let artifacts = ProtobufBackend.generate(&structure, "Order", &ConvertOptions::default());
+        assert_eq!(artifacts[0].file_name, "order.proto");
+        artifacts[0].contents.clone()
+    }
+
+    #[test]
+    fn generates_messages() {
+        let result = generate(
+            r#"{"id": 1, "userName": "a", "items": [{"sku": "x", "price": 1.5}, {"sku": "y", "price": null}],
+                "tags": ["a"], "active": true}"#,
+        );
+
+        assert_eq!(
+            result,
+            r#"syntax = "proto3";
+
+message Order {
+  int64 id = 1;
+  string user_name = 2 [json_name = "userName"];
+  repeated Item items = 3;
+  repeated string tags = 4;
+  bool active = 5;
+}
+
+message Item {
+  string sku = 1;
+  optional double price = 2;
+}
+"#
+        )
+    }
+
+    #[test]
+    fn generates_maps_oneofs_and_well_known_types() {
+        let result =
+            generate(r#"[{"scores": {"": 1}, "value": [1, "a"], "grid": [[1]], "meta": null}]"#);
+
+        assert_eq!(
+            result,
+            r#"syntax = "proto3";
+
+import "google/protobuf/struct.proto";
+
+message Order {
+  map<string, int64> scores = 1;
+  repeated Value value = 2;
+  repeated google.protobuf.Value grid = 3;
+  google.protobuf.Value meta = 4;
+}
+
+message Value {
+  oneof value {
+    int64 integer_value = 1;
+    string string_value = 2;
+  }
+}
+"#
+        )
+    }
+}
@@ -0,0 +1,199 @@
+use crate::codegen::conversion_to_rust;
+use crate::config::Config;
+use crate::options::ConvertOptions;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "Enter a json sample, ending it with a blank line, to print its types.
+
+Commands:
+  :set name <NAME>             Name of the root type [default: Root]
+  :set <TABLE>.<KEY> <VALUE>   A setting of json2rust.toml, such as
+                               `:set codegen.validation true` or
+                               `:set codegen.type_attributes [\"#[derive(Hash)]\"]`
+  :unset <TABLE>.<KEY>         Goes back to the default of a setting
+  :show                        Lists the settings
+  :help                        Prints this help
+  :quit                        Exits
+";
+
+/// The settings changed with `:set`, which last for the whole session.
+struct Session {
+    root_name: String,
+    // Settings of the config file as `(table, key, value)`, in the order they
+    // were set
+    settings: Vec<(String, String, String)>,
+}
+
+impl Session {
+    /// The config file the settings make up.
+    fn config_text(&self) -> String {
+        self.settings
+            .iter()
+            .map(|(table, key, value)| format!("[{}]\n{} = {}\n", table, key, value))
+            .collect()
+    }
+
+    fn options(&self) -> ConvertOptions {
+        let mut options = ConvertOptions::default();
+        // Every setting was checked when it was set
+        if let Ok(config) = Config::parse(&self.config_text()) {
+            config.apply(&mut options);
+        }
+        options
+    }
+
+    /// Runs a `:` command, returning what to print, or `None` to exit.
+    fn command(&mut self, command: &str) -> Option<String> {
+        let mut words = command.splitn(2, char::is_whitespace);
+        let name = words.next().unwrap_or_default();
+        let rest = words.next().unwrap_or_default().trim();
+        Some(match name {
+            ":quit" | ":q" => return None,
+            ":help" => HELP.to_string(),
+            ":show" => format!("name = \"{}\"\n{}", self.root_name, self.config_text()),
+            ":set" => self.set(rest),
+            ":unset" => match rest.split_once('.') {
+                Some((table, key)) => {
+                    self.settings.retain(|(t, k, _)| t != table || k != key);
+                    String::new()
+                }
+                None if rest == "name" => {
+                    self.root_name = "Root".to_string();
+                    String::new()
+                }
+                None => format!(
+                    "Expected a setting such as codegen.validation, got '{}'\n",
+                    rest
+                ),
+            },
+            other => format!("Unknown command '{}', see :help\n", other),
+        })
+    }
+
+    fn set(&mut self, setting: &str) -> String {
+        let (setting, value) = match setting.split_once(char::is_whitespace) {
+            Some((setting, value)) => (setting, value.trim()),
+            None => return "Expected a setting and a value, such as :set name User\n".into(),
+        };
+        if setting == "name" {
+            self.root_name = value.trim_matches('"').to_string();
+            return String::new();
+        }
+        let (table, key) = match setting.split_once('.') {
+            Some(parts) => parts,
+            None => {
+                return format!(
+                    "Expected a setting such as codegen.validation, got '{}'\n",
+                    setting
+                )
+            }
+        };
+        let text = format!("[{}]\n{} = {}\n", table, key, value);
+        if let Err(error) = Config::parse(&text) {
+            return format!("{}\n", error.message);
+        }
+        self.settings.retain(|(t, k, _)| t != table || k != key);
+        self.settings
+            .push((table.to_string(), key.to_string(), value.to_string()));
+        String::new()
+    }
+
+    /// The types generated for a sample, or why they couldn't be.
+    fn generate(&self, sample: &str) -> String {
+        let options = self.options();
+        let conversion = match crate::convert_sample_json_with_warnings(sample, &options) {
+            Ok(conversion) => conversion,
+            Err(error) => {
+                return match error.suggestion(sample) {
+                    Some(suggestion) => {
+                        format!("Invalid json: {}\nhint: {}\n", error, suggestion)
+                    }
+                    None => format!("Invalid json: {}\n", error),
+                }
+            }
+        };
+        let mut output = String::new();
+        for warning in &conversion.warnings {
+            output.push_str(&format!("warning: {}\n", warning));
+        }
+        output.push_str(&conversion_to_rust(&conversion, &self.root_name, &options));
+        output
+    }
+}
+
+/// Reads json samples from `input`, each ending with a blank line, and writes
+/// the types generated for them to `output`, until `:quit` or the end of
+/// `input`. Lines starting with `:` are commands changing the options, such as
+/// `:set codegen.validation true`, see `:help`.
+pub fn run_repl<R: BufRead, W: Write>(input: R, mut output: W) -> io::Result<()> {
+    let mut session = Session {
+        root_name: "Root".to_string(),
+        settings: Vec::new(),
+    };
+    let mut sample = String::new();
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        if sample.is_empty() && line.trim_start().starts_with(':') {
+            match session.command(line.trim()) {
+                Some(message) => write!(output, "{}", message)?,
+                None => return Ok(()),
+            }
+        } else if line.trim().is_empty() {
+            if !sample.is_empty() {
+                write!(output, "{}", session.generate(&sample))?;
+                sample.clear();
+            }
+        } else {
+            sample.push_str(&line);
+            sample.push('\n');
+        }
+        write!(output, "{}", if sample.is_empty() { "> " } else { ". " })?;
+        output.flush()?;
+    }
+    // The last sample may end with the input instead of a blank line
+    if !sample.is_empty() {
+        write!(output, "{}", session.generate(&sample))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &str) -> String {
+        let mut output = Vec::new();
+        run_repl(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn generates_types_for_each_sample() {
+        let output = run(":set name User\n{\"id\": 1,\n \"name\": \"a\"}\n\n{\"ok\": true}\n");
+
+        assert!(output.contains("pub struct User {\n    pub id: i64,\n    pub name: String,\n}"));
+        assert!(output.contains(". "));
+        assert!(output.ends_with("pub struct User {\n    pub ok: bool,\n}\n"));
+    }
+
+    #[test]
+    fn keeps_settings_until_they_are_unset() {
+        let output = run(":set codegen.ordering_derives true\n{\"id\": 1}\n\n\
+             :show\n:unset codegen.ordering_derives\n{\"id\": 1}\n\n:quit\n{\"id\": 1}\n\n");
+
+        assert_eq!(output.matches("Eq, PartialOrd, Ord").count(), 1);
+        assert!(output.contains("name = \"Root\"\n[codegen]\nordering_derives = true\n"));
+        assert_eq!(output.matches("pub struct Root").count(), 2);
+    }
+
+    #[test]
+    fn reports_invalid_settings_and_samples() {
+        let output = run(":set codegen.validation 1\n:set nope\n{\"id\": }\n\n");
+
+        assert!(output.contains("> 'validation' must be true or false\n"));
+        assert!(output.contains("Expected a setting and a value"));
+        assert!(output.contains("Invalid json: "));
+    }
+}
@@ -0,0 +1,503 @@
+//! Reports on the quality of samples, listing every path with its inferred
+//! type, how often it was present and null, how confident the inference is, and
+//! a few example values.
+
+use crate::json_writer::JsonOutput;
+use crate::pointer::{child_path, element_path};
+use crate::stats::PathStats;
+use crate::{Conversion, JsonPair, JsonStructure, JsonValue};
+
+/// The formats `conversion_report` can write.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ReportFormat {
+    /// An aligned table, for terminals
+    Text,
+    /// A standalone page, with the presence, null rate and confidence cells
+    /// colored from red to green
+    Html,
+    Json,
+}
+
+struct Row<'a> {
+    path: &'a str,
+    type_name: String,
+    stats: &'a PathStats,
+}
+
+fn type_name(structure: &JsonStructure) -> String {
+    match structure {
+        JsonStructure::Object(_) => "object".to_string(),
+        JsonStructure::Array(inner) => format!("array<{}>", type_name(inner)),
+        JsonStructure::Dictionary(pair) => format!("map<{}>", type_name(pair.value())),
+        JsonStructure::Optional(inner) => format!("optional<{}>", type_name(inner)),
+        JsonStructure::Union(variants) => variants
+            .iter()
+            .map(type_name)
+            .collect::<Vec<_>>()
+            .join(" | "),
+        JsonStructure::Value(JsonValue::String) => "string".to_string(),
+        JsonStructure::Value(JsonValue::Float) => "float".to_string(),
+        JsonStructure::Value(JsonValue::Int) => "int".to_string(),
+        JsonStructure::Value(JsonValue::Bool) => "bool".to_string(),
+        JsonStructure::Value(JsonValue::Custom(rust_type)) => rust_type.path().to_string(),
+        JsonStructure::Unknown => "unknown".to_string(),
+    }
+}
+
+/// A row for every path, in the order they were first visited.
+fn rows(conversion: &Conversion) -> Vec<Row<'_>> {
+    let mut rows: Vec<Row> = Vec::new();
+    for stats in &conversion.stats {
+        if rows.iter().any(|row| row.path == stats.path) {
+            continue;
+        }
+        rows.push(Row {
+            path: &stats.path,
+            type_name: conversion
+                .structure
+                .at(&stats.path)
+                .map(type_name)
+                .unwrap_or_else(|| "unknown".to_string()),
+            stats,
+        });
+    }
+    rows
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+fn percent(fraction: f64) -> String {
+    format!("{:.0}%", fraction * 100.0)
+}
+
+/// Writes a report of every path in a conversion, with its type, presence,
+/// null rate and example values, followed by the warnings.
+pub fn conversion_report(conversion: &Conversion, format: ReportFormat) -> String {
+    let rows = rows(conversion);
+    match format {
+        ReportFormat::Text => text_report(&rows, conversion),
+        ReportFormat::Html => html_report(&rows, conversion),
+        ReportFormat::Json => json_report(&rows, conversion),
+    }
+}
+
+fn text_report(rows: &[Row], conversion: &Conversion) -> String {
+    let mut table = vec![[
+        "PATH".to_string(),
+        "TYPE".to_string(),
+        "PRESENCE".to_string(),
+        "NULLS".to_string(),
+        "CONFIDENCE".to_string(),
+        "EXAMPLES".to_string(),
+    ]];
+    for row in rows {
+        table.push([
+            display_path(row.path).to_string(),
+            row.type_name.clone(),
+            percent(row.stats.presence()),
+            percent(row.stats.null_rate()),
+            percent(row.stats.confidence()),
+            row.stats.examples.join(", "),
+        ]);
+    }
+    let mut widths = [0; 6];
+    for cells in &table {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = String::new();
+    for cells in &table {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        output.push_str(line.join("  ").trim_end());
+        output.push('\n');
+    }
+    for warning in &conversion.warnings {
+        output.push_str(&format!("warning: {}\n", warning));
+    }
+    output
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A cell colored from red, for 0, to green, for 1.
+fn heat_cell(text: &str, goodness: f64) -> String {
+    format!(
+        "<td style=\"background: hsl({:.0}, 70%, 80%)\">{}</td>",
+        goodness * 120.0,
+        text
+    )
+}
+
+fn html_report(rows: &[Row], conversion: &Conversion) -> String {
+    let mut output = String::from(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>json2rust report</title>
+<style>
+table { border-collapse: collapse; font-family: sans-serif; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }
+</style>
+</head>
+<body>
+<table>
+<tr><th>Path</th><th>Type</th><th>Presence</th><th>Nulls</th><th>Confidence</th><th>Examples</th></tr>
+",
+    );
+    for row in rows {
+        output.push_str(&format!(
+            "<tr><td><code>{}</code></td><td>{}</td>{}{}{}<td>{}</td></tr>\n",
+            escape_html(display_path(row.path)),
+            escape_html(&row.type_name),
+            heat_cell(&percent(row.stats.presence()), row.stats.presence()),
+            heat_cell(&percent(row.stats.null_rate()), 1.0 - row.stats.null_rate()),
+            heat_cell(&percent(row.stats.confidence()), row.stats.confidence()),
+            escape_html(&row.stats.examples.join(", ")),
+        ));
+    }
+    output.push_str("</table>\n");
+    if !conversion.warnings.is_empty() {
+        output.push_str("<ul>\n");
+        for warning in &conversion.warnings {
+            output.push_str(&format!("<li>{}</li>\n", escape_html(&warning.to_string())));
+        }
+        output.push_str("</ul>\n");
+    }
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+fn json_report(rows: &[Row], conversion: &Conversion) -> String {
+    let paths = rows
+        .iter()
+        .map(|row| {
+            JsonOutput::object(vec![
+                ("path", JsonOutput::string(row.path)),
+                ("type", JsonOutput::string(row.type_name.as_str())),
+                ("count", JsonOutput::Int(row.stats.count as i64)),
+                ("presence", JsonOutput::Float(row.stats.presence())),
+                ("null_rate", JsonOutput::Float(row.stats.null_rate())),
+                ("confidence", JsonOutput::Float(row.stats.confidence())),
+                (
+                    "examples",
+                    JsonOutput::Array(
+                        row.stats
+                            .examples
+                            .iter()
+                            .map(|e| JsonOutput::string(e.as_str()))
+                            .collect(),
+                    ),
+                ),
+            ])
+        })
+        .collect();
+    let warnings = conversion
+        .warnings
+        .iter()
+        .map(|w| {
+            JsonOutput::object(vec![
+                ("path", JsonOutput::string(w.path.as_str())),
+                ("message", JsonOutput::string(w.message.as_str())),
+            ])
+        })
+        .collect();
+    let mut output = JsonOutput::object(vec![
+        ("paths", JsonOutput::Array(paths)),
+        ("warnings", JsonOutput::Array(warnings)),
+    ])
+    .to_pretty_string();
+    output.push('\n');
+    output
+}
+
+/// The objects with the same fields, found at every `(sample, path)` location.
+struct Shape<'a> {
+    fields: &'a [JsonPair],
+    locations: Vec<(&'a str, String)>,
+}
+
+impl Shape<'_> {
+    fn samples(&self) -> Vec<&str> {
+        let mut samples: Vec<&str> = self.locations.iter().map(|(sample, _)| *sample).collect();
+        samples.dedup();
+        samples
+    }
+}
+
+fn collect_shapes<'a>(
+    structure: &'a JsonStructure,
+    sample: &'a str,
+    path: String,
+    shapes: &mut Vec<Shape<'a>>,
+) {
+    match structure {
+        JsonStructure::Object(fields) => {
+            match shapes.iter_mut().find(|shape| shape.fields == &fields[..]) {
+                Some(shape) => shape.locations.push((sample, path.clone())),
+                None => shapes.push(Shape {
+                    fields,
+                    locations: vec![(sample, path.clone())],
+                }),
+            }
+            for pair in fields {
+                collect_shapes(pair.value(), sample, child_path(&path, pair.key()), shapes);
+            }
+        }
+        JsonStructure::Array(inner) => collect_shapes(inner, sample, element_path(&path), shapes),
+        JsonStructure::Dictionary(pair) => {
+            collect_shapes(pair.value(), sample, element_path(&path), shapes)
+        }
+        JsonStructure::Optional(inner) => collect_shapes(inner, sample, path, shapes),
+        JsonStructure::Union(variants) => {
+            for variant in variants {
+                collect_shapes(variant, sample, path.clone(), shapes);
+            }
+        }
+        JsonStructure::Value(_) | JsonStructure::Unknown => {}
+    }
+}
+
+/// The only field two objects disagree on, by being missing from one of them
+/// or having different types, if there is exactly one.
+fn only_difference<'a>(a: &'a [JsonPair], b: &'a [JsonPair]) -> Option<&'a str> {
+    let mut differences = a
+        .iter()
+        .filter(|pair| !b.contains(pair))
+        .chain(
+            b.iter()
+                .filter(|pair| !a.iter().any(|p| p.key() == pair.key())),
+        )
+        .map(JsonPair::key);
+    let difference = differences.next()?;
+    match differences.next() {
+        None => Some(difference),
+        Some(_) => None,
+    }
+}
+
+fn display_location((sample, path): &(&str, String)) -> String {
+    format!("{}{}", sample, display_path(path))
+}
+
+/// Writes a report on the objects found in several named samples, such as the
+/// responses of the endpoints of an API, for consolidating the generated types.
+/// It lists the shapes found in more than one sample, which
+/// `convert_named_samples` generates a single type for if they have the same
+/// name, and the near misses, objects of different samples that only differ
+/// by one field.
+pub fn shared_types_report(samples: &[(&str, &JsonStructure)], format: ReportFormat) -> String {
+    let mut shapes = Vec::new();
+    for (sample, structure) in samples {
+        collect_shapes(structure, sample, String::new(), &mut shapes);
+    }
+    let shared: Vec<&Shape> = shapes
+        .iter()
+        .filter(|shape| !shape.fields.is_empty() && shape.samples().len() > 1)
+        .collect();
+    let mut near_misses: Vec<(&str, &Shape, &Shape)> = Vec::new();
+    for (i, a) in shapes.iter().enumerate() {
+        for b in &shapes[i + 1..] {
+            if a.fields.len() < 2 || b.fields.len() < 2 {
+                continue;
+            }
+            let mut samples = a.samples();
+            samples.extend(b.samples());
+            samples.sort_unstable();
+            samples.dedup();
+            if samples.len() < 2 {
+                continue;
+            }
+            if let Some(field) = only_difference(a.fields, b.fields) {
+                near_misses.push((field, a, b));
+            }
+        }
+    }
+
+    let field_names = |shape: &Shape| -> Vec<String> {
+        shape.fields.iter().map(|p| p.key().to_string()).collect()
+    };
+    let locations = |shapes: &[&Shape]| -> Vec<String> {
+        shapes
+            .iter()
+            .flat_map(|shape| shape.locations.iter().map(display_location))
+            .collect()
+    };
+    match format {
+        ReportFormat::Text => {
+            let mut output = String::new();
+            for shape in &shared {
+                output.push_str(&format!(
+                    "shared: {{{}}} at {}\n",
+                    field_names(shape).join(", "),
+                    locations(&[shape]).join(", ")
+                ));
+            }
+            for (field, a, b) in &near_misses {
+                output.push_str(&format!(
+                    "near miss: only {} differs at {}\n",
+                    field,
+                    locations(&[a, b]).join(", ")
+                ));
+            }
+            output
+        }
+        ReportFormat::Html => {
+            let mut output = String::from(
+                "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>json2rust shared types</title>
+</head>
+<body>
+<table>
+<tr><th>Kind</th><th>Fields</th><th>Locations</th></tr>
+",
+            );
+            for shape in &shared {
+                output.push_str(&format!(
+                    "<tr><td>shared</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(&field_names(shape).join(", ")),
+                    escape_html(&locations(&[shape]).join(", "))
+                ));
+            }
+            for (field, a, b) in &near_misses {
+                output.push_str(&format!(
+                    "<tr><td>near miss</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(field),
+                    escape_html(&locations(&[a, b]).join(", "))
+                ));
+            }
+            output.push_str("</table>\n</body>\n</html>\n");
+            output
+        }
+        ReportFormat::Json => {
+            let strings = |strings: Vec<String>| {
+                JsonOutput::Array(strings.into_iter().map(JsonOutput::string).collect())
+            };
+            let shared = shared
+                .iter()
+                .map(|shape| {
+                    JsonOutput::object(vec![
+                        ("fields", strings(field_names(shape))),
+                        ("locations", strings(locations(&[shape]))),
+                    ])
+                })
+                .collect();
+            let near_misses = near_misses
+                .iter()
+                .map(|(field, a, b)| {
+                    JsonOutput::object(vec![
+                        ("field", JsonOutput::string(*field)),
+                        ("locations", strings(locations(&[a, b]))),
+                    ])
+                })
+                .collect();
+            let mut output = JsonOutput::object(vec![
+                ("shared", JsonOutput::Array(shared)),
+                ("near_misses", JsonOutput::Array(near_misses)),
+            ])
+            .to_pretty_string();
+            output.push('\n');
+            output
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert_samples_json_with_warnings, ConvertOptions};
+
+    fn conversion() -> Conversion {
+        convert_samples_json_with_warnings(
+            &[
+                r#"{"id": 1, "name": "a", "tags": ["x"]}"#,
+                r#"{"id": 2, "name": null}"#,
+                r#"{"id": 3, "name": "<b>"}"#,
+            ],
+            &ConvertOptions::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn writes_a_text_table() {
+        assert_eq!(
+            conversion_report(&conversion(), ReportFormat::Text),
+            "PATH     TYPE                     PRESENCE  NULLS  CONFIDENCE  EXAMPLES
+/        object                   100%      0%     50%
+/id      int                      100%      0%     50%         1, 2, 3
+/name    optional<string>         100%      33%    40%         \"a\", \"<b>\"
+/tags    optional<array<string>>  33%       0%     25%
+/tags/*  string                   100%      0%     25%         \"x\"
+"
+        );
+    }
+
+    #[test]
+    fn writes_html_with_heat_cells() {
+        let html = conversion_report(&conversion(), ReportFormat::Html);
+
+        assert!(html.contains(
+            "<tr><td><code>/tags</code></td><td>optional&lt;array&lt;string&gt;&gt;</td>\
+             <td style=\"background: hsl(40, 70%, 80%)\">33%</td>"
+        ));
+        assert!(html.contains("<td>&quot;a&quot;, &quot;&lt;b&gt;&quot;</td>"));
+    }
+
+    #[test]
+    fn writes_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&conversion_report(&conversion(), ReportFormat::Json)).unwrap();
+
+        assert_eq!(json["paths"][2]["path"], "/name");
+        assert_eq!(json["paths"][2]["examples"][1], "\"<b>\"");
+        assert_eq!(json["paths"][2]["confidence"], 0.4);
+        assert_eq!(json["warnings"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn reports_shapes_shared_between_samples() {
+        let orders = crate::convert_sample_json(
+            r#"[{"id": 1, "owner": {"id": 2, "name": "a"}, "author": {"id": 3, "name": "b", "email": "c"}}]"#,
+        )
+        .unwrap();
+        let repository =
+            crate::convert_sample_json(r#"{"stars": 3, "owner": {"id": 4, "name": "d"}}"#).unwrap();
+        let samples = [("Orders", &orders), ("Repository", &repository)];
+
+        assert_eq!(
+            shared_types_report(&samples, ReportFormat::Text),
+            "shared: {id, name} at Orders/*/owner, Repository/owner
+near miss: only email differs at Orders/*/owner, Repository/owner, Orders/*/author
+"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&shared_types_report(&samples, ReportFormat::Json)).unwrap();
+        assert_eq!(
+            json["shared"][0]["fields"],
+            serde_json::json!(["id", "name"])
+        );
+        assert_eq!(json["near_misses"][0]["field"], "email");
+    }
+}
@@ -0,0 +1,409 @@
+//! Editing json documents in place, keeping everything that isn't edited, such
+//! as whitespace, the order of members and how numbers are written, as it was.
+//! This is what tools sanitizing samples need, such as stripping secrets from
+//! responses before committing them as fixtures.
+
+use crate::json_writer::JsonOutput;
+use crate::parser::parse_json;
+use crate::pointer::{child_path, element_path};
+use crate::shared::Error;
+use crate::tokenizer::{
+    tokenize_json_reporting, JsonToken, JsonTokenType, KeyInterner, Leniency, Limits,
+};
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::ops::Range;
+
+/// A change to a json document. Pointers use `*` for array elements, so that
+/// an edit applies to every element, and an edit whose pointer matches nothing
+/// does nothing.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Edit {
+    /// Renames the members at `pointer` to `key`
+    RenameKey { pointer: String, key: String },
+    /// Replaces the values at `pointer` with the json `value`
+    ReplaceValue { pointer: String, value: String },
+    /// Deletes the members at `pointer`, along with their commas
+    DeleteMember { pointer: String },
+    /// Adds a member with `key` and the json `value` to the end of the objects
+    /// at `pointer`, formatted like their last member
+    InsertMember {
+        pointer: String,
+        key: String,
+        value: String,
+    },
+}
+
+/// Applies `edits` to the json document `json`. Edits inside a value that is
+/// deleted or replaced by another edit are dropped along with it.
+///
+/// ```
+/// use json2rust::{rewrite, Edit};
+///
+/// let json = "{\n  \"user\": \"a\",\n  \"token\": \"secret\"\n}";
+/// let edits = [
+///     Edit::DeleteMember { pointer: "/token".into() },
+///     Edit::InsertMember {
+///         pointer: "".into(),
+///         key: "id".into(),
+///         value: "1".into(),
+///     },
+/// ];
+/// assert_eq!(
+///     rewrite(json, &edits).unwrap(),
+///     "{\n  \"user\": \"a\",\n  \"id\": 1\n}"
+/// );
+/// ```
+pub fn rewrite(json: &str, edits: &[Edit]) -> Result<String, Error> {
+    // Invalid documents and values are reported as they would be converting them
+    parse_json(json)?;
+    for edit in edits {
+        if let Edit::ReplaceValue { value, .. } | Edit::InsertMember { value, .. } = edit {
+            parse_json(value)?;
+        }
+    }
+    let tokens = tokenize_json_reporting(
+        json,
+        Leniency::default(),
+        Limits::default(),
+        &mut KeyInterner::default(),
+        &mut |_| Ok(()),
+    )?;
+    let document = Document::read(json, &tokens);
+
+    let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+    for edit in edits {
+        match edit {
+            Edit::RenameKey { pointer, key } => {
+                let key = JsonOutput::string(key.as_str()).to_compact_string();
+                for object in &document.objects {
+                    for member in object.members.iter().filter(|m| m.path == *pointer) {
+                        replacements.push((member.key.clone(), key.clone()));
+                    }
+                }
+            }
+            Edit::ReplaceValue { pointer, value } => {
+                for (_, range) in document.values.iter().filter(|(path, _)| path == pointer) {
+                    replacements.push((range.clone(), value.clone()));
+                }
+            }
+            // Deletions are done per object, below, as where a member's comma
+            // is depends on which of the members around it are deleted too
+            Edit::DeleteMember { .. } | Edit::InsertMember { .. } => {}
+        }
+    }
+    for object in &document.objects {
+        let deleted: Vec<bool> = object
+            .members
+            .iter()
+            .map(|member| {
+                edits.iter().any(|edit| {
+                    matches!(edit, Edit::DeleteMember { pointer } if *pointer == member.path)
+                })
+            })
+            .collect();
+        replacements.extend(object.deletions(&deleted));
+        for edit in edits {
+            if let Edit::InsertMember {
+                pointer,
+                key,
+                value,
+            } = edit
+            {
+                if *pointer == object.path {
+                    replacements.push(object.insertion(json, &deleted, key, value));
+                }
+            }
+        }
+    }
+
+    // Sorting is stable, so insertions at the same place stay in order
+    replacements.sort_by_key(|(range, _)| (range.start, range.end));
+    let mut output = String::with_capacity(json.len());
+    let mut position = 0;
+    for (range, text) in replacements {
+        if range.start < position {
+            continue;
+        }
+        output.push_str(&json[position..range.start]);
+        output.push_str(&text);
+        position = range.end;
+    }
+    output.push_str(&json[position..]);
+    Ok(output)
+}
+
+/// Where the values, objects and members of a document are, in bytes.
+struct Document {
+    values: Vec<(String, Range<usize>)>,
+    objects: Vec<Object>,
+}
+
+struct Object {
+    path: String,
+    /// Right after the `{`
+    open: usize,
+    /// At the `}`
+    close: usize,
+    members: Vec<Member>,
+}
+
+struct Member {
+    path: String,
+    key: Range<usize>,
+    value: Range<usize>,
+}
+
+impl Document {
+    /// Reads where everything is in `json`, which has been parsed already.
+    fn read(json: &str, tokens: &[JsonToken]) -> Document {
+        // Spans are in characters
+        let offsets: Vec<usize> = json
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(core::iter::once(json.len()))
+            .collect();
+        let mut reader = Reader {
+            tokens,
+            offsets,
+            position: 0,
+            document: Document {
+                values: Vec::new(),
+                objects: Vec::new(),
+            },
+        };
+        reader.value("");
+        reader.document
+    }
+}
+
+struct Reader<'t> {
+    tokens: &'t [JsonToken],
+    offsets: Vec<usize>,
+    position: usize,
+    document: Document,
+}
+
+impl Reader<'_> {
+    fn next(&mut self) -> Range<usize> {
+        let token = &self.tokens[self.position];
+        self.position += 1;
+        self.offsets[token.span.start.char() as usize]..self.offsets[token.span.end.char() as usize]
+    }
+
+    fn peek_type(&self) -> &JsonTokenType {
+        &self.tokens[self.position].token_type
+    }
+
+    /// Moves past the comma after a member or element, if it isn't the last.
+    fn skip_comma(&mut self) {
+        if *self.peek_type() == JsonTokenType::Comma {
+            self.position += 1;
+        }
+    }
+
+    fn value(&mut self, path: &str) -> Range<usize> {
+        let token_type = self.peek_type().clone();
+        let first = self.next();
+        let end = match token_type {
+            JsonTokenType::ObjectStart => {
+                let mut object = Object {
+                    path: path.to_string(),
+                    open: first.end,
+                    close: first.end,
+                    members: Vec::new(),
+                };
+                while let JsonTokenType::Key(key_name) = self.peek_type() {
+                    let key_name = key_name.clone();
+                    let key = self.next();
+                    // The colon
+                    self.next();
+                    let member_path = child_path(path, &key_name);
+                    let value = self.value(&member_path);
+                    object.members.push(Member {
+                        path: member_path,
+                        key,
+                        value,
+                    });
+                    self.skip_comma();
+                }
+                let last = self.next();
+                object.close = last.start;
+                self.document.objects.push(object);
+                last.end
+            }
+            JsonTokenType::ArrayStart => {
+                let element = element_path(path);
+                while *self.peek_type() != JsonTokenType::ArrayEnd {
+                    self.value(&element);
+                    self.skip_comma();
+                }
+                self.next().end
+            }
+            _ => first.end,
+        };
+        let range = first.start..end;
+        self.document.values.push((path.to_string(), range.clone()));
+        range
+    }
+}
+
+impl Object {
+    /// What to remove to delete the members that are `deleted`, so that the
+    /// members left are still separated like they were.
+    fn deletions(&self, deleted: &[bool]) -> Vec<(Range<usize>, String)> {
+        if !deleted.contains(&true) {
+            return Vec::new();
+        }
+        if !deleted.contains(&false) {
+            return vec![(self.open..self.close, String::new())];
+        }
+        let last_kept = deleted.iter().rposition(|deleted| !deleted).unwrap_or(0);
+        (0..self.members.len())
+            .filter(|&index| deleted[index])
+            .map(|index| {
+                let member = &self.members[index];
+                let range = if index < last_kept {
+                    // Up to the next member, taking the comma after it
+                    member.key.start..self.members[index + 1].key.start
+                } else {
+                    // From the member before, taking the comma before it
+                    self.members[index - 1].value.end..member.value.end
+                };
+                (range, String::new())
+            })
+            .collect()
+    }
+
+    /// Where and what to insert to add a member after the last member that
+    /// isn't `deleted`.
+    fn insertion(
+        &self,
+        json: &str,
+        deleted: &[bool],
+        key: &str,
+        value: &str,
+    ) -> (Range<usize>, String) {
+        let key = JsonOutput::string(key).to_compact_string();
+        let last_kept = deleted.iter().rposition(|deleted| !deleted);
+        match last_kept {
+            Some(index) => {
+                let member = &self.members[index];
+                let before = match index {
+                    0 => self.open,
+                    _ => self.members[index - 1].value.end,
+                };
+                let indent = json[before..member.key.start].trim_start_matches([',', ' ']);
+                let indent = match indent {
+                    "" => " ",
+                    indent => indent,
+                };
+                let separator = &json[member.key.end..member.value.start];
+                let at = member.value.end;
+                (at..at, [",", indent, &key, separator, value].concat())
+            }
+            None => (self.open..self.open, [key.as_str(), ": ", value].concat()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delete(pointer: &str) -> Edit {
+        Edit::DeleteMember {
+            pointer: pointer.into(),
+        }
+    }
+
+    #[test]
+    fn deletes_members_with_their_commas() {
+        let json = r#"{"a": 1, "b": {"c": [1, 2]}, "d": 3}"#;
+
+        assert_eq!(
+            rewrite(json, &[delete("/a")]).unwrap(),
+            r#"{"b": {"c": [1, 2]}, "d": 3}"#
+        );
+        assert_eq!(
+            rewrite(json, &[delete("/b")]).unwrap(),
+            r#"{"a": 1, "d": 3}"#
+        );
+        assert_eq!(
+            rewrite(json, &[delete("/d")]).unwrap(),
+            r#"{"a": 1, "b": {"c": [1, 2]}}"#
+        );
+        assert_eq!(
+            rewrite(json, &[delete("/b"), delete("/d")]).unwrap(),
+            r#"{"a": 1}"#
+        );
+        assert_eq!(
+            rewrite(json, &[delete("/a"), delete("/b"), delete("/d")]).unwrap(),
+            "{}"
+        );
+        assert_eq!(
+            rewrite(json, &[delete("/b/c"), delete("/x")]).unwrap(),
+            r#"{"a": 1, "b": {}, "d": 3}"#
+        );
+    }
+
+    #[test]
+    fn edits_every_element_of_arrays() {
+        let json = "[\n  {\"name\": \"a\", \"password\": \"x\"},\n  {\"password\": \"y\"}\n]";
+        let edits = [
+            Edit::ReplaceValue {
+                pointer: "/*/password".into(),
+                value: "\"redacted\"".into(),
+            },
+            Edit::RenameKey {
+                pointer: "/*/name".into(),
+                key: "user \"name\"".into(),
+            },
+        ];
+
+        assert_eq!(
+            rewrite(json, &edits).unwrap(),
+            "[\n  {\"user \\\"name\\\"\": \"a\", \"password\": \"redacted\"},\n  \
+             {\"password\": \"redacted\"}\n]"
+        );
+    }
+
+    #[test]
+    fn inserts_members_formatted_like_the_last_member() {
+        let insert = |pointer: &str| Edit::InsertMember {
+            pointer: pointer.into(),
+            key: "new".into(),
+            value: "[true]".into(),
+        };
+        let json = "{\n    \"a\": 1,\n    \"b\": {},\n    \"c\": {\"d\": 2}\n}";
+
+        assert_eq!(
+            rewrite(
+                json,
+                &[insert(""), insert("/b"), insert("/c"), delete("/c")]
+            )
+            .unwrap(),
+            "{\n    \"a\": 1,\n    \"b\": {\"new\": [true]},\n    \"new\": [true]\n}"
+        );
+        assert_eq!(
+            rewrite(json, &[insert("/c")]).unwrap(),
+            "{\n    \"a\": 1,\n    \"b\": {},\n    \"c\": {\"d\": 2, \"new\": [true]}\n}"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_documents_and_values() {
+        assert!(matches!(
+            rewrite("{\"a\": ", &[]),
+            Err(Error::UnexpectedEndOfInput { .. })
+        ));
+        let edit = Edit::ReplaceValue {
+            pointer: "/a".into(),
+            value: "nope".into(),
+        };
+        assert!(rewrite("{\"a\": 1}", &[edit]).is_err());
+    }
+}
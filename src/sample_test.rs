@@ -0,0 +1,115 @@
+use crate::codegen::{array_element_name, root_type};
+use crate::options::ConvertOptions;
+use crate::JsonStructure;
+
+/// Quotes `text` as a raw string literal, with enough `#`s that nothing in the
+/// text ends it. Raw strings can't hold carriage returns, so text with them is
+/// escaped instead.
+fn raw_string(text: &str) -> String {
+    if text.contains('\r') {
+        return format!("{:?}", text);
+    }
+    let mut hashes = 1;
+    while text.contains(&format!("\"{}", "#".repeat(hashes))) {
+        hashes += 1;
+    }
+    let hashes = "#".repeat(hashes);
+    format!("r{}\"{}\"{}", hashes, text, hashes)
+}
+
+/// Generates a test module to append to the types generated for `sample`,
+/// checking that the sample deserializes into them. With
+/// `ConvertOptions::set_json_schemas`, it also checks the sample against the
+/// schema `schemars` produces for them, using the `jsonschema` crate.
+///
+/// This catches types that don't match their own sample, such as from a bug in
+/// the generator or a type mapping that is too narrow.
+pub fn generate_sample_tests(
+    sample: &str,
+    structure: &JsonStructure,
+    root_name: &str,
+    options: &ConvertOptions,
+) -> String {
+    let element_name = array_element_name(structure, root_name, options);
+    let root_type = root_type(structure, element_name.as_deref().unwrap_or(root_name));
+    let mut output = format!(
+        "
+#[cfg(test)]
+mod tests {{
+    use super::*;
+
+    const SAMPLE: &str = {};
+
+    #[test]
+    fn sample_deserializes() {{
+        serde_json::from_str::<{}>(SAMPLE).unwrap();
+    }}
+",
+        raw_string(sample.trim()),
+        root_type
+    );
+    if options.json_schemas {
+        output.push_str(&format!(
+            "
+    #[test]
+    fn sample_matches_the_schema() {{
+        let sample: serde_json::Value = serde_json::from_str(SAMPLE).unwrap();
+        let schema = serde_json::to_value(schemars::schema_for!({})).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let errors: Vec<String> = validator.iter_errors(&sample).map(|e| e.to_string()).collect();
+        assert!(errors.is_empty(), \"{{:?}}\", errors);
+    }}
+",
+            root_type
+        ));
+    }
+    output.push_str("}\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    #[test]
+    fn quotes_samples_as_raw_strings() {
+        assert_eq!(raw_string(r#"{"a": 1}"#), r##"r#"{"a": 1}"#"##);
+        assert_eq!(raw_string(r##"{"a": "#"}"##), r###"r##"{"a": "#"}"##"###);
+        assert_eq!(raw_string("{\r\n\"a\": 1}"), r#""{\r\n\"a\": 1}""#);
+    }
+
+    #[test]
+    fn generates_tests_against_the_sample() {
+        let sample = r#"[{"id": 1}]"#;
+        let structure = convert_sample_json(sample).unwrap();
+        let mut options = ConvertOptions::default();
+        options.set_json_schemas(true);
+
+        assert_eq!(
+            generate_sample_tests(sample, &structure, "Item", &options),
+            r##"
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"[{"id": 1}]"#;
+
+    #[test]
+    fn sample_deserializes() {
+        serde_json::from_str::<Vec<Item>>(SAMPLE).unwrap();
+    }
+
+    #[test]
+    fn sample_matches_the_schema() {
+        let sample: serde_json::Value = serde_json::from_str(SAMPLE).unwrap();
+        let schema = serde_json::to_value(schemars::schema_for!(Vec<Item>)).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let errors: Vec<String> = validator.iter_errors(&sample).map(|e| e.to_string()).collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+}
+"##
+        );
+    }
+}
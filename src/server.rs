@@ -0,0 +1,468 @@
+//! An HTTP endpoint converting samples, run by `json2rust serve`, so tools such
+//! as developer portals can offer conversion without running the command line
+//! tool themselves.
+//!
+//! `POST /convert` takes a json object with the `sample` to convert as a
+//! string, and optionally the `name` of the root type and a `config` in the
+//! format of `json2rust.toml`. It responds with `{"ok": true, "code", "warnings"}`
+//! or, when the request or the sample is invalid, `{"ok": false, "error"}`.
+//!
+//! The `[limits]` of the server's config bound every request, whatever its own
+//! config says: samples longer than `max_input_bytes`, and request bodies
+//! longer than twice that, are rejected with a 413, and samples nested deeper
+//! than `max_depth` or taking longer than `time_limit_ms` to infer with a 422.
+//! Limits the config doesn't set default to a megabyte, 128 levels and ten
+//! seconds. Connections are closed when a read or write stalls for ten
+//! seconds, and beyond 64 at once new ones are turned away with a 503.
+
+use crate::converter::Converter;
+use crate::json_writer::JsonOutput;
+use crate::parser::{parse_json_reporting, JsonNode, KeyInterner};
+use crate::shared::Error;
+use crate::tokenizer::{Leniency, Limits};
+use crate::{Config, ConvertOptions};
+use std::cell::Cell;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The longest sample of a server whose config doesn't set `max_input_bytes`.
+const DEFAULT_MAX_INPUT_BYTES: usize = 1 << 20;
+
+/// The time a conversion may take on a server whose config doesn't set
+/// `time_limit_ms`.
+const DEFAULT_TIME_LIMIT_MS: usize = 10_000;
+
+/// How long reading or writing a connection may stall before it is closed.
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most connections handled at once, each on a thread of its own.
+const MAX_CONNECTIONS: usize = 64;
+
+/// The longest request or header line read.
+const MAX_LINE_BYTES: u64 = 8 * 1024;
+
+thread_local! {
+    // When the conversion running on this thread has to stop, which converters
+    // shared between threads can't hold themselves
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// The status and body of a response.
+type Response = (u16, JsonOutput);
+
+/// The settings requests are converted with.
+struct Server {
+    config: Config,
+    // Built once from `config`, for the requests without a config of their own
+    converter: Converter,
+}
+
+/// Serves conversions on `listener` with the options of `config`, which the
+/// `config` of a request is applied on top of, handling every connection on a
+/// thread of its own. Only returns if accepting connections fails.
+pub fn run_server(listener: TcpListener, config: Config) -> io::Result<()> {
+    let server = Arc::new(Server::new(config));
+    let connections = Arc::new(AtomicUsize::new(0));
+    loop {
+        let (stream, _) = listener.accept()?;
+        // A client that went away, or stalls, has nothing to be told
+        if stream.set_read_timeout(Some(SOCKET_TIMEOUT)).is_err()
+            || stream.set_write_timeout(Some(SOCKET_TIMEOUT)).is_err()
+        {
+            continue;
+        }
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            let busy = (503, error("busy", "Too many connections, try again later"));
+            let _ = write_response(&stream, busy);
+            continue;
+        }
+        let server = Arc::clone(&server);
+        let connections = Arc::clone(&connections);
+        thread::spawn(move || {
+            let _ = handle_connection(BufReader::new(&stream), &stream, &server);
+            connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+impl Server {
+    fn new(mut config: Config) -> Server {
+        config
+            .max_input_bytes
+            .get_or_insert(DEFAULT_MAX_INPUT_BYTES);
+        config.time_limit_ms.get_or_insert(DEFAULT_TIME_LIMIT_MS);
+        let converter = Converter::new(Server::options(&config, None));
+        Server { config, converter }
+    }
+
+    /// The options of a request, with its own config applied over the
+    /// server's, except for the limits.
+    fn options(config: &Config, request_config: Option<&Config>) -> ConvertOptions {
+        let mut options = ConvertOptions::default();
+        config.apply(&mut options);
+        if let Some(request_config) = request_config {
+            request_config.apply(&mut options);
+        }
+        options
+            .set_max_input_bytes(config.max_input_bytes)
+            .set_max_depth(config.max_depth)
+            .set_cancellation(|| {
+                DEADLINE.with(|deadline| deadline.get().is_some_and(|d| Instant::now() >= d))
+            });
+        options
+    }
+
+    fn time_limit(&self) -> Option<Duration> {
+        self.config
+            .time_limit_ms
+            .map(|ms| Duration::from_millis(ms as u64))
+    }
+
+    /// The longest body read, room for a sample of `max_input_bytes` even with
+    /// every character escaped.
+    fn max_body(&self) -> Option<usize> {
+        self.config.max_input_bytes.map(|max| max.saturating_mul(2))
+    }
+}
+
+/// Reads a single request from `input` and writes the response to `output`.
+fn handle_connection<R: BufRead, W: Write>(
+    mut input: R,
+    output: W,
+    server: &Server,
+) -> io::Result<()> {
+    let response = match read_request(&mut input, server.max_body())? {
+        Ok((method, path, body)) => respond(server, &method, &path, &body),
+        Err(response) => response,
+    };
+    write_response(output, response)
+}
+
+fn write_response<W: Write>(mut output: W, (status, body): Response) -> io::Result<()> {
+    let body = body.to_pretty_string();
+    write!(
+        output,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    output.flush()
+}
+
+/// The method, path and body of a request, or the response to a request that
+/// isn't valid HTTP or has a body longer than `max_body`, which isn't read.
+fn read_request<R: BufRead>(
+    input: &mut R,
+    max_body: Option<usize>,
+) -> io::Result<Result<(String, String, String), Response>> {
+    let malformed = || (400, error("invalid_request", "Malformed HTTP request"));
+    let mut request_line = String::new();
+    read_line(input, &mut request_line)?;
+    let mut words = request_line.split_whitespace();
+    let (method, path) = match (words.next(), words.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Ok(Err(malformed())),
+    };
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        if read_line(input, &mut line)? == 0 {
+            return Ok(Err(malformed()));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                match value.trim().parse() {
+                    Ok(value) => length = value,
+                    Err(_) => return Ok(Err(malformed())),
+                }
+            }
+        }
+    }
+    if let Some(max_body) = max_body.filter(|max_body| length > *max_body) {
+        let message = format!("The body is longer than {} bytes", max_body);
+        return Ok(Err((413, error("input_too_large", &message))));
+    }
+    let mut body = Vec::new();
+    input.take(length as u64).read_to_end(&mut body)?;
+    Ok(match String::from_utf8(body) {
+        Ok(body) => Ok((method, path, body)),
+        Err(_) => Err(malformed()),
+    })
+}
+
+/// Reads a line of at most `MAX_LINE_BYTES`, so a client can't make the server
+/// buffer a header without end.
+fn read_line<R: BufRead>(input: &mut R, line: &mut String) -> io::Result<usize> {
+    input.take(MAX_LINE_BYTES).read_line(line)
+}
+
+/// The request as a json object, parsed within the limits of `server` so the
+/// body can't nest deep enough to overflow the stack.
+fn parse_request(server: &Server, body: &str) -> Option<JsonNode> {
+    let limits = Limits {
+        max_input_bytes: server.max_body(),
+        max_depth: server.config.max_depth,
+    };
+    let parsed = parse_json_reporting(
+        body,
+        Leniency::default(),
+        limits,
+        &mut KeyInterner::default(),
+        &mut |_| Ok(()),
+    );
+    match parsed {
+        Ok(parsed) if matches!(parsed.node, JsonNode::Object(_)) => Some(parsed.node),
+        _ => None,
+    }
+}
+
+/// The response to a request.
+fn respond(server: &Server, method: &str, path: &str, body: &str) -> Response {
+    if path != "/convert" {
+        return (
+            404,
+            error("not_found", "The only endpoint is POST /convert"),
+        );
+    }
+    if method != "POST" {
+        return (
+            405,
+            error("method_not_allowed", "/convert only accepts POST"),
+        );
+    }
+    let request = match parse_request(server, body) {
+        Some(request) => request,
+        None => {
+            return (
+                400,
+                error("invalid_request", "The body must be a json object"),
+            )
+        }
+    };
+    let sample = match request.get("sample").and_then(JsonNode::as_str) {
+        Some(sample) => sample,
+        None => {
+            return (
+                400,
+                error("invalid_request", "Missing the sample, as a string"),
+            )
+        }
+    };
+    let name = request
+        .get("name")
+        .and_then(JsonNode::as_str)
+        .unwrap_or("Root");
+    // Only requests with their own config need a converter of their own
+    let configured;
+    let converter = match request.get("config").and_then(JsonNode::as_str) {
+        Some(config) => match Config::parse(config) {
+            Ok(config) => {
+                configured = Converter::new(Server::options(&server.config, Some(&config)));
+                &configured
+            }
+            Err(e) => return (400, error("invalid_config", &e.to_string())),
+        },
+        None => &server.converter,
+    };
+
+    let time_limit = server.time_limit();
+    DEADLINE.with(|deadline| deadline.set(time_limit.map(|limit| Instant::now() + limit)));
+    let converted = converter.convert(sample);
+    DEADLINE.with(|deadline| deadline.set(None));
+    let conversion = match converted {
+        Ok(conversion) => conversion,
+        Err(Error::Cancelled) => {
+            let message = format!(
+                "The conversion took longer than the limit of {} ms",
+                time_limit.unwrap_or_default().as_millis()
+            );
+            return (422, error("time_limit_exceeded", &message));
+        }
+        Err(e) => {
+            let mut pairs = vec![
+                ("code", JsonOutput::string(e.code().as_str())),
+                ("message", JsonOutput::string(e.message())),
+            ];
+            if let Some(location) = e.location() {
+                pairs.push(("line", JsonOutput::Int(location.line())));
+                pairs.push(("column", JsonOutput::Int(location.column())));
+            }
+            if let Some(suggestion) = e.suggestion(sample) {
+                pairs.push(("hint", JsonOutput::string(suggestion)));
+            }
+            let status = match e {
+                Error::InputTooLarge { .. } => 413,
+                _ => 422,
+            };
+            return (
+                status,
+                JsonOutput::object(vec![
+                    ("ok", JsonOutput::Bool(false)),
+                    ("error", JsonOutput::object(pairs)),
+                ]),
+            );
+        }
+    };
+    let warnings = conversion
+        .warnings
+        .iter()
+        .map(|warning| {
+            JsonOutput::object(vec![
+                ("path", JsonOutput::string(warning.path.as_str())),
+                ("message", JsonOutput::string(warning.message.as_str())),
+            ])
+        })
+        .collect();
+    (
+        200,
+        JsonOutput::object(vec![
+            ("ok", JsonOutput::Bool(true)),
+            (
+                "code",
+                JsonOutput::string(converter.generate(&conversion, name)),
+            ),
+            ("warnings", JsonOutput::Array(warnings)),
+        ]),
+    )
+}
+
+fn error(code: &str, message: &str) -> JsonOutput {
+    JsonOutput::object(vec![
+        ("ok", JsonOutput::Bool(false)),
+        (
+            "error",
+            JsonOutput::object(vec![
+                ("code", JsonOutput::string(code)),
+                ("message", JsonOutput::string(message)),
+            ]),
+        ),
+    ])
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Unprocessable Entity",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str, body: &str) -> String {
+        request_to(&Server::new(Config::default()), method, path, body)
+    }
+
+    fn request_to(server: &Server, method: &str, path: &str, body: &str) -> String {
+        let mut response = Vec::new();
+        let input = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        );
+        handle_connection(input.as_bytes(), &mut response, server).unwrap();
+        String::from_utf8(response).unwrap()
+    }
+
+    #[test]
+    fn converts_samples() {
+        let response = request(
+            "POST",
+            "/convert",
+            r#"{"sample": "{\"user-id\": 1}", "name": "User", "config": "[codegen]\nvalidation = true"}"#,
+        );
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\"ok\": true"));
+        assert!(response.contains("pub struct User {"));
+        assert!(response.contains("Validate"));
+    }
+
+    #[test]
+    fn reports_invalid_requests_and_samples() {
+        let response = request("POST", "/convert", r#"{"sample": "[1, 2"}"#);
+        assert!(response.starts_with("HTTP/1.1 422 Unprocessable Entity\r\n"));
+        assert!(response.contains("\"code\": \"unexpected_end_of_input\""));
+        assert!(response.contains("\"line\": 1"));
+
+        let response = request("POST", "/convert", r#"{"name": "User"}"#);
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        let response = request(
+            "POST",
+            "/convert",
+            r#"{"sample": "1", "config": "[x]\ny = 1"}"#,
+        );
+        assert!(response.contains("\"code\": \"invalid_config\""));
+
+        assert!(request("GET", "/convert", "").starts_with("HTTP/1.1 405"));
+        assert!(request("POST", "/", "").starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn parses_requests_within_the_limits() {
+        let config = Config::parse("[limits]\nmax_input_bytes = 100000\nmax_depth = 64").unwrap();
+        let server = Server::new(config);
+        let body = format!(
+            r#"{{"sample": "1", "x": {}{}}}"#,
+            "[".repeat(20000),
+            "]".repeat(20000)
+        );
+
+        let response = request_to(&server, "POST", "/convert", &body);
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        // Without limits of its own, a server still bounds requests
+        let server = Server::new(Config::default());
+        assert_eq!(server.config.max_input_bytes, Some(DEFAULT_MAX_INPUT_BYTES));
+        assert_eq!(server.config.time_limit_ms, Some(DEFAULT_TIME_LIMIT_MS));
+        let response = request_to(&server, "POST", "/convert", &body);
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[test]
+    fn enforces_the_limits_of_the_server() {
+        let config = Config::parse("[limits]\nmax_input_bytes = 30\nmax_depth = 2").unwrap();
+        let server = Server::new(config);
+        let request = |body: &str| request_to(&server, "POST", "/convert", body);
+
+        let response = request(r#"{"sample": "[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]"}"#);
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+        assert!(response.contains("\"code\": \"input_too_large\""));
+
+        // Bodies too long for any sample within the limit aren't even read
+        let response = request(&format!(r#"{{"sample": "{}"}}"#, "a".repeat(60)));
+        assert!(response.starts_with("HTTP/1.1 413"));
+        assert!(response.contains("longer than 60 bytes"));
+
+        let response = request(r#"{"sample": "[[[1]]]"}"#);
+        assert!(response.starts_with("HTTP/1.1 422"));
+        assert!(response.contains("\"code\": \"too_deep\""));
+
+        // A request can't raise the limits with a config of its own
+        let response = request(r#"{"sample": "[[[1]]]", "config": "[limits]\nmax_depth = 9"}"#);
+        assert!(response.contains("\"code\": \"too_deep\""));
+
+        assert!(request(r#"{"sample": "[[1]]"}"#).starts_with("HTTP/1.1 200"));
+    }
+}
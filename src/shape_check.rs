@@ -0,0 +1,228 @@
+//! The `matches_schema` function added with `ConvertOptions::set_shape_check`,
+//! which checks payloads against the inferred structure at runtime.
+
+use crate::pointer::{child_path, element_path};
+use crate::{JsonStructure, JsonValue};
+
+const CHECKER: &str = "
+/// A difference between a payload and the structure the types were generated
+/// from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// Json pointer to the value that differs
+    pub path: String,
+    pub message: String,
+}
+
+/// Checks a payload against the structure the types were generated from,
+/// listing every difference, e.g. to log schema drift without failing to
+/// deserialize.
+#[must_use]
+pub fn matches_schema(value: &serde_json::Value) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    check_shape(&SCHEMA_SHAPE, value, \"\", &mut violations);
+    violations
+}
+
+/// The shape of a value in the samples the types were generated from.
+#[allow(dead_code)]
+enum SchemaShape {
+    Any,
+    String,
+    Int,
+    Float,
+    Bool,
+    Optional(&'static SchemaShape),
+    Array(&'static SchemaShape),
+    Map(&'static SchemaShape),
+    Object(&'static [(&'static str, SchemaShape)]),
+    Union(&'static [SchemaShape]),
+}
+
+fn value_kind(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => \"null\",
+        serde_json::Value::Bool(_) => \"a bool\",
+        serde_json::Value::Number(n) if n.is_f64() => \"a float\",
+        serde_json::Value::Number(_) => \"an integer\",
+        serde_json::Value::String(_) => \"a string\",
+        serde_json::Value::Array(_) => \"an array\",
+        serde_json::Value::Object(_) => \"an object\",
+    }
+}
+
+fn child_pointer(path: &str, key: &str) -> String {
+    format!(\"{path}/{}\", key.replace('~', \"~0\").replace('/', \"~1\"))
+}
+
+fn check_shape(
+    shape: &SchemaShape,
+    value: &serde_json::Value,
+    path: &str,
+    violations: &mut Vec<SchemaViolation>,
+) {
+    let expected = match (shape, value) {
+        (SchemaShape::Any, _)
+        | (SchemaShape::String, serde_json::Value::String(_))
+        | (SchemaShape::Bool, serde_json::Value::Bool(_))
+        | (SchemaShape::Float, serde_json::Value::Number(_))
+        | (SchemaShape::Optional(_), serde_json::Value::Null) => return,
+        (SchemaShape::Int, serde_json::Value::Number(n)) if !n.is_f64() => return,
+        (SchemaShape::Optional(inner), _) => return check_shape(inner, value, path, violations),
+        (SchemaShape::Array(element), serde_json::Value::Array(values)) => {
+            for (i, value) in values.iter().enumerate() {
+                check_shape(element, value, &format!(\"{path}/{i}\"), violations);
+            }
+            return;
+        }
+        (SchemaShape::Map(inner), serde_json::Value::Object(map)) => {
+            for (key, value) in map {
+                check_shape(inner, value, &child_pointer(path, key), violations);
+            }
+            return;
+        }
+        (SchemaShape::Object(fields), serde_json::Value::Object(map)) => {
+            for (key, shape) in *fields {
+                match map.get(*key) {
+                    Some(value) => check_shape(shape, value, &child_pointer(path, key), violations),
+                    None if matches!(shape, SchemaShape::Optional(_)) => {}
+                    None => violations.push(SchemaViolation {
+                        path: child_pointer(path, key),
+                        message: \"missing field\".to_string(),
+                    }),
+                }
+            }
+            for key in map.keys() {
+                if !fields.iter().any(|(name, _)| name == key) {
+                    violations.push(SchemaViolation {
+                        path: child_pointer(path, key),
+                        message: \"unexpected field\".to_string(),
+                    });
+                }
+            }
+            return;
+        }
+        (SchemaShape::Union(variants), _) => {
+            let matches = |variant| {
+                let mut variant_violations = Vec::new();
+                check_shape(variant, value, path, &mut variant_violations);
+                variant_violations.is_empty()
+            };
+            if variants.iter().any(matches) {
+                return;
+            }
+            \"one of the variants of a union\"
+        }
+        (SchemaShape::String, _) => \"a string\",
+        (SchemaShape::Int, _) => \"an integer\",
+        (SchemaShape::Float, _) => \"a number\",
+        (SchemaShape::Bool, _) => \"a bool\",
+        (SchemaShape::Array(_), _) => \"an array\",
+        (SchemaShape::Map(_) | SchemaShape::Object(_), _) => \"an object\",
+    };
+    violations.push(SchemaViolation {
+        path: path.to_string(),
+        message: format!(\"expected {expected}, found {}\", value_kind(value)),
+    });
+}
+";
+
+/// The `SchemaShape` expression describing `structure`, the value at the json
+/// pointer `path`. The values at `json_strings` are sent as strings holding
+/// json, with `ConvertOptions::set_json_strings`, so they are strings whatever
+/// they decode to.
+fn shape(structure: &JsonStructure, path: &str, json_strings: &[&str]) -> String {
+    if json_strings.contains(&path) {
+        return match structure {
+            JsonStructure::Optional(_) => "SchemaShape::Optional(&SchemaShape::String)",
+            _ => "SchemaShape::String",
+        }
+        .to_string();
+    }
+    match structure {
+        JsonStructure::Value(JsonValue::String) => "SchemaShape::String".to_string(),
+        JsonStructure::Value(JsonValue::Int) => "SchemaShape::Int".to_string(),
+        JsonStructure::Value(JsonValue::Float) => "SchemaShape::Float".to_string(),
+        JsonStructure::Value(JsonValue::Bool) => "SchemaShape::Bool".to_string(),
+        // Detected and mapped types can be read from any kind of value
+        JsonStructure::Value(JsonValue::Custom(_)) | JsonStructure::Unknown => {
+            "SchemaShape::Any".to_string()
+        }
+        JsonStructure::Optional(inner) => format!(
+            "SchemaShape::Optional(&{})",
+            shape(inner, path, json_strings)
+        ),
+        JsonStructure::Array(inner) => format!(
+            "SchemaShape::Array(&{})",
+            shape(inner, &element_path(path), json_strings)
+        ),
+        JsonStructure::Dictionary(pair) => format!(
+            "SchemaShape::Map(&{})",
+            shape(pair.value(), &element_path(path), json_strings)
+        ),
+        JsonStructure::Object(pairs) => {
+            let fields: Vec<String> = pairs
+                .iter()
+                .map(|pair| {
+                    let child = child_path(path, pair.key());
+                    format!(
+                        "({:?}, {})",
+                        pair.key(),
+                        shape(pair.value(), &child, json_strings)
+                    )
+                })
+                .collect();
+            format!("SchemaShape::Object(&[{}])", fields.join(", "))
+        }
+        JsonStructure::Union(variants) => {
+            let variants: Vec<String> = variants
+                .iter()
+                .map(|variant| shape(variant, path, json_strings))
+                .collect();
+            format!("SchemaShape::Union(&[{}])", variants.join(", "))
+        }
+    }
+}
+
+/// The `matches_schema` function for `structure`, along with what it needs.
+/// `json_strings` are the json pointers of the values sent as strings holding
+/// json.
+pub(crate) fn shape_check_code(structure: &JsonStructure, json_strings: &[&str]) -> String {
+    format!(
+        "{}\nstatic SCHEMA_SHAPE: SchemaShape = {};\n",
+        CHECKER,
+        shape(structure, "", json_strings)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    #[test]
+    fn describes_the_structure_as_a_static() {
+        let structure =
+            convert_sample_json(r#"[{"id": 1, "tags": ["a"], "note": null}, {"id": 2.5}]"#)
+                .unwrap();
+
+        assert_eq!(
+            shape(&structure, "", &[]),
+            "SchemaShape::Array(&SchemaShape::Object(&[(\"id\", SchemaShape::Float), \
+             (\"tags\", SchemaShape::Optional(&SchemaShape::Array(&SchemaShape::String))), \
+             (\"note\", SchemaShape::Optional(&SchemaShape::Any))]))"
+        );
+        assert!(shape_check_code(&structure, &[]).contains("pub fn matches_schema("));
+    }
+
+    #[test]
+    fn describes_json_strings_as_strings() {
+        let structure = convert_sample_json(r#"{"payload": {"a": 1}, "meta": null}"#).unwrap();
+
+        assert_eq!(
+            shape(&structure, "", &["/payload", "/meta"]),
+            "SchemaShape::Object(&[(\"payload\", SchemaShape::String), \
+             (\"meta\", SchemaShape::Optional(&SchemaShape::String))])"
+        );
+    }
+}
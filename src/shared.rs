@@ -1,4 +1,8 @@
+use alloc::{format, string::String, vec::Vec};
+use core::fmt;
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JsonTokenInfo {
     line: i64,
     column: i64,
@@ -9,18 +13,454 @@ impl JsonTokenInfo {
     pub fn new(line: i64, column: i64, char: i64) -> JsonTokenInfo {
         JsonTokenInfo { line, column, char }
     }
+
+    /// The line, starting at 1.
+    pub fn line(&self) -> i64 {
+        self.line
+    }
+
+    /// The column within the line, in characters, starting at 1.
+    pub fn column(&self) -> i64 {
+        self.column
+    }
+
+    /// The offset from the start of the input, in characters, starting at 0.
+    pub fn char(&self) -> i64 {
+        self.char
+    }
 }
+
+/// A range of the input, from `start` up to, but not including, `end`. Used
+/// for tokens and for the values they make up.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: JsonTokenInfo,
+    pub end: JsonTokenInfo,
+}
+
+impl Span {
+    pub fn new(start: JsonTokenInfo, end: JsonTokenInfo) -> Span {
+        Span { start, end }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
     MultipleDecimalSeparators(JsonTokenInfo),
     DecimalAfterExponent(JsonTokenInfo),
     InvalidNumberCharacter(JsonTokenInfo, char),
     MultipleExponentCharacters(JsonTokenInfo),
     UnknownJsonCharacter(JsonTokenInfo, char),
-    UnclosedString(JsonTokenInfo),
     NumbersCannotStartWithZero(JsonTokenInfo),
+    /// A `+` or `-` inside a number, other than right after the exponent
+    /// character, at the location of the sign
+    MisplacedNumberSign(JsonTokenInfo, char),
+    /// A number without digits before its decimal separator or exponent, such
+    /// as `.5` or `-e5`
+    NumberMissingIntegerPart(JsonTokenInfo),
+    /// A number without digits after its decimal separator, such as `5.`
+    NumberMissingFractionDigits(JsonTokenInfo),
     InvalidJson {
         location: JsonTokenInfo,
         message: String,
     },
+    /// A closing bracket that doesn't match the innermost open one, such as
+    /// the `]` in `[{]`
+    MismatchedBracket {
+        expected: char,
+        found: char,
+        open_location: JsonTokenInfo,
+        close_location: JsonTokenInfo,
+    },
+    /// The input ended while `expected` was still missing, such as in a
+    /// truncated document. `last_location` is just past the end of the input.
+    UnexpectedEndOfInput {
+        expected: String,
+        last_location: JsonTokenInfo,
+    },
+    /// A schema read as input, such as an Avro schema, is not valid
+    InvalidSchema(String),
+    /// A delimited sample could not be read, on the given line
+    InvalidCsv {
+        line: usize,
+        message: String,
+    },
+    /// A binary sample, such as MessagePack or CBOR, could not be decoded at
+    /// the given byte offset
+    InvalidBinary {
+        offset: usize,
+        message: String,
+    },
+    /// The value at the json pointer `path` was a string in some samples and a
+    /// number in others, with `MixedNumberPolicy::Error`
+    MixedStringsAndNumbers {
+        path: String,
+    },
+    /// The values at the json pointers `paths` were numbers with a fraction,
+    /// with `FloatPolicy::Error`
+    FloatsNotAllowed {
+        paths: Vec<String>,
+    },
+    /// The conversion was stopped by the check of
+    /// `ConvertOptions::set_cancellation`
+    Cancelled,
+    /// The json input was `size` bytes, more than the `limit` of
+    /// `ConvertOptions::set_max_input_bytes`
+    InputTooLarge {
+        size: usize,
+        limit: usize,
+    },
+    /// Objects and arrays were nested deeper than the `limit` of
+    /// `ConvertOptions::set_max_depth`, at the bracket going past it
+    TooDeep {
+        location: JsonTokenInfo,
+        limit: usize,
+    },
+    /// A structure had more nodes, fields, variants or keys than the `limit`
+    /// a `CompactStructure` can index
+    StructureTooLarge {
+        limit: usize,
+    },
+}
+
+/// A machine readable code for each kind of `Error`, so tools can tell errors
+/// apart without matching on `Error`, which gains variants over time.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    MultipleDecimalSeparators,
+    DecimalAfterExponent,
+    InvalidNumberCharacter,
+    MultipleExponentCharacters,
+    UnknownJsonCharacter,
+    NumbersCannotStartWithZero,
+    MisplacedNumberSign,
+    NumberMissingIntegerPart,
+    NumberMissingFractionDigits,
+    InvalidJson,
+    MismatchedBracket,
+    UnexpectedEndOfInput,
+    InvalidSchema,
+    InvalidCsv,
+    InvalidBinary,
+    MixedStringsAndNumbers,
+    FloatsNotAllowed,
+    Cancelled,
+    InputTooLarge,
+    TooDeep,
+    StructureTooLarge,
+}
+
+impl ErrorCode {
+    /// The code as text, such as `"mismatched_bracket"`. Codes never change once
+    /// released.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::MultipleDecimalSeparators => "multiple_decimal_separators",
+            ErrorCode::DecimalAfterExponent => "decimal_after_exponent",
+            ErrorCode::InvalidNumberCharacter => "invalid_number_character",
+            ErrorCode::MultipleExponentCharacters => "multiple_exponent_characters",
+            ErrorCode::UnknownJsonCharacter => "unknown_json_character",
+            ErrorCode::NumbersCannotStartWithZero => "numbers_cannot_start_with_zero",
+            ErrorCode::MisplacedNumberSign => "misplaced_number_sign",
+            ErrorCode::NumberMissingIntegerPart => "number_missing_integer_part",
+            ErrorCode::NumberMissingFractionDigits => "number_missing_fraction_digits",
+            ErrorCode::InvalidJson => "invalid_json",
+            ErrorCode::MismatchedBracket => "mismatched_bracket",
+            ErrorCode::UnexpectedEndOfInput => "unexpected_end_of_input",
+            ErrorCode::InvalidSchema => "invalid_schema",
+            ErrorCode::InvalidCsv => "invalid_csv",
+            ErrorCode::InvalidBinary => "invalid_binary",
+            ErrorCode::MixedStringsAndNumbers => "mixed_strings_and_numbers",
+            ErrorCode::FloatsNotAllowed => "floats_not_allowed",
+            ErrorCode::Cancelled => "cancelled",
+            ErrorCode::InputTooLarge => "input_too_large",
+            ErrorCode::TooDeep => "too_deep",
+            ErrorCode::StructureTooLarge => "structure_too_large",
+        }
+    }
+}
+
+impl Error {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::MultipleDecimalSeparators(_) => ErrorCode::MultipleDecimalSeparators,
+            Error::DecimalAfterExponent(_) => ErrorCode::DecimalAfterExponent,
+            Error::InvalidNumberCharacter(..) => ErrorCode::InvalidNumberCharacter,
+            Error::MultipleExponentCharacters(_) => ErrorCode::MultipleExponentCharacters,
+            Error::UnknownJsonCharacter(..) => ErrorCode::UnknownJsonCharacter,
+            Error::NumbersCannotStartWithZero(_) => ErrorCode::NumbersCannotStartWithZero,
+            Error::MisplacedNumberSign(..) => ErrorCode::MisplacedNumberSign,
+            Error::NumberMissingIntegerPart(_) => ErrorCode::NumberMissingIntegerPart,
+            Error::NumberMissingFractionDigits(_) => ErrorCode::NumberMissingFractionDigits,
+            Error::InvalidJson { .. } => ErrorCode::InvalidJson,
+            Error::MismatchedBracket { .. } => ErrorCode::MismatchedBracket,
+            Error::UnexpectedEndOfInput { .. } => ErrorCode::UnexpectedEndOfInput,
+            Error::InvalidSchema(_) => ErrorCode::InvalidSchema,
+            Error::InvalidCsv { .. } => ErrorCode::InvalidCsv,
+            Error::InvalidBinary { .. } => ErrorCode::InvalidBinary,
+            Error::MixedStringsAndNumbers { .. } => ErrorCode::MixedStringsAndNumbers,
+            Error::FloatsNotAllowed { .. } => ErrorCode::FloatsNotAllowed,
+            Error::Cancelled => ErrorCode::Cancelled,
+            Error::InputTooLarge { .. } => ErrorCode::InputTooLarge,
+            Error::TooDeep { .. } => ErrorCode::TooDeep,
+            Error::StructureTooLarge { .. } => ErrorCode::StructureTooLarge,
+        }
+    }
+
+    /// Where in the json input the error is. For a mismatched bracket this is
+    /// the closing bracket, and for truncated input the end of the input.
+    /// `None` for errors in other formats.
+    pub fn location(&self) -> Option<JsonTokenInfo> {
+        match self {
+            Error::MultipleDecimalSeparators(location)
+            | Error::DecimalAfterExponent(location)
+            | Error::InvalidNumberCharacter(location, _)
+            | Error::MultipleExponentCharacters(location)
+            | Error::UnknownJsonCharacter(location, _)
+            | Error::NumbersCannotStartWithZero(location)
+            | Error::MisplacedNumberSign(location, _)
+            | Error::NumberMissingIntegerPart(location)
+            | Error::NumberMissingFractionDigits(location)
+            | Error::InvalidJson { location, .. }
+            | Error::MismatchedBracket {
+                close_location: location,
+                ..
+            }
+            | Error::UnexpectedEndOfInput {
+                last_location: location,
+                ..
+            }
+            | Error::TooDeep { location, .. } => Some(*location),
+            Error::InvalidSchema(_)
+            | Error::InvalidCsv { .. }
+            | Error::InvalidBinary { .. }
+            | Error::MixedStringsAndNumbers { .. }
+            | Error::FloatsNotAllowed { .. }
+            | Error::Cancelled
+            | Error::InputTooLarge { .. }
+            | Error::StructureTooLarge { .. } => None,
+        }
+    }
+
+    /// A description of the error for people, such as in diagnostics,
+    /// without its location.
+    pub fn message(&self) -> String {
+        match self {
+            Error::MultipleDecimalSeparators(_) => {
+                "Number with more than one decimal separator".into()
+            }
+            Error::DecimalAfterExponent(_) => "Decimal separator after the exponent".into(),
+            Error::InvalidNumberCharacter(_, c) => format!("Unexpected '{}' in a number", c),
+            Error::MultipleExponentCharacters(_) => "Number with more than one exponent".into(),
+            Error::UnknownJsonCharacter(_, c) => format!("Unexpected character '{}'", c),
+            Error::NumbersCannotStartWithZero(_) => "Numbers can't start with 0".into(),
+            Error::MisplacedNumberSign(_, c) => format!("Misplaced '{}' in a number", c),
+            Error::NumberMissingIntegerPart(_) => {
+                "Number without digits before the '.' or exponent".into()
+            }
+            Error::NumberMissingFractionDigits(_) => "Number without digits after the '.'".into(),
+            Error::InvalidJson { message, .. } => message.clone(),
+            Error::MismatchedBracket {
+                expected, found, ..
+            } => format!("Expected '{}', found '{}'", expected, found),
+            Error::UnexpectedEndOfInput { expected, .. } => {
+                format!("Unexpected end of input, expected {}", expected)
+            }
+            Error::MixedStringsAndNumbers { .. } => {
+                "A string in some values and a number in others, see inference.mixed_numbers".into()
+            }
+            Error::FloatsNotAllowed { paths } => format!(
+                "Numbers with a fraction at {}, see inference.floats",
+                paths.join(", ")
+            ),
+            Error::InputTooLarge { size, limit } => format!(
+                "The input is {} bytes, more than the limit of {}, see limits.max_input_bytes",
+                size, limit
+            ),
+            Error::TooDeep { limit, .. } => format!(
+                "Nested more than {} levels deep, see limits.max_depth",
+                limit
+            ),
+            Error::StructureTooLarge { limit } => format!(
+                "The structure has more than {} nodes, fields, variants or keys",
+                limit
+            ),
+            Error::InvalidSchema(message) => message.clone(),
+            Error::InvalidCsv { line, message } => format!("{} on line {}", message, line),
+            Error::InvalidBinary { offset, message } => {
+                format!("{} at byte {}", message, offset)
+            }
+            Error::Cancelled => "The conversion was cancelled".into(),
+        }
+    }
+}
+
+/// The message of the error, followed by where in the json input it is.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message())?;
+        match self.location() {
+            Some(location) => write!(
+                f,
+                " at line {}, column {}",
+                location.line(),
+                location.column()
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ErrorCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Errors serialize as a flat object, which stays the same across releases:
+///
+/// - `code`, the `ErrorCode` as text
+/// - `location`, the `{"line", "column", "char"}` of `Error::location`, or
+///   `null`
+/// - `character`, the offending character of `invalid_number_character`,
+///   `unknown_json_character` and `misplaced_number_sign`
+/// - `message`, for `invalid_json`, `invalid_schema`, `invalid_csv` and
+///   `invalid_binary`
+/// - `expected`, the missing text of `unexpected_end_of_input`, or the closing
+///   bracket of `mismatched_bracket`, along with `found` and `open_location`
+/// - `line` of `invalid_csv`, and `offset` of `invalid_binary`
+/// - `path` of `mixed_strings_and_numbers`, and `paths` of
+///   `floats_not_allowed`
+/// - `limit` of `input_too_large`, along with its `size`, of `too_deep` and of
+///   `structure_too_large`
+///
+/// New fields may be added, but existing ones aren't changed or removed.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("code", &self.code())?;
+        map.serialize_entry("location", &self.location())?;
+        match self {
+            Error::InvalidNumberCharacter(_, c)
+            | Error::UnknownJsonCharacter(_, c)
+            | Error::MisplacedNumberSign(_, c) => map.serialize_entry("character", c)?,
+            Error::InvalidJson { message, .. } | Error::InvalidSchema(message) => {
+                map.serialize_entry("message", message)?
+            }
+            Error::MismatchedBracket {
+                expected,
+                found,
+                open_location,
+                ..
+            } => {
+                map.serialize_entry("expected", expected)?;
+                map.serialize_entry("found", found)?;
+                map.serialize_entry("open_location", open_location)?;
+            }
+            Error::UnexpectedEndOfInput { expected, .. } => {
+                map.serialize_entry("expected", expected)?
+            }
+            Error::InvalidCsv { line, message } => {
+                map.serialize_entry("line", line)?;
+                map.serialize_entry("message", message)?;
+            }
+            Error::InvalidBinary { offset, message } => {
+                map.serialize_entry("offset", offset)?;
+                map.serialize_entry("message", message)?;
+            }
+            Error::MixedStringsAndNumbers { path } => map.serialize_entry("path", path)?,
+            Error::FloatsNotAllowed { paths } => map.serialize_entry("paths", paths)?,
+            Error::InputTooLarge { size, limit } => {
+                map.serialize_entry("size", size)?;
+                map.serialize_entry("limit", limit)?;
+            }
+            Error::TooDeep { limit, .. } | Error::StructureTooLarge { limit } => {
+                map.serialize_entry("limit", limit)?
+            }
+            _ => {}
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_json;
+
+    #[test]
+    fn exposes_codes_and_locations() {
+        let error = parse_json("[1,\n {]").unwrap_err();
+
+        assert_eq!(error.code(), ErrorCode::MismatchedBracket);
+        assert_eq!(error.code().as_str(), "mismatched_bracket");
+        assert_eq!(error.location(), Some(JsonTokenInfo::new(2, 3, 6)));
+        assert_eq!(Error::InvalidSchema("x".to_string()).location(), None);
+        assert_eq!(
+            Error::MixedStringsAndNumbers {
+                path: "/a".to_string()
+            }
+            .code()
+            .as_str(),
+            "mixed_strings_and_numbers"
+        );
+    }
+
+    #[test]
+    fn displays_the_message_and_location() {
+        let error = parse_json("[1, .5]").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Number without digits before the '.' or exponent at line 1, column 5"
+        );
+        assert_eq!(
+            Error::InvalidCsv {
+                line: 3,
+                message: "Unclosed quote".to_string()
+            }
+            .to_string(),
+            "Unclosed quote on line 3"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_errors_to_a_flat_object() {
+        let error = parse_json("[1,\n {]").unwrap_err();
+
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "mismatched_bracket",
+                "location": {"line": 2, "column": 3, "char": 6},
+                "expected": "}",
+                "found": "]",
+                "open_location": {"line": 2, "column": 2, "char": 5},
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(Error::InvalidCsv {
+                line: 3,
+                message: "bad".to_string()
+            })
+            .unwrap(),
+            serde_json::json!({
+                "code": "invalid_csv",
+                "location": null,
+                "line": 3,
+                "message": "bad",
+            })
+        );
+    }
 }
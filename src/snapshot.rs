@@ -0,0 +1,681 @@
+//! Snapshots of a conversion, so the inference over many samples can run once
+//! and its result be generated from later, with options iterated quickly. A
+//! snapshot holds the inferred structure, as `conversion_to_json` describes it,
+//! the stats of every path, the warnings, and the samples it was inferred
+//! from. Samples can be merged into a snapshot later, recording how the
+//! structure changed in its history.
+
+use crate::accumulator::merge_structures;
+use crate::hash::Fnv;
+use crate::history::{change_to_json, structure_changes, Change, Revision};
+use crate::ir_output::structure_to_json;
+use crate::json_writer::JsonOutput;
+use crate::parser::{parse_json, JsonNode};
+use crate::shared::{JsonTokenInfo, Span};
+use crate::stats::{merge_stats, PathStats, StringFormat};
+use crate::warnings::Warning;
+use crate::{Conversion, JsonPair, JsonStructure, JsonValue, RustType};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The version of the snapshot format, raised when older versions of json2rust
+/// can't read it.
+const FORMAT_VERSION: i64 = 1;
+
+/// Names a snapshot file, in case it is mistaken for something else.
+const FORMAT: &str = "json2rust-ir";
+
+/// A sample a snapshot was inferred from.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SnapshotSample {
+    /// Where the sample was read from, such as its file name
+    pub name: String,
+    /// The hash of the sample, to tell whether it changed since
+    pub hash: u64,
+}
+
+impl SnapshotSample {
+    pub fn new<S: Into<String>>(name: S, sample: &str) -> SnapshotSample {
+        let mut hasher = Fnv::new();
+        hasher.write_str(sample);
+        SnapshotSample {
+            name: name.into(),
+            hash: hasher.finish(),
+        }
+    }
+}
+
+/// A conversion saved to be generated from later, along with where it came
+/// from.
+#[derive(Debug, PartialEq)]
+pub struct Snapshot {
+    pub conversion: Conversion,
+    pub samples: Vec<SnapshotSample>,
+    /// The version of json2rust that made the snapshot
+    pub version: String,
+    /// The updates of the snapshot with more samples, oldest first
+    pub history: Vec<Revision>,
+}
+
+/// Why a snapshot couldn't be read.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SnapshotError {
+    pub message: String,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+fn error<T, S: Into<String>>(message: S) -> Result<T, SnapshotError> {
+    Err(SnapshotError {
+        message: message.into(),
+    })
+}
+
+fn field<'a>(node: &'a JsonNode, key: &str) -> Result<&'a JsonNode, SnapshotError> {
+    node.get(key).ok_or_else(|| SnapshotError {
+        message: format!("Missing '{}'", key),
+    })
+}
+
+fn string<'a>(node: &'a JsonNode, key: &str) -> Result<&'a str, SnapshotError> {
+    match field(node, key)? {
+        JsonNode::String(s) => Ok(s),
+        _ => error(format!("'{}' must be a string", key)),
+    }
+}
+
+fn array<'a>(node: &'a JsonNode, key: &str) -> Result<&'a [JsonNode], SnapshotError> {
+    match field(node, key)? {
+        JsonNode::Array(values) => Ok(values),
+        _ => error(format!("'{}' must be an array", key)),
+    }
+}
+
+fn count(node: &JsonNode, key: &str) -> Result<usize, SnapshotError> {
+    match field(node, key)? {
+        JsonNode::Int(i) if *i >= 0 => Ok(*i as usize),
+        _ => error(format!("'{}' must be a count", key)),
+    }
+}
+
+fn number(node: &JsonNode) -> Result<f64, SnapshotError> {
+    match node {
+        JsonNode::Int(i) => Ok(*i as f64),
+        JsonNode::Float(f) => Ok(*f),
+        _ => error("Expected a number"),
+    }
+}
+
+/// A pair written as `[low, high]`, or `None` for null.
+fn range<T>(
+    node: &JsonNode,
+    key: &str,
+    read: impl Fn(&JsonNode) -> Result<T, SnapshotError>,
+) -> Result<Option<(T, T)>, SnapshotError> {
+    match node.get(key) {
+        None | Some(JsonNode::Null) => Ok(None),
+        Some(JsonNode::Array(values)) if values.len() == 2 => {
+            Ok(Some((read(&values[0])?, read(&values[1])?)))
+        }
+        Some(_) => error(format!("'{}' must be a pair of numbers", key)),
+    }
+}
+
+fn pair<T>(range: Option<(T, T)>, write: impl Fn(T) -> JsonOutput) -> JsonOutput {
+    match range {
+        Some((low, high)) => JsonOutput::Array(vec![write(low), write(high)]),
+        None => JsonOutput::Null,
+    }
+}
+
+fn position(info: &JsonTokenInfo) -> JsonOutput {
+    JsonOutput::Array(vec![
+        JsonOutput::Int(info.line()),
+        JsonOutput::Int(info.column()),
+        JsonOutput::Int(info.char()),
+    ])
+}
+
+fn read_position(node: &JsonNode) -> Result<JsonTokenInfo, SnapshotError> {
+    match node {
+        JsonNode::Array(values) if values.len() == 3 => {
+            let mut parts = [0; 3];
+            for (part, value) in parts.iter_mut().zip(values) {
+                *part = number(value)? as i64;
+            }
+            Ok(JsonTokenInfo::new(parts[0], parts[1], parts[2]))
+        }
+        _ => error("A position must be [line, column, char]"),
+    }
+}
+
+fn stats_to_json(stats: &PathStats) -> JsonOutput {
+    JsonOutput::object(vec![
+        ("path", JsonOutput::string(stats.path.as_str())),
+        ("count", JsonOutput::Int(stats.count as i64)),
+        ("null_count", JsonOutput::Int(stats.null_count as i64)),
+        ("present_count", JsonOutput::Int(stats.present_count as i64)),
+        ("parent_count", JsonOutput::Int(stats.parent_count as i64)),
+        (
+            "length_range",
+            pair(stats.length_range, |n| JsonOutput::Int(n as i64)),
+        ),
+        ("value_range", pair(stats.value_range, JsonOutput::Float)),
+        (
+            "string_format",
+            match stats.string_format {
+                Some(StringFormat::Email) => JsonOutput::string("email"),
+                Some(StringFormat::Url) => JsonOutput::string("url"),
+                Some(StringFormat::Json) => JsonOutput::string("json"),
+                None => JsonOutput::Null,
+            },
+        ),
+        // The parser doesn't keep the values of bools, so this is a word
+        (
+            "unique_elements",
+            match stats.unique_elements {
+                Some(true) => JsonOutput::string("unique"),
+                Some(false) => JsonOutput::string("repeated"),
+                None => JsonOutput::Null,
+            },
+        ),
+        (
+            "element_counts",
+            pair(stats.element_counts, |n| JsonOutput::Int(n as i64)),
+        ),
+        (
+            "int_values",
+            match &stats.int_values {
+                Some(values) => {
+                    JsonOutput::Array(values.iter().map(|v| JsonOutput::Int(*v)).collect())
+                }
+                None => JsonOutput::Null,
+            },
+        ),
+        (
+            "string_values",
+            match &stats.string_values {
+                Some(values) => JsonOutput::Array(
+                    values
+                        .iter()
+                        .map(|v| JsonOutput::string(v.as_str()))
+                        .collect(),
+                ),
+                None => JsonOutput::Null,
+            },
+        ),
+        (
+            "span",
+            match &stats.span {
+                Some(span) => JsonOutput::Array(vec![position(&span.start), position(&span.end)]),
+                None => JsonOutput::Null,
+            },
+        ),
+        (
+            "examples",
+            JsonOutput::Array(
+                stats
+                    .examples
+                    .iter()
+                    .map(|e| JsonOutput::string(e.as_str()))
+                    .collect(),
+            ),
+        ),
+        (
+            "description",
+            match &stats.description {
+                Some(description) => JsonOutput::string(description.as_str()),
+                None => JsonOutput::Null,
+            },
+        ),
+    ])
+}
+
+fn read_stats(node: &JsonNode) -> Result<PathStats, SnapshotError> {
+    let mut stats = PathStats::new(
+        string(node, "path")?,
+        count(node, "count")?,
+        count(node, "null_count")?,
+    );
+    stats.present_count = count(node, "present_count")?;
+    stats.parent_count = count(node, "parent_count")?;
+    stats.length_range = range(node, "length_range", |n| Ok(number(n)? as usize))?;
+    stats.value_range = range(node, "value_range", number)?;
+    stats.element_counts = range(node, "element_counts", |n| Ok(number(n)? as usize))?;
+    stats.string_format = match node.get("string_format") {
+        None | Some(JsonNode::Null) => None,
+        Some(JsonNode::String(format)) if format == "email" => Some(StringFormat::Email),
+        Some(JsonNode::String(format)) if format == "url" => Some(StringFormat::Url),
+        Some(JsonNode::String(format)) if format == "json" => Some(StringFormat::Json),
+        Some(_) => return error("'string_format' must be email, url, json or null"),
+    };
+    stats.unique_elements = match node.get("unique_elements") {
+        None | Some(JsonNode::Null) => None,
+        Some(JsonNode::String(s)) if s == "unique" => Some(true),
+        Some(JsonNode::String(s)) if s == "repeated" => Some(false),
+        Some(_) => return error("'unique_elements' must be unique, repeated or null"),
+    };
+    stats.int_values = match node.get("int_values") {
+        None | Some(JsonNode::Null) => None,
+        Some(JsonNode::Array(values)) => Some(
+            values
+                .iter()
+                .map(|value| match value {
+                    JsonNode::Int(value) => i64::try_from(*value).or(error("Integer out of range")),
+                    _ => error("'int_values' must be integers"),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        Some(_) => return error("'int_values' must be an array or null"),
+    };
+    stats.string_values = match node.get("string_values") {
+        None | Some(JsonNode::Null) => None,
+        Some(JsonNode::Array(values)) => Some(
+            values
+                .iter()
+                .map(|value| match value.as_str() {
+                    Some(value) => Ok(value.to_string()),
+                    None => error("'string_values' must be strings"),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        Some(_) => return error("'string_values' must be an array or null"),
+    };
+    stats.span = match node.get("span") {
+        None | Some(JsonNode::Null) => None,
+        Some(JsonNode::Array(ends)) if ends.len() == 2 => Some(Span::new(
+            read_position(&ends[0])?,
+            read_position(&ends[1])?,
+        )),
+        Some(_) => return error("'span' must be [start, end] or null"),
+    };
+    stats.examples = array(node, "examples")?
+        .iter()
+        .map(|example| match example {
+            JsonNode::String(example) => Ok(example.clone()),
+            _ => error("'examples' must be strings"),
+        })
+        .collect::<Result<_, _>>()?;
+    // Snapshots from before descriptions were lifted have none
+    stats.description = match node.get("description") {
+        None | Some(JsonNode::Null) => None,
+        Some(JsonNode::String(description)) => Some(description.clone()),
+        Some(_) => return error("'description' must be a string or null"),
+    };
+    Ok(stats)
+}
+
+/// Reads a structure as `structure_to_json` describes it.
+fn read_structure(node: &JsonNode) -> Result<JsonStructure, SnapshotError> {
+    Ok(match string(node, "kind")? {
+        "object" => JsonStructure::Object(
+            array(node, "fields")?
+                .iter()
+                .map(|field| Ok(JsonPair::new(string(field, "key")?, read_type(field)?)))
+                .collect::<Result<_, SnapshotError>>()?,
+        ),
+        "array" => JsonStructure::Array(Box::new(read_structure(field(node, "element")?)?)),
+        "dictionary" => JsonStructure::Dictionary(JsonPair::new(
+            string(node, "key")?,
+            read_structure(field(node, "value")?)?,
+        )),
+        "optional" => JsonStructure::Optional(Box::new(read_structure(field(node, "inner")?)?)),
+        "union" => JsonStructure::Union(
+            array(node, "variants")?
+                .iter()
+                .map(read_structure)
+                .collect::<Result<_, _>>()?,
+        ),
+        "custom" => {
+            JsonStructure::Value(JsonValue::Custom(RustType::new(string(node, "rust_type")?)))
+        }
+        "string" => JsonStructure::Value(JsonValue::String),
+        "float" => JsonStructure::Value(JsonValue::Float),
+        "int" => JsonStructure::Value(JsonValue::Int),
+        "bool" => JsonStructure::Value(JsonValue::Bool),
+        "unknown" => JsonStructure::Unknown,
+        other => return error(format!("Unknown kind '{}'", other)),
+    })
+}
+
+fn read_type(field: &JsonNode) -> Result<JsonStructure, SnapshotError> {
+    read_structure(self::field(field, "type")?)
+}
+
+fn read_change(node: &JsonNode) -> Result<Change, SnapshotError> {
+    let path = string(node, "path")?.to_string();
+    Ok(match string(node, "change")? {
+        "added" => Change::Added {
+            path,
+            type_name: string(node, "type")?.to_string(),
+        },
+        "removed" => Change::Removed {
+            path,
+            type_name: string(node, "type")?.to_string(),
+        },
+        "widened" => Change::Widened {
+            path,
+            from: string(node, "from")?.to_string(),
+            to: string(node, "to")?.to_string(),
+        },
+        "narrowed" => Change::Narrowed {
+            path,
+            from: string(node, "from")?.to_string(),
+            to: string(node, "to")?.to_string(),
+        },
+        "changed" => Change::Changed {
+            path,
+            from: string(node, "from")?.to_string(),
+            to: string(node, "to")?.to_string(),
+        },
+        "optional" => Change::BecameOptional { path },
+        "required" => Change::BecameRequired { path },
+        other => return error(format!("Unknown change '{}'", other)),
+    })
+}
+
+fn history_to_json(history: &[Revision]) -> JsonOutput {
+    JsonOutput::Array(
+        history
+            .iter()
+            .map(|revision| {
+                JsonOutput::object(vec![
+                    ("at", JsonOutput::Int(revision.at as i64)),
+                    (
+                        "samples",
+                        JsonOutput::Array(
+                            revision
+                                .samples
+                                .iter()
+                                .map(|name| JsonOutput::string(name.as_str()))
+                                .collect(),
+                        ),
+                    ),
+                    (
+                        "changes",
+                        JsonOutput::Array(revision.changes.iter().map(change_to_json).collect()),
+                    ),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn read_revision(node: &JsonNode) -> Result<Revision, SnapshotError> {
+    Ok(Revision {
+        at: count(node, "at")? as u64,
+        samples: array(node, "samples")?
+            .iter()
+            .map(|name| match name {
+                JsonNode::String(name) => Ok(name.clone()),
+                _ => error("The samples of a revision must be names"),
+            })
+            .collect::<Result<_, _>>()?,
+        changes: array(node, "changes")?
+            .iter()
+            .map(read_change)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+impl Snapshot {
+    /// A snapshot of `conversion`, inferred from `samples`, pairs of the names
+    /// and the texts of the samples.
+    pub fn new(conversion: Conversion, samples: &[(&str, &str)]) -> Snapshot {
+        Snapshot {
+            conversion,
+            samples: samples
+                .iter()
+                .map(|(name, sample)| SnapshotSample::new(*name, sample))
+                .collect(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            history: Vec::new(),
+        }
+    }
+
+    /// Whether `sample` is one of the samples of the snapshot, under any name.
+    pub fn has_sample(&self, sample: &str) -> bool {
+        let hash = SnapshotSample::new("", sample).hash;
+        self.samples.iter().any(|sample| sample.hash == hash)
+    }
+
+    /// Merges `conversion`, inferred from `samples` with the same options as
+    /// the snapshot, into the snapshot, as if it had been inferred from every
+    /// sample at once. Records the changes to the structure in the history, as
+    /// of `at`, in seconds since the unix epoch.
+    pub fn update(&mut self, conversion: Conversion, samples: &[(&str, &str)], at: u64) {
+        let old = std::mem::replace(&mut self.conversion.structure, JsonStructure::Unknown);
+        let structure = merge_structures(old.clone(), conversion.structure);
+        self.history.push(Revision {
+            at,
+            samples: samples.iter().map(|(name, _)| name.to_string()).collect(),
+            changes: structure_changes(&old, &structure),
+        });
+        self.conversion.structure = structure;
+        let stats = std::mem::take(&mut self.conversion.stats);
+        self.conversion.stats = merge_stats(stats, conversion.stats);
+        for warning in conversion.warnings {
+            if !self.conversion.warnings.contains(&warning) {
+                self.conversion.warnings.push(warning);
+            }
+        }
+        // The shapes of the samples may overlap, so their number is unknown
+        self.conversion.unique_shapes = None;
+        self.samples.extend(
+            samples
+                .iter()
+                .map(|(name, sample)| SnapshotSample::new(*name, sample)),
+        );
+        self.version = env!("CARGO_PKG_VERSION").to_string();
+    }
+
+    /// Writes the snapshot as json.
+    pub fn to_json(&self) -> String {
+        let conversion = &self.conversion;
+        JsonOutput::object(vec![
+            ("format", JsonOutput::string(FORMAT)),
+            ("format_version", JsonOutput::Int(FORMAT_VERSION)),
+            ("version", JsonOutput::string(self.version.as_str())),
+            (
+                "samples",
+                JsonOutput::Array(
+                    self.samples
+                        .iter()
+                        .map(|sample| {
+                            JsonOutput::object(vec![
+                                ("name", JsonOutput::string(sample.name.as_str())),
+                                ("hash", JsonOutput::string(format!("{:016x}", sample.hash))),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "unique_shapes",
+                conversion
+                    .unique_shapes
+                    .map_or(JsonOutput::Null, |n| JsonOutput::Int(n as i64)),
+            ),
+            ("structure", structure_to_json(&conversion.structure)),
+            (
+                "stats",
+                JsonOutput::Array(conversion.stats.iter().map(stats_to_json).collect()),
+            ),
+            (
+                "warnings",
+                JsonOutput::Array(
+                    conversion
+                        .warnings
+                        .iter()
+                        .map(|w| {
+                            JsonOutput::object(vec![
+                                ("path", JsonOutput::string(w.path.as_str())),
+                                ("message", JsonOutput::string(w.message.as_str())),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            ("history", history_to_json(&self.history)),
+        ])
+        .to_pretty_string()
+    }
+
+    /// Writes the history of the snapshot as json, the way it is written in
+    /// the snapshot.
+    pub fn history_json(&self) -> String {
+        history_to_json(&self.history).to_pretty_string()
+    }
+
+    /// Reads a snapshot written by [`Snapshot::to_json`].
+    pub fn parse(text: &str) -> Result<Snapshot, SnapshotError> {
+        let root = parse_json(text).map_err(|e| SnapshotError {
+            message: format!("Invalid json: {}", e),
+        })?;
+        if root.get("format").and_then(JsonNode::as_str) != Some(FORMAT) {
+            return error("Not a json2rust snapshot");
+        }
+        match field(&root, "format_version")? {
+            JsonNode::Int(version) if *version <= FORMAT_VERSION as i128 => {}
+            _ => return error("The snapshot was made by a newer json2rust, upgrade to read it"),
+        }
+        let samples = array(&root, "samples")?
+            .iter()
+            .map(|sample| {
+                let hash = string(sample, "hash")?;
+                Ok(SnapshotSample {
+                    name: string(sample, "name")?.to_string(),
+                    hash: u64::from_str_radix(hash, 16).map_err(|_| SnapshotError {
+                        message: format!("Invalid sample hash '{}'", hash),
+                    })?,
+                })
+            })
+            .collect::<Result<_, SnapshotError>>()?;
+        let warnings = array(&root, "warnings")?
+            .iter()
+            .map(|w| Ok(Warning::new(string(w, "path")?, string(w, "message")?)))
+            .collect::<Result<_, SnapshotError>>()?;
+        Ok(Snapshot {
+            conversion: Conversion {
+                structure: read_structure(field(&root, "structure")?)?,
+                warnings,
+                stats: array(&root, "stats")?
+                    .iter()
+                    .map(read_stats)
+                    .collect::<Result<_, _>>()?,
+                unique_shapes: match root.get("unique_shapes") {
+                    Some(JsonNode::Int(n)) if *n >= 0 => Some(*n as usize),
+                    _ => None,
+                },
+            },
+            samples,
+            version: string(&root, "version")?.to_string(),
+            // Snapshots written before histories were kept have none
+            history: match root.get("history") {
+                None => Vec::new(),
+                Some(_) => array(&root, "history")?
+                    .iter()
+                    .map(read_revision)
+                    .collect::<Result<_, _>>()?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert_samples_json_with_warnings, ConvertOptions};
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let samples = [
+            r#"{"id": 1, "email": "a@b.dk", "tags": ["x", "y"], "price": 1.5,
+                "scores": {"": 1, "b": 2},
+                "value": 1, "ip": "127.0.0.1"}"#,
+            r#"{"id": 2, "email": null, "tags": [], "price": 2, "value": "one", "ip": "::1"}"#,
+        ];
+        let mut options = ConvertOptions::default();
+        options.register_detector(crate::IpAddrDetector);
+        let conversion = convert_samples_json_with_warnings(&samples, &options).unwrap();
+        let snapshot = Snapshot::new(
+            conversion,
+            &[("a.json", samples[0]), ("b.json", samples[1])],
+        );
+
+        let read = Snapshot::parse(&snapshot.to_json()).unwrap();
+
+        assert_eq!(read, snapshot);
+        assert_eq!(read.samples[1], SnapshotSample::new("b.json", samples[1]));
+        assert!(matches!(
+            read.conversion
+                .structure
+                .fields()
+                .unwrap()
+                .iter()
+                .find(|field| field.key() == "scores")
+                .unwrap()
+                .value()
+                .non_optional(),
+            JsonStructure::Dictionary(_)
+        ));
+    }
+
+    #[test]
+    fn merges_samples_into_a_snapshot() {
+        let options = ConvertOptions::default();
+        let first = r#"{"id": 1, "name": "a"}"#;
+        let second = r#"{"id": 2.5, "name": "b", "tags": []}"#;
+        let mut snapshot = Snapshot::new(
+            convert_samples_json_with_warnings(&[first], &options).unwrap(),
+            &[("a.json", first)],
+        );
+        assert!(snapshot.has_sample(first));
+        assert!(!snapshot.has_sample(second));
+
+        snapshot.update(
+            convert_samples_json_with_warnings(&[second], &options).unwrap(),
+            &[("b.json", second)],
+            1_617_280_200,
+        );
+
+        let together = convert_samples_json_with_warnings(&[first, second], &options).unwrap();
+        assert_eq!(snapshot.conversion.structure, together.structure);
+        let id = |stats: &[PathStats]| stats.iter().find(|s| s.path == "/id").cloned();
+        assert_eq!(
+            id(&snapshot.conversion.stats).unwrap().value_range,
+            Some((1.0, 2.5))
+        );
+        let tags = |stats: &[PathStats]| stats.iter().find(|s| s.path == "/tags").cloned();
+        assert_eq!(
+            tags(&snapshot.conversion.stats).map(|s| (s.present_count, s.parent_count)),
+            Some((1, 2))
+        );
+        assert_eq!(snapshot.samples.len(), 2);
+        assert_eq!(
+            snapshot.history[0].to_string(),
+            "2021-04-01 12:30 UTC, 1 sample (b.json)\n  \
+             widened /id from int to float\n  \
+             added /tags: optional array of unknown\n"
+        );
+        assert_eq!(Snapshot::parse(&snapshot.to_json()).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert_eq!(
+            Snapshot::parse(r#"{"root": "Root"}"#).unwrap_err().message,
+            "Not a json2rust snapshot"
+        );
+        assert_eq!(
+            Snapshot::parse(r#"{"format": "json2rust-ir", "format_version": 2}"#)
+                .unwrap_err()
+                .message,
+            "The snapshot was made by a newer json2rust, upgrade to read it"
+        );
+    }
+}
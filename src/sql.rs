@@ -0,0 +1,287 @@
+use crate::backend::{Artifact, CodegenBackend};
+use crate::inference::{I128, U64};
+use crate::naming::{to_snake_case, transliterate, unique_name};
+use crate::options::ConvertOptions;
+use crate::pointer::{child_path, element_path};
+use crate::warnings::Warning;
+use crate::{JsonStructure, JsonValue};
+
+/// Postgres column types for custom types, by the name of the type without its
+/// path.
+const CUSTOM_COLUMN_TYPES: &[(&str, &str)] = &[
+    ("DateTime", "timestamptz"),
+    ("NaiveDateTime", "timestamp"),
+    ("NaiveDate", "date"),
+    ("NaiveTime", "time"),
+    ("Uuid", "uuid"),
+    ("Decimal", "numeric"),
+    ("IpAddr", "inet"),
+];
+
+/// Keywords that can't be column names without quoting them.
+const RESERVED_WORDS: &[&str] = &[
+    "all",
+    "and",
+    "any",
+    "as",
+    "asc",
+    "both",
+    "case",
+    "cast",
+    "check",
+    "column",
+    "constraint",
+    "create",
+    "default",
+    "desc",
+    "distinct",
+    "do",
+    "else",
+    "end",
+    "false",
+    "for",
+    "foreign",
+    "from",
+    "grant",
+    "group",
+    "having",
+    "in",
+    "into",
+    "is",
+    "join",
+    "limit",
+    "not",
+    "null",
+    "offset",
+    "on",
+    "or",
+    "order",
+    "primary",
+    "references",
+    "select",
+    "table",
+    "then",
+    "to",
+    "true",
+    "union",
+    "unique",
+    "user",
+    "using",
+    "when",
+    "where",
+    "with",
+];
+
+/// The `CREATE TABLE` statement, in the Postgres dialect, for the records of
+/// `structure`, a table named after `table_name`, along with warnings for the
+/// values that had to be stored as `jsonb`.
+///
+/// The records are the elements of samples that are arrays, and each field of
+/// them becomes a column, which is `NOT NULL` unless the field was null or
+/// missing somewhere. Arrays of scalars become Postgres arrays, while nested
+/// objects, maps and values of mixed types don't fit a column of their own.
+///
+/// ```
+/// let structure = json2rust::convert_sample_json(
+///     r#"[{"id": 1, "userName": "a", "tags": ["x"], "address": {"city": "Oslo"}}]"#,
+/// )
+/// .unwrap();
+/// let (sql, warnings) = json2rust::structure_to_sql(&structure, "Users");
+///
+/// assert_eq!(
+///     sql,
+///     "CREATE TABLE users (\n    id bigint NOT NULL,\n    user_name text NOT NULL,\n    \
+///      tags text[] NOT NULL,\n    address jsonb NOT NULL\n);\n"
+/// );
+/// assert_eq!(warnings[0].path, "/*/address");
+/// ```
+pub fn structure_to_sql(structure: &JsonStructure, table_name: &str) -> (String, Vec<Warning>) {
+    let mut root = structure.non_optional();
+    let mut pointer = String::new();
+    while let JsonStructure::Array(inner) = root {
+        root = inner.non_optional();
+        pointer = element_path(&pointer);
+    }
+    let columns: Vec<(&str, &JsonStructure, String)> = match root {
+        JsonStructure::Object(pairs) => pairs
+            .iter()
+            .map(|pair| (pair.key(), pair.value(), child_path(&pointer, pair.key())))
+            .collect(),
+        // Records that aren't objects are a single column
+        other => vec![("value", other, pointer)],
+    };
+
+    let mut warnings = Vec::new();
+    let mut taken = Vec::new();
+    let mut definitions = Vec::new();
+    for (position, (key, structure, pointer)) in columns.into_iter().enumerate() {
+        let name = unique_name(column_name(key, position), &mut taken);
+        let column_type = match column_type(structure.non_optional()) {
+            Some(column_type) => column_type,
+            None => {
+                warnings.push(Warning::new(
+                    pointer,
+                    format!(
+                        "`{}` is {}, which is stored in a jsonb column",
+                        key.escape_debug(),
+                        description(structure.non_optional())
+                    ),
+                ));
+                "jsonb".to_string()
+            }
+        };
+        let nullable = matches!(
+            structure,
+            JsonStructure::Optional(_) | JsonStructure::Unknown
+        );
+        definitions.push(format!(
+            "    {} {}{}",
+            quoted(&name),
+            column_type,
+            if nullable { "" } else { " NOT NULL" }
+        ));
+    }
+    let sql = format!(
+        "CREATE TABLE {} (\n{}\n);\n",
+        quoted(&to_snake_case(table_name)),
+        definitions.join(",\n")
+    );
+    (sql, warnings)
+}
+
+/// The snake case column name of `key`, the field at `position`.
+fn column_name(key: &str, position: usize) -> String {
+    match to_snake_case(&transliterate(key)) {
+        name if name.is_empty() => format!("column_{}", position + 1),
+        name if name.starts_with(|c: char| c.is_ascii_digit()) => format!("_{}", name),
+        name => name,
+    }
+}
+
+fn quoted(name: &str) -> String {
+    if RESERVED_WORDS.contains(&name) {
+        format!("\"{}\"", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// The type of a column holding `structure`, if it fits one.
+fn column_type(structure: &JsonStructure) -> Option<String> {
+    match structure {
+        JsonStructure::Value(JsonValue::Int) => Some("bigint".to_string()),
+        JsonStructure::Value(JsonValue::Float) => Some("double precision".to_string()),
+        JsonStructure::Value(JsonValue::Bool) => Some("boolean".to_string()),
+        JsonStructure::Value(JsonValue::String) => Some("text".to_string()),
+        JsonStructure::Value(JsonValue::Custom(rust_type)) if rust_type.path() == U64 => {
+            Some("numeric(20)".to_string())
+        }
+        JsonStructure::Value(JsonValue::Custom(rust_type)) if rust_type.path() == I128 => {
+            Some("numeric(39)".to_string())
+        }
+        JsonStructure::Value(JsonValue::Custom(rust_type)) => {
+            let name = rust_type.path().split('<').next().unwrap_or_default();
+            let name = name.rsplit("::").next().unwrap_or_default();
+            let column_type = CUSTOM_COLUMN_TYPES
+                .iter()
+                .find(|(custom, _)| *custom == name)
+                .map_or("text", |(_, column_type)| column_type);
+            Some(column_type.to_string())
+        }
+        // Postgres arrays can't hold nulls apart from their other values, or
+        // be nested in a way that differs from multidimensional arrays
+        JsonStructure::Array(inner) => match inner.as_ref() {
+            JsonStructure::Value(_) => column_type(inner).map(|t| format!("{}[]", t)),
+            _ => None,
+        },
+        JsonStructure::Optional(inner) => column_type(inner),
+        JsonStructure::Object(_)
+        | JsonStructure::Dictionary(_)
+        | JsonStructure::Union(_)
+        | JsonStructure::Unknown => None,
+    }
+}
+
+/// What a value without a column type of its own is, for warnings.
+fn description(structure: &JsonStructure) -> &'static str {
+    match structure {
+        JsonStructure::Object(_) => "a nested object",
+        JsonStructure::Dictionary(_) => "a map",
+        JsonStructure::Array(_) => "an array of nested values",
+        JsonStructure::Union(_) => "of mixed types",
+        _ => "of an unknown type",
+    }
+}
+
+/// Emits the `CREATE TABLE` statement of `structure_to_sql`, as a single
+/// `<root_name>.sql` file, with the warnings about `jsonb` columns as comments
+/// above it.
+pub struct SqlBackend;
+
+impl CodegenBackend for SqlBackend {
+    fn generate(
+        &self,
+        structure: &JsonStructure,
+        root_name: &str,
+        _options: &ConvertOptions,
+    ) -> Vec<Artifact> {
+        let (sql, warnings) = structure_to_sql(structure, root_name);
+        let mut contents = String::new();
+        for warning in warnings {
+            contents.push_str(&format!("-- {}\n", warning));
+        }
+        contents.push_str(&sql);
+        vec![Artifact::new(
+            format!("{}.sql", to_snake_case(root_name)),
+            contents,
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RustType;
+
+    #[test]
+    fn creates_tables_for_flat_records() {
+        let structure = JsonStructure::array(
+            JsonStructure::object()
+                .field("id", JsonStructure::custom(RustType::new(U64)))
+                .optional_field("order", JsonStructure::float())
+                .field(
+                    "createdAt",
+                    JsonStructure::custom(RustType::new("chrono::DateTime<chrono::Utc>")),
+                )
+                .field("tags", JsonStructure::array(JsonStructure::string()))
+                .field(
+                    "lines",
+                    JsonStructure::array(JsonStructure::object().build()),
+                )
+                .field(
+                    "labels",
+                    JsonStructure::dictionary("labels", JsonStructure::string()),
+                )
+                .field("", JsonStructure::Unknown)
+                .build(),
+        );
+        let artifacts = SqlBackend.generate(&structure, "Order", &ConvertOptions::default());
+
+        assert_eq!(artifacts[0].file_name, "order.sql");
+        assert_eq!(
+            artifacts[0].contents,
+            "-- /*/lines: `lines` is an array of nested values, which is stored in a jsonb column\n\
+             -- /*/labels: `labels` is a map, which is stored in a jsonb column\n\
+             -- /*/: `` is of an unknown type, which is stored in a jsonb column\n\
+             CREATE TABLE \"order\" (\n    \
+             id numeric(20) NOT NULL,\n    \
+             \"order\" double precision,\n    \
+             created_at timestamptz NOT NULL,\n    \
+             tags text[] NOT NULL,\n    \
+             lines jsonb NOT NULL,\n    \
+             labels jsonb NOT NULL,\n    \
+             column_7 jsonb\n\
+             );\n"
+        );
+    }
+}
@@ -0,0 +1,613 @@
+use crate::backend::{Artifact, CodegenBackend};
+use crate::codegen::union_variant_names;
+use crate::inference::{I128, U64};
+use crate::naming::{field_name, to_snake_case, unique_field_names};
+use crate::options::ConvertOptions;
+use crate::symbols::SymbolTable;
+use crate::{JsonPair, JsonStructure, JsonValue};
+
+/// The parser the generated `FromJson` impls read json with, emitted once
+/// after the types.
+const RUNTIME: &str = r#"
+/// An error reading json, at a byte offset of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+/// Reads `T` from the json document `json`.
+pub fn from_json<T: FromJson>(json: &str) -> Result<T, ParseError> {
+    let mut parser = Parser {
+        input: json.as_bytes(),
+        position: 0,
+    };
+    let value = T::from_json(&mut parser)?;
+    parser.skip_whitespace();
+    if parser.position < parser.input.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+/// A value read from json.
+pub trait FromJson: Sized {
+    fn from_json(parser: &mut Parser) -> Result<Self, ParseError>;
+}
+
+/// A json value of a shape that couldn't be inferred from the sample.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+/// A recursive descent json parser.
+pub struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            position: self.position,
+            message: message.to_string(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.input.get(self.position) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.input.get(self.position).copied()
+    }
+
+    fn literal(&mut self, literal: &str) -> bool {
+        self.skip_whitespace();
+        if self.input[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    /// Whether the next value is null, skipping it if so.
+    fn null(&mut self) -> bool {
+        self.literal("null")
+    }
+
+    fn number(&mut self) -> Result<&'a str, ParseError> {
+        self.skip_whitespace();
+        let start = self.position;
+        while let Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') =
+            self.input.get(self.position)
+        {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(self.error("expected a number"));
+        }
+        Ok(std::str::from_utf8(&self.input[start..self.position]).unwrap_or_default())
+    }
+
+    fn parse_number<T: std::str::FromStr>(&mut self) -> Result<T, ParseError> {
+        let start = self.position;
+        let text = self.number()?;
+        text.parse().map_err(|_| {
+            self.position = start;
+            self.error("invalid number")
+        })
+    }
+
+    fn hex_escape(&mut self) -> Result<u32, ParseError> {
+        let digits = self
+            .input
+            .get(self.position..self.position + 4)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u32::from_str_radix(d, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.position += 4;
+        Ok(digits)
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = *self
+                .input
+                .get(self.position)
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self
+                        .input
+                        .get(self.position)
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.position += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex_escape()?;
+                            if (0xd800..0xdc00).contains(&code) && self.literal("\\u") {
+                                let low = self.hex_escape()?;
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00));
+                            }
+                            char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid utf-8"))
+    }
+
+    /// Reads an object, calling `field` with the key of each field to read its
+    /// value.
+    pub fn object<F>(&mut self, mut field: F) -> Result<(), ParseError>
+    where
+        F: FnMut(&mut Parser<'a>, String) -> Result<(), ParseError>,
+    {
+        self.expect(b'{')?;
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(());
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(b':')?;
+            field(self, key)?;
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    /// Reads an array, calling `element` to read each element.
+    pub fn array<F>(&mut self, mut element: F) -> Result<(), ParseError>
+    where
+        F: FnMut(&mut Parser<'a>) -> Result<(), ParseError>,
+    {
+        self.expect(b'[')?;
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(());
+        }
+        loop {
+            element(self)?;
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    /// Reads `T`, or rewinds to where it started if it isn't one.
+    pub fn attempt<T: FromJson>(&mut self) -> Option<T> {
+        let start = self.position;
+        let value = T::from_json(self).ok();
+        if value.is_none() {
+            self.position = start;
+        }
+        value
+    }
+
+    pub fn missing(&self, key: &str) -> ParseError {
+        self.error(&format!("missing field `{}`", key))
+    }
+
+    pub fn no_variant(&self, name: &str) -> ParseError {
+        self.error(&format!("expected a {}", name))
+    }
+}
+
+impl FromJson for String {
+    fn from_json(parser: &mut Parser) -> Result<String, ParseError> {
+        parser.string()
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(parser: &mut Parser) -> Result<bool, ParseError> {
+        if parser.literal("true") {
+            Ok(true)
+        } else if parser.literal("false") {
+            Ok(false)
+        } else {
+            Err(parser.error("expected a bool"))
+        }
+    }
+}
+
+impl FromJson for i64 {
+    fn from_json(parser: &mut Parser) -> Result<i64, ParseError> {
+        parser.parse_number()
+    }
+}
+
+impl FromJson for u64 {
+    fn from_json(parser: &mut Parser) -> Result<u64, ParseError> {
+        parser.parse_number()
+    }
+}
+
+impl FromJson for i128 {
+    fn from_json(parser: &mut Parser) -> Result<i128, ParseError> {
+        parser.parse_number()
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(parser: &mut Parser) -> Result<f64, ParseError> {
+        parser.parse_number()
+    }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(parser: &mut Parser) -> Result<Option<T>, ParseError> {
+        if parser.null() {
+            Ok(None)
+        } else {
+            T::from_json(parser).map(Some)
+        }
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(parser: &mut Parser) -> Result<Vec<T>, ParseError> {
+        let mut elements = Vec::new();
+        parser.array(|parser| {
+            elements.push(T::from_json(parser)?);
+            Ok(())
+        })?;
+        Ok(elements)
+    }
+}
+
+impl<T: FromJson> FromJson for std::collections::BTreeMap<String, T> {
+    fn from_json(parser: &mut Parser) -> Result<Self, ParseError> {
+        let mut entries = std::collections::BTreeMap::new();
+        parser.object(|parser, key| {
+            entries.insert(key, T::from_json(parser)?);
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+}
+
+impl FromJson for JsonValue {
+    fn from_json(parser: &mut Parser) -> Result<JsonValue, ParseError> {
+        match parser.peek() {
+            Some(b'n') if parser.null() => Ok(JsonValue::Null),
+            Some(b't' | b'f') => bool::from_json(parser).map(JsonValue::Bool),
+            Some(b'"') => parser.string().map(JsonValue::String),
+            Some(b'[') => Vec::from_json(parser).map(JsonValue::Array),
+            Some(b'{') => {
+                let mut fields = Vec::new();
+                parser.object(|parser, key| {
+                    fields.push((key, JsonValue::from_json(parser)?));
+                    Ok(())
+                })?;
+                Ok(JsonValue::Object(fields))
+            }
+            _ => f64::from_json(parser).map(JsonValue::Number),
+        }
+    }
+}
+"#;
+
+struct StandaloneGenerator<'a> {
+    symbols: &'a SymbolTable,
+    definitions: Vec<String>,
+    defined: Vec<String>,
+    path: Vec<String>,
+}
+
+impl<'a> StandaloneGenerator<'a> {
+    fn type_for(&mut self, structure: &JsonStructure) -> String {
+        match structure {
+            JsonStructure::Array(inner) => format!("Vec<{}>", self.type_for(inner)),
+            JsonStructure::Dictionary(pair) => format!(
+                "std::collections::BTreeMap<String, {}>",
+                self.type_for(pair.value())
+            ),
+            JsonStructure::Object(pairs) => self.emit_struct(pairs),
+            JsonStructure::Union(variants) => self.emit_enum(variants),
+            JsonStructure::Optional(inner) => format!("Option<{}>", self.type_for(inner)),
+            JsonStructure::Value(JsonValue::String) => "String".to_string(),
+            JsonStructure::Value(JsonValue::Int) => "i64".to_string(),
+            JsonStructure::Value(JsonValue::Float) => "f64".to_string(),
+            JsonStructure::Value(JsonValue::Bool) => "bool".to_string(),
+            JsonStructure::Value(JsonValue::Custom(rust_type))
+                if rust_type.path() == U64 || rust_type.path() == I128 =>
+            {
+                rust_type.path().to_string()
+            }
+            // Other custom types would need a dependency to parse, and are
+            // usually sent as strings
+            JsonStructure::Value(JsonValue::Custom(_)) => "String".to_string(),
+            JsonStructure::Unknown => "JsonValue".to_string(),
+        }
+    }
+
+    fn reserve_definition(&mut self) -> (String, Option<usize>) {
+        let name = self.symbols.name_at(&self.path).to_string();
+        if self.defined.contains(&name) {
+            return (name, None);
+        }
+        self.defined.push(name.clone());
+        self.definitions.push(String::new());
+        (name, Some(self.definitions.len() - 1))
+    }
+
+    fn emit_struct(&mut self, pairs: &[JsonPair]) -> String {
+        let (struct_name, index) = match self.reserve_definition() {
+            (name, Some(index)) => (name, index),
+            (name, None) => return name,
+        };
+
+        // The parser and keys are prefixed, so they don't clash with the
+        // locals named after the fields
+        let mut fields = Vec::new();
+        let keys: Vec<&str> = pairs.iter().map(JsonPair::key).collect();
+        let names: Vec<String> = keys.iter().map(|key| field_name(key)).collect();
+        let names = unique_field_names(&keys, &names);
+        for (position, (pair, name)) in pairs.iter().zip(names).enumerate() {
+            let field = match name {
+                name if name.is_empty() => format!("field_{}", position + 1),
+                name => name,
+            };
+            self.path.push(pair.key().to_string());
+            let field_type = self.type_for(pair.value());
+            self.path.pop();
+            let optional = matches!(pair.value(), JsonStructure::Optional(_));
+            fields.push((pair.key(), field, field_type, optional));
+        }
+
+        let mut body = format!(
+            "#[derive(Debug, Clone, PartialEq)]\npub struct {} {{\n",
+            struct_name
+        );
+        for (_, field, field_type, _) in &fields {
+            body.push_str(&format!("    pub {}: {},\n", field, field_type));
+        }
+        body.push_str(&format!(
+            "}}\n\nimpl FromJson for {name} {{\n    \
+             fn from_json(__parser: &mut Parser) -> Result<{name}, ParseError> {{\n",
+            name = struct_name
+        ));
+        for (_, field, _, _) in &fields {
+            body.push_str(&format!("        let mut {} = None;\n", field));
+        }
+        body.push_str(
+            "        __parser.object(|__parser, __key| {\n            match __key.as_str() {\n",
+        );
+        for (key, field, _, _) in &fields {
+            body.push_str(&format!(
+                "                {:?} => {} = Some(FromJson::from_json(__parser)?),\n",
+                key, field
+            ));
+        }
+        body.push_str(
+            "                _ => {\n                    JsonValue::from_json(__parser)?;\n                }\n            \
+             }\n            Ok(())\n        })?;\n",
+        );
+        body.push_str(&format!("        Ok({} {{\n", struct_name));
+        for (key, field, _, optional) in &fields {
+            if *optional {
+                body.push_str(&format!(
+                    "            {}: {}.unwrap_or(None),\n",
+                    field, field
+                ));
+            } else {
+                body.push_str(&format!(
+                    "            {}: {}.ok_or_else(|| __parser.missing({:?}))?,\n",
+                    field, field, key
+                ));
+            }
+        }
+        body.push_str("        })\n    }\n}\n");
+
+        self.definitions[index] = body;
+        struct_name
+    }
+
+    /// Unions are read by trying each variant in turn, like serde's untagged
+    /// enums.
+    fn emit_enum(&mut self, variants: &[JsonStructure]) -> String {
+        let (enum_name, index) = match self.reserve_definition() {
+            (name, Some(index)) => (name, index),
+            (name, None) => return name,
+        };
+
+        let mut declarations = String::new();
+        let mut attempts = String::new();
+        for (variant, variant_name) in variants.iter().zip(union_variant_names(variants)) {
+            self.path.push(variant_name.clone());
+            let variant_type = self.type_for(variant);
+            self.path.pop();
+            declarations.push_str(&format!("    {}({}),\n", variant_name, variant_type));
+            attempts.push_str(&format!(
+                "        if let Some(value) = parser.attempt::<{}>() {{\n            \
+                 return Ok({}::{}(value));\n        }}\n",
+                variant_type, enum_name, variant_name
+            ));
+        }
+        self.definitions[index] = format!(
+            "#[derive(Debug, Clone, PartialEq)]\npub enum {name} {{\n{declarations}}}\n\n\
+             impl FromJson for {name} {{\n    \
+             fn from_json(parser: &mut Parser) -> Result<{name}, ParseError> {{\n\
+             {attempts}        Err(parser.no_variant({name:?}))\n    }}\n}}\n",
+            name = enum_name,
+            declarations = declarations,
+            attempts = attempts
+        );
+        enum_name
+    }
+}
+
+/// Emits rust types along with a hand-written recursive descent parser for
+/// them, as a single `<root_name>.rs` file, for consumers that can't depend
+/// on serde. Documents are read with the generated `from_json` function, and
+/// values of an unknown type as the generated `JsonValue`. Custom types other
+/// than wide integers are kept as strings.
+pub struct StandaloneBackend;
+
+impl CodegenBackend for StandaloneBackend {
+    fn generate(
+        &self,
+        structure: &JsonStructure,
+        root_name: &str,
+        options: &ConvertOptions,
+    ) -> Vec<Artifact> {
+        // Like the serde types, arrays at the root produce a type for their
+        // elements, read as a `Vec` of them
+        let mut root = structure;
+        while let JsonStructure::Array(inner) = root {
+            root = inner;
+        }
+        let symbols = SymbolTable::build(
+            root,
+            root_name,
+            options.naming_strategy.as_ref(),
+            &options.abbreviations,
+            options.acronyms,
+        );
+        let mut generator = StandaloneGenerator {
+            symbols: &symbols,
+            definitions: Vec::new(),
+            defined: Vec::new(),
+            path: Vec::new(),
+        };
+        generator.type_for(root);
+
+        let mut output = String::new();
+        for definition in &generator.definitions {
+            output.push_str(definition);
+            output.push('\n');
+        }
+        output.push_str(RUNTIME.trim_start());
+        vec![Artifact::new(
+            format!("{}.rs", to_snake_case(root_name)),
+            output,
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    fn generate(json: &str) -> String {
+        let structure = convert_sample_json(json).unwrap();
+        let artifacts = StandaloneBackend.generate(&structure, "Order", &ConvertOptions::default());
+        assert_eq!(artifacts[0].file_name, "order.rs");
+        artifacts[0].contents.clone()
+    }
+
+    #[test]
+    fn generates_types_with_a_parser() {
+        let result = generate(r#"{"id": 1, "userName": "a", "note": null, "value": [1, "a"]}"#);
+
+        assert_eq!(
+            &result[..result.find("/// An error reading json").unwrap()],
+            r#"#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub id: i64,
+    pub user_name: String,
+    pub note: Option<JsonValue>,
+    pub value: Vec<Value>,
+}
+
+impl FromJson for Order {
+    fn from_json(__parser: &mut Parser) -> Result<Order, ParseError> {
+        let mut id = None;
+        let mut user_name = None;
+        let mut note = None;
+        let mut value = None;
+        __parser.object(|__parser, __key| {
+            match __key.as_str() {
+                "id" => id = Some(FromJson::from_json(__parser)?),
+                "userName" => user_name = Some(FromJson::from_json(__parser)?),
+                "note" => note = Some(FromJson::from_json(__parser)?),
+                "value" => value = Some(FromJson::from_json(__parser)?),
+                _ => {
+                    JsonValue::from_json(__parser)?;
+                }
+            }
+            Ok(())
+        })?;
+        Ok(Order {
+            id: id.ok_or_else(|| __parser.missing("id"))?,
+            user_name: user_name.ok_or_else(|| __parser.missing("userName"))?,
+            note: note.unwrap_or(None),
+            value: value.ok_or_else(|| __parser.missing("value"))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    String(String),
+}
+
+impl FromJson for Value {
+    fn from_json(parser: &mut Parser) -> Result<Value, ParseError> {
+        if let Some(value) = parser.attempt::<i64>() {
+            return Ok(Value::Integer(value));
+        }
+        if let Some(value) = parser.attempt::<String>() {
+            return Ok(Value::String(value));
+        }
+        Err(parser.no_variant("Value"))
+    }
+}
+
+"#
+        );
+        assert!(!result.contains("serde"));
+    }
+}
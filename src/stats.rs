@@ -0,0 +1,508 @@
+use crate::hash::Fnv;
+use crate::json_writer::JsonOutput;
+use crate::parser::JsonNode;
+use crate::shared::Span;
+use alloc::{
+    collections::BTreeSet,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::convert::TryFrom;
+
+/// A format that every string observed at a path was in.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum StringFormat {
+    Email,
+    Url,
+    /// A json object or array, with `ConvertOptions::set_json_strings`
+    Json,
+}
+
+/// The most example values kept for a path.
+const MAX_EXAMPLES: usize = 3;
+
+/// The most distinct integers kept for a path, beyond which its values aren't
+/// a fixed set.
+pub(crate) const MAX_INT_VALUES: usize = 16;
+
+/// The most distinct strings kept for a path, beyond which its values aren't
+/// a fixed set.
+pub(crate) const MAX_STRING_VALUES: usize = 16;
+
+/// The number of observations that gives a confidence of one half.
+const HALF_CONFIDENCE_OBSERVATIONS: f64 = 3.0;
+
+/// Counts gathered for a single path while inferring a sample.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PathStats {
+    /// Json pointer to the value, with `*` standing in for array elements
+    pub path: String,
+    /// Number of values observed, including nulls
+    pub count: usize,
+    pub null_count: usize,
+    /// For object fields, the number of objects the field was present in. For
+    /// anything else, the same as `count`.
+    pub present_count: usize,
+    /// For object fields, the number of objects that could have had the field.
+    /// For anything else, the same as `count`.
+    pub parent_count: usize,
+    /// The shortest and longest strings observed, in characters
+    pub length_range: Option<(usize, usize)>,
+    /// The smallest and largest numbers observed
+    pub value_range: Option<(f64, f64)>,
+    pub string_format: Option<StringFormat>,
+    /// For arrays of only strings and integers, whether none of them had the
+    /// same element twice
+    pub unique_elements: Option<bool>,
+    /// The fewest and most elements of the arrays observed
+    pub element_counts: Option<(usize, usize)>,
+    /// The distinct integers observed, in order, when every value that isn't
+    /// null was one of at most 16 integers. Not gathered with
+    /// `ConvertOptions::set_redact_samples`.
+    pub int_values: Option<Vec<i64>>,
+    /// The distinct strings observed, in order, when every value that isn't
+    /// null was one of at most 16 strings. Not gathered with
+    /// `ConvertOptions::set_redact_samples`.
+    pub string_values: Option<Vec<String>>,
+    /// Where the first value at the path is in the sample, for samples read
+    /// from json
+    pub span: Option<Span>,
+    /// The first few distinct strings and numbers observed, written as json.
+    /// With `ConvertOptions::set_redact_samples`, strings are replaced by a
+    /// hash of themselves and numbers by zero.
+    pub examples: Vec<String>,
+    /// What the value is, lifted from a metadata key of the sample with
+    /// `ConvertOptions::set_field_descriptions`
+    pub description: Option<String>,
+}
+
+impl PathStats {
+    pub fn new<S: Into<String>>(path: S, count: usize, null_count: usize) -> PathStats {
+        PathStats {
+            path: path.into(),
+            count,
+            null_count,
+            present_count: count,
+            parent_count: count,
+            length_range: None,
+            value_range: None,
+            string_format: None,
+            unique_elements: None,
+            element_counts: None,
+            int_values: None,
+            string_values: None,
+            span: None,
+            examples: Vec::new(),
+            description: None,
+        }
+    }
+
+    /// Gathers the stats for every value observed at `path`, redacting the
+    /// examples if `redact` is set.
+    pub(crate) fn observe<S: Into<String>>(
+        path: S,
+        nodes: &[&JsonNode],
+        redact: bool,
+    ) -> PathStats {
+        let null_count = nodes.iter().filter(|n| ***n == JsonNode::Null).count();
+        let strings: Vec<&str> = nodes.iter().filter_map(|n| n.as_str()).collect();
+        let numbers: Vec<f64> = nodes
+            .iter()
+            .filter_map(|n| match n {
+                JsonNode::Int(value) => Some(*value as f64),
+                JsonNode::Float(value) => Some(*value),
+                _ => None,
+            })
+            .collect();
+        let lengths = strings.iter().map(|s| s.chars().count());
+        let element_counts = nodes.iter().filter_map(|n| match n {
+            JsonNode::Array(elements) => Some(elements.len()),
+            _ => None,
+        });
+        PathStats {
+            length_range: lengths.clone().min().zip(lengths.max()),
+            value_range: numbers
+                .iter()
+                .cloned()
+                .reduce(f64::min)
+                .zip(numbers.iter().cloned().reduce(f64::max)),
+            string_format: string_format(&strings),
+            unique_elements: unique_elements(nodes),
+            element_counts: element_counts.clone().min().zip(element_counts.max()),
+            int_values: if redact { None } else { int_values(nodes) },
+            string_values: if redact { None } else { string_values(nodes) },
+            examples: examples(nodes, redact),
+            ..PathStats::new(path, nodes.len(), null_count)
+        }
+    }
+
+    /// The fraction of possible places the value was present in, from 0 to 1.
+    pub fn presence(&self) -> f64 {
+        if self.parent_count == 0 {
+            0.0
+        } else {
+            self.present_count as f64 / self.parent_count as f64
+        }
+    }
+
+    /// The fraction of the observed values that were null, from 0 to 1.
+    pub fn null_rate(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.null_count as f64 / self.count as f64
+        }
+    }
+
+    /// How much the inferred type at the path can be trusted, from 0 to 1. It
+    /// grows with the number of non-null values the type was inferred from, and
+    /// for object fields with the number of objects that decided whether the
+    /// field is optional, so a type seen only a few times should be reviewed by
+    /// hand.
+    pub fn confidence(&self) -> f64 {
+        let from = |observations: usize| {
+            observations as f64 / (observations as f64 + HALF_CONFIDENCE_OBSERVATIONS)
+        };
+        from(self.count - self.null_count).min(from(self.parent_count))
+    }
+}
+
+/// Merges the stats of the samples seen by `b` into the stats of the samples
+/// seen by `a`, as if they had all been inferred together. A field only one of
+/// them saw could still have been present in the objects of the other, which
+/// count as its parents.
+#[cfg(feature = "std")]
+pub(crate) fn merge_stats(a: Vec<PathStats>, b: Vec<PathStats>) -> Vec<PathStats> {
+    let objects = |stats: &[PathStats], path: &str| {
+        stats
+            .iter()
+            .find(|s| s.path == path)
+            .map_or(0, |s| s.count - s.null_count)
+    };
+    let parent = |path: &str| match path.rfind('/') {
+        Some(_) if path.ends_with("/*") => None,
+        Some(index) => Some(path[..index].to_string()),
+        None => None,
+    };
+    let mut merged: Vec<PathStats> = a.clone();
+    for stats in &mut merged {
+        match b.iter().find(|other| other.path == stats.path) {
+            Some(other) => stats.merge(other),
+            None => {
+                if let Some(parent) = parent(&stats.path) {
+                    stats.parent_count += objects(&b, &parent);
+                }
+            }
+        }
+    }
+    for stats in b {
+        if a.iter().any(|other| other.path == stats.path) {
+            continue;
+        }
+        let mut stats = stats;
+        if let Some(parent) = parent(&stats.path) {
+            stats.parent_count += objects(&a, &parent);
+        }
+        merged.push(stats);
+    }
+    merged
+}
+
+impl PathStats {
+    #[cfg(feature = "std")]
+    fn merge(&mut self, other: &PathStats) {
+        fn widest<T: PartialOrd + Copy>(a: Option<(T, T)>, b: Option<(T, T)>) -> Option<(T, T)> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some((
+                    if b.0 < a.0 { b.0 } else { a.0 },
+                    if b.1 > a.1 { b.1 } else { a.1 },
+                )),
+                (a, b) => a.or(b),
+            }
+        }
+        self.int_values = match (&self.int_values, &other.int_values) {
+            (Some(a), Some(b)) => {
+                let values: BTreeSet<i64> = a.iter().chain(b).cloned().collect();
+                Some(values.into_iter().collect()).filter(|v: &Vec<i64>| v.len() <= MAX_INT_VALUES)
+            }
+            // Stats that saw only nulls have no integers, rather than values
+            // that aren't
+            (Some(values), None) if other.count == other.null_count => Some(values.clone()),
+            (None, Some(values)) if self.count == self.null_count => Some(values.clone()),
+            _ => None,
+        };
+        self.string_values = match (&self.string_values, &other.string_values) {
+            (Some(a), Some(b)) => {
+                let values: BTreeSet<String> = a.iter().chain(b).cloned().collect();
+                Some(values.into_iter().collect())
+                    .filter(|v: &Vec<String>| v.len() <= MAX_STRING_VALUES)
+            }
+            (Some(values), None) if other.count == other.null_count => Some(values.clone()),
+            (None, Some(values)) if self.count == self.null_count => Some(values.clone()),
+            _ => None,
+        };
+        self.count += other.count;
+        self.null_count += other.null_count;
+        self.present_count += other.present_count;
+        self.parent_count += other.parent_count;
+        // Stats without a length range saw no strings to have a format
+        self.string_format = match (self.length_range, other.length_range) {
+            (None, _) => other.string_format,
+            (_, None) => self.string_format,
+            _ if self.string_format == other.string_format => self.string_format,
+            _ => None,
+        };
+        self.length_range = widest(self.length_range, other.length_range);
+        self.value_range = widest(self.value_range, other.value_range);
+        self.unique_elements = match (self.unique_elements, other.unique_elements) {
+            (Some(a), Some(b)) => Some(a && b),
+            (a, b) => a.or(b),
+        };
+        self.element_counts = widest(self.element_counts, other.element_counts);
+        self.span = self.span.or(other.span);
+        if self.description.is_none() {
+            self.description = other.description.clone();
+        }
+        for example in &other.examples {
+            if self.examples.len() < MAX_EXAMPLES && !self.examples.contains(example) {
+                self.examples.push(example.clone());
+            }
+        }
+    }
+}
+
+fn examples(nodes: &[&JsonNode], redact: bool) -> Vec<String> {
+    let mut examples = Vec::new();
+    for node in nodes {
+        let example = match node {
+            JsonNode::String(s) if redact => {
+                let mut hash = Fnv::new();
+                hash.write_str(s);
+                format!("\"{:08x}\"", hash.finish() >> 32)
+            }
+            JsonNode::String(s) => JsonOutput::string(s.as_str()).to_pretty_string(),
+            JsonNode::Int(_) if redact => "0".to_string(),
+            JsonNode::Float(_) if redact => "0.0".to_string(),
+            JsonNode::Int(value) => value.to_string(),
+            JsonNode::Float(value) => JsonOutput::Float(*value).to_pretty_string(),
+            _ => continue,
+        };
+        if !examples.contains(&example) {
+            examples.push(example);
+            if examples.len() == MAX_EXAMPLES {
+                break;
+            }
+        }
+    }
+    examples
+}
+
+fn int_values(nodes: &[&JsonNode]) -> Option<Vec<i64>> {
+    let mut values = BTreeSet::new();
+    for node in nodes {
+        match node {
+            JsonNode::Int(value) => {
+                values.insert(i64::try_from(*value).ok()?);
+            }
+            JsonNode::Null => {}
+            _ => return None,
+        }
+        if values.len() > MAX_INT_VALUES {
+            return None;
+        }
+    }
+    Some(values.into_iter().collect()).filter(|values: &Vec<i64>| !values.is_empty())
+}
+
+fn string_values(nodes: &[&JsonNode]) -> Option<Vec<String>> {
+    let mut values = BTreeSet::new();
+    for node in nodes {
+        match node {
+            JsonNode::String(value) => {
+                values.insert(value.to_string());
+            }
+            JsonNode::Null => {}
+            _ => return None,
+        }
+        if values.len() > MAX_STRING_VALUES {
+            return None;
+        }
+    }
+    Some(values.into_iter().collect()).filter(|values: &Vec<String>| !values.is_empty())
+}
+
+fn unique_elements(nodes: &[&JsonNode]) -> Option<bool> {
+    let mut unique = None;
+    for node in nodes {
+        let elements = match node {
+            JsonNode::Array(elements) => elements,
+            JsonNode::Null => continue,
+            _ => return None,
+        };
+        let mut seen = BTreeSet::new();
+        let mut repeated = false;
+        for element in elements {
+            let element = match element {
+                JsonNode::String(s) => (Some(s.as_str()), 0),
+                JsonNode::Int(value) => (None, *value),
+                _ => return None,
+            };
+            repeated |= !seen.insert(element);
+        }
+        unique = Some(unique.unwrap_or(true) && !repeated);
+    }
+    unique
+}
+
+fn is_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !value.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+fn is_url(value: &str) -> bool {
+    let rest = value
+        .strip_prefix("https://")
+        .or_else(|| value.strip_prefix("http://"));
+    rest.is_some_and(|rest| !rest.is_empty() && !rest.contains(char::is_whitespace))
+}
+
+fn string_format(values: &[&str]) -> Option<StringFormat> {
+    if values.is_empty() {
+        None
+    } else if values.iter().all(|v| is_email(v)) {
+        Some(StringFormat::Email)
+    } else if values.iter().all(|v| is_url(v)) {
+        Some(StringFormat::Url)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculates_rates() {
+        let stats = PathStats {
+            present_count: 3,
+            parent_count: 4,
+            ..PathStats::new("/a", 3, 1)
+        };
+
+        assert_eq!(stats.presence(), 0.75);
+        assert!((stats.null_rate() - 1.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(PathStats::new("/a", 0, 0).presence(), 0.0);
+        assert_eq!(PathStats::new("/a", 0, 0).null_rate(), 0.0);
+    }
+
+    #[test]
+    fn confidence_grows_with_observations() {
+        assert_eq!(PathStats::new("/a", 3, 0).confidence(), 0.5);
+        assert_eq!(PathStats::new("/a", 4, 1).confidence(), 0.5);
+        assert_eq!(PathStats::new("/a", 2, 2).confidence(), 0.0);
+        assert!(PathStats::new("/a", 100, 0).confidence() > 0.95);
+
+        let rarely_decided = PathStats {
+            parent_count: 1,
+            ..PathStats::new("/a", 1, 0)
+        };
+        assert_eq!(rarely_decided.confidence(), 0.25);
+    }
+
+    #[test]
+    fn observes_value_ranges_and_formats() {
+        let strings = [
+            JsonNode::String("a@b.io".into()),
+            JsonNode::String("long.name@example.com".into()),
+            JsonNode::Null,
+        ];
+        let stats = PathStats::observe("/a", &strings.iter().collect::<Vec<_>>(), false);
+
+        assert_eq!(stats.null_count, 1);
+        assert_eq!(stats.length_range, Some((6, 21)));
+        assert_eq!(stats.value_range, None);
+        assert_eq!(stats.string_format, Some(StringFormat::Email));
+        assert_eq!(stats.unique_elements, None);
+        assert_eq!(
+            stats.examples,
+            vec![
+                "\"a@b.io\"".to_string(),
+                "\"long.name@example.com\"".to_string()
+            ]
+        );
+
+        let numbers = [JsonNode::Int(3), JsonNode::Float(-1.5), JsonNode::Int(10)];
+        let stats = PathStats::observe("/b", &numbers.iter().collect::<Vec<_>>(), false);
+
+        assert_eq!(stats.value_range, Some((-1.5, 10.0)));
+        assert_eq!(stats.length_range, None);
+        assert_eq!(stats.examples, vec!["3", "-1.5", "10"]);
+    }
+
+    #[test]
+    fn observes_unique_elements() {
+        let observe = |json: &str| {
+            let nodes = match crate::parser::parse_json(json).unwrap() {
+                JsonNode::Array(nodes) => nodes,
+                _ => unreachable!(),
+            };
+            PathStats::observe("/a", &nodes.iter().collect::<Vec<_>>(), false).unique_elements
+        };
+
+        assert_eq!(observe(r#"[["a", "b"], null, ["a", 1], []]"#), Some(true));
+        assert_eq!(observe(r#"[["a", "b"], ["b", "b"]]"#), Some(false));
+        assert_eq!(observe(r#"[[1, 1.5]]"#), None);
+        assert_eq!(observe(r#"[["a"], "a"]"#), None);
+        assert_eq!(observe(r#"[null]"#), None);
+    }
+
+    #[test]
+    fn observes_element_counts() {
+        let nodes = match crate::parser::parse_json(r#"[[1, 2], null, [3], [4, 5, 6]]"#).unwrap() {
+            JsonNode::Array(nodes) => nodes,
+            _ => unreachable!(),
+        };
+        let stats = PathStats::observe("/a", &nodes.iter().collect::<Vec<_>>(), false);
+
+        assert_eq!(stats.element_counts, Some((1, 3)));
+        assert_eq!(stats.length_range, None);
+    }
+
+    #[test]
+    fn redacts_examples() {
+        let nodes = [
+            JsonNode::String("a@b.io".into()),
+            JsonNode::Int(3),
+            JsonNode::Float(-1.5),
+            JsonNode::String("a@b.io".into()),
+            JsonNode::Int(10),
+        ];
+        let stats = PathStats::observe("/a", &nodes.iter().collect::<Vec<_>>(), true);
+
+        assert_eq!(stats.examples, vec!["\"b7599c89\"", "0", "0.0"]);
+        assert_eq!(stats.string_format, Some(StringFormat::Email));
+        assert_eq!(stats.length_range, Some((6, 6)));
+    }
+
+    #[test]
+    fn detects_string_formats() {
+        assert_eq!(
+            string_format(&["https://a.com/x", "http://b.org"]),
+            Some(StringFormat::Url)
+        );
+        assert_eq!(string_format(&["a@b.com", "not an email"]), None);
+        assert_eq!(string_format(&["@b.com"]), None);
+        assert_eq!(string_format(&[]), None);
+    }
+}
@@ -0,0 +1,166 @@
+//! Restyling of the generated code, which is emitted with rustfmt's defaults,
+//! to match the style of the crate it goes into.
+
+use crate::options::ConvertOptions;
+
+/// The derives of the standard library, kept apart from the derives of other
+/// crates when splitting derives.
+const STD_DERIVES: [&str; 9] = [
+    "Debug",
+    "Clone",
+    "Copy",
+    "PartialEq",
+    "Eq",
+    "PartialOrd",
+    "Ord",
+    "Hash",
+    "Default",
+];
+
+/// Whether the options ask for anything but the default style.
+pub(crate) fn is_restyled(options: &ConvertOptions) -> bool {
+    options.indent_width != 4
+        || options.hard_tabs
+        || !options.trailing_commas
+        || options.inline_field_attributes
+        || options.split_derives
+}
+
+/// Restyles `code`, which is indented with four spaces a level, according to
+/// the options. Returns the code along with the line each line of `code` ended
+/// up on, both starting at 0.
+pub(crate) fn restyle(code: &str, options: &ConvertOptions) -> (String, Vec<usize>) {
+    let lines: Vec<&str> = code.lines().collect();
+    let mut output: Vec<String> = Vec::new();
+    let mut line_map = Vec::with_capacity(lines.len());
+    // Attributes waiting to be put on the line of the field they are for
+    let mut attributes: Vec<&str> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        let next = lines.get(index + 1).map(|next| next.trim_start());
+
+        if options.inline_field_attributes
+            && indent > 0
+            && is_attribute(trimmed)
+            && next.is_some_and(|next| is_attribute(next) || is_field(next))
+        {
+            attributes.push(trimmed);
+            line_map.push(output.len());
+            continue;
+        }
+        let mut restyled = indentation(indent, options);
+        for attribute in attributes.drain(..) {
+            restyled.push_str(attribute);
+            restyled.push(' ');
+        }
+        let closes_list = next.is_some_and(|next| {
+            next.starts_with(['}', ')', ']']) && lines[index + 1].len() - next.len() < indent
+        });
+        match trimmed.strip_suffix(',') {
+            Some(item) if !options.trailing_commas && closes_list && !trimmed.starts_with("//") => {
+                restyled.push_str(item)
+            }
+            _ => restyled.push_str(trimmed),
+        }
+        line_map.push(output.len());
+        match split_derives(trimmed).filter(|_| options.split_derives) {
+            Some((std, others)) => {
+                output.push(format!("{}{}", indentation(indent, options), std));
+                output.push(format!("{}{}", indentation(indent, options), others));
+            }
+            None => output.push(restyled),
+        }
+    }
+    let mut restyled = output.join("\n");
+    if code.ends_with('\n') {
+        restyled.push('\n');
+    }
+    (restyled, line_map)
+}
+
+/// The indentation of a line indented with `spaces` spaces by default.
+fn indentation(spaces: usize, options: &ConvertOptions) -> String {
+    let level = if options.hard_tabs {
+        "\t".to_string()
+    } else {
+        " ".repeat(options.indent_width)
+    };
+    format!("{}{}", level.repeat(spaces / 4), " ".repeat(spaces % 4))
+}
+
+fn is_attribute(line: &str) -> bool {
+    line.starts_with("#[") && line.ends_with(']')
+}
+
+/// Whether a line is a field, or a variant of an enum.
+fn is_field(line: &str) -> bool {
+    !line.starts_with("//") && !line.starts_with('#') && line.ends_with(',')
+}
+
+/// The derives of the standard library and of other crates of a derive
+/// attribute, as separate attributes, if it has both.
+fn split_derives(line: &str) -> Option<(String, String)> {
+    let derives = line.strip_prefix("#[derive(")?.strip_suffix(")]")?;
+    let (std, others): (Vec<&str>, Vec<&str>) = derives
+        .split(", ")
+        .partition(|derive| STD_DERIVES.contains(derive));
+    if std.is_empty() || others.is_empty() {
+        return None;
+    }
+    Some((
+        format!("#[derive({})]", std.join(", ")),
+        format!("#[derive({})]", others.join(", ")),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_rust_with_options;
+
+    #[test]
+    fn restyles_generated_code() {
+        let mut options = ConvertOptions::default();
+        options
+            .set_indent_width(2)
+            .set_trailing_commas(false)
+            .set_inline_field_attributes(true)
+            .set_split_derives(true);
+        let structure = crate::convert_sample_json(r#"{"user-id": 1, "name": "a"}"#).unwrap();
+
+        assert_eq!(
+            generate_rust_with_options(&structure, "Root", &options),
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Root {
+  #[serde(rename = "user-id")] pub user_id: i64,
+  pub name: String
+}
+"#
+        );
+    }
+
+    #[test]
+    fn maps_lines_to_their_restyled_lines() {
+        let mut options = ConvertOptions::default();
+        options
+            .set_hard_tabs(true)
+            .set_inline_field_attributes(true)
+            .set_split_derives(true);
+        let code = "#[derive(Debug, Serialize)]\nstruct A {\n    #[serde(default)]\n    \
+                    #[serde(rename = \"B\")]\n    b: i64,\n}\n";
+
+        assert_eq!(
+            restyle(code, &options),
+            (
+                "#[derive(Debug)]\n#[derive(Serialize)]\nstruct A {\n\t#[serde(default)] \
+                 #[serde(rename = \"B\")] b: i64,\n}\n"
+                    .to_string(),
+                vec![0, 2, 3, 3, 3, 4]
+            )
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Hints for common mistakes in samples that aren't strict json, such as
+//! javascript object literals pasted as samples.
+
+use crate::shared::Error;
+use crate::tokenizer::is_word_char;
+
+impl Error {
+    /// A hint for fixing the error in `json`, the input it came from, for
+    /// common mistakes such as single quoted strings or `True`.
+    pub fn suggestion(&self, json: &str) -> Option<String> {
+        let chars: Vec<char> = json.chars().collect();
+        let index = self.location()?.char() as usize;
+        if *chars.get(index)? == '\'' {
+            return Some("JSON strings must use double quotes".to_string());
+        }
+        if let bracket @ (']' | '}') = chars[index] {
+            if chars[..index].iter().rfind(|c| !c.is_whitespace()) == Some(&',') {
+                return Some(format!(
+                    "JSON doesn't allow trailing commas, remove the ',' before '{}'",
+                    bracket
+                ));
+            }
+        }
+        let (word, end) = word_at(&chars, index)?;
+        let suggestion = match word.as_str() {
+            "NaN" | "Infinity" => {
+                "JSON has no NaN or Infinity, use null or a string instead".to_string()
+            }
+            "None" | "undefined" => format!("{} isn't valid JSON, did you mean null?", word),
+            w if ["true", "false", "null"]
+                .iter()
+                .any(|literal| w.eq_ignore_ascii_case(literal)) =>
+            {
+                format!("did you mean {}?", w.to_ascii_lowercase())
+            }
+            w if chars[end..].iter().find(|c| !c.is_whitespace()) == Some(&':') => {
+                format!("object keys must be quoted, as in \"{}\"", w)
+            }
+            _ => return None,
+        };
+        Some(suggestion)
+    }
+}
+
+/// The bare word around `index`, such as `True` for an error at its `r`, along
+/// with the index just past it. A sign before the word is skipped, for
+/// `-Infinity`.
+fn word_at(chars: &[char], index: usize) -> Option<(String, usize)> {
+    let mut start = if chars[index] == '-' {
+        index + 1
+    } else {
+        index
+    };
+    if !is_word_char(*chars.get(start)?) {
+        return None;
+    }
+    while start > 0 && is_word_char(chars[start - 1]) {
+        start -= 1;
+    }
+    let end = start
+        + chars[start..]
+            .iter()
+            .take_while(|c| is_word_char(**c))
+            .count();
+    Some((chars[start..end].iter().collect(), end))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_json;
+
+    fn suggestion(json: &str) -> Option<String> {
+        parse_json(json).unwrap_err().suggestion(json)
+    }
+
+    #[test]
+    fn suggests_fixes_for_common_mistakes() {
+        assert_eq!(
+            suggestion("{'a': 1}").as_deref(),
+            Some("JSON strings must use double quotes")
+        );
+        assert_eq!(
+            suggestion(r#"{"a": True}"#).as_deref(),
+            Some("did you mean true?")
+        );
+        assert_eq!(suggestion(r#"{"a": nul}"#), None);
+        assert_eq!(
+            suggestion(r#"{"a": NULL}"#).as_deref(),
+            Some("did you mean null?")
+        );
+        assert_eq!(
+            suggestion(r#"{"a": None}"#).as_deref(),
+            Some("None isn't valid JSON, did you mean null?")
+        );
+        assert_eq!(
+            suggestion(r#"{"a": undefined}"#).as_deref(),
+            Some("undefined isn't valid JSON, did you mean null?")
+        );
+        assert_eq!(
+            suggestion(r#"[1, -Infinity]"#).as_deref(),
+            Some("JSON has no NaN or Infinity, use null or a string instead")
+        );
+        assert_eq!(
+            suggestion("{name : 1}").as_deref(),
+            Some("object keys must be quoted, as in \"name\"")
+        );
+        assert_eq!(
+            suggestion("[1, 2,\n]").as_deref(),
+            Some("JSON doesn't allow trailing commas, remove the ',' before ']'")
+        );
+        assert_eq!(
+            suggestion(r#"{"a": 1,}"#).as_deref(),
+            Some("JSON doesn't allow trailing commas, remove the ',' before '}'")
+        );
+        assert_eq!(suggestion(r#"{"a": 1"#), None);
+    }
+}
@@ -0,0 +1,319 @@
+use crate::codegen::union_variant_names;
+use crate::naming::{
+    expand_abbreviations, type_name, type_name_with_acronyms, AcronymStyle, NameContext,
+    NamingStrategy,
+};
+use crate::JsonStructure;
+use std::collections::HashMap;
+
+/// A type that will be generated, before its final name is decided.
+struct Symbol<'a> {
+    path: Vec<String>,
+    candidate: String,
+    parent: Option<usize>,
+    shape: &'a JsonStructure,
+}
+
+/// The names of every type in a generated module, keyed by the path of the
+/// value they are generated for.
+///
+/// Different shapes that the naming strategy gives the same name are told apart
+/// by prefixing the names of their parents, e.g. `OrderItem` and `CartItem`,
+/// while identical shapes with the same name share a single type.
+pub struct SymbolTable {
+    names: HashMap<Vec<String>, String>,
+}
+
+/// Where in the structure a type is, used to name it.
+#[derive(Copy, Clone)]
+struct Position<'a> {
+    field_name: &'a str,
+    is_array_element: bool,
+}
+
+struct Collector<'a, 'n> {
+    naming: &'n dyn NamingStrategy,
+    abbreviations: &'n [(String, String)],
+    acronyms: AcronymStyle,
+    symbols: Vec<Symbol<'a>>,
+    path: Vec<String>,
+}
+
+impl<'a, 'n> Collector<'a, 'n> {
+    fn collect(&mut self, structure: &'a JsonStructure, position: Position, parent: Option<usize>) {
+        match structure {
+            JsonStructure::Array(inner) => self.collect(
+                inner,
+                Position {
+                    is_array_element: true,
+                    ..position
+                },
+                parent,
+            ),
+            JsonStructure::Dictionary(pair) => self.collect(&pair.value, position, parent),
+            JsonStructure::Optional(inner) => self.collect(inner, position, parent),
+            JsonStructure::Object(pairs) => {
+                let index = self.add_symbol(structure, position, parent);
+                for pair in pairs {
+                    self.path.push(pair.key.clone());
+                    let field_position = Position {
+                        field_name: &pair.key,
+                        is_array_element: false,
+                    };
+                    self.collect(&pair.value, field_position, Some(index));
+                    self.path.pop();
+                }
+            }
+            JsonStructure::Union(variants) => {
+                let index = self.add_symbol(structure, position, parent);
+                for (variant, variant_name) in variants.iter().zip(union_variant_names(variants)) {
+                    // Types for the variants are named as if they were a field named
+                    // after both the union and the variant
+                    let variant_field_name = format!("{} {}", position.field_name, variant_name);
+                    self.path.push(variant_name);
+                    let variant_position = Position {
+                        field_name: &variant_field_name,
+                        ..position
+                    };
+                    self.collect(variant, variant_position, parent.or(Some(index)));
+                    self.path.pop();
+                }
+            }
+            JsonStructure::Value(_) | JsonStructure::Unknown => {}
+        }
+    }
+
+    fn add_symbol(
+        &mut self,
+        shape: &'a JsonStructure,
+        position: Position,
+        parent: Option<usize>,
+    ) -> usize {
+        let name = self.naming.type_name(&NameContext {
+            path: &self.path,
+            field_name: position.field_name,
+            parent: parent.map(|p| self.symbols[p].candidate.as_str()),
+            is_array_element: position.is_array_element,
+        });
+        // The root is named by the user, so it is kept as is
+        let candidate = if self.path.is_empty() && parent.is_none() {
+            type_name(&name)
+        } else {
+            type_name_with_acronyms(
+                &expand_abbreviations(&name, self.abbreviations),
+                self.acronyms,
+            )
+        };
+        self.symbols.push(Symbol {
+            path: self.path.clone(),
+            candidate,
+            parent,
+            shape,
+        });
+        self.symbols.len() - 1
+    }
+}
+
+/// The candidate name of a symbol, prefixed by the candidate names of `depth`
+/// of its ancestors. Returns `None` if it doesn't have that many ancestors.
+fn qualified_name(symbols: &[Symbol], index: usize, depth: usize) -> Option<String> {
+    let mut name = symbols[index].candidate.clone();
+    let mut current = index;
+    for _ in 0..depth {
+        current = symbols[current].parent?;
+        let prefix = &symbols[current].candidate;
+        if !name.starts_with(prefix.as_str()) {
+            name = format!("{}{}", prefix, name);
+        }
+    }
+    Some(name)
+}
+
+fn resolve_names(symbols: &[Symbol]) -> Vec<String> {
+    let mut depths = vec![0; symbols.len()];
+    let mut names: Vec<String> = symbols.iter().map(|s| s.candidate.clone()).collect();
+
+    // Keep prefixing the names of colliding symbols with their ancestors, until
+    // they are unique or run out of ancestors
+    loop {
+        let colliding: Vec<usize> = (0..symbols.len())
+            .filter(|&i| {
+                (0..symbols.len())
+                    .any(|j| names[j] == names[i] && symbols[j].shape != symbols[i].shape)
+            })
+            .collect();
+        let mut changed = false;
+        for i in colliding {
+            if let Some(name) = qualified_name(symbols, i, depths[i] + 1) {
+                depths[i] += 1;
+                names[i] = name;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // Anything still colliding is numbered, in the order the types appear
+    let mut taken: Vec<(String, &JsonStructure)> = Vec::new();
+    for (i, symbol) in symbols.iter().enumerate() {
+        let base = names[i].clone();
+        let mut counter = 2;
+        while taken
+            .iter()
+            .any(|(name, shape)| *name == names[i] && *shape != symbol.shape)
+        {
+            names[i] = format!("{}{}", base, counter);
+            counter += 1;
+        }
+        taken.push((names[i].clone(), symbol.shape));
+    }
+    names
+}
+
+impl SymbolTable {
+    /// Names every struct and enum in `root`, which is named after `root_name`.
+    /// Words of the other names are expanded with `abbreviations`, and
+    /// acronyms are written the way `acronyms` asks for.
+    pub fn build(
+        root: &JsonStructure,
+        root_name: &str,
+        naming: &dyn NamingStrategy,
+        abbreviations: &[(String, String)],
+        acronyms: AcronymStyle,
+    ) -> SymbolTable {
+        let mut collector = Collector {
+            naming,
+            abbreviations,
+            acronyms,
+            symbols: Vec::new(),
+            path: Vec::new(),
+        };
+        let root_position = Position {
+            field_name: root_name,
+            is_array_element: false,
+        };
+        collector.collect(root, root_position, None);
+
+        let names = resolve_names(&collector.symbols);
+        SymbolTable {
+            names: collector
+                .symbols
+                .into_iter()
+                .map(|s| s.path)
+                .zip(names)
+                .collect(),
+        }
+    }
+
+    /// Whether any type is named `name`.
+    pub fn has_name(&self, name: &str) -> bool {
+        self.names.values().any(|n| n == name)
+    }
+
+    /// The name of the type generated for the value at `path`.
+    pub fn name_at(&self, path: &[String]) -> &str {
+        &self.names[path]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+    use crate::naming::{FieldNameStrategy, SingularizedStrategy};
+
+    fn names(json: &str) -> Vec<(String, String)> {
+        let structure = convert_sample_json(json).unwrap();
+        let table = SymbolTable::build(
+            &structure,
+            "Root",
+            &SingularizedStrategy::new(),
+            &[],
+            AcronymStyle::Capitalized,
+        );
+        let mut names: Vec<(String, String)> = table
+            .names
+            .into_iter()
+            .map(|(path, name)| (path.join("."), name))
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected
+            .iter()
+            .map(|(p, n)| (p.to_string(), n.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn prefixes_colliding_names_with_their_parents() {
+        let result =
+            names(r#"{"order": {"items": [{"id": 1}]}, "cart": {"items": [{"sku": "a"}]}}"#);
+
+        assert_eq!(
+            result,
+            pairs(&[
+                ("", "Root"),
+                ("cart", "Cart"),
+                ("cart.items", "CartItem"),
+                ("order", "Order"),
+                ("order.items", "OrderItem"),
+            ])
+        )
+    }
+
+    #[test]
+    fn shares_names_between_identical_shapes() {
+        let result = names(r#"{"order": {"items": [{"id": 1}]}, "cart": {"items": [{"id": 2}]}}"#);
+
+        assert_eq!(
+            result,
+            pairs(&[
+                ("", "Root"),
+                ("cart", "Cart"),
+                ("cart.items", "Item"),
+                ("order", "Order"),
+                ("order.items", "Item"),
+            ])
+        )
+    }
+
+    #[test]
+    fn goes_further_up_when_parents_collide_too() {
+        let result = names(
+            r#"{"a": {"data": {"items": [{"x": 1}]}}, "b": {"data": {"items": [{"y": 1}]}}}"#,
+        );
+
+        assert_eq!(
+            result,
+            pairs(&[
+                ("", "Root"),
+                ("a", "A"),
+                ("a.data", "AData"),
+                ("a.data.items", "ADataItem"),
+                ("b", "B"),
+                ("b.data", "BData"),
+                ("b.data.items", "BDataItem"),
+            ])
+        )
+    }
+
+    #[test]
+    fn numbers_names_without_distinguishing_parents() {
+        let structure = convert_sample_json(r#"{"root": {"a": 1}}"#).unwrap();
+        let table = SymbolTable::build(
+            &structure,
+            "Root",
+            &FieldNameStrategy,
+            &[],
+            AcronymStyle::Capitalized,
+        );
+
+        assert_eq!(table.name_at(&[]), "Root");
+        assert_eq!(table.name_at(&["root".to_string()]), "Root2");
+    }
+}
@@ -0,0 +1,61 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Directories created so far by this process, to name the next one.
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// A new directory in the system temp directory, used by this process alone
+/// and removed with everything in it when dropped.
+pub(crate) struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// A directory named `<prefix>-<pid>-<counter>`. It is created with
+    /// `create_dir` rather than `create_dir_all`, so one left behind by a
+    /// process that had the same pid is skipped rather than reused.
+    pub(crate) fn new(prefix: &str) -> io::Result<TempDir> {
+        loop {
+            let count = CREATED.fetch_add(1, Ordering::Relaxed);
+            let path = env::temp_dir().join(format!("{}-{}-{}", prefix, process::id(), count));
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(TempDir { path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_a_new_directory_each_time_and_removes_it() {
+        let first = TempDir::new("json2rust-temp-dir-test").unwrap();
+        let second = TempDir::new("json2rust-temp-dir-test").unwrap();
+        assert_ne!(first.path(), second.path());
+        assert!(first.path().is_dir() && second.path().is_dir());
+
+        let path = first.path().to_path_buf();
+        fs::write(path.join("file"), "x").unwrap();
+        drop(first);
+        assert!(!path.exists());
+        assert!(second.path().is_dir());
+    }
+}
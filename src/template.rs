@@ -0,0 +1,578 @@
+use crate::backend::{Artifact, CodegenBackend};
+use crate::codegen::{primitive_type, union_variant_names};
+use crate::naming::{field_name, to_snake_case, unique_field_names};
+use crate::options::ConvertOptions;
+use crate::pointer::{child_path, element_path};
+use crate::symbols::SymbolTable;
+use crate::{JsonPair, JsonStructure};
+use std::fmt;
+
+/// The nodes of the generated code a template renders.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum TemplateKind {
+    /// The whole file, with `{{types}}`, every rendered struct and enum,
+    /// `{{root}}`, the name of the root type, and `{{uses_hash_map}}`
+    Module,
+    /// A struct, with `{{name}}`, `{{path}}`, its json pointer, and
+    /// `{{fields}}`, its rendered fields
+    Struct,
+    /// A field of a struct, with `{{name}}`, `{{key}}`, the key in the json,
+    /// `{{key_string}}`, the key as a rust string literal, `{{type}}`,
+    /// `{{path}}`, `{{parent}}`, the name of the struct, and the flags
+    /// `{{renamed}}`, when the key isn't the name, and `{{optional}}`
+    Field,
+    /// An untagged enum of the types a value had, with `{{name}}`, `{{path}}`
+    /// and `{{variants}}`, its rendered variants
+    Enum,
+    /// A variant of an enum, with `{{name}}`, `{{type}}` and `{{parent}}`, the
+    /// name of the enum
+    Variant,
+}
+
+impl TemplateKind {
+    pub const ALL: [TemplateKind; 5] = [
+        TemplateKind::Module,
+        TemplateKind::Struct,
+        TemplateKind::Field,
+        TemplateKind::Enum,
+        TemplateKind::Variant,
+    ];
+
+    /// The file the template is read from in a template directory, such as
+    /// `struct.tmpl`.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            TemplateKind::Module => "module.tmpl",
+            TemplateKind::Struct => "struct.tmpl",
+            TemplateKind::Field => "field.tmpl",
+            TemplateKind::Enum => "enum.tmpl",
+            TemplateKind::Variant => "variant.tmpl",
+        }
+    }
+
+    fn variables(self) -> &'static [&'static str] {
+        match self {
+            TemplateKind::Module => &["types", "root", "uses_hash_map"],
+            TemplateKind::Struct => &["name", "path", "fields"],
+            TemplateKind::Field => &[
+                "name",
+                "key",
+                "key_string",
+                "type",
+                "path",
+                "parent",
+                "renamed",
+                "optional",
+            ],
+            TemplateKind::Enum => &["name", "path", "variants"],
+            TemplateKind::Variant => &["name", "type", "parent"],
+        }
+    }
+
+    fn default_template(self) -> &'static str {
+        match self {
+            TemplateKind::Module => {
+                "use serde::{Deserialize, Serialize};\n\
+                 {{#if uses_hash_map}}use std::collections::HashMap;\n{{/if}}{{types}}"
+            }
+            TemplateKind::Struct => {
+                "\n#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+                 pub struct {{name}} {\n{{fields}}}\n"
+            }
+            TemplateKind::Field => {
+                "{{#if renamed}}    #[serde(rename = {{key_string}})]\n{{/if}}    \
+                 pub {{name}}: {{type}},\n"
+            }
+            TemplateKind::Enum => {
+                "\n#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+                 #[serde(untagged)]\npub enum {{name}} {\n{{variants}}}\n"
+            }
+            TemplateKind::Variant => "    {{name}}({{type}}),\n",
+        }
+    }
+}
+
+/// Why a template couldn't be read.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TemplateError {
+    pub kind: TemplateKind,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.file_name(), self.message)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Variable(String),
+    If {
+        variable: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// The template itself or an `{{#if}}` in it, while it is parsed.
+struct Block {
+    // The variable of the `if`, or `None` for the template
+    variable: Option<String>,
+    // The `then` branch, once the `{{else}}` is reached
+    then: Option<Vec<Node>>,
+    nodes: Vec<Node>,
+}
+
+/// Parses a template, in a small subset of Handlebars: `{{variable}}` and
+/// `{{#if variable}}...{{else}}...{{/if}}`, where a variable is true when it
+/// isn't empty.
+fn parse(kind: TemplateKind, text: &str) -> Result<Vec<Node>, TemplateError> {
+    let error = |message: &str| TemplateError {
+        kind,
+        message: message.to_string(),
+    };
+    let mut stack = vec![Block {
+        variable: None,
+        then: None,
+        nodes: Vec::new(),
+    }];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let nodes = &mut stack.last_mut().unwrap().nodes;
+        let start = match rest.find("{{") {
+            Some(start) => start,
+            None => {
+                nodes.push(Node::Text(rest.to_string()));
+                break;
+            }
+        };
+        if start > 0 {
+            nodes.push(Node::Text(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| error("unclosed {{"))?;
+        let tag = rest[start + 2..start + end].trim();
+        rest = &rest[start + end + 2..];
+
+        let check = |variable: &str| {
+            if kind.variables().contains(&variable) {
+                Ok(variable.to_string())
+            } else {
+                Err(error(&format!(
+                    "unknown variable '{}', expected one of {}",
+                    variable,
+                    kind.variables().join(", ")
+                )))
+            }
+        };
+        if let Some(variable) = tag.strip_prefix("#if ") {
+            stack.push(Block {
+                variable: Some(check(variable.trim())?),
+                then: None,
+                nodes: Vec::new(),
+            });
+        } else if tag == "else" {
+            let block = stack.last_mut().unwrap();
+            if block.variable.is_none() || block.then.is_some() {
+                return Err(error("{{else}} outside of an {{#if}}"));
+            }
+            block.then = Some(std::mem::take(&mut block.nodes));
+        } else if tag == "/if" {
+            if stack.len() == 1 {
+                return Err(error("{{/if}} without an {{#if}}"));
+            }
+            let block = stack.pop().unwrap();
+            let node = match block.then {
+                Some(then) => Node::If {
+                    variable: block.variable.unwrap(),
+                    then,
+                    otherwise: block.nodes,
+                },
+                None => Node::If {
+                    variable: block.variable.unwrap(),
+                    then: block.nodes,
+                    otherwise: Vec::new(),
+                },
+            };
+            stack.last_mut().unwrap().nodes.push(node);
+        } else {
+            let variable = check(tag)?;
+            stack
+                .last_mut()
+                .unwrap()
+                .nodes
+                .push(Node::Variable(variable));
+        }
+    }
+    if stack.len() > 1 {
+        return Err(error("{{#if}} without an {{/if}}"));
+    }
+    Ok(stack.pop().unwrap().nodes)
+}
+
+fn render(nodes: &[Node], variables: &[(&str, String)], output: &mut String) {
+    let value = |name: &str| {
+        variables
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map_or("", |(_, value)| value.as_str())
+    };
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable(name) => output.push_str(value(name)),
+            Node::If {
+                variable,
+                then,
+                otherwise,
+            } => render(
+                if value(variable).is_empty() {
+                    otherwise
+                } else {
+                    then
+                },
+                variables,
+                output,
+            ),
+        }
+    }
+}
+
+/// Renders the inferred structure through user provided templates, as a
+/// single `<root_name>.rs` file, for teams whose required style, such as doc
+/// headers, attribute sets or helper impls, differs from the built-in emitter.
+/// There is a template for the module and for each struct, field, enum and
+/// variant, see [`TemplateKind`]; the defaults render plain serde types.
+///
+/// ```
+/// use json2rust::{CodegenBackend, ConvertOptions, TemplateBackend, TemplateKind};
+///
+/// let mut backend = TemplateBackend::new();
+/// backend
+///     .set_template(TemplateKind::Field, "    /// `{{key}}`\n    pub {{name}}: {{type}},\n")
+///     .unwrap();
+/// let structure = json2rust::convert_sample_json(r#"{"id": 1}"#).unwrap();
+/// let artifacts = backend.generate(&structure, "User", &ConvertOptions::default());
+/// assert!(artifacts[0].contents.contains("    /// `id`\n    pub id: i64,\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TemplateBackend {
+    // The parsed template of each kind, in the order of `TemplateKind::ALL`
+    templates: Vec<Vec<Node>>,
+}
+
+impl Default for TemplateBackend {
+    fn default() -> TemplateBackend {
+        TemplateBackend::new()
+    }
+}
+
+impl TemplateBackend {
+    /// A backend with the default templates.
+    pub fn new() -> TemplateBackend {
+        TemplateBackend {
+            templates: TemplateKind::ALL
+                .iter()
+                .map(|kind| parse(*kind, kind.default_template()).expect("valid default template"))
+                .collect(),
+        }
+    }
+
+    /// Replaces the template of `kind`, failing if it isn't valid or uses a
+    /// variable `kind` doesn't have.
+    pub fn set_template(
+        &mut self,
+        kind: TemplateKind,
+        template: &str,
+    ) -> Result<&mut TemplateBackend, TemplateError> {
+        let index = TemplateKind::ALL.iter().position(|k| *k == kind).unwrap();
+        self.templates[index] = parse(kind, template)?;
+        Ok(self)
+    }
+
+    fn render(&self, kind: TemplateKind, variables: &[(&str, String)]) -> String {
+        let index = TemplateKind::ALL.iter().position(|k| *k == kind).unwrap();
+        let mut output = String::new();
+        render(&self.templates[index], variables, &mut output);
+        output
+    }
+}
+
+struct TemplateGenerator<'a> {
+    backend: &'a TemplateBackend,
+    symbols: &'a SymbolTable,
+    definitions: Vec<String>,
+    defined: Vec<String>,
+    path: Vec<String>,
+    pointer: String,
+    uses_hash_map: bool,
+}
+
+fn flag(value: bool) -> String {
+    if value {
+        "true".to_string()
+    } else {
+        String::new()
+    }
+}
+
+impl<'a> TemplateGenerator<'a> {
+    fn type_for(&mut self, structure: &JsonStructure) -> String {
+        match structure {
+            JsonStructure::Object(pairs) => self.emit_struct(pairs),
+            JsonStructure::Union(variants) => self.emit_enum(variants),
+            JsonStructure::Array(inner) => format!("Vec<{}>", self.element_type(inner)),
+            JsonStructure::Dictionary(pair) => {
+                self.uses_hash_map = true;
+                format!("HashMap<String, {}>", self.element_type(pair.value()))
+            }
+            JsonStructure::Optional(inner) => format!("Option<{}>", self.type_for(inner)),
+            JsonStructure::Value(value) => primitive_type(value),
+            JsonStructure::Unknown => "serde_json::Value".to_string(),
+        }
+    }
+
+    fn element_type(&mut self, structure: &JsonStructure) -> String {
+        let element = element_path(&self.pointer);
+        let parent = std::mem::replace(&mut self.pointer, element);
+        let element_type = self.type_for(structure);
+        self.pointer = parent;
+        element_type
+    }
+
+    /// Reserves the place of a type before its fields are rendered, so types
+    /// come before the types they use, or returns `None` if it was rendered.
+    fn reserve(&mut self) -> (String, Option<usize>) {
+        let name = self.symbols.name_at(&self.path).to_string();
+        if self.defined.contains(&name) {
+            return (name, None);
+        }
+        self.defined.push(name.clone());
+        self.definitions.push(String::new());
+        (name, Some(self.definitions.len() - 1))
+    }
+
+    fn emit_struct(&mut self, pairs: &[JsonPair]) -> String {
+        let (name, index) = match self.reserve() {
+            (name, Some(index)) => (name, index),
+            (name, None) => return name,
+        };
+        let keys: Vec<&str> = pairs.iter().map(JsonPair::key).collect();
+        let names: Vec<String> = keys.iter().map(|key| field_name(key)).collect();
+        let names = unique_field_names(&keys, &names);
+        let mut fields = String::new();
+        for (position, (pair, field)) in pairs.iter().zip(names).enumerate() {
+            let field = match field {
+                field if field.is_empty() => format!("field_{}", position + 1),
+                field => field,
+            };
+            self.path.push(pair.key().to_string());
+            let field_pointer = child_path(&self.pointer, pair.key());
+            let parent_pointer = std::mem::replace(&mut self.pointer, field_pointer);
+            let field_type = self.type_for(pair.value());
+            let variables = [
+                ("renamed", flag(field != pair.key())),
+                ("name", field),
+                ("key", pair.key().to_string()),
+                ("key_string", format!("{:?}", pair.key())),
+                ("type", field_type),
+                ("path", self.pointer.clone()),
+                ("parent", name.clone()),
+                ("optional", flag(pair.value().is_optional())),
+            ];
+            self.pointer = parent_pointer;
+            self.path.pop();
+            fields.push_str(&self.backend.render(TemplateKind::Field, &variables));
+        }
+        self.definitions[index] = self.backend.render(
+            TemplateKind::Struct,
+            &[
+                ("name", name.clone()),
+                ("path", self.pointer.clone()),
+                ("fields", fields),
+            ],
+        );
+        name
+    }
+
+    fn emit_enum(&mut self, variants: &[JsonStructure]) -> String {
+        let (name, index) = match self.reserve() {
+            (name, Some(index)) => (name, index),
+            (name, None) => return name,
+        };
+        let mut rendered = String::new();
+        for (variant, variant_name) in variants.iter().zip(union_variant_names(variants)) {
+            self.path.push(variant_name.clone());
+            let variant_type = self.type_for(variant);
+            self.path.pop();
+            rendered.push_str(&self.backend.render(
+                TemplateKind::Variant,
+                &[
+                    ("name", variant_name),
+                    ("type", variant_type),
+                    ("parent", name.clone()),
+                ],
+            ));
+        }
+        self.definitions[index] = self.backend.render(
+            TemplateKind::Enum,
+            &[
+                ("name", name.clone()),
+                ("path", self.pointer.clone()),
+                ("variants", rendered),
+            ],
+        );
+        name
+    }
+}
+
+impl CodegenBackend for TemplateBackend {
+    fn generate(
+        &self,
+        structure: &JsonStructure,
+        root_name: &str,
+        options: &ConvertOptions,
+    ) -> Vec<Artifact> {
+        // Like the serde types, arrays at the root produce the types of their
+        // elements
+        let mut root = structure.non_optional();
+        let mut pointer = String::new();
+        while let JsonStructure::Array(inner) = root {
+            root = inner.non_optional();
+            pointer = element_path(&pointer);
+        }
+        let symbols = SymbolTable::build(
+            root,
+            root_name,
+            options.naming_strategy.as_ref(),
+            &options.abbreviations,
+            options.acronyms,
+        );
+        let mut generator = TemplateGenerator {
+            backend: self,
+            symbols: &symbols,
+            definitions: Vec::new(),
+            defined: Vec::new(),
+            path: Vec::new(),
+            pointer,
+            uses_hash_map: false,
+        };
+        if let JsonStructure::Object(_) | JsonStructure::Union(_) = root {
+            generator.type_for(root);
+        }
+
+        let module = self.render(
+            TemplateKind::Module,
+            &[
+                ("types", generator.definitions.concat()),
+                ("root", root_name.to_string()),
+                ("uses_hash_map", flag(generator.uses_hash_map)),
+            ],
+        );
+        vec![Artifact::new(
+            format!("{}.rs", to_snake_case(root_name)),
+            module,
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    fn generate(backend: &TemplateBackend, json: &str) -> String {
+        let structure = convert_sample_json(json).unwrap();
+        let artifacts = backend.generate(&structure, "Order", &ConvertOptions::default());
+        artifacts[0].contents.clone()
+    }
+
+    #[test]
+    fn default_templates_render_serde_types() {
+        let code = generate(
+            &TemplateBackend::new(),
+            r#"{"orderId": 1, "items": [{"sku": "a"}], "price": [1, "2"], "meta": {}}"#,
+        );
+
+        assert_eq!(
+            code,
+            r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    #[serde(rename = "orderId")]
+    pub order_id: i64,
+    pub items: Vec<Item>,
+    pub price: Vec<Price>,
+    pub meta: Meta,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    pub sku: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Price {
+    Integer(i64),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Meta {
+}
+"#
+        );
+    }
+
+    #[test]
+    fn renders_user_templates() {
+        let mut backend = TemplateBackend::new();
+        backend
+            .set_template(TemplateKind::Module, "// {{root}}\n{{types}}")
+            .unwrap()
+            .set_template(
+                TemplateKind::Struct,
+                "/// At `{{path}}`\n#[derive(Builder)]\npub struct {{name}} {\n{{fields}}}\n",
+            )
+            .unwrap()
+            .set_template(
+                TemplateKind::Field,
+                "    pub {{name}}: {{type}},{{#if optional}} // optional{{else}} // required{{/if}}\n",
+            )
+            .unwrap();
+        let code = generate(&backend, r#"[{"id": 1, "owner": {"name": null}}]"#);
+
+        assert_eq!(
+            code,
+            "// Order\n/// At `/*`\n#[derive(Builder)]\npub struct Order {\n    \
+             pub id: i64, // required\n    pub owner: Owner, // required\n}\n\
+             /// At `/*/owner`\n#[derive(Builder)]\npub struct Owner {\n    \
+             pub name: Option<serde_json::Value>, // optional\n}\n"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        let mut backend = TemplateBackend::new();
+
+        assert_eq!(
+            backend
+                .set_template(TemplateKind::Variant, "{{name}}({{key}})")
+                .unwrap_err()
+                .to_string(),
+            "variant.tmpl: unknown variable 'key', expected one of name, type, parent"
+        );
+        assert!(backend
+            .set_template(TemplateKind::Field, "{{#if optional}}x")
+            .is_err());
+        assert!(backend
+            .set_template(TemplateKind::Field, "{{/if}}")
+            .is_err());
+        assert!(backend.set_template(TemplateKind::Field, "{{name").is_err());
+    }
+}
@@ -0,0 +1,537 @@
+//! Helpers for pinning the code generated from samples in tests, for crates
+//! that wrap json2rust and want to notice when an upgrade changes its output.
+//!
+//! ```no_run
+//! use json2rust::testing::assert_golden;
+//! use json2rust::ConvertOptions;
+//!
+//! assert_golden(
+//!     r#"{"id": 1}"#,
+//!     "User",
+//!     &ConvertOptions::default(),
+//!     "tests/golden/user.rs",
+//! );
+//! ```
+
+use crate::codegen::conversion_to_rust;
+use crate::options::ConvertOptions;
+use std::fs;
+use std::path::Path;
+
+/// Setting this environment variable makes `assert_golden` write the generated
+/// code to the golden files, instead of comparing against them.
+pub const BLESS_VAR: &str = "JSON2RUST_BLESS";
+
+/// Generates the code for a json sample, panicking if it isn't valid json.
+pub fn generate(sample: &str, root_name: &str, options: &ConvertOptions) -> String {
+    let conversion = crate::convert_sample_json_with_warnings(sample, options)
+        .unwrap_or_else(|e| panic!("invalid json sample: {}", e));
+    conversion_to_rust(&conversion, root_name, options)
+}
+
+/// Asserts that a json sample generates exactly `expected`, showing a line diff
+/// if it doesn't.
+pub fn assert_generates(sample: &str, root_name: &str, options: &ConvertOptions, expected: &str) {
+    let actual = generate(sample, root_name, options);
+    if actual != expected {
+        panic!(
+            "generated code differs from the expected code:\n{}",
+            diff(expected, &actual)
+        );
+    }
+}
+
+/// Asserts that a json sample generates exactly the code in the golden file at
+/// `path`. With the `JSON2RUST_BLESS` environment variable set, the file is
+/// written with the generated code instead, to accept a change in the output.
+pub fn assert_golden(
+    sample: &str,
+    root_name: &str,
+    options: &ConvertOptions,
+    path: impl AsRef<Path>,
+) {
+    let actual = generate(sample, root_name, options);
+    let bless = std::env::var_os(BLESS_VAR).is_some();
+    if let Err(message) = check_golden(&actual, path.as_ref(), bless) {
+        panic!("{}", message);
+    }
+}
+
+fn check_golden(actual: &str, path: &Path, bless: bool) -> Result<(), String> {
+    if bless {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+        }
+        return fs::write(path, actual)
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e));
+    }
+    let expected = fs::read_to_string(path).map_err(|e| {
+        format!(
+            "failed to read golden file {}: {}\nrun with {}=1 to create it",
+            path.display(),
+            e,
+            BLESS_VAR
+        )
+    })?;
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(format!(
+            "generated code differs from golden file {}:\n{}\nrun with {}=1 to update it",
+            path.display(),
+            diff(&expected, actual),
+            BLESS_VAR
+        ))
+    }
+}
+
+/// Kinds of synthetic samples, each stressing a different part of the
+/// conversion, for benchmarks.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Workload {
+    /// An array of long strings with escapes, which is mostly tokenizing
+    LongStrings,
+    /// Objects and arrays nested in each other
+    DeepNesting,
+    /// A single object with many fields
+    WideObject,
+    /// Many records of one document, where fields come and go, merged into
+    /// one structure
+    ManySamples,
+}
+
+impl Workload {
+    pub const ALL: [Workload; 4] = [
+        Workload::LongStrings,
+        Workload::DeepNesting,
+        Workload::WideObject,
+        Workload::ManySamples,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Workload::LongStrings => "long_strings",
+            Workload::DeepNesting => "deep_nesting",
+            Workload::WideObject => "wide_object",
+            Workload::ManySamples => "many_samples",
+        }
+    }
+
+    /// The json samples of the workload, which grow with `size`: the number of
+    /// strings, levels, fields or records. The same size always gives the same
+    /// samples.
+    pub fn samples(self, size: usize) -> Vec<String> {
+        match self {
+            Workload::LongStrings => {
+                let strings: Vec<String> = (0..size)
+                    .map(|i| format!("\"{} \\\"quoted\\\" \\u00e9\\n{}\"", "x".repeat(1000), i))
+                    .collect();
+                vec![format!("[{}]", strings.join(", "))]
+            }
+            Workload::DeepNesting => {
+                let mut json = String::from("1");
+                for i in 0..size {
+                    json = match i % 2 {
+                        0 => format!("{{\"level_{}\": {}, \"id\": {}}}", i, json, i),
+                        _ => format!("[{}, null]", json),
+                    };
+                }
+                vec![json]
+            }
+            Workload::WideObject => {
+                let fields: Vec<String> = (0..size)
+                    .map(|i| match i % 4 {
+                        0 => format!("\"field_{}\": {}", i, i),
+                        1 => format!("\"field_{}\": \"value {}\"", i, i),
+                        2 => format!("\"field_{}\": {}.5", i, i),
+                        _ => format!("\"field_{}\": [true, false]", i),
+                    })
+                    .collect();
+                vec![format!("{{{}}}", fields.join(", "))]
+            }
+            Workload::ManySamples => (0..size)
+                .map(|i| {
+                    let mut fields = vec![
+                        format!("\"id\": {}", i),
+                        format!("\"name\": \"user {}\"", i),
+                        format!("\"tags\": [\"t{}\", \"t{}\"]", i % 7, i % 11),
+                    ];
+                    if i % 3 == 0 {
+                        fields.push(format!("\"email\": \"u{}@example.com\"", i));
+                    }
+                    if i % 5 == 0 {
+                        fields.push("\"address\": {\"city\": \"Oslo\", \"zip\": null}".into());
+                    }
+                    format!("{{{}}}", fields.join(", "))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A small deterministic random number generator, xorshift64*.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // Xorshift gets stuck at zero
+        Rng(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number below `bound`.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// A random valid json document, the same for the same `seed`, for fuzzing and
+/// the differential check against serde_json. It has nested objects and arrays, strings with escapes
+/// and non-ascii characters, numbers in every notation, and whitespace
+/// between tokens.
+pub fn arbitrary_json(seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut json = String::new();
+    write_value(&mut rng, 0, &mut json);
+    json
+}
+
+/// The document of `arbitrary_json` for `seed` with a few characters
+/// truncated, replaced or inserted, so it is usually invalid json.
+pub fn mangled_json(seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let mut chars: Vec<char> = arbitrary_json(seed).chars().collect();
+    for _ in 0..rng.below(4) {
+        let index = rng.below(chars.len() as u64 + 1) as usize;
+        match rng.below(3) {
+            0 => chars.truncate(index),
+            1 if index < chars.len() => {
+                chars[index] = char::from_u32(rng.below(0x80) as u32).unwrap_or('x')
+            }
+            _ => chars.insert(
+                index,
+                ['{', '[', '"', ',', ':', '\\', '1', '.', 'e'][rng.below(9) as usize],
+            ),
+        }
+    }
+    chars.into_iter().collect()
+}
+
+fn write_whitespace(rng: &mut Rng, json: &mut String) {
+    for _ in 0..rng.below(3) {
+        json.push([' ', '\n', '\t', '\r'][rng.below(4) as usize]);
+    }
+}
+
+fn write_string(rng: &mut Rng, json: &mut String) {
+    const PARTS: &[&str] = &[
+        "a",
+        "id",
+        "é",
+        "😀",
+        " ",
+        "\\\"",
+        "\\\\",
+        "\\/",
+        "\\n",
+        "\\t",
+        "\\u00e9",
+        "\\ud83d\\ude00",
+        "0",
+        "-",
+        "2024-01-02T03:04:05Z",
+        "127.0.0.1",
+    ];
+    json.push('"');
+    for _ in 0..rng.below(6) {
+        json.push_str(PARTS[rng.below(PARTS.len() as u64) as usize]);
+    }
+    json.push('"');
+}
+
+fn write_value(rng: &mut Rng, depth: u32, json: &mut String) {
+    write_whitespace(rng, json);
+    let kinds = if depth < 4 { 9 } else { 7 };
+    match rng.below(kinds) {
+        0 => json.push_str("null"),
+        1 => json.push_str(if rng.below(2) == 0 { "true" } else { "false" }),
+        2 => json.push_str(&(rng.next() as i64 >> rng.below(64)).to_string()),
+        3 => json.push_str(&format!(
+            "{}.{}",
+            rng.below(1000) as i64 - 500,
+            rng.below(1000)
+        )),
+        4 => json.push_str(&format!(
+            "{}e{}{}",
+            rng.below(10),
+            ["", "+", "-"][rng.below(3) as usize],
+            rng.below(30)
+        )),
+        5 | 6 => write_string(rng, json),
+        7 => {
+            json.push('[');
+            for i in 0..rng.below(4) {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_value(rng, depth + 1, json);
+            }
+            write_whitespace(rng, json);
+            json.push(']');
+        }
+        _ => {
+            json.push('{');
+            for i in 0..rng.below(4) {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_whitespace(rng, json);
+                write_string(rng, json);
+                write_whitespace(rng, json);
+                json.push(':');
+                write_value(rng, depth + 1, json);
+            }
+            write_whitespace(rng, json);
+            json.push('}');
+        }
+    }
+    write_whitespace(rng, json);
+}
+
+/// A line diff of two texts, with removed lines prefixed by `-` and added lines
+/// by `+`.
+pub(crate) fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+    // Lengths of the longest common subsequences of the suffixes
+    let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            output.push_str(&format!(" {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            output.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        } else {
+            output.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use proptest::sample::{select, Index};
+
+    const EXPECTED: &str = "use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    pub id: i64,
+}
+";
+
+    #[test]
+    fn generates_valid_workloads() {
+        for workload in Workload::ALL {
+            let samples = workload.samples(20);
+            let samples: Vec<&str> = samples.iter().map(String::as_str).collect();
+            assert!(
+                crate::convert_samples_json_with_warnings(&samples, &ConvertOptions::default())
+                    .is_ok(),
+                "{}",
+                workload.name()
+            );
+        }
+        assert_eq!(Workload::ManySamples.samples(20).len(), 20);
+    }
+
+    fn padded(value: impl Strategy<Value = String>) -> impl Strategy<Value = String> {
+        let whitespace = "[ \n\t\r]{0,2}";
+        (whitespace, value, whitespace).prop_map(|(before, value, after)| before + &value + &after)
+    }
+
+    fn json_string() -> impl Strategy<Value = String> {
+        const PARTS: &[&str] = &[
+            "a",
+            "id",
+            "é",
+            "😀",
+            " ",
+            "\\\"",
+            "\\\\",
+            "\\/",
+            "\\n",
+            "\\t",
+            "\\u00e9",
+            "\\ud83d\\ude00",
+            "0",
+            "-",
+            "2024-01-02T03:04:05Z",
+            "127.0.0.1",
+        ];
+        vec(select(PARTS), 0..6).prop_map(|parts| format!("\"{}\"", parts.concat()))
+    }
+
+    /// Valid json documents, with nested objects and arrays, strings with
+    /// escapes and non-ascii characters, numbers in every notation, and
+    /// whitespace between tokens.
+    fn json_document() -> impl Strategy<Value = String> {
+        let leaf = prop_oneof![
+            Just("null".to_string()),
+            any::<bool>().prop_map(|b| b.to_string()),
+            any::<i64>().prop_map(|i| i.to_string()),
+            (-500i64..500, 0u32..1000).prop_map(|(i, f)| format!("{}.{}", i, f)),
+            (0u32..10, select(&["", "+", "-"][..]), 0u32..30)
+                .prop_map(|(m, sign, e)| format!("{}e{}{}", m, sign, e)),
+            json_string(),
+        ];
+        padded(leaf).prop_recursive(4, 64, 4, |value| {
+            let whitespace = "[ \n\t\r]{0,2}";
+            padded(prop_oneof![
+                (vec(value.clone(), 0..4), whitespace).prop_map(|(items, end)| format!(
+                    "[{}{}]",
+                    items.join(","),
+                    end
+                )),
+                (vec((padded(json_string()), value), 0..4), whitespace).prop_map(
+                    |(fields, end)| {
+                        let fields: Vec<String> = fields
+                            .into_iter()
+                            .map(|(key, value)| format!("{}:{}", key, value))
+                            .collect();
+                        format!("{{{}{}}}", fields.join(","), end)
+                    }
+                ),
+            ])
+        })
+    }
+
+    /// Valid json cut off, with characters swapped and random ones inserted,
+    /// into input that is almost but not quite json.
+    fn mangled_document() -> impl Strategy<Value = String> {
+        let edit = (
+            any::<Index>(),
+            0u8..3,
+            0u32..0x80,
+            select(&['{', '[', '"', ',', ':', '\\', '1', '.', 'e'][..]),
+        );
+        (json_document(), vec(edit, 0..4)).prop_map(|(json, edits)| {
+            let mut chars: Vec<char> = json.chars().collect();
+            for (index, kind, replacement, inserted) in edits {
+                let index = index.index(chars.len() + 1);
+                match kind {
+                    0 => chars.truncate(index),
+                    1 if index < chars.len() => {
+                        chars[index] = char::from_u32(replacement).unwrap_or('x')
+                    }
+                    _ => chars.insert(index, inserted),
+                }
+            }
+            chars.into_iter().collect()
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(1000))]
+
+        #[test]
+        fn arbitrary_json_parses(json in json_document()) {
+            prop_assert!(serde_json::from_str::<serde_json::Value>(&json).is_ok());
+            let conversion = crate::convert_sample_json(&json);
+            prop_assert!(conversion.is_ok(), "{:?}", conversion);
+        }
+
+        #[test]
+        fn arbitrary_input_never_panics(json in mangled_document()) {
+            let mut lenient = ConvertOptions::default();
+            lenient
+                .set_lenient_numbers(true)
+                .set_relaxed_json(true)
+                .set_non_finite_numbers(true)
+                .set_extended_json(true)
+                .set_empty_strings_as_none(true);
+            for options in [&ConvertOptions::default(), &lenient] {
+                if let Ok(structure) = crate::convert_sample_json_with_options(&json, options) {
+                    crate::generate_rust_with_options(&structure, "Root", options);
+                }
+            }
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(5))]
+
+        /// Needs `cargo` and the dependencies of the generated code, run with
+        /// `cargo test -- --ignored`.
+        #[test]
+        #[ignore]
+        fn arbitrary_json_generates_code_that_compiles(
+            first in json_document(),
+            second in json_document(),
+        ) {
+            let options = ConvertOptions::default();
+            let json = format!("[{}, {}]", first, second);
+            let conversion = crate::convert_sample_json_with_warnings(&json, &options).unwrap();
+            let errors = crate::check_conversion(&conversion, &json, "Root", &options).unwrap();
+            prop_assert_eq!(errors, vec![]);
+        }
+    }
+
+    #[test]
+    fn asserts_generated_code() {
+        assert_generates(r#"{"id": 1}"#, "Root", &ConvertOptions::default(), EXPECTED);
+    }
+
+    #[test]
+    #[should_panic(expected = "-    pub id: i64,\n+    pub id: String,")]
+    fn shows_a_diff_when_the_code_differs() {
+        assert_generates(
+            r#"{"id": "a"}"#,
+            "Root",
+            &ConvertOptions::default(),
+            EXPECTED,
+        );
+    }
+
+    #[test]
+    fn blesses_and_checks_golden_files() {
+        let path = std::env::temp_dir()
+            .join("json2rust-golden-test")
+            .join("root.rs");
+        let _ = fs::remove_file(&path);
+
+        assert!(check_golden(EXPECTED, &path, false)
+            .unwrap_err()
+            .contains("to create it"));
+        check_golden(EXPECTED, &path, true).unwrap();
+        check_golden(EXPECTED, &path, false).unwrap();
+        assert!(check_golden("pub struct Root;\n", &path, false)
+            .unwrap_err()
+            .contains("+pub struct Root;"));
+    }
+}
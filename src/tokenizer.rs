@@ -1,138 +1,473 @@
-use crate::shared::{Error, JsonTokenInfo};
+use crate::shared::{Error, JsonTokenInfo, Span};
+use alloc::{
+    collections::BTreeSet,
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::mem;
+#[cfg(feature = "tracing")]
+use tracing::trace;
 
-#[derive(Debug, Eq, PartialEq)]
+// Without the `tracing` feature, tracing events compile to nothing
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace {
+    ($($arg:tt)*) => {};
+}
+
+#[derive(Debug, PartialEq)]
 pub struct JsonToken {
-    location: JsonTokenInfo,
-    token_type: JsonTokenType,
+    pub(crate) span: Span,
+    pub(crate) token_type: JsonTokenType,
 }
 
 impl JsonToken {
-    fn new(token_type: JsonTokenType, location: JsonTokenInfo) -> JsonToken {
-        JsonToken {
-            location,
-            token_type,
-        }
+    fn new(token_type: JsonTokenType, span: Span) -> JsonToken {
+        JsonToken { span, token_type }
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum JsonTokenType {
     ObjectStart,
     ObjectEnd,
     ArrayStart,
     ArrayEnd,
     String(String),
-    Float,
-    Int,
+    /// A string that is an object key, shared with every other occurrence of
+    /// the key through the `KeyInterner`
+    Key(Rc<str>),
+    Float(f64),
+    Int(i128),
+    /// An integer written with an exponent, such as `1e3`, read as one with
+    /// `Leniency::exponent_integers`
+    ExponentInt(i128),
     Bool,
+    Null,
     Colon,
+    Comma,
+}
+
+/// The object keys tokenized so far, so that each distinct key is allocated
+/// once however many objects, and samples, have it.
+#[derive(Debug, Default)]
+pub(crate) struct KeyInterner {
+    keys: BTreeSet<Rc<str>>,
+}
+
+impl KeyInterner {
+    pub(crate) fn intern(&mut self, key: &str) -> Rc<str> {
+        if let Some(interned) = self.keys.get(key) {
+            return Rc::clone(interned);
+        }
+        let interned: Rc<str> = key.into();
+        self.keys.insert(Rc::clone(&interned));
+        interned
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 struct TokenizerStringReadingState {
     starting_location: JsonTokenInfo,
+    // The quote the string started with, which ends it again
+    quote: char,
     value: String,
     escape_next: bool,
+    // Hex digits of a `\uXXXX` escape that is currently being read
+    unicode_escape: Option<String>,
+    // A high surrogate waiting for its low surrogate pair
+    pending_surrogate: Option<u32>,
+}
+
+impl TokenizerStringReadingState {
+    /// Starts reading a string into `buffer`, which must be empty.
+    fn new(
+        starting_location: JsonTokenInfo,
+        quote: char,
+        buffer: String,
+    ) -> TokenizerStringReadingState {
+        TokenizerStringReadingState {
+            starting_location,
+            quote,
+            value: buffer,
+            escape_next: false,
+            unicode_escape: None,
+            pending_surrogate: None,
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 struct TokenizerNumberReadingState {
     starting_location: JsonTokenInfo,
+    // Byte offset of the number in the input, to read its value from once it ends
+    start: usize,
     seen_decimal_char: bool,
     seen_exponent: bool,
+    // Whether the integer part of the number is a single `0`
+    starts_with_zero: bool,
+    integer_digits: usize,
+    // The last character consumed, used to validate the position of signs
+    last_char: char,
 }
 
 impl TokenizerNumberReadingState {
-    fn new(starting_location: JsonTokenInfo) -> TokenizerNumberReadingState {
+    fn new(
+        starting_location: JsonTokenInfo,
+        start: usize,
+        first_char: char,
+    ) -> TokenizerNumberReadingState {
         TokenizerNumberReadingState {
             starting_location,
+            start,
             seen_exponent: false,
             seen_decimal_char: false,
+            starts_with_zero: first_char == '0',
+            integer_digits: if first_char.is_ascii_digit() { 1 } else { 0 },
+            last_char: first_char,
+        }
+    }
+
+    fn consume(self, c: char) -> TokenizerNumberReadingState {
+        TokenizerNumberReadingState {
+            last_char: c,
+            ..self
         }
     }
 }
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+struct TokenizerLiteralReadingState {
+    starting_location: JsonTokenInfo,
+    literal: &'static str,
+    matched: usize,
+}
+
+/// A bare word in relaxed json, such as an unquoted key or `True`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+struct TokenizerWordReadingState {
+    starting_location: JsonTokenInfo,
+    // Byte offset of the word in the input
+    start: usize,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 enum TokenizerState {
     Ready,
     ReadingString(TokenizerStringReadingState),
     ReadingNumber(TokenizerNumberReadingState),
+    ReadingLiteral(TokenizerLiteralReadingState),
+    ReadingWord(TokenizerWordReadingState),
+}
+
+/// Deviations from strict json that the tokenizer accepts.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct Leniency {
+    /// Numbers without digits on one side of the decimal separator, such as
+    /// `.5` and `5.`, as in json5
+    pub numbers: bool,
+    /// Javascript object literals and Python reprs: single quoted strings,
+    /// unquoted keys, trailing commas, `True`, `False`, `None` and `undefined`
+    pub relaxed: bool,
+    /// `NaN`, `Infinity` and `-Infinity`, as written by some python and
+    /// javascript serializers
+    pub non_finite: bool,
+    /// Numbers with an exponent that are exactly integers, such as `1e3` and
+    /// `2.5e1`, are read as integers rather than floats. Not a deviation, as
+    /// json doesn't say which they are
+    pub exponent_integers: bool,
+}
+
+/// How deep objects and arrays may nest when `Limits::max_depth` isn't set.
+/// The parser and inference recurse once per level, so without a bound deeply
+/// nested, but valid, input would overflow the stack.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Bounds on the json input, for inputs from untrusted sources.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub(crate) struct Limits {
+    /// The most bytes the input may have
+    pub max_input_bytes: Option<usize>,
+    /// The most objects and arrays that may be open at once,
+    /// `DEFAULT_MAX_DEPTH` if not set
+    pub max_depth: Option<usize>,
+}
+
+/// The position of the character being read, shared by every tokenizer state.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+struct Cursor {
+    line: i64,
+    column: i64,
+    char: i64,
 }
 
-pub fn tokenize_json(json: &str) -> Result<Vec<JsonToken>, Error> {
+impl Cursor {
+    fn new() -> Cursor {
+        Cursor {
+            line: 1,
+            column: 1,
+            char: 0,
+        }
+    }
+
+    fn location(&self) -> JsonTokenInfo {
+        JsonTokenInfo::new(self.line, self.column, self.char)
+    }
+
+    /// Moves past `c`, starting a new line after a newline.
+    fn advance(&mut self, c: char) {
+        self.char += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+fn literal_token_type(literal: &str) -> JsonTokenType {
+    match literal {
+        "null" => JsonTokenType::Null,
+        _ => JsonTokenType::Bool,
+    }
+}
+
+pub(crate) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// The token for a bare word, which are either non-finite numbers or, in
+/// relaxed json, literals and unquoted keys.
+fn word_token_type(word: &str, leniency: Leniency) -> Option<JsonTokenType> {
+    match word {
+        "NaN" if leniency.non_finite => Some(JsonTokenType::Float(f64::NAN)),
+        "Infinity" if leniency.non_finite => Some(JsonTokenType::Float(f64::INFINITY)),
+        "-Infinity" if leniency.non_finite => Some(JsonTokenType::Float(f64::NEG_INFINITY)),
+        "NaN" | "Infinity" | "-Infinity" => None,
+        _ if !leniency.relaxed => None,
+        w if w.eq_ignore_ascii_case("true") || w.eq_ignore_ascii_case("false") => {
+            Some(JsonTokenType::Bool)
+        }
+        w if w.eq_ignore_ascii_case("null") || w == "None" || w == "undefined" => {
+            Some(JsonTokenType::Null)
+        }
+        w => Some(JsonTokenType::String(w.to_string())),
+    }
+}
+
+fn unescape_char(c: char) -> Option<char> {
+    match c {
+        '"' => Some('"'),
+        '\\' => Some('\\'),
+        '/' => Some('/'),
+        'b' => Some('\u{8}'),
+        'f' => Some('\u{c}'),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Tokenizes json, accepting the deviations from strict json in `leniency`.
+#[cfg(any(feature = "std", test))]
+pub fn tokenize_json(json: &str, leniency: Leniency) -> Result<Vec<JsonToken>, Error> {
+    tokenize_json_reporting(
+        json,
+        leniency,
+        Limits::default(),
+        &mut KeyInterner::default(),
+        &mut |_| Ok(()),
+    )
+}
+
+/// Bytes tokenized between calls to the `report` of `tokenize_json_reporting`.
+const REPORT_INTERVAL: usize = 1 << 20;
+
+/// Like `tokenize_json`, but within `limits`, sharing keys through `keys`, and
+/// calling `report` with the number of bytes tokenized every megabyte, and once
+/// done, stopping with its error if it returns one.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(json, keys, report), fields(length = json.len()))
+)]
+pub(crate) fn tokenize_json_reporting(
+    json: &str,
+    leniency: Leniency,
+    limits: Limits,
+    keys: &mut KeyInterner,
+    report: &mut dyn FnMut(usize) -> Result<(), Error>,
+) -> Result<Vec<JsonToken>, Error> {
+    if let Some(limit) = limits.max_input_bytes.filter(|limit| json.len() > *limit) {
+        return Err(Error::InputTooLarge {
+            size: json.len(),
+            limit,
+        });
+    }
+    let lenient_numbers = leniency.numbers;
+    let mut next_report = REPORT_INTERVAL;
     let mut tokens = Vec::new();
 
     let mut state = TokenizerState::Ready;
 
-    let mut line_number = 1;
-    let mut column_number = 1;
+    let mut cursor = Cursor::new();
+    // Brackets that are still open, innermost last
+    let mut open_brackets = Vec::new();
+    // The buffer strings are read into, kept between keys, which are interned
+    // rather than moved into their token, so reading them doesn't allocate
+    let mut buffer = String::new();
 
-    for (char_index, current_char) in json.chars().enumerate() {
-        let index = char_index as i64;
+    for (byte_index, current_char) in json.char_indices() {
+        if byte_index >= next_report {
+            report(byte_index)?;
+            next_report = byte_index + REPORT_INTERVAL;
+        }
+        let location = cursor.location();
+        let mut next = cursor;
+        next.advance(current_char);
+        // The span of just the current character
+        let char_span = Span::new(location, next.location());
         match state {
-            TokenizerState::Ready => {
-                let location = JsonTokenInfo::new(line_number, column_number, index);
-                match current_char {
-                    '{' => tokens.push(JsonToken::new(JsonTokenType::ObjectStart, location)),
-                    '}' => tokens.push(JsonToken::new(JsonTokenType::ObjectEnd, location)),
-                    '[' => tokens.push(JsonToken::new(JsonTokenType::ArrayStart, location)),
-                    ']' => tokens.push(JsonToken::new(JsonTokenType::ArrayEnd, location)),
-                    ':' => tokens.push(JsonToken::new(JsonTokenType::Colon, location)),
-                    // Don't care about commas
-                    ',' => {}
-                    '"' => {
-                        state = TokenizerState::ReadingString(TokenizerStringReadingState {
-                            starting_location: location,
-                            escape_next: false,
-                            value: "".into(),
-                        })
-                    }
-                    c if c.is_numeric() => {
-                        if c == '0' {
-                            return Err(Error::NumbersCannotStartWithZero(location));
-                        }
-                        state = TokenizerState::ReadingNumber(TokenizerNumberReadingState::new(
-                            location,
-                        ));
-                    }
-                    ' ' => {}
-                    _ => {
-                        return Err(Error::UnknownJsonCharacter(location, current_char));
-                    }
+            TokenizerState::Ready => match current_char {
+                '{' => {
+                    push_opening_bracket(&mut open_brackets, current_char, location, limits)?;
+                    tokens.push(JsonToken::new(JsonTokenType::ObjectStart, char_span));
                 }
-            }
-            TokenizerState::ReadingString(ref s) => {
-                if s.escape_next {
-                    state = TokenizerState::ReadingString(TokenizerStringReadingState {
-                        escape_next: false,
-                        value: format!("{}{}", s.value, current_char),
-                        ..*s
+                '[' => {
+                    push_opening_bracket(&mut open_brackets, current_char, location, limits)?;
+                    tokens.push(JsonToken::new(JsonTokenType::ArrayStart, char_span));
+                }
+                '}' | ']' => {
+                    push_closing_bracket(&mut tokens, &mut open_brackets, current_char, char_span)?
+                }
+                ':' => tokens.push(JsonToken::new(JsonTokenType::Colon, char_span)),
+                ',' => tokens.push(JsonToken::new(JsonTokenType::Comma, char_span)),
+                '"' => {
+                    state = TokenizerState::ReadingString(TokenizerStringReadingState::new(
+                        location,
+                        '"',
+                        mem::take(&mut buffer),
+                    ))
+                }
+                '\'' if leniency.relaxed => {
+                    state = TokenizerState::ReadingString(TokenizerStringReadingState::new(
+                        location,
+                        '\'',
+                        mem::take(&mut buffer),
+                    ))
+                }
+                c if c.is_ascii_digit() || c == '-' => {
+                    state = TokenizerState::ReadingNumber(TokenizerNumberReadingState::new(
+                        location, byte_index, c,
+                    ));
+                }
+                '.' if lenient_numbers => {
+                    state = TokenizerState::ReadingNumber(TokenizerNumberReadingState {
+                        seen_decimal_char: true,
+                        ..TokenizerNumberReadingState::new(location, byte_index, '.')
                     });
-                    continue;
                 }
-
-                match current_char {
-                    '"' => {
-                        // End reading this token
-                        tokens.push(JsonToken::new(
-                            JsonTokenType::String(s.value.clone()),
-                            s.starting_location,
-                        ));
-                        state = TokenizerState::Ready;
-                    }
-                    '\\' => {
-                        state = TokenizerState::ReadingString(TokenizerStringReadingState {
-                            escape_next: true,
-                            value: s.value.clone(),
-                            starting_location: s.starting_location,
+                '.' => return Err(Error::NumberMissingIntegerPart(location)),
+                c if (leniency.relaxed && (c.is_alphabetic() || c == '_' || c == '$'))
+                    || (leniency.non_finite && (c == 'N' || c == 'I')) =>
+                {
+                    state = TokenizerState::ReadingWord(TokenizerWordReadingState {
+                        starting_location: location,
+                        start: byte_index,
+                    });
+                }
+                't' | 'f' | 'n' => {
+                    let literal = match current_char {
+                        't' => "true",
+                        'f' => "false",
+                        _ => "null",
+                    };
+                    state = TokenizerState::ReadingLiteral(TokenizerLiteralReadingState {
+                        starting_location: location,
+                        literal,
+                        matched: 1,
+                    });
+                }
+                ' ' | '\t' | '\r' | '\n' => {}
+                _ => {
+                    return Err(Error::UnknownJsonCharacter(location, current_char));
+                }
+            },
+            TokenizerState::ReadingString(ref mut s) => {
+                if let Some(ref mut hex) = s.unicode_escape {
+                    if !current_char.is_ascii_hexdigit() {
+                        return Err(Error::InvalidJson {
+                            location,
+                            message: format!("Invalid unicode escape character '{}'", current_char),
                         });
                     }
-                    // We don't care about any other specific characters
-                    _ => {
-                        state = TokenizerState::ReadingString(TokenizerStringReadingState {
-                            value: format!("{}{}", s.value, current_char),
-                            ..*s
-                        });
+                    hex.push(current_char);
+                    if hex.len() == 4 {
+                        let code = u32::from_str_radix(hex, 16).unwrap();
+                        s.unicode_escape = None;
+                        push_unicode_code_point(s, code, location)?;
+                    }
+                } else if s.escape_next {
+                    s.escape_next = false;
+                    if current_char != 'u' {
+                        check_no_pending_surrogate(s, location)?;
+                    }
+                    if current_char == 'u' {
+                        s.unicode_escape = Some(String::new());
+                    } else {
+                        let unescaped = match current_char {
+                            '\'' if leniency.relaxed => Some('\''),
+                            c => unescape_char(c),
+                        };
+                        match unescaped {
+                            Some(c) => s.value.push(c),
+                            None => {
+                                return Err(Error::InvalidJson {
+                                    location,
+                                    message: format!(
+                                        "Invalid escape sequence '\\{}'",
+                                        current_char
+                                    ),
+                                })
+                            }
+                        }
+                    }
+                } else {
+                    if current_char != '\\' {
+                        check_no_pending_surrogate(s, location)?;
+                    }
+                    match current_char {
+                        c if c == s.quote => {
+                            // End reading this token
+                            let token_type = if is_key_position(&tokens, &open_brackets) {
+                                let key = keys.intern(&s.value);
+                                s.value.clear();
+                                buffer = mem::take(&mut s.value);
+                                JsonTokenType::Key(key)
+                            } else {
+                                JsonTokenType::String(mem::take(&mut s.value))
+                            };
+                            tokens.push(JsonToken::new(
+                                token_type,
+                                Span::new(s.starting_location, char_span.end),
+                            ));
+                            state = TokenizerState::Ready;
+                        }
+                        '\\' => s.escape_next = true,
+                        // Control characters must be escaped, except in relaxed
+                        // json, which is often pasted by hand with raw tabs
+                        c if c < ' ' && !leniency.relaxed => {
+                            return Err(Error::InvalidJson {
+                                location,
+                                message: format!(
+                                    "Unescaped control character {:?} in string",
+                                    current_char
+                                ),
+                            });
+                        }
+                        _ => s.value.push(current_char),
                     }
                 }
             }
@@ -143,89 +478,452 @@ pub fn tokenize_json(json: &str) -> Result<Vec<JsonToken>, Error> {
                 '.' if s.seen_exponent => {
                     return Err(Error::DecimalAfterExponent(s.starting_location));
                 }
+                '.' if s.integer_digits == 0 && !lenient_numbers => {
+                    return Err(Error::NumberMissingIntegerPart(s.starting_location));
+                }
                 '.' => {
                     state = TokenizerState::ReadingNumber(TokenizerNumberReadingState {
                         seen_decimal_char: true,
-                        ..s
+                        ..s.consume(current_char)
                     })
                 }
                 'e' | 'E' if s.seen_exponent => {
                     return Err(Error::MultipleExponentCharacters(s.starting_location));
                 }
+                // Even lenient numbers need a digit before the exponent
+                'e' | 'E' if s.integer_digits == 0 && !s.last_char.is_ascii_digit() => {
+                    return Err(Error::NumberMissingIntegerPart(s.starting_location));
+                }
+                'e' | 'E' if s.last_char == '.' && !lenient_numbers => {
+                    return Err(Error::NumberMissingFractionDigits(s.starting_location));
+                }
                 'e' | 'E' => {
                     state = TokenizerState::ReadingNumber(TokenizerNumberReadingState {
                         seen_exponent: true,
-                        ..s
+                        ..s.consume(current_char)
                     })
                 }
-                // This is fine, we just continue parsing it
-                c if c.is_numeric() => {}
-                ',' | ']' | '}' => {
-                    state = end_current_token(&mut tokens, &mut state)?;
-                    match current_char {
-                        ']' => tokens.push(JsonToken::new(
-                            JsonTokenType::ArrayEnd,
-                            JsonTokenInfo::new(line_number, column_number, index),
-                        )),
-                        '}' => tokens.push(JsonToken::new(
-                            JsonTokenType::ObjectEnd,
-                            JsonTokenInfo::new(line_number, column_number, index),
-                        )),
-                        _ => {}
+                '+' | '-' if s.last_char == 'e' || s.last_char == 'E' => {
+                    state = TokenizerState::ReadingNumber(s.consume(current_char));
+                }
+                '+' | '-' => {
+                    return Err(Error::MisplacedNumberSign(location, current_char));
+                }
+                c if c.is_ascii_digit() && (s.seen_decimal_char || s.seen_exponent) => {
+                    state = TokenizerState::ReadingNumber(s.consume(c));
+                }
+                c if c.is_ascii_digit() => {
+                    // A leading zero is only allowed on its own, e.g. `0` or `0.5`
+                    if s.starts_with_zero {
+                        return Err(Error::NumbersCannotStartWithZero(s.starting_location));
                     }
+                    state = TokenizerState::ReadingNumber(TokenizerNumberReadingState {
+                        starts_with_zero: s.integer_digits == 0 && c == '0',
+                        integer_digits: s.integer_digits + 1,
+                        ..s.consume(c)
+                    });
+                }
+                // Anything that may follow a value ends the number. A colon
+                // can't, but is left for the parser to report
+                ',' | ']' | '}' | ':' | ' ' | '\t' | '\r' | '\n' => {
+                    state = end_current_token(
+                        &mut tokens,
+                        &mut state,
+                        &json[..byte_index],
+                        location,
+                        leniency,
+                        &open_brackets,
+                        keys,
+                    )?;
+                    push_terminator(&mut tokens, &mut open_brackets, current_char, char_span)?;
+                }
+                'I' if leniency.non_finite && s.last_char == '-' => {
+                    state = TokenizerState::ReadingWord(TokenizerWordReadingState {
+                        starting_location: s.starting_location,
+                        start: s.start,
+                    });
                 }
                 v => return Err(Error::InvalidNumberCharacter(s.starting_location, v)),
             },
+            TokenizerState::ReadingWord(_) => match current_char {
+                c if is_word_char(c) => {}
+                ',' | ']' | '}' | ':' | ' ' | '\t' | '\r' | '\n' => {
+                    state = end_current_token(
+                        &mut tokens,
+                        &mut state,
+                        &json[..byte_index],
+                        location,
+                        leniency,
+                        &open_brackets,
+                        keys,
+                    )?;
+                    push_terminator(&mut tokens, &mut open_brackets, current_char, char_span)?;
+                }
+                c => return Err(Error::UnknownJsonCharacter(location, c)),
+            },
+            TokenizerState::ReadingLiteral(s) => {
+                if !s.literal[s.matched..].starts_with(current_char) {
+                    return Err(Error::UnknownJsonCharacter(location, current_char));
+                }
+                if s.matched + 1 == s.literal.len() {
+                    tokens.push(JsonToken::new(
+                        literal_token_type(s.literal),
+                        Span::new(s.starting_location, char_span.end),
+                    ));
+                    state = TokenizerState::Ready;
+                } else {
+                    state = TokenizerState::ReadingLiteral(TokenizerLiteralReadingState {
+                        matched: s.matched + 1,
+                        ..s
+                    });
+                }
+            }
         }
-        column_number += 1;
+        cursor = next;
     }
 
-    end_current_token(&mut tokens, &mut state)?;
+    let expected = match state {
+        TokenizerState::ReadingString(_) => Some("'\"' to close the string".to_string()),
+        TokenizerState::ReadingLiteral(s) => Some(format!("the rest of '{}'", s.literal)),
+        _ => None,
+    };
+    if let Some(expected) = expected {
+        return Err(Error::UnexpectedEndOfInput {
+            expected,
+            last_location: cursor.location(),
+        });
+    }
+    end_current_token(
+        &mut tokens,
+        &mut state,
+        json,
+        cursor.location(),
+        leniency,
+        &open_brackets,
+        keys,
+    )?;
+    report(json.len())?;
 
     Ok(tokens)
 }
 
+fn push_unicode_code_point(
+    s: &mut TokenizerStringReadingState,
+    code: u32,
+    location: JsonTokenInfo,
+) -> Result<(), Error> {
+    let invalid = || Error::InvalidJson {
+        location,
+        message: format!("Invalid unicode escape '\\u{:04x}'", code),
+    };
+    let code = match s.pending_surrogate.take() {
+        Some(high) if (0xDC00..0xE000).contains(&code) => {
+            0x10000 + ((high - 0xD800) << 10) + (code - 0xDC00)
+        }
+        Some(_) => return Err(invalid()),
+        None if (0xD800..0xDC00).contains(&code) => {
+            s.pending_surrogate = Some(code);
+            return Ok(());
+        }
+        None => code,
+    };
+    s.value
+        .push(core::char::from_u32(code).ok_or_else(invalid)?);
+    Ok(())
+}
+
+/// Fails if the string ends, or goes on with anything but a `\u` escape, after a
+/// high surrogate that must be followed by its low surrogate.
+fn check_no_pending_surrogate(
+    s: &TokenizerStringReadingState,
+    location: JsonTokenInfo,
+) -> Result<(), Error> {
+    match s.pending_surrogate {
+        Some(high) => Err(Error::InvalidJson {
+            location,
+            message: format!("Unpaired surrogate '\\u{:04x}'", high),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Whether a string read now is an object key, as it follows the start of an
+/// object or a comma in one.
+fn is_key_position(tokens: &[JsonToken], open_brackets: &[(char, JsonTokenInfo)]) -> bool {
+    matches!(open_brackets.last(), Some(('{', _)))
+        && matches!(
+            tokens.last().map(|t| &t.token_type),
+            Some(JsonTokenType::ObjectStart | JsonTokenType::Comma)
+        )
+}
+
+/// Pushes the token for a character that ended a number or word, if it is one.
+fn push_terminator(
+    tokens: &mut Vec<JsonToken>,
+    open_brackets: &mut Vec<(char, JsonTokenInfo)>,
+    c: char,
+    span: Span,
+) -> Result<(), Error> {
+    match c {
+        '}' | ']' => push_closing_bracket(tokens, open_brackets, c, span)?,
+        ':' => tokens.push(JsonToken::new(JsonTokenType::Colon, span)),
+        ',' => tokens.push(JsonToken::new(JsonTokenType::Comma, span)),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Opens a bracket, unless it nests deeper than `limits` allow.
+fn push_opening_bracket(
+    open_brackets: &mut Vec<(char, JsonTokenInfo)>,
+    bracket: char,
+    location: JsonTokenInfo,
+    limits: Limits,
+) -> Result<(), Error> {
+    let limit = limits.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+    if open_brackets.len() >= limit {
+        return Err(Error::TooDeep { location, limit });
+    }
+    open_brackets.push((bracket, location));
+    Ok(())
+}
+
+/// Pushes the token for a closing bracket, after checking that it closes the
+/// innermost open bracket. A bracket closing nothing is left for the parser.
+fn push_closing_bracket(
+    tokens: &mut Vec<JsonToken>,
+    open_brackets: &mut Vec<(char, JsonTokenInfo)>,
+    found: char,
+    span: Span,
+) -> Result<(), Error> {
+    if let Some((open, open_location)) = open_brackets.pop() {
+        let expected = if open == '{' { '}' } else { ']' };
+        if found != expected {
+            return Err(Error::MismatchedBracket {
+                expected,
+                found,
+                open_location,
+                close_location: span.start,
+            });
+        }
+    }
+    let token_type = if found == '}' {
+        JsonTokenType::ObjectEnd
+    } else {
+        JsonTokenType::ArrayEnd
+    };
+    tokens.push(JsonToken::new(token_type, span));
+    Ok(())
+}
+
+/// The location just past the end of `json`.
+pub(crate) fn end_location(json: &str) -> JsonTokenInfo {
+    let mut cursor = Cursor::new();
+    json.chars().for_each(|c| cursor.advance(c));
+    cursor.location()
+}
+
+/// Ends the number or word being read, if any, where `json` is the input up to
+/// its end, and `end` is the location there. Unquoted keys are interned in
+/// `keys`, given with the brackets still open. Strings and literals end on
+/// their own characters.
 fn end_current_token(
-    mut tokens: &mut Vec<JsonToken>,
+    tokens: &mut Vec<JsonToken>,
     state: &mut TokenizerState,
+    json: &str,
+    end: JsonTokenInfo,
+    leniency: Leniency,
+    open_brackets: &[(char, JsonTokenInfo)],
+    keys: &mut KeyInterner,
 ) -> Result<TokenizerState, Error> {
-    println!("Ending state: {:?}", state);
+    trace!(?state, "ending token");
     match state {
-        TokenizerState::ReadingString(s) => {
-            return Err(Error::UnclosedString(s.starting_location));
-        }
         TokenizerState::ReadingNumber(s) => {
-            println!("Reading number");
-            add_number_to_tokens(&mut tokens, *s);
+            add_number_to_tokens(tokens, *s, &json[s.start..], end, leniency)?
         }
-        // If the tokenizer is simple ready, then we don't really have to do anything
-        TokenizerState::Ready => {}
+        TokenizerState::ReadingWord(s) => {
+            let word = &json[s.start..];
+            let token_type = match word_token_type(word, leniency) {
+                Some(JsonTokenType::String(_)) if is_key_position(tokens, open_brackets) => {
+                    JsonTokenType::Key(keys.intern(word))
+                }
+                Some(token_type) => token_type,
+                None => {
+                    return Err(Error::UnknownJsonCharacter(
+                        s.starting_location,
+                        word.chars().next().unwrap(),
+                    ))
+                }
+            };
+            tokens.push(JsonToken::new(
+                token_type,
+                Span::new(s.starting_location, end),
+            ));
+        }
+        _ => {}
     }
     Ok(TokenizerState::Ready)
 }
 
-fn add_number_to_tokens(tokens: &mut Vec<JsonToken>, s: TokenizerNumberReadingState) -> () {
-    if s.seen_decimal_char {
-        println!("Seen decimal char");
-        tokens.push(JsonToken::new(JsonTokenType::Float, s.starting_location));
+fn add_number_to_tokens(
+    tokens: &mut Vec<JsonToken>,
+    s: TokenizerNumberReadingState,
+    text: &str,
+    end: JsonTokenInfo,
+    leniency: Leniency,
+) -> Result<(), Error> {
+    let span = Span::new(s.starting_location, end);
+    // Even lenient numbers need digits on one side of the separator
+    if s.last_char == '.' && (!leniency.numbers || s.integer_digits == 0) {
+        return Err(Error::NumberMissingFractionDigits(s.starting_location));
+    }
+    if !s.last_char.is_ascii_digit() && s.last_char != '.' {
+        return Err(Error::InvalidNumberCharacter(
+            s.starting_location,
+            s.last_char,
+        ));
+    }
+    let exact = match s.seen_exponent && leniency.exponent_integers {
+        true => exponent_integer(text),
+        false => None,
+    };
+    if let Some(value) = exact {
+        trace!(text, value, "read integer with an exponent");
+        tokens.push(JsonToken::new(JsonTokenType::ExponentInt(value), span));
+    } else if s.seen_decimal_char || s.seen_exponent {
+        let value = parse_number(text, s.starting_location)?;
+        trace!(text, value, "read float");
+        tokens.push(JsonToken::new(JsonTokenType::Float(value), span));
     } else {
-        println!("Not seen decimal");
-        tokens.push(JsonToken::new(JsonTokenType::Int, s.starting_location));
+        // Integers too large even for an i128 are kept as floats
+        let token_type = match text.parse() {
+            Ok(value) => JsonTokenType::Int(value),
+            Err(_) => JsonTokenType::Float(parse_number(text, s.starting_location)?),
+        };
+        trace!(text, token = ?token_type, "read integer");
+        tokens.push(JsonToken::new(token_type, span));
     }
+    Ok(())
+}
+
+/// The value of the text of a number, which the state machine should only let
+/// through when it parses, but is an error rather than a made up value if not.
+fn parse_number(text: &str, location: JsonTokenInfo) -> Result<f64, Error> {
+    text.parse().map_err(|_| Error::InvalidJson {
+        location,
+        message: format!("Invalid number '{}'", text),
+    })
+}
+
+/// The value of a number with an exponent, if it is exactly an integer that
+/// fits an i128.
+fn exponent_integer(text: &str) -> Option<i128> {
+    let (mantissa, exponent) = text.split_once(['e', 'E'])?;
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let mut digits = String::from(integer.trim_start_matches('-'));
+    digits.push_str(fraction);
+    let exponent = exponent
+        .parse::<i64>()
+        .ok()?
+        .checked_sub(fraction.len() as i64)?;
+    if digits.bytes().all(|b| b == b'0') {
+        return Some(0);
+    }
+    if exponent < 0 {
+        // The digits moved past the separator must all be zeros
+        let kept = digits.len().checked_sub(exponent.unsigned_abs() as usize)?;
+        if digits[kept..].bytes().any(|b| b != b'0') {
+            return None;
+        }
+        digits.truncate(kept);
+    } else if exponent > 40 {
+        // More digits than an i128 has
+        return None;
+    } else {
+        digits.extend(core::iter::repeat_n('0', exponent as usize));
+    }
+    let value: i128 = digits.parse().ok()?;
+    Some(if integer.starts_with('-') {
+        -value
+    } else {
+        value
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const LENIENT_NUMBERS: Leniency = Leniency {
+        numbers: true,
+        relaxed: false,
+        non_finite: false,
+        exponent_integers: false,
+    };
+    const RELAXED: Leniency = Leniency {
+        numbers: false,
+        relaxed: true,
+        non_finite: false,
+        exponent_integers: false,
+    };
+    const NON_FINITE: Leniency = Leniency {
+        numbers: false,
+        relaxed: false,
+        non_finite: true,
+        exponent_integers: false,
+    };
+
+    /// Counts the allocations of each thread, so tests running at the same time
+    /// don't count each other's.
+    struct CountingAllocator;
+
+    std::thread_local! {
+        static ALLOCATIONS: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
     fn simple_tokenize(json: &str) -> Vec<JsonTokenType> {
-        tokenize_json(json)
+        tokenize_json(json, Leniency::default())
             .unwrap()
             .into_iter()
             .map(|v| v.token_type)
             .collect()
     }
 
+    #[test]
+    fn allocates_each_key_once() {
+        let records = |count: usize| {
+            let records: Vec<String> = (0..count)
+                .map(|i| format!("{{\"id\": {}, \"name\": {}, \"email\": {}}}", i, i, i))
+                .collect();
+            format!("[{}]", records.join(", "))
+        };
+        let allocations = |json: &str| {
+            let before = ALLOCATIONS.with(|count| count.get());
+            let tokens = tokenize_json(json, Leniency::default()).unwrap();
+            let after = ALLOCATIONS.with(|count| count.get());
+            drop(tokens);
+            after - before
+        };
+        let (few, many) = (records(10), records(10_000));
+
+        // Only growing the token vector allocates more for more records
+        assert!(allocations(&many) < allocations(&few) + 50);
+    }
+
     #[test]
     fn tokenizes_a_simple_object() {
         let result = simple_tokenize(r#"{}"#);
@@ -254,7 +952,7 @@ mod tests {
             result,
             vec![
                 JsonTokenType::ObjectStart,
-                JsonTokenType::String("foo".into()),
+                JsonTokenType::Key("foo".into()),
                 JsonTokenType::Colon,
                 JsonTokenType::String("bar".into()),
                 JsonTokenType::ObjectEnd
@@ -284,7 +982,7 @@ mod tests {
             result,
             vec![
                 JsonTokenType::ArrayStart,
-                JsonTokenType::Int,
+                JsonTokenType::Int(42),
                 JsonTokenType::ArrayEnd
             ]
         )
@@ -298,7 +996,7 @@ mod tests {
             result,
             vec![
                 JsonTokenType::ArrayStart,
-                JsonTokenType::Float,
+                JsonTokenType::Float(42.5),
                 JsonTokenType::ArrayEnd
             ]
         )
@@ -312,10 +1010,11 @@ mod tests {
             result,
             vec![
                 JsonTokenType::ObjectStart,
-                JsonTokenType::String("foo".into()),
+                JsonTokenType::Key("foo".into()),
                 JsonTokenType::Colon,
                 JsonTokenType::String("bar".into()),
-                JsonTokenType::String("baz".into()),
+                JsonTokenType::Comma,
+                JsonTokenType::Key("baz".into()),
                 JsonTokenType::Colon,
                 JsonTokenType::String("bing".into()),
                 JsonTokenType::ObjectEnd
@@ -325,7 +1024,7 @@ mod tests {
 
     #[test]
     fn error_on_double_decimal() {
-        let result = tokenize_json("5.5.5");
+        let result = tokenize_json("5.5.5", Leniency::default());
         assert_eq!(
             result,
             Err(Error::MultipleDecimalSeparators(JsonTokenInfo::new(
@@ -336,7 +1035,7 @@ mod tests {
 
     #[test]
     fn error_on_double_exponent() {
-        let result = tokenize_json("5e5e5");
+        let result = tokenize_json("5e5e5", Leniency::default());
         assert_eq!(
             result,
             Err(Error::MultipleExponentCharacters(JsonTokenInfo::new(
@@ -347,25 +1046,487 @@ mod tests {
 
     #[test]
     fn error_on_decimal_after_exponent() {
-        let result = tokenize_json("5e5.5");
+        let result = tokenize_json("5e5.5", Leniency::default());
         assert_eq!(
             result,
             Err(Error::DecimalAfterExponent(JsonTokenInfo::new(1, 1, 0)))
         )
     }
 
+    #[test]
+    fn numbers_end_on_whitespace() {
+        let result = simple_tokenize("[1 , 2\t,3\r\n,\n  4.5\n]");
+
+        assert_eq!(
+            result,
+            vec![
+                JsonTokenType::ArrayStart,
+                JsonTokenType::Int(1),
+                JsonTokenType::Comma,
+                JsonTokenType::Int(2),
+                JsonTokenType::Comma,
+                JsonTokenType::Int(3),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(4.5),
+                JsonTokenType::ArrayEnd,
+            ]
+        )
+    }
+
+    #[test]
+    fn tracks_lines_after_numbers() {
+        let tokens = tokenize_json("[1\n]", Leniency::default()).unwrap();
+
+        assert_eq!(tokens[2].span.start, JsonTokenInfo::new(2, 1, 3));
+    }
+
+    fn locations(json: &str) -> Vec<JsonTokenInfo> {
+        tokenize_json(json, Leniency::default())
+            .unwrap()
+            .into_iter()
+            .map(|v| v.span.start)
+            .collect()
+    }
+
+    #[test]
+    fn tracks_locations_across_multi_line_documents() {
+        let json = "{\n  \"id\": 12,\n  \"ok\": true,\n  \"tags\": [\"a\", null]\n}";
+
+        assert_eq!(
+            locations(json),
+            vec![
+                JsonTokenInfo::new(1, 1, 0),
+                JsonTokenInfo::new(2, 3, 4),
+                JsonTokenInfo::new(2, 7, 8),
+                JsonTokenInfo::new(2, 9, 10),
+                JsonTokenInfo::new(2, 11, 12),
+                JsonTokenInfo::new(3, 3, 16),
+                JsonTokenInfo::new(3, 7, 20),
+                JsonTokenInfo::new(3, 9, 22),
+                JsonTokenInfo::new(3, 13, 26),
+                JsonTokenInfo::new(4, 3, 30),
+                JsonTokenInfo::new(4, 9, 36),
+                JsonTokenInfo::new(4, 11, 38),
+                JsonTokenInfo::new(4, 12, 39),
+                JsonTokenInfo::new(4, 15, 42),
+                JsonTokenInfo::new(4, 17, 44),
+                JsonTokenInfo::new(4, 21, 48),
+                JsonTokenInfo::new(5, 1, 50),
+            ]
+        )
+    }
+
+    #[test]
+    fn tracks_lines_inside_strings() {
+        // Only relaxed json allows raw newlines in strings
+        let json = "[\"a\nb\", 1,\n\"\u{e9}\u{e9}\", 2]";
+        let locations: Vec<JsonTokenInfo> = tokenize_json(json, RELAXED)
+            .unwrap()
+            .into_iter()
+            .map(|v| v.span.start)
+            .collect();
+
+        assert_eq!(
+            locations,
+            vec![
+                JsonTokenInfo::new(1, 1, 0),
+                JsonTokenInfo::new(1, 2, 1),
+                JsonTokenInfo::new(2, 3, 6),
+                JsonTokenInfo::new(2, 5, 8),
+                JsonTokenInfo::new(2, 6, 9),
+                JsonTokenInfo::new(3, 1, 11),
+                JsonTokenInfo::new(3, 5, 15),
+                JsonTokenInfo::new(3, 7, 17),
+                JsonTokenInfo::new(3, 8, 18),
+            ]
+        )
+    }
+
+    #[test]
+    fn reports_errors_at_the_offending_line() {
+        let result = tokenize_json("[\n  1,\n  \"x\",\n  tru3\n]", Leniency::default());
+
+        assert_eq!(
+            result,
+            Err(Error::UnknownJsonCharacter(
+                JsonTokenInfo::new(4, 6, 19),
+                '3'
+            ))
+        )
+    }
+
+    #[test]
+    fn numbers_end_on_colons() {
+        let result = simple_tokenize("{1: 2}");
+
+        assert_eq!(
+            result,
+            vec![
+                JsonTokenType::ObjectStart,
+                JsonTokenType::Int(1),
+                JsonTokenType::Colon,
+                JsonTokenType::Int(2),
+                JsonTokenType::ObjectEnd,
+            ]
+        )
+    }
+
+    #[test]
+    fn tokenizes_signed_exponents() {
+        let result = simple_tokenize("[1e+10, 2E+3, 5e-1, 1.5E-2]");
+
+        assert_eq!(
+            result,
+            vec![
+                JsonTokenType::ArrayStart,
+                JsonTokenType::Float(1e10),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(2e3),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(0.5),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(0.015),
+                JsonTokenType::ArrayEnd,
+            ]
+        )
+    }
+
+    #[test]
+    fn reads_exact_exponents_as_integers() {
+        let leniency = Leniency {
+            exponent_integers: true,
+            ..Leniency::default()
+        };
+        let result: Vec<JsonTokenType> = tokenize_json(
+            "[1e3, -2.5E+1, 50e-1, 0e-9, 5e-1, 1.25e1, 1e40, 1.5e-9223372036854775808]",
+            leniency,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|t| t.token_type)
+        .collect();
+
+        assert_eq!(
+            result,
+            vec![
+                JsonTokenType::ArrayStart,
+                JsonTokenType::ExponentInt(1000),
+                JsonTokenType::Comma,
+                JsonTokenType::ExponentInt(-25),
+                JsonTokenType::Comma,
+                JsonTokenType::ExponentInt(5),
+                JsonTokenType::Comma,
+                JsonTokenType::ExponentInt(0),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(0.5),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(12.5),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(1e40),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(0.0),
+                JsonTokenType::ArrayEnd,
+            ]
+        )
+    }
+
+    #[test]
+    fn error_on_misplaced_sign() {
+        assert_eq!(
+            tokenize_json("[1+2]", Leniency::default()),
+            Err(Error::MisplacedNumberSign(JsonTokenInfo::new(1, 3, 2), '+'))
+        );
+        assert_eq!(
+            tokenize_json("[1e5-2]", Leniency::default()),
+            Err(Error::MisplacedNumberSign(JsonTokenInfo::new(1, 5, 4), '-'))
+        );
+        assert_eq!(
+            tokenize_json("[1e+-2]", Leniency::default()),
+            Err(Error::MisplacedNumberSign(JsonTokenInfo::new(1, 5, 4), '-'))
+        );
+    }
+
+    #[test]
+    fn error_on_missing_integer_part() {
+        assert_eq!(
+            tokenize_json("[1, .5]", Leniency::default()),
+            Err(Error::NumberMissingIntegerPart(JsonTokenInfo::new(1, 5, 4)))
+        );
+        assert_eq!(
+            tokenize_json("-.5", Leniency::default()),
+            Err(Error::NumberMissingIntegerPart(JsonTokenInfo::new(1, 1, 0)))
+        );
+    }
+
+    #[test]
+    fn error_on_exponent_without_digits_before_it() {
+        for leniency in [Leniency::default(), LENIENT_NUMBERS] {
+            assert_eq!(
+                tokenize_json("[1, -e5]", leniency),
+                Err(Error::NumberMissingIntegerPart(JsonTokenInfo::new(1, 5, 4)))
+            );
+            assert_eq!(
+                tokenize_json("-E1", leniency),
+                Err(Error::NumberMissingIntegerPart(JsonTokenInfo::new(1, 1, 0)))
+            );
+            assert_eq!(
+                tokenize_json(".e5", leniency),
+                Err(Error::NumberMissingIntegerPart(JsonTokenInfo::new(1, 1, 0)))
+            );
+        }
+    }
+
+    #[test]
+    fn error_on_missing_fraction_digits() {
+        assert_eq!(
+            tokenize_json("[1, 5.]", Leniency::default()),
+            Err(Error::NumberMissingFractionDigits(JsonTokenInfo::new(
+                1, 5, 4
+            )))
+        );
+        assert_eq!(
+            tokenize_json("5.e3", Leniency::default()),
+            Err(Error::NumberMissingFractionDigits(JsonTokenInfo::new(
+                1, 1, 0
+            )))
+        );
+    }
+
+    #[test]
+    fn lenient_numbers_accept_missing_digits() {
+        assert_eq!(
+            tokenize_json("[.]", LENIENT_NUMBERS),
+            Err(Error::NumberMissingFractionDigits(JsonTokenInfo::new(
+                1, 2, 1
+            )))
+        );
+        let result: Vec<JsonTokenType> = tokenize_json("[.5, 5., -.5, 5.e1]", LENIENT_NUMBERS)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token_type)
+            .collect();
+
+        assert_eq!(
+            result,
+            vec![
+                JsonTokenType::ArrayStart,
+                JsonTokenType::Float(0.5),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(5.0),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(-0.5),
+                JsonTokenType::Comma,
+                JsonTokenType::Float(50.0),
+                JsonTokenType::ArrayEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn error_on_unpaired_surrogates() {
+        let error = |location, message: &str| {
+            Err(Error::InvalidJson {
+                location,
+                message: message.to_string(),
+            })
+        };
+
+        assert_eq!(
+            tokenize_json(r#""\ud800""#, Leniency::default()),
+            error(JsonTokenInfo::new(1, 8, 7), "Unpaired surrogate '\\ud800'")
+        );
+        assert_eq!(
+            tokenize_json(r#""\ud800a""#, Leniency::default()),
+            error(JsonTokenInfo::new(1, 8, 7), "Unpaired surrogate '\\ud800'")
+        );
+        assert_eq!(
+            tokenize_json(r#""\ud800\n""#, Leniency::default()),
+            error(JsonTokenInfo::new(1, 9, 8), "Unpaired surrogate '\\ud800'")
+        );
+        assert_eq!(
+            tokenize_json(r#""\udc00""#, Leniency::default()),
+            error(
+                JsonTokenInfo::new(1, 7, 6),
+                "Invalid unicode escape '\\udc00'"
+            )
+        );
+        assert!(tokenize_json(r#""\ud83d\ude00""#, Leniency::default()).is_ok());
+    }
+
+    #[test]
+    fn error_on_unescaped_control_characters() {
+        assert_eq!(
+            tokenize_json("[\"a\tb\"]", Leniency::default()),
+            Err(Error::InvalidJson {
+                location: JsonTokenInfo::new(1, 4, 3),
+                message: "Unescaped control character '\\t' in string".to_string(),
+            })
+        );
+        assert!(tokenize_json("[\"a\tb\"]", RELAXED).is_ok());
+    }
+
     #[test]
     fn error_on_unclosed_string() {
-        let result = tokenize_json(r#""foo"#);
+        let result = tokenize_json("[\n\"foo", Leniency::default());
+        assert_eq!(
+            result,
+            Err(Error::UnexpectedEndOfInput {
+                expected: "'\"' to close the string".to_string(),
+                last_location: JsonTokenInfo::new(2, 5, 6),
+            })
+        )
+    }
+
+    #[test]
+    fn relaxed_json_accepts_object_literals() {
+        let result: Vec<JsonTokenType> = tokenize_json(
+            "{name: 'it\\'s', ok: True, gone: None, $x: undefined, n: 1}",
+            RELAXED,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|v| v.token_type)
+        .collect();
+
         assert_eq!(
             result,
-            Err(Error::UnclosedString(JsonTokenInfo::new(1, 1, 0)))
+            vec![
+                JsonTokenType::ObjectStart,
+                JsonTokenType::Key("name".into()),
+                JsonTokenType::Colon,
+                JsonTokenType::String("it's".into()),
+                JsonTokenType::Comma,
+                JsonTokenType::Key("ok".into()),
+                JsonTokenType::Colon,
+                JsonTokenType::Bool,
+                JsonTokenType::Comma,
+                JsonTokenType::Key("gone".into()),
+                JsonTokenType::Colon,
+                JsonTokenType::Null,
+                JsonTokenType::Comma,
+                JsonTokenType::Key("$x".into()),
+                JsonTokenType::Colon,
+                JsonTokenType::Null,
+                JsonTokenType::Comma,
+                JsonTokenType::Key("n".into()),
+                JsonTokenType::Colon,
+                JsonTokenType::Int(1),
+                JsonTokenType::ObjectEnd,
+            ]
+        );
+        assert_eq!(
+            tokenize_json("{'a': 1}", Leniency::default()),
+            Err(Error::UnknownJsonCharacter(
+                JsonTokenInfo::new(1, 2, 1),
+                '\''
+            ))
+        );
+        assert_eq!(
+            tokenize_json("[NaN]", RELAXED),
+            Err(Error::UnknownJsonCharacter(
+                JsonTokenInfo::new(1, 2, 1),
+                'N'
+            ))
+        );
+    }
+
+    #[test]
+    fn non_finite_numbers_become_floats() {
+        let result: Vec<JsonTokenType> = tokenize_json("[NaN, Infinity,-Infinity]", NON_FINITE)
+            .unwrap()
+            .into_iter()
+            .map(|v| v.token_type)
+            .collect();
+
+        assert!(matches!(result[1], JsonTokenType::Float(v) if v.is_nan()));
+        assert_eq!(result[3], JsonTokenType::Float(f64::INFINITY));
+        assert_eq!(result[5], JsonTokenType::Float(f64::NEG_INFINITY));
+        assert_eq!(
+            tokenize_json("[Nan]", NON_FINITE),
+            Err(Error::UnknownJsonCharacter(
+                JsonTokenInfo::new(1, 2, 1),
+                'N'
+            ))
+        );
+        assert_eq!(
+            tokenize_json("[-Infinity]", Leniency::default()),
+            Err(Error::InvalidNumberCharacter(
+                JsonTokenInfo::new(1, 2, 1),
+                'I'
+            ))
+        );
+    }
+
+    #[test]
+    fn stops_at_the_limits() {
+        let limits = Limits {
+            max_input_bytes: Some(10),
+            max_depth: Some(2),
+        };
+        let tokenize = |json| {
+            tokenize_json_reporting(
+                json,
+                Leniency::default(),
+                limits,
+                &mut KeyInterner::default(),
+                &mut |_| Ok(()),
+            )
+        };
+
+        assert!(tokenize("[[1], {}]").is_ok());
+        assert_eq!(
+            tokenize("[{\"a\": []}]"),
+            Err(Error::InputTooLarge {
+                size: 11,
+                limit: 10
+            })
+        );
+        assert_eq!(
+            tokenize("[{\"a\":[]}]"),
+            Err(Error::TooDeep {
+                location: JsonTokenInfo::new(1, 7, 6),
+                limit: 2
+            })
+        );
+    }
+
+    #[test]
+    fn error_on_mismatched_brackets() {
+        assert_eq!(
+            tokenize_json("[{]", Leniency::default()),
+            Err(Error::MismatchedBracket {
+                expected: '}',
+                found: ']',
+                open_location: JsonTokenInfo::new(1, 2, 1),
+                close_location: JsonTokenInfo::new(1, 3, 2),
+            })
+        );
+        assert_eq!(
+            tokenize_json("{\"a\": [1,\n 2}]", Leniency::default()),
+            Err(Error::MismatchedBracket {
+                expected: ']',
+                found: '}',
+                open_location: JsonTokenInfo::new(1, 7, 6),
+                close_location: JsonTokenInfo::new(2, 3, 12),
+            })
+        );
+    }
+
+    #[test]
+    fn error_on_truncated_literal() {
+        let result = tokenize_json("[tr", Leniency::default());
+        assert_eq!(
+            result,
+            Err(Error::UnexpectedEndOfInput {
+                expected: "the rest of 'true'".to_string(),
+                last_location: JsonTokenInfo::new(1, 4, 3),
+            })
         )
     }
 
     #[test]
     fn error_if_number_starts_with_0() {
-        let result = tokenize_json("042");
+        let result = tokenize_json("042", Leniency::default());
         assert_eq!(
             result,
             Err(Error::NumbersCannotStartWithZero(JsonTokenInfo::new(
@@ -382,10 +1543,10 @@ mod tests {
             result,
             vec![
                 JsonTokenType::ObjectStart,
-                JsonTokenType::String("foo".into()),
+                JsonTokenType::Key("foo".into()),
                 JsonTokenType::Colon,
                 JsonTokenType::ObjectStart,
-                JsonTokenType::String("bar".into()),
+                JsonTokenType::Key("bar".into()),
                 JsonTokenType::Colon,
                 JsonTokenType::String("baz".into()),
                 JsonTokenType::ObjectEnd,
@@ -0,0 +1,137 @@
+use crate::{JsonStructure, JsonValue, RustType};
+use alloc::{string::String, vec::Vec};
+
+/// Maps fields whose name matches `pattern` to a fixed rust type, regardless of
+/// what was inferred for them.
+///
+/// Patterns are globs, where `*` matches any number of characters and `?`
+/// matches exactly one.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TypeMapping {
+    pattern: String,
+    rust_type: RustType,
+}
+
+impl TypeMapping {
+    pub fn new<S: Into<String>>(pattern: S, rust_type: RustType) -> TypeMapping {
+        TypeMapping {
+            pattern: pattern.into(),
+            rust_type,
+        }
+    }
+
+    pub fn matches(&self, field_name: &str) -> bool {
+        glob_matches(&self.pattern, field_name)
+    }
+}
+
+pub fn glob_matches(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+
+    // Classic backtracking matcher, only ever backtracking to the last `*`
+    let (mut p, mut v) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            last_star = Some((p, v));
+            p += 1;
+        } else if let Some((star_p, star_v)) = last_star {
+            p = star_p + 1;
+            v = star_v + 1;
+            last_star = Some((star_p, star_v + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Replaces the structure of every field matching one of the mappings. The
+/// first matching mapping wins, and optional fields stay optional.
+pub fn apply_type_mappings(structure: &mut JsonStructure, mappings: &[TypeMapping]) {
+    if mappings.is_empty() {
+        return;
+    }
+    match structure {
+        JsonStructure::Object(pairs) => {
+            for pair in pairs {
+                match mappings.iter().find(|m| m.matches(&pair.key)) {
+                    Some(mapping) => replace_with(&mut pair.value, &mapping.rust_type),
+                    None => apply_type_mappings(&mut pair.value, mappings),
+                }
+            }
+        }
+        JsonStructure::Dictionary(pair) => apply_type_mappings(&mut pair.value, mappings),
+        JsonStructure::Array(inner) | JsonStructure::Optional(inner) => {
+            apply_type_mappings(inner, mappings)
+        }
+        JsonStructure::Union(variants) => {
+            for variant in variants {
+                apply_type_mappings(variant, mappings);
+            }
+        }
+        JsonStructure::Value(_) | JsonStructure::Unknown => {}
+    }
+}
+
+fn replace_with(structure: &mut JsonStructure, rust_type: &RustType) {
+    match structure {
+        JsonStructure::Optional(inner) => replace_with(inner, rust_type),
+        other => *other = JsonStructure::Value(JsonValue::Custom(rust_type.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{convert_sample_json_with_options, ConvertOptions, JsonPair};
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_matches("*_at", "created_at"));
+        assert!(glob_matches("*_at", "_at"));
+        assert!(!glob_matches("*_at", "created_at_time"));
+        assert!(glob_matches("metadata", "metadata"));
+        assert!(!glob_matches("metadata", "metadatas"));
+        assert!(glob_matches("user?", "users"));
+        assert!(glob_matches("*a*b*", "xxaxxbxx"));
+        assert!(!glob_matches("*a*b*", "xxbxxaxx"));
+        assert!(glob_matches("*", ""));
+    }
+
+    #[test]
+    fn replaces_matching_fields_and_keeps_optionality() {
+        let mut options = ConvertOptions::default();
+        options
+            .add_type_mapping("*_at", RustType::new("chrono::DateTime<Utc>"))
+            .add_type_mapping("metadata", RustType::new("serde_json::Value"));
+
+        let result = convert_sample_json_with_options(
+            r#"[{"created_at": "x", "metadata": {"a": 1}}, {"metadata": {"b": 2}}]"#,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            JsonStructure::Array(Box::new(JsonStructure::Object(vec![
+                JsonPair {
+                    key: "created_at".into(),
+                    value: Box::new(JsonStructure::Optional(Box::new(JsonStructure::Value(
+                        JsonValue::Custom(RustType::new("chrono::DateTime<Utc>"))
+                    )))),
+                },
+                JsonPair {
+                    key: "metadata".into(),
+                    value: Box::new(JsonStructure::Value(JsonValue::Custom(RustType::new(
+                        "serde_json::Value"
+                    )))),
+                },
+            ])))
+        )
+    }
+}
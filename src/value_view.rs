@@ -0,0 +1,264 @@
+use crate::backend::{Artifact, CodegenBackend};
+use crate::inference::{I128, U64};
+use crate::naming::{field_name, to_snake_case, unique_field_names};
+use crate::options::ConvertOptions;
+use crate::symbols::SymbolTable;
+use crate::{JsonPair, JsonStructure, JsonValue};
+
+struct ViewGenerator<'a> {
+    symbols: &'a SymbolTable,
+    definitions: Vec<String>,
+    defined: Vec<String>,
+    path: Vec<String>,
+}
+
+impl<'a> ViewGenerator<'a> {
+    /// The type a getter returns for `structure`, inside an `Option` that is
+    /// `None` when the value is missing or isn't of that type.
+    fn view_type(&mut self, structure: &JsonStructure) -> String {
+        match structure {
+            JsonStructure::Object(pairs) => format!("{}<'a>", self.emit_view(pairs)),
+            JsonStructure::Array(inner) => format!(
+                "impl Iterator<Item = Option<{}>> + 'a",
+                self.view_type(inner)
+            ),
+            JsonStructure::Dictionary(pair) => format!(
+                "impl Iterator<Item = (&'a str, Option<{}>)> + 'a",
+                self.view_type(pair.value())
+            ),
+            JsonStructure::Optional(inner) => self.view_type(inner),
+            JsonStructure::Value(JsonValue::Int) => "i64".to_string(),
+            JsonStructure::Value(JsonValue::Float) => "f64".to_string(),
+            JsonStructure::Value(JsonValue::Bool) => "bool".to_string(),
+            JsonStructure::Value(JsonValue::Custom(rust_type))
+                if rust_type.path() == U64 || rust_type.path() == I128 =>
+            {
+                rust_type.path().to_string()
+            }
+            // Custom types are usually sent as strings, which the caller can
+            // parse
+            JsonStructure::Value(JsonValue::String)
+            | JsonStructure::Value(JsonValue::Custom(_)) => "&'a str".to_string(),
+            JsonStructure::Union(_) | JsonStructure::Unknown => "&'a Value".to_string(),
+        }
+    }
+
+    /// The expression reading `value`, a `&'a Value`, as the view type of
+    /// `structure`.
+    fn read(&self, structure: &JsonStructure, value: &str) -> String {
+        match structure {
+            JsonStructure::Object(_) => format!(
+                "{}.is_object().then(|| {}View({}))",
+                value,
+                self.symbols.name_at(&self.path),
+                value
+            ),
+            JsonStructure::Array(inner) => format!(
+                "{}.as_array().map(|a| a.iter().map(|v| {}))",
+                value,
+                self.read(inner, "v")
+            ),
+            JsonStructure::Dictionary(pair) => format!(
+                "{}.as_object().map(|o| o.iter().map(|(k, v)| (k.as_str(), {})))",
+                value,
+                self.read(pair.value(), "v")
+            ),
+            JsonStructure::Optional(inner) => match inner.as_ref() {
+                JsonStructure::Union(_) | JsonStructure::Unknown => {
+                    format!("Some({}).filter(|v| !v.is_null())", value)
+                }
+                other => self.read(other, value),
+            },
+            JsonStructure::Value(JsonValue::Int) => format!("{}.as_i64()", value),
+            JsonStructure::Value(JsonValue::Float) => format!("{}.as_f64()", value),
+            JsonStructure::Value(JsonValue::Bool) => format!("{}.as_bool()", value),
+            JsonStructure::Value(JsonValue::Custom(rust_type)) if rust_type.path() == U64 => {
+                format!("{}.as_u64()", value)
+            }
+            JsonStructure::Value(JsonValue::Custom(rust_type)) if rust_type.path() == I128 => {
+                format!(
+                    "{value}.as_i64().map(i128::from).or_else(|| {value}.as_u64().map(i128::from))",
+                    value = value
+                )
+            }
+            JsonStructure::Value(_) => format!("{}.as_str()", value),
+            JsonStructure::Union(_) | JsonStructure::Unknown => format!("Some({})", value),
+        }
+    }
+
+    /// The view of an object needs the names of the views of its fields, which
+    /// `view_type` defines, so it runs first.
+    fn emit_view(&mut self, pairs: &[JsonPair]) -> String {
+        let view_name = format!("{}View", self.symbols.name_at(&self.path));
+        if self.defined.contains(&view_name) {
+            return view_name;
+        }
+        self.defined.push(view_name.clone());
+        self.definitions.push(String::new());
+        let index = self.definitions.len() - 1;
+
+        let mut getters = String::new();
+        let keys: Vec<&str> = pairs.iter().map(JsonPair::key).collect();
+        let names: Vec<String> = keys.iter().map(|key| field_name(key)).collect();
+        let names = unique_field_names(&keys, &names);
+        for (position, (pair, name)) in pairs.iter().zip(names).enumerate() {
+            let getter = match name {
+                name if name.is_empty() => format!("field_{}", position + 1),
+                name => name,
+            };
+            self.path.push(pair.key().to_string());
+            let view_type = self.view_type(pair.value());
+            let read = self.read(pair.value(), "value");
+            self.path.pop();
+            getters.push_str(&format!(
+                "\n    pub fn {}(&self) -> Option<{}> {{\n        \
+                 let value = self.0.get({:?})?;\n        {}\n    }}\n",
+                getter,
+                view_type,
+                pair.key(),
+                read
+            ));
+        }
+        self.definitions[index] = format!(
+            "#[derive(Debug, Clone, Copy, PartialEq)]\n\
+             pub struct {name}<'a>(pub &'a Value);\n\n\
+             impl<'a> {name}<'a> {{{getters}}}\n",
+            name = view_name,
+            getters = getters
+        );
+        view_name
+    }
+}
+
+/// Emits typed views over a `serde_json::Value` instead of structs, as a
+/// single `<root_name>.rs` file, for code that keeps the `Value` to pass on
+/// fields it doesn't know about. Every object gets a `<Name>View<'a>(&'a
+/// Value)` with a getter per field, which is `None` when the field is missing
+/// or doesn't have the type it had in the sample. Values are only checked when
+/// read.
+pub struct ValueViewBackend;
+
+impl CodegenBackend for ValueViewBackend {
+    fn generate(
+        &self,
+        structure: &JsonStructure,
+        root_name: &str,
+        options: &ConvertOptions,
+    ) -> Vec<Artifact> {
+        // Like the serde types, arrays at the root produce a view of their
+        // elements
+        let mut root = structure.non_optional();
+        while let JsonStructure::Array(inner) = root {
+            root = inner.non_optional();
+        }
+        let symbols = SymbolTable::build(
+            root,
+            root_name,
+            options.naming_strategy.as_ref(),
+            &options.abbreviations,
+            options.acronyms,
+        );
+        let mut generator = ViewGenerator {
+            symbols: &symbols,
+            definitions: Vec::new(),
+            defined: Vec::new(),
+            path: Vec::new(),
+        };
+        if let JsonStructure::Object(pairs) = root {
+            generator.emit_view(pairs);
+        }
+
+        let mut output = "use serde_json::Value;\n".to_string();
+        for definition in &generator.definitions {
+            output.push('\n');
+            output.push_str(definition);
+        }
+        vec![Artifact::new(
+            format!("{}.rs", to_snake_case(root_name)),
+            output,
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_sample_json;
+
+    #[test]
+    fn generates_typed_views() {
+        let structure = convert_sample_json(
+            r#"[{"id": 1, "userName": "a", "price": null, "tags": ["x"],
+                "owner": {"active": true}, "scores": {"a": 1.5}, "meta": null}]"#,
+        )
+        .unwrap();
+        let artifacts = ValueViewBackend.generate(&structure, "Order", &ConvertOptions::default());
+
+        assert_eq!(artifacts[0].file_name, "order.rs");
+        assert_eq!(
+            artifacts[0].contents,
+            r#"use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderView<'a>(pub &'a Value);
+
+impl<'a> OrderView<'a> {
+    pub fn id(&self) -> Option<i64> {
+        let value = self.0.get("id")?;
+        value.as_i64()
+    }
+
+    pub fn user_name(&self) -> Option<&'a str> {
+        let value = self.0.get("userName")?;
+        value.as_str()
+    }
+
+    pub fn price(&self) -> Option<&'a Value> {
+        let value = self.0.get("price")?;
+        Some(value).filter(|v| !v.is_null())
+    }
+
+    pub fn tags(&self) -> Option<impl Iterator<Item = Option<&'a str>> + 'a> {
+        let value = self.0.get("tags")?;
+        value.as_array().map(|a| a.iter().map(|v| v.as_str()))
+    }
+
+    pub fn owner(&self) -> Option<OwnerView<'a>> {
+        let value = self.0.get("owner")?;
+        value.is_object().then(|| OwnerView(value))
+    }
+
+    pub fn scores(&self) -> Option<ScoresView<'a>> {
+        let value = self.0.get("scores")?;
+        value.is_object().then(|| ScoresView(value))
+    }
+
+    pub fn meta(&self) -> Option<&'a Value> {
+        let value = self.0.get("meta")?;
+        Some(value).filter(|v| !v.is_null())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OwnerView<'a>(pub &'a Value);
+
+impl<'a> OwnerView<'a> {
+    pub fn active(&self) -> Option<bool> {
+        let value = self.0.get("active")?;
+        value.as_bool()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoresView<'a>(pub &'a Value);
+
+impl<'a> ScoresView<'a> {
+    pub fn a(&self) -> Option<f64> {
+        let value = self.0.get("a")?;
+        value.as_f64()
+    }
+}
+"#
+        );
+    }
+}
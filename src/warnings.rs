@@ -0,0 +1,43 @@
+use alloc::string::String;
+use core::fmt;
+
+/// Something noteworthy found while converting a sample, which didn't stop the
+/// conversion but probably deserves a look.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Warning {
+    /// Json pointer to the value the warning is about, with `*` standing in for
+    /// array elements
+    pub path: String,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new<P: Into<String>, M: Into<String>>(path: P, message: M) -> Warning {
+        Warning {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_root_path_as_slash() {
+        assert_eq!(Warning::new("", "oops").to_string(), "/: oops");
+        assert_eq!(Warning::new("/a", "oops").to_string(), "/a: oops");
+    }
+}